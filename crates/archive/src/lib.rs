@@ -14,22 +14,301 @@ pub struct HttpError {
     pub code: ::reqwest::StatusCode,
 }
 
+/// The remote server could not be reached, so no HTTP response was received.
+#[derive(Fail, Debug)]
+#[fail(display = "{}", error)]
+pub struct ConnectionError {
+    pub error: String,
+}
+
+impl ConnectionError {
+    fn from_error(error: ::reqwest::Error) -> Self {
+        ConnectionError {
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Credentials for HTTP basic authentication with the server an archive is fetched from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: Option<String>,
+}
+
+/// A proxy server that requests for an archive are sent through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proxy {
+    /// The URL of the proxy, without any credentials.
+    pub url: String,
+    /// The credentials for basic authentication with the proxy, if it requires them.
+    pub credentials: Option<Credentials>,
+}
+
+/// Builds a GET request for `url`, authenticated with `credentials` if there are any, and
+/// sent through `proxy` if there is one.
+fn request(
+    url: &str,
+    credentials: Option<&Credentials>,
+    proxy: Option<&Proxy>,
+) -> Result<::reqwest::RequestBuilder, failure::Error> {
+    let client = match proxy {
+        Some(proxy) => ::reqwest::Client::builder()?
+            .proxy(::reqwest::Proxy::all(&proxy.url)?)
+            .build()?,
+        None => ::reqwest::Client::new()?,
+    };
+    let mut request = client.get(url)?;
+    if let Some(credentials) = credentials {
+        request.header(::reqwest::header::Authorization(::reqwest::header::Basic {
+            username: credentials.username.clone(),
+            password: credentials.password.clone(),
+        }));
+    }
+    if let Some(credentials) = proxy.and_then(|proxy| proxy.credentials.as_ref()) {
+        request.header(::reqwest::header::ProxyAuthorization(
+            ::reqwest::header::Basic {
+                username: credentials.username.clone(),
+                password: credentials.password.clone(),
+            },
+        ));
+    }
+    Ok(request)
+}
+
+/// A response to a request for the rest of a partially downloaded file.
+pub(crate) struct Resumed {
+    pub(crate) response: ::reqwest::Response,
+    /// The size of the part that was already downloaded, which the response continues.
+    pub(crate) offset: u64,
+    /// The partially downloaded file, open for appending the rest.
+    pub(crate) file: File,
+}
+
+/// The file next to the partially downloaded `file` that records the validator of the
+/// response it is downloaded from: its strong `ETag`, or else its `Last-Modified` date.
+pub fn validator_file(file: &Path) -> PathBuf {
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(".validator");
+    file.with_file_name(name)
+}
+
+/// Reads the validator recorded for the partially downloaded `file`, as the `If-Range`
+/// condition for resuming it.
+fn read_validator(file: &Path) -> Option<::reqwest::header::IfRange> {
+    let recorded = read_to_string(validator_file(file)).ok()?;
+    let mut parts = recorded.trim().splitn(2, ": ");
+    match (parts.next()?, parts.next()?) {
+        ("ETag", tag) => Some(::reqwest::header::IfRange::EntityTag(tag.parse().ok()?)),
+        ("Last-Modified", date) => Some(::reqwest::header::IfRange::Date(date.parse().ok()?)),
+        _ => None,
+    }
+}
+
+/// Records the validator of `response`, which `file` is downloaded from, if it has one
+/// that a range request can be made conditional on.
+fn record_validator(response: &::reqwest::Response, file: &Path) -> io::Result<()> {
+    let headers = response.headers();
+    let validator = match headers.get::<::reqwest::header::ETag>() {
+        // a weak tag doesn't promise the same bytes, so ranges can't be conditional on it
+        Some(etag) if !etag.weak => Some(format!("ETag: {}", **etag)),
+        _ => headers
+            .get::<::reqwest::header::LastModified>()
+            .map(|date| format!("Last-Modified: {}", **date)),
+    };
+    let record = validator_file(file);
+    match validator {
+        Some(validator) => write(record, validator),
+        None if record.exists() => remove_file(record),
+        None => Ok(()),
+    }
+}
+
+/// Determines whether the partial `response` continues a file from `offset`.
+fn continues_at(response: &::reqwest::Response, offset: u64) -> bool {
+    match response.headers().get::<::reqwest::header::ContentRange>() {
+        Some(&::reqwest::header::ContentRange(::reqwest::header::ContentRangeSpec::Bytes {
+            range: Some((first, _)),
+            ..
+        })) => first == offset,
+        _ => false,
+    }
+}
+
+/// Requests the part of `url` that is missing from `file`, which is created if it
+/// doesn't exist. The request is conditional on the validator recorded when the file was
+/// first requested, so that the rest is only appended if the file on the server is still
+/// the same. Otherwise, or if the server ignores or can't satisfy the range, the file is
+/// truncated and downloaded from the start.
+pub(crate) fn resume(
+    url: &str,
+    file: &Path,
+    credentials: Option<&Credentials>,
+    proxy: Option<&Proxy>,
+) -> Result<Resumed, failure::Error> {
+    let offset = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let validator = if offset > 0 {
+        read_validator(file)
+    } else {
+        None
+    };
+    if let Some(validator) = validator {
+        let response = request(url, credentials, proxy)?
+            .header(::reqwest::header::Range::Bytes(vec![
+                ::reqwest::header::ByteRangeSpec::AllFrom(offset),
+            ]))
+            .header(validator)
+            .send()
+            .map_err(ConnectionError::from_error)?;
+        match response.status() {
+            ::reqwest::StatusCode::PartialContent if continues_at(&response, offset) => {
+                return Ok(Resumed {
+                    response,
+                    offset,
+                    file: OpenOptions::new().append(true).open(file)?,
+                });
+            }
+            // a range that doesn't continue the file can't be appended to it
+            ::reqwest::StatusCode::PartialContent => {}
+            // the file changed on the server, or the server ignores ranges, so the
+            // response is the whole file
+            status if status.is_success() => {
+                record_validator(&response, file)?;
+                return Ok(Resumed {
+                    response,
+                    offset: 0,
+                    file: File::create(file)?,
+                });
+            }
+            // the partial file is longer than the one on the server
+            ::reqwest::StatusCode::RangeNotSatisfiable => {}
+            status => Err(HttpError { code: status })?,
+        }
+    }
+
+    let response = request(url, credentials, proxy)?
+        .send()
+        .map_err(ConnectionError::from_error)?;
+    if !response.status().is_success() {
+        Err(HttpError {
+            code: response.status(),
+        })?;
+    }
+    record_validator(&response, file)?;
+    Ok(Resumed {
+        response,
+        offset: 0,
+        file: File::create(file)?,
+    })
+}
+
+/// Downloads the file at `url` to `file`, resuming from the part of it that was
+/// already downloaded, if any. Reports the bytes of the file downloaded so far and its
+/// total size to `progress`, and returns its total size.
+pub fn download(
+    url: &str,
+    file: &Path,
+    credentials: Option<&Credentials>,
+    proxy: Option<&Proxy>,
+    progress: &mut FnMut(u64, u64),
+) -> Result<u64, failure::Error> {
+    let Resumed {
+        response,
+        offset,
+        mut file,
+    } = resume(url, file, credentials, proxy)?;
+    let total = offset
+        + response
+            .headers()
+            .get::<::reqwest::header::ContentLength>()
+            .map(|length| **length)
+            .unwrap_or(0);
+
+    progress(offset, total);
+    let mut reader = ProgressRead::new(response, offset, |position, read| {
+        let position = position + read as u64;
+        progress(position, total);
+        position
+    });
+    let written = offset + io::copy(&mut reader, &mut file)?;
+    Ok(written)
+}
+
 pub use crate::tarball::Tarball;
 pub use crate::zip::Zip;
 
-use std::fs::File;
-use std::path::Path;
+use std::collections::HashSet;
+use std::fs::{read_to_string, remove_file, write, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use progress_read::ProgressRead;
 
 pub trait Archive {
     fn compressed_size(&self) -> u64;
     fn uncompressed_size(&self) -> Option<u64>;
 
-    /// Unpacks the zip archive to the specified destination folder.
+    /// Unpacks the zip archive to the specified destination folder, returning the paths
+    /// of any entries that were skipped because they collided with an earlier entry on a
+    /// case-insensitive filesystem.
     fn unpack(
         self: Box<Self>,
         dest: &Path,
         progress: &mut FnMut(&(), usize),
-    ) -> Result<(), failure::Error>;
+    ) -> Result<Vec<PathBuf>, failure::Error>;
+}
+
+/// Detects archive entries whose paths differ only by case.
+///
+/// On case-insensitive filesystems (the default on macOS and Windows), such entries
+/// would silently overwrite each other, leaving whichever happened to be written last.
+/// To keep installs deterministic, the first entry is kept and any later colliding
+/// entries are skipped and reported.
+pub(crate) struct CaseCollisions {
+    case_insensitive: bool,
+    seen: HashSet<String>,
+    skipped: Vec<PathBuf>,
+}
+
+impl CaseCollisions {
+    /// Creates a collision detector for an archive being unpacked into `dest`, which
+    /// must already exist.
+    pub(crate) fn for_dest(dest: &Path) -> io::Result<Self> {
+        Ok(CaseCollisions {
+            case_insensitive: is_case_insensitive(dest)?,
+            seen: HashSet::new(),
+            skipped: Vec::new(),
+        })
+    }
+
+    /// Records the file entry at `path` and determines whether it should be unpacked.
+    pub(crate) fn admit(&mut self, path: &Path) -> bool {
+        if !self.case_insensitive {
+            return true;
+        }
+
+        let key = path.to_string_lossy().to_lowercase();
+        if self.seen.insert(key) {
+            true
+        } else {
+            self.skipped.push(path.to_path_buf());
+            false
+        }
+    }
+
+    /// Produces the paths of the entries that were skipped.
+    pub(crate) fn into_skipped(self) -> Vec<PathBuf> {
+        self.skipped
+    }
+}
+
+/// Determines whether the filesystem containing `dir` treats paths case-insensitively.
+fn is_case_insensitive(dir: &Path) -> io::Result<bool> {
+    let probe = dir.join(".notion-case-probe");
+    File::create(&probe)?;
+    let result = dir.join(".NOTION-CASE-PROBE").exists();
+    remove_file(&probe)?;
+    Ok(result)
 }
 
 cfg_if::cfg_if! {
@@ -47,8 +326,13 @@ cfg_if::cfg_if! {
         ///
         /// On Windows, the preferred format is zip. On Unixes, the preferred format
         /// is tarball.
-        pub fn fetch_native(url: &str, cache_file: &Path) -> Result<Box<Archive>, failure::Error> {
-            Tarball::fetch(url, cache_file)
+        pub fn fetch_native(
+            url: &str,
+            cache_file: &Path,
+            credentials: Option<&Credentials>,
+            proxy: Option<&Proxy>,
+        ) -> Result<Box<Archive>, failure::Error> {
+            Tarball::fetch(url, cache_file, credentials, proxy)
         }
     } else if #[cfg(windows)] {
         /// Load an archive in the native OS-preferred format from the specified file.
@@ -64,10 +348,152 @@ cfg_if::cfg_if! {
         ///
         /// On Windows, the preferred format is zip. On Unixes, the preferred format
         /// is tarball.
-        pub fn fetch_native(url: &str, cache_file: &Path) -> Result<Box<Archive>, failure::Error> {
-            Zip::fetch(url, cache_file)
+        pub fn fetch_native(
+            url: &str,
+            cache_file: &Path,
+            credentials: Option<&Credentials>,
+            proxy: Option<&Proxy>,
+        ) -> Result<Box<Archive>, failure::Error> {
+            Zip::fetch(url, cache_file, credentials, proxy)
         }
     } else {
         compile_error!("Unsupported OS (expected 'unix' or 'windows').");
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{resume, validator_file, CaseCollisions};
+    use std::collections::HashSet;
+    use std::fs;
+    use std::io::{self, Read, Write};
+    use std::net::TcpListener;
+    use std::path::{Path, PathBuf};
+    use std::thread::{self, JoinHandle};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_case_collisions() {
+        let mut collisions = CaseCollisions {
+            case_insensitive: true,
+            seen: HashSet::new(),
+            skipped: Vec::new(),
+        };
+        assert!(collisions.admit(Path::new("pkg/Foo")));
+        assert!(collisions.admit(Path::new("pkg/bar")));
+        assert!(!collisions.admit(Path::new("pkg/foo")));
+        assert_eq!(collisions.into_skipped(), vec![PathBuf::from("pkg/foo")]);
+    }
+
+    /// Serves each of `responses` to one request, in order, on a local port. Returns the
+    /// URL to request, and the server, which produces the requests it received.
+    fn serve(responses: Vec<&'static str>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/archive.tar.gz", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(String::from_utf8(request).unwrap().to_lowercase());
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    /// Resumes the download of `url` into `file`, returns the offset it resumed from.
+    fn resume_into(url: &str, file: &Path) -> u64 {
+        let mut resumed = resume(url, file, None, None).expect("could not resume");
+        io::copy(&mut resumed.response, &mut resumed.file).unwrap();
+        resumed.offset
+    }
+
+    #[test]
+    fn test_resume_unchanged_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("archive.tar.gz.partial");
+        fs::write(&file, "hello ").unwrap();
+        fs::write(validator_file(&file), "ETag: \"v1\"").unwrap();
+
+        let (url, server) = serve(vec![
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 6-10/11\r\nContent-Length: 5\r\nConnection: close\r\n\r\nworld",
+        ]);
+        assert_eq!(resume_into(&url, &file), 6);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "hello world");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("\r\nrange: bytes=6-\r\n"));
+        assert!(requests[0].contains("\r\nif-range: \"v1\"\r\n"));
+    }
+
+    #[test]
+    fn test_resume_changed_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("archive.tar.gz.partial");
+        fs::write(&file, "hello ").unwrap();
+        fs::write(validator_file(&file), "ETag: \"v1\"").unwrap();
+
+        // the server sends the whole file, as it no longer has the tag `v1`
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v2\"\r\nContent-Length: 11\r\nConnection: close\r\n\r\nHELLO WORLD",
+        ]);
+        assert_eq!(resume_into(&url, &file), 0);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "HELLO WORLD");
+        assert_eq!(
+            fs::read_to_string(validator_file(&file)).unwrap(),
+            "ETag: \"v2\""
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_resume_mismatched_range() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("archive.tar.gz.partial");
+        fs::write(&file, "hello ").unwrap();
+        fs::write(validator_file(&file), "ETag: \"v1\"").unwrap();
+
+        // a range that doesn't continue the file is discarded and the file requested again
+        let (url, server) = serve(vec![
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-4/11\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 11\r\nConnection: close\r\n\r\nhello world",
+        ]);
+        assert_eq!(resume_into(&url, &file), 0);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "hello world");
+
+        let requests = server.join().unwrap();
+        assert!(!requests[1].contains("\r\nrange:"));
+    }
+
+    #[test]
+    fn test_resume_without_validator() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("archive.tar.gz.partial");
+        fs::write(&file, "hello ").unwrap();
+
+        // a file that can't be told to be the same is downloaded from the start
+        let (url, server) = serve(vec![
+            "HTTP/1.1 200 OK\r\nLast-Modified: Sun, 07 Nov 1994 08:48:37 GMT\r\nContent-Length: 11\r\nConnection: close\r\n\r\nhello world",
+        ]);
+        assert_eq!(resume_into(&url, &file), 0);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "hello world");
+        assert_eq!(
+            fs::read_to_string(validator_file(&file)).unwrap(),
+            "Last-Modified: Sun, 07 Nov 1994 08:48:37 GMT"
+        );
+
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("\r\nrange:"));
+    }
+}
@@ -1,20 +1,19 @@
 //! Provides types and functions for fetching and unpacking a Node installation
 //! tarball in Unix operating systems.
 
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
 
 use failure::{self, Fail};
 use flate2::read::GzDecoder;
 use progress_read::ProgressRead;
-use reqwest;
 use reqwest::header::{AcceptRanges, ByteRangeSpec, ContentLength, Range, RangeUnit};
 use reqwest::Response;
 use tar;
 use tee::TeeReader;
 
-use super::Archive;
+use super::{resume, Archive, CaseCollisions, Credentials, Proxy, Resumed};
 
 /// A Node installation tarball.
 pub struct Tarball {
@@ -57,24 +56,31 @@ impl Tarball {
 
     /// Initiate fetching of a tarball from the given URL, returning a
     /// tarball that can be streamed (and that tees its data to a local
-    /// file as it streams).
-    pub fn fetch(url: &str, cache_file: &Path) -> Result<Box<Archive>, failure::Error> {
-        let response = reqwest::get(url)?;
-
-        if !response.status().is_success() {
-            Err(super::HttpError {
-                code: response.status(),
-            })?;
+    /// file as it streams). If the local file holds the beginning of the
+    /// tarball from an interrupted fetch, only the rest is downloaded.
+    pub fn fetch(
+        url: &str,
+        cache_file: &Path,
+        credentials: Option<&Credentials>,
+        proxy: Option<&Proxy>,
+    ) -> Result<Box<Archive>, failure::Error> {
+        let Resumed {
+            response,
+            offset,
+            file,
+        } = resume(url, cache_file, credentials, proxy)?;
+
+        let compressed_size = offset + content_length(&response)?;
+
+        // a partial response already shows that the server accepts byte ranges
+        if offset == 0 {
+            ensure_accepts_byte_ranges(&response)?;
         }
 
-        let compressed_size = content_length(&response)?;
+        let uncompressed_size = fetch_uncompressed_size(url, compressed_size, credentials, proxy)?;
 
-        ensure_accepts_byte_ranges(&response)?;
-
-        let uncompressed_size = fetch_uncompressed_size(url, compressed_size)?;
-
-        let file = File::create(cache_file)?;
-        let data = Box::new(TeeReader::new(response, file));
+        let downloaded = File::open(cache_file)?.take(offset);
+        let data = Box::new(downloaded.chain(TeeReader::new(response, file)));
 
         Ok(Box::new(Tarball {
             uncompressed_size,
@@ -95,14 +101,129 @@ impl Archive for Tarball {
         self: Box<Self>,
         dest: &Path,
         progress: &mut FnMut(&(), usize),
-    ) -> Result<(), failure::Error> {
+    ) -> Result<Vec<PathBuf>, failure::Error> {
         let decoded = GzDecoder::new(self.data);
         let mut tarball = tar::Archive::new(ProgressRead::new(decoded, (), progress));
-        tarball.unpack(dest)?;
-        Ok(())
+        fs::create_dir_all(dest)?;
+
+        // Symlinks that the destination filesystem doesn't support (e.g. FAT volumes), or
+        // that the user lacks the privilege to create (on Windows), are replaced with
+        // copies of their targets, which can only be made once the rest of the archive
+        // has been unpacked.
+        let mut copied_links: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let mut collisions = CaseCollisions::for_dest(dest)?;
+
+        for entry in tarball.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_dir() && !collisions.admit(&entry.path()?) {
+                continue;
+            }
+            if let Err(error) = entry.unpack_in(dest) {
+                let path = entry.path()?.into_owned();
+                match (entry.header().entry_type().is_symlink(), entry.link_name()?) {
+                    (true, Some(ref target)) if target.is_relative() && is_normal(&path) => {
+                        // the error of unpacking doesn't tell why the link failed, so it
+                        // is attempted again to find out
+                        match symlink(target, &dest.join(&path)) {
+                            Ok(()) => {}
+                            Err(ref link_error) if is_symlink_unsupported(link_error) => {
+                                copied_links.push((path, target.to_path_buf()));
+                            }
+                            Err(_) => Err(error)?,
+                        }
+                    }
+                    _ => Err(error)?,
+                }
+            }
+        }
+
+        for (link, target) in copied_links {
+            copy_link(dest, &link, &target)?;
+        }
+
+        Ok(collisions.into_skipped())
     }
 }
 
+/// Determines whether `path` only descends into the destination, as `tar` requires of
+/// the entries it unpacks.
+fn is_normal(path: &Path) -> bool {
+    path.is_relative()
+        && !path
+            .components()
+            .any(|component| component == Component::ParentDir)
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+/// Determines whether `error`, from creating a symlink, is because the filesystem doesn't
+/// support symlinks (`EPERM`).
+#[cfg(unix)]
+fn is_symlink_unsupported(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(1)
+}
+
+/// Determines whether `error`, from creating a symlink, is because the user lacks the
+/// privilege to create symlinks (`ERROR_PRIVILEGE_NOT_HELD`).
+#[cfg(windows)]
+fn is_symlink_unsupported(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(1314)
+}
+
+#[derive(Fail, Debug)]
+#[fail(
+    display = "cannot copy the target of the link {} ({}), which is outside of the archive or contains the link",
+    link, target
+)]
+struct LinkTargetError {
+    link: String,
+    target: String,
+}
+
+/// Replaces the symlink `link`, relative to `dest`, with a copy of its `target`. The
+/// target has to be within `dest`, and can't contain the link, whose copy would never
+/// end.
+fn copy_link(dest: &Path, link: &Path, target: &Path) -> Result<(), failure::Error> {
+    let dest = dest.canonicalize()?;
+    let path = dest.join(link);
+    let parent = path.parent().unwrap_or(&dest).canonicalize()?;
+    let copy = match path.file_name() {
+        Some(name) => parent.join(name),
+        None => path.clone(),
+    };
+    let source = parent.join(target).canonicalize()?;
+    if !source.starts_with(&dest) || copy.starts_with(&source) {
+        Err(LinkTargetError {
+            link: link.display().to_string(),
+            target: target.display().to_string(),
+        })?;
+    }
+    copy_target(&source, &copy)?;
+    Ok(())
+}
+
+/// Copies `source`, the target of the symlink `link`, into its place. A directory is
+/// copied with all of its contents.
+fn copy_target(source: &Path, link: &Path) -> io::Result<()> {
+    if !source.is_dir() {
+        return fs::copy(source, link).map(|_| ());
+    }
+    fs::create_dir_all(link)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        copy_target(&entry.path(), &link.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
 // From http://www.gzip.org/zlib/rfc-gzip.html#member-format
 //
 //   0   1   2   3   4   5   6   7
@@ -133,12 +254,16 @@ struct UnexpectedContentLengthError {
 /// of a gzip file from a URL. This makes two round-trips to the server but avoids
 /// downloading the entire gzip file. For very small files it's unlikely to be
 /// more efficient than simply downloading the entire file up front.
-fn fetch_isize(url: &str, len: u64) -> Result<[u8; 4], failure::Error> {
-    let client = reqwest::Client::new()?;
-    let mut response = client
-        .get(url)?
+fn fetch_isize(
+    url: &str,
+    len: u64,
+    credentials: Option<&Credentials>,
+    proxy: Option<&Proxy>,
+) -> Result<[u8; 4], failure::Error> {
+    let mut response = super::request(url, credentials, proxy)?
         .header(Range::Bytes(vec![ByteRangeSpec::FromTo(len - 4, len - 1)]))
-        .send()?;
+        .send()
+        .map_err(super::ConnectionError::from_error)?;
 
     if !response.status().is_success() {
         Err(super::HttpError {
@@ -190,8 +315,13 @@ fn ensure_accepts_byte_ranges(response: &Response) -> Result<(), failure::Error>
 /// an extra round-trip to the server, so it's only more efficient than just
 /// downloading the file if the file is large enough that downloading it is
 /// slower than the extra round trips.
-fn fetch_uncompressed_size(url: &str, len: u64) -> Result<u64, failure::Error> {
-    let packed = fetch_isize(url, len)?;
+fn fetch_uncompressed_size(
+    url: &str,
+    len: u64,
+    credentials: Option<&Credentials>,
+    proxy: Option<&Proxy>,
+) -> Result<u64, failure::Error> {
+    let packed = fetch_isize(url, len, credentials, proxy)?;
     Ok(unpack_isize(packed))
 }
 
@@ -204,9 +334,13 @@ fn load_uncompressed_size(file: &mut File) -> Result<u64, failure::Error> {
 #[cfg(test)]
 pub mod tests {
 
+    use super::{copy_link, copy_target, LinkTargetError};
+    use crate::is_case_insensitive;
     use crate::tarball::Tarball;
-    use std::fs::File;
-    use std::path::PathBuf;
+    use failure;
+    use std::fs::{self, File};
+    use std::path::{Path, PathBuf};
+    use tempfile::tempdir;
 
     fn fixture_path(fixture_dir: &str) -> PathBuf {
         let mut cargo_manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -225,4 +359,102 @@ pub mod tests {
         assert_eq!(tarball.uncompressed_size(), Some(10240));
         assert_eq!(tarball.compressed_size(), 402);
     }
+
+    #[test]
+    fn test_unpack_symlinks() {
+        let mut test_file_path = fixture_path("tarballs");
+        test_file_path.push("symlinks.tar.gz");
+        let test_file = File::open(test_file_path).expect("Couldn't open test file");
+        let tarball = Tarball::load(test_file).expect("Failed to load tarball");
+
+        let dest = tempdir().unwrap();
+        let skipped = tarball
+            .unpack(dest.path(), &mut |_, _| ())
+            .expect("Failed to unpack tarball");
+        assert!(skipped.is_empty());
+
+        let pkg = dest.path().join("pkg");
+        assert_eq!(
+            fs::read_to_string(pkg.join("bin").join("cli")).unwrap(),
+            "module.exports = 'index';\n"
+        );
+        assert_eq!(
+            fs::read_to_string(pkg.join("util").join("help.js")).unwrap(),
+            "module.exports = 'help';\n"
+        );
+    }
+
+    #[test]
+    fn test_unpack_case_collisions() {
+        let mut test_file_path = fixture_path("tarballs");
+        test_file_path.push("case-collision.tar.gz");
+        let test_file = File::open(test_file_path).expect("Couldn't open test file");
+        let tarball = Tarball::load(test_file).expect("Failed to load tarball");
+
+        let dest = tempdir().unwrap();
+        let skipped = tarball
+            .unpack(dest.path(), &mut |_, _| ())
+            .expect("Failed to unpack tarball");
+
+        let pkg = dest.path().join("pkg");
+        if is_case_insensitive(dest.path()).unwrap() {
+            // the first of the colliding entries is kept
+            assert_eq!(skipped, vec![PathBuf::from("pkg/foo")]);
+            assert_eq!(fs::read_to_string(pkg.join("Foo")).unwrap(), "upper\n");
+        } else {
+            assert!(skipped.is_empty());
+            assert_eq!(fs::read_to_string(pkg.join("Foo")).unwrap(), "upper\n");
+            assert_eq!(fs::read_to_string(pkg.join("foo")).unwrap(), "lower\n");
+        }
+    }
+
+    #[test]
+    fn test_copy_target() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("lib");
+        fs::create_dir_all(source.join("util")).unwrap();
+        fs::write(source.join("index.js"), "index").unwrap();
+        fs::write(source.join("util").join("help.js"), "help").unwrap();
+
+        // the target of a link may be a directory
+        let link = dir.path().join("current");
+        copy_target(&source, &link).unwrap();
+        assert_eq!(fs::read_to_string(link.join("index.js")).unwrap(), "index");
+        assert_eq!(
+            fs::read_to_string(link.join("util").join("help.js")).unwrap(),
+            "help"
+        );
+
+        let link = dir.path().join("cli");
+        copy_target(&source.join("index.js"), &link).unwrap();
+        assert_eq!(fs::read_to_string(&link).unwrap(), "index");
+    }
+
+    /// Unpacks the fixture `name`, then replaces its symlink `link` with a copy of the
+    /// target, as is done where symlinks aren't supported.
+    fn copy_fixture_link(name: &str, link: &str) -> Result<(), failure::Error> {
+        let mut test_file_path = fixture_path("tarballs");
+        test_file_path.push(name);
+        let test_file = File::open(test_file_path).expect("Couldn't open test file");
+        let tarball = Tarball::load(test_file).expect("Failed to load tarball");
+
+        let dest = tempdir().unwrap();
+        tarball.unpack(dest.path(), &mut |_, _| ())?;
+        let path = dest.path().join(link);
+        let target = fs::read_link(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        copy_link(dest.path(), Path::new(link), &target)
+    }
+
+    #[test]
+    fn test_copy_escaping_link() {
+        let error = copy_fixture_link("escaping-link.tar.gz", "pkg/passwd").unwrap_err();
+        assert!(error.downcast_ref::<LinkTargetError>().is_some());
+    }
+
+    #[test]
+    fn test_copy_looping_link() {
+        let error = copy_fixture_link("looping-link.tar.gz", "pkg/self").unwrap_err();
+        assert!(error.downcast_ref::<LinkTargetError>().is_some());
+    }
 }
@@ -3,16 +3,15 @@
 
 use std::fs::{create_dir_all, File};
 use std::io::copy;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use progress_read::ProgressRead;
-use reqwest;
 use verbatim::PathExt;
 use zip_rs::ZipArchive;
 
 use failure;
 
-use super::Archive;
+use super::{resume, Archive, CaseCollisions, Credentials, Proxy, Resumed};
 
 pub struct Zip {
     compressed_size: u64,
@@ -31,18 +30,20 @@ impl Zip {
     }
 
     /// Initiate fetching of a Node zip archive from the given URL, returning
-    /// a `Remote` data source.
-    pub fn fetch(url: &str, cache_file: &Path) -> Result<Box<Archive>, failure::Error> {
-        let mut response = reqwest::get(url)?;
-
-        if !response.status().is_success() {
-            Err(super::HttpError {
-                code: response.status(),
-            })?;
-        }
-
+    /// a `Remote` data source. If the local file holds the beginning of the
+    /// archive from an interrupted fetch, only the rest is downloaded.
+    pub fn fetch(
+        url: &str,
+        cache_file: &Path,
+        credentials: Option<&Credentials>,
+        proxy: Option<&Proxy>,
+    ) -> Result<Box<Archive>, failure::Error> {
         {
-            let mut file = File::create(cache_file)?;
+            let Resumed {
+                mut response,
+                mut file,
+                ..
+            } = resume(url, cache_file, credentials, proxy)?;
             copy(&mut response, &mut file)?;
         }
 
@@ -67,11 +68,13 @@ impl Archive for Zip {
         self: Box<Self>,
         dest: &Path,
         progress: &mut FnMut(&(), usize),
-    ) -> Result<(), failure::Error> {
+    ) -> Result<Vec<PathBuf>, failure::Error> {
         // Use a verbatim path to avoid the legacy Windows 260 byte path limit.
         let dest: &Path = &dest.to_verbatim();
 
         let mut zip = ZipArchive::new(ProgressRead::new(self.data, (), progress))?;
+        create_dir_all(dest)?;
+        let mut collisions = CaseCollisions::for_dest(dest)?;
         for i in 0..zip.len() {
             let mut entry = zip.by_index(i)?;
 
@@ -87,7 +90,7 @@ impl Archive for Zip {
 
             if is_dir {
                 create_dir_all(dest.join(subpath))?;
-            } else {
+            } else if collisions.admit(&subpath) {
                 let mut file = {
                     if let Some(basedir) = subpath.parent() {
                         create_dir_all(dest.join(basedir))?;
@@ -97,7 +100,7 @@ impl Archive for Zip {
                 copy(&mut entry, &mut file)?;
             }
         }
-        Ok(())
+        Ok(collisions.into_skipped())
     }
 }
 
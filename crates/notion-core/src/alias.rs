@@ -0,0 +1,241 @@
+//! Provides the aliases of versions: names that users give to versions, such as `backend`
+//! for `14.17.0`, and then use in place of them (as in `notion install node@backend`, or
+//! `"node": "backend"` in the toolchain of a project).
+//!
+//! Aliases are kept for each tool in the `aliases.toml` file of the user toolchain, which
+//! also records the named version that each tool of the user toolchain was installed as,
+//! so that `notion update` can resolve it again. The `lts` tag is built in: it names the
+//! newest LTS release of Node in the (cached) Node index.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use toml;
+
+use crate::fs::{ensure_containing_dir_exists, ensure_home_writable, read_file_opt};
+use crate::path::user_aliases_file;
+use crate::version::VersionSpec;
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+/// The moving tag naming the newest LTS release of Node.
+pub const LTS: &'static str = "lts";
+
+/// Thrown when a named version is neither built in nor an alias.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "No {} version is named `{}`\n\nCreate an alias for it with `notion alias create {} {}@<version>`.",
+    tool, name, name, tool
+)]
+#[notion_fail(code = "NoVersionMatch")]
+pub(crate) struct UnknownAliasError {
+    tool: String,
+    name: String,
+}
+
+/// Thrown when an alias can't be created.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Cannot create the alias `{}`\n\n{}", name, reason)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct InvalidAliasError {
+    name: String,
+    reason: &'static str,
+}
+
+/// An alias of a version of a tool.
+#[derive(Debug, PartialEq)]
+pub struct Alias<'a> {
+    pub tool: &'a str,
+    pub name: &'a str,
+    pub version: &'a str,
+}
+
+/// The aliases of the user, and the named versions of the user toolchain.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Aliases {
+    /// The aliases of each tool, by the name of the tool and then of the alias.
+    #[serde(default)]
+    aliases: BTreeMap<String, BTreeMap<String, String>>,
+    /// The named version that each tool of the user toolchain was installed as.
+    #[serde(default)]
+    installed: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    /// Returns the current aliases. A missing aliases file is treated as having none.
+    pub fn current() -> Fallible<Self> {
+        match read_file_opt(&user_aliases_file()?).unknown()? {
+            Some(src) => toml::from_str(&src).unknown(),
+            None => Ok(Aliases::default()),
+        }
+    }
+
+    fn save(&self, action: &str) -> Fallible<()> {
+        ensure_home_writable(action)?;
+        let path = user_aliases_file()?;
+        ensure_containing_dir_exists(&path)?;
+        let src = toml::to_string(self).unknown()?;
+        let mut file = File::create(&path).unknown()?;
+        file.write_all(src.as_bytes()).unknown()?;
+        Ok(())
+    }
+
+    /// Returns the version that `name` is an alias of for `tool`, if it is one.
+    pub fn get(&self, tool: &str, name: &str) -> Option<&str> {
+        self.aliases
+            .get(tool)
+            .and_then(|aliases| aliases.get(name))
+            .map(String::as_str)
+    }
+
+    /// Lists every alias, ordered by tool and then by name.
+    pub fn list(&self) -> Vec<Alias<'_>> {
+        self.aliases
+            .iter()
+            .flat_map(|(tool, aliases)| {
+                aliases.iter().map(move |(name, version)| Alias {
+                    tool,
+                    name,
+                    version,
+                })
+            })
+            .collect()
+    }
+
+    /// Lists the tools of the user toolchain that were installed as a named version,
+    /// with that name.
+    pub fn installed(&self) -> Vec<(&str, &str)> {
+        self.installed
+            .iter()
+            .map(|(tool, name)| (tool.as_str(), name.as_str()))
+            .collect()
+    }
+}
+
+/// Makes `name` an alias of `version` for `tool`, replacing any alias of that name.
+pub fn create(tool: &str, name: &str, version: &VersionSpec) -> Fallible<()> {
+    let invalid = |reason| InvalidAliasError {
+        name: name.to_string(),
+        reason,
+    };
+    if name == LTS || name == "latest" {
+        throw!(invalid("The name is reserved for a built-in tag."));
+    }
+    match VersionSpec::parse(name) {
+        Ok(VersionSpec::Tag(_)) => {}
+        _ => throw!(invalid(
+            "An alias name starts with a letter, and must not read as a version."
+        )),
+    }
+    if let VersionSpec::Tag(ref tag) = version {
+        if tag != LTS {
+            throw!(invalid(
+                "An alias can only stand for a version, a range or `lts`."
+            ));
+        }
+    }
+
+    let mut aliases = Aliases::current()?;
+    aliases
+        .aliases
+        .entry(tool.to_string())
+        .or_insert_with(BTreeMap::new)
+        .insert(name.to_string(), version.to_string());
+    aliases.save("create an alias")
+}
+
+/// Deletes the alias `name` of `tool`, returning whether there was one.
+pub fn delete(tool: &str, name: &str) -> Fallible<bool> {
+    let mut aliases = Aliases::current()?;
+    let removed = match aliases.aliases.get_mut(tool) {
+        Some(tool_aliases) => tool_aliases.remove(name).is_some(),
+        None => false,
+    };
+    if !removed {
+        return Ok(false);
+    }
+    if aliases.aliases[tool].is_empty() {
+        aliases.aliases.remove(tool);
+    }
+    aliases.save("delete an alias")?;
+    Ok(true)
+}
+
+/// Resolves the alias `name` of `tool` into the version it stands for.
+pub(crate) fn resolve(tool: &str, name: &str) -> Fallible<VersionSpec> {
+    match Aliases::current()?.get(tool, name) {
+        Some(version) => VersionSpec::parse(version),
+        None => throw!(UnknownAliasError {
+            tool: tool.to_string(),
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// Records the named version, if `matching` is one, that `tool` of the user toolchain was
+/// installed as, so that `notion update` resolves it again.
+pub(crate) fn record_installed(tool: &str, matching: &VersionSpec) -> Fallible<()> {
+    let mut aliases = Aliases::current()?;
+    let changed = match matching {
+        VersionSpec::Tag(ref tag) => {
+            aliases.installed.insert(tool.to_string(), tag.clone()) != Some(tag.clone())
+        }
+        _ => aliases.installed.remove(tool).is_some(),
+    };
+    if changed {
+        aliases.save("record the named version of the user toolchain")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{Alias, Aliases};
+    use toml;
+
+    #[test]
+    fn test_from_str() {
+        let aliases: Aliases = toml::from_str(
+            r#"
+[aliases.node]
+backend = "14.17.0"
+legacy = "8"
+
+[aliases.yarn]
+classic = "1.22"
+
+[installed]
+node = "lts"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(aliases.get("node", "backend"), Some("14.17.0"));
+        assert_eq!(aliases.get("yarn", "backend"), None);
+        assert_eq!(
+            aliases.list(),
+            vec![
+                Alias {
+                    tool: "node",
+                    name: "backend",
+                    version: "14.17.0"
+                },
+                Alias {
+                    tool: "node",
+                    name: "legacy",
+                    version: "8"
+                },
+                Alias {
+                    tool: "yarn",
+                    name: "classic",
+                    version: "1.22"
+                },
+            ]
+        );
+        assert_eq!(aliases.installed(), vec![("node", "lts")]);
+    }
+}
@@ -0,0 +1,141 @@
+//! Provides an audit log of changes to the user and project toolchains.
+//!
+//! When enabled in the `[audit]` section of the user configuration, every install and
+//! pin is recorded as a JSON object, appended as a single line to the configured file
+//! and/or sent to the system log, so that the provenance of the toolchains on a machine
+//! can be tracked.
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+use serde_json;
+
+use crate::config::AuditConfig;
+use crate::distro::DistroVersion;
+use crate::event::unix_timestamp;
+use crate::fs::ensure_containing_dir_exists;
+use crate::style::display_warning;
+use notion_fail::{Fallible, ResultExt};
+
+/// A change to a toolchain.
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AuditAction {
+    /// A tool was installed in the user toolchain.
+    Install,
+    /// A tool was pinned in a project toolchain.
+    Pin,
+}
+
+/// A single audit record, serialized as one line of JSON.
+#[derive(Serialize)]
+struct AuditRecord {
+    /// The number of milliseconds since the epoch.
+    timestamp: u64,
+    user: Option<String>,
+    action: AuditAction,
+    tool: String,
+    version: String,
+    /// The root of the project whose toolchain changed, for pins.
+    project: Option<String>,
+}
+
+impl AuditRecord {
+    fn new(action: AuditAction, version: &DistroVersion, project: Option<&Path>) -> Self {
+        let (tool, version) = match version {
+            DistroVersion::Node(runtime, _) => ("node".to_string(), runtime.to_string()),
+            DistroVersion::Yarn(version) => ("yarn".to_string(), version.to_string()),
+            DistroVersion::Npm(version) => ("npm".to_string(), version.to_string()),
+            DistroVersion::Deno(version) => ("deno".to_string(), version.to_string()),
+            DistroVersion::Package(name, version) => (name.clone(), version.to_string()),
+        };
+
+        AuditRecord {
+            timestamp: unix_timestamp(),
+            user: env::var("USER").or_else(|_| env::var("USERNAME")).ok(),
+            action,
+            tool,
+            version,
+            project: project.map(|root| root.to_string_lossy().to_string()),
+        }
+    }
+}
+
+/// Records a toolchain change, once it has been made, in the audit log destinations
+/// configured by `config`.
+pub(crate) fn record(
+    config: &AuditConfig,
+    action: AuditAction,
+    version: &DistroVersion,
+    project: Option<&Path>,
+) -> Fallible<()> {
+    if !config.is_enabled() {
+        return Ok(());
+    }
+
+    let line = serde_json::to_string(&AuditRecord::new(action, version, project)).unknown()?;
+
+    if let Some(ref file) = config.file {
+        ensure_containing_dir_exists(file)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file)
+            .unknown()?;
+        writeln!(file, "{}", line).unknown()?;
+    }
+
+    if config.syslog {
+        send_to_syslog(&line);
+    }
+
+    Ok(())
+}
+
+/// Sends a message to the system log using the standard `logger` utility. Since the
+/// toolchain has already changed, a failure is only reported as a warning.
+fn send_to_syslog(message: &str) {
+    let status = Command::new("logger")
+        .args(&["-t", "notion", "--", message])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let error = match status {
+        Ok(ref status) if status.success() => return,
+        Ok(status) => status.to_string(),
+        Err(error) => error.to_string(),
+    };
+    display_warning(format!(
+        "Could not send the audit record to the system log ({})",
+        error
+    ));
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{AuditAction, AuditRecord};
+    use crate::distro::DistroVersion;
+    use semver::Version;
+    use serde_json::Value;
+    use std::path::Path;
+
+    #[test]
+    fn test_record_serialization() {
+        let version = DistroVersion::Node(
+            Version::parse("10.13.0").unwrap(),
+            Version::parse("6.4.1").unwrap(),
+        );
+        let record = AuditRecord::new(AuditAction::Pin, &version, Some(Path::new("/src/app")));
+        let json: Value = serde_json::to_value(&record).unwrap();
+
+        assert_eq!(json["action"], "pin");
+        assert_eq!(json["tool"], "node");
+        assert_eq!(json["version"], "10.13.0");
+        assert_eq!(json["project"], "/src/app");
+    }
+}
@@ -0,0 +1,303 @@
+//! Provides validation of a project's toolchain, for use as a CI gate.
+
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+use semver::{Version, VersionReq};
+use serde_json::Value;
+
+use crate::inventory::FetchResolve;
+use crate::project::Project;
+use crate::session::Session;
+use notion_fail::{Fallible, ResultExt};
+
+/// A problem with a project's toolchain.
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    /// The current directory isn't in a Node project.
+    NotInProject,
+    /// The project doesn't pin a toolchain.
+    NotPinned,
+    /// A pinned version isn't available.
+    Unavailable {
+        tool: &'static str,
+        version: Version,
+        offline: bool,
+    },
+    /// A dependency in the manifest isn't locked.
+    NotLocked {
+        lockfile: &'static str,
+        name: String,
+        range: String,
+    },
+    /// A dependency is locked to a version outside of its range in the manifest.
+    LockMismatch {
+        lockfile: &'static str,
+        name: String,
+        range: String,
+        locked: String,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Problem::NotInProject => write!(f, "Not in a Node project"),
+            Problem::NotPinned => write!(
+                f,
+                "The project has no toolchain pins (use `notion pin node` to add them)"
+            ),
+            Problem::Unavailable {
+                tool,
+                version,
+                offline: false,
+            } => write!(f, "The pinned {} version {} is not published", tool, version),
+            Problem::Unavailable {
+                tool,
+                version,
+                offline: true,
+            } => write!(
+                f,
+                "The pinned {} version {} has not been fetched (use `notion fetch {} {}`)",
+                tool, version, tool, version
+            ),
+            Problem::NotLocked {
+                lockfile,
+                name,
+                range,
+            } => write!(f, "{}@{} is missing from {}", name, range, lockfile),
+            Problem::LockMismatch {
+                lockfile,
+                name,
+                range,
+                locked,
+            } => write!(
+                f,
+                "{} locks {} to {}, which does not satisfy {}",
+                lockfile, name, locked, range
+            ),
+        }
+    }
+}
+
+/// Checks the toolchain of the current project: that it has pins, that the pinned
+/// versions are available (upstream, or in the local inventory if `offline`), and
+/// that its lockfile, if any, agrees with its manifest.
+pub fn check(session: &Session, offline: bool) -> Fallible<Vec<Problem>> {
+    let project = match session.project()? {
+        Some(project) => project,
+        None => return Ok(vec![Problem::NotInProject]),
+    };
+
+    let mut problems = Vec::new();
+
+    match project.platform() {
+        Some(platform) => {
+            let inventory = session.inventory()?;
+            let hooks = session.hooks()?;
+
+            let node = &platform.node_runtime;
+            let node_available = if offline {
+                inventory.node.contains(node)
+            } else {
+                inventory.node.is_published(node, hooks.node.as_ref())?
+            };
+            if !node_available {
+                problems.push(Problem::Unavailable {
+                    tool: "node",
+                    version: node.clone(),
+                    offline,
+                });
+            }
+
+            if let Some(ref yarn) = platform.yarn {
+                let yarn_available = if offline {
+                    inventory.yarn.contains(yarn)
+                } else {
+                    inventory.yarn.is_published(yarn, hooks.yarn.as_ref())?
+                };
+                if !yarn_available {
+                    problems.push(Problem::Unavailable {
+                        tool: "yarn",
+                        version: yarn.clone(),
+                        offline,
+                    });
+                }
+            }
+        }
+        None => problems.push(Problem::NotPinned),
+    }
+
+    problems.extend(check_lockfile(&project)?);
+    Ok(problems)
+}
+
+/// Checks the dependencies in the manifest against `package-lock.json` or `yarn.lock`.
+fn check_lockfile(project: &Project) -> Fallible<Vec<Problem>> {
+    let manifest = project.manifest();
+    let mut dependencies: Vec<(&String, &String)> = manifest
+        .dependencies
+        .iter()
+        .chain(manifest.dev_dependencies.iter())
+        .collect();
+    dependencies.sort();
+
+    if let Some(src) = read_lockfile(project.root(), "package-lock.json")? {
+        let lock: Value = serde_json::from_str(&src).unknown()?;
+        return Ok(dependencies
+            .into_iter()
+            .filter_map(|(name, range)| {
+                let locked = npm_locked_version(&lock, name);
+                check_dependency("package-lock.json", name, range, locked)
+            })
+            .collect());
+    }
+
+    if let Some(src) = read_lockfile(project.root(), "yarn.lock")? {
+        let entries = yarn_lock_entries(&src);
+        return Ok(dependencies
+            .into_iter()
+            .filter(|(name, range)| !entries.contains(&format!("{}@{}", name, range)))
+            .map(|(name, range)| Problem::NotLocked {
+                lockfile: "yarn.lock",
+                name: name.clone(),
+                range: range.clone(),
+            })
+            .collect());
+    }
+
+    Ok(Vec::new())
+}
+
+fn read_lockfile(root: &Path, name: &str) -> Fallible<Option<String>> {
+    let path = root.join(name);
+    if path.is_file() {
+        Ok(Some(fs::read_to_string(path).unknown()?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Finds the version of the top-level dependency `name` in a `package-lock.json`,
+/// supporting both the `packages` (v2) and `dependencies` (v1) layouts.
+fn npm_locked_version(lock: &Value, name: &str) -> Option<String> {
+    lock.get("packages")
+        .and_then(|packages| packages.get(&format!("node_modules/{}", name)))
+        .or_else(|| {
+            lock.get("dependencies")
+                .and_then(|dependencies| dependencies.get(name))
+        })
+        .and_then(|entry| entry.get("version"))
+        .and_then(Value::as_str)
+        .map(String::from)
+}
+
+fn check_dependency(
+    lockfile: &'static str,
+    name: &str,
+    range: &str,
+    locked: Option<String>,
+) -> Option<Problem> {
+    let locked = match locked {
+        Some(locked) => locked,
+        None => {
+            return Some(Problem::NotLocked {
+                lockfile,
+                name: name.to_string(),
+                range: range.to_string(),
+            });
+        }
+    };
+
+    // ranges that aren't plain semver (tags, URLs, `||` unions) are only checked for presence
+    match (VersionReq::parse(range), Version::parse(&locked)) {
+        (Ok(ref requirement), Ok(ref version)) if !requirement.matches(version) => {
+            Some(Problem::LockMismatch {
+                lockfile,
+                name: name.to_string(),
+                range: range.to_string(),
+                locked,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Reads the `name@range` specifiers locked by a `yarn.lock` file.
+fn yarn_lock_entries(src: &str) -> Vec<String> {
+    src.lines()
+        .filter(|line| !line.starts_with(' ') && !line.starts_with('#') && line.ends_with(':'))
+        .flat_map(|line| {
+            line.trim_end_matches(':')
+                .split(", ")
+                .map(|specifier| specifier.trim_matches('"').to_string())
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_npm_locked_version() {
+        let v1: Value =
+            serde_json::from_str(r#"{"dependencies":{"lodash":{"version":"4.17.11"}}}"#).unwrap();
+        let v2: Value = serde_json::from_str(
+            r#"{"packages":{"node_modules/lodash":{"version":"4.17.21"}}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(npm_locked_version(&v1, "lodash"), Some("4.17.11".to_string()));
+        assert_eq!(npm_locked_version(&v2, "lodash"), Some("4.17.21".to_string()));
+        assert_eq!(npm_locked_version(&v1, "react"), None);
+    }
+
+    #[test]
+    fn test_check_dependency() {
+        let lockfile = "package-lock.json";
+        assert_eq!(
+            check_dependency(lockfile, "lodash", "^4.17.0", Some("4.17.11".to_string())),
+            None
+        );
+        assert_eq!(
+            check_dependency(lockfile, "lodash", "^4.17.0", Some("3.10.1".to_string())),
+            Some(Problem::LockMismatch {
+                lockfile,
+                name: "lodash".to_string(),
+                range: "^4.17.0".to_string(),
+                locked: "3.10.1".to_string(),
+            })
+        );
+        assert_eq!(
+            check_dependency(lockfile, "lodash", "latest", Some("3.10.1".to_string())),
+            None
+        );
+        assert!(check_dependency(lockfile, "lodash", "^4.17.0", None).is_some());
+    }
+
+    #[test]
+    fn test_yarn_lock_entries() {
+        let src = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@babel/code-frame@^7.0.0", "@babel/code-frame@^7.0.0-beta.35":
+  version "7.0.0"
+
+lodash@^4.17.0:
+  version "4.17.11"
+"#;
+        assert_eq!(
+            yarn_lock_entries(src),
+            vec![
+                "@babel/code-frame@^7.0.0",
+                "@babel/code-frame@^7.0.0-beta.35",
+                "lodash@^4.17.0",
+            ]
+        );
+    }
+}
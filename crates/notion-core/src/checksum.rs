@@ -0,0 +1,389 @@
+//! Provides the verification of downloaded artifacts against their digests.
+//!
+//! Digests are computed with any of the supported `Algorithm`s, so that artifacts can be
+//! checked against whichever digest their server publishes (or an internal mirror
+//! requires). Each archive in the inventory has its checksum, including the algorithm,
+//! recorded next to it in a `<archive>.checksum` file, which is used to detect archives
+//! that have been corrupted since they were fetched.
+//!
+//! Tarballs published on an npm registry are verified against the integrity the registry
+//! publishes for them, which is parsed from its Subresource Integrity (`sha512-<base64>`)
+//! form, or from the SHA-1 `shasum` of packages old enough not to have one.
+
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use base64;
+use blake2_rfc::blake2b::Blake2b;
+use failure::Fail;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::fs::read_file_opt;
+use notion_fail::{throw, ExitCode, Fallible, NotionError, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+/// Thrown when the digest of a file doesn't match the expected one.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Checksum mismatch for {}\n\nExpected {}\nbut found {}",
+    file, expected, actual
+)]
+#[notion_fail(code = "FileSystemError")]
+pub(crate) struct ChecksumMismatchError {
+    pub(crate) file: String,
+    pub(crate) expected: String,
+    pub(crate) actual: String,
+}
+
+/// Thrown when a checksum is not of the form `<algorithm>:<hex digest>`.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Invalid checksum: {}", checksum)]
+#[notion_fail(code = "UnknownError")]
+pub(crate) struct InvalidChecksumError {
+    checksum: String,
+}
+
+/// A digest algorithm.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake2b,
+}
+
+impl Algorithm {
+    /// The algorithm used for artifacts whose server doesn't publish a digest.
+    pub const DEFAULT: Algorithm = Algorithm::Sha256;
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Blake2b => "blake2b",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sha1" => Some(Algorithm::Sha1),
+            "sha256" => Some(Algorithm::Sha256),
+            "sha512" => Some(Algorithm::Sha512),
+            "blake2b" => Some(Algorithm::Blake2b),
+            _ => None,
+        }
+    }
+
+    /// Ranks the algorithms, so that the strongest of several published digests is used.
+    fn strength(&self) -> u8 {
+        match self {
+            Algorithm::Sha1 => 0,
+            Algorithm::Sha256 => 1,
+            Algorithm::Sha512 | Algorithm::Blake2b => 2,
+        }
+    }
+
+    fn hasher(&self) -> Box<dyn Hasher> {
+        match self {
+            Algorithm::Sha1 => Box::new(Sha1::new()),
+            Algorithm::Sha256 => Box::new(Sha256::new()),
+            Algorithm::Sha512 => Box::new(Sha512::new()),
+            Algorithm::Blake2b => Box::new(Blake2b::new(64)),
+        }
+    }
+}
+
+/// The incremental computation of a digest.
+trait Hasher {
+    fn update(&mut self, data: &[u8]);
+    fn finish(self: Box<Self>) -> Vec<u8>;
+}
+
+impl Hasher for Sha1 {
+    fn update(&mut self, data: &[u8]) {
+        self.input(data);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<u8> {
+        (*self).result().to_vec()
+    }
+}
+
+impl Hasher for Sha256 {
+    fn update(&mut self, data: &[u8]) {
+        self.input(data);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<u8> {
+        (*self).result().to_vec()
+    }
+}
+
+impl Hasher for Sha512 {
+    fn update(&mut self, data: &[u8]) {
+        self.input(data);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<u8> {
+        (*self).result().to_vec()
+    }
+}
+
+impl Hasher for Blake2b {
+    fn update(&mut self, data: &[u8]) {
+        Blake2b::update(self, data);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<u8> {
+        (*self).finalize().as_bytes().to_vec()
+    }
+}
+
+/// The digest of an artifact, along with the algorithm that computed it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    /// The digest, in lowercase hexadecimal.
+    pub digest: String,
+}
+
+impl Checksum {
+    pub fn new(algorithm: Algorithm, digest: &str) -> Self {
+        Checksum {
+            algorithm,
+            digest: digest.trim().to_lowercase(),
+        }
+    }
+
+    /// Parses the strongest supported hash of a Subresource Integrity string, such as the
+    /// `dist.integrity` of a package on an npm registry. A string may list several hashes,
+    /// separated by spaces, each of the form `<algorithm>-<base64 digest>[?<options>]`.
+    pub fn from_integrity(integrity: &str) -> Option<Self> {
+        integrity
+            .split_whitespace()
+            .filter_map(|hash| {
+                let hash = hash.splitn(2, '?').next()?;
+                let mut parts = hash.splitn(2, '-');
+                let algorithm = Algorithm::parse(parts.next()?)?;
+                let digest = base64::decode(parts.next()?).ok()?;
+                Some(Checksum {
+                    algorithm,
+                    digest: to_hex(&digest),
+                })
+            })
+            .max_by_key(|checksum| checksum.algorithm.strength())
+    }
+
+    /// Finds the digest of `file_name` in a checksum listing, such as the `SHASUMS256.txt`
+    /// of a Node release, whose lines are of the form `<hex digest>  <file name>`.
+    pub fn from_listing(algorithm: Algorithm, listing: &str, file_name: &str) -> Option<Self> {
+        listing.lines().find_map(|line| {
+            let mut parts = line.split_whitespace();
+            let digest = parts.next()?;
+            // a `*` marks files that were read in binary mode
+            let name = parts.next()?.trim_start_matches('*');
+            if name == file_name && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                Some(Checksum::new(algorithm, digest))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Computes the checksum of `data`.
+    pub fn of_bytes(algorithm: Algorithm, data: &[u8]) -> Self {
+        let mut hasher = algorithm.hasher();
+        hasher.update(data);
+        Checksum {
+            algorithm,
+            digest: to_hex(&hasher.finish()),
+        }
+    }
+
+    /// Computes the checksum of the file at `path`, reading it in chunks.
+    pub fn of_file(algorithm: Algorithm, path: &Path) -> Fallible<Self> {
+        let mut file = File::open(path).unknown()?;
+        let mut hasher = algorithm.hasher();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).unknown()?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(Checksum {
+            algorithm,
+            digest: to_hex(&hasher.finish()),
+        })
+    }
+
+    /// Verifies that the file at `path` has this checksum.
+    pub fn verify_file(&self, path: &Path) -> Fallible<()> {
+        let actual = Checksum::of_file(self.algorithm, path)?;
+        if actual != *self {
+            throw!(ChecksumMismatchError {
+                file: path.display().to_string(),
+                expected: self.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm.name(), self.digest)
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = NotionError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let mut parts = src.trim().splitn(2, ':');
+        match (parts.next().and_then(Algorithm::parse), parts.next()) {
+            (Some(algorithm), Some(digest))
+                if !digest.is_empty() && digest.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                Ok(Checksum::new(algorithm, digest))
+            }
+            _ => throw!(InvalidChecksumError {
+                checksum: src.to_string(),
+            }),
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The file recording the checksum of the inventory archive at `artifact`.
+pub(crate) fn record_file(artifact: &Path) -> PathBuf {
+    let mut name = artifact.file_name().unwrap_or_default().to_os_string();
+    name.push(".checksum");
+    artifact.with_file_name(name)
+}
+
+/// Records the checksum of the inventory archive at `artifact`.
+pub(crate) fn record(artifact: &Path, checksum: &Checksum) -> Fallible<()> {
+    fs::write(record_file(artifact), format!("{}\n", checksum)).unknown()
+}
+
+/// Removes the recorded checksum of the inventory archive at `artifact`, if any.
+pub(crate) fn forget(artifact: &Path) -> Fallible<()> {
+    let file = record_file(artifact);
+    if file.exists() {
+        fs::remove_file(file).unknown()?;
+    }
+    Ok(())
+}
+
+/// Reads the recorded checksum of the inventory archive at `artifact`, if any.
+pub(crate) fn recorded(artifact: &Path) -> Fallible<Option<Checksum>> {
+    match read_file_opt(&record_file(artifact)).unknown()? {
+        Some(src) => Ok(Some(src.parse()?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{record_file, Algorithm, Checksum};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_of_bytes() {
+        assert_eq!(
+            Checksum::of_bytes(Algorithm::Sha256, b"abc").digest,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            Checksum::of_bytes(Algorithm::Sha512, b"abc").digest,
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(
+            Checksum::of_bytes(Algorithm::Blake2b, b"abc").digest,
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+             7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        let checksum: Checksum = "sha256:BA7816BF".parse().expect("could not parse checksum");
+        assert_eq!(checksum, Checksum::new(Algorithm::Sha256, "ba7816bf"));
+        assert_eq!(checksum.to_string(), "sha256:ba7816bf");
+
+        assert!("md5:ba7816bf".parse::<Checksum>().is_err());
+        assert!("sha256:not-hex".parse::<Checksum>().is_err());
+        assert!("ba7816bf".parse::<Checksum>().is_err());
+    }
+
+    #[test]
+    fn test_from_integrity() {
+        assert_eq!(
+            Checksum::from_integrity("sha1-qZk+NkcGgWq6PiVxeFDCbJzQ2J0="),
+            Some(Checksum::new(
+                Algorithm::Sha1,
+                "a9993e364706816aba3e25717850c26c9cd0d89d"
+            ))
+        );
+        assert_eq!(
+            Checksum::from_integrity(
+                "sha1-qZk+NkcGgWq6PiVxeFDCbJzQ2J0= \
+                 sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=?foo"
+            ),
+            Some(Checksum::of_bytes(Algorithm::Sha256, b"abc"))
+        );
+        assert_eq!(
+            Checksum::from_integrity("md5-kAFQmDzST7DWlj99KOF/cg=="),
+            None
+        );
+        assert_eq!(Checksum::from_integrity("sha512-!!!!"), None);
+    }
+
+    #[test]
+    fn test_from_listing() {
+        let listing = "\
+            3bf6fa3ae4b0b3c5d2f1b5e3f8d9c4a1  node-v10.13.0-darwin-x64.tar.gz
+            b4d2f1c0e9a8b7c6d5e4f3a2b1c0d9e8  node-v10.13.0-linux-x64.tar.gz
+            a1b2c3d4e5f60718293a4b5c6d7e8f90 *node-v10.13.0-win-x64.zip
+        ";
+        assert_eq!(
+            Checksum::from_listing(Algorithm::Sha256, listing, "node-v10.13.0-linux-x64.tar.gz"),
+            Some(Checksum::new(
+                Algorithm::Sha256,
+                "b4d2f1c0e9a8b7c6d5e4f3a2b1c0d9e8"
+            ))
+        );
+        assert_eq!(
+            Checksum::from_listing(Algorithm::Sha256, listing, "node-v10.13.0-win-x64.zip"),
+            Some(Checksum::new(
+                Algorithm::Sha256,
+                "a1b2c3d4e5f60718293a4b5c6d7e8f90"
+            ))
+        );
+        assert_eq!(
+            Checksum::from_listing(Algorithm::Sha256, listing, "node-v10.13.0.tar.gz"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_record_file() {
+        assert_eq!(
+            record_file(Path::new("/inventory/node/node-v10.13.0-linux-x64.tar.gz")),
+            PathBuf::from("/inventory/node/node-v10.13.0-linux-x64.tar.gz.checksum")
+        );
+    }
+}
@@ -0,0 +1,563 @@
+//! Provides types for working with the Notion configuration file (`config.toml`).
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+
+use failure::Fail;
+use lazy_static::lazy_static;
+use lazycell::LazyCell;
+use semver::VersionReq;
+use toml;
+
+use crate::fs::{ensure_containing_dir_exists, ensure_home_writable, read_file_opt};
+use crate::path::user_config_file;
+use crate::style::ProgressTheme;
+use crate::updater::Channel;
+use notion_fail::{ExitCode, Fallible, NotionError, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+pub(crate) mod serial;
+
+/// Thrown when a value in the configuration file is invalid.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Invalid value for `{}` in the Notion configuration: {}", key, error)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct ConfigValueError {
+    pub(crate) key: String,
+    pub(crate) error: String,
+}
+
+/// Lazily loaded Notion configuration
+pub struct LazyConfig {
+    config: LazyCell<Config>,
+}
+
+impl LazyConfig {
+    /// Constructs a new `LazyConfig` (but does not initialize it).
+    pub fn new() -> LazyConfig {
+        LazyConfig {
+            config: LazyCell::new(),
+        }
+    }
+
+    /// Forces the loading of the configuration
+    pub fn get(&self) -> Fallible<&Config> {
+        self.config.try_borrow_with(|| Config::current())
+    }
+
+    /// Forces the loading of the configuration and returns a mutable reference to it
+    pub fn get_mut(&mut self) -> Fallible<&mut Config> {
+        self.config.try_borrow_mut_with(|| Config::current())
+    }
+}
+
+/// Notion configuration
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub layout: LayoutConfig,
+    pub audit: AuditConfig,
+    pub mirror: MirrorConfig,
+    pub credentials: CredentialsConfig,
+    pub prefetch: PrefetchConfig,
+    pub accessibility: AccessibilityConfig,
+    pub progress: ProgressConfig,
+    pub pin: PinConfig,
+    pub gc: GcConfig,
+    pub verify: VerifyConfig,
+    pub network: NetworkConfig,
+    pub project: ProjectConfig,
+    pub index: IndexConfig,
+    pub update: UpdateConfig,
+    pub retry: RetryConfig,
+}
+
+/// Configuration of the on-disk layout of Notion
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LayoutConfig {
+    /// The directory containing the shims, if not the default `NOTION_HOME/bin`
+    pub shim_dir: Option<PathBuf>,
+    /// A group-writable directory holding the inventory and images shared by all the
+    /// users of the machine, if they aren't kept in each user's `NOTION_HOME`
+    pub shared_dir: Option<PathBuf>,
+    /// The directory of the caches (such as the Node index), if not `NOTION_HOME/cache`
+    pub cache_dir: Option<PathBuf>,
+    /// The directory of the inventory of distribution archives, if not the `inventory`
+    /// directory of the tools (or shared) directory, e.g. on another volume
+    pub inventory_dir: Option<PathBuf>,
+    /// A read-only directory pre-seeded with an inventory and images, laid out like the
+    /// shared directory, that is used alongside the writable ones
+    pub seed_dir: Option<PathBuf>,
+    /// Whether `NOTION_HOME` is provisioned read-only, so that Notion only runs tools
+    /// from the existing inventory and refuses any change to it
+    pub read_only: bool,
+}
+
+lazy_static! {
+    /// The layout settings of the configuration file, once they have been read.
+    static ref LAYOUT: Mutex<Option<LayoutConfig>> = Mutex::new(None);
+}
+
+impl LayoutConfig {
+    /// Calls `f` with the layout settings of the configuration file, which is read only
+    /// once for all the paths that depend on it. A configuration file that can't be read
+    /// is taken to change none of them, so that every path can still be determined (not
+    /// least by `notion config` to repair the file); its error is reported by whatever
+    /// loads the whole configuration.
+    pub(crate) fn with_current<T>(f: impl FnOnce(&LayoutConfig) -> T) -> T {
+        let mut layout = LAYOUT.lock().unwrap_or_else(PoisonError::into_inner);
+        let layout = layout.get_or_insert_with(|| {
+            Config::current()
+                .map(|config| config.layout)
+                .unwrap_or_default()
+        });
+        f(layout)
+    }
+}
+
+/// Configuration of the audit log of toolchain changes
+#[derive(Debug, Default, PartialEq)]
+pub struct AuditConfig {
+    /// The file to append audit records to, as JSON lines
+    pub file: Option<PathBuf>,
+    /// Whether to also send audit records to the system log
+    pub syslog: bool,
+}
+
+impl AuditConfig {
+    /// Determines whether any audit log destination is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some() || self.syslog
+    }
+}
+
+/// Configuration of the releases copied by `notion mirror sync`
+#[derive(Debug, Default, PartialEq)]
+pub struct MirrorConfig {
+    /// The directory to populate, if not given on the command line
+    pub dir: Option<PathBuf>,
+    /// The Node versions to mirror; the newest release matching each requirement is copied
+    pub node: Vec<VersionReq>,
+    /// The Yarn versions to mirror; the newest release matching each requirement is copied
+    pub yarn: Vec<VersionReq>,
+    /// The Node platforms to mirror (e.g. `linux-x64`), if not only the current one
+    pub platforms: Vec<String>,
+}
+
+/// Configuration of where credentials for authenticated servers are looked up
+#[derive(Debug, Default, PartialEq)]
+pub struct CredentialsConfig {
+    /// Whether to look up credentials in the OS credential store if `.netrc` has none
+    pub keychain: bool,
+}
+
+/// Configuration of the background prefetching of new releases
+#[derive(Debug, Default, PartialEq)]
+pub struct PrefetchConfig {
+    /// Whether to fetch new LTS patches of the pinned Node versions, checked at most daily
+    pub lts: bool,
+}
+
+/// Configuration of how `notion pin` writes package.json
+#[derive(Debug, Default, PartialEq)]
+pub struct PinConfig {
+    /// Whether to mirror the pinned Node version into `engines.node`, as with `--engines`
+    pub engines: bool,
+}
+
+/// Configuration of how the toolchain of a project is determined
+#[derive(Debug, Default, PartialEq)]
+pub struct ProjectConfig {
+    /// Whether a project without a toolchain, or a `.nvmrc` or `.node-version` file, runs
+    /// the Node version that best satisfies its `engines.node` range (preferring the
+    /// fetched versions)
+    pub engines: bool,
+}
+
+/// Configuration of the on-disk cache of the Node index
+#[derive(Debug, Default, PartialEq)]
+pub struct IndexConfig {
+    /// How many minutes a fetched index is used before it is revalidated, overriding the
+    /// caching headers of the server, if set
+    pub ttl_minutes: Option<u32>,
+}
+
+/// Configuration of `notion self-update`
+#[derive(Debug, Default, PartialEq)]
+pub struct UpdateConfig {
+    /// The release channel to update from, if not given on the command line
+    pub channel: Channel,
+}
+
+/// Configuration of the verification of downloaded Node archives
+#[derive(Debug, Default, PartialEq)]
+pub struct VerifyConfig {
+    /// Whether to also check the GPG signature of the published checksums, against the
+    /// Node release keys in the user's keyring
+    pub signatures: bool,
+}
+
+/// Configuration of the proxy that requests are sent through
+#[derive(Debug, Default, PartialEq)]
+pub struct NetworkConfig {
+    /// The proxy for `http` URLs, overriding `HTTP_PROXY`
+    pub http_proxy: Option<String>,
+    /// The proxy for `https` URLs, overriding `HTTPS_PROXY`
+    pub https_proxy: Option<String>,
+    /// The hosts (and their subdomains) reached without a proxy, in addition to the ones
+    /// in `NO_PROXY`
+    pub no_proxy: Vec<String>,
+}
+
+/// Configuration of how downloads of indexes and archives are retried after transient
+/// failures
+#[derive(Debug, Default, PartialEq)]
+pub struct RetryConfig {
+    /// How many times a download is attempted in all (3 if not set, 1 to never retry)
+    pub attempts: Option<u32>,
+    /// The delay before the first retry, which doubles for every retry after it (500 if
+    /// not set)
+    pub backoff_ms: Option<u64>,
+    /// The HTTP statuses that are retried, besides connection failures (408, 429, 500,
+    /// 502, 503 and 504 if not set)
+    pub statuses: Option<Vec<u16>>,
+}
+
+/// Configuration of the automatic garbage collection of the inventory
+#[derive(Debug, Default, PartialEq)]
+pub struct GcConfig {
+    /// Removes every unneeded version after this many installs, if set
+    pub after_installs: Option<u32>,
+    /// Removes the least recently fetched unneeded versions whenever the inventory and
+    /// images take more than this many MiB, if set
+    pub max_size_mb: Option<u64>,
+    /// How many of the most recently fetched versions of each tool are always kept
+    /// (1 if not set)
+    pub keep_recent: Option<u32>,
+}
+
+impl GcConfig {
+    /// Determines whether any automatic collection is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.after_installs.is_some() || self.max_size_mb.is_some()
+    }
+}
+
+/// Configuration of accessibility features
+#[derive(Debug, Default, PartialEq)]
+pub struct AccessibilityConfig {
+    /// Whether to report progress as plain-text lines that screen readers can follow
+    pub screen_reader: bool,
+}
+
+/// Configuration of how progress bars and spinners are drawn
+#[derive(Debug, Default, PartialEq)]
+pub struct ProgressConfig {
+    /// The named theme to start from (`default`, `ascii` or `minimal`)
+    pub theme: Option<String>,
+    /// The template of progress bars, where `{bar}` stands for the bar itself
+    pub bar_template: Option<String>,
+    /// The template of spinners
+    pub spinner_template: Option<String>,
+    /// The characters drawing the filled part, the head and the empty part of a bar
+    pub progress_chars: Option<String>,
+    /// The frames of a spinner, followed by the character shown when it finishes
+    pub tick_chars: Option<String>,
+    /// The colors of the bar, as `<filled>/<empty>` (e.g. `cyan/blue`), or `none`
+    pub bar_color: Option<String>,
+}
+
+impl ProgressConfig {
+    /// Resolves the theme described by this configuration.
+    pub fn theme(&self) -> ProgressTheme {
+        let mut theme = self
+            .theme
+            .as_ref()
+            .and_then(|name| ProgressTheme::named(name))
+            .unwrap_or_default();
+
+        if let Some(ref template) = self.bar_template {
+            theme.bar_template = template.clone();
+        }
+        if let Some(ref template) = self.spinner_template {
+            theme.spinner_template = template.clone();
+        }
+        if let Some(ref chars) = self.progress_chars {
+            theme.progress_chars = chars.clone();
+        }
+        if let Some(ref chars) = self.tick_chars {
+            theme.tick_chars = Some(chars.clone());
+        }
+        if let Some(ref color) = self.bar_color {
+            theme.bar_color = if color == "none" {
+                None
+            } else {
+                Some(color.clone())
+            };
+        }
+
+        theme
+    }
+}
+
+impl Config {
+    /// Returns the current configuration, loaded from the filesystem. A missing
+    /// configuration file is treated as an empty configuration.
+    pub fn current() -> Fallible<Self> {
+        match read_file_opt(&user_config_file()?).unknown()? {
+            Some(src) => src.parse(),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Writes this configuration back to the configuration file.
+    pub fn save(&self) -> Fallible<()> {
+        ensure_home_writable("change the Notion configuration")?;
+        let path = user_config_file()?;
+        ensure_containing_dir_exists(&path)?;
+        let src = self.to_toml()?;
+        let mut file = File::create(&path).unknown()?;
+        file.write_all(src.as_bytes()).unknown()?;
+        // the paths determined from now on follow the saved layout
+        *LAYOUT.lock().unwrap_or_else(PoisonError::into_inner) = Some(self.layout.clone());
+        Ok(())
+    }
+
+    /// Renders this configuration as the contents of a configuration file.
+    pub(crate) fn to_toml(&self) -> Fallible<String> {
+        toml::to_string(&self.to_serial()).unknown()
+    }
+}
+
+impl FromStr for Config {
+    type Err = NotionError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let serial: serial::Config = toml::from_str(src).unknown()?;
+        Ok(serial.into_config()?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::Config;
+    use crate::updater::Channel;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_from_str_empty() {
+        let config: Config = "".parse().expect("Could not parse empty config");
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_from_str_shim_dir() {
+        let config: Config = "[layout]\nshim_dir = \"/opt/notion/bin\"\n"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(
+            config.layout.shim_dir,
+            Some(PathBuf::from("/opt/notion/bin"))
+        );
+    }
+
+    #[test]
+    fn test_from_str_read_only() {
+        let config: Config = "[layout]\nread_only = true\n"
+            .parse()
+            .expect("Could not parse config");
+        assert!(config.layout.read_only);
+        assert_eq!(config.layout.shim_dir, None);
+    }
+
+    #[test]
+    fn test_from_str_shared_dir() {
+        let config: Config = "[layout]\nshared_dir = \"/srv/notion\"\n"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(config.layout.shared_dir, Some(PathBuf::from("/srv/notion")));
+    }
+
+    #[test]
+    fn test_from_str_layout_dirs() {
+        let config: Config = "[layout]\n\
+                              cache_dir = \"/var/cache/notion\"\n\
+                              inventory_dir = \"/mnt/tools/inventory\"\n\
+                              seed_dir = \"/opt/notion-seed\"\n"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(
+            config.layout.cache_dir,
+            Some(PathBuf::from("/var/cache/notion"))
+        );
+        assert_eq!(
+            config.layout.inventory_dir,
+            Some(PathBuf::from("/mnt/tools/inventory"))
+        );
+        assert_eq!(
+            config.layout.seed_dir,
+            Some(PathBuf::from("/opt/notion-seed"))
+        );
+        assert_eq!(config.layout.shared_dir, None);
+    }
+
+    #[test]
+    fn test_from_str_audit() {
+        let config: Config = "[audit]\nfile = \"/var/log/notion.jsonl\"\nsyslog = true\n"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(
+            config.audit.file,
+            Some(PathBuf::from("/var/log/notion.jsonl"))
+        );
+        assert!(config.audit.syslog);
+        assert!(config.audit.is_enabled());
+    }
+
+    #[test]
+    fn test_from_str_credentials() {
+        let config: Config = "[credentials]\nkeychain = true\n"
+            .parse()
+            .expect("Could not parse config");
+        assert!(config.credentials.keychain);
+    }
+
+    #[test]
+    fn test_from_str_prefetch() {
+        let config: Config = "[prefetch]\nlts = true\n"
+            .parse()
+            .expect("Could not parse config");
+        assert!(config.prefetch.lts);
+    }
+
+    #[test]
+    fn test_from_str_verify() {
+        let config: Config = "[verify]\nsignatures = true\n"
+            .parse()
+            .expect("Could not parse config");
+        assert!(config.verify.signatures);
+    }
+
+    #[test]
+    fn test_from_str_network() {
+        let config: Config = "[network]\nhttps_proxy = \"http://proxy.example.com:8080\"\nno_proxy = [\"example.com\"]\n"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(
+            config.network.https_proxy,
+            Some("http://proxy.example.com:8080".to_string())
+        );
+        assert_eq!(config.network.http_proxy, None);
+        assert_eq!(config.network.no_proxy, vec!["example.com".to_string()]);
+        assert!("[network]\nhttp_proxy = \"http://\"\n"
+            .parse::<Config>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_from_str_pin() {
+        let config: Config = "[pin]\nengines = true\n"
+            .parse()
+            .expect("Could not parse config");
+        assert!(config.pin.engines);
+    }
+
+    #[test]
+    fn test_from_str_project() {
+        let config: Config = "[project]\nengines = true\n"
+            .parse()
+            .expect("Could not parse config");
+        assert!(config.project.engines);
+        assert!(!Config::default().project.engines);
+    }
+
+    #[test]
+    fn test_from_str_index() {
+        let config: Config = "[index]
+ttl_minutes = 30
+"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(config.index.ttl_minutes, Some(30));
+        assert_eq!(Config::default().index.ttl_minutes, None);
+    }
+
+    #[test]
+    fn test_from_str_gc() {
+        let config: Config = "[gc]\nafter_installs = 5\nmax_size_mb = 2048\n"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(config.gc.after_installs, Some(5));
+        assert_eq!(config.gc.max_size_mb, Some(2048));
+        assert_eq!(config.gc.keep_recent, None);
+        assert!(config.gc.is_enabled());
+        assert!(!Config::default().gc.is_enabled());
+    }
+
+    #[test]
+    fn test_from_str_accessibility() {
+        let config: Config = "[accessibility]\nscreen_reader = true\n"
+            .parse()
+            .expect("Could not parse config");
+        assert!(config.accessibility.screen_reader);
+    }
+
+    #[test]
+    fn test_from_str_progress() {
+        let config: Config = "[progress]\ntheme = \"ascii\"\nbar_color = \"none\"\n"
+            .parse()
+            .expect("Could not parse config");
+        let theme = config.progress.theme();
+        assert_eq!(theme.tick_chars, Some("|/-\\ ".to_string()));
+        assert_eq!(theme.bar_color, None);
+
+        assert!("[progress]\ntheme = \"fancy\"\n".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_from_str_update() {
+        let config: Config = "[update]\nchannel = \"canary\"\n"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(config.update.channel, Channel::Canary);
+        assert_eq!(Config::default().update.channel, Channel::Stable);
+
+        assert!("[update]\nchannel = \"nightly\"\n"
+            .parse::<Config>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_from_str_retry() {
+        let config: Config = "[retry]\nattempts = 5\nbackoff_ms = 100\nstatuses = [503]\n"
+            .parse()
+            .expect("Could not parse config");
+        assert_eq!(config.retry.attempts, Some(5));
+        assert_eq!(config.retry.backoff_ms, Some(100));
+        assert_eq!(config.retry.statuses, Some(vec![503]));
+
+        assert!("[retry]\nattempts = 0\n".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn test_from_str_mirror() {
+        let config: Config = r#"
+[mirror]
+dir = "/srv/mirror"
+node = ["10", "^8.9"]
+yarn = ["1.12"]
+platforms = ["linux-x64", "win-x64"]
+"#
+        .parse()
+        .expect("Could not parse config");
+        assert_eq!(config.mirror.dir, Some(PathBuf::from("/srv/mirror")));
+        assert_eq!(config.mirror.node.len(), 2);
+        assert_eq!(config.mirror.yarn.len(), 1);
+        assert_eq!(config.mirror.platforms, vec!["linux-x64", "win-x64"]);
+
+        assert!("[mirror]\nnode = [\"ten\"]\n".parse::<Config>().is_err());
+    }
+}
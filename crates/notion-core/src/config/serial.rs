@@ -0,0 +1,521 @@
+use std::path::PathBuf;
+
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+
+use super::ConfigValueError;
+use crate::network;
+use crate::style::ProgressTheme;
+use crate::updater::Channel;
+use notion_fail::{throw, Fallible, ResultExt};
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<LayoutConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audit: Option<AuditConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror: Option<MirrorConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<CredentialsConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefetch: Option<PrefetchConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessibility: Option<AccessibilityConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<ProgressConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pin: Option<PinConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gc: Option<GcConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify: Option<VerifyConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<ProjectConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<IndexConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<UpdateConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct LayoutConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shim_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inventory_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct AuditConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syslog: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct MirrorConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub node: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub yarn: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct CredentialsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keychain: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct PrefetchConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lts: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct PinConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engines: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ProjectConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engines: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct IndexConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_minutes: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct UpdateConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct RetryConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backoff_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub statuses: Option<Vec<u16>>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct VerifyConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signatures: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct NetworkConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub https_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_proxy: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct GcConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_installs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size_mb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_recent: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct AccessibilityConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen_reader: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ProgressConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bar_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spinner_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress_chars: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick_chars: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bar_color: Option<String>,
+}
+
+impl Config {
+    pub fn into_config(self) -> Fallible<super::Config> {
+        Ok(super::Config {
+            layout: self.layout.unwrap_or_default().into_layout_config(),
+            audit: self.audit.unwrap_or_default().into_audit_config(),
+            mirror: self.mirror.unwrap_or_default().into_mirror_config()?,
+            credentials: super::CredentialsConfig {
+                keychain: self
+                    .credentials
+                    .and_then(|credentials| credentials.keychain)
+                    .unwrap_or(false),
+            },
+            prefetch: super::PrefetchConfig {
+                lts: self
+                    .prefetch
+                    .and_then(|prefetch| prefetch.lts)
+                    .unwrap_or(false),
+            },
+            accessibility: super::AccessibilityConfig {
+                screen_reader: self
+                    .accessibility
+                    .and_then(|accessibility| accessibility.screen_reader)
+                    .unwrap_or(false),
+            },
+            progress: self.progress.unwrap_or_default().into_progress_config()?,
+            pin: super::PinConfig {
+                engines: self.pin.and_then(|pin| pin.engines).unwrap_or(false),
+            },
+            gc: self.gc.unwrap_or_default().into_gc_config(),
+            verify: super::VerifyConfig {
+                signatures: self
+                    .verify
+                    .and_then(|verify| verify.signatures)
+                    .unwrap_or(false),
+            },
+            network: self.network.unwrap_or_default().into_network_config()?,
+            project: super::ProjectConfig {
+                engines: self
+                    .project
+                    .and_then(|project| project.engines)
+                    .unwrap_or(false),
+            },
+            index: super::IndexConfig {
+                ttl_minutes: self.index.and_then(|index| index.ttl_minutes),
+            },
+            update: self.update.unwrap_or_default().into_update_config()?,
+            retry: self.retry.unwrap_or_default().into_retry_config()?,
+        })
+    }
+}
+
+impl LayoutConfig {
+    fn into_layout_config(self) -> super::LayoutConfig {
+        super::LayoutConfig {
+            shim_dir: self.shim_dir.map(PathBuf::from),
+            shared_dir: self.shared_dir.map(PathBuf::from),
+            cache_dir: self.cache_dir.map(PathBuf::from),
+            inventory_dir: self.inventory_dir.map(PathBuf::from),
+            seed_dir: self.seed_dir.map(PathBuf::from),
+            read_only: self.read_only.unwrap_or(false),
+        }
+    }
+}
+
+impl AuditConfig {
+    fn into_audit_config(self) -> super::AuditConfig {
+        super::AuditConfig {
+            file: self.file.map(PathBuf::from),
+            syslog: self.syslog.unwrap_or(false),
+        }
+    }
+}
+
+impl MirrorConfig {
+    fn into_mirror_config(self) -> Fallible<super::MirrorConfig> {
+        Ok(super::MirrorConfig {
+            dir: self.dir.map(PathBuf::from),
+            node: parse_requirements("mirror.node", &self.node)?,
+            yarn: parse_requirements("mirror.yarn", &self.yarn)?,
+            platforms: self.platforms,
+        })
+    }
+}
+
+impl GcConfig {
+    fn into_gc_config(self) -> super::GcConfig {
+        super::GcConfig {
+            after_installs: self.after_installs,
+            max_size_mb: self.max_size_mb,
+            keep_recent: self.keep_recent,
+        }
+    }
+}
+
+impl NetworkConfig {
+    fn into_network_config(self) -> Fallible<super::NetworkConfig> {
+        let proxies = vec![
+            ("network.http_proxy", &self.http_proxy),
+            ("network.https_proxy", &self.https_proxy),
+        ];
+        for (key, proxy) in proxies {
+            if let Some(proxy) = proxy {
+                if let Err(error) = network::parse_proxy(proxy) {
+                    throw!(ConfigValueError {
+                        key: key.to_string(),
+                        error: format!("{} ({})", error, proxy),
+                    });
+                }
+            }
+        }
+
+        Ok(super::NetworkConfig {
+            http_proxy: self.http_proxy,
+            https_proxy: self.https_proxy,
+            no_proxy: self.no_proxy,
+        })
+    }
+}
+
+impl ProgressConfig {
+    fn into_progress_config(self) -> Fallible<super::ProgressConfig> {
+        if let Some(ref name) = self.theme {
+            if ProgressTheme::named(name).is_none() {
+                throw!(ConfigValueError {
+                    key: "progress.theme".to_string(),
+                    error: format!(
+                        "unknown theme `{}` (expected `default`, `ascii` or `minimal`)",
+                        name
+                    ),
+                });
+            }
+        }
+
+        Ok(super::ProgressConfig {
+            theme: self.theme,
+            bar_template: self.bar_template,
+            spinner_template: self.spinner_template,
+            progress_chars: self.progress_chars,
+            tick_chars: self.tick_chars,
+            bar_color: self.bar_color,
+        })
+    }
+}
+
+impl UpdateConfig {
+    fn into_update_config(self) -> Fallible<super::UpdateConfig> {
+        let channel = match self.channel {
+            Some(channel) => match channel.parse::<Channel>() {
+                Ok(channel) => channel,
+                Err(_) => throw!(ConfigValueError {
+                    key: "update.channel".to_string(),
+                    error: format!(
+                        "unknown channel `{}` (expected `stable` or `canary`)",
+                        channel
+                    ),
+                }),
+            },
+            None => Channel::default(),
+        };
+        Ok(super::UpdateConfig { channel })
+    }
+}
+
+impl RetryConfig {
+    fn into_retry_config(self) -> Fallible<super::RetryConfig> {
+        if self.attempts == Some(0) {
+            throw!(ConfigValueError {
+                key: "retry.attempts".to_string(),
+                error: "a download is attempted at least once".to_string(),
+            });
+        }
+        Ok(super::RetryConfig {
+            attempts: self.attempts,
+            backoff_ms: self.backoff_ms,
+            statuses: self.statuses,
+        })
+    }
+}
+
+fn parse_requirements(key: &str, requirements: &[String]) -> Fallible<Vec<VersionReq>> {
+    requirements
+        .iter()
+        .map(|requirement| {
+            VersionReq::parse(requirement).with_context(|error: &semver::ReqParseError| {
+                ConfigValueError {
+                    key: key.to_string(),
+                    error: format!("{} ({})", error, requirement),
+                }
+            })
+        })
+        .collect()
+}
+
+impl super::Config {
+    pub(crate) fn to_serial(&self) -> Config {
+        Config {
+            layout: Some(LayoutConfig {
+                shim_dir: self
+                    .layout
+                    .shim_dir
+                    .as_ref()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+                shared_dir: self
+                    .layout
+                    .shared_dir
+                    .as_ref()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+                cache_dir: self
+                    .layout
+                    .cache_dir
+                    .as_ref()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+                inventory_dir: self
+                    .layout
+                    .inventory_dir
+                    .as_ref()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+                seed_dir: self
+                    .layout
+                    .seed_dir
+                    .as_ref()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+                read_only: if self.layout.read_only {
+                    Some(true)
+                } else {
+                    None
+                },
+            }),
+            audit: if self.audit.is_enabled() {
+                Some(AuditConfig {
+                    file: self
+                        .audit
+                        .file
+                        .as_ref()
+                        .map(|file| file.to_string_lossy().to_string()),
+                    syslog: if self.audit.syslog { Some(true) } else { None },
+                })
+            } else {
+                None
+            },
+            mirror: if self.mirror == super::MirrorConfig::default() {
+                None
+            } else {
+                Some(MirrorConfig {
+                    dir: self
+                        .mirror
+                        .dir
+                        .as_ref()
+                        .map(|dir| dir.to_string_lossy().to_string()),
+                    node: self.mirror.node.iter().map(ToString::to_string).collect(),
+                    yarn: self.mirror.yarn.iter().map(ToString::to_string).collect(),
+                    platforms: self.mirror.platforms.clone(),
+                })
+            },
+            credentials: if self.credentials.keychain {
+                Some(CredentialsConfig {
+                    keychain: Some(true),
+                })
+            } else {
+                None
+            },
+            prefetch: if self.prefetch.lts {
+                Some(PrefetchConfig { lts: Some(true) })
+            } else {
+                None
+            },
+            accessibility: if self.accessibility.screen_reader {
+                Some(AccessibilityConfig {
+                    screen_reader: Some(true),
+                })
+            } else {
+                None
+            },
+            progress: if self.progress == super::ProgressConfig::default() {
+                None
+            } else {
+                Some(ProgressConfig {
+                    theme: self.progress.theme.clone(),
+                    bar_template: self.progress.bar_template.clone(),
+                    spinner_template: self.progress.spinner_template.clone(),
+                    progress_chars: self.progress.progress_chars.clone(),
+                    tick_chars: self.progress.tick_chars.clone(),
+                    bar_color: self.progress.bar_color.clone(),
+                })
+            },
+            pin: if self.pin.engines {
+                Some(PinConfig {
+                    engines: Some(true),
+                })
+            } else {
+                None
+            },
+            gc: if self.gc == super::GcConfig::default() {
+                None
+            } else {
+                Some(GcConfig {
+                    after_installs: self.gc.after_installs,
+                    max_size_mb: self.gc.max_size_mb,
+                    keep_recent: self.gc.keep_recent,
+                })
+            },
+            verify: if self.verify.signatures {
+                Some(VerifyConfig {
+                    signatures: Some(true),
+                })
+            } else {
+                None
+            },
+            network: if self.network == super::NetworkConfig::default() {
+                None
+            } else {
+                Some(NetworkConfig {
+                    http_proxy: self.network.http_proxy.clone(),
+                    https_proxy: self.network.https_proxy.clone(),
+                    no_proxy: self.network.no_proxy.clone(),
+                })
+            },
+            project: if self.project.engines {
+                Some(ProjectConfig {
+                    engines: Some(true),
+                })
+            } else {
+                None
+            },
+            index: self.index.ttl_minutes.map(|ttl_minutes| IndexConfig {
+                ttl_minutes: Some(ttl_minutes),
+            }),
+            update: if self.update.channel == Channel::default() {
+                None
+            } else {
+                Some(UpdateConfig {
+                    channel: Some(self.update.channel.to_string()),
+                })
+            },
+            retry: if self.retry == super::RetryConfig::default() {
+                None
+            } else {
+                Some(RetryConfig {
+                    attempts: self.retry.attempts,
+                    backoff_ms: self.retry.backoff_ms,
+                    statuses: self.retry.statuses.clone(),
+                })
+            },
+        }
+    }
+}
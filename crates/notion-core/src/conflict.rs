@@ -0,0 +1,253 @@
+//! Provides the detection of other Node installations that shadow the Notion shims.
+//!
+//! When another version manager's shims (nvm, nodenv, asdf, ...) or a system Node come
+//! before the shim directory on `PATH`, the shell runs them instead of the shims, so that
+//! nothing Notion selects has any effect. Notion warns about this once for each shadowing
+//! directory when a command starts, and on every tool invocation when `NOTION_DEBUG` is
+//! set.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::Finding;
+use crate::env::is_debug;
+use crate::fs::{ensure_containing_dir_exists, is_read_only_home};
+use crate::path;
+use crate::style::display_warning;
+use notion_fail::{Fallible, ResultExt};
+
+/// A directory on `PATH` providing `node` before the shim directory.
+#[derive(Debug, PartialEq)]
+pub struct Conflict {
+    /// The directory that wins.
+    pub dir: PathBuf,
+    /// The shim directory that loses.
+    pub shim_dir: PathBuf,
+}
+
+impl Conflict {
+    /// The tool that installed the shadowing directory, as far as its path tells.
+    pub fn manager(&self) -> &'static str {
+        manager_of(&self.dir)
+    }
+}
+
+fn manager_of(dir: &Path) -> &'static str {
+    let dir = dir.to_string_lossy().replace('\\', "/").to_lowercase();
+    let markers: &[(&str, &'static str)] = &[
+        ("/.nvm/", "nvm"),
+        ("/nvm/", "nvm"),
+        ("/.nodenv/", "nodenv"),
+        ("/.asdf/", "asdf"),
+        ("/.fnm/", "fnm"),
+        ("/fnm_multishells/", "fnm"),
+        ("/.volta/", "Volta"),
+        ("/n/versions/", "n"),
+    ];
+    markers
+        .iter()
+        .find(|(marker, _)| format!("{}/", dir).contains(marker))
+        .map(|(_, manager)| *manager)
+        .unwrap_or("a system Node installation")
+}
+
+fn node_executable() -> &'static str {
+    if cfg!(windows) {
+        "node.exe"
+    } else {
+        "node"
+    }
+}
+
+/// Finds the first entry of `entries` that provides `node` before `shim_dir`, if any.
+/// Nothing is found when the shim directory isn't on the path at all, since then Notion
+/// isn't set up in this environment to begin with. Entries under `image_dirs` are Notion's
+/// own images, which it puts on the path of the tools it runs, so they are skipped.
+fn shadowing(
+    entries: &[PathBuf],
+    shim_dir: &Path,
+    image_dirs: &[PathBuf],
+    has_node: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    let shim_index = entries.iter().position(|entry| entry == shim_dir)?;
+    entries[..shim_index]
+        .iter()
+        .filter(|entry| !image_dirs.iter().any(|dir| entry.starts_with(dir)))
+        .find(|entry| has_node(entry))
+        .cloned()
+}
+
+/// Detects whether another Node installation shadows the shim directory on `PATH`.
+pub fn detect() -> Fallible<Option<Conflict>> {
+    let shim_dir = path::shim_dir()?;
+    let entries: Vec<PathBuf> = match env::var_os("PATH") {
+        Some(path) => env::split_paths(&path).collect(),
+        None => return Ok(None),
+    };
+    let mut image_dirs = vec![path::image_dir()?];
+    if let Some(seed) = path::seed_dir()? {
+        image_dirs.push(seed.join("tools").join("image"));
+    }
+    Ok(shadowing(&entries, &shim_dir, &image_dirs, |dir| {
+        dir.join(node_executable()).is_file()
+    })
+    .map(|dir| Conflict { dir, shim_dir }))
+}
+
+/// Checks that the shim directory is on `PATH`, and that no other Node installation
+/// shadows it.
+pub(crate) fn diagnose() -> Fallible<Vec<Finding>> {
+    let shim_dir = path::shim_dir()?;
+    let on_path = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).any(|entry| entry == shim_dir))
+        .unwrap_or(false);
+    if !on_path {
+        return Ok(vec![Finding::new(format!(
+            "The shim directory {} is not on PATH; see `notion env` for the shell setup",
+            shim_dir.display()
+        ))]);
+    }
+
+    Ok(detect()?
+        .map(|conflict| {
+            Finding::new(format!(
+                "`node` is found in {} ({}) before the shim directory on PATH",
+                conflict.dir.display(),
+                conflict.manager()
+            ))
+        })
+        .into_iter()
+        .collect())
+}
+
+fn warn(conflict: &Conflict) {
+    display_warning(format!(
+        "`node` is found in {} ({}) before Notion's shim directory {}, so {} will run instead \
+         of the version Notion selects.\n\nMove {} to the front of PATH in your shell profile, \
+         or remove the other tool's setup.",
+        conflict.dir.display(),
+        conflict.manager(),
+        conflict.shim_dir.display(),
+        if conflict.manager() == "a system Node installation" {
+            "that Node".to_string()
+        } else {
+            format!("{}'s Node", conflict.manager())
+        },
+        conflict.shim_dir.display()
+    ));
+}
+
+/// Warns about a shadowing Node installation the first time it is detected.
+pub fn warn_once() -> Fallible<()> {
+    let conflict = match detect()? {
+        Some(conflict) => conflict,
+        None => return Ok(()),
+    };
+
+    let record = path::path_conflict_file()?;
+    let dir = conflict.dir.to_string_lossy().into_owned();
+    if let Ok(warned) = fs::read_to_string(&record) {
+        if warned.lines().any(|line| line == dir) {
+            return Ok(());
+        }
+    }
+
+    warn(&conflict);
+    if !is_read_only_home() {
+        ensure_containing_dir_exists(&record)?;
+        let mut warned = fs::read_to_string(&record).unwrap_or_default();
+        warned.push_str(&dir);
+        warned.push('\n');
+        fs::write(&record, warned).unknown()?;
+    }
+    Ok(())
+}
+
+/// Warns about a shadowing Node installation on every tool invocation, if `NOTION_DEBUG`
+/// is set.
+pub fn warn_if_debug() -> Fallible<()> {
+    if is_debug() {
+        if let Some(conflict) = detect()? {
+            warn(&conflict);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{manager_of, shadowing};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_shadowing() {
+        let shim_dir = PathBuf::from("/home/user/.notion/bin");
+        let nvm = PathBuf::from("/home/user/.nvm/versions/node/v8.9.4/bin");
+        let usr = PathBuf::from("/usr/bin");
+        let has_node = |dir: &Path| dir == nvm.as_path() || dir == usr.as_path();
+
+        assert_eq!(
+            shadowing(
+                &[usr.clone(), nvm.clone(), shim_dir.clone()],
+                &shim_dir,
+                &[],
+                has_node
+            ),
+            Some(usr.clone())
+        );
+        assert_eq!(
+            shadowing(&[shim_dir.clone(), nvm.clone()], &shim_dir, &[], has_node),
+            None
+        );
+        assert_eq!(
+            shadowing(&[nvm.clone(), usr.clone()], &shim_dir, &[], has_node),
+            None
+        );
+    }
+
+    #[test]
+    fn test_shadowing_skips_images() {
+        let shim_dir = PathBuf::from("/home/user/.notion/bin");
+        let image_dir = PathBuf::from("/home/user/.notion/tools/image");
+        let seed_image_dir = PathBuf::from("/opt/notion-seed/tools/image");
+        let image = image_dir.join("node/10.13.0/6.4.1/bin");
+        let seed_image = seed_image_dir.join("node/8.9.4/5.6.0/bin");
+        let usr = PathBuf::from("/usr/bin");
+        let image_dirs = [image_dir.clone(), seed_image_dir.clone()];
+
+        assert_eq!(
+            shadowing(
+                &[image.clone(), seed_image.clone(), shim_dir.clone()],
+                &shim_dir,
+                &image_dirs,
+                |_| true
+            ),
+            None
+        );
+        assert_eq!(
+            shadowing(
+                &[image.clone(), usr.clone(), shim_dir.clone()],
+                &shim_dir,
+                &image_dirs,
+                |_| true
+            ),
+            Some(usr.clone())
+        );
+    }
+
+    #[test]
+    fn test_manager_of() {
+        assert_eq!(
+            manager_of(Path::new("/home/user/.nvm/versions/node/v8.9.4/bin")),
+            "nvm"
+        );
+        assert_eq!(manager_of(Path::new("/home/user/.nodenv/shims")), "nodenv");
+        assert_eq!(manager_of(Path::new("/home/user/.asdf/shims")), "asdf");
+        assert_eq!(
+            manager_of(Path::new("/usr/local/bin")),
+            "a system Node installation"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Provides lookup of credentials for servers that require authentication, such as
+//! internal mirrors.
+//!
+//! Credentials are never stored in Notion's own configuration files. Instead they are
+//! looked up in the user's `.netrc` file (or the file named by the `NETRC` environment
+//! variable) and, when enabled with `keychain = true` in the `[credentials]` section of
+//! the configuration, in the OS credential store:
+//!
+//! - macOS: an internet password for the host in the Keychain
+//! - Windows: a web credential for the host in the Credential Manager
+//! - Linux: a Secret Service item with the attributes `service=notion` and `host=<host>`
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use archive::Credentials;
+use reqwest::{self, Url};
+
+use crate::config::Config;
+use crate::network;
+use notion_fail::Fallible;
+
+/// Builds a GET request for `url`, authenticated with the credentials for its host,
+/// if there are any, and sent through its proxy.
+pub(crate) fn get(url: &str) -> Fallible<reqwest::RequestBuilder> {
+    let mut request = network::get(url)?;
+    if let Some(credentials) = for_url(url)? {
+        request.basic_auth(credentials.username, credentials.password);
+    }
+    Ok(request)
+}
+
+/// Builds a POST request for `url`, authenticated and proxied as with `get`.
+pub(crate) fn post(url: &str) -> Fallible<reqwest::RequestBuilder> {
+    let mut request = network::post(url)?;
+    if let Some(credentials) = for_url(url)? {
+        request.basic_auth(credentials.username, credentials.password);
+    }
+    Ok(request)
+}
+
+/// Looks up the credentials for the host of `url`.
+pub(crate) fn for_url(url: &str) -> Fallible<Option<Credentials>> {
+    let host = match Url::parse(url).ok().and_then(|url| url.host_str().map(String::from)) {
+        Some(host) => host,
+        None => return Ok(None),
+    };
+
+    if let Some(credentials) = from_netrc(&host) {
+        return Ok(Some(credentials));
+    }
+
+    if Config::current()?.credentials.keychain {
+        return Ok(from_keychain(&host));
+    }
+
+    Ok(None)
+}
+
+fn netrc_file() -> Option<PathBuf> {
+    if let Some(file) = env::var_os("NETRC") {
+        return Some(PathBuf::from(file));
+    }
+
+    let home = dirs::home_dir()?;
+    let file_name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+    Some(home.join(file_name))
+}
+
+fn from_netrc(host: &str) -> Option<Credentials> {
+    let src = fs::read_to_string(netrc_file()?).ok()?;
+    parse_netrc(&src, host)
+}
+
+/// Finds the credentials for `host` in the contents of a `.netrc` file, falling back
+/// to the `default` entry.
+fn parse_netrc(src: &str, host: &str) -> Option<Credentials> {
+    let mut matched: Option<Credentials> = None;
+    let mut default: Option<Credentials> = None;
+    // the entry currently being read, and whether it applies to `host`
+    let mut current: Option<(bool, Credentials)> = None;
+    let mut in_macro = false;
+
+    let finish = |current: Option<(bool, Credentials)>,
+                  matched: &mut Option<Credentials>,
+                  default: &mut Option<Credentials>| {
+        match current {
+            Some((true, credentials)) => {
+                if matched.is_none() {
+                    *matched = Some(credentials);
+                }
+            }
+            Some((false, credentials)) => {
+                if default.is_none() {
+                    *default = Some(credentials);
+                }
+            }
+            None => {}
+        }
+    };
+
+    for line in src.lines() {
+        // macro definitions run until the next blank line
+        if in_macro {
+            in_macro = !line.trim().is_empty();
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            match token {
+                "machine" => {
+                    finish(current.take(), &mut matched, &mut default);
+                    if let Some(machine) = tokens.next() {
+                        if machine == host {
+                            current = Some((true, empty_credentials()));
+                        }
+                    }
+                }
+                "default" => {
+                    finish(current.take(), &mut matched, &mut default);
+                    current = Some((false, empty_credentials()));
+                }
+                "login" => {
+                    if let (Some((_, credentials)), Some(login)) = (current.as_mut(), tokens.next())
+                    {
+                        credentials.username = login.to_string();
+                    }
+                }
+                "password" => {
+                    if let (Some((_, credentials)), Some(password)) =
+                        (current.as_mut(), tokens.next())
+                    {
+                        credentials.password = Some(password.to_string());
+                    }
+                }
+                "account" => {
+                    tokens.next();
+                }
+                "macdef" => {
+                    in_macro = true;
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    finish(current, &mut matched, &mut default);
+    matched.or(default)
+}
+
+fn empty_credentials() -> Credentials {
+    Credentials {
+        username: String::new(),
+        password: None,
+    }
+}
+
+/// Runs a credential store command, returning its output if it succeeded.
+fn run(command: &mut Command) -> Option<String> {
+    let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn from_keychain(host: &str) -> Option<Credentials> {
+    let attributes = run(Command::new("security").args(&["find-internet-password", "-s", host]))?;
+    let password = run(Command::new("security").args(&[
+        "find-internet-password",
+        "-s",
+        host,
+        "-w",
+    ]))?;
+
+    // the account is listed as `"acct"<blob>="alice"`
+    let username = attributes
+        .lines()
+        .find(|line| line.trim().starts_with("\"acct\""))
+        .and_then(|line| line.splitn(2, '=').nth(1))
+        .map(|value| value.trim().trim_matches('"').to_string())?;
+
+    Some(Credentials {
+        username,
+        password: Some(password.trim_end_matches('\n').to_string()),
+    })
+}
+
+#[cfg(windows)]
+fn from_keychain(host: &str) -> Option<Credentials> {
+    let script = format!(
+        "[void][Windows.Security.Credentials.PasswordVault,Windows.Security.Credentials,ContentType=WindowsRuntime]; \
+         $c = (New-Object Windows.Security.Credentials.PasswordVault).FindAllByResource('{}')[0]; \
+         $c.RetrievePassword(); $c.UserName; $c.Password",
+        host.replace('\'', "''")
+    );
+    let output = run(Command::new("powershell").args(&["-NoProfile", "-Command", &script]))?;
+
+    let mut lines = output.lines();
+    let username = lines.next()?.trim().to_string();
+    let password = lines.next().map(|password| password.trim().to_string());
+    Some(Credentials { username, password })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn from_keychain(host: &str) -> Option<Credentials> {
+    let output = run(Command::new("secret-tool").args(&[
+        "search", "service", "notion", "host", host,
+    ]))?;
+
+    // items are listed as `attribute.user = alice` and `secret = ...` lines
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let mut parts = line.splitn(2, " = ");
+            if parts.next()?.trim() == key {
+                parts.next().map(String::from)
+            } else {
+                None
+            }
+        })
+    };
+
+    Some(Credentials {
+        username: value("attribute.user")?,
+        password: value("secret"),
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::parse_netrc;
+    use archive::Credentials;
+
+    fn credentials(username: &str, password: &str) -> Option<Credentials> {
+        Some(Credentials {
+            username: username.to_string(),
+            password: Some(password.to_string()),
+        })
+    }
+
+    #[test]
+    fn test_parse_netrc() {
+        let src = "machine mirror.example.com\n  login alice\n  password s3cret\n\n\
+                   machine other.example.com login bob password hunter2\n";
+        assert_eq!(
+            parse_netrc(src, "mirror.example.com"),
+            credentials("alice", "s3cret")
+        );
+        assert_eq!(
+            parse_netrc(src, "other.example.com"),
+            credentials("bob", "hunter2")
+        );
+        assert_eq!(parse_netrc(src, "nodejs.org"), None);
+    }
+
+    #[test]
+    fn test_parse_netrc_default_and_macdef() {
+        let src = "macdef init\nmachine fake login nobody password nothing\n\n\
+                   machine mirror.example.com login alice password s3cret\n\
+                   default login anonymous password guest\n";
+        assert_eq!(parse_netrc(src, "fake"), credentials("anonymous", "guest"));
+        assert_eq!(
+            parse_netrc(src, "mirror.example.com"),
+            credentials("alice", "s3cret")
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Provides the checks of `notion doctor`, which diagnose the problems with a Notion
+//! installation that would otherwise only show up as confusing failures later on.
+//!
+//! Each subsystem contributes the checks of its own state (the shims, the inventory,
+//! the `PATH`), and reports what it finds as `Finding`s. A finding that Notion knows how
+//! to repair carries a `Fix`, which `notion doctor --fix` offers to apply.
+
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+
+use tempfile::NamedTempFile;
+
+use crate::checksum;
+use crate::conflict;
+use crate::env::is_offline;
+use crate::fs::{ensure_dir_exists, ensure_home_writable, is_read_only_home};
+use crate::inventory;
+use crate::network;
+use crate::path;
+use crate::shim;
+use notion_fail::{Fallible, ResultExt};
+
+/// A problem found by a check.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub problem: String,
+    pub fix: Option<Fix>,
+}
+
+impl Finding {
+    pub(crate) fn new(problem: String) -> Self {
+        Finding { problem, fix: None }
+    }
+
+    pub(crate) fn fixable(problem: String, fix: Fix) -> Self {
+        Finding {
+            problem,
+            fix: Some(fix),
+        }
+    }
+}
+
+/// A repair that Notion can make for a finding.
+#[derive(Debug, PartialEq)]
+pub enum Fix {
+    /// Creates a missing directory.
+    CreateDir(PathBuf),
+    /// Creates a missing shim.
+    CreateShim(String),
+    /// Replaces a shim that no longer links to the launcher.
+    RelinkShim(String),
+    /// Deletes a shim that nothing uses.
+    DeleteShim(String),
+    /// Deletes a leftover file.
+    RemoveFile(PathBuf),
+    /// Deletes a corrupted inventory archive, along with its recorded checksum, so that
+    /// it is downloaded again when it is next needed.
+    RemoveArchive(PathBuf),
+}
+
+impl Display for Fix {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Fix::CreateDir(dir) => write!(f, "Create {}", dir.display()),
+            Fix::CreateShim(name) => write!(f, "Create the shim for `{}`", name),
+            Fix::RelinkShim(name) => write!(f, "Recreate the shim for `{}`", name),
+            Fix::DeleteShim(name) => write!(f, "Delete the shim for `{}`", name),
+            Fix::RemoveFile(file) | Fix::RemoveArchive(file) => {
+                write!(f, "Delete {}", file.display())
+            }
+        }
+    }
+}
+
+impl Fix {
+    /// Applies the repair.
+    pub fn apply(&self) -> Fallible<()> {
+        ensure_home_writable(&self.to_string().to_lowercase())?;
+        match self {
+            Fix::CreateDir(dir) => ensure_dir_exists(dir),
+            Fix::CreateShim(name) => shim::create(name).map(|_| ()),
+            Fix::RelinkShim(name) => {
+                fs::remove_file(path::shim_file(name)?).unknown()?;
+                shim::create(name).map(|_| ())
+            }
+            Fix::DeleteShim(name) => shim::delete(name).map(|_| ()),
+            Fix::RemoveFile(file) => remove_if_exists(file),
+            Fix::RemoveArchive(file) => {
+                remove_if_exists(file)?;
+                checksum::forget(file)
+            }
+        }
+    }
+}
+
+fn remove_if_exists(file: &PathBuf) -> Fallible<()> {
+    if file.exists() {
+        fs::remove_file(file).unknown()?;
+    }
+    Ok(())
+}
+
+/// The findings of one check.
+pub struct Report {
+    /// What the check examines, e.g. `Shims`.
+    pub check: &'static str,
+    pub findings: Vec<Finding>,
+}
+
+/// Runs every check. A check that fails to run at all is reported as a finding of its
+/// own, so that one broken subsystem doesn't hide the problems of the others.
+pub fn run() -> Vec<Report> {
+    let checks: &[(&'static str, fn() -> Fallible<Vec<Finding>>)] = &[
+        ("PATH", conflict::diagnose),
+        ("NOTION_HOME", diagnose_home),
+        ("Shims", shim::diagnose),
+        ("Inventory", inventory::diagnose),
+        ("Network", diagnose_network),
+        #[cfg(windows)]
+        ("Long paths", diagnose_long_paths),
+    ];
+    checks
+        .iter()
+        .map(|(check, diagnose)| Report {
+            check,
+            findings: diagnose().unwrap_or_else(|error| {
+                vec![Finding::new(format!("The check could not run: {}", error))]
+            }),
+        })
+        .collect()
+}
+
+/// Checks that `NOTION_HOME` exists and that Notion can write to it (unless it is
+/// deliberately read-only).
+fn diagnose_home() -> Fallible<Vec<Finding>> {
+    let home = path::notion_home()?;
+    if !home.is_dir() {
+        return Ok(vec![Finding::fixable(
+            format!("{} does not exist", home.display()),
+            Fix::CreateDir(home),
+        )]);
+    }
+    if is_read_only_home() {
+        return Ok(Vec::new());
+    }
+    match NamedTempFile::new_in(&home) {
+        Ok(_) => Ok(Vec::new()),
+        Err(error) => Ok(vec![Finding::new(format!(
+            "{} is not writable ({}), so Notion can't fetch or install tools",
+            home.display(),
+            error
+        ))]),
+    }
+}
+
+/// Checks that the public Node server is reachable, through the configured proxy if any.
+fn diagnose_network() -> Fallible<Vec<Finding>> {
+    if is_offline() {
+        return Ok(Vec::new());
+    }
+    let url = inventory::public_node_version_index();
+    let unreachable = |reason: String| {
+        Ok(vec![Finding::new(format!(
+            "Could not reach {} ({})",
+            url, reason
+        ))])
+    };
+    match network::get(&url)?.send() {
+        Ok(ref response) if response.status().is_success() => Ok(Vec::new()),
+        Ok(response) => unreachable(format!("status {}", response.status())),
+        Err(error) => unreachable(error.to_string()),
+    }
+}
+
+/// Checks that Win32 long path support is enabled, without which the tools that Notion
+/// runs may fail on deep `node_modules` trees.
+#[cfg(windows)]
+fn diagnose_long_paths() -> Fallible<Vec<Finding>> {
+    match path::long_paths_enabled() {
+        Some(false) => Ok(vec![Finding::new(
+            r"Long path support is disabled (LongPathsEnabled in HKLM\SYSTEM\CurrentControlSet\Control\FileSystem), so tools may fail on deep node_modules trees"
+                .to_string(),
+        )]),
+        _ => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::Fix;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_fix_display() {
+        assert_eq!(
+            Fix::DeleteShim("tsc".to_string()).to_string(),
+            "Delete the shim for `tsc`"
+        );
+        assert_eq!(
+            Fix::RemoveArchive(PathBuf::from("/notion/node-v8.9.4.tar.gz")).to_string(),
+            "Delete /notion/node-v8.9.4.tar.gz"
+        );
+    }
+}
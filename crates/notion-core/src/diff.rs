@@ -0,0 +1,139 @@
+//! Provides line-based diffs, used to preview the changes Notion is about to make to
+//! files in the user's repository, such as `package.json`.
+
+use std::cmp::max;
+
+use console::style;
+
+/// A line of a diff.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl<'a> Line<'a> {
+    fn is_change(&self) -> bool {
+        match self {
+            Line::Same(_) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Computes a minimal line diff between `old` and `new`, based on their longest
+/// common subsequence of lines.
+pub(crate) fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                max(lengths[i + 1][j], lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|&line| Line::Removed(line)));
+    lines.extend(new[j..].iter().map(|&line| Line::Added(line)));
+    lines
+}
+
+/// Renders the changes in a diff, with `context` unchanged lines around each change,
+/// colored in the style of `git diff`. Returns `None` if nothing changed.
+pub(crate) fn render(lines: &[Line<'_>], context: usize) -> Option<String> {
+    let changes: Vec<usize> = (0..lines.len())
+        .filter(|&index| lines[index].is_change())
+        .collect();
+    if changes.is_empty() {
+        return None;
+    }
+
+    let is_shown = |index: usize| {
+        changes
+            .iter()
+            .any(|&change| index + context >= change && index <= change + context)
+    };
+
+    let mut rendered = Vec::new();
+    let mut skipped = false;
+    for (index, line) in lines.iter().enumerate() {
+        if !is_shown(index) {
+            skipped = true;
+            continue;
+        }
+        if skipped && !rendered.is_empty() {
+            rendered.push(style("  ...").dim().to_string());
+        }
+        skipped = false;
+
+        rendered.push(match line {
+            Line::Same(text) => format!("  {}", text),
+            Line::Removed(text) => style(format!("- {}", text)).red().to_string(),
+            Line::Added(text) => style(format!("+ {}", text)).green().to_string(),
+        });
+    }
+
+    Some(rendered.join("\n"))
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{diff_lines, render, Line};
+
+    #[test]
+    fn test_diff_lines() {
+        let old = "{\n  \"name\": \"app\",\n  \"toolchain\": {\n    \"node\": \"8.9.4\"\n  }\n}";
+        let new = "{\n  \"name\": \"app\",\n  \"toolchain\": {\n    \"node\": \"10.13.0\"\n  }\n}";
+
+        assert_eq!(
+            diff_lines(old, new),
+            vec![
+                Line::Same("{"),
+                Line::Same("  \"name\": \"app\","),
+                Line::Same("  \"toolchain\": {"),
+                Line::Removed("    \"node\": \"8.9.4\""),
+                Line::Added("    \"node\": \"10.13.0\""),
+                Line::Same("  }"),
+                Line::Same("}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let old = "a\nb\nc\nd\ne\nf";
+        let new = "a\nb\nc\nd\ne\nF";
+        let rendered = render(&diff_lines(old, new), 1).unwrap();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "  e");
+        assert!(lines[1].contains("- f"));
+        assert!(lines[2].contains("+ F"));
+
+        assert_eq!(render(&diff_lines(old, old), 1), None);
+    }
+}
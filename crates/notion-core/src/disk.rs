@@ -0,0 +1,200 @@
+//! Provides the disk-space preflight check run before a distribution is fetched.
+//!
+//! A download that runs out of space fails in the middle of unpacking, with a bare
+//! `ENOSPC` and a half-written image. Instead, the space a fetch needs (the rest of the
+//! archive in the inventory and the unpacked image in the temporary directory) is
+//! estimated up front and checked against what's available on each volume.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use failure::Fail;
+
+use notion_fail::{throw, ExitCode, Fallible, NotionFail};
+use notion_fail_derive::*;
+
+/// How many times larger than its archive an image is assumed to be, when the archive
+/// doesn't record its uncompressed size.
+const EXPANSION_FACTOR: u64 = 3;
+
+/// Thrown when a volume doesn't have enough room for a fetch.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Not enough disk space to fetch {}\n\n{} is needed on {}, but only {} is available.",
+    tool, required, volume, available
+)]
+#[notion_fail(code = "FileSystemError")]
+pub(crate) struct InsufficientSpaceError {
+    tool: String,
+    volume: String,
+    required: Size,
+    available: Size,
+}
+
+/// A number of bytes, displayed in the largest unit that keeps it above 1.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Size(pub u64);
+
+impl Display for Size {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = self.0 as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            write!(f, "{} {}", self.0, UNITS[0])
+        } else {
+            write!(f, "{:.1} {}", size, UNITS[unit])
+        }
+    }
+}
+
+/// A mounted volume and its free space.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Volume {
+    pub(crate) mount: PathBuf,
+    pub(crate) available: u64,
+}
+
+/// Estimates the size of the image unpacked from an archive.
+pub(crate) fn unpacked_size(compressed: u64, uncompressed: Option<u64>) -> u64 {
+    uncompressed.unwrap_or(compressed.saturating_mul(EXPANSION_FACTOR))
+}
+
+/// Checks that each of the `requirements`, the number of bytes a fetch of `tool` will
+/// write into a directory, fits on the volume holding the directory. Requirements on the
+/// same volume are added up. Volumes whose free space can't be determined are skipped, so
+/// that the check never blocks a fetch on a platform it doesn't understand.
+pub(crate) fn preflight(tool: &str, requirements: &[(&Path, u64)]) -> Fallible<()> {
+    let volumes: Vec<(Volume, u64)> = requirements
+        .iter()
+        .filter_map(|&(dir, bytes)| volume_of(dir).map(|volume| (volume, bytes)))
+        .collect();
+
+    if let Some((volume, required)) = shortfall(&volumes) {
+        throw!(InsufficientSpaceError {
+            tool: tool.to_string(),
+            volume: volume.mount.display().to_string(),
+            required: Size(required),
+            available: Size(volume.available),
+        });
+    }
+    Ok(())
+}
+
+/// Finds the first volume whose requirements, added up, exceed its free space.
+fn shortfall(requirements: &[(Volume, u64)]) -> Option<(Volume, u64)> {
+    let mut totals: BTreeMap<&Path, (&Volume, u64)> = BTreeMap::new();
+    for (volume, bytes) in requirements {
+        let total = totals.entry(&volume.mount).or_insert((volume, 0));
+        total.1 = total.1.saturating_add(*bytes);
+    }
+
+    totals
+        .values()
+        .find(|(volume, required)| *required > volume.available)
+        .map(|&(volume, required)| (volume.clone(), required))
+}
+
+/// Runs a command that reports free space, returning its output if it succeeded.
+fn run(command: &mut Command) -> Option<String> {
+    let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output().ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        None
+    }
+}
+
+#[cfg(unix)]
+fn volume_of(dir: &Path) -> Option<Volume> {
+    parse_df(&run(Command::new("df").arg("-Pk").arg(dir))?)
+}
+
+/// Parses the POSIX output of `df -Pk`:
+///
+/// ```text
+/// Filesystem     1024-blocks      Used Available Capacity Mounted on
+/// /dev/sda1        102400000  51200000  51200000      50% /home
+/// ```
+#[cfg(any(unix, test))]
+fn parse_df(output: &str) -> Option<Volume> {
+    let fields: Vec<&str> = output.lines().nth(1)?.split_whitespace().collect();
+    if fields.len() < 6 {
+        return None;
+    }
+    let available: u64 = fields[3].parse().ok()?;
+    Some(Volume {
+        mount: PathBuf::from(fields[5..].join(" ")),
+        available: available.saturating_mul(1024),
+    })
+}
+
+#[cfg(windows)]
+fn volume_of(dir: &Path) -> Option<Volume> {
+    let script = format!(
+        "$d = New-Object System.IO.DriveInfo([System.IO.Path]::GetPathRoot('{}')); \
+         $d.Name; $d.AvailableFreeSpace",
+        dir.display().to_string().replace('\'', "''")
+    );
+    let output = run(Command::new("powershell").args(&["-NoProfile", "-Command", &script]))?;
+
+    let mut lines = output.lines();
+    let mount = PathBuf::from(lines.next()?.trim());
+    let available = lines.next()?.trim().parse().ok()?;
+    Some(Volume { mount, available })
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{parse_df, shortfall, unpacked_size, Size, Volume};
+    use std::path::PathBuf;
+
+    fn volume(mount: &str, available: u64) -> Volume {
+        Volume {
+            mount: PathBuf::from(mount),
+            available,
+        }
+    }
+
+    #[test]
+    fn test_parse_df() {
+        let output = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                      /dev/disk1s1     488245288 401455916  82532612      83% /Volumes/Macintosh HD\n";
+        assert_eq!(
+            parse_df(output),
+            Some(volume("/Volumes/Macintosh HD", 82532612 * 1024))
+        );
+        assert_eq!(
+            parse_df("Filesystem 1024-blocks Used Available Capacity Mounted on\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_shortfall() {
+        let home = volume("/home", 100);
+        let tmp = volume("/tmp", 1000);
+
+        assert_eq!(shortfall(&[(home.clone(), 60), (tmp.clone(), 600)]), None);
+        assert_eq!(
+            shortfall(&[(home.clone(), 60), (tmp.clone(), 600), (home.clone(), 60)]),
+            Some((home, 120))
+        );
+    }
+
+    #[test]
+    fn test_size_display() {
+        assert_eq!(Size(512).to_string(), "512 B");
+        assert_eq!(Size(1536).to_string(), "1.5 KiB");
+        assert_eq!(Size(25 * 1024 * 1024).to_string(), "25.0 MiB");
+        assert_eq!(unpacked_size(10, Some(40)), 40);
+        assert_eq!(unpacked_size(10, None), 30);
+    }
+}
@@ -0,0 +1,158 @@
+//! Provides the `DenoKind` type, which describes how Deno is provisioned, and the
+//! `DenoDistro` type, which represents a provisioned Deno distribution.
+
+use std::fs::{self, rename, File};
+use std::path::{Path, PathBuf};
+use std::string::ToString;
+
+use failure;
+use regex::Regex;
+use semver::Version;
+
+use archive::{Archive, Credentials, Proxy, Zip};
+use notion_fail::{Fallible, ResultExt};
+
+use super::kind::{ArchiveDistro, ToolKind};
+use crate::distro::DistroVersion;
+use crate::fs::{ensure_dir_exists, long_path};
+use crate::inventory::{
+    public_deno_latest_version, public_deno_version_index, resolve_deno_release,
+    resolve_deno_versions,
+};
+use crate::path;
+use crate::tool::ToolSpec;
+use crate::version::VersionSpec;
+
+#[cfg(feature = "mock-network")]
+use mockito;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "mock-network")] {
+        pub(crate) fn public_deno_server_root() -> String {
+            mockito::SERVER_URL.to_string()
+        }
+    } else {
+        pub(crate) fn public_deno_server_root() -> String {
+            "https://github.com/denoland/deno/releases/download".to_string()
+        }
+    }
+}
+
+/// The Deno runtime.
+pub struct DenoKind;
+
+/// A provisioned Deno distribution.
+pub type DenoDistro = ArchiveDistro<DenoKind>;
+
+impl ToolKind for DenoKind {
+    type VersionDetails = Version;
+
+    const DISPLAY_NAME: &'static str = "Deno";
+    const INDEX_FILE_NAME: &'static str = "releases";
+    const LATEST_FILE_NAME: &'static str = "latest";
+    const BINARIES: &'static [&'static str] = &["deno"];
+
+    fn tool_spec(version: &Version) -> ToolSpec {
+        ToolSpec::Deno(VersionSpec::exact(version))
+    }
+
+    fn distro_file_name(version: &Version) -> String {
+        path::deno_distro_file_name(&version.to_string())
+    }
+
+    fn distro_file_regex() -> Regex {
+        Regex::new(
+            r"(?x)
+            deno
+            -
+            v(?P<version>\d+\.\d+\.\d+) # Deno version
+            -
+            (?P<target>[a-z0-9_-]+)     # target triple
+            \.zip
+            ",
+        )
+        .unwrap()
+    }
+
+    /// The URL of the release zip on GitHub. The upstream file name doesn't include
+    /// the version, so it differs from the name in the inventory.
+    fn public_distro_url(version: &Version) -> String {
+        format!(
+            "{}/v{}/{}",
+            public_deno_server_root(),
+            version,
+            path::deno_archive_file_name()
+        )
+    }
+
+    fn public_index_url() -> String {
+        public_deno_version_index()
+    }
+
+    fn public_latest_url() -> String {
+        public_deno_latest_version()
+    }
+
+    fn inventory_dir() -> Fallible<PathBuf> {
+        path::deno_inventory_dir()
+    }
+
+    fn image_dir(version: &Version) -> Fallible<PathBuf> {
+        path::deno_image_dir(&version.to_string())
+    }
+
+    fn load_archive(file: File) -> Result<Box<dyn Archive>, failure::Error> {
+        Zip::load(file)
+    }
+
+    fn fetch_archive(
+        url: &str,
+        cache_file: &Path,
+        credentials: Option<&Credentials>,
+        proxy: Option<&Proxy>,
+    ) -> Result<Box<dyn Archive>, failure::Error> {
+        Zip::fetch(url, cache_file, credentials, proxy)
+    }
+
+    /// Moves the executable, which is the only entry in the release zip, into the
+    /// `bin` directory of the image.
+    fn install_image(version: &Version, unpacked: &Path) -> Fallible<DistroVersion> {
+        let bin_dir = path::deno_image_bin_dir(&version.to_string())?;
+
+        ensure_dir_exists(&bin_dir)?;
+
+        let binary = path::deno_binary_file_name();
+        let dest = bin_dir.join(&binary);
+        rename(unpacked.join(&binary), long_path(&dest)).unknown()?;
+        make_executable(&dest)?;
+
+        Ok(DistroVersion::Deno(version.clone()))
+    }
+
+    fn installed_version(version: Version) -> Fallible<DistroVersion> {
+        Ok(DistroVersion::Deno(version))
+    }
+
+    fn fetch_index(url: &str) -> Fallible<Vec<Version>> {
+        Ok(resolve_deno_versions(url)?.into_iter().rev().collect())
+    }
+
+    fn fetch_latest(url: &str) -> Fallible<Version> {
+        resolve_deno_release(url)
+    }
+}
+
+/// Zip archives don't record Unix permissions, so the executable bit is set by hand.
+#[cfg(unix)]
+fn make_executable(file: &Path) -> Fallible<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(file).unknown()?.permissions();
+    permissions.set_mode(0o755);
+    fs::set_permissions(file, permissions).unknown()
+}
+
+#[cfg(windows)]
+fn make_executable(_file: &Path) -> Fallible<()> {
+    Ok(())
+}
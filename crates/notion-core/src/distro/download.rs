@@ -0,0 +1,149 @@
+//! Provides the concurrent download of distribution archives, so that fetching several
+//! tools at once (such as the Node and Yarn of a project's platform) doesn't wait for
+//! each download in turn.
+//!
+//! Each archive is downloaded to its partial file in the inventory (resuming any
+//! earlier interrupted download), and only takes the place of the distribution archive
+//! once it is complete and its checksum verified, so that fetching the tool afterwards
+//! finds it in the inventory and only has to unpack it.
+
+use std::cell::Cell;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+
+use archive::{self, Credentials, Proxy};
+use failure;
+use semver::Version;
+
+use super::error::DownloadError;
+use super::kind::{partial_file, remove_file_if_exists};
+use crate::checksum::{self, Algorithm, Checksum};
+use crate::retry::RetryPolicy;
+use crate::style::{Action, ProgressGroup};
+use crate::tool::ToolSpec;
+use notion_fail::{throw, Fallible, ResultExt};
+
+/// A distribution archive to download.
+pub(crate) struct Download {
+    /// The name of the tool in messages, e.g. `Node`.
+    pub(crate) tool: &'static str,
+    pub(crate) version: Version,
+    /// Produces the tool specifier of `version`, for reporting errors.
+    pub(crate) tool_spec: fn(&Version) -> ToolSpec,
+    pub(crate) url: String,
+    /// The distribution archive in the inventory.
+    pub(crate) distro_file: PathBuf,
+    pub(crate) credentials: Option<Credentials>,
+    pub(crate) proxy: Option<Proxy>,
+    /// The checksum its server publishes for the archive, if any.
+    pub(crate) expected: Option<Checksum>,
+}
+
+impl Download {
+    /// Verifies the downloaded archive and moves it in place of the distribution archive.
+    fn complete(self) -> Fallible<()> {
+        let partial = partial_file(&self.distro_file);
+        let checksum = match self.expected {
+            Some(expected) => {
+                let actual = Checksum::of_file(expected.algorithm, &partial)?;
+                if actual != expected {
+                    fs::remove_file(&partial).unknown()?;
+                    throw!(DownloadError::ChecksumMismatch {
+                        toolspec: (self.tool_spec)(&self.version),
+                        expected: expected.to_string(),
+                        actual: actual.to_string(),
+                    });
+                }
+                expected
+            }
+            None => Checksum::of_file(Algorithm::DEFAULT, &partial)?,
+        };
+        checksum::record(&self.distro_file, &checksum)?;
+        fs::rename(&partial, &self.distro_file).unknown()?;
+        remove_file_if_exists(&archive::validator_file(&partial))
+    }
+}
+
+/// Downloads all of `downloads` at the same time, each with its own progress bar. Every
+/// download runs to completion (or failure) before the first failure is reported, and
+/// what was downloaded of a failed archive is kept to resume from.
+pub(crate) fn all(downloads: Vec<Download>) -> Fallible<()> {
+    if downloads.is_empty() {
+        return Ok(());
+    }
+
+    let policy = RetryPolicy::current()?;
+    let group = ProgressGroup::new(&format!(
+        "Downloading {} archive{}",
+        downloads.len(),
+        if downloads.len() == 1 { "" } else { "s" }
+    ));
+    let handles: Vec<_> = downloads
+        .iter()
+        .map(|download| {
+            let progress = group.add(
+                Action::Fetching,
+                &format!("{} v{}", download.tool, download.version),
+                0,
+            );
+            let url = download.url.clone();
+            let partial = partial_file(&download.distro_file);
+            let credentials = download.credentials.clone();
+            let proxy = download.proxy.clone();
+            let policy = policy.clone();
+            thread::spawn(move || {
+                let length = Cell::new(0);
+                let reported = Cell::new(0);
+                // a retry resumes from what the failed attempts downloaded
+                let result = policy.run(
+                    || {
+                        archive::download(
+                            &url,
+                            &partial,
+                            credentials.as_ref(),
+                            proxy.as_ref(),
+                            &mut |position, total| {
+                                if total != length.get() {
+                                    length.set(total);
+                                    progress.set_length(total);
+                                }
+                                progress.inc(position.saturating_sub(reported.get()));
+                                reported.set(position);
+                            },
+                        )
+                    },
+                    |retry, attempts| {
+                        progress.retrying(retry, attempts);
+                        length.set(0);
+                        reported.set(0);
+                    },
+                );
+                match result {
+                    Ok(_) => progress.finish(),
+                    Err(_) => progress.abandon(),
+                }
+                result
+            })
+        })
+        .collect();
+    group.join();
+
+    let results: Vec<Result<u64, failure::Error>> = handles
+        .into_iter()
+        .map(|handle| {
+            handle
+                .join()
+                .unwrap_or_else(|_| Err(failure::err_msg("the download thread panicked")))
+        })
+        .collect();
+
+    for (download, result) in downloads.into_iter().zip(results) {
+        if let Err(error) = result {
+            let toolspec = (download.tool_spec)(&download.version);
+            return Err(error).with_context(DownloadError::for_tool(toolspec, download.url));
+        }
+        download.complete()?;
+    }
+    Ok(())
+}
@@ -1,11 +1,13 @@
 //! Provides error types for the installer tools.
 
+use crate::path::{ARCH, OS};
+use crate::retry::RetryError;
 use crate::tool::ToolSpec;
-use archive::HttpError;
+use archive::{ConnectionError, HttpError};
 use notion_fail::{ExitCode, NotionFail};
 
 use failure::Fail;
-use reqwest::StatusCode;
+use reqwest::{StatusCode, Url};
 use std::fmt;
 
 #[derive(Debug, Fail)]
@@ -13,11 +15,25 @@ pub(crate) enum DownloadError {
     NotFound {
         toolspec: ToolSpec,
     },
+    Unreachable {
+        toolspec: ToolSpec,
+        host: String,
+        error: String,
+    },
     Other {
         toolspec: ToolSpec,
         from_url: String,
         error: String,
     },
+    ChecksumMismatch {
+        toolspec: ToolSpec,
+        expected: String,
+        actual: String,
+    },
+    InvalidSignature {
+        toolspec: ToolSpec,
+        error: String,
+    },
 }
 
 impl NotionFail for DownloadError {
@@ -28,7 +44,20 @@ impl NotionFail for DownloadError {
     fn exit_code(&self) -> ExitCode {
         match self {
             DownloadError::NotFound { .. } => ExitCode::NoVersionMatch,
+            DownloadError::Unreachable { .. } => ExitCode::NetworkError,
             DownloadError::Other { .. } => ExitCode::NetworkError,
+            DownloadError::ChecksumMismatch { .. } => ExitCode::NetworkError,
+            DownloadError::InvalidSignature { .. } => ExitCode::NetworkError,
+        }
+    }
+
+    fn error_code(&self) -> &'static str {
+        match self {
+            DownloadError::NotFound { .. } => "download-not-found",
+            DownloadError::Unreachable { .. } => "download-unreachable",
+            DownloadError::Other { .. } => "download-failed",
+            DownloadError::ChecksumMismatch { .. } => "checksum-mismatch",
+            DownloadError::InvalidSignature { .. } => "invalid-signature",
         }
     }
 }
@@ -36,7 +65,25 @@ impl NotionFail for DownloadError {
 impl fmt::Display for DownloadError {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            // Node is built separately for each platform, so a missing distribution
+            // doesn't necessarily mean that the version doesn't exist at all.
+            DownloadError::NotFound {
+                toolspec: toolspec @ ToolSpec::Node(_),
+            } => write!(
+                fmt,
+                "No {}-{} build exists for {}\n\nSee `notion help install` for help choosing another version, or building this one from source.",
+                OS, ARCH, toolspec
+            ),
             DownloadError::NotFound { toolspec } => write!(fmt, "{} not found", toolspec),
+            DownloadError::Unreachable {
+                toolspec,
+                host,
+                error,
+            } => write!(
+                fmt,
+                "Could not reach {} to download {}\n{}\n\nPlease check your network connection and try again.",
+                host, toolspec, error
+            ),
             DownloadError::Other {
                 toolspec,
                 from_url,
@@ -46,6 +93,20 @@ impl fmt::Display for DownloadError {
                 "Failed to download {} from {}\n{}",
                 toolspec, from_url, error
             ),
+            DownloadError::ChecksumMismatch {
+                toolspec,
+                expected,
+                actual,
+            } => write!(
+                fmt,
+                "The download of {} does not match its published checksum\n\nExpected {}\nbut found {}\n\nThe archive was corrupted or altered in transit, and has been removed. Please try again, and if this keeps happening, check any proxy or mirror between you and the server.",
+                toolspec, expected, actual
+            ),
+            DownloadError::InvalidSignature { toolspec, error } => write!(
+                fmt,
+                "Could not verify the signature of the checksums published for {}\n{}\n\nImport the Node release keys (see https://github.com/nodejs/node#release-keys) into your GPG keyring, or set `signatures = false` in the `[verify]` section of the Notion configuration.",
+                toolspec, error
+            ),
         }
     }
 }
@@ -56,18 +117,164 @@ impl DownloadError {
         from_url: String,
     ) -> impl FnOnce(&failure::Error) -> DownloadError {
         move |error| {
+            // a download that was retried is classified by its last failure, and reports
+            // the failure of every attempt
+            let (last, attempts) = match error.downcast_ref::<RetryError>() {
+                Some(retry) => (retry.last(), Some(retry.to_string())),
+                None => (error, None),
+            };
             if let Some(HttpError {
                 code: StatusCode::NotFound,
-            }) = error.downcast_ref::<HttpError>()
+            }) = last.downcast_ref::<HttpError>()
             {
                 DownloadError::NotFound { toolspec: toolspec }
+            } else if let Some(ConnectionError { error }) = last.downcast_ref::<ConnectionError>() {
+                DownloadError::Unreachable {
+                    toolspec: toolspec,
+                    host: host_of(&from_url),
+                    error: attempts.unwrap_or_else(|| error.clone()),
+                }
             } else {
                 DownloadError::Other {
                     toolspec: toolspec,
                     from_url: from_url,
-                    error: error.to_string(),
+                    error: attempts.unwrap_or_else(|| error.to_string()),
                 }
             }
         }
     }
 }
+
+/// Extracts the host name of a URL for error messages, falling back to the whole URL.
+fn host_of(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use archive::{ConnectionError, HttpError};
+    use failure;
+    use reqwest::StatusCode;
+    use semver::Version;
+
+    use super::DownloadError;
+    use crate::config::RetryConfig;
+    use crate::path::{ARCH, OS};
+    use crate::retry::RetryPolicy;
+    use crate::tool::ToolSpec;
+    use crate::version::VersionSpec;
+    use notion_fail::{ExitCode, NotionFail};
+
+    const URL: &str = "https://nodejs.org/dist/v10.15.0/node-v10.15.0-linux-x64.tar.gz";
+
+    fn node() -> ToolSpec {
+        ToolSpec::Node(VersionSpec::exact(&Version::new(10, 15, 0)))
+    }
+
+    fn classify(toolspec: ToolSpec, error: failure::Error) -> DownloadError {
+        DownloadError::for_tool(toolspec, URL.to_string())(&error)
+    }
+
+    fn unreachable() -> failure::Error {
+        ConnectionError {
+            error: "connection refused".to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_for_tool_not_found() {
+        let error = classify(
+            node(),
+            HttpError {
+                code: StatusCode::NotFound,
+            }
+            .into(),
+        );
+        match error {
+            DownloadError::NotFound { .. } => {}
+            ref other => panic!("expected NotFound, got {:?}", other),
+        }
+        assert_eq!(error.exit_code() as i32, ExitCode::NoVersionMatch as i32);
+        assert_eq!(error.error_code(), "download-not-found");
+        assert!(error.to_string().starts_with(&format!(
+            "No {}-{} build exists for node version 10.15.0\n",
+            OS, ARCH
+        )));
+
+        let yarn = ToolSpec::Yarn(VersionSpec::exact(&Version::new(1, 12, 3)));
+        let error = classify(
+            yarn,
+            HttpError {
+                code: StatusCode::NotFound,
+            }
+            .into(),
+        );
+        assert_eq!(error.to_string(), "yarn version 1.12.3 not found");
+    }
+
+    #[test]
+    fn test_for_tool_unreachable() {
+        let error = classify(node(), unreachable());
+        match error {
+            DownloadError::Unreachable { ref host, .. } => assert_eq!(host, "nodejs.org"),
+            ref other => panic!("expected Unreachable, got {:?}", other),
+        }
+        assert_eq!(error.exit_code() as i32, ExitCode::NetworkError as i32);
+        assert_eq!(error.error_code(), "download-unreachable");
+        assert!(error.to_string().starts_with(
+            "Could not reach nodejs.org to download node version 10.15.0\nconnection refused\n"
+        ));
+    }
+
+    #[test]
+    fn test_for_tool_unreachable_after_retries() {
+        let policy = RetryPolicy::from_config(&RetryConfig {
+            attempts: Some(2),
+            backoff_ms: Some(0),
+            statuses: None,
+        });
+        let retried = policy
+            .run(
+                || -> Result<(), failure::Error> { Err(unreachable()) },
+                |_, _| {},
+            )
+            .expect_err("an unreachable server should fail");
+
+        let error = classify(node(), retried);
+        match error {
+            DownloadError::Unreachable { ref error, .. } => assert_eq!(
+                error,
+                "Gave up after 2 attempts:\n  attempt 1: connection refused\n  attempt 2: connection refused"
+            ),
+            ref other => panic!("expected Unreachable, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_for_tool_other() {
+        let error = classify(
+            node(),
+            HttpError {
+                code: StatusCode::Forbidden,
+            }
+            .into(),
+        );
+        match error {
+            DownloadError::Other { ref from_url, .. } => assert_eq!(from_url, URL),
+            ref other => panic!("expected Other, got {:?}", other),
+        }
+        assert_eq!(error.error_code(), "download-failed");
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Failed to download node version 10.15.0 from {}\nHTTP failure (403 Forbidden)",
+                URL
+            )
+        );
+    }
+}
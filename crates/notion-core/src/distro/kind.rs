@@ -0,0 +1,470 @@
+//! Provides the `ToolKind` trait, which describes a tool that Notion can provision, and
+//! the `ArchiveDistro` type, a distribution of any such tool that is published as an
+//! archive.
+//!
+//! Implementing `ToolKind` for a tool gives it the shared fetching, unpacking, loading of
+//! its part of the inventory and resolution of versions against its index. The rest of
+//! the per-tool wiring is still spelled out for each tool: its `ToolSpec` and
+//! `DistroVersion` variants, its collection in the `Inventory` (and the `match`es there
+//! that dispatch on the tool), its field of `PlatformSpec` and `Image`, its recovery in
+//! the journal, and its shim in the `tool` module.
+
+use std::fs::{self, File};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use archive::{validator_file, Archive, Credentials, Proxy};
+use failure;
+use regex::Regex;
+use semver::Version;
+use tempfile::tempdir_in;
+
+use super::download::Download;
+use super::{warn_case_collisions, Distro, DistroVersion, Fetched};
+use crate::checksum::{self, Algorithm, Checksum};
+use crate::credentials;
+use crate::disk;
+use crate::distro::error::DownloadError;
+use crate::fs::{
+    dir_size, ensure_containing_dir_exists, ensure_dir_exists, ensure_home_writable, long_path,
+};
+use crate::hook::ToolHooks;
+use crate::interrupt::{self, Cleanup, Registration};
+use crate::inventory::Collection;
+use crate::journal::{Journal, Step};
+use crate::network;
+use crate::output;
+use crate::path;
+use crate::registry;
+use crate::retry::RetryPolicy;
+use crate::style::{display_warning, progress_bar, progress_spinner, Action, Progress};
+use crate::tool::ToolSpec;
+use notion_fail::{throw, Fallible, ResultExt};
+
+/// A tool that Notion can fetch into its inventory.
+pub trait ToolKind: Sized {
+    /// The details of a fetched version of the tool.
+    type VersionDetails;
+
+    /// The name of the tool in messages, e.g. `Node`.
+    const DISPLAY_NAME: &'static str;
+
+    /// The file name that `index` hooks are resolved against.
+    const INDEX_FILE_NAME: &'static str;
+
+    /// The file name that `latest` hooks are resolved against.
+    const LATEST_FILE_NAME: &'static str;
+
+    /// The executables provided by the tool.
+    const BINARIES: &'static [&'static str];
+
+    /// The tool specifier for an exact version of the tool.
+    fn tool_spec(version: &Version) -> ToolSpec;
+
+    /// The file name of the distribution archive for `version`.
+    fn distro_file_name(version: &Version) -> String;
+
+    /// A regex matching the names of the distribution archives in the inventory, with
+    /// a `version` named capture.
+    fn distro_file_regex() -> Regex;
+
+    /// The URL of the distribution archive for `version` on the public server.
+    fn public_distro_url(version: &Version) -> String;
+
+    /// The URL of the index of published versions on the public server.
+    fn public_index_url() -> String;
+
+    /// The URL that `fetch_latest` reads the latest version from on the public server.
+    fn public_latest_url() -> String;
+
+    /// The directory of the inventory holding the distribution archives.
+    fn inventory_dir() -> Fallible<PathBuf>;
+
+    /// The directory that the image of `version` is installed into.
+    fn image_dir(version: &Version) -> Fallible<PathBuf>;
+
+    /// Opens a distribution archive from the filesystem.
+    fn load_archive(file: File) -> Result<Box<dyn Archive>, failure::Error>;
+
+    /// Downloads a distribution archive, caching it in `cache_file`.
+    fn fetch_archive(
+        url: &str,
+        cache_file: &Path,
+        credentials: Option<&Credentials>,
+        proxy: Option<&Proxy>,
+    ) -> Result<Box<dyn Archive>, failure::Error>;
+
+    /// Moves a distribution unpacked into `unpacked` into its image directory.
+    fn install_image(version: &Version, unpacked: &Path) -> Fallible<DistroVersion>;
+
+    /// Describes a version that has already been fetched.
+    fn installed_version(version: Version) -> Fallible<DistroVersion>;
+
+    /// Reads the published versions from the index at `url`, newest first.
+    fn fetch_index(url: &str) -> Fallible<Vec<Version>>;
+
+    /// Reads the latest published version from `url`.
+    fn fetch_latest(url: &str) -> Fallible<Version>;
+
+    /// Reads the newest LTS version from the index at `url`, for tools with LTS releases.
+    fn fetch_lts(_url: &str) -> Fallible<Option<Version>> {
+        Ok(None)
+    }
+
+    /// Fetches the checksum the server publishes for the distribution archive of
+    /// `version` at `url`, if it publishes one outside of an npm registry.
+    fn published_checksum(_version: &Version, _url: &str) -> Fallible<Option<Checksum>> {
+        Ok(None)
+    }
+}
+
+/// A provisioned distribution of a tool that is published as an archive.
+pub struct ArchiveDistro<K: ToolKind> {
+    archive: Box<dyn Archive>,
+    version: Version,
+    /// The journal entry of the download, if the archive is being downloaded.
+    journal: Option<Journal>,
+    /// The checksum its server publishes for the archive being downloaded, if any.
+    expected: Option<Checksum>,
+    /// Removes the archive being downloaded if the process is interrupted.
+    _interrupt: Option<Registration>,
+    phantom: PhantomData<K>,
+}
+
+/// The file that the distribution archive `distro_file` is downloaded to, which takes
+/// its place once it is complete. An interrupted download leaves it behind, so that
+/// fetching the archive again only downloads the rest.
+pub(crate) fn partial_file(distro_file: &Path) -> PathBuf {
+    let mut name = distro_file.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    distro_file.with_file_name(name)
+}
+
+/// Determines the checksum that the server publishes for the archive of `version` at
+/// `url`, if any: the integrity of an npm registry tarball, or one the tool publishes.
+fn expected_checksum<K: ToolKind>(version: &Version, url: &str) -> Fallible<Option<Checksum>> {
+    match registry::tarball_integrity(url)? {
+        Some(integrity) => Ok(Some(integrity)),
+        None => K::published_checksum(version, url),
+    }
+}
+
+/// Check if the fetched file is valid. It may have been corrupted or interrupted in the middle of
+/// downloading, or modified since, in which case it no longer matches its recorded checksum.
+fn distro_is_valid<K: ToolKind>(file: &PathBuf) -> bool {
+    if file.is_file() {
+        match checksum::recorded(file) {
+            Ok(Some(checksum)) if checksum.verify_file(file).is_err() => return false,
+            Err(_) => return false,
+            _ => {}
+        }
+        if let Ok(file) = File::open(file) {
+            return K::load_archive(file).is_ok();
+        }
+    }
+    false
+}
+
+impl<K: ToolKind> ArchiveDistro<K> {
+    /// Provision a distribution from a remote distributor.
+    fn remote(version: Version, url: &str) -> Fallible<Self> {
+        let distro_file = K::inventory_dir()?.join(K::distro_file_name(&version));
+
+        let existing = path::overlay(distro_file.clone())?;
+        if distro_is_valid::<K>(&existing) {
+            return ArchiveDistro::local(version, File::open(existing).unknown()?);
+        }
+
+        ensure_home_writable(&format!("fetch {} v{}", K::DISPLAY_NAME, version))?;
+        ensure_containing_dir_exists(&distro_file)?;
+        let expected = expected_checksum::<K>(&version, url)?;
+        // what is downloaded is kept if the process is interrupted, to resume from later
+        let interrupt = interrupt::register(vec![
+            Cleanup::RemoveFile(distro_file.clone()),
+            Cleanup::RemoveFile(checksum::record_file(&distro_file)),
+        ]);
+        let journal = Journal::begin(K::DISPLAY_NAME, &version, Step::Downloading)?;
+        let credentials = credentials::for_url(url)?;
+        let proxy = network::proxy_for(url)?;
+        output::verbose(format!(
+            "Downloading {} v{} from {}",
+            K::DISPLAY_NAME,
+            version,
+            url
+        ));
+        let partial = partial_file(&distro_file);
+        // the progress bar only starts once the archive is reached, so any retries of
+        // the connection are shown on a spinner until then
+        let mut retrying: Option<Progress> = None;
+        let archive = RetryPolicy::current()?
+            .run(
+                || K::fetch_archive(url, &partial, credentials.as_ref(), proxy.as_ref()),
+                |retry, attempts| {
+                    retrying
+                        .get_or_insert_with(|| progress_spinner(&format!("Fetching v{}", version)))
+                        .retrying(retry, attempts)
+                },
+            )
+            .with_context(DownloadError::for_tool(
+                K::tool_spec(&version),
+                url.to_string(),
+            ))?;
+        if let Some(spinner) = retrying {
+            spinner.finish();
+        }
+
+        Ok(ArchiveDistro {
+            archive,
+            version: version,
+            journal: Some(journal),
+            expected,
+            _interrupt: Some(interrupt),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Describes the download of the archive of `version` from `url`, for downloading it
+    /// along with other archives, unless a valid archive is already in the inventory.
+    pub(crate) fn download(version: Version, url: &str) -> Fallible<Option<Download>> {
+        let distro_file = K::inventory_dir()?.join(K::distro_file_name(&version));
+        if distro_is_valid::<K>(&path::overlay(distro_file.clone())?) {
+            return Ok(None);
+        }
+
+        ensure_home_writable(&format!("fetch {} v{}", K::DISPLAY_NAME, version))?;
+        ensure_containing_dir_exists(&distro_file)?;
+        Ok(Some(Download {
+            tool: K::DISPLAY_NAME,
+            expected: expected_checksum::<K>(&version, url)?,
+            credentials: credentials::for_url(url)?,
+            proxy: network::proxy_for(url)?,
+            tool_spec: K::tool_spec,
+            url: url.to_string(),
+            distro_file,
+            version,
+        }))
+    }
+
+    /// Resolves the URL of the archive of `version`, from the `distro` hook if there is
+    /// one or the public server otherwise.
+    pub(crate) fn url(version: &Version, hooks: Option<&ToolHooks<Self>>) -> Fallible<String> {
+        match hooks {
+            Some(&ToolHooks {
+                distro: Some(ref hook),
+                ..
+            }) => hook.resolve(version, &K::distro_file_name(version)),
+            _ => Ok(K::public_distro_url(version)),
+        }
+    }
+
+    /// Provision a distribution from the filesystem.
+    fn local(version: Version, file: File) -> Fallible<Self> {
+        Ok(ArchiveDistro {
+            archive: K::load_archive(file).unknown()?,
+            version: version,
+            journal: None,
+            expected: None,
+            _interrupt: None,
+            phantom: PhantomData,
+        })
+    }
+
+    pub(crate) fn distro_file(version: &Version) -> Fallible<PathBuf> {
+        Ok(K::inventory_dir()?.join(K::distro_file_name(version)))
+    }
+
+    /// Unpacks the archive and installs its image, recording each step in `journal`.
+    fn install(self, mut journal: Journal) -> Fallible<DistroVersion> {
+        let staging = path::staging_dir()?;
+        ensure_dir_exists(&staging)?;
+        let temp = tempdir_in(long_path(staging)).unknown()?;
+        let _interrupt = interrupt::register(vec![
+            Cleanup::RemoveDir(K::image_dir(&self.version)?),
+            Cleanup::RemoveDir(temp.path().to_path_buf()),
+        ]);
+        self.preflight(temp.path())?;
+
+        // archives that are already in the inventory are only unpacked
+        let action = if journal.step() < Step::Verified {
+            Action::Fetching
+        } else {
+            Action::Unpacking
+        };
+        let bar = progress_bar(
+            action,
+            &format!("v{}", self.version),
+            self.archive
+                .uncompressed_size()
+                .unwrap_or(self.archive.compressed_size()),
+        );
+
+        let skipped = self
+            .archive
+            .unpack(temp.path(), &mut |_, read| {
+                bar.inc(read as u64);
+            })
+            .unknown()?;
+
+        // archives that stream as they unpack are only complete once they're unpacked
+        if journal.step() < Step::Verified {
+            journal.record(Step::Downloaded)?;
+            let distro_file = ArchiveDistro::<K>::distro_file(&self.version)?;
+            let partial = partial_file(&distro_file);
+            fs::rename(&partial, &distro_file).unknown()?;
+            remove_file_if_exists(&validator_file(&partial))?;
+            let checksum = match self.expected {
+                Some(ref expected) => {
+                    let actual = Checksum::of_file(expected.algorithm, &distro_file)?;
+                    if actual != *expected {
+                        remove_file_if_exists(&distro_file)?;
+                        journal.finish()?;
+                        throw!(DownloadError::ChecksumMismatch {
+                            toolspec: K::tool_spec(&self.version),
+                            expected: expected.to_string(),
+                            actual: actual.to_string(),
+                        });
+                    }
+                    expected.clone()
+                }
+                None => Checksum::of_file(Algorithm::DEFAULT, &distro_file)?,
+            };
+            checksum::record(&distro_file, &checksum)?;
+            journal.record(Step::Verified)?;
+        }
+
+        journal.record(Step::Unpacking)?;
+        let installed = K::install_image(&self.version, temp.path())?;
+        journal.finish()?;
+
+        bar.finish();
+        warn_case_collisions(&format!("{} v{}", K::DISPLAY_NAME, self.version), &skipped);
+        Ok(installed)
+    }
+
+    /// Recovers a fetch of `version` that was interrupted, as recorded by `journal`. If
+    /// the archive was completely downloaded, its image is installed again; otherwise the
+    /// image is removed, keeping the partial download for the next fetch to resume.
+    pub(crate) fn recover(version: Version, journal: Journal) -> Fallible<()> {
+        let distro_file = ArchiveDistro::<K>::distro_file(&version)?;
+        remove_dir_if_exists(&K::image_dir(&version)?)?;
+
+        if journal.step() >= Step::Verified && distro_is_valid::<K>(&distro_file) {
+            display_warning(format!(
+                "resuming the interrupted fetch of {} v{}",
+                K::DISPLAY_NAME,
+                version
+            ));
+            let distro = ArchiveDistro::<K>::local(version, File::open(&distro_file).unknown()?)?;
+            return distro.install(journal).map(|_| ());
+        }
+
+        display_warning(format!(
+            "rolling back the interrupted fetch of {} v{}",
+            K::DISPLAY_NAME,
+            version
+        ));
+        remove_file_if_exists(&distro_file)?;
+        checksum::forget(&distro_file)?;
+        journal.finish()
+    }
+
+    /// Removes `version` from the inventory: its archive, the recorded checksum and its
+    /// image. Returns the number of bytes freed.
+    pub(crate) fn remove(version: &Version) -> Fallible<u64> {
+        let distro_file = ArchiveDistro::<K>::distro_file(version)?;
+        let partial = partial_file(&distro_file);
+        let image_dir = K::image_dir(version)?;
+        let size = dir_size(&distro_file) + dir_size(&partial) + dir_size(&image_dir);
+
+        remove_file_if_exists(&distro_file)?;
+        remove_file_if_exists(&partial)?;
+        remove_file_if_exists(&validator_file(&partial))?;
+        checksum::forget(&distro_file)?;
+        remove_dir_if_exists(&image_dir)?;
+        Ok(size)
+    }
+
+    /// Checks that there is room for the rest of the archive in the inventory and for the
+    /// image unpacked into `temp`, before anything is unpacked.
+    fn preflight(&self, temp: &Path) -> Fallible<()> {
+        let inventory_dir = K::inventory_dir()?;
+        let distro_file = ArchiveDistro::<K>::distro_file(&self.version)?;
+
+        // archives that stream as they unpack have only been partly written so far
+        let compressed = self.archive.compressed_size();
+        let written = fs::metadata(partial_file(&distro_file))
+            .map(|m| m.len())
+            .unwrap_or(0);
+        let unpacked = disk::unpacked_size(compressed, self.archive.uncompressed_size());
+
+        disk::preflight(
+            &format!("{} v{}", K::DISPLAY_NAME, self.version),
+            &[
+                (inventory_dir.as_path(), compressed.saturating_sub(written)),
+                (temp, unpacked),
+            ],
+        )
+    }
+}
+
+impl<K: ToolKind> Distro for ArchiveDistro<K> {
+    type VersionDetails = K::VersionDetails;
+
+    /// Provisions a new Distro based on the Version and possible Hooks
+    fn new(version: Version, hooks: Option<&ToolHooks<Self>>) -> Fallible<Self> {
+        let url = ArchiveDistro::<K>::url(&version, hooks)?;
+        ArchiveDistro::remote(version, &url)
+    }
+
+    /// Produces a reference to this distribution's version.
+    fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Fetches this version of the tool. (It is left to the responsibility of the
+    /// `Collection` to update its state after fetching succeeds.)
+    fn fetch(mut self, collection: &Collection<Self>) -> Fallible<Fetched<DistroVersion>> {
+        if collection.contains(&self.version) {
+            if let Some(journal) = self.journal.take() {
+                journal.finish()?;
+            }
+            return Ok(Fetched::Already(K::installed_version(self.version)?));
+        }
+
+        ensure_home_writable(&format!("fetch {} v{}", K::DISPLAY_NAME, self.version))?;
+        let journal = match self.journal.take() {
+            Some(journal) => journal,
+            None => Journal::begin(K::DISPLAY_NAME, &self.version, Step::Verified)?,
+        };
+        Ok(Fetched::Now(self.install(journal)?))
+    }
+}
+
+fn remove_dir_if_exists(dir: &Path) -> Fallible<()> {
+    if dir.exists() {
+        fs::remove_dir_all(long_path(dir)).unknown()?;
+    }
+    Ok(())
+}
+
+pub(crate) fn remove_file_if_exists(file: &Path) -> Fallible<()> {
+    if file.exists() {
+        fs::remove_file(file).unknown()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::partial_file;
+    use std::path::Path;
+
+    #[test]
+    fn test_partial_file() {
+        assert_eq!(
+            partial_file(Path::new("/inventory/node-v10.13.0-linux-x64.tar.gz")),
+            Path::new("/inventory/node-v10.13.0-linux-x64.tar.gz.partial")
+        );
+    }
+}
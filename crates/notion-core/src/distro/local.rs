@@ -0,0 +1,399 @@
+//! Provides installing Node from a build on the local filesystem, for environments that
+//! can't reach a Node server and build or host Node themselves.
+//!
+//! The build is a distribution archive (a `.tar.gz`, or a `.zip`) or the directory that
+//! one unpacks into, which must have the layout of a Node distribution. Its version is
+//! the one given, or else the one in its `include/node/node_version.h` header, or else
+//! the one in its file name. The build is copied into the staging directory, leaving the
+//! original in place, and moved into the image directory of the version like an
+//! unpacked distribution. As there is no distribution archive in the inventory, it is
+//! recorded by a `node-v<version>-local` file next to the `node-v<version>-npm` file,
+//! which the inventory picks up like an archive.
+
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use archive::{Archive, Tarball, Zip};
+use failure::Fail;
+use regex::Regex;
+use semver::Version;
+use tempfile::{tempdir_in, TempDir};
+
+use super::node::{save_default_npm_version, Manifest};
+use super::DistroVersion;
+use crate::fs::{ensure_containing_dir_exists, ensure_dir_exists, ensure_home_writable, long_path};
+use crate::interrupt::{self, Cleanup, Registration};
+use crate::inventory::serial::versions_matching;
+use crate::path;
+use crate::style::{progress_bar, progress_spinner, Action};
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+/// Thrown when there is no file or directory at the path of the local build.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Could not find a Node build at {}", path)]
+#[notion_fail(code = "FileSystemError")]
+pub(crate) struct LocalBuildNotFoundError {
+    path: String,
+}
+
+/// Thrown when the local build doesn't have the layout of a Node distribution.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "{} is not a Node build: it has no {}\n\nA build must have the layout of a Node distribution, as published on https://nodejs.org.",
+    path, missing
+)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct LocalBuildLayoutError {
+    path: String,
+    missing: String,
+}
+
+/// Thrown when the archive of the local build can't be read or unpacked.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "{} is not a valid archive of a Node build: {}\n\nA build must be a `.tar.gz` or `.zip` archive of a Node distribution, or the directory one unpacks into.",
+    path, error
+)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct LocalBuildArchiveError {
+    path: String,
+    error: String,
+}
+
+impl LocalBuildArchiveError {
+    fn for_file<E: Display>(file: &Path) -> impl FnOnce(&E) -> LocalBuildArchiveError {
+        let path = file.display().to_string();
+        move |error| LocalBuildArchiveError {
+            path,
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Thrown when the version of the local build can't be determined.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Could not determine the Node version of {}\n\nGive it with `--version`, e.g. `notion install node --file={} --version=10.15.0`.",
+    path, path
+)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct LocalBuildVersionError {
+    path: String,
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(windows)] {
+        /// The Node executable, relative to the root of a distribution.
+        const NODE_EXECUTABLE: &[&str] = &["node.exe"];
+        /// The manifest of the bundled npm, relative to the root of a distribution.
+        const NPM_MANIFEST: &[&str] = &["node_modules", "npm", "package.json"];
+    } else {
+        /// The Node executable, relative to the root of a distribution.
+        const NODE_EXECUTABLE: &[&str] = &["bin", "node"];
+        /// The manifest of the bundled npm, relative to the root of a distribution.
+        const NPM_MANIFEST: &[&str] = &["lib", "node_modules", "npm", "package.json"];
+    }
+}
+
+/// The header declaring the version of Node, relative to the root of a distribution.
+const VERSION_HEADER: &[&str] = &["include", "node", "node_version.h"];
+
+fn local_build_regex() -> Regex {
+    Regex::new(r"^node-v(?P<version>\d+\.\d+\.\d+)-local$").unwrap()
+}
+
+/// Reads the versions of Node that were installed from a local build.
+pub(crate) fn provided_versions() -> Fallible<BTreeSet<Version>> {
+    versions_matching(&path::node_inventory_dir()?, &local_build_regex())
+}
+
+/// A local Node build, copied into the staging directory to be installed.
+pub(crate) struct LocalBuild {
+    version: Version,
+    /// The archive or directory the build was copied from.
+    source: PathBuf,
+    /// The root of the copy, in the temporary directory.
+    root: PathBuf,
+    _temp: TempDir,
+    /// Removes the copy if the process is interrupted.
+    _interrupt: Registration,
+}
+
+impl LocalBuild {
+    /// Copies the build at `path`, a distribution archive or the directory of one, into
+    /// the staging directory, checks its layout and determines its version, unless it
+    /// is given as `version`.
+    pub(crate) fn stage(path: &Path, version: Option<&Version>) -> Fallible<Self> {
+        let display = path.display().to_string();
+        ensure_home_writable(&format!("install Node from {}", display))?;
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(_) => throw!(LocalBuildNotFoundError { path: display }),
+        };
+
+        let staging = path::staging_dir()?;
+        ensure_dir_exists(&staging)?;
+        let temp = tempdir_in(long_path(staging)).unknown()?;
+        let interrupt = interrupt::register(vec![Cleanup::RemoveDir(temp.path().to_path_buf())]);
+
+        if metadata.is_dir() {
+            let spinner = progress_spinner(&format!("Copying {}", display));
+            let name = path.file_name().unwrap_or_else(|| OsStr::new("node"));
+            copy_dir(path, &temp.path().join(name))?;
+            spinner.finish();
+        } else {
+            unpack(path, temp.path())?;
+        }
+
+        let root = find_root(temp.path())?;
+        for relative in &[NODE_EXECUTABLE, NPM_MANIFEST] {
+            let relative = join(Path::new(""), relative);
+            if !root.join(&relative).is_file() {
+                throw!(LocalBuildLayoutError {
+                    path: display,
+                    missing: relative.display().to_string(),
+                });
+            }
+        }
+
+        let version = match version {
+            Some(version) => version.clone(),
+            None => match header_version(&root)?.or_else(|| name_version(path)) {
+                Some(version) => version,
+                None => throw!(LocalBuildVersionError { path: display }),
+            },
+        };
+
+        Ok(LocalBuild {
+            version,
+            source: path.to_path_buf(),
+            root,
+            _temp: temp,
+            _interrupt: interrupt,
+        })
+    }
+
+    /// The version of the build.
+    pub(crate) fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// The archive or directory the build was copied from.
+    pub(crate) fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Moves the build into its image directory and records it in the inventory.
+    pub(crate) fn install(self) -> Fallible<DistroVersion> {
+        let version = &self.version;
+        let _interrupt = interrupt::register(vec![
+            Cleanup::RemoveFile(path::node_npm_version_file(&version.to_string())?),
+            Cleanup::RemoveDir(path::node_image_root_dir()?.join(version.to_string())),
+        ]);
+
+        let npm = Manifest::version(&join(&self.root, NPM_MANIFEST))?;
+        save_default_npm_version(version, &npm)?;
+
+        let dest = path::node_image_dir(&version.to_string(), &npm.to_string())?;
+        // left behind by an install interrupted before it was recorded
+        if dest.exists() {
+            fs::remove_dir_all(long_path(&dest)).unknown()?;
+        }
+        ensure_containing_dir_exists(&dest)?;
+        fs::rename(&self.root, long_path(&dest)).unknown()?;
+
+        File::create(path::node_local_build_file(&version.to_string())?).unknown()?;
+        Ok(DistroVersion::Node(version.clone(), npm))
+    }
+}
+
+fn join(root: &Path, relative: &[&str]) -> PathBuf {
+    relative
+        .iter()
+        .fold(root.to_path_buf(), |path, component| path.join(component))
+}
+
+/// Unpacks the archive at `file` into `dest`, as a zip file if it is named like one and as
+/// a tarball otherwise.
+fn unpack(file: &Path, dest: &Path) -> Fallible<()> {
+    let source = File::open(file).unknown()?;
+    let is_zip = file.extension().map_or(false, |extension| {
+        extension.to_string_lossy().eq_ignore_ascii_case("zip")
+    });
+    let archive: Box<dyn Archive> = if is_zip {
+        Zip::load(source)
+    } else {
+        Tarball::load(source)
+    }
+    .with_context(LocalBuildArchiveError::for_file(file))?;
+
+    let name = file
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let bar = progress_bar(
+        Action::Unpacking,
+        &name,
+        archive
+            .uncompressed_size()
+            .unwrap_or(archive.compressed_size()),
+    );
+    archive
+        .unpack(dest, &mut |_, read| {
+            bar.inc(read as u64);
+        })
+        .with_context(LocalBuildArchiveError::for_file(file))?;
+    bar.finish();
+    Ok(())
+}
+
+/// The root of the distribution staged in `dir`: `dir` itself if the Node executable is in
+/// it, or else the only directory in it, as a distribution archive unpacks into.
+fn find_root(dir: &Path) -> Fallible<PathBuf> {
+    if join(dir, NODE_EXECUTABLE).is_file() {
+        return Ok(dir.to_path_buf());
+    }
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).unknown()? {
+        entries.push(entry.unknown()?.path());
+    }
+    match entries.as_slice() {
+        [only] if only.is_dir() => Ok(only.clone()),
+        _ => Ok(dir.to_path_buf()),
+    }
+}
+
+/// Copies the directory `from` to `to`, keeping the symbolic links (such as `bin/npm`)
+/// as links.
+fn copy_dir(from: &Path, to: &Path) -> Fallible<()> {
+    ensure_dir_exists(to)?;
+    for entry in fs::read_dir(from).unknown()? {
+        let entry = entry.unknown()?;
+        let file_type = entry.file_type().unknown()?;
+        let dest = to.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else if file_type.is_symlink() {
+            copy_link(&entry.path(), &dest)?;
+        } else {
+            fs::copy(entry.path(), long_path(&dest)).unknown()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_link(from: &Path, to: &Path) -> Fallible<()> {
+    std::os::unix::fs::symlink(fs::read_link(from).unknown()?, to).unknown()
+}
+
+#[cfg(windows)]
+fn copy_link(from: &Path, to: &Path) -> Fallible<()> {
+    fs::copy(from, long_path(to)).unknown()?;
+    Ok(())
+}
+
+/// Reads the version declared by the `node_version.h` header of the distribution at
+/// `root`, if it has one.
+fn header_version(root: &Path) -> Fallible<Option<Version>> {
+    let header = join(root, VERSION_HEADER);
+    if !header.is_file() {
+        return Ok(None);
+    }
+    Ok(parse_header(&fs::read_to_string(header).unknown()?))
+}
+
+fn parse_header(header: &str) -> Option<Version> {
+    let define =
+        Regex::new(r"(?m)^#define NODE_(?P<part>MAJOR|MINOR|PATCH)_VERSION\s+(?P<number>\d+)\s*$")
+            .unwrap();
+    let (mut major, mut minor, mut patch) = (None, None, None);
+    for captures in define.captures_iter(header) {
+        let number = captures["number"].parse::<u64>().ok();
+        match &captures["part"] {
+            "MAJOR" => major = number,
+            "MINOR" => minor = number,
+            _ => patch = number,
+        }
+    }
+    Some(Version::new(major?, minor?, patch?))
+}
+
+/// Reads the version in the file name of `path`, e.g. `10.15.0` from
+/// `node-v10.15.0-linux-x64.tar.gz`.
+fn name_version(path: &Path) -> Option<Version> {
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    let version = Regex::new(r"(?:^|[^0-9.])v?(?P<version>\d+\.\d+\.\d+)").unwrap();
+    let captures = version.captures(&name)?;
+    Version::parse(&captures["version"]).ok()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{local_build_regex, name_version, parse_header, unpack};
+    use semver::Version;
+    use std::fs;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_local_build_regex() {
+        let re = local_build_regex();
+        assert_eq!(
+            &re.captures("node-v10.15.0-local").unwrap()["version"],
+            "10.15.0"
+        );
+        assert!(re.captures("node-v10.15.0-source").is_none());
+        assert!(re.captures("node-v10.15.0-npm").is_none());
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let header = "#ifndef SRC_NODE_VERSION_H_\n\
+                      #define SRC_NODE_VERSION_H_\n\
+                      \n\
+                      #define NODE_MAJOR_VERSION 10\n\
+                      #define NODE_MINOR_VERSION 15\n\
+                      #define NODE_PATCH_VERSION 0\n\
+                      \n\
+                      #define NODE_VERSION_IS_LTS 1\n";
+        assert_eq!(parse_header(header), Some(Version::new(10, 15, 0)));
+        assert_eq!(parse_header("#define NODE_MAJOR_VERSION 10\n"), None);
+    }
+
+    #[test]
+    fn test_name_version() {
+        assert_eq!(
+            name_version(Path::new("/builds/node-v10.15.0-linux-x64.tar.gz")),
+            Some(Version::new(10, 15, 0))
+        );
+        assert_eq!(
+            name_version(Path::new("node-10.15.1-custom.zip")),
+            Some(Version::new(10, 15, 1))
+        );
+        assert_eq!(name_version(Path::new("/builds/node-custom.tar.gz")), None);
+    }
+
+    #[test]
+    fn test_unpack_invalid_archive() {
+        let dir = tempdir().expect("could not create temporary directory");
+        for name in &["node-v10.15.0.tar.gz", "node-v10.15.0.zip"] {
+            let file = dir.path().join(name);
+            fs::write(&file, "not an archive of a Node build").unwrap();
+
+            let error = unpack(&file, &dir.path().join("unpacked"))
+                .expect_err("unpacked an invalid archive");
+            assert!(error.is_user_friendly());
+            assert!(error.to_string().starts_with(&format!(
+                "{} is not a valid archive of a Node build: ",
+                file.display()
+            )));
+        }
+    }
+}
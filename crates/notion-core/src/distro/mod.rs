@@ -1,14 +1,22 @@
 //! Provides types for fetching tool distributions into the local inventory.
 
-mod error;
+pub mod deno;
+pub(crate) mod download;
+pub(crate) mod error;
+pub mod kind;
+pub(crate) mod local;
 pub mod node;
+pub mod npm;
+pub(crate) mod source;
 pub mod yarn;
 
 use crate::hook::ToolHooks;
 use crate::inventory::Collection;
+use crate::style::display_warning;
 use notion_fail::Fallible;
 use semver::Version;
 use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
 
 /// The result of a requested installation.
 pub enum Fetched<V> {
@@ -41,6 +49,7 @@ pub enum DistroVersion {
     Node(Version, Version),
     Yarn(Version),
     Npm(Version),
+    Deno(Version),
     Package(String, Version),
 }
 
@@ -52,6 +61,7 @@ impl Display for DistroVersion {
             }
             &DistroVersion::Yarn(ref version) => format!("yarn version {}", version),
             &DistroVersion::Npm(ref version) => format!("npm version {}", version),
+            &DistroVersion::Deno(ref version) => format!("deno version {}", version),
             &DistroVersion::Package(ref name, ref version) => {
                 format!("{} version {}", name, version)
             }
@@ -73,3 +83,15 @@ pub trait Distro: Sized {
     /// to update its state after fetching succeeds.)
     fn fetch(self, collection: &Collection<Self>) -> Fallible<Fetched<DistroVersion>>;
 }
+
+/// Warns about the archive entries that were skipped while unpacking `distro`, because
+/// they differ only by case from an earlier entry and the filesystem is case-insensitive.
+fn warn_case_collisions(distro: &str, skipped: &[PathBuf]) {
+    for path in skipped {
+        display_warning(format!(
+            "{} contains files that differ only by case; skipped {}",
+            distro,
+            path.display()
+        ));
+    }
+}
@@ -1,26 +1,35 @@
-//! Provides the `NodeDistro` type, which represents a provisioned Node distribution.
+//! Provides the `NodeKind` type, which describes how Node is provisioned, and the
+//! `NodeDistro` type, which represents a provisioned Node distribution.
 
-use std::fs::{read_to_string, rename, File};
-use std::io::Write;
+use std::fs::{self, read_to_string, rename, File};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::string::ToString;
 
-use archive::{self, Archive};
+use archive::{self, Archive, Credentials, Proxy};
+use failure;
+use regex::Regex;
 use serde::Deserialize;
-use tempfile::tempdir_in;
+use tempfile::tempdir;
 
-use super::{Distro, Fetched};
+use super::kind::{ArchiveDistro, ToolKind};
+use crate::checksum::{Algorithm, Checksum};
+use crate::config::Config;
+use crate::credentials;
 use crate::distro::error::DownloadError;
 use crate::distro::DistroVersion;
-use crate::fs::ensure_containing_dir_exists;
-use crate::hook::ToolHooks;
-use crate::inventory::NodeCollection;
+use crate::fs::{ensure_containing_dir_exists, long_path};
+use crate::inventory::{
+    node_index_lts_versions, node_index_versions, public_node_version_index, NoVersionFoundError,
+    RegistryFetchError,
+};
 use crate::path;
-use crate::style::{progress_bar, Action};
+use crate::retry::RetryPolicy;
 use crate::tool::ToolSpec;
 use crate::version::VersionSpec;
 
-use notion_fail::{Fallible, ResultExt};
+use notion_fail::{throw, Fallible, ResultExt};
 use semver::Version;
 
 #[cfg(feature = "mock-network")]
@@ -29,22 +38,16 @@ use serde_json;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "mock-network")] {
-        fn public_node_server_root() -> String {
+        pub(crate) fn public_node_server_root() -> String {
             mockito::SERVER_URL.to_string()
         }
     } else {
-        fn public_node_server_root() -> String {
+        pub(crate) fn public_node_server_root() -> String {
             "https://nodejs.org/dist".to_string()
         }
     }
 }
 
-/// A provisioned Node distribution.
-pub struct NodeDistro {
-    archive: Box<dyn Archive>,
-    version: Version,
-}
-
 /// A full Node version including not just the version of Node itself
 /// but also the specific version of npm installed globally with that
 /// Node installation.
@@ -58,7 +61,7 @@ pub struct NodeVersion {
 
 /// Load the local npm version file to determine the default npm version for a given version of Node
 pub fn load_default_npm_version(node: &Version) -> Fallible<Version> {
-    let npm_version_file_path = path::node_npm_version_file(&node.to_string())?;
+    let npm_version_file_path = path::overlay(path::node_npm_version_file(&node.to_string())?)?;
     Ok(read_to_string(npm_version_file_path)
         .unknown()?
         .parse()
@@ -66,7 +69,7 @@ pub fn load_default_npm_version(node: &Version) -> Fallible<Version> {
 }
 
 /// Save the default npm version to the filesystem for a given version of Node
-fn save_default_npm_version(node: &Version, npm: &Version) -> Fallible<()> {
+pub(crate) fn save_default_npm_version(node: &Version, npm: &Version) -> Fallible<()> {
     let npm_version_file_path = path::node_npm_version_file(&node.to_string())?;
     let mut npm_version_file = File::create(npm_version_file_path).unknown()?;
     npm_version_file
@@ -75,21 +78,6 @@ fn save_default_npm_version(node: &Version, npm: &Version) -> Fallible<()> {
     Ok(())
 }
 
-/// Check if the fetched file is valid. It may have been corrupted or interrupted in the middle of
-/// downloading.
-// ISSUE(#134) - verify checksum
-fn distro_is_valid(file: &PathBuf) -> bool {
-    if file.is_file() {
-        if let Ok(file) = File::open(file) {
-            match archive::load_native(file) {
-                Ok(_) => return true,
-                Err(_) => return false,
-            }
-        }
-    }
-    false
-}
-
 #[derive(Deserialize)]
 pub struct Manifest {
     version: String,
@@ -101,124 +89,212 @@ impl Manifest {
         serde_json::de::from_reader(file).unknown()
     }
 
-    fn version(path: &Path) -> Fallible<Version> {
+    pub(crate) fn version(path: &Path) -> Fallible<Version> {
         Manifest::read(path)?.version.parse().unknown()
     }
 }
 
-impl NodeDistro {
-    /// Provision a Node distribution from the public Node distributor (`https://nodejs.org`).
-    fn public(version: Version) -> Fallible<Self> {
-        let distro_file_name = path::node_distro_file_name(&version.to_string());
-        let url = format!(
-            "{}/v{}/{}",
-            public_node_server_root(),
-            version,
-            &distro_file_name
-        );
-        NodeDistro::remote(version, &url)
-    }
+/// The Node runtime.
+pub struct NodeKind;
 
-    /// Provision a Node distribution from a remote distributor.
-    fn remote(version: Version, url: &str) -> Fallible<Self> {
-        let distro_file_name = path::node_distro_file_name(&version.to_string());
-        let distro_file = path::node_inventory_dir()?.join(&distro_file_name);
+/// A provisioned Node distribution.
+pub type NodeDistro = ArchiveDistro<NodeKind>;
 
-        if distro_is_valid(&distro_file) {
-            return NodeDistro::local(version, File::open(distro_file).unknown()?);
-        }
+impl ToolKind for NodeKind {
+    type VersionDetails = NodeVersion;
+
+    const DISPLAY_NAME: &'static str = "Node";
+    const INDEX_FILE_NAME: &'static str = "index.json";
+    const LATEST_FILE_NAME: &'static str = "index.json";
+    const BINARIES: &'static [&'static str] = &["node", "npm", "npx"];
 
-        ensure_containing_dir_exists(&distro_file)?;
-        Ok(NodeDistro {
-            archive: archive::fetch_native(url, &distro_file).with_context(
-                DownloadError::for_tool(
-                    ToolSpec::Node(VersionSpec::exact(&version)),
-                    url.to_string(),
-                ),
-            )?,
-            version: version,
-        })
-    }
-
-    /// Provision a Node distribution from the filesystem.
-    fn local(version: Version, file: File) -> Fallible<Self> {
-        Ok(NodeDistro {
-            archive: archive::load_native(file).unknown()?,
-            version: version,
-        })
+    fn tool_spec(version: &Version) -> ToolSpec {
+        ToolSpec::Node(VersionSpec::exact(version))
     }
-}
 
-impl Distro for NodeDistro {
-    type VersionDetails = NodeVersion;
+    fn distro_file_name(version: &Version) -> String {
+        path::node_distro_file_name(&version.to_string())
+    }
 
-    /// Provisions a new Distro based on the Version and possible Hooks
-    fn new(version: Version, hooks: Option<&ToolHooks<Self>>) -> Fallible<Self> {
-        match hooks {
-            Some(&ToolHooks {
-                distro: Some(ref hook),
-                ..
-            }) => {
-                let url =
-                    hook.resolve(&version, &path::node_distro_file_name(&version.to_string()))?;
-                NodeDistro::remote(version, &url)
-            }
-            _ => NodeDistro::public(version),
-        }
+    fn distro_file_regex() -> Regex {
+        Regex::new(
+            r"(?x)
+            node
+            -
+            v(?P<version>\d+\.\d+\.\d+) # Node version
+            -
+            (?P<os>[a-z]+)              # operating system
+            -
+            (?P<arch>[a-z0-9]+)         # architecture
+            \.(zip|tar\.gz)
+            ",
+        )
+        .unwrap()
     }
 
-    /// Produces a reference to this distribution's Node version.
-    fn version(&self) -> &Version {
-        &self.version
+    /// The URL of the distribution on the public Node distributor (`https://nodejs.org`).
+    fn public_distro_url(version: &Version) -> String {
+        format!(
+            "{}/v{}/{}",
+            public_node_server_root(),
+            version,
+            NodeKind::distro_file_name(version)
+        )
     }
 
-    /// Fetches this version of Node. (It is left to the responsibility of the `NodeCollection`
-    /// to update its state after fetching succeeds.)
-    fn fetch(self, collection: &NodeCollection) -> Fallible<Fetched<DistroVersion>> {
-        if collection.contains(&self.version) {
-            let npm = load_default_npm_version(&self.version)?;
+    fn public_index_url() -> String {
+        public_node_version_index()
+    }
 
-            return Ok(Fetched::Already(DistroVersion::Node(self.version, npm)));
-        }
+    // NOTE: This assumes the registry always produces a list in sorted order
+    //       from newest to oldest. This should be specified as a requirement
+    //       when we document the plugin API.
+    fn public_latest_url() -> String {
+        public_node_version_index()
+    }
+
+    fn inventory_dir() -> Fallible<PathBuf> {
+        path::node_inventory_dir()
+    }
 
-        let temp = tempdir_in(path::tmp_dir()?).unknown()?;
-        let bar = progress_bar(
-            Action::Fetching,
-            &format!("v{}", self.version),
-            self.archive
-                .uncompressed_size()
-                .unwrap_or(self.archive.compressed_size()),
-        );
+    fn image_dir(version: &Version) -> Fallible<PathBuf> {
+        Ok(path::node_image_root_dir()?.join(version.to_string()))
+    }
 
-        self.archive
-            .unpack(temp.path(), &mut |_, read| {
-                bar.inc(read as u64);
-            })
-            .unknown()?;
+    fn load_archive(file: File) -> Result<Box<dyn Archive>, failure::Error> {
+        archive::load_native(file)
+    }
+
+    fn fetch_archive(
+        url: &str,
+        cache_file: &Path,
+        credentials: Option<&Credentials>,
+        proxy: Option<&Proxy>,
+    ) -> Result<Box<dyn Archive>, failure::Error> {
+        archive::fetch_native(url, cache_file, credentials, proxy)
+    }
 
-        let version_string = self.version.to_string();
+    fn install_image(version: &Version, unpacked: &Path) -> Fallible<DistroVersion> {
+        let version_string = version.to_string();
 
-        let npm_package_json = temp
-            .path()
-            .join(path::node_archive_npm_package_json_path(&version_string));
+        let npm_package_json =
+            unpacked.join(path::node_archive_npm_package_json_path(&version_string));
 
         let npm = Manifest::version(&npm_package_json)?;
 
         // Save the npm version number in the npm version file for this distro:
-        save_default_npm_version(&self.version, &npm)?;
+        save_default_npm_version(version, &npm)?;
 
         let dest = path::node_image_dir(&version_string, &npm.to_string())?;
 
         ensure_containing_dir_exists(&dest)?;
 
         rename(
-            temp.path()
-                .join(path::node_archive_root_dir_name(&version_string)),
-            dest,
+            unpacked.join(path::node_archive_root_dir_name(&version_string)),
+            long_path(dest),
         )
         .unknown()?;
 
-        bar.finish_and_clear();
-        Ok(Fetched::Now(DistroVersion::Node(self.version, npm)))
+        Ok(DistroVersion::Node(version.clone(), npm))
+    }
+
+    fn installed_version(version: Version) -> Fallible<DistroVersion> {
+        let npm = load_default_npm_version(&version)?;
+        Ok(DistroVersion::Node(version, npm))
+    }
+
+    fn fetch_index(url: &str) -> Fallible<Vec<Version>> {
+        node_index_versions(url)
+    }
+
+    fn fetch_latest(url: &str) -> Fallible<Version> {
+        match node_index_versions(url)?.into_iter().next() {
+            Some(version) => Ok(version),
+            None => throw!(NoVersionFoundError {
+                tool: NodeKind::DISPLAY_NAME,
+                matching: "latest".to_string(),
+            }),
+        }
     }
+
+    fn fetch_lts(url: &str) -> Fallible<Option<Version>> {
+        Ok(node_index_lts_versions(url)?.into_iter().next())
+    }
+
+    /// Reads the checksum of the archive from the `SHASUMS256.txt` published next to it,
+    /// as on `https://nodejs.org` and its mirrors, checking its signature first if
+    /// configured. Servers that don't publish one verify nothing.
+    fn published_checksum(version: &Version, url: &str) -> Fallible<Option<Checksum>> {
+        let (dir, file_name) = match url.rfind('/') {
+            Some(index) => (&url[..index], &url[index + 1..]),
+            None => return Ok(None),
+        };
+        let shasums_url = format!("{}/{}", dir, SHASUMS_FILE_NAME);
+        let shasums = match fetch_published(&shasums_url)? {
+            Some(shasums) => shasums,
+            None => return Ok(None),
+        };
+
+        if Config::current()?.verify.signatures {
+            verify_signature(version, &shasums_url, &shasums)?;
+        }
+        Ok(Checksum::from_listing(
+            Algorithm::Sha256,
+            &String::from_utf8_lossy(&shasums),
+            file_name,
+        ))
+    }
+}
+
+/// The listing of the SHA-256 checksums of the archives of a Node release.
+const SHASUMS_FILE_NAME: &'static str = "SHASUMS256.txt";
+
+/// Downloads `url`, or returns `None` if the server doesn't publish it.
+fn fetch_published(url: &str) -> Fallible<Option<Vec<u8>>> {
+    let mut response = RetryPolicy::current()?
+        .send(|| credentials::get(url), url, None)
+        .with_context(RegistryFetchError::from_error)?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+    let mut contents = Vec::new();
+    response.read_to_end(&mut contents).unknown()?;
+    Ok(Some(contents))
+}
+
+/// Verifies the detached GPG signature published as `<shasums_url>.sig` with `gpg`,
+/// against the keys in the user's keyring.
+fn verify_signature(version: &Version, shasums_url: &str, shasums: &[u8]) -> Fallible<()> {
+    let invalid = |error: String| DownloadError::InvalidSignature {
+        toolspec: NodeKind::tool_spec(version),
+        error,
+    };
+
+    let signature = match fetch_published(&format!("{}.sig", shasums_url))? {
+        Some(signature) => signature,
+        None => throw!(invalid(format!("{}.sig was not found", shasums_url))),
+    };
+
+    let dir = tempdir().unknown()?;
+    let shasums_file = dir.path().join(SHASUMS_FILE_NAME);
+    let signature_file = dir.path().join(format!("{}.sig", SHASUMS_FILE_NAME));
+    fs::write(&shasums_file, shasums).unknown()?;
+    fs::write(&signature_file, signature).unknown()?;
+
+    let output = match Command::new("gpg")
+        .arg("--batch")
+        .arg("--verify")
+        .arg(&signature_file)
+        .arg(&shasums_file)
+        .output()
+    {
+        Ok(output) => output,
+        Err(error) => throw!(invalid(format!("could not run gpg: {}", error))),
+    };
+    if !output.status.success() {
+        throw!(invalid(
+            String::from_utf8_lossy(&output.stderr).trim().to_string()
+        ));
+    }
+    Ok(())
 }
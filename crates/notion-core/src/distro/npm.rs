@@ -0,0 +1,139 @@
+//! Provides the `NpmKind` type, which describes how npm is provisioned on its own, and
+//! the `NpmDistro` type, which represents a provisioned npm distribution.
+//!
+//! Node comes with a bundled npm, which is used unless another npm version is chosen.
+//! A chosen version is fetched from its tarball on the npm registry, and its image is
+//! put ahead of the Node image on the `PATH`, so that it runs on the platform's Node.
+
+use std::fs::{rename, File};
+use std::path::{Path, PathBuf};
+use std::string::ToString;
+
+use failure;
+use regex::Regex;
+use semver::Version;
+
+use archive::{Archive, Credentials, Proxy, Tarball};
+use notion_fail::{Fallible, ResultExt};
+
+use super::kind::{ArchiveDistro, ToolKind};
+use crate::distro::DistroVersion;
+use crate::fs::{ensure_containing_dir_exists, long_path};
+use crate::inventory::{public_npm_version_index, resolve_npm_latest, resolve_npm_versions};
+use crate::path;
+use crate::tool::ToolSpec;
+use crate::version::VersionSpec;
+
+#[cfg(feature = "mock-network")]
+use mockito;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "mock-network")] {
+        pub(crate) fn public_npm_server_root() -> String {
+            format!("{}/npm-registry", mockito::SERVER_URL)
+        }
+    } else {
+        pub(crate) fn public_npm_server_root() -> String {
+            "https://registry.npmjs.org".to_string()
+        }
+    }
+}
+
+/// The npm package manager, when chosen apart from the npm bundled with Node.
+pub struct NpmKind;
+
+/// A provisioned npm distribution.
+pub type NpmDistro = ArchiveDistro<NpmKind>;
+
+impl ToolKind for NpmKind {
+    type VersionDetails = Version;
+
+    const DISPLAY_NAME: &'static str = "npm";
+    const INDEX_FILE_NAME: &'static str = "npm";
+    const LATEST_FILE_NAME: &'static str = "npm";
+    const BINARIES: &'static [&'static str] = &["npm", "npx"];
+
+    fn tool_spec(version: &Version) -> ToolSpec {
+        ToolSpec::Npm(VersionSpec::exact(version))
+    }
+
+    fn distro_file_name(version: &Version) -> String {
+        path::npm_distro_file_name(&version.to_string())
+    }
+
+    fn distro_file_regex() -> Regex {
+        Regex::new(
+            r"(?x)
+            npm
+            -
+            (?P<version>\d+\.\d+\.\d+) # npm version
+            \.tgz
+            ",
+        )
+        .unwrap()
+    }
+
+    /// The URL of the tarball on the npm registry, which is verified against the
+    /// integrity the registry publishes for it.
+    fn public_distro_url(version: &Version) -> String {
+        format!(
+            "{}/npm/-/{}",
+            public_npm_server_root(),
+            NpmKind::distro_file_name(version)
+        )
+    }
+
+    fn public_index_url() -> String {
+        public_npm_version_index()
+    }
+
+    /// The latest version is the `latest` dist-tag of the same document as the index.
+    fn public_latest_url() -> String {
+        public_npm_version_index()
+    }
+
+    fn inventory_dir() -> Fallible<PathBuf> {
+        path::npm_inventory_dir()
+    }
+
+    fn image_dir(version: &Version) -> Fallible<PathBuf> {
+        path::npm_image_dir(&version.to_string())
+    }
+
+    fn load_archive(file: File) -> Result<Box<dyn Archive>, failure::Error> {
+        Tarball::load(file)
+    }
+
+    fn fetch_archive(
+        url: &str,
+        cache_file: &Path,
+        credentials: Option<&Credentials>,
+        proxy: Option<&Proxy>,
+    ) -> Result<Box<dyn Archive>, failure::Error> {
+        Tarball::fetch(url, cache_file, credentials, proxy)
+    }
+
+    /// Moves the package, which registry tarballs root at `package`, into the image. Its
+    /// `bin` directory holds the `npm` and `npx` launchers, which run on the `node` found
+    /// on the `PATH`.
+    fn install_image(version: &Version, unpacked: &Path) -> Fallible<DistroVersion> {
+        let dest = path::npm_image_dir(&version.to_string())?;
+
+        ensure_containing_dir_exists(&dest)?;
+        rename(unpacked.join("package"), long_path(dest)).unknown()?;
+
+        Ok(DistroVersion::Npm(version.clone()))
+    }
+
+    fn installed_version(version: Version) -> Fallible<DistroVersion> {
+        Ok(DistroVersion::Npm(version))
+    }
+
+    fn fetch_index(url: &str) -> Fallible<Vec<Version>> {
+        Ok(resolve_npm_versions(url)?.into_iter().rev().collect())
+    }
+
+    fn fetch_latest(url: &str) -> Fallible<Version> {
+        resolve_npm_latest(url)
+    }
+}
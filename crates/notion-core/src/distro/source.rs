@@ -0,0 +1,274 @@
+//! Provides building Node from its source, the only way to provision Node on platforms
+//! that no prebuilt distribution is published for.
+//!
+//! The source tarball is downloaded into the staging directory and checked against the
+//! `SHASUMS256.txt` published with the release, then unpacked there, configured, built
+//! (with a job for each CPU, unless `MAKEFLAGS` says otherwise) and installed into a
+//! prefix there, which is then moved into the image directory of the version like an
+//! unpacked distribution. As there is no distribution archive in the inventory, the build
+//! is recorded by a `node-v<version>-source` file next to the `node-v<version>-npm` file,
+//! which the inventory picks up like an archive.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::Command;
+
+use archive::{self, Archive, Tarball};
+use failure::Fail;
+use num_cpus;
+use regex::Regex;
+use semver::Version;
+use tempfile::tempdir_in;
+
+use super::kind::ToolKind;
+use super::node::{
+    public_node_server_root, save_default_npm_version, Manifest, NodeDistro, NodeKind,
+};
+use super::DistroVersion;
+use crate::checksum::Checksum;
+use crate::credentials;
+use crate::distro::error::DownloadError;
+use crate::fs::{ensure_containing_dir_exists, ensure_dir_exists, long_path};
+use crate::hook::ToolHooks;
+use crate::interrupt::{self, Cleanup};
+use crate::inventory::serial::versions_matching;
+use crate::network;
+use crate::path;
+use crate::style::{Action, Steps};
+use crate::tool::ToolSpec;
+use crate::version::VersionSpec;
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+/// Thrown when a step of building Node from source fails.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Could not build Node v{} from source: `{}` failed\n\n{}",
+    version, step, output
+)]
+#[notion_fail(code = "ExecutionFailure")]
+pub(crate) struct SourceBuildError {
+    version: String,
+    step: String,
+    output: String,
+}
+
+/// Thrown when building from source is requested on a platform it isn't supported on.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Building Node from source is not yet supported on Windows")]
+#[notion_fail(code = "NotYetImplemented")]
+pub(crate) struct SourceBuildUnsupportedError;
+
+/// How many lines of the output of a failed build step are shown.
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// The steps of a build: fetching the source, configuring, building and installing.
+const BUILD_STEPS: usize = 4;
+
+/// The file name of the source tarball of `version`.
+fn source_file_name(version: &Version) -> String {
+    format!("node-v{}.tar.gz", version)
+}
+
+/// The name of the directory the source tarball of `version` unpacks into.
+fn source_root_dir_name(version: &Version) -> String {
+    format!("node-v{}", version)
+}
+
+fn source_build_regex() -> Regex {
+    Regex::new(r"^node-v(?P<version>\d+\.\d+\.\d+)-source$").unwrap()
+}
+
+/// Reads the versions of Node that were built from source.
+pub(crate) fn built_versions() -> Fallible<BTreeSet<Version>> {
+    versions_matching(&path::node_inventory_dir()?, &source_build_regex())
+}
+
+/// Builds `version` of Node from its source, which is downloaded from the `distro` hook
+/// (resolved against the name of the source tarball) or the public Node server, and
+/// installs it into the inventory.
+pub(crate) fn build(
+    version: &Version,
+    hooks: Option<&ToolHooks<NodeDistro>>,
+) -> Fallible<DistroVersion> {
+    if cfg!(windows) {
+        throw!(SourceBuildUnsupportedError);
+    }
+
+    let url = match hooks {
+        Some(&ToolHooks {
+            distro: Some(ref hook),
+            ..
+        }) => hook.resolve(version, &source_file_name(version))?,
+        _ => format!(
+            "{}/v{}/{}",
+            public_node_server_root(),
+            version,
+            source_file_name(version)
+        ),
+    };
+
+    let staging = path::staging_dir()?;
+    ensure_dir_exists(&staging)?;
+    let temp = tempdir_in(long_path(staging)).unknown()?;
+    let _interrupt = interrupt::register(vec![
+        Cleanup::RemoveDir(temp.path().to_path_buf()),
+        Cleanup::RemoveFile(path::node_npm_version_file(&version.to_string())?),
+        Cleanup::RemoveDir(path::node_image_root_dir()?.join(version.to_string())),
+    ]);
+
+    let toolspec = || ToolSpec::Node(VersionSpec::exact(version));
+    let expected = NodeKind::published_checksum(version, &url)?;
+    let credentials = credentials::for_url(&url)?;
+    let proxy = network::proxy_for(&url)?;
+    let tarball = temp.path().join(source_file_name(version));
+
+    let steps = Steps::new(BUILD_STEPS);
+    let bar = steps.bar(Action::Fetching, &format!("v{} (source)", version), 0);
+    let mut length = 0;
+    let mut reported = 0;
+    archive::download(
+        &url,
+        &tarball,
+        credentials.as_ref(),
+        proxy.as_ref(),
+        &mut |position, total| {
+            if total != length {
+                length = total;
+                bar.set_length(total);
+            }
+            bar.inc(position.saturating_sub(reported));
+            reported = position;
+        },
+    )
+    .with_context(DownloadError::for_tool(toolspec(), url.clone()))?;
+    bar.finish();
+
+    // the source is checked before any of it is unpacked, let alone built
+    if let Some(expected) = expected {
+        let actual = Checksum::of_file(expected.algorithm, &tarball)?;
+        if actual != expected {
+            throw!(DownloadError::ChecksumMismatch {
+                toolspec: toolspec(),
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+    }
+    let archive: Box<dyn Archive> = Tarball::load(File::open(&tarball).unknown()?).unknown()?;
+    archive.unpack(temp.path(), &mut |_, _| {}).unknown()?;
+
+    let source = temp.path().join(source_root_dir_name(version));
+    let prefix = temp.path().join("install");
+
+    run_step(
+        &steps,
+        version,
+        Action::Configuring,
+        Command::new("./configure")
+            .arg(format!("--prefix={}", prefix.display()))
+            .current_dir(&source),
+    )?;
+    let mut make = Command::new("make");
+    // the user's MAKEFLAGS (e.g. `-j8`) take precedence
+    if env::var_os("MAKEFLAGS").is_none() {
+        make.arg(format!("-j{}", num_cpus::get()));
+    }
+    run_step(&steps, version, Action::Building, make.current_dir(&source))?;
+    run_step(
+        &steps,
+        version,
+        Action::Installing,
+        Command::new("make").arg("install").current_dir(&source),
+    )?;
+
+    install(version, &prefix)
+}
+
+/// Runs the next of the `steps` of the build, with a spinner showing its progress.
+fn run_step(
+    steps: &Steps,
+    version: &Version,
+    action: Action,
+    command: &mut Command,
+) -> Fallible<()> {
+    let spinner = steps.spinner(action, &format!("Node v{}", version));
+    let output = command.output();
+    spinner.finish();
+
+    let output = output.unknown()?;
+    if !output.status.success() {
+        let mut log = String::from_utf8_lossy(&output.stdout).into_owned();
+        log.push_str(&String::from_utf8_lossy(&output.stderr));
+        throw!(SourceBuildError {
+            version: version.to_string(),
+            step: format!("{:?}", command),
+            output: tail(&log, OUTPUT_TAIL_LINES),
+        });
+    }
+    Ok(())
+}
+
+/// Moves the Node installed into `prefix` into its image directory and records it in the
+/// inventory.
+fn install(version: &Version, prefix: &Path) -> Fallible<DistroVersion> {
+    let npm = Manifest::version(
+        &prefix
+            .join("lib")
+            .join("node_modules")
+            .join("npm")
+            .join("package.json"),
+    )?;
+    save_default_npm_version(version, &npm)?;
+
+    let dest = path::node_image_dir(&version.to_string(), &npm.to_string())?;
+    // left behind by a build interrupted before it was recorded
+    if dest.exists() {
+        fs::remove_dir_all(long_path(&dest)).unknown()?;
+    }
+    ensure_containing_dir_exists(&dest)?;
+    fs::rename(prefix, long_path(&dest)).unknown()?;
+
+    File::create(path::node_source_build_file(&version.to_string())?).unknown()?;
+    Ok(DistroVersion::Node(version.clone(), npm))
+}
+
+/// The last `lines` lines of `log`.
+fn tail(log: &str, lines: usize) -> String {
+    let all: Vec<&str> = log.trim_end().lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{source_build_regex, source_file_name, tail};
+    use semver::Version;
+
+    #[test]
+    fn test_source_file_name() {
+        assert_eq!(
+            source_file_name(&Version::parse("10.15.0").unwrap()),
+            "node-v10.15.0.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_source_build_regex() {
+        let re = source_build_regex();
+        assert_eq!(
+            &re.captures("node-v10.15.0-source").unwrap()["version"],
+            "10.15.0"
+        );
+        assert!(re.captures("node-v10.15.0-npm").is_none());
+        assert!(re.captures("node-v10.15.0-linux-x64.tar.gz").is_none());
+    }
+
+    #[test]
+    fn test_tail() {
+        assert_eq!(tail("a\nb\nc\n", 2), "b\nc");
+        assert_eq!(tail("a\n", 5), "a");
+    }
+}
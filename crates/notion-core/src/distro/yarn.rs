@@ -1,23 +1,28 @@
-//! Provides the `YarnDistro` type, which represents a provisioned Yarn distribution.
+//! Provides the `YarnKind` type, which describes how Yarn is provisioned, and the
+//! `YarnDistro` type, which represents a provisioned Yarn distribution.
 
 use std::fs::{rename, File};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::string::ToString;
 
+use failure;
+use regex::Regex;
+use reqwest;
 use semver::Version;
-use tempfile::tempdir_in;
 
-use archive::{Archive, Tarball};
+use archive::{Archive, Credentials, Proxy, Tarball};
 use notion_fail::{Fallible, ResultExt};
 
-use super::{Distro, Fetched};
-use crate::distro::error::DownloadError;
+use super::kind::{ArchiveDistro, ToolKind};
+use crate::credentials;
 use crate::distro::DistroVersion;
-use crate::fs::ensure_containing_dir_exists;
-use crate::hook::ToolHooks;
-use crate::inventory::YarnCollection;
+use crate::fs::{ensure_containing_dir_exists, long_path};
+use crate::inventory::{
+    public_yarn_latest_version, public_yarn_version_index, resolve_yarn_versions,
+    RegistryFetchError,
+};
 use crate::path;
-use crate::style::{progress_bar, Action};
+use crate::retry::RetryPolicy;
 use crate::tool::ToolSpec;
 use crate::version::VersionSpec;
 
@@ -26,138 +31,118 @@ use mockito;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "mock-network")] {
-        fn public_yarn_server_root() -> String {
+        pub(crate) fn public_yarn_server_root() -> String {
             mockito::SERVER_URL.to_string()
         }
     } else {
-        fn public_yarn_server_root() -> String {
+        pub(crate) fn public_yarn_server_root() -> String {
             "https://github.com/yarnpkg/yarn/releases/download".to_string()
         }
     }
 }
 
+/// The Yarn package manager.
+pub struct YarnKind;
+
 /// A provisioned Yarn distribution.
-pub struct YarnDistro {
-    archive: Box<dyn Archive>,
-    version: Version,
-}
+pub type YarnDistro = ArchiveDistro<YarnKind>;
 
-/// Check if the fetched file is valid. It may have been corrupted or interrupted in the middle of
-/// downloading.
-// ISSUE(#134) - verify checksum
-fn distro_is_valid(file: &PathBuf) -> bool {
-    if file.is_file() {
-        if let Ok(file) = File::open(file) {
-            match Tarball::load(file) {
-                Ok(_) => return true,
-                Err(_) => return false,
-            }
-        }
+impl ToolKind for YarnKind {
+    type VersionDetails = Version;
+
+    const DISPLAY_NAME: &'static str = "Yarn";
+    const INDEX_FILE_NAME: &'static str = "releases";
+    const LATEST_FILE_NAME: &'static str = "latest-version";
+    const BINARIES: &'static [&'static str] = &["yarn"];
+
+    fn tool_spec(version: &Version) -> ToolSpec {
+        ToolSpec::Yarn(VersionSpec::exact(version))
     }
-    false
-}
 
-impl YarnDistro {
-    /// Provision a Yarn distribution from the public distributor (`https://yarnpkg.com`).
-    fn public(version: Version) -> Fallible<Self> {
-        let version_str = version.to_string();
-        let distro_file_name = path::yarn_distro_file_name(&version_str);
-        let url = format!(
+    fn distro_file_name(version: &Version) -> String {
+        path::yarn_distro_file_name(&version.to_string())
+    }
+
+    fn distro_file_regex() -> Regex {
+        Regex::new(
+            r"(?x)
+            yarn
+            -
+            v(?P<version>\d+\.\d+\.\d+) # Yarn version
+            \.tar\.gz
+            ",
+        )
+        .unwrap()
+    }
+
+    /// The URL of the distribution on the public distributor (`https://yarnpkg.com`).
+    fn public_distro_url(version: &Version) -> String {
+        format!(
             "{}/v{}/{}",
             public_yarn_server_root(),
-            version_str,
-            distro_file_name
-        );
-        YarnDistro::remote(version, &url)
+            version,
+            YarnKind::distro_file_name(version)
+        )
     }
 
-    /// Provision a Yarn distribution from a remote distributor.
-    fn remote(version: Version, url: &str) -> Fallible<Self> {
-        let distro_file_name = path::yarn_distro_file_name(&version.to_string());
-        let distro_file = path::yarn_inventory_dir()?.join(&distro_file_name);
-
-        if distro_is_valid(&distro_file) {
-            return YarnDistro::local(version, File::open(distro_file).unknown()?);
-        }
+    fn public_index_url() -> String {
+        public_yarn_version_index()
+    }
 
-        ensure_containing_dir_exists(&distro_file)?;
-        Ok(YarnDistro {
-            archive: Tarball::fetch(url, &distro_file).with_context(DownloadError::for_tool(
-                ToolSpec::Yarn(VersionSpec::exact(&version)),
-                url.to_string(),
-            ))?,
-            version: version,
-        })
+    fn public_latest_url() -> String {
+        public_yarn_latest_version()
     }
 
-    /// Provision a Yarn distribution from the filesystem.
-    fn local(version: Version, file: File) -> Fallible<Self> {
-        Ok(YarnDistro {
-            archive: Tarball::load(file).unknown()?,
-            version: version,
-        })
+    fn inventory_dir() -> Fallible<PathBuf> {
+        path::yarn_inventory_dir()
     }
-}
 
-impl Distro for YarnDistro {
-    type VersionDetails = Version;
+    fn image_dir(version: &Version) -> Fallible<PathBuf> {
+        path::yarn_image_dir(&version.to_string())
+    }
 
-    /// Provisions a new Distro based on the Version and possible Hooks
-    fn new(version: Version, hooks: Option<&ToolHooks<Self>>) -> Fallible<Self> {
-        match hooks {
-            Some(&ToolHooks {
-                distro: Some(ref hook),
-                ..
-            }) => {
-                let url =
-                    hook.resolve(&version, &path::yarn_distro_file_name(&version.to_string()))?;
-                YarnDistro::remote(version, &url)
-            }
-            _ => YarnDistro::public(version),
-        }
+    fn load_archive(file: File) -> Result<Box<dyn Archive>, failure::Error> {
+        Tarball::load(file)
     }
 
-    /// Produces a reference to this distro's Yarn version.
-    fn version(&self) -> &Version {
-        &self.version
+    fn fetch_archive(
+        url: &str,
+        cache_file: &Path,
+        credentials: Option<&Credentials>,
+        proxy: Option<&Proxy>,
+    ) -> Result<Box<dyn Archive>, failure::Error> {
+        Tarball::fetch(url, cache_file, credentials, proxy)
     }
 
-    /// Fetches this version of Yarn. (It is left to the responsibility of the `YarnCollection`
-    /// to update its state after fetching succeeds.)
-    fn fetch(self, collection: &YarnCollection) -> Fallible<Fetched<DistroVersion>> {
-        if collection.contains(&self.version) {
-            return Ok(Fetched::Already(DistroVersion::Yarn(self.version)));
-        }
+    fn install_image(version: &Version, unpacked: &Path) -> Fallible<DistroVersion> {
+        let version_string = version.to_string();
 
-        let temp = tempdir_in(path::tmp_dir()?).unknown()?;
-        let bar = progress_bar(
-            Action::Fetching,
-            &format!("v{}", self.version),
-            self.archive
-                .uncompressed_size()
-                .unwrap_or(self.archive.compressed_size()),
-        );
+        let dest = path::yarn_image_dir(&version_string)?;
 
-        self.archive
-            .unpack(temp.path(), &mut |_, read| {
-                bar.inc(read as u64);
-            })
-            .unknown()?;
+        ensure_containing_dir_exists(&dest)?;
 
-        let version_string = self.version.to_string();
+        // the tarballs published on the npm registry are rooted at `package` instead
+        let mut root = unpacked.join(path::yarn_archive_root_dir_name(&version_string));
+        if !root.exists() {
+            root = unpacked.join("package");
+        }
+        rename(root, long_path(dest)).unknown()?;
 
-        let dest = path::yarn_image_dir(&version_string)?;
+        Ok(DistroVersion::Yarn(version.clone()))
+    }
 
-        ensure_containing_dir_exists(&dest)?;
+    fn installed_version(version: Version) -> Fallible<DistroVersion> {
+        Ok(DistroVersion::Yarn(version))
+    }
 
-        rename(
-            temp.path()
-                .join(path::yarn_archive_root_dir_name(&version_string)),
-            dest,
-        )
-        .unknown()?;
+    fn fetch_index(url: &str) -> Fallible<Vec<Version>> {
+        Ok(resolve_yarn_versions(url)?.into_iter().rev().collect())
+    }
 
-        bar.finish_and_clear();
-        Ok(Fetched::Now(DistroVersion::Yarn(self.version)))
+    fn fetch_latest(url: &str) -> Fallible<Version> {
+        let mut response: reqwest::Response = RetryPolicy::current()?
+            .send(|| credentials::get(url), url, None)
+            .with_context(RegistryFetchError::from_error)?;
+        Version::parse(&response.text().unknown()?).unknown()
     }
 }
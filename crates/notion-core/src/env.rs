@@ -3,12 +3,173 @@
 use std::env;
 use std::path::{Path, PathBuf};
 
+use semver::Version;
+
+use crate::output::Verbosity;
+
 pub const UNSAFE_GLOBAL: &'static str = "NOTION_UNSAFE_GLOBAL";
 
+/// Makes every prompt take its default answer (or fail if it has none), so that
+/// Notion never waits for input in automation.
+pub const NONINTERACTIVE: &'static str = "NOTION_NONINTERACTIVE";
+
+/// Makes Notion resolve versions from the local inventory only, never reaching a server,
+/// as with `notion --offline`.
+pub const OFFLINE: &'static str = "NOTION_OFFLINE";
+
+/// Selects how progress is displayed (`fancy`, `plain` or `json`).
+pub const PROGRESS: &'static str = "NOTION_PROGRESS";
+
+/// Designates the file descriptor that progress events are written to in the `json`
+/// progress mode, instead of the standard error (Unix only).
+pub const PROGRESS_FD: &'static str = "NOTION_PROGRESS_FD";
+
+/// Selects how much Notion reports (`quiet`, `normal`, `verbose` or `debug`), as with
+/// `notion --quiet` or `notion --verbose`.
+pub const VERBOSITY: &'static str = "NOTION_VERBOSITY";
+
+/// Enables the accessibility mode, which reports progress as occasional plain-text lines
+/// that screen readers can follow (unless `NOTION_PROGRESS` selects a mode explicitly).
+pub const SCREEN_READER: &'static str = "NOTION_SCREEN_READER";
+
+/// Makes Notion treat `NOTION_HOME` as read-only, running tools from the existing
+/// inventory but refusing to fetch, shim or otherwise write anything there.
+pub const READ_ONLY: &'static str = "NOTION_READ_ONLY";
+
+/// Selects a named profile of the project's `toolchainProfiles` in place of its default
+/// `toolchain`; `notion use --profile` sets it for the current shell.
+pub const PROFILE: &'static str = "NOTION_PROFILE";
+
+/// Makes the shims report diagnostics, such as another Node installation shadowing them
+/// on `PATH`, on every invocation.
+pub const DEBUG: &'static str = "NOTION_DEBUG";
+
+/// Skips the automatic garbage collection of the inventory (see `gc` in `config.toml`)
+/// for a single command.
+pub const NO_GC: &'static str = "NOTION_NO_GC";
+
+/// Selects how errors are reported: `json` makes Notion write each error to stderr as a
+/// single JSON object, for tools that wrap Notion, instead of styled text.
+pub const OUTPUT: &'static str = "NOTION_OUTPUT";
+
+/// The name under which the `toolchain` of a project is selected explicitly.
+pub const DEFAULT_PROFILE: &'static str = "default";
+
+/// The npm configuration variable (also honored by Yarn) that selects the prefix
+/// for global package installs.
+pub(crate) const GLOBAL_PREFIX: &'static str = "NPM_CONFIG_PREFIX";
+
+/// The npm configuration variable that selects the cache directory, used to scope
+/// `npx` and `npm exec` downloads to a single Node version.
+pub(crate) const NPM_CACHE: &'static str = "NPM_CONFIG_CACHE";
+
+/// The Yarn configuration variable that selects the cache directory, used to scope
+/// `yarn dlx` downloads to a single Node version.
+pub(crate) const YARN_CACHE: &'static str = "YARN_CACHE_FOLDER";
+
+/// The Yarn configuration variable that selects the folder that `yarn global add`
+/// installs into, which Yarn keeps apart from the global prefix.
+pub(crate) const YARN_GLOBAL_FOLDER: &'static str = "YARN_GLOBAL_FOLDER";
+
 pub(crate) fn shell_name() -> Option<String> {
     env::var_os("NOTION_SHELL").map(|s| s.to_string_lossy().into_owned())
 }
 
+/// Determines whether Notion should keep its messages terse, either because the user
+/// asked for it with `NOTION_QUIET` (or the quiet verbosity) or because it is running in
+/// a CI environment.
+pub(crate) fn is_quiet() -> bool {
+    env::var_os("NOTION_QUIET").is_some() || is_ci() || Verbosity::current() == Verbosity::Quiet
+}
+
+/// The variables set by CI systems, some of which (Azure Pipelines, Jenkins, TeamCity)
+/// don't set `CI`.
+const CI_VARIABLES: [&'static str; 5] = [
+    "CI",
+    "CONTINUOUS_INTEGRATION",
+    "TF_BUILD",
+    "JENKINS_URL",
+    "TEAMCITY_VERSION",
+];
+
+/// Determines whether Notion is running in a CI environment, whose logs can't render
+/// animated progress.
+pub(crate) fn is_ci() -> bool {
+    CI_VARIABLES.iter().any(|name| env::var_os(name).is_some())
+}
+
+/// Determines whether errors are reported as JSON (see `NOTION_OUTPUT`).
+pub fn is_json_output() -> bool {
+    match env::var(OUTPUT) {
+        Ok(value) => value == "json",
+        Err(_) => false,
+    }
+}
+
+/// Determines whether the user asked Notion never to prompt for input.
+pub(crate) fn is_noninteractive() -> bool {
+    match env::var_os(NONINTERACTIVE) {
+        Some(value) => !value.is_empty() && value != "0",
+        None => false,
+    }
+}
+
+/// Determines whether the user asked Notion to work offline with `NOTION_OFFLINE`.
+pub(crate) fn is_offline() -> bool {
+    match env::var_os(OFFLINE) {
+        Some(value) => !value.is_empty() && value != "0",
+        None => false,
+    }
+}
+
+/// Determines whether the user enabled the accessibility mode with `NOTION_SCREEN_READER`.
+pub(crate) fn is_screen_reader() -> bool {
+    match env::var_os(SCREEN_READER) {
+        Some(value) => !value.is_empty() && value != "0",
+        None => false,
+    }
+}
+
+/// Determines whether the user enabled the shim diagnostics with `NOTION_DEBUG`.
+pub(crate) fn is_debug() -> bool {
+    match env::var_os(DEBUG) {
+        Some(value) => !value.is_empty() && value != "0",
+        None => false,
+    }
+}
+
+/// Determines whether the user skipped the automatic garbage collection with `NOTION_NO_GC`.
+pub(crate) fn is_gc_disabled() -> bool {
+    match env::var_os(NO_GC) {
+        Some(value) => !value.is_empty() && value != "0",
+        None => false,
+    }
+}
+
+/// Determines whether the user made `NOTION_HOME` read-only with `NOTION_READ_ONLY`.
+pub(crate) fn is_read_only() -> bool {
+    match env::var_os(READ_ONLY) {
+        Some(value) => !value.is_empty() && value != "0",
+        None => false,
+    }
+}
+
+/// Returns the toolchain profile selected with `NOTION_PROFILE`, unless it selects the
+/// default toolchain.
+pub(crate) fn toolchain_profile() -> Option<String> {
+    env::var(PROFILE)
+        .ok()
+        .filter(|profile| !profile.is_empty() && profile != DEFAULT_PROFILE)
+}
+
+/// Returns the version of `tool` selected for the current shell session by `notion shell`,
+/// which is exported as `NOTION_<TOOL>_VERSION`.
+pub(crate) fn shell_tool_version(tool: &str) -> Option<Version> {
+    env::var(format!("NOTION_{}_VERSION", tool.to_ascii_uppercase()))
+        .ok()
+        .and_then(|version| Version::parse(version.trim()).ok())
+}
+
 pub fn postscript_path() -> Option<PathBuf> {
     env::var_os("NOTION_POSTSCRIPT")
         .as_ref()
@@ -28,6 +189,16 @@ pub mod tests {
         assert_eq!(shell_name().unwrap(), "bash".to_string());
     }
 
+    #[test]
+    fn test_is_noninteractive() {
+        env::set_var(NONINTERACTIVE, "1");
+        assert!(is_noninteractive());
+        env::set_var(NONINTERACTIVE, "0");
+        assert!(!is_noninteractive());
+        env::remove_var(NONINTERACTIVE);
+        assert!(!is_noninteractive());
+    }
+
     #[test]
     fn test_postscript_path() {
         env::set_var("NOTION_POSTSCRIPT", "/some/path");
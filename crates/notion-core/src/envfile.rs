@@ -0,0 +1,96 @@
+//! Provides the generation of environment files for a platform image, which let tools
+//! that read env files (like Docker Compose or IDE run configurations) use the pinned
+//! toolchain without going through the shims.
+
+use semver::Version;
+
+use crate::platform::Image;
+use notion_fail::Fallible;
+
+/// The syntax of an environment file.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// `KEY=value` lines, as read by Docker Compose `env_file` and most IDEs.
+    Dotenv,
+    /// A POSIX shell script of `export` statements, to be sourced.
+    Shell,
+}
+
+impl Format {
+    /// Parses a format name, as given to `notion envfile --format`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "dotenv" => Some(Format::Dotenv),
+            "sh" => Some(Format::Shell),
+            _ => None,
+        }
+    }
+}
+
+/// Produces the variables that select `image`: a `PATH` that finds its executables
+/// before any others, and the `NOTION_<TOOL>_VERSION` variable of each of its tools.
+pub fn variables(image: &Image) -> Fallible<Vec<(String, String)>> {
+    let path = image.path()?.to_string_lossy().into_owned();
+    let mut variables = vec![("PATH".to_string(), path)];
+
+    let mut push_version = |tool: &str, version: &Version| {
+        variables.push((
+            format!("NOTION_{}_VERSION", tool.to_ascii_uppercase()),
+            version.to_string(),
+        ));
+    };
+    push_version("node", &image.node.runtime);
+    if let Some(ref npm) = image.npm {
+        push_version("npm", npm);
+    }
+    if let Some(ref yarn) = image.yarn {
+        push_version("yarn", yarn);
+    }
+    if let Some(ref deno) = image.deno {
+        push_version("deno", deno);
+    }
+
+    Ok(variables)
+}
+
+/// Renders `variables` as an environment file in the given format.
+pub fn render(format: Format, variables: &[(String, String)]) -> String {
+    variables
+        .iter()
+        .map(|(name, value)| match format {
+            // the dotenv readers disagree on quoting, so values are written verbatim
+            Format::Dotenv => format!("{}={}\n", name, value),
+            Format::Shell => format!("export {}='{}'\n", name, value.replace('\'', r"'\''")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{render, Format};
+
+    #[test]
+    fn test_render() {
+        let variables = vec![
+            ("PATH".to_string(), "/notion/node/bin:/usr/bin".to_string()),
+            ("NOTION_NODE_VERSION".to_string(), "10.13.0".to_string()),
+        ];
+
+        assert_eq!(
+            render(Format::Dotenv, &variables),
+            "PATH=/notion/node/bin:/usr/bin\nNOTION_NODE_VERSION=10.13.0\n"
+        );
+        assert_eq!(
+            render(Format::Shell, &variables),
+            "export PATH='/notion/node/bin:/usr/bin'\nexport NOTION_NODE_VERSION='10.13.0'\n"
+        );
+        assert_eq!(
+            render(
+                Format::Shell,
+                &[("PATH".to_string(), "/it's/here".to_string())]
+            ),
+            "export PATH='/it'\\''s/here'\n"
+        );
+    }
+}
@@ -1,15 +1,22 @@
 //! Events for the sessions in executables and shims and everything
+//!
+//! Events are only ever sent anywhere when a `publish` hook is configured in the
+//! `[events]` section of `hooks.toml`: to the standard input of a plugin executable, or
+//! in the body of a POST request to a URL. Without one, they are dropped with the
+//! session.
 
 use os_info;
 
 use std::env;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::Serialize;
 
+use crate::credentials;
 use crate::hook::Publish;
 use crate::monitor::Monitor;
 use crate::session::ActivityKind;
+use crate::style::display_warning;
 use notion_fail::{ExitCode, NotionError};
 
 // the Event data that is serialized to JSON and sent the plugin
@@ -38,12 +45,22 @@ enum EventKind {
     },
     Error {
         exit_code: ExitCode,
+        code: &'static str,
         error: String,
         env: ErrorEnv,
     },
     ToolEnd {
         exit_code: i32,
     },
+    FetchStart {
+        tool: String,
+    },
+    FetchEnd {
+        tool: String,
+        version: String,
+        duration_ms: u64,
+        bytes: Option<u64>,
+    },
 }
 
 impl EventKind {
@@ -57,7 +74,7 @@ impl EventKind {
 }
 
 // returns the current number of milliseconds since the epoch
-fn unix_timestamp() -> u64 {
+pub(crate) fn unix_timestamp() -> u64 {
     let start = SystemTime::now();
     let duration = start
         .duration_since(UNIX_EPOCH)
@@ -92,15 +109,21 @@ fn get_error_env() -> ErrorEnv {
 
 pub struct EventLog {
     events: Vec<Event>,
+    // the activity that fetch events are reported under
+    activity: ActivityKind,
 }
 
 impl EventLog {
     /// Constructs a new 'EventLog'
     pub fn new() -> Self {
-        EventLog { events: Vec::new() }
+        EventLog {
+            events: Vec::new(),
+            activity: ActivityKind::Notion,
+        }
     }
 
     pub fn add_event_start(&mut self, activity_kind: ActivityKind) {
+        self.activity = activity_kind;
         self.add_event(EventKind::Start, activity_kind)
     }
     pub fn add_event_end(&mut self, activity_kind: ActivityKind, exit_code: ExitCode) {
@@ -114,6 +137,7 @@ impl EventLog {
         self.add_event(
             EventKind::Error {
                 exit_code: exit_code,
+                code: error.error_code(),
                 error: error.to_string(),
                 env: get_error_env(),
             },
@@ -121,6 +145,34 @@ impl EventLog {
         )
     }
 
+    /// Records that fetching the tool `tool` (such as `node@^10`) started.
+    pub fn add_event_fetch_start(&mut self, tool: String) {
+        let activity_kind = self.activity;
+        self.add_event(EventKind::FetchStart { tool }, activity_kind)
+    }
+
+    /// Records that fetching the tool `tool` produced `version` after `duration`,
+    /// downloading an archive of `bytes` bytes, if it wasn't already fetched.
+    pub fn add_event_fetch_end(
+        &mut self,
+        tool: String,
+        version: String,
+        duration: Duration,
+        bytes: Option<u64>,
+    ) {
+        let activity_kind = self.activity;
+        let duration_ms = duration.as_secs() * 1_000 + u64::from(duration.subsec_millis());
+        self.add_event(
+            EventKind::FetchEnd {
+                tool,
+                version,
+                duration_ms,
+                bytes,
+            },
+            activity_kind,
+        )
+    }
+
     fn add_event(&mut self, event_kind: EventKind, activity_kind: ActivityKind) {
         let event = event_kind.into_event(activity_kind);
         self.events.push(event);
@@ -128,7 +180,20 @@ impl EventLog {
 
     pub fn publish(&mut self, plugin: Option<&Publish>) {
         match plugin {
-            Some(&Publish::Url(_)) => unimplemented!(),
+            Some(&Publish::Url(ref url)) => {
+                let sent = match credentials::post(url) {
+                    Ok(mut request) => request
+                        .json(&self.events)
+                        .send()
+                        .and_then(|response| response.error_for_status())
+                        .map(|_| ())
+                        .map_err(|error| error.to_string()),
+                    Err(error) => Err(error.to_string()),
+                };
+                if let Err(error) = sent {
+                    display_warning(&format!("Could not publish events to {} ({})", url, error));
+                }
+            }
             Some(&Publish::Bin(ref command)) => {
                 let mut monitor = Monitor::new(command);
                 monitor.send_events(&self.events);
@@ -144,7 +209,9 @@ pub mod tests {
     use super::EventLog;
     use crate::session::ActivityKind;
     use notion_fail::{ExitCode, FailExt};
+    use serde_json;
     use std::io;
+    use std::time::Duration;
 
     #[test]
     fn test_adding_events() {
@@ -168,4 +235,24 @@ pub mod tests {
         assert_eq!(event_log.events.len(), 4);
         assert_eq!(event_log.events[3].name, "install");
     }
+
+    #[test]
+    fn test_fetch_events() {
+        let mut event_log = EventLog::new();
+        event_log.add_event_start(ActivityKind::Install);
+        event_log.add_event_fetch_start("node@^10".to_string());
+        event_log.add_event_fetch_end(
+            "node@^10".to_string(),
+            "node 10.15.0 (with npm 6.4.1)".to_string(),
+            Duration::from_millis(1500),
+            Some(4096),
+        );
+        assert_eq!(event_log.events.len(), 3);
+        assert_eq!(event_log.events[1].name, "install");
+
+        let json = serde_json::to_value(&event_log.events[2]).unwrap();
+        assert_eq!(json["name"], "install");
+        assert_eq!(json["event"]["fetchend"]["duration_ms"], 1500);
+        assert_eq!(json["event"]["fetchend"]["bytes"], 4096);
+    }
 }
@@ -0,0 +1,243 @@
+//! Provides the `ToolchainLock` type, the exact versions of the current toolchain and
+//! of the packages installed in the user toolchain, as written by `notion export`. A
+//! lock can be replayed on another machine with `notion setup --from`, which fetches
+//! every version it lists and makes them the user defaults.
+
+use std::fmt::Display;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use failure::Fail;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use crate::package;
+use crate::platform::PlatformSpec;
+use crate::session::Session;
+use crate::tool::ToolSpec;
+use crate::toolchain::serial::Platform;
+use crate::version::VersionSpec;
+use notion_fail::{ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+/// Thrown when the toolchain lock given to `notion setup --from` could not be read.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Could not read the toolchain lock {}\n{}", file, error)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct LockFileError {
+    file: String,
+    error: String,
+}
+
+impl LockFileError {
+    fn for_file<E: Display>(file: &Path) -> impl FnOnce(&E) -> LockFileError {
+        let file = file.display().to_string();
+        move |error| LockFileError {
+            file,
+            error: error.to_string(),
+        }
+    }
+}
+
+/// The exact versions of a toolchain and of its global packages.
+#[derive(Debug, PartialEq)]
+pub struct ToolchainLock {
+    /// The platform that runs, if any: the current project's, or the user default.
+    pub platform: Option<PlatformSpec>,
+    /// The packages installed in the user toolchain, by name.
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A package installed in the user toolchain.
+#[derive(Debug, PartialEq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: Version,
+    /// The platform the package was installed with, if the lock records it. A package
+    /// without one is installed with the restored default platform.
+    pub platform: Option<PlatformSpec>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerialLock {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    platform: Option<Platform>,
+    #[serde(default)]
+    packages: Vec<SerialPackage>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerialPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    platform: Option<Platform>,
+}
+
+impl ToolchainLock {
+    /// Captures the platform that runs in the current directory and the packages of the
+    /// user toolchain.
+    pub fn current(session: &Session) -> Fallible<Self> {
+        Ok(ToolchainLock {
+            platform: session
+                .current_platform()?
+                .map(|platform| (*platform).clone()),
+            packages: package::installed()?
+                .into_iter()
+                .map(|package| LockedPackage {
+                    name: package.name,
+                    version: package.version,
+                    platform: Some(package.platform),
+                })
+                .collect(),
+        })
+    }
+
+    /// Fetches every version in this lock and makes them the user defaults. The npm of
+    /// the platform is only installed on its own if it isn't the one bundled with Node,
+    /// and the packages are installed last, each with its own recorded platform, or else
+    /// with the restored one.
+    pub fn setup(&self, session: &mut Session) -> Fallible<()> {
+        if let Some(ref platform) = self.platform {
+            session.install(&ToolSpec::Node(VersionSpec::exact(&platform.node_runtime)))?;
+            if let Some(ref npm) = platform.npm {
+                let bundled = session
+                    .user_platform()?
+                    .and_then(|platform| platform.npm.clone());
+                if bundled.as_ref() != Some(npm) {
+                    session.install(&ToolSpec::Npm(VersionSpec::exact(npm)))?;
+                }
+            }
+            if let Some(ref yarn) = platform.yarn {
+                session.install(&ToolSpec::Yarn(VersionSpec::exact(yarn)))?;
+            }
+            if let Some(ref deno) = platform.deno {
+                session.install(&ToolSpec::Deno(VersionSpec::exact(deno)))?;
+            }
+        }
+
+        for package in &self.packages {
+            session.install_package(
+                &package.name,
+                &VersionSpec::exact(&package.version),
+                package.platform.as_ref(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes this lock to a JSON string.
+    pub fn to_json(&self) -> Fallible<String> {
+        let serial = SerialLock {
+            platform: self.platform.as_ref().map(|platform| platform.to_serial()),
+            packages: self
+                .packages
+                .iter()
+                .map(|package| SerialPackage {
+                    name: package.name.clone(),
+                    version: package.version.to_string(),
+                    platform: package
+                        .platform
+                        .as_ref()
+                        .map(|platform| platform.to_serial()),
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&serial).unknown()
+    }
+
+    /// Reads a lock from the JSON file `file`.
+    pub fn from_file(file: &Path) -> Fallible<Self> {
+        let src = read_to_string(file).with_context(LockFileError::for_file(file))?;
+        ToolchainLock::from_json(&src, file)
+    }
+
+    /// Deserializes a lock from the JSON string `src`, read from `file`.
+    fn from_json(src: &str, file: &Path) -> Fallible<Self> {
+        let serial: SerialLock =
+            serde_json::de::from_str(src).with_context(LockFileError::for_file(file))?;
+        let mut packages = Vec::new();
+        for package in serial.packages {
+            packages.push(LockedPackage {
+                name: package.name,
+                version: VersionSpec::parse_version(&package.version)?,
+                platform: match package.platform {
+                    Some(platform) => platform.into_image()?,
+                    None => None,
+                },
+            });
+        }
+        Ok(ToolchainLock {
+            platform: match serial.platform {
+                Some(platform) => platform.into_image()?,
+                None => None,
+            },
+            packages,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{LockedPackage, ToolchainLock};
+    use crate::platform::PlatformSpec;
+    use semver::Version;
+    use std::path::Path;
+
+    #[test]
+    fn test_round_trip() {
+        let lock = ToolchainLock {
+            platform: Some(PlatformSpec {
+                node_runtime: Version::parse("10.13.0").unwrap(),
+                npm: Some(Version::parse("6.4.1").unwrap()),
+                yarn: Some(Version::parse("1.12.3").unwrap()),
+                deno: None,
+            }),
+            packages: vec![
+                LockedPackage {
+                    name: "@angular/cli".to_string(),
+                    version: Version::parse("7.1.4").unwrap(),
+                    platform: Some(PlatformSpec {
+                        node_runtime: Version::parse("8.9.4").unwrap(),
+                        npm: Some(Version::parse("5.6.0").unwrap()),
+                        yarn: None,
+                        deno: None,
+                    }),
+                },
+                LockedPackage {
+                    name: "cowsay".to_string(),
+                    version: Version::parse("1.3.1").unwrap(),
+                    platform: None,
+                },
+            ],
+        };
+
+        let json = lock.to_json().expect("could not serialize lock");
+        let parsed =
+            ToolchainLock::from_json(&json, Path::new("lock.json")).expect("could not parse lock");
+        assert_eq!(parsed, lock);
+    }
+
+    #[test]
+    fn test_from_json_empty() {
+        let parsed =
+            ToolchainLock::from_json("{}", Path::new("lock.json")).expect("could not parse lock");
+        assert_eq!(parsed.platform, None);
+        assert_eq!(parsed.packages.len(), 0);
+    }
+
+    #[test]
+    fn test_from_json_invalid() {
+        let error = ToolchainLock::from_json("{\"packages\": 1}", Path::new("lock.json"))
+            .expect_err("should not parse lock");
+        assert!(error.is_user_friendly());
+        assert!(error
+            .to_string()
+            .starts_with("Could not read the toolchain lock lock.json"));
+    }
+}
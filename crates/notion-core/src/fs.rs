@@ -1,11 +1,16 @@
 //! Provides utilities for operating on the filesystem.
 
+use std::fmt;
 use std::fs::{self, create_dir_all, read_dir, DirEntry, File, Metadata};
 use std::io::{self, ErrorKind};
 use std::path::{Path, PathBuf};
 
 use failure::Fail;
+use verbatim::PathExt;
 
+use crate::config::Config;
+use crate::env::is_read_only;
+use crate::path;
 use notion_fail::{throw, ExitCode, FailExt, Fallible, NotionFail, ResultExt};
 use notion_fail_derive::*;
 
@@ -35,6 +40,211 @@ impl CreateDirError {
     }
 }
 
+/// Thrown when Notion lacks the permissions to write to a file or directory.
+#[derive(Debug, Fail)]
+pub(crate) struct PermissionError {
+    /// The path that could not be written.
+    pub(crate) path: PathBuf,
+    /// The closest ancestor of `path` that exists, which is usually the one whose
+    /// permissions need to change.
+    pub(crate) existing: Option<PathBuf>,
+    /// The owner of `existing`, if it could be determined.
+    pub(crate) owner: Option<String>,
+    /// Whether `existing` is in `NOTION_HOME` or the shim directory, which Notion expects
+    /// to own, so that taking ownership of it can safely be suggested.
+    pub(crate) managed: bool,
+}
+
+impl PermissionError {
+    pub(crate) fn for_path(path: &Path) -> impl FnOnce(&io::Error) -> PermissionError {
+        let path = path.to_path_buf();
+        move |_error| {
+            let existing = path.ancestors().find(|p| p.exists()).map(Path::to_path_buf);
+            let owner = existing.as_ref().and_then(|p| owner_of(p));
+            let managed = existing.as_ref().map_or(false, |existing| {
+                let dirs = [path::notion_home().ok(), path::shim_dir().ok()];
+                is_within(existing, dirs.iter().flatten())
+            });
+            PermissionError {
+                path,
+                existing,
+                owner,
+                managed,
+            }
+        }
+    }
+}
+
+/// Determines whether `path` is one of `dirs` or inside one of them.
+fn is_within<'a>(path: &Path, mut dirs: impl Iterator<Item = &'a PathBuf>) -> bool {
+    dirs.any(|dir| path.starts_with(dir))
+}
+
+impl NotionFail for PermissionError {
+    fn is_user_friendly(&self) -> bool {
+        true
+    }
+
+    fn exit_code(&self) -> ExitCode {
+        ExitCode::PermissionError
+    }
+
+    fn error_code(&self) -> &'static str {
+        "permission"
+    }
+}
+
+impl fmt::Display for PermissionError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "Permission denied writing to {}", self.path.display())?;
+
+        if let Some(ref existing) = self.existing {
+            match self.owner {
+                Some(ref owner) => {
+                    writeln!(fmt, "\n\n{} is owned by {}.", existing.display(), owner)?
+                }
+                None => writeln!(fmt)?,
+            }
+
+            if cfg!(unix) && self.managed {
+                writeln!(fmt, "\nTo take ownership of it, run:\n")?;
+                writeln!(fmt, "    sudo chown -R $(whoami) {}", existing.display())?;
+            } else {
+                writeln!(
+                    fmt,
+                    "\nMake sure your user account has write access to {}.",
+                    existing.display()
+                )?;
+            }
+        } else {
+            writeln!(fmt)?;
+        }
+
+        write!(
+            fmt,
+            "\nAlternatively, set NOTION_HOME to a directory you own, or see `notion help relocate` to move the shims."
+        )
+    }
+}
+
+/// Determines the name of the user that owns `path`.
+#[cfg(unix)]
+fn owner_of(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let uid = fs::metadata(path).ok()?.uid();
+
+    // look up the user name without binding to libc; fall back to the numeric id
+    let name = fs::read_to_string("/etc/passwd").ok().and_then(|passwd| {
+        passwd.lines().find_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?;
+            if id == uid.to_string() {
+                Some(name.to_string())
+            } else {
+                None
+            }
+        })
+    });
+
+    Some(name.unwrap_or_else(|| format!("uid {}", uid)))
+}
+
+/// Determines the name of the user that owns `path`.
+#[cfg(not(unix))]
+fn owner_of(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Thrown when a command would write to `NOTION_HOME` while it is read-only.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Cannot {} because NOTION_HOME is read-only\n\nUnset NOTION_READ_ONLY (or `layout.read_only` in the Notion configuration) to allow changes.",
+    action
+)]
+#[notion_fail(code = "PermissionError")]
+pub(crate) struct ReadOnlyHomeError {
+    pub(crate) action: String,
+}
+
+/// Determines whether `NOTION_HOME` is read-only, either because `NOTION_READ_ONLY` is set
+/// or because `layout.read_only` is enabled in the configuration.
+pub(crate) fn is_read_only_home() -> bool {
+    is_read_only()
+        || Config::current()
+            .map(|config| config.layout.read_only)
+            .unwrap_or(false)
+}
+
+/// Fails with a `ReadOnlyHomeError` describing `action` if `NOTION_HOME` is read-only.
+pub(crate) fn ensure_home_writable(action: &str) -> Fallible<()> {
+    if is_read_only_home() {
+        throw!(ReadOnlyHomeError {
+            action: action.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Converts a path to its verbatim form (`\\?\C:\...`) on Windows, which isn't subject
+/// to the legacy 260 byte `MAX_PATH` limit. Node images and `node_modules` trees
+/// easily exceed that limit, so filesystem operations on them should go through this.
+/// On other platforms, the path is returned unchanged.
+pub fn long_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    path.as_ref().to_verbatim()
+}
+
+/// Creates a directory and any missing parent directories, diagnosing failures that
+/// are due to insufficient permissions. Directories created in the shared directory are
+/// made writable by the other users of the machine (see `share_created_dirs`).
+pub fn ensure_dir_exists(dir: &Path) -> Fallible<()> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    match fs::create_dir_all(long_path(dir)) {
+        Ok(()) => {
+            share_created_dirs(dir);
+            Ok(())
+        }
+        Err(error) => {
+            if error.kind() == ErrorKind::PermissionDenied {
+                throw!(error.with_context(PermissionError::for_path(dir)));
+            }
+            let dir_name = dir.to_string_lossy().to_string();
+            throw!(error.with_context(CreateDirError::for_dir(dir_name)));
+        }
+    }
+}
+
+/// Makes `dir` and its ancestors up to the shared directory group-writable, with the
+/// setgid bit so that everything created in them belongs to the same group, if `dir` is
+/// in the shared directory. Directories owned by other users are left as they are, since
+/// whoever created them has already shared them.
+#[cfg(unix)]
+fn share_created_dirs(dir: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let shared = match path::shared_dir() {
+        Ok(Some(shared)) => shared,
+        _ => return,
+    };
+    for ancestor in dir.ancestors().take_while(|dir| dir.starts_with(&shared)) {
+        if let Ok(metadata) = fs::metadata(ancestor) {
+            let mut permissions = metadata.permissions();
+            let mode = permissions.mode();
+            if mode & 0o2070 != 0o2070 {
+                permissions.set_mode(mode | 0o2070);
+                let _ = fs::set_permissions(ancestor, permissions);
+            }
+        }
+    }
+}
+
+/// On Windows, the permissions of the shared directory are inherited by its contents.
+#[cfg(windows)]
+fn share_created_dirs(_dir: &Path) {}
+
 #[derive(Debug, Fail, NotionFail)]
 #[fail(display = "`path` internal error")]
 #[notion_fail(code = "UnknownError")]
@@ -43,8 +253,7 @@ pub(crate) struct PathInternalError;
 /// This creates the parent directory of the input path, assuming the input path is a file.
 pub fn ensure_containing_dir_exists<P: AsRef<Path>>(path: &P) -> Fallible<()> {
     if let Some(dir) = path.as_ref().parent() {
-        fs::create_dir_all(dir)
-            .with_context(CreateDirError::for_dir(dir.to_string_lossy().to_string()))
+        ensure_dir_exists(dir)
     } else {
         // this was called for a file with no parent directory
         throw!(PathInternalError.unknown());
@@ -85,3 +294,67 @@ pub fn read_dir_eager(dir: &Path) -> Fallible<impl Iterator<Item = (DirEntry, Me
         .collect::<Fallible<Vec<(DirEntry, Metadata)>>>()?
         .into_iter())
 }
+
+/// Computes the total size of the files under `path` (or of `path` itself, if it is a
+/// file). Entries that can't be read are counted as empty.
+pub(crate) fn dir_size(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(ref metadata) if metadata.is_dir() => read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| dir_size(&entry.path()))
+                    .sum()
+            })
+            .unwrap_or(0),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    fn permission_error(existing: &str, managed: bool) -> PermissionError {
+        PermissionError {
+            path: PathBuf::from(existing).join("bin"),
+            existing: Some(PathBuf::from(existing)),
+            owner: Some("root".to_string()),
+            managed,
+        }
+    }
+
+    #[test]
+    fn test_is_within() {
+        let dirs = vec![
+            PathBuf::from("/home/user/.notion"),
+            PathBuf::from("/opt/shims"),
+        ];
+        assert!(is_within(Path::new("/home/user/.notion"), dirs.iter()));
+        assert!(is_within(
+            Path::new("/home/user/.notion/tools"),
+            dirs.iter()
+        ));
+        assert!(is_within(Path::new("/opt/shims"), dirs.iter()));
+        assert!(!is_within(Path::new("/home/user"), dirs.iter()));
+        assert!(!is_within(Path::new("/usr/local"), dirs.iter()));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_permission_error_suggests_chown_for_notion_dirs() {
+        let message = permission_error("/home/user/.notion", true).to_string();
+        assert!(message.contains("/home/user/.notion is owned by root."));
+        assert!(message.contains("sudo chown -R $(whoami) /home/user/.notion"));
+    }
+
+    #[test]
+    fn test_permission_error_omits_chown_for_other_dirs() {
+        let message = permission_error("/usr/local", false).to_string();
+        assert!(message.contains("/usr/local is owned by root."));
+        assert!(!message.contains("chown"));
+        assert!(message.contains("Make sure your user account has write access to /usr/local."));
+    }
+}
@@ -0,0 +1,522 @@
+//! Provides the automatic garbage collection of the inventory.
+//!
+//! When `gc.after_installs` or `gc.max_size_mb` is set in `config.toml`, Notion removes
+//! the fetched versions that are no longer needed after an install: every
+//! `after_installs` installs, all of them, and whenever the inventory and images take
+//! more than `max_size_mb`, the least recently fetched ones until they fit again. The
+//! versions pinned by the user toolchain and the current project, the Node versions that
+//! installed packages run with, and the `keep_recent` most recently fetched versions of
+//! each tool, are never removed.
+//!
+//! Each removal is reported, and `NOTION_NO_GC` skips the collection for a single
+//! command. A shared inventory is never collected automatically, since the versions the
+//! other users of the machine rely on can't be known.
+//!
+//! Versions are also removed on demand, one at a time by `notion uninstall` or all of
+//! the unneeded ones by `notion cache prune`, which keep the pinned versions as well
+//! unless forced.
+
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use failure::Fail;
+use semver::Version;
+
+use crate::disk::Size;
+use crate::distro::deno::DenoKind;
+use crate::distro::kind::{ArchiveDistro, ToolKind};
+use crate::distro::node::NodeKind;
+use crate::distro::npm::NpmKind;
+use crate::distro::yarn::YarnKind;
+use crate::env::is_gc_disabled;
+use crate::fs::{dir_size, ensure_containing_dir_exists, ensure_home_writable, is_read_only_home};
+use crate::lock::LockFile;
+use crate::package;
+use crate::path;
+use crate::platform::PlatformSpec;
+use crate::session::Session;
+use crate::tool::ToolSpec;
+use crate::version::VersionSpec;
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+/// How many of the most recently fetched versions of each tool are kept by default.
+const DEFAULT_KEEP_RECENT: u32 = 1;
+
+/// Thrown when uninstalling a version that the user toolchain or current project uses.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "{} v{} is {}\n\nUse `--force` to remove it anyway; it will be fetched again the next time it is used.",
+    tool, version, user
+)]
+#[notion_fail(code = "InvalidArguments")]
+struct VersionInUseError {
+    tool: &'static str,
+    version: Version,
+    user: &'static str,
+}
+
+/// Thrown when uninstalling a version that isn't in the inventory.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "{} v{} is not fetched\n\nSee `notion list` for the versions in the inventory.",
+    tool, version
+)]
+#[notion_fail(code = "NoVersionMatch")]
+struct NotFetchedError {
+    tool: &'static str,
+    version: Version,
+}
+
+/// Thrown when uninstalling something other than an exact version of Node, Yarn, npm or Deno.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Cannot uninstall {}\n\nUninstall an exact version of node, yarn, npm or deno, e.g. `notion uninstall node@10.13.0`.",
+    toolspec
+)]
+#[notion_fail(code = "InvalidArguments")]
+struct UninstallSpecError {
+    toolspec: String,
+}
+
+/// A version that was removed from the inventory.
+#[derive(Debug, PartialEq)]
+pub struct Removed {
+    pub tool: &'static str,
+    pub version: Version,
+    /// The number of bytes freed.
+    pub size: u64,
+}
+
+impl Display for Removed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} v{} ({})", self.tool, self.version, Size(self.size))
+    }
+}
+
+/// A version in the inventory that may be removed.
+#[derive(Debug, PartialEq, Clone)]
+struct Candidate {
+    tool: &'static str,
+    version: Version,
+    fetched: SystemTime,
+    size: u64,
+}
+
+/// Counts an install towards the next collection, if collecting after a number of
+/// installs is configured.
+pub(crate) fn record_install(session: &Session) -> Fallible<()> {
+    if session.config()?.gc.after_installs.is_none() || is_read_only_home() {
+        return Ok(());
+    }
+    let file = path::gc_install_count_file()?;
+    ensure_containing_dir_exists(&file)?;
+    fs::write(&file, (install_count()? + 1).to_string()).unknown()
+}
+
+fn install_count() -> Fallible<u32> {
+    Ok(fs::read_to_string(path::gc_install_count_file()?)
+        .ok()
+        .and_then(|count| count.trim().parse().ok())
+        .unwrap_or(0))
+}
+
+/// Runs the configured collection, if it is due. Returns the removed versions.
+pub fn collect_if_due(session: &mut Session) -> Fallible<Vec<Removed>> {
+    let config = &session.config()?.gc;
+    if !config.is_enabled() || is_gc_disabled() || is_read_only_home() {
+        return Ok(Vec::new());
+    }
+    if path::shared_dir()?.is_some() {
+        return Ok(Vec::new());
+    }
+    let after_installs = config.after_installs;
+    let max_size = config.max_size_mb.map(|mb| mb * 1024 * 1024);
+    let keep_recent = config.keep_recent.unwrap_or(DEFAULT_KEEP_RECENT) as usize;
+
+    let prune = match after_installs {
+        Some(after) => install_count()? >= after,
+        None => false,
+    };
+    let excess = match max_size {
+        Some(max) => {
+            let used = dir_size(&path::inventory_dir()?) + dir_size(&path::image_dir()?);
+            used.saturating_sub(max)
+        }
+        None => 0,
+    };
+    if !prune && excess == 0 {
+        return Ok(Vec::new());
+    }
+
+    let _lock = lock(session)?;
+    let pinned = pinned(session)?;
+    let candidates = candidates(session)?;
+    let planned = plan(
+        candidates,
+        Order::Fetched,
+        keep_recent,
+        &pinned,
+        if prune { None } else { Some(excess) },
+    );
+
+    let mut removed = Vec::new();
+    for candidate in planned {
+        removed.push(remove(session, candidate)?);
+    }
+    if prune {
+        let file = path::gc_install_count_file()?;
+        if file.exists() {
+            fs::remove_file(file).unknown()?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Removes an exact version of a tool from the inventory, unless it is used by the user
+/// toolchain or the current project and `force` isn't given.
+pub fn uninstall(session: &mut Session, toolspec: &ToolSpec, force: bool) -> Fallible<Removed> {
+    let (tool, version) = match toolspec {
+        ToolSpec::Node(VersionSpec::Exact(version)) => (NodeKind::DISPLAY_NAME, version),
+        ToolSpec::Yarn(VersionSpec::Exact(version)) => (YarnKind::DISPLAY_NAME, version),
+        ToolSpec::Deno(VersionSpec::Exact(version)) => (DenoKind::DISPLAY_NAME, version),
+        ToolSpec::Npm(VersionSpec::Exact(version)) => (NpmKind::DISPLAY_NAME, version),
+        _ => throw!(UninstallSpecError {
+            toolspec: toolspec.to_string(),
+        }),
+    };
+    ensure_home_writable(&format!("uninstall {} v{}", tool, version))?;
+
+    let _lock = lock(session)?;
+    let fetched = {
+        let inventory = session.inventory()?;
+        match tool {
+            tool if tool == NodeKind::DISPLAY_NAME => inventory.node.contains(version),
+            tool if tool == YarnKind::DISPLAY_NAME => inventory.yarn.contains(version),
+            tool if tool == NpmKind::DISPLAY_NAME => inventory.npm.contains(version),
+            _ => inventory.deno.contains(version),
+        }
+    };
+    if !fetched {
+        throw!(NotFetchedError {
+            tool,
+            version: version.clone(),
+        });
+    }
+    if !force {
+        let users = vec![
+            (
+                session.user_platform()?,
+                "the default in the user toolchain",
+            ),
+            (session.project_platform()?, "pinned by the current project"),
+        ];
+        for (platform, user) in users {
+            let pinned = platform.map(|platform| platform_versions(&platform));
+            if pinned.map_or(false, |pinned| pinned.contains(&(tool, version.clone()))) {
+                throw!(VersionInUseError {
+                    tool,
+                    version: version.clone(),
+                    user,
+                });
+            }
+        }
+    }
+
+    remove(
+        session,
+        Candidate {
+            tool,
+            version: version.clone(),
+            fetched: UNIX_EPOCH,
+            size: 0,
+        },
+    )
+}
+
+/// Removes every version in the inventory but the `keep_latest` highest of each tool and
+/// (unless `force` is given) the versions used by the user toolchain and the current
+/// project. Returns the removed versions.
+pub fn prune(session: &mut Session, keep_latest: usize, force: bool) -> Fallible<Vec<Removed>> {
+    ensure_home_writable("prune the inventory")?;
+
+    let _lock = lock(session)?;
+    let pinned = if force { Vec::new() } else { pinned(session)? };
+    let candidates = candidates(session)?;
+    let planned = plan(candidates, Order::Version, keep_latest, &pinned, None);
+
+    let mut removed = Vec::new();
+    for candidate in planned {
+        removed.push(remove(session, candidate)?);
+    }
+    Ok(removed)
+}
+
+/// The versions pinned by the user toolchain and the current project, and the Node
+/// versions of the installed packages.
+fn pinned(session: &Session) -> Fallible<Vec<(&'static str, Version)>> {
+    let mut pinned = Vec::new();
+    let platforms = vec![session.user_platform()?, session.project_platform()?];
+    for platform in platforms.into_iter().flatten() {
+        pinned.extend(platform_versions(&platform));
+    }
+    for package in package::installed()? {
+        pinned.push((NodeKind::DISPLAY_NAME, package.platform.node_runtime));
+    }
+    Ok(pinned)
+}
+
+/// The versions of each tool in `platform`.
+fn platform_versions(platform: &PlatformSpec) -> Vec<(&'static str, Version)> {
+    let mut versions = vec![(NodeKind::DISPLAY_NAME, platform.node_runtime.clone())];
+    if let Some(ref yarn) = platform.yarn {
+        versions.push((YarnKind::DISPLAY_NAME, yarn.clone()));
+    }
+    if let Some(ref deno) = platform.deno {
+        versions.push((DenoKind::DISPLAY_NAME, deno.clone()));
+    }
+    if let Some(ref npm) = platform.npm {
+        versions.push((NpmKind::DISPLAY_NAME, npm.clone()));
+    }
+    versions
+}
+
+/// The versions in the inventory, with when they were fetched and their size.
+fn candidates(session: &Session) -> Fallible<Vec<Candidate>> {
+    let inventory = session.inventory()?;
+    let mut candidates = Vec::new();
+    for version in &inventory.node.versions {
+        // versions built from source or installed from a local build have no archive,
+        // only their marker
+        let source_marker = path::node_source_build_file(&version.to_string())?;
+        let local_marker = path::node_local_build_file(&version.to_string())?;
+        let fetched = fetched_at(&ArchiveDistro::<NodeKind>::distro_file(version)?)
+            .or_else(|| fetched_at(&source_marker))
+            .or_else(|| fetched_at(&local_marker))
+            .unwrap_or(UNIX_EPOCH);
+        candidates.push(Candidate {
+            tool: NodeKind::DISPLAY_NAME,
+            version: version.clone(),
+            fetched,
+            size: size_of::<NodeKind>(version)?,
+        });
+    }
+    for version in &inventory.yarn.versions {
+        candidates.push(candidate::<YarnKind>(version)?);
+    }
+    for version in &inventory.deno.versions {
+        candidates.push(candidate::<DenoKind>(version)?);
+    }
+    for version in &inventory.npm.versions {
+        candidates.push(candidate::<NpmKind>(version)?);
+    }
+    Ok(candidates)
+}
+
+fn candidate<K: ToolKind>(version: &Version) -> Fallible<Candidate> {
+    Ok(Candidate {
+        tool: K::DISPLAY_NAME,
+        version: version.clone(),
+        fetched: fetched_at(&ArchiveDistro::<K>::distro_file(version)?).unwrap_or(UNIX_EPOCH),
+        size: size_of::<K>(version)?,
+    })
+}
+
+fn fetched_at(file: &Path) -> Option<SystemTime> {
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn size_of<K: ToolKind>(version: &Version) -> Fallible<u64> {
+    Ok(dir_size(&ArchiveDistro::<K>::distro_file(version)?) + dir_size(&K::image_dir(version)?))
+}
+
+/// What makes a version more recent than another when selecting the ones to keep.
+#[derive(Debug, Clone, Copy)]
+enum Order {
+    /// When it was fetched.
+    Fetched,
+    /// Its version number.
+    Version,
+}
+
+/// Selects the candidates to remove, least recent first: all of the ones that aren't
+/// kept, or, if `excess` bytes are to be freed, only as many as needed.
+fn plan(
+    mut candidates: Vec<Candidate>,
+    order: Order,
+    keep_recent: usize,
+    pinned: &[(&'static str, Version)],
+    excess: Option<u64>,
+) -> Vec<Candidate> {
+    // newest first, so that the most recent of each tool are seen first
+    match order {
+        Order::Fetched => candidates.sort_by(|a, b| b.fetched.cmp(&a.fetched)),
+        Order::Version => candidates.sort_by(|a, b| b.version.cmp(&a.version)),
+    }
+
+    let mut seen: Vec<&'static str> = Vec::new();
+    let mut removable: Vec<Candidate> = Vec::new();
+    for candidate in candidates {
+        let recent = seen.iter().filter(|tool| **tool == candidate.tool).count() < keep_recent;
+        seen.push(candidate.tool);
+        let is_pinned = pinned
+            .iter()
+            .any(|(tool, version)| *tool == candidate.tool && *version == candidate.version);
+        if !recent && !is_pinned {
+            removable.push(candidate);
+        }
+    }
+    removable.reverse();
+
+    match excess {
+        None => removable,
+        Some(excess) => {
+            let mut freed = 0;
+            removable
+                .into_iter()
+                .take_while(|candidate| {
+                    let needed = freed < excess;
+                    freed += candidate.size;
+                    needed
+                })
+                .collect()
+        }
+    }
+}
+
+/// Locks the inventory while versions are removed from it. The inventory is loaded before
+/// the lock is taken, since loading it recovers any interrupted fetch under the same lock.
+fn lock(session: &mut Session) -> Fallible<Option<LockFile>> {
+    session.inventory_mut()?.lock()
+}
+
+fn remove(session: &mut Session, candidate: Candidate) -> Fallible<Removed> {
+    let version = candidate.version;
+    let inventory = session.inventory_mut()?;
+    let size = match candidate.tool {
+        tool if tool == NodeKind::DISPLAY_NAME => {
+            let size = ArchiveDistro::<NodeKind>::remove(&version)?;
+            for file in &[
+                path::node_npm_version_file(&version.to_string())?,
+                path::node_source_build_file(&version.to_string())?,
+                path::node_local_build_file(&version.to_string())?,
+            ] {
+                if file.exists() {
+                    fs::remove_file(file).unknown()?;
+                }
+            }
+            inventory.node.versions.remove(&version);
+            size
+        }
+        tool if tool == YarnKind::DISPLAY_NAME => {
+            inventory.yarn.versions.remove(&version);
+            ArchiveDistro::<YarnKind>::remove(&version)?
+        }
+        tool if tool == NpmKind::DISPLAY_NAME => {
+            inventory.npm.versions.remove(&version);
+            ArchiveDistro::<NpmKind>::remove(&version)?
+        }
+        _ => {
+            inventory.deno.versions.remove(&version);
+            ArchiveDistro::<DenoKind>::remove(&version)?
+        }
+    };
+    Ok(Removed {
+        tool: candidate.tool,
+        version,
+        size,
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{plan, Candidate, Order};
+    use semver::Version;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn candidate(tool: &'static str, version: &str, day: u64) -> Candidate {
+        Candidate {
+            tool,
+            version: Version::parse(version).unwrap(),
+            fetched: UNIX_EPOCH + Duration::from_secs(day * 24 * 60 * 60),
+            size: 100,
+        }
+    }
+
+    fn versions(planned: Vec<Candidate>) -> Vec<String> {
+        planned
+            .into_iter()
+            .map(|candidate| format!("{}@{}", candidate.tool, candidate.version))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan() {
+        let candidates = vec![
+            candidate("Node", "8.9.4", 1),
+            candidate("Node", "10.13.0", 3),
+            candidate("Node", "6.11.1", 2),
+            candidate("Node", "11.6.0", 4),
+            candidate("Yarn", "1.12.3", 0),
+        ];
+        let pinned = vec![("Node", Version::parse("6.11.1").unwrap())];
+
+        assert_eq!(
+            versions(plan(candidates.clone(), Order::Fetched, 1, &pinned, None)),
+            vec!["Node@8.9.4", "Node@10.13.0"]
+        );
+        assert_eq!(
+            versions(plan(
+                candidates.clone(),
+                Order::Fetched,
+                0,
+                &pinned,
+                Some(150)
+            )),
+            vec!["Yarn@1.12.3", "Node@8.9.4"]
+        );
+        assert_eq!(
+            versions(plan(
+                candidates.clone(),
+                Order::Fetched,
+                1,
+                &pinned,
+                Some(50)
+            )),
+            vec!["Node@8.9.4"]
+        );
+        assert!(plan(candidates, Order::Fetched, 5, &[], None).is_empty());
+    }
+
+    #[test]
+    fn test_plan_by_version() {
+        let candidates = vec![
+            candidate("Node", "10.13.0", 1),
+            candidate("Node", "8.9.4", 4),
+            candidate("Node", "11.6.0", 2),
+            candidate("Node", "6.11.1", 3),
+            candidate("Yarn", "1.12.3", 0),
+        ];
+        let pinned = vec![("Node", Version::parse("6.11.1").unwrap())];
+
+        assert_eq!(
+            versions(plan(candidates.clone(), Order::Version, 2, &pinned, None)),
+            vec!["Node@8.9.4"]
+        );
+        assert_eq!(
+            versions(plan(candidates, Order::Version, 0, &[], None)),
+            vec![
+                "Yarn@1.12.3",
+                "Node@6.11.1",
+                "Node@8.9.4",
+                "Node@10.13.0",
+                "Node@11.6.0"
+            ]
+        );
+    }
+}
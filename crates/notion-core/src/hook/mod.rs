@@ -6,6 +6,7 @@ use std::str::FromStr;
 use lazycell::LazyCell;
 use toml;
 
+use crate::distro::deno::DenoDistro;
 use crate::distro::node::NodeDistro;
 use crate::distro::yarn::YarnDistro;
 use crate::distro::Distro;
@@ -50,7 +51,9 @@ impl LazyHookConfig {
 pub struct HookConfig {
     pub node: Option<ToolHooks<NodeDistro>>,
     pub yarn: Option<ToolHooks<YarnDistro>>,
+    pub deno: Option<ToolHooks<DenoDistro>>,
     pub events: Option<EventHooks>,
+    pub registry: Option<RegistryHooks>,
 }
 
 /// Notion hooks for an individual tool
@@ -89,10 +92,18 @@ pub struct EventHooks {
     pub publish: Option<Publish>,
 }
 
+/// Notion hooks related to the npm registry.
+#[derive(PartialEq, Debug)]
+pub struct RegistryHooks {
+    /// The registry that unscoped tool packages are resolved against, overriding the
+    /// `registry` setting of `.npmrc` (but not `NPM_CONFIG_REGISTRY`), if any.
+    pub url: Option<String>,
+}
+
 #[cfg(test)]
 pub mod tests {
 
-    use super::{tool, HookConfig, Publish};
+    use super::{tool, HookConfig, Publish, RegistryHooks};
     use std::fs;
     use std::path::PathBuf;
 
@@ -119,6 +130,24 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_from_str_registry() {
+        let fixture_dir = fixture_path("hooks");
+        let mut url_file = fixture_dir.clone();
+
+        url_file.push("registry.toml");
+        let hooks: HookConfig = fs::read_to_string(url_file)
+            .expect("Chould not read registry.toml")
+            .parse()
+            .expect("Could not parse registry.toml");
+        assert_eq!(
+            hooks.registry,
+            Some(RegistryHooks {
+                url: Some("https://npm.example.com/".to_string())
+            })
+        );
+    }
+
     #[test]
     fn test_from_str_bins() {
         let fixture_dir = fixture_path("hooks");
@@ -1,6 +1,7 @@
 use super::tool;
 use std::marker::PhantomData;
 
+use crate::distro::deno::DenoDistro;
 use crate::distro::node::NodeDistro;
 use crate::distro::yarn::YarnDistro;
 use crate::distro::Distro;
@@ -110,7 +111,9 @@ impl PublishHook {
 pub struct HookConfig {
     pub node: Option<ToolHooks<NodeDistro>>,
     pub yarn: Option<ToolHooks<YarnDistro>>,
+    pub deno: Option<ToolHooks<DenoDistro>>,
     pub events: Option<EventHooks>,
+    pub registry: Option<RegistryHooks>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -131,6 +134,18 @@ impl EventHooks {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "registry")]
+pub struct RegistryHooks {
+    pub url: Option<String>,
+}
+
+impl RegistryHooks {
+    pub fn into_registry_hooks(self) -> super::RegistryHooks {
+        super::RegistryHooks { url: self.url }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename = "tool")]
 pub struct ToolHooks<I> {
@@ -155,11 +170,17 @@ impl HookConfig {
             } else {
                 None
             },
+            deno: if let Some(d) = self.deno {
+                Some(d.into_tool_hooks()?)
+            } else {
+                None
+            },
             events: if let Some(e) = self.events {
                 Some(e.into_event_hooks()?)
             } else {
                 None
             },
+            registry: self.registry.map(|r| r.into_registry_hooks()),
         })
     }
 }
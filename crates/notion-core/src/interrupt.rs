@@ -0,0 +1,189 @@
+//! Provides the cleanup of long-running operations interrupted with Ctrl-C.
+//!
+//! A process interrupted by `SIGINT` exits without running any destructors, which would
+//! leave a partial archive in the inventory, a half-moved image, the staging directory
+//! and the inventory lock behind. Instead, operations register the files and
+//! directories they are creating for as long as they are incomplete, and the interrupt
+//! handler removes all of them (in the reverse order of registration) before exiting,
+//! leaving the inventory exactly as it was before the operation started.
+//!
+//! While a tool runs as a child process, the interrupt is left to the tool, which
+//! receives it as well, so that e.g. the Node REPL can handle Ctrl-C itself.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
+
+use ctrlc;
+use lazy_static::lazy_static;
+
+use crate::fs::long_path;
+use crate::output;
+
+/// The exit code of a process interrupted by `SIGINT`, by shell convention.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// A file or directory to remove if the operation creating it is interrupted, or a file
+/// to put back if the operation replacing it is.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Cleanup {
+    RemoveFile(PathBuf),
+    RemoveDir(PathBuf),
+    /// Moves `backup` back in place of `target`, if it exists.
+    RestoreFile {
+        backup: PathBuf,
+        target: PathBuf,
+    },
+}
+
+impl Cleanup {
+    fn run(&self) {
+        // each cleanup is attempted regardless of the others failing
+        let _ = match self {
+            Cleanup::RemoveFile(file) => fs::remove_file(file),
+            Cleanup::RemoveDir(dir) => fs::remove_dir_all(long_path(dir)),
+            Cleanup::RestoreFile { backup, target } if backup.exists() => {
+                fs::rename(backup, target)
+            }
+            Cleanup::RestoreFile { .. } => Ok(()),
+        };
+    }
+}
+
+lazy_static! {
+    /// The cleanups of the operations in progress, by registration id.
+    static ref PENDING: Mutex<Vec<(usize, Vec<Cleanup>)>> = Mutex::new(Vec::new());
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of child processes running that the interrupt is left to.
+static CHILDREN: AtomicUsize = AtomicUsize::new(0);
+
+static INSTALL: Once = Once::new();
+
+/// The cleanups registered by an operation, which are forgotten once it is dropped (when
+/// the operation has completed, or failed and cleaned up after itself).
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct Registration {
+    id: usize,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = PENDING.lock() {
+            pending.retain(|&(id, _)| id != self.id);
+        }
+    }
+}
+
+/// Registers `cleanups` to be run if the process is interrupted before the returned
+/// registration is dropped.
+pub(crate) fn register(cleanups: Vec<Cleanup>) -> Registration {
+    install_handler();
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.push((id, cleanups));
+    }
+    Registration { id }
+}
+
+/// Marks a child process as running until the returned guard is dropped, so that an
+/// interrupt is left to the child.
+#[must_use]
+pub struct ChildGuard {
+    _private: (),
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        CHILDREN.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Leaves interrupts to the child process started next, until the guard is dropped.
+pub fn defer_to_child() -> ChildGuard {
+    install_handler();
+    CHILDREN.fetch_add(1, Ordering::SeqCst);
+    ChildGuard { _private: () }
+}
+
+fn install_handler() {
+    INSTALL.call_once(|| {
+        // without a handler, interrupts keep their default behavior
+        let _ = ctrlc::set_handler(handle);
+    });
+}
+
+fn handle() {
+    if CHILDREN.load(Ordering::SeqCst) > 0 {
+        return;
+    }
+
+    let pending = match PENDING.lock() {
+        Ok(mut pending) => pending.drain(..).collect(),
+        Err(_) => Vec::new(),
+    };
+    if !pending.is_empty() {
+        output::info("\nInterrupted; cleaning up");
+    }
+    for cleanup in ordered(pending) {
+        cleanup.run();
+    }
+    process::exit(INTERRUPTED_EXIT_CODE);
+}
+
+/// Orders the registered cleanups so that the latest registered run first.
+fn ordered(pending: Vec<(usize, Vec<Cleanup>)>) -> Vec<Cleanup> {
+    pending
+        .into_iter()
+        .rev()
+        .flat_map(|(_, cleanups)| cleanups.into_iter().rev())
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{ordered, Cleanup};
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_ordered() {
+        let lock = Cleanup::RemoveFile(PathBuf::from("inventory.lock"));
+        let archive = Cleanup::RemoveFile(PathBuf::from("node-v10.13.0.tar.gz"));
+        let staging = Cleanup::RemoveDir(PathBuf::from("staging/tmp1234"));
+        assert_eq!(
+            ordered(vec![
+                (0, vec![lock.clone()]),
+                (1, vec![archive.clone(), staging.clone()]),
+            ]),
+            vec![staging, archive, lock]
+        );
+    }
+
+    #[test]
+    fn test_restore_file() {
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("notion");
+        let backup = dir.path().join("notion.old");
+        fs::write(&backup, "old").unwrap();
+
+        let restore = Cleanup::RestoreFile {
+            backup: backup.clone(),
+            target: target.clone(),
+        };
+        restore.run();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+        assert!(!backup.exists());
+
+        // without a backup, the target is left as it is
+        restore.run();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+    }
+}
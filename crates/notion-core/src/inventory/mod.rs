@@ -2,9 +2,11 @@
 //! of available tool versions.
 
 use std::collections::{BTreeSet, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::Write;
 use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::string::ToString;
 use std::time::{Duration, SystemTime};
@@ -12,23 +14,46 @@ use std::time::{Duration, SystemTime};
 use failure::Fail;
 use lazycell::LazyCell;
 use reqwest;
-use reqwest::header::{CacheControl, CacheDirective, Expires, HttpDate};
+use reqwest::header::{
+    CacheControl, CacheDirective, ETag, Expires, HttpDate, IfModifiedSince, IfNoneMatch,
+    LastModified,
+};
+use reqwest::StatusCode;
 use serde_json;
 use tempfile::NamedTempFile;
 
-use crate::distro::node::NodeDistro;
-use crate::distro::yarn::YarnDistro;
+use crate::alias;
+use crate::checksum;
+use crate::config::Config;
+use crate::credentials;
+use crate::diagnostics::{Finding, Fix};
+use crate::distro::deno::{DenoDistro, DenoKind};
+use crate::distro::download::{self, Download};
+use crate::distro::kind::{ArchiveDistro, ToolKind};
+use crate::distro::local::{self, LocalBuild};
+use crate::distro::node::{NodeDistro, NodeKind};
+use crate::distro::npm::{NpmDistro, NpmKind};
+use crate::distro::source;
+use crate::distro::yarn::{YarnDistro, YarnKind};
 use crate::distro::{Distro, DistroVersion, Fetched};
-use crate::fs::{ensure_containing_dir_exists, read_file_opt};
+use crate::env::is_offline;
+use crate::fs::{
+    ensure_containing_dir_exists, ensure_home_writable, is_read_only_home, read_dir_eager,
+    read_file_opt,
+};
 use crate::hook::{HookConfig, ToolHooks};
+use crate::journal;
+use crate::lock::LockFile;
 use crate::path;
-use crate::style::progress_spinner;
+use crate::retry::RetryPolicy;
+use crate::style::{display_warning, progress_spinner, Progress};
 use crate::tool::ToolSpec;
 use crate::version::VersionSpec;
 use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
 use notion_fail_derive::*;
 use semver::{Version, VersionReq};
 
+pub mod query;
 pub(crate) mod serial;
 
 #[cfg(feature = "mock-network")]
@@ -37,28 +62,50 @@ use mockito;
 // ISSUE (#86): Move public repository URLs to config file
 cfg_if::cfg_if! {
     if #[cfg(feature = "mock-network")] {
-        fn public_node_version_index() -> String {
+        pub(crate) fn public_node_version_index() -> String {
             format!("{}/node-dist/index.json", mockito::SERVER_URL)
         }
-        fn public_yarn_version_index() -> String {
+        pub(crate) fn public_yarn_version_index() -> String {
             format!("{}/yarn-releases/index.json", mockito::SERVER_URL)
         }
-        fn public_yarn_latest_version() -> String {
+        pub(crate) fn public_yarn_latest_version() -> String {
             format!("{}/yarn-latest", mockito::SERVER_URL)
         }
+        pub(crate) fn public_deno_version_index() -> String {
+            format!("{}/deno-releases/index.json", mockito::SERVER_URL)
+        }
+        pub(crate) fn public_deno_latest_version() -> String {
+            format!("{}/deno-releases/latest", mockito::SERVER_URL)
+        }
+        pub(crate) fn public_npm_version_index() -> String {
+            format!("{}/npm-registry/npm", mockito::SERVER_URL)
+        }
     } else {
         /// Returns the URL of the index of available Node versions on the public Node server.
-        fn public_node_version_index() -> String {
+        pub(crate) fn public_node_version_index() -> String {
             "https://nodejs.org/dist/index.json".to_string()
         }
         /// Return the URL of the index of available Yarn versions on the public git repository.
-        fn public_yarn_version_index() -> String {
+        pub(crate) fn public_yarn_version_index() -> String {
             "https://api.github.com/repos/yarnpkg/yarn/releases".to_string()
         }
         /// URL of the latest Yarn version on the public yarnpkg.com
-        fn public_yarn_latest_version() -> String {
+        pub(crate) fn public_yarn_latest_version() -> String {
             "https://yarnpkg.com/latest-version".to_string()
         }
+        /// Returns the URL of the index of Deno releases on the public git repository.
+        pub(crate) fn public_deno_version_index() -> String {
+            "https://api.github.com/repos/denoland/deno/releases".to_string()
+        }
+        /// Returns the URL of the latest Deno release on the public git repository.
+        pub(crate) fn public_deno_latest_version() -> String {
+            "https://api.github.com/repos/denoland/deno/releases/latest".to_string()
+        }
+        /// Returns the URL of the metadata of the npm package on the public npm registry,
+        /// which lists its versions and dist-tags.
+        pub(crate) fn public_npm_version_index() -> String {
+            "https://registry.npmjs.org/npm".to_string()
+        }
     }
 }
 
@@ -95,19 +142,55 @@ pub struct Collection<D: Distro> {
 
 pub type NodeCollection = Collection<NodeDistro>;
 pub type YarnCollection = Collection<YarnDistro>;
+pub type DenoCollection = Collection<DenoDistro>;
+pub type NpmCollection = Collection<NpmDistro>;
 
 /// The inventory of locally available tool versions.
 pub struct Inventory {
     pub node: NodeCollection,
     pub yarn: YarnCollection,
+    pub deno: DenoCollection,
+    pub npm: NpmCollection,
 }
 
 impl Inventory {
     /// Returns the current inventory.
     fn current() -> Fallible<Inventory> {
+        // fetches interrupted by a crash would otherwise leave versions that look fetched
+        journal::recover()?;
+        Inventory::load()
+    }
+
+    fn load() -> Fallible<Inventory> {
+        let mut node = NodeCollection::load()?;
+        // versions built from source or installed from a local build have no archive in
+        // the inventory
+        node.versions.extend(source::built_versions()?);
+        node.versions.extend(local::provided_versions()?);
+
         Ok(Inventory {
-            node: NodeCollection::load()?,
+            node,
             yarn: YarnCollection::load()?,
+            deno: DenoCollection::load()?,
+            npm: NpmCollection::load()?,
+        })
+    }
+
+    /// Resolves the version of a Tool matching the specified semantic versioning
+    /// requirements, without fetching it. Packages are resolved as they are installed.
+    pub fn resolve(&self, toolspec: &ToolSpec, hooks: &HookConfig) -> Fallible<ToolSpec> {
+        Ok(match toolspec {
+            ToolSpec::Node(version) => {
+                NodeKind::tool_spec(&self.node.resolve_version(version, hooks.node.as_ref())?)
+            }
+            ToolSpec::Yarn(version) => {
+                YarnKind::tool_spec(&self.yarn.resolve_version(version, hooks.yarn.as_ref())?)
+            }
+            ToolSpec::Deno(version) => {
+                DenoKind::tool_spec(&self.deno.resolve_version(version, hooks.deno.as_ref())?)
+            }
+            ToolSpec::Npm(version) => NpmKind::tool_spec(&self.npm.resolve_version(version, None)?),
+            ToolSpec::Package(name, version) => ToolSpec::Package(name.clone(), version.clone()),
         })
     }
 
@@ -116,31 +199,131 @@ impl Inventory {
         &mut self,
         toolspec: &ToolSpec,
         hooks: &HookConfig,
+    ) -> Fallible<Fetched<DistroVersion>> {
+        let _lock = self.lock()?;
+        self.fetch_locked(toolspec, hooks)
+    }
+
+    /// Fetches the Tool versions matching each of `toolspecs`, downloading all of their
+    /// archives at the same time before unpacking each of them in turn.
+    pub fn fetch_all(
+        &mut self,
+        toolspecs: &[ToolSpec],
+        hooks: &HookConfig,
+    ) -> Fallible<Vec<Fetched<DistroVersion>>> {
+        let _lock = self.lock()?;
+
+        let mut exact = Vec::new();
+        let mut downloads = Vec::new();
+        for toolspec in toolspecs {
+            let (toolspec, download) = match toolspec {
+                ToolSpec::Node(version) => self.node.download(&version, hooks.node.as_ref())?,
+                ToolSpec::Yarn(version) => self.yarn.download(&version, hooks.yarn.as_ref())?,
+                ToolSpec::Deno(version) => self.deno.download(&version, hooks.deno.as_ref())?,
+                ToolSpec::Npm(version) => self.npm.download(&version, None)?,
+                ToolSpec::Package(name, _) => throw!(FetchPackageError { name: name.clone() }),
+            };
+            exact.push(toolspec);
+            downloads.extend(download);
+        }
+        download::all(downloads)?;
+
+        exact
+            .iter()
+            .map(|toolspec| self.fetch_locked(toolspec, hooks))
+            .collect()
+    }
+
+    fn fetch_locked(
+        &mut self,
+        toolspec: &ToolSpec,
+        hooks: &HookConfig,
     ) -> Fallible<Fetched<DistroVersion>> {
         match toolspec {
             ToolSpec::Node(version) => self.node.fetch(&version, hooks.node.as_ref()),
             ToolSpec::Yarn(version) => self.yarn.fetch(&version, hooks.yarn.as_ref()),
-            // ISSUE (#175) implement as part of fetching packages
-            ToolSpec::Npm(_) => unimplemented!("cannot fetch npm"),
-            ToolSpec::Package(name, _) => unimplemented!("cannot fetch {}", name),
+            ToolSpec::Deno(version) => self.deno.fetch(&version, hooks.deno.as_ref()),
+            // npm is always fetched from the registry, so it has no hooks
+            ToolSpec::Npm(version) => self.npm.fetch(&version, None),
+            ToolSpec::Package(name, _) => throw!(FetchPackageError { name: name.clone() }),
+        }
+    }
+
+    /// Builds a Node version matching the specified semantic versioning requirements from
+    /// its source, unless it is already in the inventory.
+    pub fn build_node(
+        &mut self,
+        matching: &VersionSpec,
+        hooks: &HookConfig,
+    ) -> Fallible<Fetched<DistroVersion>> {
+        let hooks = hooks.node.as_ref();
+        let version = self.node.resolve_version(matching, hooks)?;
+        let _lock = self.lock()?;
+        if self.node.contains(&version) {
+            return Ok(Fetched::Already(NodeKind::installed_version(version)?));
         }
+
+        ensure_home_writable(&format!("build Node v{}", version))?;
+        let built = source::build(&version, hooks)?;
+        self.node.versions.insert(version);
+        Ok(Fetched::Now(built))
+    }
+
+    /// Installs the staged local Node `build` into the inventory, unless its version is
+    /// already in the inventory.
+    pub(crate) fn install_local_node(
+        &mut self,
+        build: LocalBuild,
+    ) -> Fallible<Fetched<DistroVersion>> {
+        let _lock = self.lock()?;
+        let version = build.version().clone();
+        if self.node.contains(&version) {
+            display_warning(format!(
+                "Node v{} is already in the inventory, so {} was not installed over it (uninstall it first to replace it)",
+                version,
+                build.source().display()
+            ));
+            return Ok(Fetched::Already(NodeKind::installed_version(version)?));
+        }
+
+        let installed = build.install()?;
+        self.node.versions.insert(version);
+        Ok(Fetched::Now(installed))
+    }
+
+    /// Locks the inventory, for as long as the returned lock is held, while versions are
+    /// fetched and added to it. Another process may have fetched the same versions while
+    /// this one waited for the lock, so the versions are read again once it is held,
+    /// leaving them to be reused rather than unpacked over. A read-only inventory can't
+    /// change, so it needs no lock (and refuses any fetch).
+    pub(crate) fn lock(&mut self) -> Fallible<Option<LockFile>> {
+        if is_read_only_home() {
+            return Ok(None);
+        }
+        let lock = LockFile::acquire(&path::inventory_lock_file()?)?;
+        *self = Inventory::load()?;
+        Ok(Some(lock))
     }
 }
 
-/// Thrown when there is no Node version matching a requested semver specifier.
+/// Thrown when there is no version of a tool matching a requested semver specifier.
 #[derive(Debug, Fail, NotionFail)]
-#[fail(display = "No Node version found for {}", matching)]
+#[fail(display = "No {} version found for {}", tool, matching)]
 #[notion_fail(code = "NoVersionMatch")]
-struct NoNodeVersionFoundError {
-    matching: String,
+pub(crate) struct NoVersionFoundError {
+    pub(crate) tool: &'static str,
+    pub(crate) matching: String,
 }
 
-/// Thrown when there is no Yarn version matching a requested semver specifier.
+/// Thrown when a package is fetched, since packages are only fetched by installing them.
 #[derive(Debug, Fail, NotionFail)]
-#[fail(display = "No Yarn version found for {}", matching)]
-#[notion_fail(code = "NoVersionMatch")]
-struct NoYarnVersionFoundError {
-    matching: String,
+#[fail(
+    display = "Cannot fetch the package `{}`\n\nUse `notion install {}` to install it.",
+    name, name
+)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct FetchPackageError {
+    name: String,
 }
 
 impl<D: Distro> Collection<D> {
@@ -160,24 +343,88 @@ pub trait FetchResolve<D: Distro> {
 
     /// Resolves the specified semantic versioning requirements into a distribution
     fn resolve(&self, matching: &VersionSpec, hooks: Option<&ToolHooks<D>>) -> Fallible<D> {
-        let version = match *matching {
-            VersionSpec::Latest => self.resolve_latest(hooks)?,
-            VersionSpec::Semver(ref requirement) => self.resolve_semver(requirement, hooks)?,
-            VersionSpec::Exact(ref version) => version.clone(),
-        };
-
+        let version = self.resolve_version(matching, hooks)?;
         D::new(version, hooks)
     }
 
+    /// Resolves the specified semantic versioning requirements into a version, without
+    /// provisioning its distribution
+    fn resolve_version(
+        &self,
+        matching: &VersionSpec,
+        hooks: Option<&ToolHooks<D>>,
+    ) -> Fallible<Version> {
+        match *matching {
+            VersionSpec::Latest => self.resolve_latest(hooks),
+            VersionSpec::Semver(ref requirement) => self.resolve_semver(requirement, hooks),
+            VersionSpec::Exact(ref version) => Ok(version.clone()),
+            VersionSpec::Tag(ref tag) => self.resolve_tag(tag, hooks),
+        }
+    }
+
     /// Resolves the latest version for this tool, using either the `latest` hook or the public registry
     fn resolve_latest(&self, hooks: Option<&ToolHooks<D>>) -> Fallible<Version>;
 
+    /// Resolves a moving tag (`lts`) for this tool, using either the `index` hook or the public registry
+    fn resolve_tag(&self, tag: &str, hooks: Option<&ToolHooks<D>>) -> Fallible<Version>;
+
     /// Resolves a SemVer version for this tool, using either the `index` hook or the public registry
     fn resolve_semver(
         &self,
         matching: &VersionReq,
         hooks: Option<&ToolHooks<D>>,
     ) -> Fallible<Version>;
+
+    /// Determines whether the exact version is available, according to either the `index`
+    /// hook or the public registry
+    fn is_published(&self, version: &Version, hooks: Option<&ToolHooks<D>>) -> Fallible<bool>;
+}
+
+/// Thrown when working offline and no version in the inventory matches a request.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "No {} version matching {} is available offline\n\nVersions in the inventory: {}",
+    tool, matching, cached
+)]
+#[notion_fail(code = "NoVersionMatch")]
+pub(crate) struct OfflineVersionError {
+    tool: &'static str,
+    matching: String,
+    cached: String,
+}
+
+/// Resolves `matching` against the versions in the inventory, newest first.
+fn resolve_offline(
+    tool: &'static str,
+    matching: &VersionSpec,
+    versions: &BTreeSet<Version>,
+) -> Fallible<Version> {
+    let found = match *matching {
+        VersionSpec::Latest => versions.iter().next_back(),
+        VersionSpec::Semver(ref requirement) => {
+            versions.iter().rev().find(|v| requirement.matches(v))
+        }
+        VersionSpec::Exact(ref version) => versions.get(version),
+        // which version a moving tag names can't be known without the index
+        VersionSpec::Tag(_) => None,
+    };
+    match found {
+        Some(version) => Ok(version.clone()),
+        None => throw!(OfflineVersionError {
+            tool,
+            matching: matching.to_string(),
+            cached: if versions.is_empty() {
+                "none".to_string()
+            } else {
+                versions
+                    .iter()
+                    .rev()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            },
+        }),
+    }
 }
 
 /// Thrown when the public registry for Node or Yarn could not be downloaded.
@@ -189,149 +436,320 @@ pub(crate) struct RegistryFetchError {
 }
 
 impl RegistryFetchError {
-    pub(crate) fn from_error(error: &reqwest::Error) -> RegistryFetchError {
+    pub(crate) fn from_error<E: fmt::Display>(error: &E) -> RegistryFetchError {
         RegistryFetchError {
             error: error.to_string(),
         }
     }
 }
 
-fn match_node_version(
-    url: &str,
-    predicate: impl Fn(&NodeEntry) -> bool,
-) -> Fallible<Option<Version>> {
-    let index: NodeIndex = resolve_node_versions(url)?.into_index()?;
-    let mut entries = index.entries.into_iter();
-    Ok(entries
-        .find(predicate)
-        .map(|NodeEntry { version, .. }| version))
+impl<K: ToolKind> Collection<ArchiveDistro<K>> {
+    /// Resolves the version matching `matching`, and describes the download of its
+    /// archive unless it is already in the inventory.
+    fn download(
+        &self,
+        matching: &VersionSpec,
+        hooks: Option<&ToolHooks<ArchiveDistro<K>>>,
+    ) -> Fallible<(ToolSpec, Option<Download>)> {
+        let version = self.resolve_version(matching, hooks)?;
+        let toolspec = K::tool_spec(&version);
+        if is_offline() || self.contains(&version) {
+            return Ok((toolspec, None));
+        }
+
+        let url = ArchiveDistro::<K>::url(&version, hooks)?;
+        Ok((toolspec, ArchiveDistro::<K>::download(version, &url)?))
+    }
+
+    /// Lists the published versions, newest first, or the versions in the inventory
+    /// while offline.
+    pub(crate) fn published_versions(
+        &self,
+        hooks: Option<&ToolHooks<ArchiveDistro<K>>>,
+    ) -> Fallible<Vec<Version>> {
+        if is_offline() {
+            return Ok(self.versions.iter().rev().cloned().collect());
+        }
+        K::fetch_index(&index_url(hooks)?)
+    }
 }
 
-impl FetchResolve<NodeDistro> for NodeCollection {
+impl<K: ToolKind> FetchResolve<ArchiveDistro<K>> for Collection<ArchiveDistro<K>> {
     fn fetch(
         &mut self,
         matching: &VersionSpec,
-        hooks: Option<&ToolHooks<NodeDistro>>,
+        hooks: Option<&ToolHooks<ArchiveDistro<K>>>,
     ) -> Fallible<Fetched<DistroVersion>> {
+        // resolved from the inventory, so the version never needs to be provisioned
+        if is_offline() {
+            let version = self.resolve_version(matching, hooks)?;
+            return Ok(Fetched::Already(K::installed_version(version)?));
+        }
+
         let distro = self.resolve(matching, hooks)?;
+        let version = distro.version().clone();
         let fetched = distro.fetch(&self).unknown()?;
 
-        if let &Fetched::Now(DistroVersion::Node(ref version, ..)) = &fetched {
-            self.versions.insert(version.clone());
+        if let &Fetched::Now(_) = &fetched {
+            self.versions.insert(version);
         }
 
         Ok(fetched)
     }
 
-    fn resolve_latest(&self, hooks: Option<&ToolHooks<NodeDistro>>) -> Fallible<Version> {
-        // NOTE: This assumes the registry always produces a list in sorted order
-        //       from newest to oldest. This should be specified as a requirement
-        //       when we document the plugin API.
+    fn resolve_version(
+        &self,
+        matching: &VersionSpec,
+        hooks: Option<&ToolHooks<ArchiveDistro<K>>>,
+    ) -> Fallible<Version> {
+        // an alias stands for another version, which is resolved in its place
+        if let VersionSpec::Tag(ref tag) = *matching {
+            if tag != alias::LTS {
+                let aliased = alias::resolve(&K::DISPLAY_NAME.to_lowercase(), tag)?;
+                return self.resolve_version(&aliased, hooks);
+            }
+        }
+        if is_offline() {
+            return resolve_offline(K::DISPLAY_NAME, matching, &self.versions);
+        }
+        match *matching {
+            VersionSpec::Latest => self.resolve_latest(hooks),
+            VersionSpec::Semver(ref requirement) => self.resolve_semver(requirement, hooks),
+            VersionSpec::Exact(ref version) => Ok(version.clone()),
+            VersionSpec::Tag(ref tag) => self.resolve_tag(tag, hooks),
+        }
+    }
+
+    fn resolve_latest(&self, hooks: Option<&ToolHooks<ArchiveDistro<K>>>) -> Fallible<Version> {
         let url = match hooks {
             Some(&ToolHooks {
                 latest: Some(ref hook),
                 ..
-            }) => hook.resolve("index.json")?,
-            _ => public_node_version_index(),
+            }) => hook.resolve(K::LATEST_FILE_NAME)?,
+            _ => K::public_latest_url(),
         };
-        let version_opt = match_node_version(&url, |_| true)?;
+        K::fetch_latest(&url)
+    }
 
-        if let Some(version) = version_opt {
-            Ok(version)
-        } else {
-            throw!(NoNodeVersionFoundError {
-                matching: "latest".to_string()
-            })
+    fn resolve_tag(
+        &self,
+        tag: &str,
+        hooks: Option<&ToolHooks<ArchiveDistro<K>>>,
+    ) -> Fallible<Version> {
+        match K::fetch_lts(&index_url(hooks)?)? {
+            Some(version) => Ok(version),
+            None => throw!(NoVersionFoundError {
+                tool: K::DISPLAY_NAME,
+                matching: tag.to_string(),
+            }),
         }
     }
 
     fn resolve_semver(
         &self,
         matching: &VersionReq,
-        hooks: Option<&ToolHooks<NodeDistro>>,
+        hooks: Option<&ToolHooks<ArchiveDistro<K>>>,
     ) -> Fallible<Version> {
         // ISSUE #34: also make sure this OS is available for this version
-        let url = match hooks {
-            Some(&ToolHooks {
-                index: Some(ref hook),
-                ..
-            }) => hook.resolve("index.json")?,
-            _ => public_node_version_index(),
-        };
-        let version_opt = match_node_version(&url, |&NodeEntry { version: ref v, .. }| {
-            matching.matches(v)
-        })?;
+        let versions = K::fetch_index(&index_url(hooks)?)?;
+        let version_opt = versions.into_iter().find(|v| matching.matches(v));
 
         if let Some(version) = version_opt {
             Ok(version)
         } else {
-            throw!(NoNodeVersionFoundError {
+            throw!(NoVersionFoundError {
+                tool: K::DISPLAY_NAME,
                 matching: matching.to_string()
             })
         }
     }
-}
 
-impl FetchResolve<YarnDistro> for YarnCollection {
-    /// Fetches a Yarn version matching the specified semantic versioning requirements.
-    fn fetch(
-        &mut self,
-        matching: &VersionSpec,
-        hooks: Option<&ToolHooks<YarnDistro>>,
-    ) -> Fallible<Fetched<DistroVersion>> {
-        let distro = self.resolve(&matching, hooks)?;
-        let fetched = distro.fetch(&self).unknown()?;
-
-        if let &Fetched::Now(DistroVersion::Yarn(ref version)) = &fetched {
-            self.versions.insert(version.clone());
+    fn is_published(
+        &self,
+        version: &Version,
+        hooks: Option<&ToolHooks<ArchiveDistro<K>>>,
+    ) -> Fallible<bool> {
+        if is_offline() {
+            return Ok(self.contains(version));
         }
+        Ok(K::fetch_index(&index_url(hooks)?)?.contains(version))
+    }
+}
 
-        Ok(fetched)
+/// Returns the URL of the index of a tool, using either the `index` hook or the public registry.
+fn index_url<K: ToolKind>(hooks: Option<&ToolHooks<ArchiveDistro<K>>>) -> Fallible<String> {
+    match hooks {
+        Some(&ToolHooks {
+            index: Some(ref hook),
+            ..
+        }) => hook.resolve(K::INDEX_FILE_NAME),
+        _ => Ok(K::public_index_url()),
     }
+}
 
-    fn resolve_latest(&self, hooks: Option<&ToolHooks<YarnDistro>>) -> Fallible<Version> {
-        let url = match hooks {
-            Some(&ToolHooks {
-                latest: Some(ref hook),
-                ..
-            }) => hook.resolve("latest-version")?,
-            _ => public_yarn_latest_version(),
-        };
-        let mut response: reqwest::Response =
-            reqwest::get(&url).with_context(RegistryFetchError::from_error)?;
-        Version::parse(&response.text().unknown()?).unknown()
+/// Reads the versions in the Node index at `url`, newest first.
+pub(crate) fn node_index_versions(url: &str) -> Fallible<Vec<Version>> {
+    let index: NodeIndex = resolve_node_versions(url)?.into_index()?;
+    Ok(index
+        .entries
+        .into_iter()
+        .map(|NodeEntry { version, .. }| version)
+        .collect())
+}
+
+/// Reads the LTS versions in the Node index, newest first.
+pub(crate) fn node_lts_versions(hooks: Option<&ToolHooks<NodeDistro>>) -> Fallible<Vec<Version>> {
+    node_index_lts_versions(&index_url(hooks)?)
+}
+
+/// Reads the LTS versions in the Node index at `url`, newest first.
+pub(crate) fn node_index_lts_versions(url: &str) -> Fallible<Vec<Version>> {
+    let index: NodeIndex = resolve_node_versions(url)?.into_index()?;
+    Ok(index
+        .entries
+        .into_iter()
+        .filter(|entry| entry.lts)
+        .map(|NodeEntry { version, .. }| version)
+        .collect())
+}
+
+/// Reads the newest LTS version in the cached Node index, even if it has expired,
+/// without reaching the network.
+pub(crate) fn cached_node_lts() -> Fallible<Option<Version>> {
+    let cached = match read_file_opt(&path::node_index_file()?).unknown()? {
+        Some(cached) => cached,
+        None => return Ok(None),
+    };
+    // a corrupted cache only costs the suggestion it would have made
+    let index = match serde_json::from_str::<serial::NodeIndex>(&cached) {
+        Ok(index) => index.into_index()?,
+        Err(_) => return Ok(None),
+    };
+    Ok(index
+        .entries
+        .into_iter()
+        .filter(|entry| entry.lts)
+        .map(|entry| entry.version)
+        .max())
+}
+
+/// Reads the versions in the Yarn index at `url`.
+pub(crate) fn resolve_yarn_versions(url: &str) -> Fallible<BTreeSet<Version>> {
+    let spinner = progress_spinner(&format!("Fetching public registry: {}", url));
+    let releases: serial::YarnIndex = RetryPolicy::current()?
+        .send(|| credentials::get(url), url, Some(&spinner))
+        .with_context(RegistryFetchError::from_error)?
+        .json()
+        .unknown()?;
+    let releases = releases.into_index()?.entries;
+    spinner.finish();
+    Ok(releases)
+}
+
+/// Reads the versions in the Deno index at `url` that are available for this platform.
+pub(crate) fn resolve_deno_versions(url: &str) -> Fallible<BTreeSet<Version>> {
+    let spinner = progress_spinner(&format!("Fetching public registry: {}", url));
+    let releases: serial::DenoIndex = RetryPolicy::current()?
+        .send(|| credentials::get(url), url, Some(&spinner))
+        .with_context(RegistryFetchError::from_error)?
+        .json()
+        .unknown()?;
+    let versions = releases.into_versions(&path::deno_archive_file_name())?;
+    spinner.finish();
+    Ok(versions)
+}
+
+/// Reads the version of the Deno release at `url`.
+pub(crate) fn resolve_deno_release(url: &str) -> Fallible<Version> {
+    let release: serial::DenoEntry = RetryPolicy::current()?
+        .send(|| credentials::get(url), url, None)
+        .with_context(RegistryFetchError::from_error)?
+        .json()
+        .unknown()?;
+    release.version()
+}
+
+/// Reads the metadata document of the npm package at `url`.
+fn fetch_npm_index(url: &str, progress: Option<&Progress>) -> Fallible<serial::NpmIndex> {
+    RetryPolicy::current()?
+        .send(|| credentials::get(url), url, progress)
+        .and_then(|response| Ok(response.error_for_status()?))
+        .and_then(|mut response| Ok(response.json()?))
+        .with_context(RegistryFetchError::from_error)
+}
+
+/// Reads the versions of npm published in the registry metadata at `url`.
+pub(crate) fn resolve_npm_versions(url: &str) -> Fallible<BTreeSet<Version>> {
+    let spinner = progress_spinner(&format!("Fetching public registry: {}", url));
+    let versions = fetch_npm_index(url, Some(&spinner))?.into_versions();
+    spinner.finish();
+    Ok(versions)
+}
+
+/// Reads the latest version of npm in the registry metadata at `url`.
+pub(crate) fn resolve_npm_latest(url: &str) -> Fallible<Version> {
+    fetch_npm_index(url, None)?.latest()
+}
+
+/// Checks the archives of the inventory against their recorded checksums, reporting the
+/// archives that were corrupted or modified since they were fetched, and the checksum
+/// records left behind by archives that are gone.
+pub(crate) fn diagnose() -> Fallible<Vec<Finding>> {
+    let mut files = Vec::new();
+    for dir in &[
+        path::node_inventory_dir()?,
+        path::yarn_inventory_dir()?,
+        path::deno_inventory_dir()?,
+        path::npm_inventory_dir()?,
+        path::package_inventory_dir()?,
+    ] {
+        collect_files(dir, &mut files)?;
     }
 
-    fn resolve_semver(
-        &self,
-        matching: &VersionReq,
-        hooks: Option<&ToolHooks<YarnDistro>>,
-    ) -> Fallible<Version> {
-        let url = match hooks {
-            Some(&ToolHooks {
-                index: Some(ref hook),
-                ..
-            }) => hook.resolve("releases")?,
-            _ => public_yarn_version_index(),
+    let mut findings = Vec::new();
+    for file in files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if name.ends_with(".checksum") {
+            let archive = file.with_file_name(name.trim_end_matches(".checksum"));
+            if !archive.exists() {
+                findings.push(Finding::fixable(
+                    format!(
+                        "{} records the checksum of a missing archive",
+                        file.display()
+                    ),
+                    Fix::RemoveFile(file.clone()),
+                ));
+            }
+            continue;
+        }
+        let intact = match checksum::recorded(&file) {
+            Ok(Some(checksum)) => checksum.verify_file(&file).is_ok(),
+            Ok(None) => true,
+            Err(_) => false,
         };
+        if !intact {
+            findings.push(Finding::fixable(
+                format!("{} does not match its recorded checksum", file.display()),
+                Fix::RemoveArchive(file.clone()),
+            ));
+        }
+    }
+    Ok(findings)
+}
 
-        let spinner = progress_spinner(&format!("Fetching public registry: {}", url));
-        let releases: serial::YarnIndex = reqwest::get(&url)
-            .with_context(RegistryFetchError::from_error)?
-            .json()
-            .unknown()?;
-        let releases = releases.into_index()?.entries;
-        spinner.finish_and_clear();
-        let version_opt = releases.into_iter().rev().find(|v| matching.matches(v));
-
-        if let Some(version) = version_opt {
-            Ok(version)
+/// Collects the files under `dir`, which may not exist.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Fallible<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for (entry, metadata) in read_dir_eager(dir)? {
+        if metadata.is_dir() {
+            collect_files(&entry.path(), files)?;
         } else {
-            throw!(NoYarnVersionFoundError {
-                matching: matching.to_string()
-            })
+            files.push(entry.path());
         }
     }
+    Ok(())
 }
 
 /// The index of the public Node server.
@@ -344,6 +762,7 @@ pub struct NodeEntry {
     pub version: Version,
     pub npm: Version,
     pub files: NodeDistroFiles,
+    pub lts: bool,
 }
 
 /// The public Yarn index.
@@ -377,6 +796,11 @@ fn read_cached_opt() -> Fallible<Option<serial::NodeIndex>> {
     Ok(None)
 }
 
+/// Reads the public index from the Node cache, whether or not it has expired.
+fn read_stale_opt() -> Fallible<Option<String>> {
+    read_file_opt(&path::node_index_file()?).unknown()
+}
+
 /// Get the cache max-age of an HTTP reponse.
 fn max_age(response: &reqwest::Response) -> u32 {
     if let Some(cache_control_header) = response.headers().get::<CacheControl>() {
@@ -391,50 +815,133 @@ fn max_age(response: &reqwest::Response) -> u32 {
     4 * 60 * 60
 }
 
-fn resolve_node_versions(url: &str) -> Fallible<serial::NodeIndex> {
-    match read_cached_opt()? {
-        Some(serial) => Ok(serial),
-        None => {
-            let spinner = progress_spinner(&format!("Fetching public registry: {}", url));
-            let mut response: reqwest::Response =
-                reqwest::get(url).with_context(RegistryFetchError::from_error)?;
-            let response_text: String = response.text().unknown()?;
-            let cached: NamedTempFile = NamedTempFile::new_in(path::tmp_dir()?).unknown()?;
+/// Writes `contents` to `file`, replacing it at once.
+fn persist_cache_file(file: PathBuf, contents: &str) -> Fallible<()> {
+    let temp: NamedTempFile = NamedTempFile::new_in(path::tmp_dir()?).unknown()?;
 
-            // Block to borrow cached for cached_file.
-            {
-                let mut cached_file: &File = cached.as_file();
-                cached_file.write(response_text.as_bytes()).unknown()?;
-            }
+    // Block to borrow temp for temp_file.
+    {
+        let mut temp_file: &File = temp.as_file();
+        temp_file.write(contents.as_bytes()).unknown()?;
+    }
 
-            let index_cache_file = path::node_index_file()?;
-            ensure_containing_dir_exists(&index_cache_file)?;
-            cached.persist(index_cache_file).unknown()?;
+    ensure_containing_dir_exists(&file)?;
+    temp.persist(file).unknown()?;
+    Ok(())
+}
 
-            let expiry: NamedTempFile = NamedTempFile::new_in(path::tmp_dir()?).unknown()?;
+/// Records when the Node index fetched (or revalidated) in `response` expires: after the
+/// `ttl_minutes` of the configuration if set, or else as the server's headers say.
+fn cache_node_index_expiry(response: &reqwest::Response) -> Fallible<()> {
+    let ttl = Config::current()?
+        .index
+        .ttl_minutes
+        .map(|minutes| u64::from(minutes) * 60);
+    let expiry = match (ttl, response.headers().get::<Expires>()) {
+        (None, Some(expires_header)) => expires_header.to_string(),
+        (ttl, _) => {
+            let seconds = ttl.unwrap_or_else(|| max_age(response).into());
+            HttpDate::from(SystemTime::now() + Duration::from_secs(seconds)).to_string()
+        }
+    };
+    persist_cache_file(path::node_index_expiry_file()?, &expiry)
+}
 
-            // Block to borrow expiry for expiry_file.
-            {
-                let mut expiry_file: &File = expiry.as_file();
+/// Caches the Node index fetched in `response`, along with its expiry date and the
+/// validators to revalidate it with.
+fn cache_node_index(response_text: &str, response: &reqwest::Response) -> Fallible<()> {
+    persist_cache_file(path::node_index_file()?, response_text)?;
+
+    let validators = serial::IndexValidators {
+        etag: response.headers().get::<ETag>().map(ToString::to_string),
+        last_modified: response
+            .headers()
+            .get::<LastModified>()
+            .map(ToString::to_string),
+    };
+    let validators = serde_json::to_string(&validators).unknown()?;
+    persist_cache_file(path::node_index_validators_file()?, &validators)?;
+
+    cache_node_index_expiry(response)
+}
 
-                if let Some(expires_header) = response.headers().get::<Expires>() {
-                    write!(expiry_file, "{}", expires_header).unknown()?;
-                } else {
-                    let expiry_date =
-                        SystemTime::now() + Duration::from_secs(max_age(&response).into());
+/// Adds the validators of the cached Node index to `request`, so that the server only
+/// sends the index again if it has changed.
+fn add_validators(request: &mut reqwest::RequestBuilder) -> Fallible<()> {
+    let validators = match read_file_opt(&path::node_index_validators_file()?).unknown()? {
+        Some(src) => serde_json::de::from_str(&src).unknown()?,
+        None => serial::IndexValidators::default(),
+    };
+    if let Some(etag) = validators.etag.and_then(|etag| etag.parse().ok()) {
+        request.header(IfNoneMatch::Items(vec![etag]));
+    }
+    if let Some(date) = validators.last_modified.and_then(|date| date.parse().ok()) {
+        request.header(IfModifiedSince(date));
+    }
+    Ok(())
+}
 
-                    write!(expiry_file, "{}", HttpDate::from(expiry_date)).unknown()?;
-                }
+/// Fetches the Node index at `url`, or only revalidates the cached index if there is one.
+fn fetch_node_index(url: &str) -> Fallible<serial::NodeIndex> {
+    let spinner = progress_spinner(&format!("Fetching public registry: {}", url));
+    let stale = read_stale_opt()?;
+    let request = || {
+        let mut request = credentials::get(url)?;
+        if stale.is_some() {
+            add_validators(&mut request)?;
+        }
+        Ok(request)
+    };
+    let mut response: reqwest::Response = RetryPolicy::current()?
+        .send(request, url, Some(&spinner))
+        .with_context(RegistryFetchError::from_error)?;
+
+    // a read-only NOTION_HOME can't cache the index, so it's fetched every time
+    let response_text = match (response.status(), stale) {
+        (StatusCode::NotModified, Some(stale)) => {
+            if !is_read_only_home() {
+                cache_node_index_expiry(&response)?;
             }
+            stale
+        }
+        (status, _) if status.is_success() => {
+            let response_text: String = response.text().unknown()?;
+            if !is_read_only_home() {
+                cache_node_index(&response_text, &response)?;
+            }
+            response_text
+        }
+        (status, _) => throw!(RegistryFetchError {
+            error: format!("{} responded with {}", url, status),
+        }),
+    };
 
-            let index_expiry_file = path::node_index_expiry_file()?;
-            ensure_containing_dir_exists(&index_expiry_file)?;
-            expiry.persist(index_expiry_file).unknown()?;
+    let serial: serial::NodeIndex = serde_json::de::from_str(&response_text).unknown()?;
 
-            let serial: serial::NodeIndex = serde_json::de::from_str(&response_text).unknown()?;
+    spinner.finish();
+    Ok(serial)
+}
 
-            spinner.finish_and_clear();
-            Ok(serial)
-        }
+fn resolve_node_versions(url: &str) -> Fallible<serial::NodeIndex> {
+    if let Some(serial) = read_cached_opt()? {
+        return Ok(serial);
+    }
+    match fetch_node_index(url) {
+        Ok(serial) => Ok(serial),
+        // an outdated index is better than none while the registry can't be reached
+        Err(error) => match (error.exit_code(), read_stale_opt()?) {
+            (ExitCode::NetworkError, Some(stale)) => {
+                display_warning(format!("{}\nUsing the cached Node index.", error));
+                Ok(serde_json::de::from_str(&stale).unknown()?)
+            }
+            _ => Err(error),
+        },
     }
 }
+
+/// Fetches the Node index again (or revalidates the cached one) even if the cached index
+/// hasn't expired, and returns how many versions it lists.
+pub(crate) fn refresh_node_index(hooks: Option<&ToolHooks<NodeDistro>>) -> Fallible<usize> {
+    let index: NodeIndex = fetch_node_index(&index_url(hooks)?)?.into_index()?;
+    Ok(index.entries.len())
+}
@@ -0,0 +1,360 @@
+//! Provides queries over the contents of the inventory, for listing what is
+//! available locally, and over the published versions that can be fetched into it.
+
+use std::fs;
+use std::path::Path;
+
+use semver::Version;
+use serde::Serialize;
+use serde_json;
+
+use super::{node_lts_versions, serial};
+use crate::alias;
+use crate::env::is_offline;
+use crate::fs::read_file_opt;
+use crate::path;
+use crate::platform::PlatformSpec;
+use crate::session::Session;
+use crate::tool::ToolSpec;
+use crate::version::VersionSpec;
+use notion_fail::{Fallible, ResultExt};
+
+/// A version of a tool or package in the inventory.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Entry {
+    /// The name of the tool (`node`, `npm`, `yarn` or `deno`) or package.
+    pub name: String,
+    #[serde(serialize_with = "serialize_version")]
+    pub version: Version,
+    /// Whether this is a package, as opposed to a tool.
+    pub package: bool,
+    /// Whether this version is in the user toolchain.
+    pub default: bool,
+    /// Whether this version is pinned by the current project.
+    pub pinned: bool,
+}
+
+fn serialize_version<S: serde::Serializer>(
+    version: &Version,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&version.to_string())
+}
+
+impl Entry {
+    /// Renders this entry as a line of `notion list` output.
+    pub fn to_line(&self) -> String {
+        let marks: Vec<&str> = vec![("default", self.default), ("pinned", self.pinned)]
+            .into_iter()
+            .filter(|(_, marked)| *marked)
+            .map(|(mark, _)| mark)
+            .collect();
+        if marks.is_empty() {
+            format!("{} v{}", self.name, self.version)
+        } else {
+            format!("{} v{} ({})", self.name, self.version, marks.join(", "))
+        }
+    }
+}
+
+/// The version of the tool `name` in `platform`, if any.
+fn platform_version<'a>(platform: &'a Option<PlatformSpec>, name: &str) -> Option<&'a Version> {
+    let platform = platform.as_ref()?;
+    match name {
+        "node" => Some(&platform.node_runtime),
+        "npm" => platform.npm.as_ref(),
+        "yarn" => platform.yarn.as_ref(),
+        "deno" => platform.deno.as_ref(),
+        _ => None,
+    }
+}
+
+/// Lists every fetched version of each tool, oldest first, followed by the fetched
+/// versions of packages, marking the ones in the user toolchain and the ones pinned by
+/// the current project.
+pub fn entries(session: &Session) -> Fallible<Vec<Entry>> {
+    let inventory = session.inventory()?;
+    let user = session.user_platform()?.map(|platform| (*platform).clone());
+    let project = session
+        .project_platform()?
+        .map(|platform| (*platform).clone());
+
+    let tools = vec![
+        ("node", &inventory.node.versions),
+        ("npm", &inventory.npm.versions),
+        ("yarn", &inventory.yarn.versions),
+        ("deno", &inventory.deno.versions),
+    ];
+    let mut entries = Vec::new();
+    for (name, versions) in tools {
+        for version in versions {
+            entries.push(Entry {
+                name: name.to_string(),
+                version: version.clone(),
+                package: false,
+                default: platform_version(&user, name) == Some(version),
+                pinned: platform_version(&project, name) == Some(version),
+            });
+        }
+    }
+    entries.extend(packages(&path::package_inventory_dir()?)?);
+    Ok(entries)
+}
+
+/// Lists the fetched versions of packages, stored as `<name>/<version>` entries of the
+/// package inventory (with scoped packages in a directory for their scope). Packages
+/// aren't part of a platform, so they are never marked.
+fn packages(dir: &Path) -> Fallible<Vec<Entry>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for package in fs::read_dir(dir).unknown()? {
+        let package = package.unknown()?;
+        if !package.file_type().unknown()?.is_dir() {
+            continue;
+        }
+        let name = package.file_name().to_string_lossy().into_owned();
+        if name.starts_with('@') {
+            for scoped in fs::read_dir(package.path()).unknown()? {
+                let scoped = scoped.unknown()?;
+                let scoped_name = scoped.file_name().to_string_lossy().into_owned();
+                let name = format!("{}/{}", name, scoped_name);
+                entries.extend(package_versions(&name, &scoped.path())?);
+            }
+        } else {
+            entries.extend(package_versions(&name, &package.path())?);
+        }
+    }
+    entries.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    Ok(entries)
+}
+
+/// Lists the versions of the package `name` in its directory of the package inventory.
+fn package_versions(name: &str, dir: &Path) -> Fallible<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for version in fs::read_dir(dir).unknown()? {
+        let version = version.unknown()?;
+        // anything that isn't a version directory is left alone
+        if let Ok(version) = Version::parse(&version.file_name().to_string_lossy()) {
+            entries.push(Entry {
+                name: name.to_string(),
+                version,
+                package: true,
+                default: false,
+                pinned: false,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Lists the versions of the tool `name` to offer in shell completions, newest first:
+/// the fetched versions, and for Node also the versions of the cached index (even if it
+/// has expired). Never reaches the network, so that completing stays instant.
+pub fn completion_versions(session: &Session, name: &str) -> Fallible<Vec<Version>> {
+    let inventory = session.inventory()?;
+    let mut versions = match name {
+        "node" => inventory.node.versions.clone(),
+        "npm" => inventory.npm.versions.clone(),
+        "yarn" => inventory.yarn.versions.clone(),
+        "deno" => inventory.deno.versions.clone(),
+        _ => return Ok(Vec::new()),
+    };
+    if name == "node" {
+        if let Some(cached) = read_file_opt(&path::node_index_file()?).unknown()? {
+            // a corrupted cache only costs the completions it would have offered
+            if let Ok(index) = serde_json::from_str::<serial::NodeIndex>(&cached) {
+                versions.extend(
+                    index
+                        .into_index()?
+                        .entries
+                        .into_iter()
+                        .map(|entry| entry.version),
+                );
+            }
+        }
+    }
+    Ok(versions.into_iter().rev().collect())
+}
+
+/// A published version of a tool, as offered by the version picker.
+#[derive(Debug, PartialEq)]
+pub struct Available {
+    pub version: Version,
+    /// Whether this is an LTS release of Node.
+    pub lts: bool,
+    /// Whether this version is in the inventory.
+    pub installed: bool,
+}
+
+impl Available {
+    /// Renders this version as a choice of the version picker.
+    pub fn to_label(&self) -> String {
+        let marks: Vec<&str> = vec![("lts", self.lts), ("installed", self.installed)]
+            .into_iter()
+            .filter(|(_, marked)| *marked)
+            .map(|(mark, _)| mark)
+            .collect();
+        if marks.is_empty() {
+            format!("v{}", self.version)
+        } else {
+            format!("v{} ({})", self.version, marks.join(", "))
+        }
+    }
+}
+
+/// Lists the published versions of the tool of `toolspec` that match its version,
+/// newest first, marking the LTS releases of Node and the versions already fetched.
+/// While offline, only the fetched versions are listed.
+pub fn available(session: &Session, toolspec: &ToolSpec) -> Fallible<Vec<Available>> {
+    let inventory = session.inventory()?;
+    let hooks = session.hooks()?;
+    let (name, published, fetched, lts, matching) = match toolspec {
+        ToolSpec::Node(matching) => (
+            "node",
+            inventory.node.published_versions(hooks.node.as_ref())?,
+            &inventory.node.versions,
+            if is_offline() {
+                Vec::new()
+            } else {
+                node_lts_versions(hooks.node.as_ref())?
+            },
+            matching,
+        ),
+        ToolSpec::Yarn(matching) => (
+            "yarn",
+            inventory.yarn.published_versions(hooks.yarn.as_ref())?,
+            &inventory.yarn.versions,
+            Vec::new(),
+            matching,
+        ),
+        ToolSpec::Deno(matching) => (
+            "deno",
+            inventory.deno.published_versions(hooks.deno.as_ref())?,
+            &inventory.deno.versions,
+            Vec::new(),
+            matching,
+        ),
+        ToolSpec::Npm(matching) => (
+            "npm",
+            inventory.npm.published_versions(None)?,
+            &inventory.npm.versions,
+            Vec::new(),
+            matching,
+        ),
+        ToolSpec::Package(..) => return Ok(Vec::new()),
+    };
+
+    // an alias stands for another version, which is matched in its place
+    let matching = match matching {
+        VersionSpec::Tag(tag) if tag != alias::LTS => alias::resolve(name, tag)?,
+        matching => matching.clone(),
+    };
+    Ok(published
+        .into_iter()
+        .map(|version| Available {
+            lts: lts.contains(&version),
+            installed: fetched.contains(&version),
+            version,
+        })
+        .filter(|available| match matching {
+            VersionSpec::Latest => true,
+            VersionSpec::Semver(ref requirement) => requirement.matches(&available.version),
+            VersionSpec::Exact(ref version) => available.version == *version,
+            VersionSpec::Tag(_) => available.lts,
+        })
+        .collect())
+}
+
+/// Serializes `entries` to a JSON array.
+pub fn to_json(entries: &[Entry]) -> Fallible<String> {
+    serde_json::to_string_pretty(entries).unknown()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{packages, to_json, Available, Entry};
+    use semver::Version;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn entry(name: &str, version: &str, default: bool, pinned: bool) -> Entry {
+        Entry {
+            name: name.to_string(),
+            version: Version::parse(version).unwrap(),
+            package: false,
+            default,
+            pinned,
+        }
+    }
+
+    #[test]
+    fn test_to_line() {
+        assert_eq!(
+            entry("node", "10.13.0", false, false).to_line(),
+            "node v10.13.0"
+        );
+        assert_eq!(
+            entry("node", "10.13.0", true, false).to_line(),
+            "node v10.13.0 (default)"
+        );
+        assert_eq!(
+            entry("yarn", "1.12.3", true, true).to_line(),
+            "yarn v1.12.3 (default, pinned)"
+        );
+    }
+
+    #[test]
+    fn test_available_to_label() {
+        let available = |lts, installed| Available {
+            version: Version::parse("14.17.0").unwrap(),
+            lts,
+            installed,
+        };
+        assert_eq!(available(false, false).to_label(), "v14.17.0");
+        assert_eq!(available(true, false).to_label(), "v14.17.0 (lts)");
+        assert_eq!(
+            available(true, true).to_label(),
+            "v14.17.0 (lts, installed)"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let json = to_json(&[entry("node", "8.9.4", false, true)]).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!([{
+                "name": "node",
+                "version": "8.9.4",
+                "package": false,
+                "default": false,
+                "pinned": true
+            }])
+        );
+    }
+
+    #[test]
+    fn test_packages() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("typescript").join("3.2.2")).unwrap();
+        fs::create_dir_all(dir.path().join("ember-cli").join("3.5.0")).unwrap();
+        fs::create_dir_all(dir.path().join("ember-cli").join("partial")).unwrap();
+        fs::create_dir_all(dir.path().join("@vue").join("cli").join("3.2.1")).unwrap();
+
+        let found: Vec<String> = packages(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.to_line())
+            .collect();
+        assert_eq!(
+            found,
+            vec!["@vue/cli v3.2.1", "ember-cli v3.5.0", "typescript v3.2.2"]
+        );
+        assert!(packages(&dir.path().join("missing")).unwrap().is_empty());
+    }
+}
@@ -1,9 +1,10 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::iter::FromIterator;
 use std::marker::PhantomData;
 use std::path::Path;
 
-use super::{NodeCollection, YarnCollection};
+use super::Collection;
+use crate::distro::kind::{ArchiveDistro, ToolKind};
 use crate::fs::read_dir_eager;
 use crate::path;
 use notion_fail::{Fallible, ResultExt};
@@ -17,13 +18,19 @@ use serde::{Deserialize, Serialize};
 /// and parsing the `version` named capture as a semantic version.
 ///
 /// The regex should contain the `version` named capture by using the Rust regex
-/// syntax `?P<version>`.
-fn versions_matching(dir: &Path, re: &Regex) -> Fallible<BTreeSet<Version>> {
+/// syntax `?P<version>`. Archives that are still being downloaded (see
+/// `kind::partial_file`) aren't versions of the inventory yet, so they are skipped, along
+/// with the validators recorded for resuming them.
+pub(crate) fn versions_matching(dir: &Path, re: &Regex) -> Fallible<BTreeSet<Version>> {
     Ok(read_dir_eager(dir)?
         .filter(|(_, metadata)| metadata.is_file())
         .filter_map(|(entry, _)| {
             if let Some(file_name) = entry.path().file_name() {
-                if let Some(caps) = re.captures(&file_name.to_string_lossy()) {
+                let file_name = file_name.to_string_lossy();
+                if file_name.ends_with(".partial") || file_name.ends_with(".partial.validator") {
+                    return None;
+                }
+                if let Some(caps) = re.captures(&file_name) {
                     return Some(Version::parse(&caps["version"]).unknown());
                 }
             }
@@ -32,46 +39,18 @@ fn versions_matching(dir: &Path, re: &Regex) -> Fallible<BTreeSet<Version>> {
         .collect::<Fallible<BTreeSet<Version>>>()?)
 }
 
-impl NodeCollection {
+impl<K: ToolKind> Collection<ArchiveDistro<K>> {
     pub(crate) fn load() -> Fallible<Self> {
-        let re = Regex::new(
-            r"(?x)
-            node
-            -
-            v(?P<version>\d+\.\d+\.\d+) # Node version
-            -
-            (?P<os>[a-z]+)              # operating system
-            -
-            (?P<arch>[a-z0-9]+)         # architecture
-            \.(zip|tar\.gz)
-            ",
-        )
-        .unwrap();
-
-        let versions = versions_matching(&path::node_inventory_dir()?, &re)?;
-
-        Ok(NodeCollection {
-            versions: versions,
-            phantom: PhantomData,
-        })
-    }
-}
+        let inventory_dir = K::inventory_dir()?;
+        let mut versions = versions_matching(&inventory_dir, &K::distro_file_regex())?;
+        // the archives of the seed directory are in the inventory too, read-only
+        if let Some(seeded) = path::seeded(&inventory_dir)? {
+            if seeded.is_dir() {
+                versions.extend(versions_matching(&seeded, &K::distro_file_regex())?);
+            }
+        }
 
-impl YarnCollection {
-    pub(crate) fn load() -> Fallible<Self> {
-        let re = Regex::new(
-            r"(?x)
-            yarn
-            -
-            v(?P<version>\d+\.\d+\.\d+) # Yarn version
-            \.tar\.gz
-            ",
-        )
-        .unwrap();
-
-        let versions = versions_matching(&path::yarn_inventory_dir()?, &re)?;
-
-        Ok(YarnCollection {
+        Ok(Collection {
             versions: versions,
             phantom: PhantomData,
         })
@@ -86,6 +65,9 @@ pub struct NodeEntry {
     pub version: String,
     pub npm: Option<String>,
     pub files: Vec<String>,
+    /// The codename of the LTS line (e.g. `"Dubnium"`) for LTS releases, `false` otherwise.
+    #[serde(default)]
+    pub lts: serde_json::Value,
 }
 
 fn trim_version(s: &str) -> &str {
@@ -110,6 +92,7 @@ impl NodeIndex {
                     version: Version::parse(version).unknown()?,
                     npm: Version::parse(&npm).unknown()?,
                     files: data,
+                    lts: entry.lts.is_string(),
                 });
             }
         }
@@ -161,3 +144,81 @@ impl YarnIndex {
         Ok(super::YarnIndex { entries })
     }
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DenoIndex(Vec<DenoEntry>);
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DenoEntry {
+    /// Deno releases are given a tag name of the form "v$version".
+    pub tag_name: String,
+
+    /// The assets of the release, which include a zip of the executable for each
+    /// supported platform.
+    pub assets: Vec<DenoAsset>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DenoAsset {
+    /// The filename of an asset included in a Deno GitHub release.
+    pub name: String,
+}
+
+impl DenoEntry {
+    pub fn version(&self) -> Fallible<Version> {
+        Version::parse(trim_version(&self.tag_name[..])).unknown()
+    }
+}
+
+impl DenoIndex {
+    /// Produces the versions that include a release archive named `archive`, i.e. that
+    /// are available for the current platform.
+    pub fn into_versions(self, archive: &str) -> Fallible<BTreeSet<Version>> {
+        let mut versions = BTreeSet::new();
+        for entry in self.0 {
+            if entry.assets.iter().any(|asset| asset.name == archive) {
+                versions.insert(entry.version()?);
+            }
+        }
+        Ok(versions)
+    }
+}
+
+/// The metadata document of the `npm` package on the npm registry.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NpmIndex {
+    #[serde(rename = "dist-tags")]
+    pub dist_tags: NpmDistTags,
+
+    /// The metadata of each published version, by version.
+    pub versions: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NpmDistTags {
+    pub latest: String,
+}
+
+impl NpmIndex {
+    /// Produces the published versions, leaving out any that aren't valid semver.
+    pub fn into_versions(self) -> BTreeSet<Version> {
+        self.versions
+            .keys()
+            .filter_map(|version| Version::parse(version).ok())
+            .collect()
+    }
+
+    /// Produces the version of the `latest` dist-tag.
+    pub fn latest(&self) -> Fallible<Version> {
+        Version::parse(&self.dist_tags.latest).unknown()
+    }
+}
+
+/// The `ETag` and `Last-Modified` headers of the cached Node index.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct IndexValidators {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
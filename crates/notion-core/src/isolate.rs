@@ -0,0 +1,133 @@
+//! Provides the isolated environment of `notion exec --isolated`, for reproducing issues
+//! that only happen on some machines.
+//!
+//! A command run in isolation inherits none of the environment except a few variables
+//! that describe the user and terminal (`HOME`, `TERM`, `LANG`, ...). Its `PATH` finds
+//! the executables of the pinned toolchain first, followed by the entries of the current
+//! `PATH` except Notion's shims and the entries under the root of a version manager (nvm,
+//! nodenv, fnm, n). The variables those managers and npm set are removed too. System
+//! directories such as `/usr/bin` are kept even if they hold a `node` of their own, as
+//! they hold every other executable as well, and the pinned Node comes first anyway.
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use crate::envfile;
+use crate::path;
+use crate::platform::Image;
+use notion_fail::{Fallible, ResultExt};
+
+/// The variables passed through to a command run in isolation.
+const PRESERVED: &[&str] = &[
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "LANG",
+    "TMPDIR",
+    "TZ",
+    // needed for processes to start at all on Windows
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+];
+
+/// The variables holding the root of a version manager, whose `PATH` entries are removed.
+const VERSION_MANAGER_ROOTS: &[&str] = &["NVM_DIR", "NODENV_ROOT", "FNM_DIR", "N_PREFIX"];
+
+/// Whether the variable `name` is passed through to a command run in isolation.
+fn is_preserved(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    upper.starts_with("LC_") || PRESERVED.contains(&upper.as_str())
+}
+
+/// Whether the `PATH` entry `dir` leads to the Node installations of a version manager.
+fn is_foreign_node(dir: &Path, manager_roots: &[PathBuf]) -> bool {
+    manager_roots.iter().any(|root| dir.starts_with(root))
+}
+
+/// Produces the `PATH` of a command run in isolation in `image`.
+pub fn path(image: &Image) -> Fallible<OsString> {
+    let shim_dir = path::shim_dir()?;
+    let manager_roots: Vec<PathBuf> = VERSION_MANAGER_ROOTS
+        .iter()
+        .filter_map(|var| env::var_os(var))
+        .filter(|root| !root.is_empty())
+        .map(PathBuf::from)
+        .collect();
+
+    let current = env::var_os("PATH").unwrap_or_default();
+    let entries = image.bins()?.into_iter().chain(
+        env::split_paths(&current)
+            .filter(|dir| *dir != shim_dir && !is_foreign_node(dir, &manager_roots)),
+    );
+    env::join_paths(entries).unknown()
+}
+
+/// Produces the complete environment of a command run in isolation in `image`.
+pub fn variables(image: &Image) -> Fallible<Vec<(OsString, OsString)>> {
+    let mut variables: Vec<(OsString, OsString)> = env::vars_os()
+        .filter(|(name, _)| name.to_str().map(is_preserved).unwrap_or(false))
+        .collect();
+
+    variables.push((
+        OsString::from("NOTION_HOME"),
+        path::notion_home()?.into_os_string(),
+    ));
+    variables.push((OsString::from("PATH"), self::path(image)?));
+    for (name, value) in envfile::variables(image)? {
+        if name != "PATH" {
+            variables.push((OsString::from(name), OsString::from(value)));
+        }
+    }
+
+    Ok(variables)
+}
+
+/// Builds the command running `exe` with `args` in isolation in `image`.
+pub fn command<A: AsRef<OsStr>>(
+    image: &Image,
+    exe: &OsStr,
+    args: &[A],
+) -> Fallible<std::process::Command> {
+    let mut command = std::process::Command::new(exe);
+    command.args(args).env_clear().envs(variables(image)?);
+    Ok(command)
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{is_foreign_node, is_preserved};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_is_preserved() {
+        assert!(is_preserved("HOME"));
+        assert!(is_preserved("LC_ALL"));
+        assert!(is_preserved("SystemRoot"));
+        assert!(!is_preserved("NVM_DIR"));
+        assert!(!is_preserved("NODE_PATH"));
+        assert!(!is_preserved("npm_config_prefix"));
+        assert!(!is_preserved("PATH"));
+    }
+
+    #[test]
+    fn test_is_foreign_node() {
+        let nvm = PathBuf::from("/home/user/.nvm");
+        assert!(is_foreign_node(
+            &nvm.join("versions").join("node").join("v8.9.4").join("bin"),
+            &[nvm.clone()]
+        ));
+        assert!(!is_foreign_node(&PathBuf::from("/usr/bin"), &[nvm]));
+    }
+}
@@ -0,0 +1,208 @@
+//! Provides the install journal, which makes fetching a tool recoverable after a crash.
+//!
+//! The inventory considers a version fetched as soon as its archive is in the inventory
+//! directory, so a download or unpack that is interrupted by a crash or power loss
+//! would otherwise leave a version that looks installed but has a truncated archive or
+//! a half-moved image. Each fetch records the step it has reached in a journal entry
+//! under `tools/inventory/journal`, which is removed once the fetch completes. Any entry
+//! still present when the inventory is next loaded belongs to an interrupted fetch, which
+//! is then either resumed from its verified archive or rolled back.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use tempfile::NamedTempFile;
+
+use crate::distro::deno::DenoKind;
+use crate::distro::kind::{ArchiveDistro, ToolKind};
+use crate::distro::node::NodeKind;
+use crate::distro::npm::NpmKind;
+use crate::distro::yarn::YarnKind;
+use crate::fs::{ensure_dir_exists, is_read_only_home, read_dir_eager};
+use crate::interrupt::{self, Cleanup, Registration};
+use crate::lock::LockFile;
+use crate::path;
+use notion_fail::{Fallible, ResultExt};
+
+/// The steps of a fetch, in the order they are reached.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Step {
+    /// The archive is being downloaded into the inventory, and may be truncated.
+    Downloading,
+    /// The archive has been downloaded, but its checksum hasn't been recorded yet.
+    Downloaded,
+    /// The archive is complete and its checksum has been recorded.
+    Verified,
+    /// The unpacked archive is being moved into its image directory.
+    Unpacking,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Entry {
+    tool: String,
+    version: String,
+    step: Step,
+}
+
+/// The journal entry of a fetch in progress.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    file: PathBuf,
+    entry: Entry,
+    /// Removes the entry if the process is interrupted, along with the partial fetch.
+    _interrupt: Option<Registration>,
+}
+
+impl Journal {
+    /// Starts the journal entry for fetching `version` of `tool`, at `step`.
+    pub(crate) fn begin(tool: &str, version: &Version, step: Step) -> Fallible<Self> {
+        let dir = path::install_journal_dir()?;
+        ensure_dir_exists(&dir)?;
+        let file = dir.join(file_name(tool, version));
+        let mut journal = Journal {
+            _interrupt: Some(interrupt::register(vec![Cleanup::RemoveFile(file.clone())])),
+            file,
+            entry: Entry {
+                tool: tool.to_string(),
+                version: version.to_string(),
+                step,
+            },
+        };
+        journal.record(step)?;
+        Ok(journal)
+    }
+
+    fn load(file: &Path) -> Option<Self> {
+        let entry = serde_json::from_reader(File::open(file).ok()?).ok()?;
+        Some(Journal {
+            file: file.to_path_buf(),
+            entry,
+            _interrupt: None,
+        })
+    }
+
+    pub(crate) fn step(&self) -> Step {
+        self.entry.step
+    }
+
+    /// Records that the fetch has reached `step`. The entry is replaced atomically, so
+    /// that a crash while recording can't leave it unreadable.
+    pub(crate) fn record(&mut self, step: Step) -> Fallible<()> {
+        self.entry.step = step;
+        let dir = self.file.parent().unwrap_or_else(|| Path::new("."));
+        let temp = NamedTempFile::new_in(dir).unknown()?;
+        {
+            let mut file: &File = temp.as_file();
+            file.write_all(serde_json::to_string(&self.entry).unknown()?.as_bytes())
+                .unknown()?;
+        }
+        temp.persist(&self.file).unknown()?;
+        Ok(())
+    }
+
+    /// Removes the journal entry once the fetch has completed.
+    pub(crate) fn finish(self) -> Fallible<()> {
+        match fs::remove_file(&self.file) {
+            Ok(()) => Ok(()),
+            Err(ref error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error).unknown(),
+        }
+    }
+}
+
+fn file_name(tool: &str, version: &Version) -> String {
+    format!("{}-{}.json", tool.to_lowercase(), version)
+}
+
+/// Resumes or rolls back the fetches that were interrupted, as recorded by the journal
+/// entries left behind.
+pub(crate) fn recover() -> Fallible<()> {
+    let dir = path::install_journal_dir()?;
+    if is_read_only_home() || !has_entries(&dir) {
+        return Ok(());
+    }
+
+    // entries of fetches still running in other processes are removed before they
+    // release the lock, so any entry left once it's acquired has been abandoned
+    let _lock = LockFile::acquire(&path::inventory_lock_file()?)?;
+
+    for (entry, metadata) in read_dir_eager(&dir)? {
+        if !metadata.is_file() {
+            continue;
+        }
+        let journal = match Journal::load(&entry.path()) {
+            Some(journal) => journal,
+            None => {
+                fs::remove_file(entry.path()).unknown()?;
+                continue;
+            }
+        };
+        let version = match Version::parse(&journal.entry.version) {
+            Ok(version) => version,
+            Err(_) => {
+                journal.finish()?;
+                continue;
+            }
+        };
+
+        match journal.entry.tool.as_str() {
+            tool if tool == NodeKind::DISPLAY_NAME => {
+                ArchiveDistro::<NodeKind>::recover(version, journal)?
+            }
+            tool if tool == YarnKind::DISPLAY_NAME => {
+                ArchiveDistro::<YarnKind>::recover(version, journal)?
+            }
+            tool if tool == DenoKind::DISPLAY_NAME => {
+                ArchiveDistro::<DenoKind>::recover(version, journal)?
+            }
+            tool if tool == NpmKind::DISPLAY_NAME => {
+                ArchiveDistro::<NpmKind>::recover(version, journal)?
+            }
+            _ => journal.finish()?,
+        }
+    }
+    Ok(())
+}
+
+fn has_entries(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{file_name, Entry, Step};
+    use semver::Version;
+    use serde_json;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name("Node", &Version::parse("10.13.0").unwrap()),
+            "node-10.13.0.json"
+        );
+    }
+
+    #[test]
+    fn test_entry_round_trip() {
+        let entry = Entry {
+            tool: "Yarn".to_string(),
+            version: "1.12.3".to_string(),
+            step: Step::Downloaded,
+        };
+        let serialized = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"tool":"Yarn","version":"1.12.3","step":"downloaded"}"#
+        );
+        assert_eq!(serde_json::from_str::<Entry>(&serialized).unwrap(), entry);
+        assert!(Step::Downloading < Step::Verified);
+    }
+}
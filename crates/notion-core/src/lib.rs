@@ -2,21 +2,51 @@
 
 #![cfg_attr(feature = "universal-docs", feature(doc_cfg))]
 
+pub mod alias;
+mod audit;
+pub mod check;
+pub mod checksum;
+pub mod config;
+pub mod conflict;
+mod credentials;
+pub mod diagnostics;
+mod diff;
+pub mod disk;
 mod distro;
 pub mod env;
+pub mod envfile;
 mod event;
+pub mod export;
+pub mod gc;
 pub(crate) mod fs;
 mod hook;
+pub mod interrupt;
 pub mod inventory;
+pub mod isolate;
+mod journal;
+pub mod lifecycle;
+mod lock;
 pub mod manifest;
+pub mod mirror;
 pub mod monitor;
+mod network;
+pub mod output;
+pub mod package;
 pub mod path;
 pub mod platform;
+pub mod policy;
+pub mod prefetch;
 pub mod project;
+pub mod prompt;
+pub mod registry;
+mod retry;
 pub mod session;
+pub mod setup;
 pub mod shell;
 pub mod shim;
+pub mod snapshot;
 pub mod style;
 pub mod tool;
 pub mod toolchain;
+pub mod updater;
 pub mod version;
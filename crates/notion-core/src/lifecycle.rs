@@ -0,0 +1,623 @@
+//! Provides the support status of Node versions, and the warnings about pinned versions
+//! that are no longer supported.
+//!
+//! The Node index only says which releases are LTS releases, so the dates each release
+//! line leaves active LTS support and reaches its end of life come from the release
+//! schedule of the Node project (`schedule.json` in https://github.com/nodejs/Release).
+//! It is fetched at most once a day and cached next to the Node index, as
+//! `cache/node/schedule.json`. Offline, or when it can't be fetched, the cached copy is
+//! used, or else the schedule built into Notion. A release line newer than the schedule
+//! is taken to follow the newest known line of its kind (even lines become LTS lines, odd
+//! lines don't), a year later for every two major versions.
+//!
+//! When the Node version a shim runs (the current project's, or the user default) is
+//! past its end of life or past its LTS window, a warning is shown at most once a day
+//! for each version. The day's warnings are recorded in `tools/node-support.json`,
+//! along with the projects they were checked in, which `notion outdated` checks again.
+//! Where nothing can be recorded, as in a read-only `NOTION_HOME`, no warning is shown,
+//! so that it doesn't repeat on every run.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use crate::env::is_offline;
+use crate::fs::{ensure_containing_dir_exists, is_read_only_home, read_file_opt};
+use crate::inventory::cached_node_lts;
+use crate::network;
+use crate::package;
+use crate::path;
+use crate::project::Project;
+use crate::session::{Session, ToolSource};
+use crate::style::display_warning;
+use notion_fail::{Fallible, ResultExt};
+
+#[cfg(feature = "mock-network")]
+use mockito;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "mock-network")] {
+        fn public_node_schedule() -> String {
+            format!("{}/node-schedule.json", mockito::SERVER_URL)
+        }
+    } else {
+        /// Returns the URL of the release schedule in the public repository of the Node
+        /// release team.
+        fn public_node_schedule() -> String {
+            "https://raw.githubusercontent.com/nodejs/Release/master/schedule.json".to_string()
+        }
+    }
+}
+
+/// How long a fetched release schedule is used before it is fetched again.
+const SCHEDULE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A calendar day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: u32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    const fn new(year: u32, month: u32, day: u32) -> Self {
+        Date { year, month, day }
+    }
+
+    /// The day `days` days after 1970-01-01.
+    fn from_days(days: u64) -> Self {
+        // the civil calendar algorithm of Howard Hinnant, for days after the epoch
+        let days = days + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date::new(year as u32, month as u32, day as u32)
+    }
+
+    /// Today, in UTC.
+    pub fn today() -> Self {
+        Date::from_days(today_days())
+    }
+
+    /// Parses a day written as `YYYY-MM-DD`.
+    fn parse(src: &str) -> Option<Self> {
+        let mut parts = src.splitn(3, '-').map(|part| part.parse::<u32>().ok());
+        match (parts.next()?, parts.next()?, parts.next()?) {
+            (Some(year), Some(month), Some(day)) => Some(Date::new(year, month, day)),
+            _ => None,
+        }
+    }
+
+    /// The same day `years` years later (the 28th for a 29th of February).
+    fn add_years(self, years: u32) -> Self {
+        let day = if self.month == 2 && self.day == 29 {
+            28
+        } else {
+            self.day
+        };
+        Date::new(self.year + years, self.month, day)
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The days since 1970-01-01, in UTC.
+fn today_days() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / (24 * 60 * 60))
+        .unwrap_or(0)
+}
+
+/// A release line of Node, by major version.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ReleaseLine {
+    major: u64,
+    /// The day an LTS line left active LTS for maintenance, if it is an LTS line.
+    maintenance: Option<Date>,
+    /// The last day the line was supported.
+    end: Date,
+}
+
+const fn line(major: u64, maintenance: Option<Date>, end: Date) -> ReleaseLine {
+    ReleaseLine {
+        major,
+        maintenance,
+        end,
+    }
+}
+
+/// The release schedule of Node (https://github.com/nodejs/Release), from Node 4 on, as
+/// it was when this version of Notion was released.
+const SCHEDULE: &[ReleaseLine] = &[
+    line(4, Some(Date::new(2017, 4, 1)), Date::new(2018, 4, 30)),
+    line(5, None, Date::new(2016, 6, 30)),
+    line(6, Some(Date::new(2018, 4, 30)), Date::new(2019, 4, 30)),
+    line(7, None, Date::new(2017, 6, 30)),
+    line(8, Some(Date::new(2019, 1, 1)), Date::new(2019, 12, 31)),
+    line(9, None, Date::new(2018, 6, 30)),
+    line(10, Some(Date::new(2020, 5, 19)), Date::new(2021, 4, 30)),
+    line(11, None, Date::new(2019, 6, 1)),
+    line(12, Some(Date::new(2020, 11, 30)), Date::new(2022, 4, 30)),
+    line(13, None, Date::new(2020, 6, 1)),
+    line(14, Some(Date::new(2021, 10, 19)), Date::new(2023, 4, 30)),
+    line(15, None, Date::new(2021, 6, 1)),
+    line(16, Some(Date::new(2022, 10, 18)), Date::new(2023, 9, 11)),
+    line(17, None, Date::new(2022, 6, 1)),
+    line(18, Some(Date::new(2023, 10, 18)), Date::new(2025, 4, 30)),
+    line(19, None, Date::new(2023, 6, 1)),
+    line(20, Some(Date::new(2024, 10, 22)), Date::new(2026, 4, 30)),
+    line(21, None, Date::new(2024, 6, 1)),
+    line(22, Some(Date::new(2025, 10, 21)), Date::new(2027, 4, 30)),
+    line(23, None, Date::new(2025, 6, 1)),
+    line(24, Some(Date::new(2026, 10, 20)), Date::new(2028, 4, 30)),
+    line(25, None, Date::new(2026, 6, 1)),
+];
+
+/// The last day that Node 0.x (and io.js, whose 1.x to 3.x releases came before Node 4)
+/// was supported.
+const LEGACY_END: Date = Date::new(2016, 12, 31);
+
+/// A release line in the `schedule.json` of the Node release team.
+#[derive(Deserialize)]
+struct SerialReleaseLine {
+    lts: Option<String>,
+    maintenance: Option<String>,
+    end: String,
+}
+
+/// The release schedule of Node.
+#[derive(Debug, PartialEq)]
+pub struct Schedule {
+    lines: Vec<ReleaseLine>,
+}
+
+impl Schedule {
+    /// The schedule built into Notion.
+    fn builtin() -> Self {
+        Schedule {
+            lines: SCHEDULE.to_vec(),
+        }
+    }
+
+    /// Parses the `schedule.json` of the Node release team, which lists the release
+    /// lines by version (`v0.10`, `v4`, ...). Only the lines from Node 4 on are kept.
+    fn parse(src: &str) -> Option<Self> {
+        let serial: BTreeMap<String, SerialReleaseLine> = serde_json::from_str(src).ok()?;
+        let mut lines = Vec::new();
+        for (version, line) in serial {
+            let major = match version.trim_start_matches('v').parse::<u64>() {
+                Ok(major) if major >= 4 => major,
+                _ => continue,
+            };
+            // only an LTS line is past its LTS window once in maintenance
+            let maintenance = match line.lts {
+                Some(_) => line.maintenance.as_ref().and_then(|date| Date::parse(date)),
+                None => None,
+            };
+            lines.push(ReleaseLine {
+                major,
+                maintenance,
+                end: Date::parse(&line.end)?,
+            });
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        lines.sort_by_key(|line| line.major);
+        Some(Schedule { lines })
+    }
+
+    /// The current release schedule: the cached one if it was fetched in the last day,
+    /// or else the one fetched now. Offline, or if it can't be fetched, the cached one
+    /// however old, or else the built-in one.
+    pub fn current() -> Fallible<Self> {
+        let file = path::node_schedule_file()?;
+        let cached = read_file_opt(&file)
+            .unknown()?
+            .and_then(|src| Schedule::parse(&src));
+        if let Some(schedule) = cached {
+            if is_offline() || is_fresh(&file) {
+                return Ok(schedule);
+            }
+            return Ok(fetch_schedule()?.unwrap_or(schedule));
+        }
+        if is_offline() {
+            return Ok(Schedule::builtin());
+        }
+        Ok(fetch_schedule()?.unwrap_or_else(Schedule::builtin))
+    }
+
+    /// The release line of `major`. A line newer than the schedule follows the newest
+    /// line with the same parity, a year later for every two major versions.
+    fn line(&self, major: u64) -> Option<ReleaseLine> {
+        if let Some(line) = self.lines.iter().find(|line| line.major == major) {
+            return Some(*line);
+        }
+        let known = self
+            .lines
+            .iter()
+            .filter(|line| line.major < major && line.major % 2 == major % 2)
+            .last()?;
+        let years = ((major - known.major) / 2) as u32;
+        Some(ReleaseLine {
+            major,
+            maintenance: known.maintenance.map(|date| date.add_years(years)),
+            end: known.end.add_years(years),
+        })
+    }
+}
+
+/// Whether `file` was written in the last `SCHEDULE_TTL`.
+fn is_fresh(file: &Path) -> bool {
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map_or(false, |age| age < SCHEDULE_TTL)
+}
+
+/// Fetches the release schedule and caches it, or returns `None` if it can't be fetched.
+fn fetch_schedule() -> Fallible<Option<Schedule>> {
+    let url = public_node_schedule();
+    let mut response = match network::get(&url)?.send() {
+        Ok(response) => response,
+        Err(_) => return Ok(None),
+    };
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+    let src = match response.text() {
+        Ok(src) => src,
+        Err(_) => return Ok(None),
+    };
+    let schedule = match Schedule::parse(&src) {
+        Some(schedule) => schedule,
+        None => return Ok(None),
+    };
+    if !is_read_only_home() {
+        let file = path::node_schedule_file()?;
+        ensure_containing_dir_exists(&file)?;
+        fs::write(file, src).unknown()?;
+    }
+    Ok(Some(schedule))
+}
+
+/// Whether a Node version is still supported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Support {
+    /// Its release line is current or in active LTS.
+    Active,
+    /// Its LTS line is past its LTS window, and only receives critical fixes until
+    /// `end`.
+    Maintenance { end: Date },
+    /// Its release line reached its end of life on `end`, and receives no fixes at all.
+    EndOfLife { end: Date },
+}
+
+impl Support {
+    /// The support of the Node `version` on the day `today`, according to `schedule`.
+    pub fn of(version: &Version, today: Date, schedule: &Schedule) -> Self {
+        if version.major < 4 {
+            return Support::EndOfLife { end: LEGACY_END };
+        }
+        match schedule.line(version.major) {
+            Some(line) if today > line.end => Support::EndOfLife { end: line.end },
+            Some(line) if line.maintenance.map_or(false, |start| today >= start) => {
+                Support::Maintenance { end: line.end }
+            }
+            _ => Support::Active,
+        }
+    }
+
+    /// Whether the version needs attention.
+    pub fn is_outdated(&self) -> bool {
+        *self != Support::Active
+    }
+}
+
+impl Display for Support {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Support::Active => write!(f, "is supported"),
+            Support::Maintenance { end } => write!(
+                f,
+                "is past its LTS window, and only receives critical fixes until its end of life on {}",
+                end
+            ),
+            Support::EndOfLife { end } => write!(
+                f,
+                "reached its end of life on {}, and no longer receives fixes, including security fixes",
+                end
+            ),
+        }
+    }
+}
+
+/// The record of the warnings shown today and of the projects they were checked in.
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct SupportState {
+    /// The day of the recorded warnings, in days since 1970-01-01.
+    #[serde(default)]
+    day: u64,
+    /// The Node versions warned about on that day.
+    #[serde(default)]
+    warned: BTreeSet<String>,
+    /// The roots of the projects whose Node version was checked.
+    #[serde(default)]
+    projects: BTreeSet<String>,
+}
+
+impl SupportState {
+    fn current() -> Fallible<Self> {
+        match read_file_opt(&path::node_support_file()?).unknown()? {
+            // a corrupted record only costs a repeated warning
+            Some(src) => Ok(serde_json::from_str(&src).unwrap_or_default()),
+            None => Ok(SupportState::default()),
+        }
+    }
+
+    fn save(&self) -> Fallible<()> {
+        let file = path::node_support_file()?;
+        ensure_containing_dir_exists(&file)?;
+        fs::write(file, serde_json::to_string_pretty(self).unknown()?).unknown()
+    }
+}
+
+/// Warns about the Node `version` selected by `source` if it needs attention, unless it
+/// was already warned about today. The root of the project that selected it, if any, is
+/// recorded for `notion outdated`.
+pub(crate) fn warn_once_a_day(
+    version: &Version,
+    source: ToolSource,
+    project: Option<&Path>,
+) -> Fallible<()> {
+    if is_read_only_home() {
+        return Ok(());
+    }
+
+    let today = today_days();
+    let mut state = SupportState::current()?;
+    let mut changed = false;
+    if let Some(root) = project {
+        changed |= state.projects.insert(root.to_string_lossy().into_owned());
+    }
+    if state.day != today {
+        state.day = today;
+        state.warned.clear();
+        changed = true;
+    }
+    // the schedule is only needed for a version not warned about yet today
+    if !state.warned.contains(&version.to_string()) {
+        let support = Support::of(version, Date::from_days(today), &Schedule::current()?);
+        if support.is_outdated() {
+            state.warned.insert(version.to_string());
+            changed = true;
+            display_warning(warning(version, source, support)?);
+        }
+    }
+    if changed {
+        state.save()?;
+    }
+    Ok(())
+}
+
+fn warning(version: &Version, source: ToolSource, support: Support) -> Fallible<String> {
+    let mut message = format!("Node v{} ({}) {}.", version, source, support);
+    if let Some(lts) = cached_node_lts()? {
+        if lts > *version {
+            let command = match source {
+                ToolSource::User => "notion install node lts",
+                _ => "notion pin node lts",
+            };
+            message.push_str(&format!(
+                "\n\nThe newest LTS release is v{} (`{}`).",
+                lts, command
+            ));
+        }
+    }
+    message.push_str(
+        "\n\nRun `notion outdated` to see every pinned Node version that needs attention.",
+    );
+    Ok(message)
+}
+
+/// A pinned Node version that needs attention.
+#[derive(Debug, PartialEq)]
+pub struct Outdated {
+    /// What pins the version: `user default`, `project <root>` or `package <name>`.
+    pub pin: String,
+    pub version: Version,
+    pub support: Support,
+}
+
+impl Outdated {
+    /// Renders this pin as a line of `notion outdated` output.
+    pub fn to_line(&self) -> String {
+        format!("{}: Node v{} {}", self.pin, self.version, self.support)
+    }
+}
+
+/// Lists the pinned Node versions that need attention: the user default, the current
+/// project's, those of the projects that the warnings were checked in (that still pin
+/// one), and the ones that installed packages run with.
+pub fn outdated(session: &Session) -> Fallible<Vec<Outdated>> {
+    let today = Date::today();
+    let schedule = Schedule::current()?;
+    let mut pins = Vec::new();
+    if let Some(platform) = session.user_platform()? {
+        pins.push(("user default".to_string(), platform.node_runtime.clone()));
+    }
+
+    let mut roots: BTreeSet<PathBuf> = SupportState::current()?
+        .projects
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    if let Some(project) = session.project()? {
+        roots.insert(project.root().to_path_buf());
+    }
+    for root in roots {
+        // a project that was moved or deleted since is skipped
+        if let Some(project) = Project::for_dir(&root)? {
+            if project.root() != root {
+                continue;
+            }
+            if let Some(platform) = project.platform() {
+                pins.push((
+                    format!("project {}", root.display()),
+                    platform.node_runtime.clone(),
+                ));
+            }
+        }
+    }
+
+    for package in package::installed()? {
+        pins.push((
+            format!("package {}", package.name),
+            package.platform.node_runtime.clone(),
+        ));
+    }
+
+    Ok(pins
+        .into_iter()
+        .map(|(pin, version)| Outdated {
+            support: Support::of(&version, today, &schedule),
+            pin,
+            version,
+        })
+        .filter(|outdated| outdated.support.is_outdated())
+        .collect())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{Date, Schedule, Support};
+    use semver::Version;
+
+    const SCHEDULE_JSON: &str = r#"{
+  "v0.10": { "start": "2013-03-11", "end": "2016-10-31" },
+  "v16": {
+    "start": "2021-04-20",
+    "lts": "2021-10-26",
+    "maintenance": "2022-10-18",
+    "end": "2023-09-11",
+    "codename": "Gallium"
+  },
+  "v17": { "start": "2021-10-19", "maintenance": "2022-04-01", "end": "2022-06-01" }
+}"#;
+
+    #[test]
+    fn test_from_days() {
+        assert_eq!(Date::from_days(0), Date::new(1970, 1, 1));
+        assert_eq!(Date::from_days(11_016), Date::new(2000, 2, 29));
+        assert_eq!(Date::from_days(20_740), Date::new(2026, 10, 14));
+        assert_eq!(Date::new(2026, 4, 30).to_string(), "2026-04-30");
+    }
+
+    #[test]
+    fn test_support() {
+        let today = Date::new(2024, 1, 15);
+        let schedule = Schedule::builtin();
+        let support =
+            |version: &str| Support::of(&Version::parse(version).unwrap(), today, &schedule);
+
+        assert_eq!(support("20.11.0"), Support::Active);
+        assert_eq!(support("21.5.0"), Support::Active);
+        assert_eq!(
+            support("18.19.0"),
+            Support::Maintenance {
+                end: Date::new(2025, 4, 30)
+            }
+        );
+        assert_eq!(
+            support("16.20.2"),
+            Support::EndOfLife {
+                end: Date::new(2023, 9, 11)
+            }
+        );
+        assert_eq!(
+            support("0.12.18"),
+            Support::EndOfLife {
+                end: Date::new(2016, 12, 31)
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let schedule = Schedule::parse(SCHEDULE_JSON).unwrap();
+        let today = Date::new(2022, 5, 1);
+        let support =
+            |version: &str| Support::of(&Version::parse(version).unwrap(), today, &schedule);
+
+        assert_eq!(support("16.15.0"), Support::Active);
+        // odd lines have no LTS window
+        assert_eq!(support("17.9.0"), Support::Active);
+        assert_eq!(
+            Support::of(
+                &Version::parse("17.9.0").unwrap(),
+                Date::new(2022, 7, 1),
+                &schedule
+            ),
+            Support::EndOfLife {
+                end: Date::new(2022, 6, 1)
+            }
+        );
+
+        assert_eq!(Schedule::parse("{}"), None);
+        assert_eq!(Schedule::parse("not json"), None);
+    }
+
+    #[test]
+    fn test_support_newer_than_schedule() {
+        let schedule = Schedule::builtin();
+        let support = |version: &str, today: Date| {
+            Support::of(&Version::parse(version).unwrap(), today, &schedule)
+        };
+
+        // an LTS line follows 24, a year later for every two major versions
+        assert_eq!(support("30.0.0", Date::new(2029, 1, 1)), Support::Active);
+        assert_eq!(
+            support("30.0.0", Date::new(2030, 1, 1)),
+            Support::Maintenance {
+                end: Date::new(2031, 4, 30)
+            }
+        );
+        assert_eq!(
+            support("30.0.0", Date::new(2032, 1, 1)),
+            Support::EndOfLife {
+                end: Date::new(2031, 4, 30)
+            }
+        );
+        // and a current line follows 25
+        assert_eq!(
+            support("27.0.0", Date::new(2028, 1, 1)),
+            Support::EndOfLife {
+                end: Date::new(2027, 6, 1)
+            }
+        );
+    }
+}
@@ -0,0 +1,417 @@
+//! Provides a lock file protocol for serializing modifications of the inventory.
+//!
+//! Advisory locks (`flock`) are unreliable when `NOTION_HOME` lives on NFS, as is
+//! common for network home directories, so the lock is instead a file recording the
+//! process id and host name of its owner. The lock file is written under a unique name
+//! and then hard-linked into place, which is atomic even on NFS. A lock is considered
+//! stale once its owner is no longer running (if it's on the same host) or once it
+//! hasn't been modified for `STALE_AGE`, so a crashed process can't block Notion forever.
+//! While it is held, the lock is touched every `HEARTBEAT_INTERVAL`, so that holding it
+//! for longer (as building Node from source does) doesn't make it look abandoned.
+//!
+//! A stale lock is taken over by renaming it to a unique name, which only one process
+//! can do, and it is only removed once it is confirmed to still be the same stale lock.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::fs::ensure_containing_dir_exists;
+use crate::interrupt::{self, Cleanup, Registration};
+use crate::output;
+use notion_fail::{throw, FailExt, Fallible, ResultExt};
+
+/// The age after which a lock is considered abandoned, even if its owner can't be
+/// confirmed to have exited (e.g. because it runs on another host).
+const STALE_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// The interval at which a held lock is touched, well within `STALE_AGE`.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The interval between attempts to acquire a lock held by another process.
+const RETRY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The process holding a lock.
+#[derive(Debug, PartialEq)]
+struct Owner {
+    pid: u32,
+    host: String,
+}
+
+impl Owner {
+    fn current() -> Self {
+        Owner {
+            pid: process::id(),
+            host: host_name(),
+        }
+    }
+
+    fn parse(src: &str) -> Option<Self> {
+        let mut lines = src.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let host = lines.next()?.trim().to_string();
+        Some(Owner { pid, host })
+    }
+
+    fn serialize(&self) -> String {
+        format!("{}\n{}\n", self.pid, self.host)
+    }
+
+    /// Reads the owner of the lock file at `path`, if it is recognizable.
+    fn read(path: &Path) -> Option<Self> {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| Owner::parse(&contents))
+    }
+
+    /// Determines whether this owner has abandoned the lock file at `path`.
+    fn is_stale(&self, path: &Path) -> bool {
+        if self.host == host_name() && !is_running(self.pid) {
+            return true;
+        }
+
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map(|age| age > STALE_AGE)
+            .unwrap_or(false)
+    }
+}
+
+/// An exclusive lock, held until it is dropped.
+pub(crate) struct LockFile {
+    path: PathBuf,
+    owner: Owner,
+    /// Stops the heartbeat when dropped.
+    heartbeat: Option<(Sender<()>, JoinHandle<()>)>,
+    /// Releases the lock if the process is interrupted while holding it.
+    _interrupt: Registration,
+}
+
+impl LockFile {
+    /// Acquires the lock at `path`, waiting for any other process holding it.
+    pub(crate) fn acquire(path: &Path) -> Fallible<LockFile> {
+        ensure_containing_dir_exists(&path)?;
+        let owner = Owner::current();
+        let mut waiting = false;
+
+        loop {
+            if try_create(path, &owner).unknown()? {
+                return Ok(LockFile {
+                    path: path.to_path_buf(),
+                    heartbeat: Some(heartbeat(path.to_path_buf(), Owner::current())),
+                    owner,
+                    _interrupt: interrupt::register(vec![Cleanup::RemoveFile(path.to_path_buf())]),
+                });
+            }
+
+            // the lock may have been released since we tried to create it
+            let holder = match fs::read_to_string(path) {
+                Ok(contents) => Owner::parse(&contents),
+                Err(ref error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => throw!(error.unknown()),
+            };
+
+            match holder {
+                Some(ref holder) if !holder.is_stale(path) => {
+                    if !waiting {
+                        output::info(format!(
+                            "Waiting for another Notion process (pid {} on {}) to finish...",
+                            holder.pid, holder.host
+                        ));
+                        waiting = true;
+                    }
+                    thread::sleep(RETRY_INTERVAL);
+                }
+                // the lock was abandoned, or its contents are unrecognizable
+                _ => {
+                    take_over(path, holder.as_ref(), &owner).unknown()?;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        // disconnecting the heartbeat stops it, before the lock is removed
+        if let Some((stop, thread)) = self.heartbeat.take() {
+            drop(stop);
+            let _ = thread.join();
+        }
+
+        // only remove the lock if it wasn't taken over after being considered stale
+        if Owner::read(&self.path).as_ref() == Some(&self.owner) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Starts touching the lock file at `path` held by `owner` every `HEARTBEAT_INTERVAL`,
+/// until the returned sender is dropped.
+fn heartbeat(path: PathBuf, owner: Owner) -> (Sender<()>, JoinHandle<()>) {
+    let (stop, stopped) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(HEARTBEAT_INTERVAL) {
+            let _ = touch(&path, &owner);
+        }
+    });
+    (stop, thread)
+}
+
+/// Updates the modification time of the lock file at `path`, by writing its contents
+/// again, if it is still held by `owner`. A lock that was removed isn't created again.
+fn touch(path: &Path, owner: &Owner) -> io::Result<()> {
+    if Owner::read(path).as_ref() != Some(owner) {
+        return Ok(());
+    }
+    OpenOptions::new()
+        .write(true)
+        .open(path)?
+        .write_all(owner.serialize().as_bytes())
+}
+
+/// Takes over the lock file at `path`, which was read as held by `holder` and found to
+/// be stale. The lock is renamed to a name unique to `owner`, which only one of the
+/// processes taking it over at the same time can do, and is then removed if its holder
+/// is still `holder` and it is still stale. If another process released it and took it
+/// anew in the meantime, the takeover fails and the fresh lock is put back, to be
+/// waited for.
+fn take_over(path: &Path, holder: Option<&Owner>, owner: &Owner) -> io::Result<()> {
+    let claimed = unique_name(path, owner, "stale");
+    match fs::rename(path, &claimed) {
+        Ok(()) => {}
+        // another process took it over first
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    }
+
+    let claimed_holder = Owner::read(&claimed);
+    let unchanged = claimed_holder.as_ref() == holder
+        && claimed_holder.map_or(true, |holder| holder.is_stale(&claimed));
+    if !unchanged {
+        match fs::hard_link(&claimed, path) {
+            Ok(()) => {}
+            Err(ref error) if error.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(error) => {
+                fs::remove_file(&claimed)?;
+                return Err(error);
+            }
+        }
+    }
+    fs::remove_file(&claimed)
+}
+
+/// A name for a file next to the lock file at `path`, unique to `owner`.
+fn unique_name(path: &Path, owner: &Owner, purpose: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(format!(".{}.{}.{}", purpose, owner.host, owner.pid));
+    path.with_file_name(name)
+}
+
+/// Attempts to atomically create the lock file at `path` for `owner`, returning whether
+/// the lock was acquired.
+fn try_create(path: &Path, owner: &Owner) -> io::Result<bool> {
+    let temp = unique_name(path, owner, "new");
+
+    File::create(&temp)?.write_all(owner.serialize().as_bytes())?;
+
+    let acquired = match fs::hard_link(&temp, path) {
+        Ok(()) => true,
+        Err(ref error) if error.kind() == io::ErrorKind::AlreadyExists => false,
+        // NFS can report a failure for a link that was created, so check the link count
+        Err(_) if link_count(&temp)? == 2 => true,
+        // the filesystem doesn't support hard links, so fall back to exclusive creation
+        Err(_) => match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(mut file) => {
+                file.write_all(owner.serialize().as_bytes())?;
+                true
+            }
+            Err(ref error) if error.kind() == io::ErrorKind::AlreadyExists => false,
+            Err(error) => {
+                fs::remove_file(&temp)?;
+                return Err(error);
+            }
+        },
+    };
+
+    fs::remove_file(&temp)?;
+    Ok(acquired)
+}
+
+#[cfg(unix)]
+fn link_count(path: &Path) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(fs::metadata(path)?.nlink())
+}
+
+#[cfg(not(unix))]
+fn link_count(_path: &Path) -> io::Result<u64> {
+    Ok(1)
+}
+
+/// Determines whether the process `pid` on this host is still running. If that can't
+/// be determined, the process is assumed to be running.
+fn is_running(pid: u32) -> bool {
+    let status = if cfg!(windows) {
+        Command::new("tasklist")
+            .args(&["/FI", &format!("PID eq {}", pid), "/NH"])
+            .stderr(Stdio::null())
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+    } else {
+        Command::new("kill")
+            .args(&["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+    };
+
+    status.unwrap_or(true)
+}
+
+fn host_name() -> String {
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| {
+            fs::read_to_string("/etc/hostname")
+                .ok()
+                .map(|name| name.trim().to_string())
+        })
+        .or_else(|| {
+            Command::new("hostname")
+                .output()
+                .ok()
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_owner_round_trip() {
+        let owner = Owner {
+            pid: 1234,
+            host: "build-01".to_string(),
+        };
+        assert_eq!(Owner::parse(&owner.serialize()), Some(owner));
+        assert_eq!(Owner::parse("not a lock"), None);
+    }
+
+    #[test]
+    fn test_acquire_and_release() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("inventory.lock");
+
+        {
+            let _lock = LockFile::acquire(&path).unwrap();
+            let holder = Owner::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+            assert_eq!(holder, Owner::current());
+        }
+
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_acquire_waits_for_release() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("inventory.lock");
+
+        let held = LockFile::acquire(&path).unwrap();
+        let released = thread::spawn(move || {
+            thread::sleep(RETRY_INTERVAL * 2);
+            let released = SystemTime::now();
+            drop(held);
+            released
+        });
+
+        let _lock = LockFile::acquire(&path).unwrap();
+        let acquired = SystemTime::now();
+        assert!(acquired >= released.join().unwrap());
+    }
+
+    #[test]
+    fn test_acquire_stale() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("inventory.lock");
+
+        // a lock held by a process on this host that no longer exists
+        let abandoned = Owner {
+            pid: u32::max_value(),
+            host: host_name(),
+        };
+        fs::write(&path, abandoned.serialize()).unwrap();
+
+        let _lock = LockFile::acquire(&path).unwrap();
+        let holder = Owner::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(holder, Owner::current());
+    }
+
+    #[test]
+    fn test_take_over_keeps_a_fresh_lock() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("inventory.lock");
+
+        // the stale lock that was read was replaced by a fresh one since
+        let abandoned = Owner {
+            pid: u32::max_value(),
+            host: host_name(),
+        };
+        let fresh = Owner::current();
+        fs::write(&path, fresh.serialize()).unwrap();
+
+        take_over(&path, Some(&abandoned), &Owner::current()).unwrap();
+        assert_eq!(Owner::read(&path), Some(fresh));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_take_over_removes_a_stale_lock() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("inventory.lock");
+
+        let abandoned = Owner {
+            pid: u32::max_value(),
+            host: host_name(),
+        };
+        fs::write(&path, abandoned.serialize()).unwrap();
+
+        take_over(&path, Some(&abandoned), &Owner::current()).unwrap();
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_touch() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("inventory.lock");
+        let owner = Owner::current();
+
+        // a released lock isn't created again
+        touch(&path, &owner).unwrap();
+        assert!(!path.exists());
+
+        // a lock taken over by another process is left alone
+        let other = Owner {
+            pid: owner.pid + 1,
+            host: owner.host.clone(),
+        };
+        fs::write(&path, other.serialize()).unwrap();
+        touch(&path, &owner).unwrap();
+        assert_eq!(Owner::read(&path), Some(other));
+    }
+}
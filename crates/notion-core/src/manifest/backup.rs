@@ -0,0 +1,109 @@
+//! Provides backups of `package.json` files, taken whenever Notion rewrites one.
+//!
+//! Backups are kept in `NOTION_HOME`, in a directory per manifest named after a hash of
+//! its path, so that the previous contents can be recovered even if the project isn't
+//! under version control.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use blake2_rfc::blake2b::blake2b;
+
+use crate::event::unix_timestamp;
+use crate::fs::{ensure_dir_exists, read_dir_eager};
+use crate::path;
+use notion_fail::{Fallible, ResultExt};
+
+/// The number of backups kept for each manifest.
+const MAX_BACKUPS: usize = 10;
+
+/// Returns the directory holding the backups of the manifest at `package_file`.
+pub fn backup_dir(package_file: &Path) -> Fallible<PathBuf> {
+    Ok(path::manifest_backup_root_dir()?.join(dir_name(package_file)))
+}
+
+fn dir_name(package_file: &Path) -> String {
+    let hash = blake2b(16, &[], package_file.to_string_lossy().as_bytes());
+    hash.as_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Saves `contents`, the current contents of the manifest at `package_file`, as its
+/// newest backup, discarding the oldest backups beyond `MAX_BACKUPS`.
+pub(crate) fn save(package_file: &Path, contents: &str) -> Fallible<PathBuf> {
+    let dir = backup_dir(package_file)?;
+    ensure_dir_exists(&dir)?;
+    fs::write(
+        dir.join("source"),
+        package_file.to_string_lossy().as_bytes(),
+    )
+    .unknown()?;
+
+    let backup = dir.join(format!("package-{}.json", unix_timestamp()));
+    fs::write(&backup, contents).unknown()?;
+
+    for stale in list(package_file)?.into_iter().skip(MAX_BACKUPS) {
+        fs::remove_file(stale).unknown()?;
+    }
+
+    Ok(backup)
+}
+
+/// Lists the backups of the manifest at `package_file`, newest first.
+pub fn list(package_file: &Path) -> Fallible<Vec<PathBuf>> {
+    let dir = backup_dir(package_file)?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<(u64, PathBuf)> = read_dir_eager(&dir)?
+        .filter(|(_, metadata)| metadata.is_file())
+        .filter_map(|(entry, _)| {
+            let path = entry.path();
+            let timestamp = backup_timestamp(&path)?;
+            Some((timestamp, path))
+        })
+        .collect();
+    backups.sort_by(|a, b| b.0.cmp(&a.0));
+
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Parses the timestamp out of a backup file name of the form `package-<millis>.json`.
+fn backup_timestamp(path: &Path) -> Option<u64> {
+    let name = path.file_name()?.to_str()?;
+    if name.starts_with("package-") && name.ends_with(".json") {
+        name["package-".len()..name.len() - ".json".len()]
+            .parse()
+            .ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{backup_timestamp, dir_name};
+    use std::path::Path;
+
+    #[test]
+    fn test_dir_name() {
+        let name = dir_name(Path::new("/src/app/package.json"));
+        assert_eq!(name.len(), 32);
+        assert_eq!(name, dir_name(Path::new("/src/app/package.json")));
+        assert_ne!(name, dir_name(Path::new("/src/other/package.json")));
+    }
+
+    #[test]
+    fn test_backup_timestamp() {
+        assert_eq!(
+            backup_timestamp(Path::new("/backups/package-1546300800000.json")),
+            Some(1546300800000)
+        );
+        assert_eq!(backup_timestamp(Path::new("/backups/source")), None);
+        assert_eq!(backup_timestamp(Path::new("/backups/package-x.json")), None);
+    }
+}
@@ -1,13 +1,17 @@
 //! Provides the `Manifest` type, which represents a Node manifest file (`package.json`).
 
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
+use std::ffi::OsStr;
+use std::fs::{write, File};
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use failure::Fail;
 
+use crate::diff;
+use crate::env::{is_quiet, toolchain_profile};
+use crate::fs::is_read_only_home;
 use crate::platform::PlatformSpec;
 use detect_indent;
 use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
@@ -16,6 +20,7 @@ use semver::Version;
 use serde::Serialize;
 use serde_json;
 
+pub mod backup;
 pub(crate) mod serial;
 
 #[derive(Debug, Fail, NotionFail)]
@@ -33,10 +38,62 @@ impl PackageReadError {
     }
 }
 
+/// Thrown when the selected toolchain profile is not in the manifest.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Unknown toolchain profile `{}`\n\nThe `toolchainProfiles` in package.json are: {}",
+    profile, available
+)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct UnknownProfileError {
+    pub(crate) profile: String,
+    pub(crate) available: String,
+}
+
+/// Thrown when a toolchain that extends another pins no Node version, and neither does
+/// the toolchain it extends.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "No Node version is pinned by the toolchain in package.json, nor by the toolchain it extends (`{}`)",
+    extends
+)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct NoExtendedNodeError {
+    pub(crate) extends: String,
+}
+
+/// Thrown when the toolchain of a package.json extends itself, through the toolchains it
+/// extends.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "The toolchain in {} extends itself", file)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct ExtendsCycleError {
+    pub(crate) file: String,
+}
+
+/// A toolchain whose Node version is a named version, such as `lts` or an alias, which
+/// is resolved into a platform image when it is needed.
+#[derive(Debug, PartialEq)]
+pub struct TaggedPlatform {
+    pub node: String,
+    pub npm: Option<Version>,
+    pub yarn: Option<Version>,
+    pub deno: Option<Version>,
+}
+
 /// A Node manifest file.
 pub struct Manifest {
-    /// The platform image specified by the `toolchain` section.
+    /// The platform image specified by the `toolchain` section, or by the selected
+    /// profile of the `toolchainProfiles` section.
     pub platform: Option<Rc<PlatformSpec>>,
+    /// The toolchain in place of `platform` when its Node version is a named version.
+    pub tagged_platform: Option<TaggedPlatform>,
+    /// The Node version range of the `engines` section, if any.
+    pub engines_node: Option<String>,
+    /// The names of the profiles in the `toolchainProfiles` section.
+    pub profiles: Vec<String>,
+    /// The globs of the packages of the workspace, if this is the root of one.
+    pub workspaces: Vec<String>,
     /// The `dependencies` section.
     pub dependencies: HashMap<String, String>,
     /// The `devDependencies` section.
@@ -46,14 +103,39 @@ pub struct Manifest {
 }
 
 impl Manifest {
-    /// Loads and parses a Node manifest for the project rooted at the specified path.
+    /// Loads and parses a Node manifest for the project rooted at the specified path. If
+    /// its toolchain extends another package.json, the tools it doesn't pin are taken from
+    /// the toolchain of that one.
     pub fn for_dir(project_root: &Path) -> Fallible<Manifest> {
+        Manifest::for_dir_extended_by(project_root, &[])
+    }
+
+    /// Loads the manifest of `project_root`, which the toolchains of the `extending`
+    /// directories extend (each the one before it).
+    fn for_dir_extended_by(project_root: &Path, extending: &[PathBuf]) -> Fallible<Manifest> {
         let maybe_file = File::open(project_root.join("package.json"));
 
         match maybe_file {
             Ok(file) => {
-                let serial: serial::Manifest = serde_json::de::from_reader(file).unknown()?;
-                serial.into_manifest()
+                let mut serial: serial::Manifest = serde_json::de::from_reader(file).unknown()?;
+                let profile = toolchain_profile();
+                let profile = profile.as_ref().map(String::as_str);
+                if let Some(extends) = serial.extends(profile) {
+                    let mut base_dir = project_root.join(extends);
+                    if base_dir.file_name() == Some(OsStr::new("package.json")) {
+                        base_dir.pop();
+                    }
+                    let mut extending = extending.to_vec();
+                    extending.push(project_root.to_path_buf());
+                    if extending.iter().any(|dir| same_dir(dir, &base_dir)) {
+                        throw!(ExtendsCycleError {
+                            file: project_root.join("package.json").display().to_string(),
+                        });
+                    }
+                    let base = Manifest::for_dir_extended_by(&base_dir, &extending)?;
+                    serial.extend(profile, base.toolchain());
+                }
+                serial.into_manifest(profile)
             }
             Err(error) => {
                 if project_root.is_dir() {
@@ -70,6 +152,29 @@ impl Manifest {
         }
     }
 
+    /// Takes the toolchain of the workspace root `root` in place of this manifest's, which
+    /// pins none, along with its `engines.node` range if this one has none.
+    pub fn inherit(&mut self, root: Manifest) {
+        self.platform = root.platform;
+        self.tagged_platform = root.tagged_platform;
+        self.profiles = root.profiles;
+        if self.engines_node.is_none() {
+            self.engines_node = root.engines_node;
+        }
+    }
+
+    /// Returns true if the manifest has a `toolchain` or any `toolchainProfiles`.
+    pub fn has_toolchain(&self) -> bool {
+        self.platform.is_some() || self.tagged_platform.is_some() || !self.profiles.is_empty()
+    }
+
+    /// Returns the selected toolchain as it is written in package.json, if any.
+    fn toolchain(&self) -> Option<serial::ToolchainSpec> {
+        self.node_and_npm_strs().map(|(node, npm)| {
+            serial::ToolchainSpec::new(node, npm, self.yarn_str(), self.deno_str())
+        })
+    }
+
     /// Returns a reference to the platform image specified by manifest, if any.
     pub fn platform(&self) -> Option<Rc<PlatformSpec>> {
         self.platform.as_ref().map(|p| p.clone())
@@ -94,6 +199,22 @@ impl Manifest {
         self.platform().map(|t| t.node_runtime.to_string())
     }
 
+    /// Returns the pinned Node version, which may be a named version, and npm version as
+    /// Strings, if any.
+    pub fn node_and_npm_strs(&self) -> Option<(String, Option<String>)> {
+        match (self.platform(), &self.tagged_platform) {
+            (Some(platform), _) => Some((
+                platform.node_runtime.to_string(),
+                platform.npm.as_ref().map(|npm| npm.to_string()),
+            )),
+            (None, Some(tagged)) => Some((
+                tagged.node.clone(),
+                tagged.npm.as_ref().map(|npm| npm.to_string()),
+            )),
+            (None, None) => None,
+        }
+    }
+
     /// Returns the pinned verison of Yarn as a Version, if any.
     pub fn yarn(&self) -> Option<Version> {
         self.platform().map(|t| t.yarn.clone()).unwrap_or(None)
@@ -101,42 +222,131 @@ impl Manifest {
 
     /// Returns the pinned verison of Yarn as a String, if any.
     pub fn yarn_str(&self) -> Option<String> {
-        self.platform()
-            .and_then(|t| t.yarn.as_ref().map(|yarn| yarn.to_string()))
+        match self.tagged_platform {
+            Some(ref tagged) => tagged.yarn.as_ref().map(|yarn| yarn.to_string()),
+            None => self
+                .platform()
+                .and_then(|t| t.yarn.as_ref().map(|yarn| yarn.to_string())),
+        }
+    }
+
+    /// Returns the pinned verison of Deno as a String, if any.
+    pub fn deno_str(&self) -> Option<String> {
+        match self.tagged_platform {
+            Some(ref tagged) => tagged.deno.as_ref().map(|deno| deno.to_string()),
+            None => self
+                .platform()
+                .and_then(|t| t.deno.as_ref().map(|deno| deno.to_string())),
+        }
     }
 
     /// Writes the input ToolchainManifest to package.json, adding the "toolchain" key if
-    /// necessary.
+    /// necessary. With `engines`, the pinned Node version is mirrored into `engines.node`
+    /// too. The change is previewed as a diff first (unless the output is terse, though
+    /// a dry run always shows it); with `dry_run`, nothing is written.
+    /// The previous contents are kept as a backup (see the `backup` module).
     pub fn update_toolchain(
         toolchain: serial::ToolchainSpec,
         package_file: PathBuf,
+        engines: bool,
+        dry_run: bool,
     ) -> Fallible<()> {
-        // parse the entire package.json file into a Value
-        let file = File::open(&package_file).unknown()?;
-        let mut v: serde_json::Value = serde_json::from_reader(file).unknown()?;
+        // read the entire package.json file, and parse it into a Value
+        let mut contents = String::new();
+        let mut file = File::open(&package_file).unknown()?;
+        file.read_to_string(&mut contents).unknown()?;
+        let mut v: serde_json::Value = serde_json::from_str(&contents).unknown()?;
 
         // detect indentation in package.json
-        let mut contents = String::new();
-        let mut indent_file = File::open(&package_file).unknown()?;
-        indent_file.read_to_string(&mut contents).unknown()?;
         let indent = detect_indent::detect_indent(&contents);
 
         if let Some(map) = v.as_object_mut() {
-            // update the "toolchain" key
+            let profile = toolchain_profile();
+            if let (true, None, Some(node)) = (engines, &profile, &toolchain.node) {
+                sync_engines(map, node);
+            }
+            // update the "toolchain" key, or the selected profile
             let toolchain_value = serde_json::to_value(toolchain).unknown()?;
-            map.insert("toolchain".to_string(), toolchain_value);
-
-            // serialize the updated contents back to package.json
-            let file = File::create(package_file).unknown()?;
-            let formatter =
-                serde_json::ser::PrettyFormatter::with_indent(indent.indent().as_bytes());
-            let mut ser = serde_json::Serializer::with_formatter(file, formatter);
-            map.serialize(&mut ser).unknown()?;
+            set_toolchain(map, profile, toolchain_value);
+
+            // serialize the updated contents
+            let mut updated = Vec::new();
+            {
+                let formatter =
+                    serde_json::ser::PrettyFormatter::with_indent(indent.indent().as_bytes());
+                let mut ser = serde_json::Serializer::with_formatter(&mut updated, formatter);
+                map.serialize(&mut ser).unknown()?;
+            }
+            let updated = String::from_utf8(updated).unknown()?;
+
+            // the preview is all a dry run produces, so it is shown even when quiet
+            if dry_run || !is_quiet() {
+                if let Some(preview) = diff::render(&diff::diff_lines(&contents, &updated), 2) {
+                    println!("{}", preview);
+                }
+            }
+
+            if !dry_run && updated != contents {
+                // the backups are kept in NOTION_HOME, but the project itself is writable
+                if !is_read_only_home() {
+                    backup::save(&package_file, &contents)?;
+                }
+                write(package_file, updated).unknown()?;
+            }
         }
         Ok(())
     }
 }
 
+/// Determines whether `a` and `b` are the same directory.
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Sets the default `toolchain`, or else the `toolchainProfiles` entry of `profile`.
+fn set_toolchain(
+    map: &mut serde_json::Map<String, serde_json::Value>,
+    profile: Option<String>,
+    toolchain: serde_json::Value,
+) {
+    let profile = match profile {
+        Some(profile) => profile,
+        None => {
+            map.insert("toolchain".to_string(), toolchain);
+            return;
+        }
+    };
+    if let Some(profiles) = map
+        .get_mut("toolchainProfiles")
+        .and_then(|v| v.as_object_mut())
+    {
+        profiles.insert(profile, toolchain);
+        return;
+    }
+    let mut profiles = serde_json::Map::new();
+    profiles.insert(profile, toolchain);
+    map.insert(
+        "toolchainProfiles".to_string(),
+        serde_json::Value::Object(profiles),
+    );
+}
+
+/// Sets `engines.node` to the pinned `node` version, keeping the other engines and the
+/// position of the `engines` key if it's already there.
+fn sync_engines(map: &mut serde_json::Map<String, serde_json::Value>, node: &str) {
+    let node = serde_json::Value::String(node.to_string());
+    if let Some(engines) = map.get_mut("engines").and_then(|v| v.as_object_mut()) {
+        engines.insert("node".to_string(), node);
+        return;
+    }
+    let mut engines = serde_json::Map::new();
+    engines.insert("node".to_string(), node);
+    map.insert("engines".to_string(), serde_json::Value::Object(engines));
+}
+
 // unit tests
 
 #[cfg(test)]
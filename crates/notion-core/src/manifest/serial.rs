@@ -1,12 +1,12 @@
 use super::super::{manifest, platform};
 use crate::version::VersionSpec;
 
-use notion_fail::Fallible;
+use notion_fail::{throw, Fallible};
 
 use serde;
 use serde::de::{Deserialize, Deserializer, Error, MapAccess, Visitor};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::hash::Hash;
 use std::marker::PhantomData;
@@ -52,25 +52,82 @@ pub struct Manifest {
     #[serde(rename = "devDependencies")]
     pub dev_dependencies: HashMap<String, String>,
 
+    #[serde(default, deserialize_with = "deserialize_toolchain")]
     pub toolchain: Option<ToolchainSpec>,
 
+    // named alternatives to the "toolchain", selected with `NOTION_PROFILE`
+    #[serde(default, deserialize_with = "deserialize_profiles")]
+    #[serde(rename = "toolchainProfiles")]
+    pub toolchain_profiles: BTreeMap<String, ToolchainSpec>,
+
+    // the packages of a workspace: an array of globs (npm and Yarn), or an object with
+    // the array as its "packages" (Yarn)
+    #[serde(default)]
+    pub workspaces: Option<serde_json::Value>,
+
     // the "bin" field can be a map or a string
     // (see https://docs.npmjs.com/files/package.json#bin)
     #[serde(default)] // handles Option
     pub bin: Option<BinMap<String, String>>,
+
+    // only the "node" range of the "engines" is used, and older packages sometimes have
+    // an array here, so it isn't required to be a map of strings
+    #[serde(default)]
+    pub engines: Option<serde_json::Value>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct ToolchainSpec {
-    pub node: String,
+    // only optional when the toolchain extends another, which it is then inherited from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub npm: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub yarn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deno: Option<String>,
+    // the package.json (or the directory of it) whose toolchain this one overrides
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+}
+
+fn validate_toolchain<E: Error>(toolchain: &ToolchainSpec) -> Result<(), E> {
+    if toolchain.node.is_none() && toolchain.extends.is_none() {
+        return Err(E::missing_field("node"));
+    }
+    Ok(())
+}
+
+fn deserialize_toolchain<'de, D>(deserializer: D) -> Result<Option<ToolchainSpec>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let toolchain = Option::<ToolchainSpec>::deserialize(deserializer)?;
+    if let Some(ref toolchain) = toolchain {
+        validate_toolchain(toolchain)?;
+    }
+    Ok(toolchain)
+}
+
+fn deserialize_profiles<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, ToolchainSpec>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let profiles = BTreeMap::<String, ToolchainSpec>::deserialize(deserializer)?;
+    for toolchain in profiles.values() {
+        validate_toolchain(toolchain)?;
+    }
+    Ok(profiles)
 }
 
 impl Manifest {
-    pub fn into_manifest(self) -> Fallible<manifest::Manifest> {
+    /// Converts the manifest, taking its platform from the named `profile` if there is
+    /// one, or else from the default `toolchain`.
+    pub fn into_manifest(self, profile: Option<&str>) -> Fallible<manifest::Manifest> {
+        let workspaces = self.workspace_patterns();
         let mut map = HashMap::new();
         if let Some(ref bin) = self.bin {
             for (name, path) in bin.iter() {
@@ -83,8 +140,44 @@ impl Manifest {
                 }
             }
         }
+        let toolchain = match profile {
+            // a manifest without any toolchain pins nothing, whichever the profile
+            Some(_) if !self.has_toolchain() => None,
+            Some(profile) => match self.toolchain_profiles.get(profile) {
+                Some(toolchain) => Some(toolchain),
+                None => throw!(manifest::UnknownProfileError {
+                    profile: profile.to_string(),
+                    available: self.profile_names().join(", "),
+                }),
+            },
+            None => self.toolchain.as_ref(),
+        };
+        if let Some(toolchain) = toolchain {
+            if toolchain.node.is_none() {
+                throw!(manifest::NoExtendedNodeError {
+                    extends: toolchain.extends.clone().unwrap_or_default(),
+                });
+            }
+        }
+        let (platform, tagged_platform) = match toolchain {
+            Some(toolchain) if toolchain.has_node_tag() => {
+                (None, Some(toolchain.into_tagged_platform()?))
+            }
+            Some(toolchain) => (Some(toolchain.into_platform()?), None),
+            None => (None, None),
+        };
+        let engines_node = self
+            .engines
+            .as_ref()
+            .and_then(|engines| engines.get("node"))
+            .and_then(|node| node.as_str())
+            .map(String::from);
         Ok(manifest::Manifest {
-            platform: self.into_platform()?.map(Rc::new),
+            platform: platform.map(Rc::new),
+            tagged_platform,
+            engines_node,
+            profiles: self.profile_names(),
+            workspaces,
             dependencies: self.dependencies,
             dev_dependencies: self.dev_dependencies,
             bin: map,
@@ -93,22 +186,59 @@ impl Manifest {
 
     pub fn into_platform(&self) -> Fallible<Option<platform::PlatformSpec>> {
         if let Some(toolchain) = &self.toolchain {
-            return Ok(Some(platform::PlatformSpec {
-                node_runtime: VersionSpec::parse_version(&toolchain.node)?,
-                npm: if let Some(npm) = &toolchain.npm {
-                    Some(VersionSpec::parse_version(&npm)?)
-                } else {
-                    None
-                },
-                yarn: if let Some(yarn) = &toolchain.yarn {
-                    Some(VersionSpec::parse_version(&yarn)?)
-                } else {
-                    None
-                },
-            }));
+            return Ok(Some(toolchain.into_platform()?));
         }
         Ok(None)
     }
+
+    fn profile_names(&self) -> Vec<String> {
+        self.toolchain_profiles.keys().cloned().collect()
+    }
+
+    /// Returns true if the manifest has a `toolchain` or any `toolchainProfiles`.
+    pub fn has_toolchain(&self) -> bool {
+        self.toolchain.is_some() || !self.toolchain_profiles.is_empty()
+    }
+
+    /// Returns what the toolchain selected by `profile` (as in `into_manifest`) extends,
+    /// if it extends another.
+    pub fn extends(&self, profile: Option<&str>) -> Option<&str> {
+        let toolchain = match profile {
+            Some(profile) => self.toolchain_profiles.get(profile),
+            None => self.toolchain.as_ref(),
+        };
+        toolchain.and_then(|toolchain| toolchain.extends.as_ref().map(String::as_str))
+    }
+
+    /// Fills in the tools that the toolchain selected by `profile` doesn't pin from `base`,
+    /// the toolchain that it extends.
+    pub fn extend(&mut self, profile: Option<&str>, base: Option<ToolchainSpec>) {
+        let toolchain = match profile {
+            Some(profile) => self.toolchain_profiles.get_mut(profile),
+            None => self.toolchain.as_mut(),
+        };
+        if let (Some(toolchain), Some(base)) = (toolchain, base) {
+            toolchain.node = toolchain.node.take().or(base.node);
+            toolchain.npm = toolchain.npm.take().or(base.npm);
+            toolchain.yarn = toolchain.yarn.take().or(base.yarn);
+            toolchain.deno = toolchain.deno.take().or(base.deno);
+        }
+    }
+
+    /// Returns the globs of the workspace packages, if this is the root of a workspace.
+    fn workspace_patterns(&self) -> Vec<String> {
+        let patterns = match self.workspaces {
+            Some(serde_json::Value::Object(ref workspaces)) => workspaces.get("packages"),
+            ref workspaces => workspaces.as_ref(),
+        };
+        match patterns {
+            Some(serde_json::Value::Array(patterns)) => patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(String::from))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 impl ToolchainSpec {
@@ -116,13 +246,61 @@ impl ToolchainSpec {
         node_version: String,
         npm_version: Option<String>,
         yarn_version: Option<String>,
+        deno_version: Option<String>,
     ) -> Self {
         ToolchainSpec {
-            node: node_version,
+            node: Some(node_version),
             npm: npm_version,
             yarn: yarn_version,
+            deno: deno_version,
+            extends: None,
         }
     }
+
+    fn node(&self) -> &str {
+        self.node.as_ref().map(String::as_str).unwrap_or_default()
+    }
+
+    pub fn into_platform(&self) -> Fallible<platform::PlatformSpec> {
+        let tagged = self.into_tagged_platform()?;
+        Ok(platform::PlatformSpec {
+            node_runtime: VersionSpec::parse_version(self.node())?,
+            npm: tagged.npm,
+            yarn: tagged.yarn,
+            deno: tagged.deno,
+        })
+    }
+
+    /// Returns true if the Node version of this toolchain is a named version.
+    fn has_node_tag(&self) -> bool {
+        match VersionSpec::parse(self.node()) {
+            Ok(VersionSpec::Tag(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Parses a toolchain whose Node version is a named version (such as `lts`), which
+    /// is only resolved when the platform is needed.
+    pub fn into_tagged_platform(&self) -> Fallible<manifest::TaggedPlatform> {
+        Ok(manifest::TaggedPlatform {
+            node: self.node().to_string(),
+            npm: if let Some(npm) = &self.npm {
+                Some(VersionSpec::parse_version(&npm)?)
+            } else {
+                None
+            },
+            yarn: if let Some(yarn) = &self.yarn {
+                Some(VersionSpec::parse_version(&yarn)?)
+            } else {
+                None
+            },
+            deno: if let Some(deno) = &self.deno {
+                Some(VersionSpec::parse_version(&deno)?)
+            } else {
+                None
+            },
+        })
+    }
 }
 
 // (deserialization adapted from https://serde.rs/deserialize-map.html)
@@ -196,7 +374,8 @@ where
 #[cfg(test)]
 pub mod tests {
 
-    use super::{BinMap, Manifest};
+    use super::{BinMap, Manifest, ToolchainSpec};
+    use semver::Version;
     use serde_json;
     use std::collections::HashMap;
 
@@ -300,7 +479,10 @@ pub mod tests {
         }"#;
         let manifest_node_only: Manifest =
             serde_json::de::from_str(package_node_only).expect("Could not deserialize string");
-        assert_eq!(manifest_node_only.toolchain.unwrap().node, "0.11.4");
+        assert_eq!(
+            manifest_node_only.toolchain.unwrap().node.unwrap(),
+            "0.11.4"
+        );
 
         let package_node_npm = r#"{
             "toolchain": {
@@ -313,7 +495,7 @@ pub mod tests {
         let toolchain_node_npm = manifest_node_npm
             .toolchain
             .expect("Did not parse toolchain correctly");
-        assert_eq!(toolchain_node_npm.node, "0.10.5");
+        assert_eq!(toolchain_node_npm.node.unwrap(), "0.10.5");
         assert_eq!(toolchain_node_npm.npm.unwrap(), "1.2.18");
 
         let package_yarn_only = r#"{
@@ -339,8 +521,150 @@ pub mod tests {
         let toolchain_node_and_yarn = manifest_node_and_yarn
             .toolchain
             .expect("Did not parse toolchain correctly");
-        assert_eq!(toolchain_node_and_yarn.node, "0.10.5");
+        assert_eq!(toolchain_node_and_yarn.node.unwrap(), "0.10.5");
         assert_eq!(toolchain_node_and_yarn.yarn.unwrap(), "1.2.1");
+
+        let package_node_and_deno = r#"{
+            "toolchain": {
+                "node": "10.15.0",
+                "deno": "1.0.0"
+            }
+        }"#;
+        let manifest_node_and_deno: Manifest =
+            serde_json::de::from_str(package_node_and_deno).expect("Could not deserialize string");
+        let platform = manifest_node_and_deno
+            .into_platform()
+            .expect("Could not parse toolchain")
+            .expect("Did not parse toolchain correctly");
+        assert_eq!(platform.deno, Some(Version::parse("1.0.0").unwrap()));
+        assert_eq!(platform.yarn, None);
+    }
+
+    #[test]
+    fn test_package_toolchain_profiles() {
+        let package = r#"{
+            "toolchain": {
+                "node": "12.4.0"
+            },
+            "toolchainProfiles": {
+                "legacy": {
+                    "node": "8.16.0",
+                    "yarn": "1.12.3"
+                }
+            }
+        }"#;
+        let parse = || -> Manifest {
+            serde_json::de::from_str(package).expect("Could not deserialize string")
+        };
+
+        let default = parse()
+            .into_manifest(None)
+            .expect("Could not parse manifest");
+        assert_eq!(default.node(), Some(Version::parse("12.4.0").unwrap()));
+        assert_eq!(default.profiles, vec!["legacy".to_string()]);
+
+        let legacy = parse()
+            .into_manifest(Some("legacy"))
+            .expect("Could not parse manifest");
+        assert_eq!(legacy.node(), Some(Version::parse("8.16.0").unwrap()));
+        assert_eq!(legacy.yarn(), Some(Version::parse("1.12.3").unwrap()));
+
+        assert!(parse().into_manifest(Some("nightly")).is_err());
+    }
+
+    #[test]
+    fn test_package_toolchain_tag() {
+        let package = r#"{
+            "toolchain": {
+                "node": "lts",
+                "yarn": "1.12.3"
+            }
+        }"#;
+        let manifest: Manifest =
+            serde_json::de::from_str(package).expect("Could not deserialize string");
+        let manifest = manifest
+            .into_manifest(None)
+            .expect("Could not parse manifest");
+        assert!(manifest.platform().is_none());
+        let tagged = manifest
+            .tagged_platform
+            .expect("Did not parse toolchain correctly");
+        assert_eq!(tagged.node, "lts");
+        assert_eq!(tagged.yarn, Some(Version::parse("1.12.3").unwrap()));
+    }
+
+    #[test]
+    fn test_package_toolchain_extends() {
+        let package = r#"{
+            "toolchain": {
+                "extends": "../../package.json",
+                "yarn": "1.22.0"
+            }
+        }"#;
+        let parse = || -> Manifest {
+            serde_json::de::from_str(package).expect("Could not deserialize string")
+        };
+        assert_eq!(parse().extends(None), Some("../../package.json"));
+        assert!(parse().into_manifest(None).is_err());
+
+        let mut manifest = parse();
+        manifest.extend(
+            None,
+            Some(ToolchainSpec::new(
+                "10.15.0".to_string(),
+                None,
+                Some("1.12.3".to_string()),
+                None,
+            )),
+        );
+        let manifest = manifest
+            .into_manifest(None)
+            .expect("Could not parse manifest");
+        assert_eq!(manifest.node(), Some(Version::parse("10.15.0").unwrap()));
+        assert_eq!(manifest.yarn(), Some(Version::parse("1.22.0").unwrap()));
+    }
+
+    #[test]
+    fn test_package_workspaces() {
+        let npm: Manifest = serde_json::de::from_str(r#"{ "workspaces": ["packages/*"] }"#)
+            .expect("Could not deserialize string");
+        assert_eq!(
+            npm.into_manifest(None).unwrap().workspaces,
+            vec!["packages/*".to_string()]
+        );
+
+        let yarn: Manifest = serde_json::de::from_str(
+            r#"{ "workspaces": { "packages": ["apps/*"], "nohoist": ["**/react"] } }"#,
+        )
+        .expect("Could not deserialize string");
+        assert_eq!(
+            yarn.into_manifest(None).unwrap().workspaces,
+            vec!["apps/*".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_package_engines() {
+        let package = r#"{
+            "engines": {
+                "node": ">=12 <15",
+                "npm": ">=6"
+            }
+        }"#;
+        let manifest: Manifest =
+            serde_json::de::from_str(package).expect("Could not deserialize string");
+        let manifest = manifest
+            .into_manifest(None)
+            .expect("Could not parse manifest");
+        assert_eq!(manifest.engines_node, Some(">=12 <15".to_string()));
+        assert!(manifest.platform().is_none());
+
+        let legacy: Manifest = serde_json::de::from_str(r#"{ "engines": ["node >= 0.4"] }"#)
+            .expect("Could not deserialize string");
+        let legacy = legacy
+            .into_manifest(None)
+            .expect("Could not parse manifest");
+        assert_eq!(legacy.engines_node, None);
     }
 
     #[test]
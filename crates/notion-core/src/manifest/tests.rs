@@ -1,4 +1,4 @@
-use crate::manifest::Manifest;
+use crate::manifest::{sync_engines, Manifest};
 use semver::Version;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
@@ -127,3 +127,23 @@ fn handles_dep_with_no_bin() {
     let expected_bin = HashMap::new();
     assert_eq!(bin, expected_bin);
 }
+
+#[test]
+fn syncs_engines() {
+    let mut map: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(r#"{"name":"app","engines":{"npm":">=6","node":">=8"},"main":"x"}"#)
+            .unwrap();
+    sync_engines(&mut map, "10.13.0");
+    assert_eq!(
+        serde_json::to_string(&map).unwrap(),
+        r#"{"name":"app","engines":{"npm":">=6","node":"10.13.0"},"main":"x"}"#
+    );
+
+    let mut map: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(r#"{"name":"app"}"#).unwrap();
+    sync_engines(&mut map, "10.13.0");
+    assert_eq!(
+        serde_json::to_string(&map).unwrap(),
+        r#"{"name":"app","engines":{"node":"10.13.0"}}"#
+    );
+}
@@ -0,0 +1,414 @@
+//! Provides support for populating a mirror of the public Node and Yarn servers.
+//!
+//! A mirror directory is laid out like the public servers, so that once it is served
+//! over HTTP, the hooks in `hooks.toml` can point at it:
+//!
+//! ```text
+//! <mirror>/
+//!   node/
+//!     index.json                            [node.index] / [node.latest] prefix
+//!     index.tab
+//!     v10.13.0/
+//!       SHASUMS256.txt
+//!       SHASUMS256.txt.sig
+//!       node-v10.13.0-linux-x64.tar.gz      [node.distro] template
+//!   yarn/
+//!     releases                              [yarn.index] prefix
+//!     latest-version                        [yarn.latest] prefix
+//!     v1.12.3/
+//!       yarn-v1.12.3.tar.gz                 [yarn.distro] template
+//! ```
+//!
+//! The indexes are filtered to the mirrored releases, so that clients never resolve a
+//! version that isn't available from the mirror. The Node archives are verified against
+//! the `SHASUMS256.txt` of their release, which is fetched again on every sync, so that
+//! an archive that was corrupted in the mirror is downloaded again.
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use failure::Fail;
+use reqwest;
+use semver::{Version, VersionReq};
+use serde_json::{self, Value};
+use tempfile::NamedTempFile;
+
+use crate::checksum::{Algorithm, Checksum, ChecksumMismatchError};
+use crate::config::MirrorConfig;
+use crate::credentials;
+use crate::distro::node::public_node_server_root;
+use crate::distro::yarn::public_yarn_server_root;
+use crate::fs::ensure_containing_dir_exists;
+use crate::inventory::{public_node_version_index, public_yarn_version_index};
+use crate::path::{self, ARCH, OS};
+use crate::style::progress_spinner;
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+/// Thrown when a file could not be downloaded into the mirror.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Could not download {} into the mirror\n{}", url, error)]
+#[notion_fail(code = "NetworkError")]
+pub(crate) struct MirrorDownloadError {
+    url: String,
+    error: String,
+}
+
+impl MirrorDownloadError {
+    fn for_url(url: &str) -> impl FnOnce(&reqwest::Error) -> MirrorDownloadError {
+        let url = url.to_string();
+        move |error| MirrorDownloadError {
+            url,
+            error: error.to_string(),
+        }
+    }
+}
+
+/// Thrown when `notion mirror sync` is run without any releases configured.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "No releases to mirror\n\nList the Node and Yarn versions to mirror in the [mirror] section of the Notion configuration."
+)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct NothingToMirrorError;
+
+/// Thrown when `notion mirror sync` is run without a mirror directory.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "No mirror directory specified\n\nPass a directory to `notion mirror sync` or set `dir` in the [mirror] section of the Notion configuration."
+)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct NoMirrorDirError;
+
+/// The result of synchronizing a mirror.
+pub struct Synced {
+    /// The mirrored Node versions.
+    pub node: BTreeSet<Version>,
+    /// The mirrored Yarn versions.
+    pub yarn: BTreeSet<Version>,
+    /// The number of files that were downloaded (as opposed to already present).
+    pub downloaded: usize,
+}
+
+/// Downloads the releases selected by `config`, along with their indexes and
+/// checksums, into the mirror directory `dir` (or the one in `config`).
+pub fn sync(config: &MirrorConfig, dir: Option<PathBuf>) -> Fallible<Synced> {
+    let dir = match dir.or_else(|| config.dir.clone()) {
+        Some(dir) => dir,
+        None => throw!(NoMirrorDirError),
+    };
+
+    if config.node.is_empty() && config.yarn.is_empty() {
+        throw!(NothingToMirrorError);
+    }
+
+    let mut synced = Synced {
+        node: BTreeSet::new(),
+        yarn: BTreeSet::new(),
+        downloaded: 0,
+    };
+
+    if !config.node.is_empty() {
+        sync_node(config, &dir.join("node"), &mut synced)?;
+    }
+
+    if !config.yarn.is_empty() {
+        sync_yarn(config, &dir.join("yarn"), &mut synced)?;
+    }
+
+    Ok(synced)
+}
+
+fn sync_node(config: &MirrorConfig, dir: &Path, synced: &mut Synced) -> Fallible<()> {
+    let index_url = public_node_version_index();
+    let index: Vec<Value> = serde_json::from_str(&fetch_text(&index_url)?).unknown()?;
+    let available = index.iter().filter_map(|entry| entry_version(entry, "version"));
+    synced.node = select(&config.node, available);
+
+    let platforms = if config.platforms.is_empty() {
+        vec![format!("{}-{}", OS, ARCH)]
+    } else {
+        config.platforms.clone()
+    };
+
+    let server_root = public_node_server_root();
+    for version in &synced.node {
+        let version_dir = dir.join(format!("v{}", version));
+        let version_url = format!("{}/v{}", server_root, version);
+
+        let shasums = fetch_text(&format!("{}/{}", version_url, SHASUMS_FILE_NAME))?;
+        write_file(&version_dir.join(SHASUMS_FILE_NAME), &shasums)?;
+
+        let signature = format!("{}.sig", SHASUMS_FILE_NAME);
+        let url = format!("{}/{}", version_url, signature);
+        if download(&url, &version_dir.join(&signature), None)? {
+            synced.downloaded += 1;
+        }
+
+        for platform in &platforms {
+            let file = node_distro_file_name(version, platform);
+            let checksum = Checksum::from_listing(Algorithm::Sha256, &shasums, &file);
+            let url = format!("{}/{}", version_url, file);
+            if download(&url, &version_dir.join(&file), checksum.as_ref())? {
+                synced.downloaded += 1;
+            }
+        }
+    }
+
+    let mirrored: Vec<&Value> = index
+        .iter()
+        .filter(|entry| match entry_version(entry, "version") {
+            Some(version) => synced.node.contains(&version),
+            None => false,
+        })
+        .collect();
+    write_file(
+        &dir.join("index.json"),
+        &serde_json::to_string(&mirrored).unknown()?,
+    )?;
+
+    let tab_url = format!("{}index.tab", index_url.trim_end_matches("index.json"));
+    write_file(
+        &dir.join("index.tab"),
+        &filter_index_tab(&fetch_text(&tab_url)?, &synced.node),
+    )?;
+
+    Ok(())
+}
+
+fn sync_yarn(config: &MirrorConfig, dir: &Path, synced: &mut Synced) -> Fallible<()> {
+    let releases: Vec<Value> =
+        serde_json::from_str(&fetch_text(&public_yarn_version_index())?).unknown()?;
+    let available = releases
+        .iter()
+        .filter(|release| is_full_yarn_release(release))
+        .filter_map(|release| entry_version(release, "tag_name"));
+    synced.yarn = select(&config.yarn, available);
+
+    let server_root = public_yarn_server_root();
+    for version in &synced.yarn {
+        let file = path::yarn_distro_file_name(&version.to_string());
+        let url = format!("{}/v{}/{}", server_root, version, file);
+        if download(&url, &dir.join(format!("v{}", version)).join(&file), None)? {
+            synced.downloaded += 1;
+        }
+    }
+
+    let mirrored: Vec<&Value> = releases
+        .iter()
+        .filter(|release| match entry_version(release, "tag_name") {
+            Some(version) => synced.yarn.contains(&version),
+            None => false,
+        })
+        .collect();
+    write_file(
+        &dir.join("releases"),
+        &serde_json::to_string(&mirrored).unknown()?,
+    )?;
+
+    if let Some(latest) = synced.yarn.iter().next_back() {
+        write_file(&dir.join("latest-version"), &latest.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Selects the newest version matching each of `requirements`.
+fn select(
+    requirements: &[VersionReq],
+    available: impl Iterator<Item = Version>,
+) -> BTreeSet<Version> {
+    let available: BTreeSet<Version> = available.collect();
+    requirements
+        .iter()
+        .filter_map(|requirement| {
+            available
+                .iter()
+                .rev()
+                .find(|version| requirement.matches(version))
+                .cloned()
+        })
+        .collect()
+}
+
+/// Reads the version of an index entry from the string field `field`, which may
+/// have a leading `v`.
+fn entry_version(entry: &Value, field: &str) -> Option<Version> {
+    let version = entry.get(field)?.as_str()?.trim();
+    let version = if version.starts_with('v') {
+        &version[1..]
+    } else {
+        version
+    };
+    Version::parse(version).ok()
+}
+
+/// Determines whether a Yarn release from the GitHub API includes a release tarball.
+fn is_full_yarn_release(release: &Value) -> bool {
+    let tag_name = match release.get("tag_name").and_then(Value::as_str) {
+        Some(tag_name) => tag_name,
+        None => return false,
+    };
+    let tarball = format!("yarn-{}.tar.gz", tag_name);
+
+    release
+        .get("assets")
+        .and_then(Value::as_array)
+        .map(|assets| {
+            assets
+                .iter()
+                .any(|asset| asset.get("name").and_then(Value::as_str) == Some(tarball.as_str()))
+        })
+        .unwrap_or(false)
+}
+
+/// Keeps the header and the lines for mirrored versions of the public `index.tab`.
+fn filter_index_tab(src: &str, versions: &BTreeSet<Version>) -> String {
+    src.lines()
+        .enumerate()
+        .filter(|&(i, line)| {
+            i == 0
+                || line
+                    .split('\t')
+                    .next()
+                    .and_then(|version| Version::parse(version.trim_start_matches('v')).ok())
+                    .map(|version| versions.contains(&version))
+                    .unwrap_or(false)
+        })
+        .map(|(_, line)| format!("{}\n", line))
+        .collect()
+}
+
+/// The listing of the SHA-256 checksums of the archives of a Node release.
+const SHASUMS_FILE_NAME: &str = "SHASUMS256.txt";
+
+/// The name of the Node distribution archive for `platform` (e.g. `linux-x64`).
+fn node_distro_file_name(version: &Version, platform: &str) -> String {
+    let extension = if platform.starts_with("win") {
+        "zip"
+    } else {
+        "tar.gz"
+    };
+    format!("node-v{}-{}.{}", version, platform, extension)
+}
+
+fn fetch_text(url: &str) -> Fallible<String> {
+    let spinner = progress_spinner(&format!("Fetching public registry: {}", url));
+    let mut response = credentials::get(url)?
+        .send()
+        .with_context(MirrorDownloadError::for_url(url))?;
+    if !response.status().is_success() {
+        throw!(MirrorDownloadError {
+            url: url.to_string(),
+            error: format!("Server responded with {}", response.status()),
+        });
+    }
+    let text = response.text().with_context(MirrorDownloadError::for_url(url))?;
+    spinner.finish();
+    Ok(text)
+}
+
+/// Downloads `url` to `dest`, unless it is already present, and has the `checksum` if
+/// one is given. A download that doesn't have the `checksum` is rejected. Returns whether
+/// the file was downloaded.
+fn download(url: &str, dest: &Path, checksum: Option<&Checksum>) -> Fallible<bool> {
+    if dest.is_file() {
+        match checksum {
+            Some(checksum) if checksum.verify_file(dest).is_err() => {}
+            _ => return Ok(false),
+        }
+    }
+
+    let spinner = progress_spinner(&format!("Mirroring {}", url));
+    let mut response = credentials::get(url)?
+        .send()
+        .with_context(MirrorDownloadError::for_url(url))?;
+    if !response.status().is_success() {
+        throw!(MirrorDownloadError {
+            url: url.to_string(),
+            error: format!("Server responded with {}", response.status()),
+        });
+    }
+
+    // download to a temporary file first, so an interrupted sync is simply resumed
+    ensure_containing_dir_exists(&dest)?;
+    let temp = NamedTempFile::new_in(dest.parent().unwrap()).unknown()?;
+    {
+        let mut file: &File = temp.as_file();
+        response
+            .copy_to(&mut file)
+            .with_context(MirrorDownloadError::for_url(url))?;
+    }
+    if let Some(checksum) = checksum {
+        let actual = Checksum::of_file(checksum.algorithm, temp.path())?;
+        if actual != *checksum {
+            throw!(ChecksumMismatchError {
+                file: url.to_string(),
+                expected: checksum.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+    }
+    temp.persist(dest).unknown()?;
+
+    spinner.finish();
+    Ok(true)
+}
+
+fn write_file(path: &Path, contents: &str) -> Fallible<()> {
+    ensure_containing_dir_exists(&path)?;
+    let temp = NamedTempFile::new_in(path.parent().unwrap()).unknown()?;
+    temp.as_file().write_all(contents.as_bytes()).unknown()?;
+    temp.persist(path).unknown()?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+
+    fn versions(versions: &[&str]) -> BTreeSet<Version> {
+        versions.iter().map(|v| Version::parse(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_select() {
+        let available = versions(&["8.9.4", "8.12.0", "10.12.0", "10.13.0", "11.1.0"]);
+        let requirements = vec![
+            VersionReq::parse("10").unwrap(),
+            VersionReq::parse("^8.9").unwrap(),
+            VersionReq::parse("6").unwrap(),
+        ];
+        assert_eq!(
+            select(&requirements, available.into_iter()),
+            versions(&["8.12.0", "10.13.0"])
+        );
+    }
+
+    #[test]
+    fn test_filter_index_tab() {
+        let src = "version\tdate\tfiles\n\
+                   v11.1.0\t2018-10-30\tlinux-x64\n\
+                   v10.13.0\t2018-10-30\tlinux-x64\n";
+        assert_eq!(
+            filter_index_tab(src, &versions(&["10.13.0"])),
+            "version\tdate\tfiles\nv10.13.0\t2018-10-30\tlinux-x64\n"
+        );
+    }
+
+    #[test]
+    fn test_node_distro_file_name() {
+        let version = Version::parse("10.13.0").unwrap();
+        assert_eq!(
+            node_distro_file_name(&version, "linux-x64"),
+            "node-v10.13.0-linux-x64.tar.gz"
+        );
+        assert_eq!(
+            node_distro_file_name(&version, "win-x86"),
+            "node-v10.13.0-win-x86.zip"
+        );
+    }
+}
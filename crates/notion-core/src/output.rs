@@ -0,0 +1,119 @@
+//! Provides the levels of detail of Notion's output, and the reporting of messages at
+//! each of them.
+//!
+//! The level is selected with `notion --quiet` or `notion --verbose`, which forward it
+//! to the shims and any other Notion process through `NOTION_VERBOSITY`, so that e.g.
+//! `NOTION_VERBOSITY=quiet` makes the shims run silently. Errors are displayed at every
+//! level; the quiet level hides everything else, including warnings and progress.
+
+use std::env;
+use std::fmt::Display;
+
+use console::style;
+
+use crate::env::{is_debug, VERBOSITY};
+
+/// How much Notion reports about what it is doing, from least to most.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Verbosity {
+    /// Errors only.
+    Quiet,
+    /// Errors, warnings, progress and status messages.
+    Normal,
+    /// Also the details of each step, such as the URLs being fetched.
+    Verbose,
+    /// Also the diagnostics that are only useful to debug Notion itself.
+    Debug,
+}
+
+impl Verbosity {
+    /// Parses a verbosity name, as given in `NOTION_VERBOSITY`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "quiet" => Some(Verbosity::Quiet),
+            "normal" => Some(Verbosity::Normal),
+            "verbose" => Some(Verbosity::Verbose),
+            "debug" => Some(Verbosity::Debug),
+            _ => None,
+        }
+    }
+
+    /// The name of this verbosity, as set in `NOTION_VERBOSITY`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Verbosity::Quiet => "quiet",
+            Verbosity::Normal => "normal",
+            Verbosity::Verbose => "verbose",
+            Verbosity::Debug => "debug",
+        }
+    }
+
+    /// Returns the verbosity selected by `NOTION_VERBOSITY`. Otherwise, `NOTION_DEBUG`
+    /// selects the debug level, and the normal level is the default.
+    pub fn current() -> Self {
+        if let Some(verbosity) = env::var(VERBOSITY)
+            .ok()
+            .and_then(|name| Verbosity::parse(&name))
+        {
+            return verbosity;
+        }
+
+        if is_debug() {
+            Verbosity::Debug
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
+
+/// Determines whether messages of the given `level` are displayed.
+pub fn enabled(level: Verbosity) -> bool {
+    Verbosity::current() >= level
+}
+
+/// Displays a status message to stderr, unless the output is quiet.
+pub fn info<D: Display>(message: D) {
+    if enabled(Verbosity::Normal) {
+        eprintln!("{}", message);
+    }
+}
+
+/// Displays the details of a step to stderr, if the output is verbose.
+pub fn verbose<D: Display>(message: D) {
+    if enabled(Verbosity::Verbose) {
+        eprintln!("{}", message);
+    }
+}
+
+/// Displays a diagnostic to stderr with a styled prefix, at the debug level.
+pub fn debug<D: Display>(message: D) {
+    if enabled(Verbosity::Debug) {
+        eprintln!("{} {}", style("debug:").dim().bold(), message);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::Verbosity;
+
+    #[test]
+    fn test_parse() {
+        for verbosity in &[
+            Verbosity::Quiet,
+            Verbosity::Normal,
+            Verbosity::Verbose,
+            Verbosity::Debug,
+        ] {
+            assert_eq!(Verbosity::parse(verbosity.name()), Some(*verbosity));
+        }
+        assert_eq!(Verbosity::parse("loud"), None);
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(Verbosity::Quiet < Verbosity::Normal);
+        assert!(Verbosity::Normal < Verbosity::Verbose);
+        assert!(Verbosity::Verbose < Verbosity::Debug);
+    }
+}
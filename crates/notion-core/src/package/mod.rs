@@ -0,0 +1,504 @@
+//! Provides the installation of packages as tools of the user toolchain, such as
+//! `notion install typescript`.
+//!
+//! Each version of a package is installed with npm into its own image directory, apart
+//! from every other package and from the global packages of any Node version, using the
+//! default Node of the user toolchain at the time of the install. That platform is
+//! recorded with the package and each of its binaries, and the shims of the binaries
+//! always run them with it, whatever Node the current project pins, so that a tool keeps
+//! working the way it did when it was installed.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use failure::Fail;
+use semver::Version;
+use serde_json::{self, Value};
+use tempfile::tempdir_in;
+
+use crate::checksum::Checksum;
+use crate::distro::error::DownloadError;
+use crate::distro::kind::partial_file;
+use crate::fs::{
+    ensure_containing_dir_exists, ensure_dir_exists, ensure_home_writable, long_path, read_file_opt,
+};
+use crate::interrupt::{self, Cleanup};
+use crate::inventory::RegistryFetchError;
+use crate::output;
+use crate::path;
+use crate::platform::{Image, PlatformSpec};
+use crate::registry::{dist_checksum, Registry};
+use crate::session::Session;
+use crate::shim;
+use crate::style::progress_spinner;
+use crate::tool::ToolSpec;
+use crate::version::VersionSpec;
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+pub(crate) mod serial;
+
+/// Thrown when a package is installed without a default Node to install it with.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Installing {} requires a default Node version\n\nInstall one with `notion install node` first.",
+    package
+)]
+#[notion_fail(code = "ConfigurationError")]
+struct NoPackagePlatformError {
+    package: String,
+}
+
+/// Thrown when no published version of a package matches the requested version.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "No version of {} found matching {}", package, matching)]
+#[notion_fail(code = "NoVersionMatch")]
+struct NoPackageVersionError {
+    package: String,
+    matching: String,
+}
+
+/// Thrown when npm fails to install a package into its image.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Could not install {} v{}\n{}", package, version, error)]
+#[notion_fail(code = "ExecutionFailure")]
+struct PackageInstallError {
+    package: String,
+    version: String,
+    error: String,
+}
+
+/// Thrown when the record of an installed package has lost its platform.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "The installed package {} has no recorded Node version\n\nReinstall it with `notion install {}`.",
+    package, package
+)]
+#[notion_fail(code = "ConfigurationError")]
+struct MissingPlatformError {
+    package: String,
+}
+
+/// A package installed in the user toolchain.
+#[derive(Debug, PartialEq)]
+pub struct PackageConfig {
+    pub name: String,
+    pub version: Version,
+    /// The platform the package was installed with, which its binaries run with.
+    pub platform: PlatformSpec,
+    /// The names of the binaries the package provides.
+    pub bins: Vec<String>,
+}
+
+/// A binary provided by an installed package.
+#[derive(Debug, PartialEq)]
+pub struct BinConfig {
+    pub name: String,
+    /// The package that provides the binary.
+    pub package: String,
+    pub version: Version,
+    /// The platform the binary runs with.
+    pub platform: PlatformSpec,
+}
+
+impl PackageConfig {
+    /// Reads the record of the installed package `name`, if it is installed.
+    pub fn current(name: &str) -> Fallible<Option<Self>> {
+        match read_file_opt(&path::user_package_config_file(name)?).unknown()? {
+            Some(src) => {
+                let config: serial::PackageConfig = serde_json::from_str(&src).unknown()?;
+                Ok(Some(config.into_config()?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self) -> Fallible<()> {
+        let file = path::user_package_config_file(&self.name)?;
+        ensure_containing_dir_exists(&file)?;
+        let src = serde_json::to_string_pretty(&self.to_serial()).unknown()?;
+        fs::write(file, src).unknown()
+    }
+
+    /// The record of the binary `name` of this package.
+    fn bin_config(&self, name: &str) -> BinConfig {
+        BinConfig {
+            name: name.to_string(),
+            package: self.name.clone(),
+            version: self.version.clone(),
+            platform: self.platform.clone(),
+        }
+    }
+}
+
+impl BinConfig {
+    /// Reads the record of the binary `name`, if an installed package provides it.
+    pub fn current(name: &str) -> Fallible<Option<Self>> {
+        match read_file_opt(&path::user_tool_bin_config(name)?).unknown()? {
+            Some(src) => {
+                let config: serial::BinConfig = serde_json::from_str(&src).unknown()?;
+                Ok(Some(config.into_config()?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self) -> Fallible<()> {
+        let file = path::user_tool_bin_config(&self.name)?;
+        ensure_containing_dir_exists(&file)?;
+        let src = serde_json::to_string_pretty(&self.to_serial()).unknown()?;
+        fs::write(file, src).unknown()
+    }
+
+    /// The directory holding the executable of this binary.
+    pub fn bin_dir(&self) -> Fallible<PathBuf> {
+        path::package_image_bin_dir(&self.package, &self.version.to_string())
+    }
+}
+
+/// Lists the packages installed in the user toolchain.
+pub fn installed() -> Fallible<Vec<PackageConfig>> {
+    let dir = path::user_package_dir()?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(&dir).unknown()? {
+        let entry = entry.unknown()?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        // scoped packages are recorded in a directory for their scope
+        if file_name.starts_with('@') && entry.file_type().unknown()?.is_dir() {
+            for scoped in fs::read_dir(entry.path()).unknown()? {
+                let scoped = scoped.unknown()?.file_name().to_string_lossy().into_owned();
+                if scoped.ends_with(".json") {
+                    names.push(format!(
+                        "{}/{}",
+                        file_name,
+                        scoped.trim_end_matches(".json")
+                    ));
+                }
+            }
+        } else if file_name.ends_with(".json") {
+            names.push(file_name.trim_end_matches(".json").to_string());
+        }
+    }
+    names.sort();
+
+    let mut packages = Vec::new();
+    for name in names {
+        if let Some(config) = PackageConfig::current(&name)? {
+            packages.push(config);
+        }
+    }
+    Ok(packages)
+}
+
+/// A version of a package published on a registry.
+#[derive(Debug, PartialEq)]
+struct Published {
+    version: Version,
+    tarball: String,
+    checksum: Option<Checksum>,
+}
+
+/// Installs the latest version of the package `name` matching `matching` with `platform`,
+/// or else the default platform of the user toolchain, and creates the shims of its
+/// binaries.
+pub(crate) fn install(
+    session: &mut Session,
+    name: &str,
+    matching: &VersionSpec,
+    platform: Option<&PlatformSpec>,
+) -> Fallible<PackageConfig> {
+    ensure_home_writable(&format!("install {}", name))?;
+    let platform = match platform {
+        Some(platform) => platform.clone(),
+        None => match session.user_platform()? {
+            Some(platform) => (*platform).clone(),
+            None => throw!(NoPackagePlatformError {
+                package: name.to_string()
+            }),
+        },
+    };
+
+    let registry = session.npm_registry(name)?;
+    let published = resolve(&registry, name, matching)?;
+    let image = platform.checkout(session)?;
+    let tarball = fetch(&registry, name, &published)?;
+
+    let version = published.version.to_string();
+    install_image(name, &version, &image, &tarball)?;
+    let manifest_file = path::package_image_module_dir(name, &version)?.join("package.json");
+    let manifest: Value =
+        serde_json::from_str(&fs::read_to_string(manifest_file).unknown()?).unknown()?;
+
+    let config = PackageConfig {
+        name: name.to_string(),
+        version: published.version,
+        platform: PlatformSpec {
+            node_runtime: image.node.runtime.clone(),
+            npm: Some(image.node.npm.clone()),
+            yarn: None,
+            deno: None,
+        },
+        bins: bin_names(name, &manifest),
+    };
+
+    // the binaries that an earlier version provided but this one doesn't are removed
+    if let Some(previous) = PackageConfig::current(name)? {
+        for bin in previous
+            .bins
+            .iter()
+            .filter(|bin| !config.bins.contains(bin))
+        {
+            let file = path::user_tool_bin_config(bin)?;
+            if file.exists() {
+                fs::remove_file(file).unknown()?;
+            }
+            shim::delete(bin)?;
+        }
+    }
+    config.save()?;
+    for bin in &config.bins {
+        config.bin_config(bin).save()?;
+        shim::create(bin)?;
+    }
+
+    output::info(format!(
+        "Installed {} v{} with Node v{}{}",
+        config.name,
+        config.version,
+        config.platform.node_runtime,
+        if config.bins.is_empty() {
+            String::new()
+        } else {
+            format!(" (providing {})", config.bins.join(", "))
+        }
+    ));
+    Ok(config)
+}
+
+/// Resolves the published version of `name` matching `matching` on `registry`.
+fn resolve(registry: &Registry, name: &str, matching: &VersionSpec) -> Fallible<Published> {
+    let url = registry.metadata_url(name);
+    let spinner = progress_spinner(&format!("Fetching public registry: {}", url));
+    let packument: Value = registry
+        .get(&url)?
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|mut response| response.json())
+        .with_context(RegistryFetchError::from_error)?;
+    spinner.finish();
+
+    match published(&packument, matching) {
+        Some(published) => Ok(published),
+        None => throw!(NoPackageVersionError {
+            package: name.to_string(),
+            matching: matching.to_string(),
+        }),
+    }
+}
+
+/// Finds the version matching `matching` in the metadata document of a package: the
+/// version tagged `latest` (or with another dist-tag), or else the highest version that
+/// satisfies the requirement.
+fn published(packument: &Value, matching: &VersionSpec) -> Option<Published> {
+    let versions = packument["versions"].as_object()?;
+    let version = match matching {
+        VersionSpec::Latest => Version::parse(packument["dist-tags"]["latest"].as_str()?).ok()?,
+        VersionSpec::Semver(requirement) => versions
+            .keys()
+            .filter_map(|version| Version::parse(version).ok())
+            .filter(|version| requirement.matches(version))
+            .max()?,
+        VersionSpec::Exact(version) => version.clone(),
+        VersionSpec::Tag(tag) => Version::parse(packument["dist-tags"][tag].as_str()?).ok()?,
+    };
+
+    let dist = &versions.get(&version.to_string())?["dist"];
+    Some(Published {
+        tarball: dist["tarball"].as_str()?.to_string(),
+        checksum: dist_checksum(dist),
+        version,
+    })
+}
+
+/// Downloads the tarball of `published` to the inventory, unless it is already there,
+/// and verifies it against the integrity published for it.
+fn fetch(registry: &Registry, name: &str, published: &Published) -> Fallible<PathBuf> {
+    let distro_file = path::package_distro_file(name, &published.version.to_string())?;
+    if distro_file.is_file() && verify(&distro_file, &published.checksum)? {
+        return Ok(distro_file);
+    }
+
+    ensure_containing_dir_exists(&distro_file)?;
+    let partial = partial_file(&distro_file);
+    let spinner = progress_spinner(&format!("Fetching {} v{}", name, published.version));
+    let mut response = registry
+        .get(&published.tarball)?
+        .send()
+        .and_then(|response| response.error_for_status())
+        .with_context(RegistryFetchError::from_error)?;
+    let mut file = File::create(&partial).unknown()?;
+    io::copy(&mut response, &mut file).unknown()?;
+    spinner.finish();
+
+    if let Some(ref expected) = published.checksum {
+        let actual = Checksum::of_file(expected.algorithm, &partial)?;
+        if actual != *expected {
+            fs::remove_file(&partial).unknown()?;
+            throw!(DownloadError::ChecksumMismatch {
+                toolspec: ToolSpec::Package(
+                    name.to_string(),
+                    VersionSpec::exact(&published.version)
+                ),
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+    }
+    fs::rename(&partial, &distro_file).unknown()?;
+    Ok(distro_file)
+}
+
+/// Determines whether `file` matches the `expected` checksum, if there is one.
+fn verify(file: &Path, expected: &Option<Checksum>) -> Fallible<bool> {
+    match expected {
+        Some(expected) => Ok(Checksum::of_file(expected.algorithm, file)? == *expected),
+        None => Ok(true),
+    }
+}
+
+/// Installs the package `tarball` into the image of `name` at `version`, with the npm of
+/// `image`. The package is installed in a staging directory first, so that a failed
+/// install leaves any earlier image of the version in place.
+fn install_image(name: &str, version: &str, image: &Image, tarball: &Path) -> Fallible<()> {
+    let staging = path::staging_dir()?;
+    ensure_dir_exists(&staging)?;
+    let temp = tempdir_in(long_path(staging)).unknown()?;
+    let _interrupt = interrupt::register(vec![Cleanup::RemoveDir(temp.path().to_path_buf())]);
+
+    let node_str = image.node.runtime.to_string();
+    let npm_str = image.node.npm.to_string();
+    let npm = path::node_image_bin_dir(&node_str, &npm_str)?.join(if cfg!(windows) {
+        "npm.cmd"
+    } else {
+        "npm"
+    });
+
+    let spinner = progress_spinner(&format!(
+        "Installing {} v{} with Node v{}",
+        name, version, node_str
+    ));
+    let output = Command::new(npm)
+        .args(&["install", "--global", "--prefix"])
+        .arg(temp.path())
+        .arg(tarball)
+        .env("PATH", image.path()?)
+        .output()
+        .unknown()?;
+    spinner.finish();
+    if !output.status.success() {
+        throw!(PackageInstallError {
+            package: name.to_string(),
+            version: version.to_string(),
+            error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    let image_dir = path::package_image_dir(name, version)?;
+    if image_dir.exists() {
+        fs::remove_dir_all(long_path(&image_dir)).unknown()?;
+    }
+    ensure_containing_dir_exists(&image_dir)?;
+    fs::rename(temp.into_path(), image_dir).unknown()
+}
+
+/// Reads the names of the binaries that a package provides from its `package.json`: a
+/// single `bin` is named after the package (without its scope), and a map of `bin`
+/// entries names each binary.
+fn bin_names(name: &str, manifest: &Value) -> Vec<String> {
+    match &manifest["bin"] {
+        Value::String(_) => vec![name.rsplit('/').next().unwrap_or(name).to_string()],
+        Value::Object(bins) => bins.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{bin_names, published};
+    use crate::checksum::{Algorithm, Checksum};
+    use crate::version::VersionSpec;
+    use semver::{Version, VersionReq};
+    use serde_json::json;
+
+    fn packument() -> serde_json::Value {
+        json!({
+            "dist-tags": { "latest": "3.1.6" },
+            "versions": {
+                "3.1.6": {
+                    "dist": {
+                        "tarball": "https://registry.npmjs.org/typescript/-/typescript-3.1.6.tgz",
+                        "shasum": "b6543a83cfc8c2befb3f4c8fba6896f5b0c9be68"
+                    }
+                },
+                "3.2.1": {
+                    "dist": {
+                        "tarball": "https://registry.npmjs.org/typescript/-/typescript-3.2.1.tgz"
+                    }
+                },
+                "2.9.2": {
+                    "dist": {
+                        "tarball": "https://registry.npmjs.org/typescript/-/typescript-2.9.2.tgz"
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_published() {
+        let latest = published(&packument(), &VersionSpec::Latest).unwrap();
+        assert_eq!(latest.version, Version::parse("3.1.6").unwrap());
+        assert_eq!(
+            latest.checksum,
+            Some(Checksum::new(
+                Algorithm::Sha1,
+                "b6543a83cfc8c2befb3f4c8fba6896f5b0c9be68"
+            ))
+        );
+
+        let matching = VersionSpec::Semver(VersionReq::parse("^3.1").unwrap());
+        let highest = published(&packument(), &matching).unwrap();
+        assert_eq!(highest.version, Version::parse("3.2.1").unwrap());
+        assert_eq!(
+            highest.tarball,
+            "https://registry.npmjs.org/typescript/-/typescript-3.2.1.tgz"
+        );
+        assert_eq!(highest.checksum, None);
+
+        let missing = VersionSpec::exact(&Version::parse("4.0.0").unwrap());
+        assert_eq!(published(&packument(), &missing), None);
+    }
+
+    #[test]
+    fn test_bin_names() {
+        assert_eq!(
+            bin_names("@vue/cli", &json!({ "bin": "bin/vue.js" })),
+            vec!["cli"]
+        );
+        assert_eq!(
+            bin_names(
+                "typescript",
+                &json!({ "bin": { "tsc": "bin/tsc", "tsserver": "bin/tsserver" } })
+            ),
+            vec!["tsc", "tsserver"]
+        );
+        assert!(bin_names("left-pad", &json!({})).is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use super::{BinConfig as BinConfigSpec, MissingPlatformError, PackageConfig as PackageConfigSpec};
+use crate::toolchain::serial::Platform;
+use notion_fail::{throw, Fallible, ResultExt};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PackageConfig {
+    pub name: String,
+    pub version: String,
+    pub platform: Platform,
+    #[serde(default)]
+    pub bins: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct BinConfig {
+    pub name: String,
+    pub package: String,
+    pub version: String,
+    pub platform: Platform,
+}
+
+impl PackageConfig {
+    pub fn into_config(self) -> Fallible<PackageConfigSpec> {
+        let platform = match self.platform.into_image()? {
+            Some(platform) => platform,
+            None => throw!(MissingPlatformError { package: self.name }),
+        };
+        Ok(PackageConfigSpec {
+            version: Version::parse(&self.version).unknown()?,
+            name: self.name,
+            platform,
+            bins: self.bins,
+        })
+    }
+}
+
+impl BinConfig {
+    pub fn into_config(self) -> Fallible<BinConfigSpec> {
+        let platform = match self.platform.into_image()? {
+            Some(platform) => platform,
+            None => throw!(MissingPlatformError {
+                package: self.package
+            }),
+        };
+        Ok(BinConfigSpec {
+            version: Version::parse(&self.version).unknown()?,
+            name: self.name,
+            package: self.package,
+            platform,
+        })
+    }
+}
+
+impl PackageConfigSpec {
+    pub fn to_serial(&self) -> PackageConfig {
+        PackageConfig {
+            name: self.name.clone(),
+            version: self.version.to_string(),
+            platform: self.platform.to_serial(),
+            bins: self.bins.clone(),
+        }
+    }
+}
+
+impl BinConfigSpec {
+    pub fn to_serial(&self) -> BinConfig {
+        BinConfig {
+            name: self.name.clone(),
+            package: self.package.clone(),
+            version: self.version.to_string(),
+            platform: self.platform.to_serial(),
+        }
+    }
+}
@@ -4,6 +4,7 @@
 use std::env;
 use std::path::{Path, PathBuf};
 
+use crate::config::LayoutConfig;
 use notion_fail::Fallible;
 
 cfg_if::cfg_if! {
@@ -32,14 +33,35 @@ pub fn notion_home() -> Fallible<PathBuf> {
     }
 }
 
+/// Returns the cache directory, honoring the `NOTION_CACHE_DIR` environment variable or
+/// else the `layout.cache_dir` setting of the user configuration file.
 pub fn cache_dir() -> Fallible<PathBuf> {
-    Ok(notion_home()?.join("cache"))
+    if let Some(dir) = env::var_os("NOTION_CACHE_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    match LayoutConfig::with_current(|layout| layout.cache_dir.clone()) {
+        Some(dir) => Ok(dir),
+        None => Ok(notion_home()?.join("cache")),
+    }
 }
 
 pub fn tmp_dir() -> Fallible<PathBuf> {
     Ok(notion_home()?.join("tmp"))
 }
 
+/// The directory that distributions are unpacked into before they are moved into their
+/// image, which is on the same volume as the images so that the move is a rename.
+pub fn staging_dir() -> Fallible<PathBuf> {
+    match shared_dir()? {
+        Some(dir) => Ok(dir.join("tmp")),
+        None => tmp_dir(),
+    }
+}
+
+pub fn manifest_backup_root_dir() -> Fallible<PathBuf> {
+    Ok(notion_home()?.join("backups").join("manifests"))
+}
+
 pub fn node_inventory_dir() -> Fallible<PathBuf> {
     Ok(inventory_dir()?.join("node"))
 }
@@ -48,14 +70,62 @@ pub fn yarn_inventory_dir() -> Fallible<PathBuf> {
     Ok(inventory_dir()?.join("yarn"))
 }
 
+pub fn deno_inventory_dir() -> Fallible<PathBuf> {
+    Ok(inventory_dir()?.join("deno"))
+}
+
+pub fn npm_inventory_dir() -> Fallible<PathBuf> {
+    Ok(inventory_dir()?.join("npm"))
+}
+
 pub fn package_inventory_dir() -> Fallible<PathBuf> {
     Ok(inventory_dir()?.join("packages"))
 }
 
+/// The tarball of a package version, as downloaded from its registry.
+pub fn package_distro_file(name: &str, version: &str) -> Fallible<PathBuf> {
+    Ok(package_inventory_dir()?
+        .join(name)
+        .join(version)
+        .join("package.tgz"))
+}
+
+/// The lock file held by a Notion process while it modifies the inventory.
+pub fn inventory_lock_file() -> Fallible<PathBuf> {
+    Ok(inventory_dir()?.join("inventory.lock"))
+}
+
+/// The directory holding the journal entries of fetches in progress.
+pub fn install_journal_dir() -> Fallible<PathBuf> {
+    Ok(inventory_dir()?.join("journal"))
+}
+
+/// Global packages belong to each user, so they stay in `NOTION_HOME` even when the
+/// inventory is shared.
+pub fn node_globals_root_dir() -> Fallible<PathBuf> {
+    Ok(tools_dir()?.join("inventory").join("globals").join("node"))
+}
+
+pub fn node_global_prefix_dir(node: &str) -> Fallible<PathBuf> {
+    Ok(node_globals_root_dir()?.join(node))
+}
+
+/// The folder that Yarn installs global packages into under the given version of Node,
+/// whose binaries Yarn links into the global prefix.
+pub fn node_yarn_global_dir(node: &str) -> Fallible<PathBuf> {
+    Ok(node_global_prefix_dir(node)?.join("yarn"))
+}
+
 pub fn node_cache_dir() -> Fallible<PathBuf> {
     Ok(cache_dir()?.join("node"))
 }
 
+/// The cache used by one-off execution flows (`npx`, `npm exec`, `yarn dlx`)
+/// running under the given version of Node.
+pub fn node_exec_cache_dir(node: &str) -> Fallible<PathBuf> {
+    Ok(cache_dir()?.join("exec").join(node))
+}
+
 pub fn node_index_file() -> Fallible<PathBuf> {
     Ok(node_cache_dir()?.join("index.json"))
 }
@@ -64,8 +134,40 @@ pub fn node_index_expiry_file() -> Fallible<PathBuf> {
     Ok(node_cache_dir()?.join("index.json.expires"))
 }
 
+/// Records the `ETag` and `Last-Modified` headers of the cached Node index, to revalidate
+/// it with once it expires.
+pub fn node_index_validators_file() -> Fallible<PathBuf> {
+    Ok(node_cache_dir()?.join("index.json.validators"))
+}
+
+/// The release schedule of Node, as last fetched from the Node release team.
+pub fn node_schedule_file() -> Fallible<PathBuf> {
+    Ok(node_cache_dir()?.join("schedule.json"))
+}
+
+/// Records when Notion last checked for new LTS releases to prefetch.
+pub fn node_lts_prefetch_file() -> Fallible<PathBuf> {
+    Ok(node_cache_dir()?.join("lts-prefetch"))
+}
+
+/// Records the warnings about unsupported Node versions shown today, and the projects
+/// whose pins `notion outdated` checks.
+pub fn node_support_file() -> Fallible<PathBuf> {
+    Ok(tools_dir()?.join("node-support.json"))
+}
+
+/// Counts the installs since the inventory was last garbage collected.
+pub fn gc_install_count_file() -> Fallible<PathBuf> {
+    Ok(tools_dir()?.join("gc-installs"))
+}
+
+/// Records the `PATH` entries that were reported for shadowing the shim directory.
+pub fn path_conflict_file() -> Fallible<PathBuf> {
+    Ok(cache_dir()?.join("path-conflicts"))
+}
+
 pub fn image_dir() -> Fallible<PathBuf> {
-    Ok(tools_dir()?.join("image"))
+    Ok(shared_tools_dir()?.join("image"))
 }
 
 pub fn node_image_root_dir() -> Fallible<PathBuf> {
@@ -85,23 +187,152 @@ pub fn yarn_image_dir(version: &str) -> Fallible<PathBuf> {
 }
 
 pub fn yarn_image_bin_dir(version: &str) -> Fallible<PathBuf> {
-    Ok(yarn_image_dir(version)?.join("bin"))
+    Ok(overlay(yarn_image_dir(version)?)?.join("bin"))
 }
 
-pub fn shim_dir() -> Fallible<PathBuf> {
+pub fn deno_image_root_dir() -> Fallible<PathBuf> {
+    Ok(image_dir()?.join("deno"))
+}
+
+pub fn deno_image_dir(version: &str) -> Fallible<PathBuf> {
+    Ok(deno_image_root_dir()?.join(version))
+}
+
+pub fn deno_image_bin_dir(version: &str) -> Fallible<PathBuf> {
+    Ok(overlay(deno_image_dir(version)?)?.join("bin"))
+}
+
+/// The root of the images of npm versions fetched on their own, rather than installed
+/// with Node.
+pub fn npm_image_root_dir() -> Fallible<PathBuf> {
+    Ok(image_dir()?.join("npm"))
+}
+
+pub fn npm_image_dir(version: &str) -> Fallible<PathBuf> {
+    Ok(npm_image_root_dir()?.join(version))
+}
+
+pub fn npm_image_bin_dir(version: &str) -> Fallible<PathBuf> {
+    Ok(overlay(npm_image_dir(version)?)?.join("bin"))
+}
+
+pub fn package_image_root_dir() -> Fallible<PathBuf> {
+    Ok(image_dir()?.join("packages"))
+}
+
+/// The prefix that a package version is installed in, apart from every other package.
+pub fn package_image_dir(name: &str, version: &str) -> Fallible<PathBuf> {
+    Ok(package_image_root_dir()?.join(name).join(version))
+}
+
+pub fn default_shim_dir() -> Fallible<PathBuf> {
     Ok(notion_home()?.join("bin"))
 }
 
+/// Returns the shim directory, honoring the `NOTION_SHIM_DIR` environment variable or
+/// else the `layout.shim_dir` setting of the user configuration file.
+pub fn shim_dir() -> Fallible<PathBuf> {
+    if let Some(dir) = env::var_os("NOTION_SHIM_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    match LayoutConfig::with_current(|layout| layout.shim_dir.clone()) {
+        Some(dir) => Ok(dir),
+        None => default_shim_dir(),
+    }
+}
+
 pub fn user_hooks_file() -> Fallible<PathBuf> {
     Ok(notion_home()?.join("hooks.toml"))
 }
 
+pub fn user_config_file() -> Fallible<PathBuf> {
+    Ok(notion_home()?.join("config.toml"))
+}
+
+/// Returns the organization policy file, which can be overridden with the
+/// `NOTION_POLICY_FILE` environment variable.
+pub fn policy_file() -> Fallible<PathBuf> {
+    if let Some(file) = env::var_os("NOTION_POLICY_FILE") {
+        Ok(PathBuf::from(file))
+    } else {
+        default_policy_file()
+    }
+}
+
 pub fn tools_dir() -> Fallible<PathBuf> {
     Ok(notion_home()?.join("tools"))
 }
 
+/// Returns the shared directory holding the inventory and images of all the users of a
+/// machine, if the `NOTION_SHARED_DIR` environment variable or the `layout.shared_dir`
+/// setting of the user configuration file selects one.
+pub fn shared_dir() -> Fallible<Option<PathBuf>> {
+    if let Some(dir) = env::var_os("NOTION_SHARED_DIR") {
+        return Ok(Some(PathBuf::from(dir)));
+    }
+    Ok(LayoutConfig::with_current(|layout| {
+        layout.shared_dir.clone()
+    }))
+}
+
+/// The directory holding the inventory and images: the shared directory if there is one,
+/// or else the user's own tools directory.
+pub fn shared_tools_dir() -> Fallible<PathBuf> {
+    match shared_dir()? {
+        Some(dir) => Ok(dir.join("tools")),
+        None => tools_dir(),
+    }
+}
+
+/// Returns the inventory directory, honoring the `NOTION_INVENTORY_DIR` environment
+/// variable or else the `layout.inventory_dir` setting of the user configuration file.
 pub fn inventory_dir() -> Fallible<PathBuf> {
-    Ok(tools_dir()?.join("inventory"))
+    if let Some(dir) = env::var_os("NOTION_INVENTORY_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    match LayoutConfig::with_current(|layout| layout.inventory_dir.clone()) {
+        Some(dir) => Ok(dir),
+        None => Ok(shared_tools_dir()?.join("inventory")),
+    }
+}
+
+/// Returns the read-only directory pre-seeded with an inventory and images, if the
+/// `NOTION_SEED_DIR` environment variable or the `layout.seed_dir` setting of the user
+/// configuration file selects one.
+pub fn seed_dir() -> Fallible<Option<PathBuf>> {
+    if let Some(dir) = env::var_os("NOTION_SEED_DIR") {
+        return Ok(Some(PathBuf::from(dir)));
+    }
+    Ok(LayoutConfig::with_current(|layout| layout.seed_dir.clone()))
+}
+
+/// Maps `path`, in the inventory or the images, to the same path in the seed directory,
+/// if there is one.
+pub fn seeded(path: &Path) -> Fallible<Option<PathBuf>> {
+    let seed = match seed_dir()? {
+        Some(seed) => seed.join("tools"),
+        None => return Ok(None),
+    };
+    if let Ok(relative) = path.strip_prefix(inventory_dir()?) {
+        return Ok(Some(seed.join("inventory").join(relative)));
+    }
+    if let Ok(relative) = path.strip_prefix(image_dir()?) {
+        return Ok(Some(seed.join("image").join(relative)));
+    }
+    Ok(None)
+}
+
+/// Returns `path` if it exists, or else its counterpart in the seed directory if that
+/// exists. Fetches are always written to `path`, so the seed directory can be mounted
+/// read-only, with the writable inventory and images as an overlay for new versions.
+pub fn overlay(path: PathBuf) -> Fallible<PathBuf> {
+    if path.exists() {
+        return Ok(path);
+    }
+    match seeded(&path)? {
+        Some(seeded) if seeded.exists() => Ok(seeded),
+        _ => Ok(path),
+    }
 }
 
 pub fn user_toolchain_dir() -> Fallible<PathBuf> {
@@ -112,6 +343,32 @@ pub fn user_platform_file() -> Fallible<PathBuf> {
     Ok(user_toolchain_dir()?.join("platform.json"))
 }
 
+pub fn user_aliases_file() -> Fallible<PathBuf> {
+    Ok(user_toolchain_dir()?.join("aliases.toml"))
+}
+
+pub fn user_shims_file() -> Fallible<PathBuf> {
+    Ok(user_toolchain_dir()?.join("shims.json"))
+}
+
+/// The packages installed in the user toolchain, recorded as `<package>.json` files.
+pub fn user_package_dir() -> Fallible<PathBuf> {
+    Ok(user_toolchain_dir()?.join("packages"))
+}
+
+pub fn user_package_config_file(name: &str) -> Fallible<PathBuf> {
+    Ok(user_package_dir()?.join(format!("{}.json", name)))
+}
+
+/// The binaries of the installed packages, recorded as `<binary>.json` files.
+pub fn user_tool_bin_dir() -> Fallible<PathBuf> {
+    Ok(user_toolchain_dir()?.join("bins"))
+}
+
+pub fn user_tool_bin_config(bin_name: &str) -> Fallible<PathBuf> {
+    Ok(user_tool_bin_dir()?.join(format!("{}.json", bin_name)))
+}
+
 pub fn node_distro_file_name(version: &str) -> String {
     format!(
         "{}.{}",
@@ -125,6 +382,18 @@ pub fn node_npm_version_file(version: &str) -> Fallible<PathBuf> {
     Ok(node_inventory_dir()?.join(&filename))
 }
 
+/// The file recording that this version of Node was built from source, in place of a
+/// distribution archive.
+pub fn node_source_build_file(version: &str) -> Fallible<PathBuf> {
+    Ok(node_inventory_dir()?.join(format!("node-v{}-source", version)))
+}
+
+/// The file recording that this version of Node was installed from a local build, in
+/// place of a distribution archive.
+pub fn node_local_build_file(version: &str) -> Fallible<PathBuf> {
+    Ok(node_inventory_dir()?.join(format!("node-v{}-local", version)))
+}
+
 pub fn node_archive_root_dir_name(version: &str) -> String {
     format!("node-v{}-{}-{}", version, OS, ARCH)
 }
@@ -145,6 +414,27 @@ pub fn yarn_archive_root_dir_name(version: &str) -> String {
     format!("yarn-v{}", version)
 }
 
+/// The name of the Deno release archive for this platform, as published upstream.
+pub fn deno_archive_file_name() -> String {
+    format!("deno-{}.zip", DENO_TARGET)
+}
+
+/// The name a Deno release archive is stored under in the inventory. Unlike the
+/// upstream name, it includes the version.
+pub fn deno_distro_file_name(version: &str) -> String {
+    format!("deno-v{}-{}.zip", version, DENO_TARGET)
+}
+
+/// The name of an npm tarball, as published on the npm registry.
+pub fn npm_distro_file_name(version: &str) -> String {
+    format!("npm-{}.tgz", version)
+}
+
+/// The name of the Deno executable in a release archive.
+pub fn deno_binary_file_name() -> String {
+    format!("deno{}", env::consts::EXE_SUFFIX)
+}
+
 #[cfg(test)]
 pub mod tests {
 
@@ -166,6 +456,26 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_node_global_prefix_dir() {
+        assert_eq!(
+            node_global_prefix_dir("10.13.0").unwrap(),
+            inventory_dir()
+                .unwrap()
+                .join("globals")
+                .join("node")
+                .join("10.13.0")
+        );
+    }
+
+    #[test]
+    fn test_node_yarn_global_dir() {
+        assert_eq!(
+            node_yarn_global_dir("10.13.0").unwrap(),
+            node_global_prefix_dir("10.13.0").unwrap().join("yarn")
+        );
+    }
+
     #[test]
     fn test_yarn_distro_file_name() {
         assert_eq!(yarn_distro_file_name("1.2.3"), "yarn-v1.2.3.tar.gz");
@@ -178,4 +488,13 @@ pub mod tests {
             "yarn-v1.2.3".to_string()
         );
     }
+
+    #[test]
+    fn test_deno_distro_file_name() {
+        assert_eq!(
+            deno_distro_file_name("1.2.3"),
+            format!("deno-v1.2.3-{}.zip", DENO_TARGET)
+        );
+        assert_eq!(deno_archive_file_name(), format!("deno-{}.zip", DENO_TARGET));
+    }
 }
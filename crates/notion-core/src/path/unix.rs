@@ -11,7 +11,9 @@ use failure::Fail;
 use notion_fail::{ExitCode, Fallible, NotionFail};
 use notion_fail_derive::*;
 
-use super::{node_image_dir, notion_home, shim_dir};
+use super::{
+    node_global_prefix_dir, node_image_dir, notion_home, overlay, package_image_dir, shim_dir,
+};
 
 #[derive(Debug, Fail, NotionFail)]
 #[fail(display = "environment variable 'HOME' is not set")]
@@ -46,12 +48,31 @@ cfg_if::cfg_if! {
     }
 }
 
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "macos")] {
+        /// The target triple in the name of a Deno release archive.
+        pub const DENO_TARGET: &'static str = "x86_64-apple-darwin";
+    } else {
+        /// The target triple in the name of a Deno release archive.
+        pub const DENO_TARGET: &'static str = "x86_64-unknown-linux-gnu";
+    }
+}
+
 // ~/
 //     .notion/
 //         cache/                                          cache_dir
 //             node/                                       node_cache_dir
 //                 index.json                              node_index_file
 //                 index.json.expires                      node_index_expiry_file
+//                 schedule.json                           node_schedule_file
+//                 lts-prefetch                            node_lts_prefetch_file
+//             exec/
+//                 10.13.0/                                node_exec_cache_dir("10.13.0")
+//         backups/
+//             manifests/                                  manifest_backup_root_dir
+//                 3f2a...e91c/                            (one per package.json)
+//                     source                              (the path of the package.json)
+//                     package-1546300800000.json
 //         bin/                                            shim_dir
 //             node                                        shim_file("node")
 //             npm
@@ -59,12 +80,26 @@ cfg_if::cfg_if! {
 //             ...
 //         tools/                                          tools_dir
 //             inventory/                                  inventory_dir
+//                 inventory.lock                          inventory_lock_file
+//                 journal/                                install_journal_dir
+//                     node-10.13.0.json                   (one per fetch in progress)
 //                 node/                                   node_inventory_dir
 //                     node-v4.8.4-linux-x64.tar.gz        node_distro_file_name("4.8.4")
 //                     node-v4.8.4-npm                     node_npm_version_file("4.8.4")
 //                     ...
 //                 packages/                               package_inventory_dir
 //                 yarn/                                   yarn_inventory_dir
+//                 deno/                                   deno_inventory_dir
+//                     deno-v1.0.0-x86_64-unknown-linux-gnu.zip
+//                                                         deno_distro_file_name("1.0.0")
+//                 npm/                                    npm_inventory_dir
+//                     npm-8.1.0.tgz                       npm_distro_file_name("8.1.0")
+//                 globals/
+//                     node/                               node_globals_root_dir
+//                         10.13.0/                        node_global_prefix_dir("10.13.0")
+//                             bin/                        node_global_bin_dir("10.13.0")
+//                             lib/node_modules/
+//                             yarn/                       node_yarn_global_dir("10.13.0")
 //             image/                                      image_dir
 //                 node/                                   node_image_root_dir
 //                     10.13.0/
@@ -72,6 +107,12 @@ cfg_if::cfg_if! {
 //                             bin/                        node_image_bin_dir("10.13.0", "6.4.0")
 //                 yarn/                                   yarn_image_root_dir
 //                     1.7.0/                              yarn_image_dir("1.7.0")
+//                 deno/                                   deno_image_root_dir
+//                     1.0.0/                              deno_image_dir("1.0.0")
+//                         bin/                            deno_image_bin_dir("1.0.0")
+//                 npm/                                    npm_image_root_dir
+//                     8.1.0/                              npm_image_dir("8.1.0")
+//                         bin/                            npm_image_bin_dir("8.1.0")
 //             user/                                       user_toolchain_dir
 //                 bins/
 //                     ember ~> ../packages/ember-cli
@@ -80,10 +121,45 @@ cfg_if::cfg_if! {
 //                         package.toml
 //                         contents/
 //                 platform.json                           user_platform_file
+//                 shims.json                              user_shims_file
 //         notion                                          notion_file
 //         launchbin                                       launchbin_file
 //         launchscript                                    launchscript_file
 //         hooks.toml                                      user_hooks_file
+//         config.toml                                     user_config_file
+
+// With a shared directory (`NOTION_SHARED_DIR` or `layout.shared_dir`), the inventory and
+// the images move there, and are used by every user of the machine, while shims, the user
+// toolchain and global packages stay in each user's NOTION_HOME:
+//
+// /srv/notion/                                            shared_dir
+//     tmp/                                                staging_dir
+//     tools/                                              shared_tools_dir
+//         inventory/                                      inventory_dir
+//             ...
+//         image/                                          image_dir
+//             ...
+
+// The cache directory (`NOTION_CACHE_DIR` or `layout.cache_dir`) and the inventory
+// (`NOTION_INVENTORY_DIR` or `layout.inventory_dir`) can each be moved on their own, e.g.
+// to another volume. A seed directory (`NOTION_SEED_DIR` or `layout.seed_dir`), laid out
+// like the shared directory, holds a pre-seeded inventory and images that are only read,
+// so it can be mounted read-only; versions it lacks are fetched into the writable ones:
+//
+// /opt/notion-seed/                                       seed_dir
+//     tools/
+//         inventory/                                      seeded(inventory_dir)
+//             ...
+//         image/                                          seeded(image_dir)
+//             ...
+
+// /etc/
+//     notion/
+//         policy.toml                                     default_policy_file
+
+pub fn default_policy_file() -> Fallible<PathBuf> {
+    Ok(PathBuf::from("/etc/notion/policy.toml"))
+}
 
 pub fn default_notion_home() -> Fallible<PathBuf> {
     let home = dirs::home_dir().ok_or(NoHomeEnvVar)?;
@@ -95,16 +171,38 @@ pub fn archive_extension() -> String {
 }
 
 pub fn node_image_bin_dir(node: &str, npm: &str) -> Fallible<PathBuf> {
-    Ok(node_image_dir(node, npm)?.join("bin"))
+    Ok(overlay(node_image_dir(node, npm)?)?.join("bin"))
 }
 
 // 3rd-party binaries installed globally for this node version
 pub fn node_image_3p_bin_dir(node: &str, npm: &str) -> Fallible<PathBuf> {
-    Ok(node_image_dir(node, npm)?.join("lib/node_modules/.bin"))
+    Ok(overlay(node_image_dir(node, npm)?)?.join("lib/node_modules/.bin"))
+}
+
+// binaries of a package installed in its own image
+pub fn package_image_bin_dir(name: &str, version: &str) -> Fallible<PathBuf> {
+    Ok(package_image_dir(name, version)?.join("bin"))
+}
+
+// the installed package itself, with its `package.json`
+pub fn package_image_module_dir(name: &str, version: &str) -> Fallible<PathBuf> {
+    Ok(package_image_dir(name, version)?
+        .join("lib")
+        .join("node_modules")
+        .join(name))
+}
+
+// binaries of legacy global packages installed under this node version
+pub fn node_global_bin_dir(node: &str) -> Fallible<PathBuf> {
+    Ok(node_global_prefix_dir(node)?.join("bin"))
 }
 
 pub fn shim_file(toolname: &str) -> Fallible<PathBuf> {
-    Ok(shim_dir()?.join(toolname))
+    Ok(shim_dir()?.join(shim_file_name(toolname)))
+}
+
+pub fn shim_file_name(toolname: &str) -> String {
+    toolname.to_string()
 }
 
 pub fn notion_file() -> Fallible<PathBuf> {
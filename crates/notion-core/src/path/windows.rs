@@ -1,10 +1,12 @@
 //! Provides functions for determining the paths of files and directories
 //! in a standard Notion layout in Windows operating systems.
 
+use std::env;
 use std::io;
 #[cfg(windows)]
 use std::os::windows;
 use std::path::PathBuf;
+use std::process::Command;
 
 use dirs;
 use failure::Fail;
@@ -12,7 +14,9 @@ use failure::Fail;
 use notion_fail::{ExitCode, Fallible, NotionFail};
 use notion_fail_derive::*;
 
-use super::{node_image_dir, notion_home, shim_dir};
+use super::{
+    node_global_prefix_dir, node_image_dir, notion_home, overlay, package_image_dir, shim_dir,
+};
 
 // These are taken from: https://nodejs.org/dist/index.json and are used
 // by `path::archive_root_dir` to determine the root directory of the
@@ -30,6 +34,9 @@ cfg_if::cfg_if! {
     }
 }
 
+/// The target triple in the name of a Deno release archive.
+pub const DENO_TARGET: &'static str = "x86_64-pc-windows-msvc";
+
 #[derive(Debug, Fail, NotionFail)]
 #[fail(display = "Windows LocalAppData directory not found")]
 #[notion_fail(code = "EnvironmentError")]
@@ -41,6 +48,15 @@ pub(crate) struct NoDataLocalDir;
 //             node\                                       node_cache_dir
 //                 index.json                              node_index_file
 //                 index.json.expires                      node_index_expiry_file
+//                 schedule.json                           node_schedule_file
+//                 lts-prefetch                            node_lts_prefetch_file
+//             exec\
+//                 10.13.0\                                node_exec_cache_dir("10.13.0")
+//         backups\
+//             manifests\                                  manifest_backup_root_dir
+//                 3f2a...e91c\                            (one per package.json)
+//                     source                              (the path of the package.json)
+//                     package-1546300800000.json
 //         bin\                                            shim_dir
 //             node                                        shim_file("node")
 //             npm
@@ -48,19 +64,40 @@ pub(crate) struct NoDataLocalDir;
 //             ...
 //         tools\                                          tools_dir
 //             inventory\                                  inventory_dir
+//                 inventory.lock                          inventory_lock_file
+//                 journal\                                install_journal_dir
+//                     node-10.13.0.json                   (one per fetch in progress)
 //                 node\                                   node_inventory_dir
 //                     node-v4.8.4-win-x64.zip             node_archive_file("4.8.4")
 //                     node-v4.8.4-npm                     node_npm_version_file("4.8.4")
 //                     ...
 //                 packages\                               package_inventory_dir
 //                 yarn\                                   yarn_inventory_dir
+//                 deno\                                   deno_inventory_dir
+//                     deno-v1.0.0-x86_64-pc-windows-msvc.zip
+//                                                         deno_distro_file_name("1.0.0")
+//                 npm\                                    npm_inventory_dir
+//                     npm-8.1.0.tgz                       npm_distro_file_name("8.1.0")
+//                 globals\
+//                     node\                               node_globals_root_dir
+//                         10.13.0\                        node_global_prefix_dir("10.13.0")
+//                                                         node_global_bin_dir("10.13.0")
+//                             node_modules\
+//                             yarn\                       node_yarn_global_dir("10.13.0")
 //             image\                                      image_dir
 //                 node\                                   node_image_root_dir
 //                     10.13.0\
 //                         6.4.0\                          node_image_dir("10.13.0", "6.4.0")
 //                                                         node_image_bin_dir("10.13.0", "6.4.0")
+//                                                         node_image_3p_bin_dir("10.13.0", "6.4.0")
 //                 yarn\                                   yarn_image_root_dir
 //                     1.7.0\                              yarn_image_dir("1.7.0")
+//                 deno\                                   deno_image_root_dir
+//                     1.0.0\                              deno_image_dir("1.0.0")
+//                         bin\                            deno_image_bin_dir("1.0.0")
+//                 npm\                                    npm_image_root_dir
+//                     8.1.0\                              npm_image_dir("8.1.0")
+//                         bin\                            npm_image_bin_dir("8.1.0")
 //             user\                                       user_toolchain_dir
 //                 bins\
 //                     ember ~> ..\packages\ember-cli
@@ -69,10 +106,48 @@ pub(crate) struct NoDataLocalDir;
 //                         package.toml
 //                         contents\
 //                 platform.json                           user_platform_file
+//                 shims.json                              user_shims_file
 //         notion.exe                                      notion_file
 //         launchbin.exe                                   launchbin_file
 //         launchscript.exe                                launchscript_file
 //         hooks.toml                                      user_hooks_file
+//         config.toml                                     user_config_file
+
+// With a shared directory (`NOTION_SHARED_DIR` or `layout.shared_dir`), the inventory and
+// the images move there, and are used by every user of the machine, while shims, the user
+// toolchain and global packages stay in each user's NOTION_HOME:
+//
+// D:\Notion\                                              shared_dir
+//     tmp\                                                staging_dir
+//     tools\                                              shared_tools_dir
+//         inventory\                                      inventory_dir
+//             ...
+//         image\                                          image_dir
+//             ...
+
+// The cache directory (`NOTION_CACHE_DIR` or `layout.cache_dir`) and the inventory
+// (`NOTION_INVENTORY_DIR` or `layout.inventory_dir`) can each be moved on their own, e.g.
+// to another volume. A seed directory (`NOTION_SEED_DIR` or `layout.seed_dir`), laid out
+// like the shared directory, holds a pre-seeded inventory and images that are only read,
+// so it can be mounted read-only; versions it lacks are fetched into the writable ones:
+//
+// E:\NotionSeed\                                          seed_dir
+//     tools\
+//         inventory\                                      seeded(inventory_dir)
+//             ...
+//         image\                                          seeded(image_dir)
+//             ...
+
+// C:\ProgramData\
+//     Notion\
+//         policy.toml                                     default_policy_file
+
+pub fn default_policy_file() -> Fallible<PathBuf> {
+    let program_data = env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"));
+    Ok(program_data.join("Notion").join("policy.toml"))
+}
 
 pub fn default_notion_home() -> Fallible<PathBuf> {
     let home = dirs::data_local_dir().ok_or(NoDataLocalDir)?;
@@ -84,13 +159,30 @@ pub fn archive_extension() -> String {
 }
 
 pub fn node_image_bin_dir(node: &str, npm: &str) -> Fallible<PathBuf> {
-    node_image_dir(node, npm)
+    overlay(node_image_dir(node, npm)?)
+}
+
+// 3rd-party binaries installed globally for this node version, which npm puts
+// (as `.cmd` launchers) directly in its prefix, the root of the image
+pub fn node_image_3p_bin_dir(node: &str, npm: &str) -> Fallible<PathBuf> {
+    overlay(node_image_dir(node, npm)?)
 }
 
-// 3rd-party binaries installed globally for this node version
-pub fn node_image_3p_bin_dir(_node: &str, _npm: &str) -> Fallible<PathBuf> {
-    // ISSUE (#90) Figure out where binaries are globally installed on Windows
-    unimplemented!("global 3rd party executables not yet implemented for Windows")
+// binaries of a package installed in its own image
+pub fn package_image_bin_dir(name: &str, version: &str) -> Fallible<PathBuf> {
+    package_image_dir(name, version)
+}
+
+// the installed package itself, with its `package.json`
+pub fn package_image_module_dir(name: &str, version: &str) -> Fallible<PathBuf> {
+    Ok(package_image_dir(name, version)?
+        .join("node_modules")
+        .join(name))
+}
+
+// binaries of legacy global packages installed under this node version
+pub fn node_global_bin_dir(node: &str) -> Fallible<PathBuf> {
+    node_global_prefix_dir(node)
 }
 
 pub fn launchbin_file() -> Fallible<PathBuf> {
@@ -106,7 +198,11 @@ pub fn notion_file() -> Fallible<PathBuf> {
 }
 
 pub fn shim_file(toolname: &str) -> Fallible<PathBuf> {
-    Ok(shim_dir()?.join(&format!("{}.exe", toolname)))
+    Ok(shim_dir()?.join(shim_file_name(toolname)))
+}
+
+pub fn shim_file_name(toolname: &str) -> String {
+    format!("{}.exe", toolname)
 }
 
 pub fn create_file_symlink(src: PathBuf, dst: PathBuf) -> Result<(), io::Error> {
@@ -117,3 +213,32 @@ pub fn create_file_symlink(src: PathBuf, dst: PathBuf) -> Result<(), io::Error>
     #[cfg(feature = "universal-docs")]
     unimplemented!()
 }
+
+/// Determines whether Win32 long path support is enabled (the `LongPathsEnabled`
+/// registry setting). Without it, tools run by Notion may fail on deep `node_modules`
+/// trees, even though Notion itself uses verbatim paths. Returns `None` if the
+/// registry could not be queried.
+pub fn long_paths_enabled() -> Option<bool> {
+    let output = Command::new("reg")
+        .args(&[
+            "query",
+            r"HKLM\SYSTEM\CurrentControlSet\Control\FileSystem",
+            "/v",
+            "LongPathsEnabled",
+        ])
+        .output()
+        .ok()?;
+
+    // a missing value means the setting has never been turned on
+    if !output.status.success() {
+        return Some(false);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value = stdout
+        .lines()
+        .find(|line| line.trim_start().starts_with("LongPathsEnabled"))
+        .and_then(|line| line.split_whitespace().last())?;
+
+    Some(value == "0x1")
+}
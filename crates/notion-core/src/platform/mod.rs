@@ -1,11 +1,13 @@
 use std::ffi::OsString;
 use std::path::PathBuf;
 
+use blake2_rfc::blake2b::blake2b;
 use envoy;
 use semver::Version;
 
 use crate::distro::node::{load_default_npm_version, NodeVersion};
-use crate::path;
+use crate::fs::{ensure_dir_exists, is_read_only_home};
+use crate::path::{self, ARCH, OS};
 use crate::session::Session;
 use notion_fail::{Fallible, ResultExt};
 
@@ -14,29 +16,69 @@ use notion_fail::{Fallible, ResultExt};
 pub struct PlatformSpec {
     /// The pinned version of Node.
     pub node_runtime: Version,
-    /// The pinned version of npm, if any.
+    /// The pinned version of npm, if any. Unless it is the npm bundled with Node, it is
+    /// fetched on its own and layered over the Node image.
     pub npm: Option<Version>,
     /// The pinned version of Yarn, if any.
     pub yarn: Option<Version>,
+    /// The pinned version of Deno, if any.
+    pub deno: Option<Version>,
 }
 
 impl PlatformSpec {
-    pub fn checkout(&self, session: &mut Session) -> Fallible<Image> {
-        session.ensure_node(&self.node_runtime)?;
+    /// Produces a key identifying this toolchain on the current OS and architecture,
+    /// suitable for keying caches of `NOTION_HOME` in CI. The key is deterministic, and
+    /// changes exactly when one of the versions (or the platform) changes.
+    pub fn cache_key(&self) -> String {
+        let mut manifest = format!(
+            "notion-cache-v1\nos={}\narch={}\nnode={}\nnpm={}\nyarn={}\n",
+            OS,
+            ARCH,
+            self.node_runtime,
+            self.npm
+                .as_ref()
+                .map(|npm| npm.to_string())
+                .unwrap_or_else(|| "bundled".to_string()),
+            self.yarn
+                .as_ref()
+                .map(|yarn| yarn.to_string())
+                .unwrap_or_else(|| "none".to_string()),
+        );
+        // only appended when pinned, so that existing keys are unchanged
+        if let Some(ref deno) = self.deno {
+            manifest.push_str(&format!("deno={}\n", deno));
+        }
+        let hash = blake2b(16, &[], manifest.as_bytes());
+        let hex: String = hash
+            .as_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("notion-{}-{}-{}", OS, ARCH, hex)
+    }
 
-        if let Some(ref yarn_version) = self.yarn {
-            session.ensure_yarn(yarn_version)?;
+    /// Returns the pinned version of npm if it isn't the one bundled with Node, which can
+    /// only be told once Node has been fetched.
+    pub(crate) fn custom_npm(&self) -> Fallible<Option<Version>> {
+        match self.npm {
+            Some(ref npm) if *npm != load_default_npm_version(&self.node_runtime)? => {
+                Ok(Some(npm.clone()))
+            }
+            _ => Ok(None),
         }
+    }
+
+    pub fn checkout(&self, session: &mut Session) -> Fallible<Image> {
+        session.ensure_platform(self)?;
 
         Ok(Image {
             node: NodeVersion {
                 runtime: self.node_runtime.clone(),
-                npm: match self.npm {
-                    Some(ref version) => version.clone(),
-                    None => load_default_npm_version(&self.node_runtime)?,
-                },
+                npm: load_default_npm_version(&self.node_runtime)?,
             },
+            npm: self.custom_npm()?,
             yarn: self.yarn.clone(),
+            deno: self.deno.clone(),
         })
     }
 }
@@ -44,26 +86,79 @@ impl PlatformSpec {
 /// A platform image.
 #[derive(Eq, PartialEq, Clone, Debug)]
 pub struct Image {
-    /// The pinned version of Node.
+    /// The pinned version of Node, with the npm bundled with it.
     pub node: NodeVersion,
+    /// The pinned version of npm, if it isn't the one bundled with Node.
+    pub npm: Option<Version>,
     /// The pinned version of Yarn, if any.
     pub yarn: Option<Version>,
+    /// The pinned version of Deno, if any.
+    pub deno: Option<Version>,
 }
 
 impl Image {
+    /// Returns the version of npm that the image runs: the pinned one, or else the one
+    /// bundled with Node.
+    pub fn npm_version(&self) -> &Version {
+        self.npm.as_ref().unwrap_or(&self.node.npm)
+    }
+
     pub fn bins(&self) -> Fallible<Vec<PathBuf>> {
         let node_str = self.node.runtime.to_string();
         let npm_str = self.node.npm.to_string();
-        let mut bins = vec![path::node_image_bin_dir(&node_str, &npm_str)?];
+        let mut bins = Vec::new();
+        // the pinned npm is found before the one in the Node image
+        if let Some(ref npm) = self.npm {
+            bins.push(path::npm_image_bin_dir(&npm.to_string())?);
+        }
+        bins.push(path::node_image_bin_dir(&node_str, &npm_str)?);
         if let Some(ref yarn) = self.yarn {
             let yarn_str = yarn.to_string();
             bins.push(path::yarn_image_bin_dir(&yarn_str)?);
         }
+        if let Some(ref deno) = self.deno {
+            bins.push(path::deno_image_bin_dir(&deno.to_string())?);
+        }
         Ok(bins)
     }
 
+    /// Returns the global package prefix for this image's Node version, creating it and
+    /// its bin directory if necessary (unless `NOTION_HOME` is read-only). Legacy global
+    /// installs (`npm install -g` with `NOTION_UNSAFE_GLOBAL`) are written here so that
+    /// each Node version gets its own set of global packages.
+    pub fn global_prefix(&self) -> Fallible<PathBuf> {
+        let node_str = self.node.runtime.to_string();
+        let prefix = path::node_global_prefix_dir(&node_str)?;
+        if !is_read_only_home() {
+            ensure_dir_exists(&path::node_global_bin_dir(&node_str)?)?;
+        }
+        Ok(prefix)
+    }
+
+    /// Returns the folder that `yarn global add` installs into for this image's Node
+    /// version, creating it if necessary (unless `NOTION_HOME` is read-only). Its
+    /// binaries are linked into the bin directory of `global_prefix`.
+    pub fn yarn_global_folder(&self) -> Fallible<PathBuf> {
+        let folder = path::node_yarn_global_dir(&self.node.runtime.to_string())?;
+        if !is_read_only_home() {
+            ensure_dir_exists(&folder)?;
+        }
+        Ok(folder)
+    }
+
+    /// Returns the cache directory for one-off executions (`npx`, `npm exec`, `yarn dlx`)
+    /// under this image's Node version, creating it if necessary (unless `NOTION_HOME` is
+    /// read-only).
+    pub fn exec_cache(&self) -> Fallible<PathBuf> {
+        let cache = path::node_exec_cache_dir(&self.node.runtime.to_string())?;
+        if !is_read_only_home() {
+            ensure_dir_exists(&cache)?;
+        }
+        Ok(cache)
+    }
+
     /// Produces a modified version of the current `PATH` environment variable that
-    /// will find toolchain executables (Node, Yarn) in the installation directories
+    /// will find toolchain executables (Node, Yarn, Deno) in the installation directories
     /// for the given versions instead of in the Notion shim directory.
     pub fn path(&self) -> Fallible<OsString> {
         let old_path = envoy::path().unwrap_or(envoy::Var::from(""));
@@ -169,7 +264,9 @@ mod test {
                 runtime: v123.clone(),
                 npm: v643.clone(),
             },
+            npm: None,
             yarn: None,
+            deno: None,
         };
 
         assert_eq!(
@@ -182,7 +279,9 @@ mod test {
                 runtime: v123.clone(),
                 npm: v643.clone(),
             },
+            npm: None,
             yarn: Some(v457.clone()),
+            deno: None,
         };
 
         assert_eq!(
@@ -192,6 +291,29 @@ mod test {
                 expected_node_bin, expected_yarn_bin
             ),
         );
+
+        let npm_bin = notion_home()
+            .unwrap()
+            .join("tools")
+            .join("image")
+            .join("npm")
+            .join("8.1.0")
+            .join("bin");
+        let expected_npm_bin = npm_bin.as_path().to_str().unwrap();
+
+        let with_npm_image = Image {
+            npm: Some(Version::parse("8.1.0").unwrap()),
+            ..no_yarn_image
+        };
+
+        assert_eq!(
+            with_npm_image.path().unwrap().into_string().unwrap(),
+            format!(
+                "{}:{}:/usr/bin:/blah:/doesnt/matter/bin",
+                expected_npm_bin, expected_node_bin
+            ),
+        );
+        assert_eq!(with_npm_image.npm_version().to_string(), "8.1.0");
     }
 
     #[cfg(windows)]
@@ -235,7 +357,9 @@ mod test {
                 runtime: v123.clone(),
                 npm: v643.clone(),
             },
+            npm: None,
             yarn: None,
+            deno: None,
         };
 
         assert_eq!(
@@ -248,7 +372,9 @@ mod test {
                 runtime: v123.clone(),
                 npm: v643.clone(),
             },
+            npm: None,
             yarn: Some(v457.clone()),
+            deno: None,
         };
 
         assert_eq!(
@@ -350,4 +476,29 @@ mod test {
             expected_path
         );
     }
+
+    #[test]
+    fn test_cache_key() {
+        let platform = PlatformSpec {
+            node_runtime: Version::parse("10.13.0").unwrap(),
+            npm: None,
+            yarn: Some(Version::parse("1.12.3").unwrap()),
+            deno: None,
+        };
+        let key = platform.cache_key();
+        assert!(key.starts_with(&format!("notion-{}-{}-", OS, ARCH)));
+        assert_eq!(key, platform.clone().cache_key());
+
+        let repinned = PlatformSpec {
+            yarn: Some(Version::parse("1.13.0").unwrap()),
+            ..platform
+        };
+        assert_ne!(key, repinned.cache_key());
+
+        let with_deno = PlatformSpec {
+            deno: Some(Version::parse("1.0.0").unwrap()),
+            ..repinned.clone()
+        };
+        assert_ne!(repinned.cache_key(), with_deno.cache_key());
+    }
 }
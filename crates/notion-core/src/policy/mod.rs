@@ -0,0 +1,205 @@
+//! Provides types for working with organization policy files (`policy.toml`), which
+//! let administrators restrict the tool versions that may be installed or pinned on a
+//! machine.
+
+use std::path::PathBuf;
+
+use failure::Fail;
+use lazycell::LazyCell;
+use semver::{Version, VersionReq};
+use toml;
+
+use crate::fs::read_file_opt;
+use crate::path::policy_file;
+use crate::style::display_warning;
+use crate::tool::ToolSpec;
+use crate::version::VersionSpec;
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+pub(crate) mod serial;
+
+/// Thrown when the policy file could not be parsed.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Invalid policy file {}: {}", file, error)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct PolicyParseError {
+    pub(crate) file: String,
+    pub(crate) error: String,
+}
+
+/// Thrown when a tool version is not allowed by the organization policy.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "{} is not allowed by the policy in {}: {}",
+    version, file, reason
+)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct PolicyViolationError {
+    version: String,
+    file: String,
+    reason: String,
+}
+
+/// Lazily loaded organization policy
+pub struct LazyPolicy {
+    policy: LazyCell<Option<Policy>>,
+}
+
+impl LazyPolicy {
+    /// Constructs a new `LazyPolicy` (but does not initialize it).
+    pub fn new() -> LazyPolicy {
+        LazyPolicy {
+            policy: LazyCell::new(),
+        }
+    }
+
+    /// Forces the loading of the policy, which is `None` if there is no policy file.
+    pub fn get(&self) -> Fallible<Option<&Policy>> {
+        Ok(self.policy.try_borrow_with(|| Policy::current())?.as_ref())
+    }
+}
+
+/// How the policy treats versions it doesn't allow
+#[derive(Debug, PartialEq)]
+pub enum PolicyMode {
+    /// Installing or pinning a disallowed version fails.
+    Enforce,
+    /// Installing or pinning a disallowed version only prints a warning.
+    Warn,
+}
+
+/// Restrictions on the versions of a single tool
+#[derive(Debug, Default, PartialEq)]
+pub struct ToolPolicy {
+    /// The range of allowed versions, if restricted.
+    pub allowed: Option<VersionReq>,
+    /// Versions that are never allowed.
+    pub banned: Vec<Version>,
+}
+
+impl ToolPolicy {
+    /// Determines why `version` is not allowed by this policy, if it isn't.
+    fn violation(&self, version: &Version) -> Option<String> {
+        if self.banned.contains(version) {
+            return Some(format!("version {} is banned", version));
+        }
+
+        match self.allowed {
+            Some(ref allowed) if !allowed.matches(version) => {
+                Some(format!("allowed versions are {}", allowed))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An organization policy
+#[derive(Debug, PartialEq)]
+pub struct Policy {
+    /// The file this policy was read from.
+    pub file: PathBuf,
+    pub mode: PolicyMode,
+    pub node: ToolPolicy,
+    pub yarn: ToolPolicy,
+}
+
+impl Policy {
+    /// Returns the current policy, loaded from the filesystem, or `None` if there is
+    /// no policy file.
+    pub fn current() -> Fallible<Option<Self>> {
+        let file = policy_file()?;
+        match read_file_opt(&file).unknown()? {
+            Some(src) => Ok(Some(Policy::parse(&src, file)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses the contents of the policy file `file`.
+    fn parse(src: &str, file: PathBuf) -> Fallible<Self> {
+        let serial: serial::Policy =
+            toml::from_str(src).with_context(|error: &toml::de::Error| PolicyParseError {
+                file: file.to_string_lossy().to_string(),
+                error: error.to_string(),
+            })?;
+        serial.into_policy(file)
+    }
+
+    /// Checks that the version `toolspec` resolved to is allowed by this policy, before
+    /// it is fetched. Disallowed versions are an error in `enforce` mode and a warning in
+    /// `warn` mode.
+    pub fn check(&self, toolspec: &ToolSpec) -> Fallible<()> {
+        let violation = match toolspec {
+            ToolSpec::Node(VersionSpec::Exact(runtime)) => self.node.violation(runtime),
+            ToolSpec::Yarn(VersionSpec::Exact(version)) => self.yarn.violation(version),
+            _ => None,
+        };
+
+        if let Some(reason) = violation {
+            let error = PolicyViolationError {
+                version: toolspec.to_string(),
+                file: self.file.to_string_lossy().to_string(),
+                reason,
+            };
+
+            match self.mode {
+                PolicyMode::Enforce => throw!(error),
+                PolicyMode::Warn => display_warning(error),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{Policy, PolicyMode};
+    use crate::tool::ToolSpec;
+    use crate::version::VersionSpec;
+    use semver::Version;
+    use std::path::PathBuf;
+
+    fn parse(src: &str) -> Policy {
+        Policy::parse(src, PathBuf::from("policy.toml")).expect("Could not parse policy")
+    }
+
+    #[test]
+    fn test_parse() {
+        let policy = parse(
+            r#"
+mode = "warn"
+
+[node]
+allowed = ">=10, <12"
+banned = ["10.1.0"]
+"#,
+        );
+        assert_eq!(policy.mode, PolicyMode::Warn);
+        assert!(policy.node.allowed.is_some());
+        assert_eq!(policy.node.banned, vec![Version::parse("10.1.0").unwrap()]);
+        assert_eq!(policy.yarn.allowed, None);
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = parse(
+            r#"
+[node]
+allowed = ">=10, <12"
+banned = ["10.1.0"]
+"#,
+        );
+        let exact = |version: &str| VersionSpec::exact(&Version::parse(version).unwrap());
+        let node = |version: &str| ToolSpec::Node(exact(version));
+
+        assert_eq!(policy.mode, PolicyMode::Enforce);
+        assert!(policy.check(&node("10.2.0")).is_ok());
+        assert!(policy.check(&node("10.1.0")).is_err());
+        assert!(policy.check(&node("12.0.0")).is_err());
+        assert!(policy.check(&ToolSpec::Yarn(exact("1.12.3"))).is_ok());
+        // unresolved versions can't be checked yet
+        assert!(policy.check(&ToolSpec::Node(VersionSpec::Latest)).is_ok());
+    }
+}
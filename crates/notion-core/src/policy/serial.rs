@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+
+use super::PolicyParseError;
+use notion_fail::{throw, Fallible, ResultExt};
+
+#[derive(Deserialize)]
+pub struct Policy {
+    pub mode: Option<String>,
+    pub node: Option<ToolPolicy>,
+    pub yarn: Option<ToolPolicy>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ToolPolicy {
+    pub allowed: Option<String>,
+    #[serde(default)]
+    pub banned: Vec<String>,
+}
+
+impl Policy {
+    pub fn into_policy(self, file: PathBuf) -> Fallible<super::Policy> {
+        let file_name = file.to_string_lossy().to_string();
+        let mode = match self.mode.as_ref().map(String::as_str) {
+            None | Some("enforce") => super::PolicyMode::Enforce,
+            Some("warn") => super::PolicyMode::Warn,
+            Some(mode) => throw!(PolicyParseError {
+                file: file_name,
+                error: format!("unknown mode `{}` (expected `enforce` or `warn`)", mode),
+            }),
+        };
+
+        Ok(super::Policy {
+            mode,
+            node: self.node.unwrap_or_default().into_tool_policy(&file_name)?,
+            yarn: self.yarn.unwrap_or_default().into_tool_policy(&file_name)?,
+            file,
+        })
+    }
+}
+
+impl ToolPolicy {
+    fn into_tool_policy(self, file_name: &str) -> Fallible<super::ToolPolicy> {
+        let parse_error = |error: &semver::ReqParseError| PolicyParseError {
+            file: file_name.to_string(),
+            error: error.to_string(),
+        };
+
+        let allowed = match self.allowed {
+            Some(allowed) => Some(VersionReq::parse(&allowed).with_context(parse_error)?),
+            None => None,
+        };
+
+        let banned = self
+            .banned
+            .iter()
+            .map(|version| {
+                Version::parse(version).with_context(|error: &semver::SemVerError| {
+                    PolicyParseError {
+                        file: file_name.to_string(),
+                        error: error.to_string(),
+                    }
+                })
+            })
+            .collect::<Fallible<Vec<Version>>>()?;
+
+        Ok(super::ToolPolicy { allowed, banned })
+    }
+}
@@ -0,0 +1,135 @@
+//! Provides the opt-in background prefetching of new LTS releases of Node.
+//!
+//! When `prefetch.lts` is enabled in `config.toml`, Notion checks at most once a day,
+//! after a command completes, whether a newer LTS patch has been published for the Node
+//! majors pinned by the user toolchain and the current project, and fetches it into the
+//! inventory. The check runs in a detached `notion fetch --lts-updates` process, so that
+//! it never delays or clutters the output of the command itself.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use semver::Version;
+
+use crate::distro::Fetched;
+use crate::env::{is_offline, NONINTERACTIVE};
+use crate::fs::{ensure_containing_dir_exists, is_read_only_home};
+use crate::inventory::node_lts_versions;
+use crate::path;
+use crate::session::Session;
+use crate::tool::ToolSpec;
+use crate::version::VersionSpec;
+use notion_fail::{Fallible, ResultExt};
+
+/// The minimum time between two checks for new LTS releases.
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Starts a background check for new LTS releases if prefetching is enabled and the
+/// last check is more than a day old. Returns whether a check was started.
+pub fn spawn_if_due(session: &Session) -> Fallible<bool> {
+    if !session.config()?.prefetch.lts || is_read_only_home() || is_offline() || !is_due()? {
+        return Ok(false);
+    }
+
+    // record the check before starting it, so that a failing check isn't retried
+    // after every command
+    let stamp = path::node_lts_prefetch_file()?;
+    ensure_containing_dir_exists(&stamp)?;
+    fs::write(&stamp, b"").unknown()?;
+
+    Command::new(env::current_exe().unknown()?)
+        .args(&["fetch", "--lts-updates"])
+        .env(NONINTERACTIVE, "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unknown()?;
+
+    Ok(true)
+}
+
+fn is_due() -> Fallible<bool> {
+    let checked = match fs::metadata(path::node_lts_prefetch_file()?) {
+        Ok(metadata) => metadata.modified().unknown()?,
+        Err(_) => return Ok(true),
+    };
+
+    // a check recorded in the future (e.g. after a clock change) counts as stale
+    Ok(checked
+        .elapsed()
+        .map(|elapsed| elapsed >= CHECK_INTERVAL)
+        .unwrap_or(true))
+}
+
+/// Fetches the newest LTS release in the major line of each pinned Node version, if it
+/// is newer than the pinned version. Returns the versions that were newly fetched.
+pub fn fetch_lts_updates(session: &mut Session) -> Fallible<Vec<Version>> {
+    let mut pinned = Vec::new();
+    if let Some(platform) = session.user_platform()? {
+        pinned.push(platform.node_runtime.clone());
+    }
+    if let Some(platform) = session.project_platform()? {
+        pinned.push(platform.node_runtime.clone());
+    }
+    if pinned.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let lts = node_lts_versions(session.hooks()?.node.as_ref())?;
+
+    let mut fetched = Vec::new();
+    for version in updates(&pinned, &lts) {
+        let toolspec = ToolSpec::Node(VersionSpec::exact(&version));
+        if let Fetched::Now(_) = session.fetch(&toolspec)? {
+            fetched.push(version);
+        }
+    }
+    Ok(fetched)
+}
+
+/// Selects, for each of the `pinned` versions, the newest of the `lts` versions with the
+/// same major version, if it is newer than the pinned one.
+fn updates(pinned: &[Version], lts: &[Version]) -> BTreeSet<Version> {
+    pinned
+        .iter()
+        .filter_map(|pinned| {
+            lts.iter()
+                .filter(|version| version.major == pinned.major && *version > pinned)
+                .max()
+                .cloned()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::updates;
+    use semver::Version;
+
+    fn versions(versions: &[&str]) -> Vec<Version> {
+        versions
+            .iter()
+            .map(|v| Version::parse(v).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_updates() {
+        let lts = versions(&["10.15.1", "10.15.0", "8.15.0", "8.14.1", "6.16.0"]);
+
+        assert_eq!(
+            updates(&versions(&["10.13.0", "8.15.0"]), &lts),
+            versions(&["10.15.1"]).into_iter().collect()
+        );
+        assert_eq!(
+            updates(&versions(&["8.9.4", "8.11.0"]), &lts),
+            versions(&["8.15.0"]).into_iter().collect()
+        );
+        assert!(updates(&versions(&["11.6.0"]), &lts).is_empty());
+    }
+}
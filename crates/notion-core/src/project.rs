@@ -1,9 +1,10 @@
 //! Provides the `Project` type, which represents a Node project tree in
 //! the filesystem.
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::env;
 use std::ffi::OsStr;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
@@ -14,9 +15,15 @@ use crate::distro::node::load_default_npm_version;
 use crate::distro::DistroVersion;
 use crate::manifest::{serial, Manifest};
 use crate::platform::PlatformSpec;
-use crate::shim;
+use crate::shim::{self, ShimManifest};
+use crate::version::VersionSpec;
 use notion_fail::{throw, ExitCode, Fallible, NotionError, NotionFail, ResultExt};
 use notion_fail_derive::*;
+use semver::Version;
+
+/// The files that other version managers pin the Node version of a project in, by
+/// precedence.
+const NODE_VERSION_FILES: &[&str] = &[".nvmrc", ".node-version"];
 
 fn is_node_root(dir: &Path) -> bool {
     dir.join("package.json").is_file()
@@ -34,6 +41,88 @@ fn is_project_root(dir: &Path) -> bool {
     is_node_root(dir) && !is_dependency(dir)
 }
 
+/// Returns the root of the workspace that the project at `dir` is a package of, if any,
+/// with its manifest.
+fn workspace_root(dir: &Path) -> Option<(PathBuf, Manifest)> {
+    for ancestor in dir
+        .ancestors()
+        .skip(1)
+        .filter(|ancestor| is_project_root(ancestor))
+    {
+        // a package.json that can't be read isn't taken for the root of a workspace
+        let manifest = match Manifest::for_dir(ancestor) {
+            Ok(manifest) => manifest,
+            Err(_) => continue,
+        };
+        let member = dir.strip_prefix(ancestor).unwrap_or(dir);
+        if is_workspace_member(&manifest.workspaces, member) {
+            return Some((ancestor.to_path_buf(), manifest));
+        }
+    }
+    None
+}
+
+/// Determines whether the package at the relative path `member` is matched by the
+/// workspace globs `patterns` (where a glob starting with `!` excludes packages).
+fn is_workspace_member(patterns: &[String], member: &Path) -> bool {
+    let member: Vec<String> = member
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let member: Vec<&str> = member.iter().map(String::as_str).collect();
+    let matches = |pattern: &str| {
+        let segments: Vec<&str> = pattern
+            .split('/')
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .collect();
+        glob_matches(&segments, &member)
+    };
+
+    let mut included = false;
+    for pattern in patterns {
+        if pattern.starts_with('!') {
+            if matches(&pattern[1..]) {
+                return false;
+            }
+        } else if matches(pattern) {
+            included = true;
+        }
+    }
+    included
+}
+
+/// Matches the segments of a path against those of a glob, where `**` matches any number
+/// of segments.
+fn glob_matches(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => {
+            glob_matches(rest, path) || (!path.is_empty() && glob_matches(pattern, &path[1..]))
+        }
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path)) => segment_matches(segment, name) && glob_matches(rest, path),
+            None => false,
+        },
+    }
+}
+
+/// Matches a file name against a glob segment, where `*` matches any characters and `?`
+/// any one character.
+fn segment_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|skip| matches(rest, &name[skip..])),
+            Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && matches(rest, &name[1..]),
+        }
+    }
+    matches(&pattern, &name)
+}
+
 pub struct LazyDependentBins {
     bins: LazyCell<HashMap<String, String>>,
 }
@@ -68,7 +157,7 @@ impl DepPackageReadError {
     }
 }
 
-/// Thrown when a user tries to pin a Yarn version before pinning a Node version.
+/// Thrown when a user tries to pin a Yarn, npm or Deno version before pinning a Node version.
 #[derive(Debug, Fail, NotionFail)]
 #[fail(display = "There is no pinned node version for this project")]
 #[notion_fail(code = "ConfigurationError")]
@@ -80,6 +169,44 @@ impl NoPinnedNodeVersion {
     }
 }
 
+/// Thrown when a `.nvmrc` or `.node-version` file doesn't contain a Node version.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Could not read the Node version `{}` in {}\n\nUse a version or range (e.g. `10.13.0` or `10`), `node` or `lts/*`, or pin a version with `notion pin node`.",
+    contents, file
+)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct NodeVersionFileError {
+    file: String,
+    contents: String,
+}
+
+/// A Node version requested by a `.nvmrc` or `.node-version` file.
+#[derive(Debug, PartialEq)]
+pub enum NodeVersionRequest {
+    /// A version or range, resolved like the versions given to `notion pin`.
+    Spec(VersionSpec),
+    /// The newest LTS release of Node (`lts/*`).
+    Lts,
+}
+
+impl NodeVersionRequest {
+    /// Parses the contents of a `.nvmrc` or `.node-version` file, the way nvm and nodenv
+    /// read them: the first line, with or without a leading `v`.
+    fn parse(src: &str) -> Option<Self> {
+        let line = src.lines().map(str::trim).find(|line| !line.is_empty())?;
+        match line {
+            "node" | "stable" | "latest" => Some(NodeVersionRequest::Spec(VersionSpec::Latest)),
+            "lts/*" => Some(NodeVersionRequest::Lts),
+            _ => line
+                .trim_start_matches('v')
+                .parse()
+                .ok()
+                .map(NodeVersionRequest::Spec),
+        }
+    }
+}
+
 /// A lazily loaded Project
 pub struct LazyProject {
     project: LazyCell<Option<Rc<Project>>>,
@@ -117,6 +244,9 @@ pub struct Project {
     manifest: Manifest,
     project_root: PathBuf,
     dependent_bins: LazyDependentBins,
+    version_file_platform: LazyCell<Option<Rc<PlatformSpec>>>,
+    engines_platform: LazyCell<Option<Rc<PlatformSpec>>>,
+    tagged_platform: LazyCell<Option<Rc<PlatformSpec>>>,
 }
 
 impl Project {
@@ -127,8 +257,9 @@ impl Project {
         Self::for_dir(&current_dir)
     }
 
-    /// Returns the Node project for the input directory, if any.
-    fn for_dir(base_dir: &Path) -> Fallible<Option<Rc<Project>>> {
+    /// Returns the Node project for the input directory, if any. A package of a workspace
+    /// that pins no toolchain takes the toolchain of the root of the workspace.
+    pub(crate) fn for_dir(base_dir: &Path) -> Fallible<Option<Rc<Project>>> {
         let mut dir = base_dir.clone();
         while !is_project_root(dir) {
             dir = match dir.parent() {
@@ -139,13 +270,110 @@ impl Project {
             }
         }
 
+        let mut manifest = Manifest::for_dir(&dir)?;
+        let mut member = dir.to_path_buf();
+        while !manifest.has_toolchain() {
+            match workspace_root(&member) {
+                Some((root, root_manifest)) => {
+                    manifest.inherit(root_manifest);
+                    member = root;
+                }
+                None => break,
+            }
+        }
+
         Ok(Some(Rc::new(Project {
-            manifest: Manifest::for_dir(&dir)?,
+            manifest,
             project_root: PathBuf::from(dir),
             dependent_bins: LazyDependentBins::new(),
+            version_file_platform: LazyCell::new(),
+            engines_platform: LazyCell::new(),
+            tagged_platform: LazyCell::new(),
         })))
     }
 
+    /// Returns the Node version requested by the `.nvmrc` or `.node-version` file of this
+    /// project, if it has one.
+    pub fn node_version_file(&self) -> Fallible<Option<NodeVersionRequest>> {
+        for name in NODE_VERSION_FILES {
+            let file = self.project_root.join(name);
+            if !file.is_file() {
+                continue;
+            }
+            let contents = fs::read_to_string(&file).unknown()?;
+            return match NodeVersionRequest::parse(&contents) {
+                Some(request) => Ok(Some(request)),
+                None => throw!(NodeVersionFileError {
+                    file: file.display().to_string(),
+                    contents: contents.trim().to_string(),
+                }),
+            };
+        }
+        Ok(None)
+    }
+
+    /// Returns the platform requested by the `.nvmrc` or `.node-version` file of this
+    /// project, if it has one, with its Node version resolved by `resolve`. The platform
+    /// is only resolved once.
+    pub(crate) fn version_file_platform<F>(&self, resolve: F) -> Fallible<Option<Rc<PlatformSpec>>>
+    where
+        F: FnOnce(&NodeVersionRequest) -> Fallible<Version>,
+    {
+        let platform = self.version_file_platform.try_borrow_with(|| {
+            Ok(match self.node_version_file()? {
+                Some(request) => Some(Rc::new(PlatformSpec {
+                    node_runtime: resolve(&request)?,
+                    npm: None,
+                    yarn: None,
+                    deno: None,
+                })),
+                None => None,
+            })
+        })?;
+        Ok(platform.clone())
+    }
+
+    /// Returns the platform satisfying the `engines.node` range of the manifest, if it has
+    /// one, with the Node version resolved by `resolve`. The platform is only resolved once.
+    pub(crate) fn engines_platform<F>(&self, resolve: F) -> Fallible<Option<Rc<PlatformSpec>>>
+    where
+        F: FnOnce(&str) -> Fallible<Version>,
+    {
+        let platform = self.engines_platform.try_borrow_with(|| {
+            Ok(match self.manifest.engines_node {
+                Some(ref range) => Some(Rc::new(PlatformSpec {
+                    node_runtime: resolve(range)?,
+                    npm: None,
+                    yarn: None,
+                    deno: None,
+                })),
+                None => None,
+            })
+        })?;
+        Ok(platform.clone())
+    }
+
+    /// Returns the platform of the toolchain of the manifest whose Node version is a named
+    /// version, if it has one, with the Node version resolved by `resolve`. The platform is
+    /// only resolved once.
+    pub(crate) fn tagged_platform<F>(&self, resolve: F) -> Fallible<Option<Rc<PlatformSpec>>>
+    where
+        F: FnOnce(&str) -> Fallible<Version>,
+    {
+        let platform = self.tagged_platform.try_borrow_with(|| {
+            Ok(match self.manifest.tagged_platform {
+                Some(ref tagged) => Some(Rc::new(PlatformSpec {
+                    node_runtime: resolve(&tagged.node)?,
+                    npm: tagged.npm.clone(),
+                    yarn: tagged.yarn.clone(),
+                    deno: tagged.deno.clone(),
+                })),
+                None => None,
+            })
+        })?;
+        Ok(platform.clone())
+    }
+
     /// Returns the pinned platform image, if any.
     pub fn platform(&self) -> Option<Rc<PlatformSpec>> {
         self.manifest.platform()
@@ -153,7 +381,7 @@ impl Project {
 
     /// Returns true if the project manifest contains a toolchain.
     pub fn is_pinned(&self) -> bool {
-        self.manifest.platform().is_some()
+        self.manifest.platform().is_some() || self.manifest.tagged_platform.is_some()
     }
 
     /// Returns the project manifest (`package.json`) for this project.
@@ -161,6 +389,11 @@ impl Project {
         &self.manifest
     }
 
+    /// Returns the root directory of this project.
+    pub fn root(&self) -> &Path {
+        &self.project_root
+    }
+
     /// Returns the path to the `package.json` file for this project.
     pub fn package_file(&self) -> PathBuf {
         self.project_root.join("package.json")
@@ -184,25 +417,63 @@ impl Project {
     }
 
     /// Automatically shim the binaries of all direct dependencies of this project and
-    /// return a vector of any errors which occurred while doing so.
+    /// return a vector of any errors which occurred while doing so. Only the shims that
+    /// changed since the last run are created or deleted.
     pub fn autoshim(&self) -> Vec<NotionError> {
         let dependent_binaries = self.dependent_binary_names_fault_tolerant();
         let mut errors = Vec::new();
+        let mut names = BTreeSet::new();
 
         for result in dependent_binaries {
             match result {
                 Ok(name) => {
-                    if let Err(error) = shim::create(&name) {
-                        errors.push(error);
-                    }
+                    names.insert(name);
                 }
                 Err(error) => errors.push(error),
             }
         }
 
+        // if some dependencies couldn't be read, their shims can't be told apart from
+        // stale ones, so nothing is deleted or recorded
+        let complete = errors.is_empty();
+        if let Err(error) = self.update_shims(names, complete, &mut errors) {
+            errors.push(error);
+        }
+
         errors
     }
 
+    fn update_shims(
+        &self,
+        mut names: BTreeSet<String>,
+        complete: bool,
+        errors: &mut Vec<NotionError>,
+    ) -> Fallible<()> {
+        let mut manifest = ShimManifest::current()?;
+        let delta = manifest.delta(&self.project_root, &names)?;
+        if delta.is_empty() {
+            return Ok(());
+        }
+
+        let created = shim::create_all(&delta.create)?;
+        for (name, result) in delta.create.iter().zip(created) {
+            if let Err(error) = result {
+                names.remove(name);
+                errors.push(error);
+            }
+        }
+
+        if complete {
+            for name in delta.delete {
+                shim::delete(&name)?;
+            }
+            manifest.record(&self.project_root, names);
+            manifest.save()?;
+        }
+
+        Ok(())
+    }
+
     /// Returns a mapping of the names to paths for all the binaries installed
     /// by direct dependencies of the current project.
     fn dependent_binaries(&self) -> Fallible<HashMap<String, String>> {
@@ -260,8 +531,15 @@ impl Project {
         path
     }
 
-    /// Writes the specified version of Node or Yarn to the `toolchain` in package.json.
-    pub fn pin(&self, distro_version: &DistroVersion) -> Fallible<()> {
+    /// Writes the specified version of Node, Yarn, npm or Deno to the `toolchain` in package.json.
+    /// With `engines`, the Node version is also written to `engines.node`, and with `dry_run`,
+    /// the change is only previewed.
+    pub fn pin(
+        &self,
+        distro_version: &DistroVersion,
+        engines: bool,
+        dry_run: bool,
+    ) -> Fallible<()> {
         match distro_version {
             DistroVersion::Node(runtime, npm) => {
                 // prevent writing the npm version if it is equal to the default version
@@ -275,27 +553,57 @@ impl Project {
                 let toolchain = serial::ToolchainSpec::new(
                     runtime.to_string(),
                     npm_str,
-                    self.manifest().yarn_str().clone(),
+                    self.manifest().yarn_str(),
+                    self.manifest().deno_str(),
                 );
-                Manifest::update_toolchain(toolchain, self.package_file())?;
+                Manifest::update_toolchain(toolchain, self.package_file(), engines, dry_run)?;
             }
             DistroVersion::Yarn(version) => {
-                if let Some(platform) = self.manifest().platform() {
+                if let Some((node, npm)) = self.manifest().node_and_npm_strs() {
+                    let toolchain = serial::ToolchainSpec::new(
+                        node,
+                        npm,
+                        Some(version.to_string()),
+                        self.manifest().deno_str(),
+                    );
+                    Manifest::update_toolchain(toolchain, self.package_file(), engines, dry_run)?;
+                } else {
+                    throw!(NoPinnedNodeVersion::new());
+                }
+            }
+            DistroVersion::Deno(version) => {
+                if let Some((node, npm)) = self.manifest().node_and_npm_strs() {
+                    let toolchain = serial::ToolchainSpec::new(
+                        node,
+                        npm,
+                        self.manifest().yarn_str(),
+                        Some(version.to_string()),
+                    );
+                    Manifest::update_toolchain(toolchain, self.package_file(), engines, dry_run)?;
+                } else {
+                    throw!(NoPinnedNodeVersion::new());
+                }
+            }
+            DistroVersion::Npm(version) => {
+                if let Some((node, _)) = self.manifest().node_and_npm_strs() {
                     let toolchain = serial::ToolchainSpec::new(
-                        platform.node_runtime.to_string(),
-                        platform.npm.as_ref().map(|npm| npm.to_string()),
+                        node,
                         Some(version.to_string()),
+                        self.manifest().yarn_str(),
+                        self.manifest().deno_str(),
                     );
-                    Manifest::update_toolchain(toolchain, self.package_file())?;
+                    Manifest::update_toolchain(toolchain, self.package_file(), engines, dry_run)?;
                 } else {
                     throw!(NoPinnedNodeVersion::new());
                 }
             }
-            // ISSUE (#175) When we can `notion install npm` then it can be pinned in the toolchain
-            DistroVersion::Npm(_) => unimplemented!("cannot pin npm in \"toolchain\""),
             DistroVersion::Package(_, _) => throw!(CannotPinPackageError::new()),
         }
-        println!("Pinned {} in package.json", distro_version);
+        if dry_run {
+            println!("Would pin {} in package.json (dry run)", distro_version);
+        } else {
+            println!("Pinned {} in package.json", distro_version);
+        }
         Ok(())
     }
 }
@@ -308,7 +616,10 @@ pub mod tests {
     use std::ffi::OsStr;
     use std::path::PathBuf;
 
-    use crate::project::Project;
+    use crate::project::{is_workspace_member, NodeVersionRequest, Project};
+    use crate::version::VersionSpec;
+    use semver::Version;
+    use std::path::Path;
 
     fn fixture_path(fixture_dir: &str) -> PathBuf {
         let mut cargo_manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -393,4 +704,94 @@ pub mod tests {
 
         assert!(test_project.get_dependency_path(&"foo".to_string()) == expected_path);
     }
+
+    #[test]
+    fn parses_node_version_requests() {
+        assert_eq!(
+            NodeVersionRequest::parse("v10.13.0\n"),
+            Some(NodeVersionRequest::Spec(
+                VersionSpec::parse("10.13.0").unwrap()
+            ))
+        );
+        assert_eq!(
+            NodeVersionRequest::parse("\n8\n"),
+            Some(NodeVersionRequest::Spec(VersionSpec::parse("8").unwrap()))
+        );
+        assert_eq!(
+            NodeVersionRequest::parse("node"),
+            Some(NodeVersionRequest::Spec(VersionSpec::Latest))
+        );
+        assert_eq!(
+            NodeVersionRequest::parse("lts/*"),
+            Some(NodeVersionRequest::Lts)
+        );
+        assert_eq!(NodeVersionRequest::parse("lts/dubnium"), None);
+        assert_eq!(NodeVersionRequest::parse(""), None);
+    }
+
+    #[test]
+    fn reads_node_version_file() {
+        let test_project = Project::for_dir(&fixture_path("nvmrc")).unwrap().unwrap();
+        assert_eq!(
+            test_project.node_version_file().unwrap(),
+            Some(NodeVersionRequest::Spec(
+                VersionSpec::parse("10.13.0").unwrap()
+            ))
+        );
+
+        let basic = Project::for_dir(&fixture_path("basic")).unwrap().unwrap();
+        assert_eq!(basic.node_version_file().unwrap(), None);
+    }
+
+    #[test]
+    fn matches_workspace_members() {
+        let patterns = vec![
+            "packages/*".to_string(),
+            "tools/**".to_string(),
+            "!packages/excluded".to_string(),
+        ];
+        assert!(is_workspace_member(&patterns, Path::new("packages/foo")));
+        assert!(is_workspace_member(
+            &patterns,
+            Path::new("tools/lint/config")
+        ));
+        assert!(!is_workspace_member(
+            &patterns,
+            Path::new("packages/excluded")
+        ));
+        assert!(!is_workspace_member(
+            &patterns,
+            Path::new("packages/foo/bar")
+        ));
+        assert!(!is_workspace_member(&patterns, Path::new("apps/web")));
+        assert!(is_workspace_member(
+            &vec!["./pkg-*".to_string()],
+            Path::new("pkg-a")
+        ));
+    }
+
+    #[test]
+    fn uses_workspace_toolchain() {
+        let member = fixture_path("workspace/packages/inherits");
+        let project = Project::for_dir(&member).unwrap().unwrap();
+        assert_eq!(project.root(), member.as_path());
+        let platform = project.platform().expect("Did not inherit the toolchain");
+        assert_eq!(platform.node_runtime, Version::parse("10.15.0").unwrap());
+        assert_eq!(platform.yarn, Some(Version::parse("1.12.3").unwrap()));
+
+        let outside = Project::for_dir(&fixture_path("workspace/tools/outside"))
+            .unwrap()
+            .unwrap();
+        assert!(outside.platform().is_none());
+    }
+
+    #[test]
+    fn extends_toolchain() {
+        let project = Project::for_dir(&fixture_path("workspace/packages/extends"))
+            .unwrap()
+            .unwrap();
+        let platform = project.platform().expect("Did not extend the toolchain");
+        assert_eq!(platform.node_runtime, Version::parse("10.15.0").unwrap());
+        assert_eq!(platform.yarn, Some(Version::parse("1.22.0").unwrap()));
+    }
 }
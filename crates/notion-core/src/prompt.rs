@@ -0,0 +1,217 @@
+//! Provides prompts for asking the user questions on the command line.
+//!
+//! Prompts never block in automation: when Notion is run non-interactively (with
+//! `NOTION_NONINTERACTIVE=1`, with `notion --no-input`, or without a terminal), every
+//! prompt immediately takes its default answer, or fails if it doesn't have one.
+
+use std::io::{self, BufRead, Write};
+
+use console::user_attended;
+use failure::Fail;
+
+use crate::env::is_noninteractive;
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+/// Thrown when a prompt without a default answer is reached in non-interactive mode.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Could not ask \"{}\" because Notion is running non-interactively\n\nPass the answer on the command line instead.",
+    question
+)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct NoInputError {
+    question: String,
+}
+
+/// Determines whether Notion may prompt the user for input.
+pub fn is_interactive() -> bool {
+    !is_noninteractive() && user_attended()
+}
+
+/// Asks the user a yes-or-no question, returning `default` if they just press enter or
+/// if Notion is running non-interactively.
+pub fn confirm(question: &str, default: Option<bool>) -> Fallible<bool> {
+    if !is_interactive() {
+        match default {
+            Some(answer) => return Ok(answer),
+            None => throw!(NoInputError {
+                question: question.to_string(),
+            }),
+        }
+    }
+
+    let hint = match default {
+        Some(true) => "[Y/n]",
+        Some(false) => "[y/N]",
+        None => "[y/n]",
+    };
+
+    let stdin = io::stdin();
+    loop {
+        eprint!("{} {} ", question, hint);
+        io::stderr().flush().unknown()?;
+
+        let mut line = String::new();
+        // treat a closed stdin like an empty answer, so we can't loop forever
+        if stdin.lock().read_line(&mut line).unknown()? == 0 {
+            eprintln!();
+            return match default {
+                Some(answer) => Ok(answer),
+                None => throw!(NoInputError {
+                    question: question.to_string(),
+                }),
+            };
+        }
+
+        if let Some(answer) = parse_answer(&line, default) {
+            return Ok(answer);
+        }
+    }
+}
+
+/// The most choices that `select` lists at once; the rest are reached by filtering.
+const SELECT_PAGE: usize = 15;
+
+/// What the user answered to `select`.
+#[derive(Debug, PartialEq)]
+enum Selection {
+    /// The index of a chosen item in the full list.
+    Pick(usize),
+    /// The default answer, by just pressing enter.
+    Default,
+    /// Text to narrow the listed choices down to those containing it.
+    Filter(String),
+}
+
+/// Asks the user to pick one of `choices`, returning its index. The first choices are
+/// listed with a number to answer with, and any other answer lists the choices that
+/// contain it instead. Returns `default` if the user just presses enter or if Notion is
+/// running non-interactively.
+pub fn select(question: &str, choices: &[String], default: Option<usize>) -> Fallible<usize> {
+    if !is_interactive() {
+        match default {
+            Some(index) => return Ok(index),
+            None => throw!(NoInputError {
+                question: question.to_string(),
+            }),
+        }
+    }
+
+    let hint = match default {
+        Some(index) => format!("[number, text to filter, or enter for {}]", choices[index]),
+        None => "[number or text to filter]".to_string(),
+    };
+
+    let stdin = io::stdin();
+    let mut filter = String::new();
+    loop {
+        let shown = filter_choices(choices, &filter);
+        if shown.is_empty() {
+            eprintln!("Nothing matches `{}`", filter);
+        }
+        for (number, &index) in shown.iter().take(SELECT_PAGE).enumerate() {
+            eprintln!("  {:>2}) {}", number + 1, choices[index]);
+        }
+        if shown.len() > SELECT_PAGE {
+            eprintln!("      \u{2026} and {} more", shown.len() - SELECT_PAGE);
+        }
+        eprint!("{} {} ", question, hint);
+        io::stderr().flush().unknown()?;
+
+        let mut line = String::new();
+        // treat a closed stdin like an empty answer, so we can't loop forever
+        let closed = stdin.lock().read_line(&mut line).unknown()? == 0;
+        if closed {
+            eprintln!();
+        }
+        let shown: Vec<usize> = shown.into_iter().take(SELECT_PAGE).collect();
+        match parse_selection(&line, &shown) {
+            Selection::Pick(index) => return Ok(index),
+            Selection::Default => match default {
+                Some(index) => return Ok(index),
+                None if closed => throw!(NoInputError {
+                    question: question.to_string(),
+                }),
+                None => {}
+            },
+            Selection::Filter(text) => filter = text,
+        }
+    }
+}
+
+/// The indices of the choices that contain `filter`, ignoring case.
+fn filter_choices(choices: &[String], filter: &str) -> Vec<usize> {
+    let filter = filter.to_lowercase();
+    choices
+        .iter()
+        .enumerate()
+        .filter(|(_, choice)| choice.to_lowercase().contains(&filter))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Reads an answer to `select`, where `shown` holds the indices of the listed choices
+/// in the order they were numbered.
+fn parse_selection(line: &str, shown: &[usize]) -> Selection {
+    let line = line.trim();
+    if line.is_empty() {
+        return Selection::Default;
+    }
+    match line.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= shown.len() => Selection::Pick(shown[number - 1]),
+        _ => Selection::Filter(line.to_string()),
+    }
+}
+
+fn parse_answer(line: &str, default: Option<bool>) -> Option<bool> {
+    match line.trim().to_lowercase().as_str() {
+        "" => default,
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{filter_choices, parse_answer, parse_selection, Selection};
+
+    #[test]
+    fn test_parse_answer() {
+        assert_eq!(parse_answer("y\n", None), Some(true));
+        assert_eq!(parse_answer(" No \n", Some(true)), Some(false));
+        assert_eq!(parse_answer("\n", Some(true)), Some(true));
+        assert_eq!(parse_answer("\n", None), None);
+        assert_eq!(parse_answer("maybe\n", Some(false)), None);
+    }
+
+    #[test]
+    fn test_filter_choices() {
+        let choices = vec![
+            "v14.17.0 (lts)".to_string(),
+            "v15.14.0".to_string(),
+            "v14.16.1 (LTS, installed)".to_string(),
+        ];
+        assert_eq!(filter_choices(&choices, ""), vec![0, 1, 2]);
+        assert_eq!(filter_choices(&choices, "v14"), vec![0, 2]);
+        assert_eq!(filter_choices(&choices, "lts"), vec![0, 2]);
+        assert_eq!(filter_choices(&choices, "v16"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        let shown = vec![0, 2];
+        assert_eq!(parse_selection("2\n", &shown), Selection::Pick(2));
+        assert_eq!(parse_selection("\n", &shown), Selection::Default);
+        assert_eq!(
+            parse_selection("3\n", &shown),
+            Selection::Filter("3".to_string())
+        );
+        assert_eq!(
+            parse_selection(" v14 \n", &shown),
+            Selection::Filter("v14".to_string())
+        );
+    }
+}
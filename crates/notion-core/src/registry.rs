@@ -0,0 +1,391 @@
+//! Provides the npm registry settings that tool packages are resolved and fetched with.
+//!
+//! The settings are read the way npm itself reads them, so that packages published only
+//! to a private registry can be installed as tools with the configuration the project
+//! already has: the `.npmrc` of the project takes precedence over the user's `~/.npmrc`
+//! (or the file named by `NPM_CONFIG_USERCONFIG`), and `NPM_CONFIG_REGISTRY` over both.
+//! Scoped packages use their scope's registry (`@corp:registry=...`) if there is one, and
+//! requests to a registry carry its `_authToken`, if one is configured for it.
+//!
+//! Tarballs downloaded from a registry are verified against the integrity published in
+//! the `dist` metadata of their version.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use reqwest::header::{Authorization, Bearer};
+use serde_json::Value;
+
+use crate::checksum::{Algorithm, Checksum};
+use crate::credentials;
+use crate::inventory::RegistryFetchError;
+use crate::network;
+use notion_fail::{Fallible, ResultExt};
+
+/// The public npm registry, used when no registry is configured.
+pub const PUBLIC_REGISTRY: &'static str = "https://registry.npmjs.org/";
+
+/// The npm settings that apply to a project.
+#[derive(Debug, Default, PartialEq)]
+pub struct Npmrc {
+    settings: HashMap<String, String>,
+}
+
+/// The registry that a package is resolved against.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Registry {
+    /// The base URL of the registry, ending with a `/`.
+    pub url: String,
+    /// The token that requests to the registry are authenticated with, if any.
+    pub token: Option<String>,
+}
+
+/// A tarball published on a registry, as identified by its URL,
+/// `<registry>/<package>/-/<name>-<version>.tgz`.
+#[derive(Debug, PartialEq)]
+struct Tarball {
+    registry: String,
+    package: String,
+    version: String,
+}
+
+impl Tarball {
+    fn parse(url: &str) -> Option<Self> {
+        let index = url.rfind("/-/")?;
+        let (base, file) = (&url[..index], &url[index + 3..]);
+        if !file.ends_with(".tgz") || file.contains('/') {
+            return None;
+        }
+
+        // the package is the last path segment, or the last two for a scoped package
+        let mut segments = base.rsplitn(3, '/');
+        let name = segments.next()?;
+        let package = match segments.next() {
+            Some(scope) if scope.starts_with('@') => format!("{}/{}", scope, name),
+            _ => name.to_string(),
+        };
+        let registry = &url[..index - package.len()];
+
+        let prefix = format!("{}-", name);
+        if !file.starts_with(&prefix) || !registry.contains("//") {
+            return None;
+        }
+        let version = &file[prefix.len()..file.len() - ".tgz".len()];
+        Some(Tarball {
+            registry: registry.to_string(),
+            package,
+            version: version.to_string(),
+        })
+    }
+}
+
+impl Npmrc {
+    /// Loads the settings of the project at `project_root` (if any) and the user.
+    pub fn load(project_root: Option<&Path>) -> Fallible<Self> {
+        let mut npmrc = Npmrc::default();
+        if let Some(file) = user_npmrc_file() {
+            npmrc.merge(&fs::read_to_string(file).unwrap_or_default());
+        }
+        if let Some(root) = project_root {
+            npmrc.merge(&fs::read_to_string(root.join(".npmrc")).unwrap_or_default());
+        }
+        if let Some(registry) = env_setting("registry") {
+            npmrc.settings.insert("registry".to_string(), registry);
+        }
+        Ok(npmrc)
+    }
+
+    /// Resolves unscoped packages against `url` instead of the configured registry,
+    /// unless `NPM_CONFIG_REGISTRY` is set.
+    pub fn override_registry(&mut self, url: &str) {
+        if env_setting("registry").is_none() {
+            self.settings
+                .insert("registry".to_string(), url.to_string());
+        }
+    }
+
+    /// Adds the settings in the contents of an `.npmrc` file, overriding earlier ones.
+    fn merge(&mut self, src: &str) {
+        for line in src.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                let value = value.trim().trim_matches('"');
+                self.settings
+                    .insert(key.trim().to_string(), expand_env_vars(value));
+            }
+        }
+    }
+
+    /// Determines the registry that `package` is resolved against.
+    pub fn registry_for(&self, package: &str) -> Registry {
+        let scoped =
+            scope_of(package).and_then(|scope| self.settings.get(&format!("{}:registry", scope)));
+        let url = scoped
+            .or_else(|| self.settings.get("registry"))
+            .map(|url| with_trailing_slash(url))
+            .unwrap_or_else(|| PUBLIC_REGISTRY.to_string());
+        let token = self.token_for(&url);
+        Registry { url, token }
+    }
+
+    /// Finds the `_authToken` configured for `url`, or for the closest of its parent paths.
+    fn token_for(&self, url: &str) -> Option<String> {
+        let mut prefix = match url.find("//") {
+            Some(index) => url[index..].to_string(),
+            None => return None,
+        };
+        loop {
+            if let Some(token) = self.settings.get(&format!("{}:_authToken", prefix)) {
+                return Some(token.clone());
+            }
+            // drop the last path segment, down to `//host/`
+            let trimmed = prefix.trim_end_matches('/');
+            match trimmed.rfind('/') {
+                Some(index) if index > 1 => prefix = trimmed[..=index].to_string(),
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl Registry {
+    /// The URL of the metadata document (the "packument") of `package`.
+    pub fn metadata_url(&self, package: &str) -> String {
+        format!("{}{}", self.url, package.replace('/', "%2f"))
+    }
+
+    /// The URL of the metadata of one version of `package`.
+    fn version_url(&self, package: &str, version: &str) -> String {
+        format!("{}/{}", self.metadata_url(package), version)
+    }
+
+    /// Builds a GET request for `url` on this registry, authenticated with its token, or
+    /// else with the credentials for its host, if there are any.
+    pub fn get(&self, url: &str) -> Fallible<reqwest::RequestBuilder> {
+        match self.token {
+            Some(ref token) => {
+                let mut request = network::get(url)?;
+                request.header(Authorization(Bearer {
+                    token: token.clone(),
+                }));
+                Ok(request)
+            }
+            None => credentials::get(url),
+        }
+    }
+}
+
+/// Looks up the integrity that the registry publishes for the tarball at `url`, if `url`
+/// is a registry tarball. Versions published without an integrity are left unverified.
+pub(crate) fn tarball_integrity(url: &str) -> Fallible<Option<Checksum>> {
+    let tarball = match Tarball::parse(url) {
+        Some(tarball) => tarball,
+        None => return Ok(None),
+    };
+    let npmrc = Npmrc::load(None)?;
+    let registry = Registry {
+        token: npmrc.token_for(&tarball.registry),
+        url: tarball.registry,
+    };
+
+    let metadata: Value = registry
+        .get(&registry.version_url(&tarball.package, &tarball.version))?
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|mut response| response.json())
+        .with_context(RegistryFetchError::from_error)?;
+    Ok(dist_checksum(&metadata["dist"]))
+}
+
+/// Reads the checksum from the `dist` metadata of a version: its SRI `integrity`, or
+/// else the SHA-1 `shasum` that older versions are published with.
+pub(crate) fn dist_checksum(dist: &Value) -> Option<Checksum> {
+    dist["integrity"]
+        .as_str()
+        .and_then(Checksum::from_integrity)
+        .or_else(|| {
+            dist["shasum"]
+                .as_str()
+                .map(|shasum| Checksum::new(Algorithm::Sha1, shasum))
+        })
+}
+
+fn user_npmrc_file() -> Option<PathBuf> {
+    if let Some(file) = env_setting("userconfig") {
+        return Some(PathBuf::from(file));
+    }
+    Some(dirs::home_dir()?.join(".npmrc"))
+}
+
+/// Reads an npm setting from the environment, where npm accepts it as
+/// `npm_config_<key>` in any case.
+fn env_setting(key: &str) -> Option<String> {
+    env::var(format!("npm_config_{}", key))
+        .or_else(|_| env::var(format!("NPM_CONFIG_{}", key.to_uppercase())))
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+fn scope_of(package: &str) -> Option<&str> {
+    if package.starts_with('@') {
+        package.split('/').next()
+    } else {
+        None
+    }
+}
+
+fn with_trailing_slash(url: &str) -> String {
+    if url.ends_with('/') {
+        url.to_string()
+    } else {
+        format!("{}/", url)
+    }
+}
+
+/// Replaces the `${VAR}` references in an `.npmrc` value with the values of the
+/// environment variables, as npm does. Undefined variables are left as they are.
+fn expand_env_vars(value: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        match rest[start..].find('}') {
+            Some(end) => {
+                let name = &rest[start + 2..start + end];
+                expanded.push_str(&rest[..start]);
+                match env::var(name) {
+                    Ok(var) => expanded.push_str(&var),
+                    Err(_) => expanded.push_str(&rest[start..=start + end]),
+                }
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{dist_checksum, expand_env_vars, Npmrc, Registry, Tarball, PUBLIC_REGISTRY};
+    use crate::checksum::{Algorithm, Checksum};
+    use serde_json::json;
+    use std::env;
+
+    fn npmrc(src: &str) -> Npmrc {
+        let mut npmrc = Npmrc::default();
+        npmrc.merge(src);
+        npmrc
+    }
+
+    #[test]
+    fn test_registry_for() {
+        let npmrc = npmrc(
+            "; the company registry\n\
+             registry=https://npm.example.com/api/npm\n\
+             @corp:registry = https://npm.corp.example.com/\n\
+             //npm.corp.example.com/:_authToken=abc123\n",
+        );
+
+        assert_eq!(
+            npmrc.registry_for("typescript"),
+            Registry {
+                url: "https://npm.example.com/api/npm/".to_string(),
+                token: None,
+            }
+        );
+        assert_eq!(
+            npmrc.registry_for("@corp/cli"),
+            Registry {
+                url: "https://npm.corp.example.com/".to_string(),
+                token: Some("abc123".to_string()),
+            }
+        );
+        assert_eq!(
+            Npmrc::default().registry_for("@other/cli").url,
+            PUBLIC_REGISTRY
+        );
+    }
+
+    #[test]
+    fn test_token_for_parent_path() {
+        let npmrc = npmrc("//npm.example.com/:_authToken=outer\n");
+        assert_eq!(
+            npmrc.token_for("https://npm.example.com/api/npm/"),
+            Some("outer".to_string())
+        );
+        assert_eq!(npmrc.token_for("https://other.example.com/"), None);
+    }
+
+    #[test]
+    fn test_metadata_url() {
+        let registry = Npmrc::default().registry_for("@corp/cli");
+        assert_eq!(
+            registry.metadata_url("@corp/cli"),
+            "https://registry.npmjs.org/@corp%2fcli"
+        );
+    }
+
+    #[test]
+    fn test_tarball_parse() {
+        assert_eq!(
+            Tarball::parse("https://registry.npmjs.org/yarn/-/yarn-1.12.3.tgz"),
+            Some(Tarball {
+                registry: "https://registry.npmjs.org/".to_string(),
+                package: "yarn".to_string(),
+                version: "1.12.3".to_string(),
+            })
+        );
+        assert_eq!(
+            Tarball::parse("https://npm.corp.example.com/api/@corp/cli/-/cli-2.0.0-beta.1.tgz"),
+            Some(Tarball {
+                registry: "https://npm.corp.example.com/api/".to_string(),
+                package: "@corp/cli".to_string(),
+                version: "2.0.0-beta.1".to_string(),
+            })
+        );
+        assert_eq!(
+            Tarball::parse(
+                "https://github.com/yarnpkg/yarn/releases/download/v1.12.3/yarn-v1.12.3.tar.gz"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_dist_checksum() {
+        let sha256 = Checksum::of_bytes(Algorithm::Sha256, b"abc");
+        assert_eq!(
+            dist_checksum(&json!({
+                "integrity": "sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=",
+                "shasum": "a9993e364706816aba3e25717850c26c9cd0d89d",
+            })),
+            Some(sha256)
+        );
+        assert_eq!(
+            dist_checksum(&json!({ "shasum": "A9993E364706816ABA3E25717850C26C9CD0D89D" })),
+            Some(Checksum::new(
+                Algorithm::Sha1,
+                "a9993e364706816aba3e25717850c26c9cd0d89d"
+            ))
+        );
+        assert_eq!(dist_checksum(&json!({})), None);
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        env::set_var("NOTION_TEST_NPM_TOKEN", "s3cret");
+        assert_eq!(expand_env_vars("${NOTION_TEST_NPM_TOKEN}"), "s3cret");
+        assert_eq!(
+            expand_env_vars("a-${NOTION_TEST_UNDEFINED_VAR}-b"),
+            "a-${NOTION_TEST_UNDEFINED_VAR}-b"
+        );
+    }
+}
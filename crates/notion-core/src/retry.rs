@@ -0,0 +1,365 @@
+//! Provides the retrying of Notion's downloads (the indexes and the distribution
+//! archives) after transient network failures, so that a dropped connection or a busy
+//! server doesn't fail a whole install.
+//!
+//! A download is attempted up to `attempts` times, as set in the `[retry]` section of
+//! the configuration. The delay before each retry starts at `backoff_ms` and doubles
+//! every time. Failures to reach the server are retried, as are the HTTP statuses in
+//! `statuses`; any other failure is reported right away. Once every attempt has failed,
+//! the error lists the failure of each of them.
+
+use std::fmt;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use archive::{ConnectionError, HttpError};
+use failure::{self, Fail};
+use reqwest::{self, RequestBuilder, Response, StatusCode};
+
+use crate::config::{Config, RetryConfig};
+use crate::output;
+use crate::style::Progress;
+use notion_fail::Fallible;
+
+/// The number of times a download is attempted, if not configured.
+const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry, if not configured.
+const DEFAULT_BACKOFF_MS: u64 = 500;
+
+/// The HTTP statuses that are retried, if not configured: timeouts, rate limits and
+/// the server errors that are usually temporary.
+const DEFAULT_STATUSES: &[u16] = &[408, 429, 500, 502, 503, 504];
+
+/// How the downloads are retried.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RetryPolicy {
+    attempts: u32,
+    backoff: Duration,
+    statuses: Vec<u16>,
+}
+
+/// Thrown when a download failed every time it was attempted.
+#[derive(Debug, Fail)]
+pub(crate) struct RetryError {
+    /// The failure of each attempt, in order.
+    failures: Vec<String>,
+    /// The failure of the last attempt.
+    last: failure::Error,
+}
+
+impl RetryError {
+    /// The failure of the last attempt, which tells what kind of failure it was.
+    pub(crate) fn last(&self) -> &failure::Error {
+        &self.last
+    }
+}
+
+impl fmt::Display for RetryError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "Gave up after {} attempts:", self.failures.len())?;
+        for (index, failure) in self.failures.iter().enumerate() {
+            write!(fmt, "\n  attempt {}: {}", index + 1, failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// Thrown by `send` when the server responded with a status that is retried.
+#[derive(Debug, Fail)]
+#[fail(display = "{} responded with {}", url, status)]
+struct StatusError {
+    url: String,
+    status: StatusCode,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::from_config(&RetryConfig::default())
+    }
+}
+
+impl RetryPolicy {
+    /// The policy set by the `[retry]` section of the configuration.
+    pub(crate) fn current() -> Fallible<Self> {
+        Ok(RetryPolicy::from_config(&Config::current()?.retry))
+    }
+
+    pub(crate) fn from_config(config: &RetryConfig) -> Self {
+        RetryPolicy {
+            attempts: config.attempts.unwrap_or(DEFAULT_ATTEMPTS),
+            backoff: Duration::from_millis(config.backoff_ms.unwrap_or(DEFAULT_BACKOFF_MS)),
+            statuses: config
+                .statuses
+                .clone()
+                .unwrap_or_else(|| DEFAULT_STATUSES.to_vec()),
+        }
+    }
+
+    /// Runs `attempt` until it succeeds, fails with an error that isn't transient, or
+    /// has been attempted as many times as the policy allows. Before each retry, waits
+    /// for the backoff and calls `on_retry` with the number of the attempt about to be
+    /// made and the number of attempts in all.
+    ///
+    /// An operation that failed on its first attempt fails with its own error; one that
+    /// was retried fails with a `RetryError` listing every failure.
+    pub(crate) fn run<T, F, R>(&self, mut attempt: F, mut on_retry: R) -> Result<T, failure::Error>
+    where
+        F: FnMut() -> Result<T, failure::Error>,
+        R: FnMut(u32, u32),
+    {
+        let mut failures = Vec::new();
+        let mut delay = self.backoff;
+        loop {
+            let error = match attempt() {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            let made = failures.len() as u32 + 1;
+            if made >= self.attempts || !self.is_transient(&error) {
+                if failures.is_empty() {
+                    return Err(error);
+                }
+                failures.push(error.to_string());
+                return Err(RetryError {
+                    failures,
+                    last: error,
+                }
+                .into());
+            }
+
+            failures.push(error.to_string());
+            thread::sleep(delay);
+            delay *= 2;
+            on_retry(made + 1, self.attempts);
+        }
+    }
+
+    /// Whether `error` is a failure that another attempt might not run into.
+    fn is_transient(&self, error: &failure::Error) -> bool {
+        if error.downcast_ref::<ConnectionError>().is_some() {
+            true
+        } else if let Some(HttpError { code }) = error.downcast_ref::<HttpError>() {
+            self.retries_status(*code)
+        } else if let Some(StatusError { status, .. }) = error.downcast_ref::<StatusError>() {
+            self.retries_status(*status)
+        } else if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            match error.status() {
+                Some(status) => self.retries_status(status),
+                // no response was received
+                None => error.is_http(),
+            }
+        } else if let Some(error) = error.downcast_ref::<io::Error>() {
+            match error.kind() {
+                io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::Interrupted => true,
+                _ => false,
+            }
+        } else {
+            false
+        }
+    }
+
+    fn retries_status(&self, status: StatusCode) -> bool {
+        self.statuses.contains(&status.as_u16())
+    }
+
+    /// Sends the request built by `request` to `url`, retrying as the policy allows, and
+    /// reporting the retries on `progress` if there is one. A request can only be sent
+    /// once, so `request` is called again to build each attempt. A response with any
+    /// status that isn't retried is returned for the caller to handle.
+    pub(crate) fn send<B>(
+        &self,
+        request: B,
+        url: &str,
+        progress: Option<&Progress>,
+    ) -> Result<Response, failure::Error>
+    where
+        B: Fn() -> Fallible<RequestBuilder>,
+    {
+        self.run(
+            || {
+                let response = request()?.send()?;
+                if self.retries_status(response.status()) {
+                    Err(StatusError {
+                        url: url.to_string(),
+                        status: response.status(),
+                    })?;
+                }
+                Ok(response)
+            },
+            |retry, attempts| match progress {
+                Some(progress) => progress.retrying(retry, attempts),
+                None => output::verbose(format!(
+                    "Fetching {} again (retry {}/{})",
+                    url, retry, attempts
+                )),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use archive::{ConnectionError, HttpError};
+    use failure;
+    use reqwest::StatusCode;
+
+    use super::{RetryError, RetryPolicy};
+    use crate::config::RetryConfig;
+
+    fn policy(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            backoff: Duration::from_millis(0),
+            statuses: vec![503],
+        }
+    }
+
+    fn unreachable() -> failure::Error {
+        ConnectionError {
+            error: "connection refused".to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(
+            RetryPolicy::default(),
+            RetryPolicy {
+                attempts: 3,
+                backoff: Duration::from_millis(500),
+                statuses: vec![408, 429, 500, 502, 503, 504],
+            }
+        );
+        assert_eq!(
+            RetryPolicy::from_config(&RetryConfig {
+                attempts: Some(5),
+                backoff_ms: Some(10),
+                statuses: Some(vec![503]),
+            }),
+            RetryPolicy {
+                attempts: 5,
+                backoff: Duration::from_millis(10),
+                statuses: vec![503],
+            }
+        );
+    }
+
+    #[test]
+    fn test_run_retries_transient_failures() {
+        let made = Cell::new(0);
+        let mut retries = Vec::new();
+        let result = policy(3).run(
+            || {
+                made.set(made.get() + 1);
+                if made.get() < 3 {
+                    Err(unreachable())
+                } else {
+                    Ok(made.get())
+                }
+            },
+            |retry, attempts| retries.push((retry, attempts)),
+        );
+        assert_eq!(result.expect("should succeed on the last attempt"), 3);
+        assert_eq!(retries, vec![(2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn test_run_lists_every_failure() {
+        let error = policy(2)
+            .run::<(), _, _>(|| Err(unreachable()), |_, _| {})
+            .unwrap_err();
+        let retry = error
+            .downcast_ref::<RetryError>()
+            .expect("should be a retry error");
+        assert_eq!(
+            retry.to_string(),
+            "Gave up after 2 attempts:\n  attempt 1: connection refused\n  attempt 2: connection refused"
+        );
+        assert!(retry.last().downcast_ref::<ConnectionError>().is_some());
+    }
+
+    #[test]
+    fn test_run_fails_right_away_on_other_failures() {
+        let made = Cell::new(0);
+        let error = policy(3)
+            .run::<(), _, _>(
+                || {
+                    made.set(made.get() + 1);
+                    Err(HttpError {
+                        code: StatusCode::NotFound,
+                    }
+                    .into())
+                },
+                |_, _| {},
+            )
+            .unwrap_err();
+        assert_eq!(made.get(), 1);
+        assert!(error.downcast_ref::<HttpError>().is_some());
+    }
+
+    #[test]
+    fn test_run_retries_configured_statuses() {
+        let made = Cell::new(0);
+        let _ = policy(3).run::<(), _, _>(
+            || {
+                made.set(made.get() + 1);
+                Err(HttpError {
+                    code: StatusCode::ServiceUnavailable,
+                }
+                .into())
+            },
+            |_, _| {},
+        );
+        assert_eq!(made.get(), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "mock-network")]
+    fn test_send_builds_each_attempt() {
+        use mockito::{mock, SERVER_URL};
+        use reqwest::header::Headers;
+        use reqwest::Client;
+
+        let _unavailable = mock("GET", "/retried")
+            .match_header("x-attempt", "1")
+            .with_status(503)
+            .create();
+        let _available = mock("GET", "/retried")
+            .match_header("x-attempt", "2")
+            .with_status(200)
+            .with_body("available")
+            .create();
+
+        let url = format!("{}/retried", SERVER_URL);
+        let built = Cell::new(0);
+        let mut response = policy(3)
+            .send(
+                || {
+                    built.set(built.get() + 1);
+                    let mut headers = Headers::new();
+                    headers.set_raw("x-attempt", built.get().to_string());
+                    let mut request = Client::new().get(&url);
+                    request.headers(headers);
+                    Ok(request)
+                },
+                &url,
+                None,
+            )
+            .expect("should succeed on the second attempt");
+        assert_eq!(built.get(), 2);
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert_eq!(response.text().expect("should read the body"), "available");
+    }
+}
@@ -4,22 +4,48 @@
 
 use std::rc::Rc;
 
+use crate::alias::{self, Aliases};
+use crate::audit::{self, AuditAction};
+use crate::config::{Config, LazyConfig};
+use crate::distro::deno::DenoKind;
+use crate::distro::kind::{ArchiveDistro, ToolKind};
+use crate::distro::local::LocalBuild;
+use crate::distro::node::NodeKind;
+use crate::distro::npm::NpmKind;
+use crate::distro::yarn::YarnKind;
 use crate::distro::{DistroVersion, Fetched};
+use crate::env::{shell_tool_version, DEFAULT_PROFILE};
+use crate::gc;
 use crate::hook::{HookConfig, LazyHookConfig, Publish};
-use crate::inventory::{Inventory, LazyInventory};
-use crate::platform::PlatformSpec;
-use crate::project::{LazyProject, Project};
-use crate::tool::ToolSpec;
+use crate::inventory::{
+    node_lts_versions, refresh_node_index, FetchResolve, Inventory, LazyInventory,
+    NoVersionFoundError,
+};
+use crate::lifecycle;
+use crate::manifest::UnknownProfileError;
+use crate::package;
+use crate::platform::{Image, PlatformSpec};
+use crate::policy::{LazyPolicy, Policy};
+use crate::project::{LazyProject, NodeVersionRequest, Project};
+use crate::registry::{Npmrc, Registry};
+use crate::shell::Postscript;
+use crate::shim::CORE_SHIMS;
+use crate::style::display_warning;
+use crate::tool::{self, ToolSpec};
 use crate::toolchain::LazyToolchain;
 use crate::version::VersionSpec;
 
 use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::time::{Duration, Instant};
 
 use failure::Fail;
+use serde::Serialize;
 
 use crate::event::EventLog;
-use notion_fail::{throw, ExitCode, Fallible, NotionError, NotionFail};
+use notion_fail::{throw, ExitCode, Fallible, NotionError, NotionFail, ResultExt};
 use notion_fail_derive::*;
 use semver::Version;
 
@@ -37,12 +63,37 @@ pub enum ActivityKind {
     Npm,
     Npx,
     Yarn,
+    Deno,
     Notion,
     Tool,
     Help,
     Version,
     Binary,
     Shim,
+    Relocate,
+    Snapshot,
+    Restore,
+    Mirror,
+    Ci,
+    Check,
+    Setup,
+    Shell,
+    Envfile,
+    Env,
+    Use,
+    Exec,
+    List,
+    Cache,
+    Doctor,
+    Completions,
+    Run,
+    Alias,
+    Update,
+    Refresh,
+    SelfUpdate,
+    Which,
+    Export,
+    Outdated,
 }
 
 impl Display for ActivityKind {
@@ -60,17 +111,198 @@ impl Display for ActivityKind {
             &ActivityKind::Npm => "npm",
             &ActivityKind::Npx => "npx",
             &ActivityKind::Yarn => "yarn",
+            &ActivityKind::Deno => "deno",
             &ActivityKind::Notion => "notion",
             &ActivityKind::Tool => "tool",
             &ActivityKind::Help => "help",
             &ActivityKind::Version => "version",
             &ActivityKind::Binary => "binary",
             &ActivityKind::Shim => "shim",
+            &ActivityKind::Relocate => "relocate",
+            &ActivityKind::Snapshot => "snapshot",
+            &ActivityKind::Restore => "restore",
+            &ActivityKind::Mirror => "mirror",
+            &ActivityKind::Ci => "ci",
+            &ActivityKind::Check => "check",
+            &ActivityKind::Setup => "setup",
+            &ActivityKind::Shell => "shell",
+            &ActivityKind::Envfile => "envfile",
+            &ActivityKind::Env => "env",
+            &ActivityKind::Use => "use",
+            &ActivityKind::Exec => "exec",
+            &ActivityKind::List => "list",
+            &ActivityKind::Cache => "cache",
+            &ActivityKind::Doctor => "doctor",
+            &ActivityKind::Completions => "completions",
+            &ActivityKind::Run => "run",
+            &ActivityKind::Alias => "alias",
+            &ActivityKind::Update => "update",
+            &ActivityKind::Refresh => "refresh",
+            &ActivityKind::SelfUpdate => "self-update",
+            &ActivityKind::Which => "which",
+            &ActivityKind::Export => "export",
+            &ActivityKind::Outdated => "outdated",
         };
         f.write_str(s)
     }
 }
 
+/// Thrown when the user tries to override a tool other than Node, npm, Yarn or Deno in a shell.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Cannot override {} in a shell", tool)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct ShellOverrideError {
+    tool: String,
+}
+
+/// Applies the overrides exported by `notion shell` to `platform`. Overriding Node also
+/// drops any pinned npm version, since it may not work with the new Node version, unless
+/// npm is overridden as well.
+fn with_shell_overrides(platform: Option<Rc<PlatformSpec>>) -> Option<Rc<PlatformSpec>> {
+    let node = shell_tool_version("node");
+    let npm = shell_tool_version("npm");
+    let yarn = shell_tool_version("yarn");
+    let deno = shell_tool_version("deno");
+    if node.is_none() && npm.is_none() && yarn.is_none() && deno.is_none() {
+        return platform;
+    }
+
+    let mut spec = match (platform, node) {
+        (Some(platform), node) => {
+            let mut spec = (*platform).clone();
+            if let Some(node) = node {
+                spec.node_runtime = node;
+                spec.npm = None;
+            }
+            spec
+        }
+        (None, Some(node)) => PlatformSpec {
+            node_runtime: node,
+            npm: None,
+            yarn: None,
+            deno: None,
+        },
+        // without a Node version, there is no platform to add npm, Yarn or Deno to
+        (None, None) => return None,
+    };
+    if npm.is_some() {
+        spec.npm = npm;
+    }
+    if yarn.is_some() {
+        spec.yarn = yarn;
+    }
+    if deno.is_some() {
+        spec.deno = deno;
+    }
+    Some(Rc::new(spec))
+}
+
+/// Thrown when a tool other than Node, npm, npx, Yarn or Deno is resolved.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Cannot resolve `{}` (expected `node`, `npm`, `npx`, `yarn` or `deno`)",
+    tool
+)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct UnresolvableToolError {
+    tool: String,
+}
+
+/// Where the version of a tool that runs is selected.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToolSource {
+    /// The `toolchain` of the current project (or of its workspace).
+    Project,
+    /// The `.nvmrc` or `.node-version` file of the current project.
+    VersionFile,
+    /// The `engines.node` range of the current project.
+    Engines,
+    /// The user toolchain.
+    User,
+    /// The versions selected for the current shell session by `notion shell`.
+    Shell,
+    /// The npm bundled with the selected Node version.
+    Bundled,
+}
+
+impl Display for ToolSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ToolSource::Project => "project pin",
+            ToolSource::VersionFile => "project .nvmrc or .node-version file",
+            ToolSource::Engines => "project engines fallback",
+            ToolSource::User => "user default",
+            ToolSource::Shell => "notion shell override",
+            ToolSource::Bundled => "bundled with Node",
+        })
+    }
+}
+
+/// The executable that a tool runs, as a shim would run it.
+#[derive(Serialize, Debug)]
+pub struct ResolvedTool {
+    /// The name of the tool (`node`, `npm`, `npx`, `yarn` or `deno`).
+    pub tool: String,
+    #[serde(serialize_with = "serialize_version")]
+    pub version: Version,
+    pub source: ToolSource,
+    /// The executable that runs, if it is in the image of the tool.
+    pub path: Option<PathBuf>,
+}
+
+impl ResolvedTool {
+    /// Serializes the tool to a JSON object.
+    pub fn to_json(&self) -> Fallible<String> {
+        serde_json::to_string_pretty(self).unknown()
+    }
+}
+
+/// Serializes `tools` to a JSON array.
+pub fn to_json(tools: &[ResolvedTool]) -> Fallible<String> {
+    serde_json::to_string_pretty(tools).unknown()
+}
+
+fn serialize_version<S: serde::Serializer>(
+    version: &Version,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&version.to_string())
+}
+
+#[cfg(unix)]
+const EXECUTABLE_EXTENSIONS: &[&str] = &[""];
+
+#[cfg(windows)]
+const EXECUTABLE_EXTENSIONS: &[&str] = &[".exe", ".cmd", ""];
+
+/// Finds the executable `name` in the first of `dirs` that has it, as `PATH` would.
+fn find_executable(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    dirs.iter()
+        .flat_map(|dir| {
+            EXECUTABLE_EXTENSIONS
+                .iter()
+                .map(move |extension| dir.join(format!("{}{}", name, extension)))
+        })
+        .find(|file| file.is_file())
+}
+
+/// The error the shim of `tool` reports when no version of it is selected: npm and npx
+/// come with Node, so for them it is Node that is missing.
+fn no_tool_error(session: &Session, tool: &str) -> Fallible<NotionError> {
+    match tool {
+        "yarn" => Ok(tool::NoSuchToolError {
+            tool: "Yarn".to_string(),
+        }
+        .into()),
+        "deno" => Ok(tool::NoSuchToolError {
+            tool: "Deno".to_string(),
+        }
+        .into()),
+        _ => tool::no_node_error(session),
+    }
+}
+
 /// Thrown when the user tries to pin Node or Yarn versions outside of a package.
 #[derive(Debug, Fail, NotionFail)]
 #[fail(display = "Not in a node package")]
@@ -89,8 +321,12 @@ impl NotInPackageError {
 ///     - the current directory
 ///     - the Node project tree that contains the current directory (if any)
 ///     - the Notion hook configuration
+///     - the Notion user configuration
+///     - the organization policy, if any
 ///     - the inventory of locally-fetched Notion tools
 pub struct Session {
+    config: LazyConfig,
+    policy: LazyPolicy,
     hooks: LazyHookConfig,
     inventory: LazyInventory,
     toolchain: LazyToolchain,
@@ -102,6 +338,8 @@ impl Session {
     /// Constructs a new `Session`.
     pub fn new() -> Session {
         Session {
+            config: LazyConfig::new(),
+            policy: LazyPolicy::new(),
             hooks: LazyHookConfig::new(),
             inventory: LazyInventory::new(),
             toolchain: LazyToolchain::new(),
@@ -115,10 +353,143 @@ impl Session {
         self.project.get()
     }
 
+    /// Returns the platform of the current project, or else the user toolchain, with the
+    /// versions selected for the current shell session by `notion shell` taking
+    /// precedence over both.
     pub fn current_platform(&self) -> Fallible<Option<Rc<PlatformSpec>>> {
-        match self.project_platform()? {
+        let platform = self
+            .current_platform_source()?
+            .map(|(platform, _)| platform);
+        Ok(with_shell_overrides(platform))
+    }
+
+    /// Returns the platform of the current project, or else the user toolchain, with where
+    /// it was selected, before any `notion shell` overrides.
+    fn current_platform_source(&self) -> Fallible<Option<(Rc<PlatformSpec>, ToolSource)>> {
+        match self.project_platform_source()? {
             Some(platform) => Ok(Some(platform)),
-            None => self.user_platform(),
+            None => Ok(self
+                .user_platform()?
+                .map(|platform| (platform, ToolSource::User))),
+        }
+    }
+
+    /// Warns, at most once a day, if the Node version that runs in the current directory
+    /// is past its end of life or its LTS window.
+    pub fn warn_unsupported_node(&self) -> Fallible<()> {
+        let (selected, source) = match self.current_platform_source()? {
+            Some(selected) => selected,
+            None => return Ok(()),
+        };
+        let root = match source {
+            ToolSource::User => None,
+            _ => self.project()?.map(|project| project.root().to_path_buf()),
+        };
+        let platform = self.current_platform()?.unwrap_or_else(|| selected.clone());
+        let source = if platform.node_runtime == selected.node_runtime {
+            source
+        } else {
+            ToolSource::Shell
+        };
+        lifecycle::warn_once_a_day(
+            &platform.node_runtime,
+            source,
+            root.as_ref().map(|root| root.as_path()),
+        )
+    }
+
+    /// Resolves the executable that the shim of `tool` (`node`, `npm`, `npx`, `yarn` or
+    /// `deno`) runs in the current directory, with its version and where that version is
+    /// selected. Like the shim, this fetches the versions of the platform that are missing.
+    pub fn resolve_tool(&mut self, tool: &str) -> Fallible<ResolvedTool> {
+        Ok(self.checkout_tool(tool)?.1)
+    }
+
+    /// Checks out the image that the shim of `tool` runs in the current directory,
+    /// fetching the versions of the platform that are missing, and resolves the
+    /// executable of `tool` in it. The shims and `resolve_tool` both go through this, so
+    /// that `notion which` reports what the shim would run.
+    pub(crate) fn checkout_tool(&mut self, tool: &str) -> Fallible<(Image, ResolvedTool)> {
+        if !CORE_SHIMS.contains(&tool) {
+            throw!(UnresolvableToolError {
+                tool: tool.to_string(),
+            });
+        }
+
+        let selected = self.current_platform_source()?;
+        let platform_source = selected
+            .as_ref()
+            .map_or(ToolSource::Shell, |&(_, source)| source);
+        let platform = match with_shell_overrides(selected.map(|(platform, _)| platform)) {
+            Some(platform) => platform,
+            None => throw!(no_tool_error(self, tool)?),
+        };
+        // a platform without Yarn or Deno isn't checked out for them
+        match tool {
+            "yarn" if platform.yarn.is_none() => throw!(no_tool_error(self, tool)?),
+            "deno" if platform.deno.is_none() => throw!(no_tool_error(self, tool)?),
+            _ => {}
+        }
+        let source_of = |name: &str| match shell_tool_version(name) {
+            Some(_) => ToolSource::Shell,
+            None => platform_source,
+        };
+
+        let image = platform.checkout(self)?;
+        let (version, source) = match tool {
+            "node" => (image.node.runtime.clone(), source_of("node")),
+            "npm" | "npx" => {
+                let source = match image.npm {
+                    Some(_) => source_of("npm"),
+                    None => ToolSource::Bundled,
+                };
+                (image.npm_version().clone(), source)
+            }
+            "yarn" => match image.yarn {
+                Some(ref yarn) => (yarn.clone(), source_of("yarn")),
+                None => throw!(no_tool_error(self, tool)?),
+            },
+            _ => match image.deno {
+                Some(ref deno) => (deno.clone(), source_of("deno")),
+                None => throw!(no_tool_error(self, tool)?),
+            },
+        };
+
+        let resolved = ResolvedTool {
+            tool: tool.to_string(),
+            version,
+            source,
+            path: find_executable(&image.bins()?, tool),
+        };
+        Ok((image, resolved))
+    }
+
+    /// Resolves each tool of the current platform as `resolve_tool` does: Node, npm and
+    /// npx, then Yarn and Deno if they are selected.
+    pub fn resolve_tools(&mut self) -> Fallible<Vec<ResolvedTool>> {
+        let platform = match self.current_platform()? {
+            Some(platform) => platform,
+            None => throw!(tool::no_node_error(self)?),
+        };
+        let mut tools = vec!["node", "npm", "npx"];
+        if platform.yarn.is_some() {
+            tools.push("yarn");
+        }
+        if platform.deno.is_some() {
+            tools.push("deno");
+        }
+        tools
+            .into_iter()
+            .map(|tool| self.resolve_tool(tool))
+            .collect()
+    }
+
+    /// Returns the bin directories of the current platform, in the order the shims would
+    /// search them, fetching the versions that are missing.
+    pub fn platform_bins(&mut self) -> Fallible<Vec<PathBuf>> {
+        match self.current_platform()? {
+            Some(platform) => platform.checkout(self)?.bins(),
+            None => throw!(tool::no_node_error(self)?),
         }
     }
 
@@ -129,14 +500,106 @@ impl Session {
             .map(|platform| Rc::new(platform.clone())))
     }
 
-    /// Returns the current project's pinned platform image, if any.
+    /// Returns the current project's pinned platform image, if any, resolving a named Node
+    /// version (such as `lts`) of its `toolchain`. A project without a `toolchain` in
+    /// package.json uses the Node version of its `.nvmrc` or `.node-version` file, if it
+    /// has one, or else (with `project.engines` in the configuration) a Node version
+    /// satisfying its `engines.node` range.
     pub fn project_platform(&self) -> Fallible<Option<Rc<PlatformSpec>>> {
+        Ok(self
+            .project_platform_source()?
+            .map(|(platform, _)| platform))
+    }
+
+    /// Returns the current project's platform image, if any, as `project_platform` does,
+    /// with where it was selected.
+    fn project_platform_source(&self) -> Fallible<Option<(Rc<PlatformSpec>, ToolSource)>> {
         if let Some(ref project) = self.project()? {
-            return Ok(project.platform());
+            if let Some(platform) = project.platform() {
+                return Ok(Some((platform, ToolSource::Project)));
+            }
+            if let Some(platform) = project.tagged_platform(|tag| {
+                let hooks = self.hooks()?.node.as_ref();
+                let tag = VersionSpec::Tag(tag.to_string());
+                self.inventory()?.node.resolve_version(&tag, hooks)
+            })? {
+                return Ok(Some((platform, ToolSource::Project)));
+            }
+            if let Some(platform) =
+                project.version_file_platform(|request| self.resolve_node_request(request))?
+            {
+                return Ok(Some((platform, ToolSource::VersionFile)));
+            }
+            if self.config()?.project.engines {
+                return Ok(project
+                    .engines_platform(|range| self.resolve_engines_range(range))?
+                    .map(|platform| (platform, ToolSource::Engines)));
+            }
         }
         Ok(None)
     }
 
+    /// Resolves the `engines.node` range of a manifest, preferring the newest fetched
+    /// version that satisfies it, or else the newest published one.
+    fn resolve_engines_range(&self, range: &str) -> Fallible<Version> {
+        let alternatives = VersionSpec::parse_npm_range(range)?;
+        let inventory = self.inventory()?;
+        let fetched = inventory
+            .node
+            .versions
+            .iter()
+            .rev()
+            .find(|version| alternatives.iter().any(|req| req.matches(version)));
+        if let Some(version) = fetched {
+            return Ok(version.clone());
+        }
+
+        let hooks = self.hooks()?.node.as_ref();
+        let newest = alternatives
+            .into_iter()
+            .filter_map(|req| {
+                inventory
+                    .node
+                    .resolve_version(&VersionSpec::Semver(req), hooks)
+                    .ok()
+            })
+            .max();
+        match newest {
+            Some(version) => Ok(version),
+            None => throw!(NoVersionFoundError {
+                tool: NodeKind::DISPLAY_NAME,
+                matching: range.to_string(),
+            }),
+        }
+    }
+
+    /// Resolves the Node version requested by a `.nvmrc` or `.node-version` file.
+    fn resolve_node_request(&self, request: &NodeVersionRequest) -> Fallible<Version> {
+        let hooks = self.hooks()?.node.as_ref();
+        match request {
+            NodeVersionRequest::Spec(spec) => self.inventory()?.node.resolve_version(spec, hooks),
+            NodeVersionRequest::Lts => match node_lts_versions(hooks)?.into_iter().next() {
+                Some(version) => Ok(version),
+                None => throw!(NoVersionFoundError {
+                    tool: NodeKind::DISPLAY_NAME,
+                    matching: "lts/*".to_string(),
+                }),
+            },
+        }
+    }
+
+    /// Determines the npm registry that the tool package `package` is resolved against,
+    /// according to the `.npmrc` settings of the current project and the user, and the
+    /// `registry` hook, if any.
+    pub fn npm_registry(&self, package: &str) -> Fallible<Registry> {
+        let project = self.project()?;
+        let mut npmrc = Npmrc::load(project.as_ref().map(|project| project.root()))?;
+        if let Some(url) = self.hooks()?.registry.as_ref().and_then(|r| r.url.as_ref()) {
+            npmrc.override_registry(url);
+        }
+        Ok(npmrc.registry_for(package))
+    }
+
     /// Produces a reference to the current inventory.
     pub fn inventory(&self) -> Fallible<&Inventory> {
         self.inventory.get()
@@ -152,58 +615,332 @@ impl Session {
         self.hooks.get()
     }
 
-    /// Ensures that a specific Node version has been fetched and unpacked
-    pub(crate) fn ensure_node(&mut self, version: &Version) -> Fallible<()> {
-        let inventory = self.inventory.get_mut()?;
-
-        if !inventory.node.contains(version) {
-            let hooks = self.hooks.get()?;
-            inventory.fetch(&ToolSpec::Node(VersionSpec::exact(version)), hooks)?;
-        }
+    /// Produces a reference to the user configuration
+    pub fn config(&self) -> Fallible<&Config> {
+        self.config.get()
+    }
 
-        Ok(())
+    /// Produces a mutable reference to the user configuration
+    pub fn config_mut(&mut self) -> Fallible<&mut Config> {
+        self.config.get_mut()
     }
 
-    /// Ensures that a specific Yarn version has been fetched and unpacked
-    pub(crate) fn ensure_yarn(&mut self, version: &Version) -> Fallible<()> {
-        let inventory = self.inventory.get_mut()?;
+    /// Ensures that the tool versions of a platform have been fetched and unpacked,
+    /// downloading any that are missing at the same time.
+    pub(crate) fn ensure_platform(&mut self, platform: &PlatformSpec) -> Fallible<()> {
+        let inventory = self.inventory()?;
 
-        if !inventory.yarn.contains(version) {
-            let hooks = self.hooks.get()?;
-            inventory.fetch(&ToolSpec::Yarn(VersionSpec::exact(version)), hooks)?;
+        let mut missing = Vec::new();
+        if !inventory.node.contains(&platform.node_runtime) {
+            missing.push(ToolSpec::Node(VersionSpec::exact(&platform.node_runtime)));
+        }
+        if let Some(ref yarn) = platform.yarn {
+            if !inventory.yarn.contains(yarn) {
+                missing.push(ToolSpec::Yarn(VersionSpec::exact(yarn)));
+            }
+        }
+        if let Some(ref deno) = platform.deno {
+            if !inventory.deno.contains(deno) {
+                missing.push(ToolSpec::Deno(VersionSpec::exact(deno)));
+            }
         }
 
+        match missing.len() {
+            0 => {}
+            1 => {
+                self.fetch(&missing[0])?;
+            }
+            _ => {
+                self.fetch_all(&missing)?;
+            }
+        }
+
+        // whether npm is the one bundled with Node is only known once Node is fetched
+        if let Some(npm) = platform.custom_npm()? {
+            if !self.inventory()?.npm.contains(&npm) {
+                self.fetch(&ToolSpec::Npm(VersionSpec::exact(&npm)))?;
+            }
+        }
         Ok(())
     }
 
     /// Installs a Tool matching the specified semantic versioning requirements,
-    /// and updates the `toolchain` as necessary.
+    /// and updates the `toolchain` as necessary. Packages are installed in their own
+    /// image, with the current default platform of the toolchain.
     pub fn install(&mut self, toolspec: &ToolSpec) -> Fallible<()> {
+        if let ToolSpec::Package(name, matching) = toolspec {
+            return self.install_package(name, matching, None);
+        }
+
         let distro_version = self.fetch(toolspec)?.into_version();
         let toolchain = self.toolchain.get_mut()?;
-        toolchain.set_active(distro_version)?;
-        Ok(())
+        toolchain.set_active(distro_version.clone())?;
+        audit::record(
+            &self.config()?.audit,
+            AuditAction::Install,
+            &distro_version,
+            None,
+        )?;
+        match toolspec {
+            ToolSpec::Node(matching) => alias::record_installed("node", matching)?,
+            ToolSpec::Yarn(matching) => alias::record_installed("yarn", matching)?,
+            ToolSpec::Deno(matching) => alias::record_installed("deno", matching)?,
+            ToolSpec::Npm(matching) => alias::record_installed("npm", matching)?,
+            _ => {}
+        }
+        gc::record_install(self)
+    }
+
+    /// Installs the package `name` matching `matching` in its own image, with `platform`,
+    /// or else the current default platform of the toolchain.
+    pub fn install_package(
+        &mut self,
+        name: &str,
+        matching: &VersionSpec,
+        platform: Option<&PlatformSpec>,
+    ) -> Fallible<()> {
+        let installed = package::install(self, name, matching, platform)?;
+        audit::record(
+            &self.config()?.audit,
+            AuditAction::Install,
+            &DistroVersion::Package(installed.name, installed.version),
+            None,
+        )?;
+        gc::record_install(self)
+    }
+
+    /// Fetches the Node index again, whether or not the cached index has expired, and
+    /// returns how many versions it lists.
+    pub fn refresh_index(&self) -> Fallible<usize> {
+        refresh_node_index(self.hooks()?.node.as_ref())
+    }
+
+    /// Installs again each tool of the user toolchain that was installed as a named
+    /// version (such as `lts` or an alias), resolving the name against a freshly fetched
+    /// index, and returns those tools.
+    pub fn update(&mut self) -> Fallible<Vec<ToolSpec>> {
+        self.refresh_index()?;
+        let mut updated = Vec::new();
+        for (tool, tag) in Aliases::current()?.installed() {
+            let toolspec = ToolSpec::from_str(tool, VersionSpec::Tag(tag.to_string()));
+            self.install(&toolspec)?;
+            updated.push(toolspec);
+        }
+        Ok(updated)
+    }
+
+    /// Builds a Node version matching the specified semantic versioning requirements from
+    /// source and sets it as the default in the user toolchain.
+    pub fn install_from_source(&mut self, matching: &VersionSpec) -> Fallible<()> {
+        let matching = match self.resolve_allowed(&ToolSpec::Node(matching.clone()))? {
+            ToolSpec::Node(matching) => matching,
+            _ => unreachable!(),
+        };
+        let distro_version = {
+            let inventory = self.inventory.get_mut()?;
+            let hooks = self.hooks.get()?;
+            inventory.build_node(&matching, hooks)?.into_version()
+        };
+        let toolchain = self.toolchain.get_mut()?;
+        toolchain.set_active(distro_version.clone())?;
+        audit::record(
+            &self.config()?.audit,
+            AuditAction::Install,
+            &distro_version,
+            None,
+        )?;
+        gc::record_install(self)
+    }
+
+    /// Installs the Node build at `path`, a distribution archive or the directory of one,
+    /// into the inventory as `version` if given, and sets it as the default in the user
+    /// toolchain.
+    pub fn install_local_node(&mut self, path: &Path, version: Option<&Version>) -> Fallible<()> {
+        let build = LocalBuild::stage(path, version)?;
+        self.check_policy(&NodeKind::tool_spec(build.version()))?;
+        let distro_version = self
+            .inventory
+            .get_mut()?
+            .install_local_node(build)?
+            .into_version();
+        let toolchain = self.toolchain.get_mut()?;
+        toolchain.set_active(distro_version.clone())?;
+        audit::record(
+            &self.config()?.audit,
+            AuditAction::Install,
+            &distro_version,
+            None,
+        )?;
+        gc::record_install(self)
+    }
+
+    /// Resolves the version of a Tool matching the specified semantic versioning
+    /// requirements and checks it against the organization policy, so that a version the
+    /// policy forbids is never fetched.
+    fn resolve_allowed(&self, tool: &ToolSpec) -> Fallible<ToolSpec> {
+        let resolved = self.inventory()?.resolve(tool, self.hooks()?)?;
+        self.check_policy(&resolved)?;
+        Ok(resolved)
     }
 
-    /// Fetches a Tool version matching the specified semantic versioning requirements.
+    /// Fetches a Tool version matching the specified semantic versioning requirements,
+    /// if the organization policy allows it.
     pub fn fetch(&mut self, tool: &ToolSpec) -> Fallible<Fetched<DistroVersion>> {
-        let inventory = self.inventory.get_mut()?;
-        let hooks = self.hooks.get()?;
-        inventory.fetch(&tool, hooks)
+        let resolved = self.resolve_allowed(tool)?;
+        self.event_log.add_event_fetch_start(tool.to_string());
+        let start = Instant::now();
+        let fetched = {
+            let inventory = self.inventory.get_mut()?;
+            let hooks = self.hooks.get()?;
+            inventory.fetch(&resolved, hooks)?
+        };
+        self.add_event_fetch_end(tool, &fetched, start.elapsed());
+        Ok(fetched)
+    }
+
+    /// Fetches the Tool versions matching each of `tools`, downloading them at the same
+    /// time, if the organization policy allows all of them.
+    pub fn fetch_all(&mut self, tools: &[ToolSpec]) -> Fallible<Vec<Fetched<DistroVersion>>> {
+        let mut resolved = Vec::new();
+        for tool in tools {
+            resolved.push(self.resolve_allowed(tool)?);
+        }
+        for tool in tools {
+            self.event_log.add_event_fetch_start(tool.to_string());
+        }
+        let start = Instant::now();
+        let fetched = {
+            let inventory = self.inventory.get_mut()?;
+            let hooks = self.hooks.get()?;
+            inventory.fetch_all(&resolved, hooks)?
+        };
+        // the downloads run at the same time, so each takes as long as all of them
+        let duration = start.elapsed();
+        for (tool, fetched) in tools.iter().zip(&fetched) {
+            self.add_event_fetch_end(tool, fetched, duration);
+        }
+        Ok(fetched)
+    }
+
+    fn add_event_fetch_end(
+        &mut self,
+        tool: &ToolSpec,
+        fetched: &Fetched<DistroVersion>,
+        duration: Duration,
+    ) {
+        let (version, bytes) = match fetched {
+            Fetched::Now(version) => (version, downloaded_bytes(version)),
+            Fetched::Already(version) => (version, None),
+        };
+        self.event_log
+            .add_event_fetch_end(tool.to_string(), version.to_string(), duration, bytes);
+    }
+
+    /// Fetches a Tool version matching the specified semantic versioning requirements and
+    /// produces the postscript that selects it for the current shell session.
+    pub fn shell_override(&mut self, toolspec: &ToolSpec) -> Fallible<Postscript> {
+        let (tool, version) = match self.fetch(toolspec)?.into_version() {
+            DistroVersion::Node(version, _) => ("node", version),
+            DistroVersion::Npm(version) => ("npm", version),
+            DistroVersion::Yarn(version) => ("yarn", version),
+            DistroVersion::Deno(version) => ("deno", version),
+            other => throw!(ShellOverrideError {
+                tool: other.to_string(),
+            }),
+        };
+        Ok(Postscript::ToolVersion {
+            tool: tool.to_string(),
+            version,
+        })
+    }
+
+    /// Fetches the Node version matching `node`, and the Yarn version matching `yarn` if
+    /// any, and produces the platform of the two, for running a single command under it
+    /// without pinning or installing anything.
+    pub fn ephemeral_platform(
+        &mut self,
+        node: VersionSpec,
+        yarn: Option<VersionSpec>,
+    ) -> Fallible<PlatformSpec> {
+        let mut tools = vec![ToolSpec::Node(node)];
+        tools.extend(yarn.map(ToolSpec::Yarn));
+
+        // the versions are fetched in the order of the tools
+        let mut fetched = self
+            .fetch_all(&tools)?
+            .into_iter()
+            .map(Fetched::into_version);
+        let (node_runtime, npm) = match fetched.next() {
+            Some(DistroVersion::Node(runtime, npm)) => (runtime, npm),
+            _ => unreachable!(),
+        };
+        let yarn = match fetched.next() {
+            Some(DistroVersion::Yarn(version)) => Some(version),
+            _ => None,
+        };
+        Ok(PlatformSpec {
+            node_runtime,
+            npm: Some(npm),
+            yarn,
+            deno: None,
+        })
+    }
+
+    /// Produces the postscript that selects the toolchain `profile` of the current project
+    /// for the current shell session, or its default toolchain for `default`.
+    pub fn use_profile(&self, profile: &str) -> Fallible<Postscript> {
+        if profile == DEFAULT_PROFILE {
+            return Ok(Postscript::Profile(None));
+        }
+        match self.project()? {
+            Some(ref project) if project.manifest().profiles.iter().any(|p| p == profile) => {
+                Ok(Postscript::Profile(Some(profile.to_string())))
+            }
+            Some(ref project) => throw!(UnknownProfileError {
+                profile: profile.to_string(),
+                available: project.manifest().profiles.join(", "),
+            }),
+            None => throw!(NotInPackageError::new()),
+        }
     }
 
     /// Updates toolchain in package.json with the Tool version matching the specified semantic
-    /// versioning requirements.
-    pub fn pin(&mut self, toolspec: &ToolSpec) -> Fallible<()> {
+    /// versioning requirements. With `engines` (or `pin.engines` in the configuration), the
+    /// Node version is mirrored into `engines.node`. With `dry_run`, the change is only
+    /// previewed.
+    pub fn pin(&mut self, toolspec: &ToolSpec, engines: bool, dry_run: bool) -> Fallible<()> {
         if let Some(ref project) = self.project()? {
             let distro_version = self.fetch(toolspec)?.into_version();
-            project.pin(&distro_version)?;
+            let engines = engines || self.config()?.pin.engines;
+            project.pin(&distro_version, engines, dry_run)?;
+            if dry_run {
+                return Ok(());
+            }
+            audit::record(
+                &self.config()?.audit,
+                AuditAction::Pin,
+                &distro_version,
+                Some(project.root()),
+            )?;
         } else {
             throw!(NotInPackageError::new());
         }
         Ok(())
     }
 
+    /// Produces a reference to the organization policy, if there is one.
+    pub fn policy(&self) -> Fallible<Option<&Policy>> {
+        self.policy.get()
+    }
+
+    /// Checks that a resolved tool version is allowed by the organization policy, if any.
+    fn check_policy(&self, toolspec: &ToolSpec) -> Fallible<()> {
+        match self.policy()? {
+            Some(policy) => policy.check(toolspec),
+            None => Ok(()),
+        }
+    }
+
     pub fn add_event_start(&mut self, activity_kind: ActivityKind) {
         self.event_log.add_event_start(activity_kind)
     }
@@ -223,7 +960,7 @@ impl Session {
                 self.event_log.publish(plugin);
             }
             Err(e) => {
-                eprintln!("Warning: invalid config file ({})", e);
+                display_warning(format!("invalid config file ({})", e));
             }
         }
     }
@@ -239,6 +976,21 @@ impl Session {
     }
 }
 
+/// The size of the archive that was downloaded for `version`, if it has one.
+fn downloaded_bytes(version: &DistroVersion) -> Option<u64> {
+    let archive = match version {
+        DistroVersion::Node(runtime, _) => ArchiveDistro::<NodeKind>::distro_file(runtime),
+        DistroVersion::Yarn(version) => ArchiveDistro::<YarnKind>::distro_file(version),
+        DistroVersion::Npm(version) => ArchiveDistro::<NpmKind>::distro_file(version),
+        DistroVersion::Deno(version) => ArchiveDistro::<DenoKind>::distro_file(version),
+        DistroVersion::Package(..) => return None,
+    };
+    archive
+        .ok()
+        .and_then(|archive| fs::metadata(archive).ok())
+        .map(|metadata| metadata.len())
+}
+
 fn publish_plugin(hooks: &LazyHookConfig) -> Fallible<Option<&Publish>> {
     let hooks = hooks.get()?;
     Ok(hooks
@@ -0,0 +1,270 @@
+//! Provides the setup of the user environment on Windows, where `NOTION_HOME` and the
+//! `PATH` entry of the shim directory are stored in the registry (under
+//! `HKEY_CURRENT_USER\Environment`) instead of in a shell profile.
+//!
+//! On Unix, the environment is configured by the installer, which edits the shell
+//! profile, so setting it up from here isn't supported. The profile evaluates the output
+//! of `notion env` (see `profile_script`), so that it follows the configured location of
+//! the shim directory.
+
+use std::path::Path;
+
+use failure::Fail;
+
+use notion_fail::{ExitCode, Fallible, NotionFail};
+use notion_fail_derive::*;
+
+#[cfg(windows)]
+mod windows;
+
+/// Thrown when the environment is set up on an OS where the installer takes care of it.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "`notion setup` is only available on Windows (on Unix, the installer configures your shell profile)"
+)]
+#[notion_fail(code = "NotYetImplemented")]
+pub(crate) struct SetupUnsupportedError;
+
+/// Thrown when a user environment variable could not be written to the registry.
+#[cfg(windows)]
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Could not update the user environment in the registry ({})", key)]
+#[notion_fail(code = "EnvironmentError")]
+pub(crate) struct SetupRegistryError {
+    pub(crate) key: String,
+}
+
+/// The changes made to the user environment.
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    /// Whether `NOTION_HOME` was set (or unset).
+    pub notion_home: bool,
+    /// Whether the shim directory was added to (or removed from) `PATH`.
+    pub path: bool,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        !self.notion_home && !self.path
+    }
+}
+
+/// Records `notion_home` as `NOTION_HOME` and puts `shim_dir` at the front of the user
+/// `PATH`, then notifies running programs (such as Explorer) of the change, so that
+/// new terminals pick it up.
+#[cfg(windows)]
+pub fn install(notion_home: &Path, shim_dir: &Path) -> Fallible<Changes> {
+    let mut changes = Changes::default();
+
+    let home = notion_home.to_string_lossy();
+    let current = windows::read_user_var("NOTION_HOME")?;
+    if current.as_ref().map(String::as_str) != Some(home.as_ref()) {
+        windows::write_user_var("NOTION_HOME", &home)?;
+        changes.notion_home = true;
+    }
+
+    let path = windows::read_user_var("Path")?.unwrap_or_default();
+    let updated = prepend_entry(&path, &shim_dir.to_string_lossy());
+    if updated != path {
+        windows::write_user_var("Path", &updated)?;
+        changes.path = true;
+    }
+
+    if !changes.is_empty() {
+        windows::broadcast_change();
+    }
+    Ok(changes)
+}
+
+/// Undoes `install`: removes `shim_dir` from the user `PATH` and unsets `NOTION_HOME`.
+#[cfg(windows)]
+pub fn uninstall(shim_dir: &Path) -> Fallible<Changes> {
+    let mut changes = Changes::default();
+
+    if let Some(path) = windows::read_user_var("Path")? {
+        let updated = remove_entry(&path, &shim_dir.to_string_lossy());
+        if updated != path {
+            windows::write_user_var("Path", &updated)?;
+            changes.path = true;
+        }
+    }
+
+    if windows::read_user_var("NOTION_HOME")?.is_some() {
+        windows::delete_user_var("NOTION_HOME")?;
+        changes.notion_home = true;
+    }
+
+    if !changes.is_empty() {
+        windows::broadcast_change();
+    }
+    Ok(changes)
+}
+
+#[cfg(not(windows))]
+pub fn install(_notion_home: &Path, _shim_dir: &Path) -> Fallible<Changes> {
+    Err(SetupUnsupportedError.into())
+}
+
+#[cfg(not(windows))]
+pub fn uninstall(_shim_dir: &Path) -> Fallible<Changes> {
+    Err(SetupUnsupportedError.into())
+}
+
+/// The shells that `profile_script` can produce commands for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ProfileShell {
+    /// POSIX shells, such as bash and zsh.
+    Sh,
+    Fish,
+    PowerShell,
+}
+
+impl ProfileShell {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sh" | "bash" | "zsh" => Some(ProfileShell::Sh),
+            "fish" => Some(ProfileShell::Fish),
+            "powershell" => Some(ProfileShell::PowerShell),
+            _ => None,
+        }
+    }
+}
+
+/// Renders the commands that record `notion_home` as `NOTION_HOME` and put `shim_dir`
+/// at the front of `PATH`, for a shell profile to evaluate.
+pub fn profile_script(shell: ProfileShell, notion_home: &Path, shim_dir: &Path) -> String {
+    path_script(shell, notion_home, &[shim_dir])
+}
+
+/// Renders the commands that record `notion_home` as `NOTION_HOME` and put `dirs`, in
+/// order, at the front of `PATH`.
+pub fn path_script(shell: ProfileShell, notion_home: &Path, dirs: &[&Path]) -> String {
+    let home = notion_home.to_string_lossy();
+    let dirs = dirs.iter().map(|dir| dir.to_string_lossy());
+    match shell {
+        ProfileShell::Sh => format!(
+            "export NOTION_HOME='{}'\nexport PATH={}\"$PATH\"\n",
+            home.replace('\'', "'\\''"),
+            dirs.map(|dir| format!("'{}':", dir.replace('\'', "'\\''")))
+                .collect::<String>()
+        ),
+        ProfileShell::Fish => format!(
+            "set -gx NOTION_HOME '{}'\nset -gx PATH {}$PATH\n",
+            home.replace('\\', "\\\\").replace('\'', "\\'"),
+            dirs.map(|dir| format!("'{}' ", dir.replace('\\', "\\\\").replace('\'', "\\'")))
+                .collect::<String>()
+        ),
+        ProfileShell::PowerShell => format!(
+            "$env:NOTION_HOME = '{}'\n$env:Path = '{}' + $env:Path\n",
+            home.replace('\'', "''"),
+            dirs.map(|dir| format!("{};", dir.replace('\'', "''")))
+                .collect::<String>()
+        ),
+    }
+}
+
+/// Compares `PATH` entries the way Windows does: case-insensitively, and ignoring a
+/// trailing separator.
+fn same_entry(a: &str, b: &str) -> bool {
+    let trim = |entry: &str| entry.trim().trim_end_matches('\\').to_lowercase();
+    trim(a) == trim(b)
+}
+
+/// Moves `dir` to the front of the `;`-separated `path`, adding it if it's missing.
+fn prepend_entry(path: &str, dir: &str) -> String {
+    let rest = remove_entry(path, dir);
+    if rest.is_empty() {
+        dir.to_string()
+    } else {
+        format!("{};{}", dir, rest)
+    }
+}
+
+/// Removes every occurrence of `dir` (and any empty entries) from the `;`-separated `path`.
+fn remove_entry(path: &str, dir: &str) -> String {
+    path.split(';')
+        .filter(|entry| !entry.trim().is_empty() && !same_entry(entry, dir))
+        .collect::<Vec<&str>>()
+        .join(";")
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{path_script, prepend_entry, profile_script, remove_entry, ProfileShell};
+    use std::path::Path;
+
+    #[test]
+    fn test_prepend_entry() {
+        let shims = r"C:\Users\johndoe\AppData\Local\Notion\bin";
+
+        assert_eq!(prepend_entry("", shims), shims);
+        assert_eq!(
+            prepend_entry(r"%USERPROFILE%\bin;C:\tools", shims),
+            format!(r"{};%USERPROFILE%\bin;C:\tools", shims)
+        );
+        assert_eq!(
+            prepend_entry(
+                r"C:\tools;c:\users\johndoe\appdata\local\notion\bin\",
+                shims
+            ),
+            format!(r"{};C:\tools", shims)
+        );
+    }
+
+    #[test]
+    fn test_profile_script() {
+        let home = Path::new("/home/o'neil/.notion");
+        let shims = Path::new("/opt/notion/bin");
+
+        assert_eq!(
+            profile_script(ProfileShell::Sh, home, shims),
+            "export NOTION_HOME='/home/o'\\''neil/.notion'\n\
+             export PATH='/opt/notion/bin':\"$PATH\"\n"
+        );
+        assert_eq!(
+            profile_script(ProfileShell::Fish, home, shims),
+            "set -gx NOTION_HOME '/home/o\\'neil/.notion'\nset -gx PATH '/opt/notion/bin' $PATH\n"
+        );
+        assert_eq!(
+            profile_script(ProfileShell::PowerShell, home, shims),
+            "$env:NOTION_HOME = '/home/o''neil/.notion'\n$env:Path = '/opt/notion/bin;' + $env:Path\n"
+        );
+        assert_eq!(ProfileShell::parse("zsh"), Some(ProfileShell::Sh));
+        assert_eq!(ProfileShell::parse("tcsh"), None);
+    }
+
+    #[test]
+    fn test_path_script() {
+        let home = Path::new("/root/.notion");
+        let node = Path::new("/root/.notion/tools/image/node/10.13.0/bin");
+        let shims = Path::new("/root/.notion/bin");
+
+        assert_eq!(
+            path_script(ProfileShell::Sh, home, &[node, shims]),
+            "export NOTION_HOME='/root/.notion'\n\
+             export PATH='/root/.notion/tools/image/node/10.13.0/bin':'/root/.notion/bin':\"$PATH\"\n"
+        );
+        assert_eq!(
+            path_script(ProfileShell::Fish, home, &[node, shims]),
+            "set -gx NOTION_HOME '/root/.notion'\n\
+             set -gx PATH '/root/.notion/tools/image/node/10.13.0/bin' '/root/.notion/bin' $PATH\n"
+        );
+        assert_eq!(
+            path_script(ProfileShell::PowerShell, home, &[node, shims]),
+            "$env:NOTION_HOME = '/root/.notion'\n\
+             $env:Path = '/root/.notion/tools/image/node/10.13.0/bin;/root/.notion/bin;' + $env:Path\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_entry() {
+        let shims = r"C:\Users\johndoe\AppData\Local\Notion\bin";
+
+        assert_eq!(
+            remove_entry(&format!(r"{};C:\tools;;{}", shims, shims), shims),
+            r"C:\tools"
+        );
+        assert_eq!(remove_entry(r"C:\tools", shims), r"C:\tools");
+    }
+}
@@ -0,0 +1,90 @@
+//! Provides access to the user environment variables stored in the Windows registry.
+
+use std::process::{Command, Stdio};
+
+use notion_fail::{throw, Fallible, ResultExt};
+
+use super::SetupRegistryError;
+
+/// The registry key holding the user environment variables.
+const ENVIRONMENT_KEY: &'static str = r"HKCU\Environment";
+
+/// Reads the unexpanded value of the user environment variable `name`.
+pub(super) fn read_user_var(name: &str) -> Fallible<Option<String>> {
+    let output = Command::new("reg")
+        .args(&["query", ENVIRONMENT_KEY, "/v", name])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .unknown()?;
+
+    // a missing value makes the query fail
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    // values are listed as `    <name>    <type>    <data>`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().find_map(|line| {
+        let mut columns = line.trim().splitn(3, "    ");
+        if !columns.next()?.eq_ignore_ascii_case(name) {
+            return None;
+        }
+        let _type = columns.next()?;
+        Some(columns.next().unwrap_or("").trim().to_string())
+    }))
+}
+
+/// Writes the user environment variable `name`. Values are stored as `REG_EXPAND_SZ`,
+/// so that references to other variables (like `%USERPROFILE%`) keep working.
+pub(super) fn write_user_var(name: &str, value: &str) -> Fallible<()> {
+    reg(&[
+        "add",
+        ENVIRONMENT_KEY,
+        "/v",
+        name,
+        "/t",
+        "REG_EXPAND_SZ",
+        "/d",
+        value,
+        "/f",
+    ])
+}
+
+pub(super) fn delete_user_var(name: &str) -> Fallible<()> {
+    reg(&["delete", ENVIRONMENT_KEY, "/v", name, "/f"])
+}
+
+fn reg(args: &[&str]) -> Fallible<()> {
+    let status = Command::new("reg")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .unknown()?;
+    if !status.success() {
+        throw!(SetupRegistryError {
+            key: ENVIRONMENT_KEY.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Broadcasts `WM_SETTINGCHANGE` for the environment, which makes Explorer (and the
+/// terminals it starts afterwards) reload the user variables. This is best-effort: if it
+/// fails, the change still takes effect at the next login.
+pub(super) fn broadcast_change() {
+    let script = "Add-Type -Namespace Notion -Name Native -MemberDefinition \
+         '[DllImport(\"user32.dll\", CharSet = CharSet.Auto)] \
+         public static extern IntPtr SendMessageTimeout(IntPtr hWnd, uint Msg, UIntPtr wParam, \
+         string lParam, uint fuFlags, uint uTimeout, out UIntPtr lpdwResult);'; \
+         $result = [UIntPtr]::Zero; \
+         [void][Notion.Native]::SendMessageTimeout([IntPtr]0xffff, 0x1a, [UIntPtr]::Zero, \
+         'Environment', 2, 5000, [ref]$result)";
+    let _ = Command::new("powershell")
+        .args(&["-NoProfile", "-Command", script])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
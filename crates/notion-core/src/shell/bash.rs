@@ -29,6 +29,13 @@ impl Shell for Bash {
                 tool.to_ascii_uppercase(),
                 version
             ),
+            &Postscript::ClearToolVersion(ref tool) => {
+                format!("unset NOTION_{}_VERSION\n", tool.to_ascii_uppercase())
+            }
+            &Postscript::Profile(Some(ref profile)) => {
+                format!("export NOTION_PROFILE={}\n", profile)
+            }
+            &Postscript::Profile(None) => "unset NOTION_PROFILE\n".to_string(),
         }
     }
 }
@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+use super::{Postscript, Shell};
+
+pub(crate) struct Cmd {
+    pub(crate) postscript_path: PathBuf,
+}
+
+/// Escapes `value` for a quoted `SET` in a batch file, where `%` would otherwise start
+/// a variable expansion.
+fn escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+impl Shell for Cmd {
+    fn postscript_path(&self) -> &Path {
+        &self.postscript_path
+    }
+
+    // batch files are run line by line, which is only reliable with CRLF line endings
+    fn compile_postscript(&self, postscript: &Postscript) -> String {
+        match postscript {
+            &Postscript::Activate(ref s) => format!(
+                "SET \"PATH={}\"\r\nSET \"NOTION_HOME=%LOCALAPPDATA%\\Notion\"\r\n",
+                escape(s)
+            ),
+            &Postscript::Deactivate(ref s) => {
+                format!("SET \"PATH={}\"\r\nSET NOTION_HOME=\r\n", escape(s))
+            }
+            &Postscript::ToolVersion {
+                ref tool,
+                ref version,
+            } => format!(
+                "SET NOTION_{}_VERSION={}\r\n",
+                tool.to_ascii_uppercase(),
+                version
+            ),
+            &Postscript::ClearToolVersion(ref tool) => {
+                format!("SET NOTION_{}_VERSION=\r\n", tool.to_ascii_uppercase())
+            }
+            &Postscript::Profile(Some(ref profile)) => {
+                format!("SET \"NOTION_PROFILE={}\"\r\n", escape(profile))
+            }
+            &Postscript::Profile(None) => "SET NOTION_PROFILE=\r\n".to_string(),
+        }
+    }
+}
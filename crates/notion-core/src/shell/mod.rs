@@ -13,13 +13,19 @@ use notion_fail_derive::*;
 use crate::env;
 
 mod bash;
+mod cmd;
+mod powershell;
 
 pub(crate) use self::bash::Bash;
+pub(crate) use self::cmd::Cmd;
+pub(crate) use self::powershell::PowerShell;
 
 pub enum Postscript {
     Activate(String),
     Deactivate(String),
     ToolVersion { tool: String, version: Version },
+    ClearToolVersion(String),
+    Profile(Option<String>),
 }
 
 /// Thrown when the postscript file was not specified in the Notion environment.
@@ -89,6 +95,8 @@ impl FromStr for CurrentShell {
 
         Ok(CurrentShell(match src {
             "bash" => Box::new(Bash { postscript_path }),
+            "powershell" => Box::new(PowerShell { postscript_path }),
+            "cmd" => Box::new(Cmd { postscript_path }),
             _ => {
                 throw!(UnrecognizedShellError {
                     name: src.to_string()
@@ -109,6 +117,15 @@ pub mod tests {
     fn test_compile_postscript() {
         let bash = CurrentShell::from_str("bash").expect("Could not create bash shell");
 
+        assert_eq!(
+            bash.compile_postscript(&Postscript::Profile(Some("legacy".to_string()))),
+            "export NOTION_PROFILE=legacy\n"
+        );
+        assert_eq!(
+            bash.compile_postscript(&Postscript::Profile(None)),
+            "unset NOTION_PROFILE\n"
+        );
+
         assert_eq!(
             bash.compile_postscript(&Postscript::Deactivate("some:path".to_string())),
             "export PATH='some:path'\nunset NOTION_HOME\n"
@@ -130,9 +147,46 @@ pub mod tests {
             "export NOTION_TEST_VERSION=2.4.5\n"
         );
 
+        assert_eq!(
+            bash.compile_postscript(&Postscript::ClearToolVersion("test".to_string())),
+            "unset NOTION_TEST_VERSION\n"
+        );
+
         assert_eq!(
             bash.compile_postscript(&Postscript::Activate("some:path".to_string())),
             "export PATH='some:path'\nexport NOTION_HOME=\"${HOME}/.notion\"\n"
         );
     }
+
+    #[test]
+    fn test_compile_powershell_postscript() {
+        let powershell =
+            CurrentShell::from_str("powershell").expect("Could not create PowerShell shell");
+
+        assert_eq!(
+            powershell.compile_postscript(&Postscript::Activate(r"C:\it's;C:\bin".to_string())),
+            "$env:Path = 'C:\\it''s;C:\\bin'\n$env:NOTION_HOME = \"$env:LOCALAPPDATA\\Notion\"\n"
+        );
+        assert_eq!(
+            powershell.compile_postscript(&Postscript::ClearToolVersion("node".to_string())),
+            "Remove-Item Env:NOTION_NODE_VERSION -ErrorAction SilentlyContinue\n"
+        );
+    }
+
+    #[test]
+    fn test_compile_cmd_postscript() {
+        let cmd = CurrentShell::from_str("cmd").expect("Could not create cmd shell");
+
+        assert_eq!(
+            cmd.compile_postscript(&Postscript::Deactivate(r"C:\100%;C:\bin".to_string())),
+            "SET \"PATH=C:\\100%%;C:\\bin\"\r\nSET NOTION_HOME=\r\n"
+        );
+        assert_eq!(
+            cmd.compile_postscript(&Postscript::ToolVersion {
+                tool: "node".to_string(),
+                version: Version::parse("10.13.0").unwrap()
+            }),
+            "SET NOTION_NODE_VERSION=10.13.0\r\n"
+        );
+    }
 }
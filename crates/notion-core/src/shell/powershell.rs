@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+use super::{Postscript, Shell};
+
+pub(crate) struct PowerShell {
+    pub(crate) postscript_path: PathBuf,
+}
+
+/// Quotes `value` as a PowerShell literal string, in which only `'` needs escaping.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+impl Shell for PowerShell {
+    fn postscript_path(&self) -> &Path {
+        &self.postscript_path
+    }
+
+    fn compile_postscript(&self, postscript: &Postscript) -> String {
+        match postscript {
+            &Postscript::Activate(ref s) => format!(
+                "$env:Path = {}\n$env:NOTION_HOME = \"$env:LOCALAPPDATA\\Notion\"\n",
+                quote(s)
+            ),
+            &Postscript::Deactivate(ref s) => format!(
+                "$env:Path = {}\nRemove-Item Env:NOTION_HOME -ErrorAction SilentlyContinue\n",
+                quote(s)
+            ),
+            &Postscript::ToolVersion {
+                ref tool,
+                ref version,
+            } => format!(
+                "$env:NOTION_{}_VERSION = '{}'\n",
+                tool.to_ascii_uppercase(),
+                version
+            ),
+            &Postscript::ClearToolVersion(ref tool) => format!(
+                "Remove-Item Env:NOTION_{}_VERSION -ErrorAction SilentlyContinue\n",
+                tool.to_ascii_uppercase()
+            ),
+            &Postscript::Profile(Some(ref profile)) => {
+                format!("$env:NOTION_PROFILE = {}\n", quote(profile))
+            }
+            &Postscript::Profile(None) => {
+                "Remove-Item Env:NOTION_PROFILE -ErrorAction SilentlyContinue\n".to_string()
+            }
+        }
+    }
+}
@@ -1,11 +1,22 @@
 //! Provides utilities for modifying shims for 3rd-party executables
 
-use std::{fs, io};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::{env, fs, io, thread};
 
 use failure::Fail;
+use serde::{Deserialize, Serialize};
+use serde_json;
 
+use crate::config::Config;
+use crate::diagnostics::{Finding, Fix};
+use crate::fs::{
+    ensure_containing_dir_exists, ensure_dir_exists, ensure_home_writable, read_dir_eager,
+    read_file_opt, PermissionError,
+};
+use crate::package::BinConfig;
 use crate::path;
-use notion_fail::{throw, ExitCode, FailExt, Fallible, NotionFail};
+use notion_fail::{throw, ExitCode, FailExt, Fallible, NotionFail, ResultExt};
 use notion_fail_derive::*;
 
 #[derive(Debug, Fail, NotionFail)]
@@ -29,6 +40,18 @@ impl SymlinkError {
     }
 }
 
+/// Thrown when the shims would be moved while `NOTION_SHIM_DIR` overrides the shim
+/// directory of the configuration.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Cannot move the shims while NOTION_SHIM_DIR is set (to {})\n\nNOTION_SHIM_DIR overrides the shim directory of the configuration. Unset it and run `notion relocate` again.",
+    dir
+)]
+#[notion_fail(code = "ConfigurationError")]
+pub(crate) struct ShimDirOverriddenError {
+    dir: String,
+}
+
 #[derive(PartialEq)]
 pub enum ShimResult {
     Created,
@@ -37,21 +60,40 @@ pub enum ShimResult {
     DoesntExist,
 }
 
+/// The shims that every Notion installation provides.
+pub(crate) const CORE_SHIMS: &[&str] = &["node", "npm", "npx", "yarn", "deno"];
+
 fn is_3p_shim(name: &str) -> bool {
-    match name {
-        "node" | "yarn" | "npm" | "npx" => false,
-        _ => true,
+    !CORE_SHIMS.contains(&name)
+}
+
+/// Creates `shim` as a link to `launchbin`. On filesystems that don't support symbolic
+/// links (such as FAT volumes, some network shares, or Windows machines where policy
+/// forbids them), this falls back to a hard link and finally to a copy; since the
+/// launcher dispatches on the name it was invoked with, all three behave the same.
+fn link_launchbin(launchbin: &Path, shim: &Path) -> io::Result<()> {
+    match path::create_file_symlink(launchbin.to_path_buf(), shim.to_path_buf()) {
+        Err(ref err)
+            if err.kind() != io::ErrorKind::AlreadyExists
+                && err.kind() != io::ErrorKind::PermissionDenied =>
+        {
+            fs::hard_link(launchbin, shim).or_else(|_| fs::copy(launchbin, shim).map(|_| ()))
+        }
+        result => result,
     }
 }
 
 pub fn create(shim_name: &str) -> Fallible<ShimResult> {
+    ensure_home_writable(&format!("create the shim for `{}`", shim_name))?;
     let launchbin = path::launchbin_file()?;
     let shim = path::shim_file(shim_name)?;
-    match path::create_file_symlink(launchbin, shim) {
+    match link_launchbin(&launchbin, &shim) {
         Ok(_) => Ok(ShimResult::Created),
         Err(err) => {
             if err.kind() == io::ErrorKind::AlreadyExists {
                 Ok(ShimResult::AlreadyExists)
+            } else if err.kind() == io::ErrorKind::PermissionDenied {
+                throw!(err.with_context(PermissionError::for_path(&shim)));
             } else {
                 throw!(err.with_context(SymlinkError::from_io_error));
             }
@@ -59,12 +101,73 @@ pub fn create(shim_name: &str) -> Fallible<ShimResult> {
     }
 }
 
+/// The maximum number of threads linking shims in `create_all`.
+const MAX_SHIM_THREADS: usize = 8;
+
+/// Creates the shims for all of `shim_names` in a single pass, resolving the shim
+/// directory once and linking the shims in parallel. Returns the result of creating each
+/// shim, in the order of `shim_names`.
+pub fn create_all(shim_names: &[String]) -> Fallible<Vec<Fallible<ShimResult>>> {
+    ensure_home_writable("create shims")?;
+    let launchbin = path::launchbin_file()?;
+    let shim_dir = path::shim_dir()?;
+    ensure_dir_exists(&shim_dir)?;
+
+    let shims: Vec<PathBuf> = shim_names
+        .iter()
+        .map(|name| shim_dir.join(path::shim_file_name(name)))
+        .collect();
+    if shims.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let threads = ::std::cmp::min(shims.len(), MAX_SHIM_THREADS);
+    let chunk_size = (shims.len() + threads - 1) / threads;
+    let handles: Vec<_> = shims
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let launchbin = launchbin.clone();
+            let chunk = chunk.to_vec();
+            thread::spawn(move || {
+                chunk
+                    .iter()
+                    .map(|shim| link_launchbin(&launchbin, shim))
+                    .collect::<Vec<io::Result<()>>>()
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(shims.len());
+    for handle in handles {
+        let linked = handle.join().map_err(|_| SymlinkError {
+            error: "a thread creating shims panicked".to_string(),
+        })?;
+        results.extend(linked);
+    }
+
+    Ok(results
+        .into_iter()
+        .zip(shims.iter())
+        .map(|(result, shim)| match result {
+            Ok(_) => Ok(ShimResult::Created),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                Ok(ShimResult::AlreadyExists)
+            }
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                Err(err.with_context(PermissionError::for_path(shim)))
+            }
+            Err(err) => Err(err.with_context(SymlinkError::from_io_error)),
+        })
+        .collect())
+}
+
 pub fn delete(shim_name: &str) -> Fallible<ShimResult> {
     if !is_3p_shim(shim_name) {
         throw!(SymlinkError {
             error: format!("cannot delete `{}`, not a 3rd-party executable", shim_name),
         });
     }
+    ensure_home_writable(&format!("delete the shim for `{}`", shim_name))?;
     let shim = path::shim_file(shim_name)?;
     match fs::remove_file(shim) {
         Ok(_) => Ok(ShimResult::Deleted),
@@ -77,3 +180,339 @@ pub fn delete(shim_name: &str) -> Fallible<ShimResult> {
         }
     }
 }
+
+/// Moves every shim in the current shim directory into `new_dir` and records the new
+/// location in the user configuration. Third-party shims are recreated as links to
+/// `launchbin`, while the core shims (`node`, `npm`, `npx`, `yarn`) are copied.
+/// Returns the names of the shims that were moved.
+///
+/// Since `NOTION_SHIM_DIR` would still override the recorded location, the shims can't be
+/// moved while it is set.
+pub fn relocate(config: &mut Config, new_dir: PathBuf) -> Fallible<Vec<String>> {
+    if let Some(dir) = env::var_os("NOTION_SHIM_DIR") {
+        throw!(ShimDirOverriddenError {
+            dir: dir.to_string_lossy().to_string(),
+        });
+    }
+    let old_dir = path::shim_dir()?;
+    if old_dir == new_dir {
+        return Ok(Vec::new());
+    }
+    ensure_home_writable("move the shims")?;
+
+    let moved = copy_shims(&old_dir, &new_dir, &path::launchbin_file()?)?;
+
+    config.layout.shim_dir = Some(new_dir);
+    config.save()?;
+
+    for name in moved.iter() {
+        fs::remove_file(old_dir.join(name)).unknown()?;
+    }
+
+    Ok(moved)
+}
+
+/// Copies every shim in `old_dir` into `new_dir`, linking the third-party shims to
+/// `launchbin` again, and returns the names of the shims copied.
+fn copy_shims(old_dir: &Path, new_dir: &Path, launchbin: &Path) -> Fallible<Vec<String>> {
+    ensure_dir_exists(new_dir)?;
+
+    let mut copied = Vec::new();
+    if !old_dir.is_dir() {
+        return Ok(copied);
+    }
+    for (entry, _) in read_dir_eager(old_dir)? {
+        let file_name = entry.file_name();
+        let name = file_name.to_string_lossy().to_string();
+        let dest = new_dir.join(&file_name);
+        let stem = Path::new(&file_name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| name.clone());
+
+        if is_3p_shim(&stem) {
+            if let Err(err) = link_launchbin(launchbin, &dest) {
+                if err.kind() != io::ErrorKind::AlreadyExists {
+                    throw!(err.with_context(SymlinkError::from_io_error));
+                }
+            }
+        } else {
+            fs::copy(entry.path(), dest).unknown()?;
+        }
+        copied.push(name);
+    }
+    Ok(copied)
+}
+
+/// Checks the shim directory: that it and the launcher exist, that the core shims are
+/// present, that every shim still reaches the launcher, and that every third-party shim
+/// belongs to a project that still exists or to an installed package.
+pub(crate) fn diagnose() -> Fallible<Vec<Finding>> {
+    let launchbin = path::launchbin_file()?;
+    if !launchbin.is_file() {
+        return Ok(vec![Finding::new(format!(
+            "The launcher {} is missing, so no shim can run; reinstall Notion to restore it",
+            launchbin.display()
+        ))]);
+    }
+
+    let mut findings = Vec::new();
+    let shim_dir = path::shim_dir()?;
+    if !shim_dir.is_dir() {
+        findings.push(Finding::fixable(
+            format!("The shim directory {} does not exist", shim_dir.display()),
+            Fix::CreateDir(shim_dir.clone()),
+        ));
+    }
+
+    for name in CORE_SHIMS {
+        if fs::symlink_metadata(path::shim_file(name)?).is_err() {
+            findings.push(Finding::fixable(
+                format!("The shim for `{}` is missing", name),
+                Fix::CreateShim(name.to_string()),
+            ));
+        }
+    }
+
+    if !shim_dir.is_dir() {
+        return Ok(findings);
+    }
+
+    let manifest = ShimManifest::current()?;
+    for (entry, _) in read_dir_eager(&shim_dir)? {
+        let file_name = entry.file_name();
+        let name = Path::new(&file_name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_name.to_string_lossy().to_string());
+
+        // a shim that is a link to a launcher that moved can't be followed anymore
+        if fs::metadata(entry.path()).is_err() {
+            findings.push(Finding::fixable(
+                format!("The shim for `{}` no longer links to the launcher", name),
+                Fix::RelinkShim(name.clone()),
+            ));
+            continue;
+        }
+
+        if is_3p_shim(&name)
+            && !manifest.is_used(&name, |root| root.is_dir())
+            && BinConfig::current(&name)?.is_none()
+        {
+            findings.push(Finding::fixable(
+                format!(
+                    "The shim for `{}` is not used by any project or installed package",
+                    name
+                ),
+                Fix::DeleteShim(name),
+            ));
+        }
+    }
+    Ok(findings)
+}
+
+/// The record of the third-party shims created for the dependencies of each project,
+/// which lets `autoshim` create and delete only the shims that changed since it last ran.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct ShimManifest {
+    /// The shim names for each project, keyed by the project root.
+    projects: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// The shims that need to be created and deleted to bring a project up to date.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ShimDelta {
+    pub(crate) create: Vec<String>,
+    pub(crate) delete: Vec<String>,
+}
+
+impl ShimDelta {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.delete.is_empty()
+    }
+}
+
+impl ShimManifest {
+    /// Loads the manifest, treating a missing file as an empty manifest.
+    pub(crate) fn current() -> Fallible<Self> {
+        match read_file_opt(&path::user_shims_file()?).unknown()? {
+            Some(src) => serde_json::from_str(&src).unknown(),
+            None => Ok(ShimManifest::default()),
+        }
+    }
+
+    pub(crate) fn save(&self) -> Fallible<()> {
+        ensure_home_writable("record the project shims")?;
+        let path = path::user_shims_file()?;
+        ensure_containing_dir_exists(&path)?;
+        fs::write(path, serde_json::to_string_pretty(self).unknown()?).unknown()
+    }
+
+    /// Computes the changes needed for the project at `root` to have exactly the shims in
+    /// `names`. Shims that are recorded but missing from the shim directory are
+    /// recreated, and shims still used by another project are never deleted.
+    pub(crate) fn delta(&self, root: &Path, names: &BTreeSet<String>) -> Fallible<ShimDelta> {
+        let empty = BTreeSet::new();
+        let previous = self.projects.get(&project_key(root)).unwrap_or(&empty);
+
+        let mut create = Vec::new();
+        let mut shim_dir = None;
+        for name in names {
+            if !previous.contains(name) {
+                create.push(name.clone());
+                continue;
+            }
+            if shim_dir.is_none() {
+                shim_dir = Some(path::shim_dir()?);
+            }
+            let shim = shim_dir.as_ref().unwrap().join(path::shim_file_name(name));
+            if !shim.exists() {
+                create.push(name.clone());
+            }
+        }
+
+        let delete = previous
+            .difference(names)
+            .filter(|name| is_3p_shim(name) && !self.is_used_elsewhere(root, name))
+            .cloned()
+            .collect();
+
+        Ok(ShimDelta { create, delete })
+    }
+
+    /// Records `names` as the shims of the project at `root`.
+    pub(crate) fn record(&mut self, root: &Path, names: BTreeSet<String>) {
+        let key = project_key(root);
+        if names.is_empty() {
+            self.projects.remove(&key);
+        } else {
+            self.projects.insert(key, names);
+        }
+    }
+
+    /// Determines whether a project whose root still exists uses the shim `name`.
+    fn is_used(&self, name: &str, root_exists: impl Fn(&Path) -> bool) -> bool {
+        self.projects
+            .iter()
+            .any(|(project, names)| names.contains(name) && root_exists(Path::new(project)))
+    }
+
+    fn is_used_elsewhere(&self, root: &Path, name: &str) -> bool {
+        let key = project_key(root);
+        self.projects
+            .iter()
+            .any(|(project, names)| *project != key && names.contains(name))
+    }
+}
+
+fn project_key(root: &Path) -> String {
+    root.to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{copy_shims, ShimManifest};
+    use crate::config::Config;
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempfile::tempdir;
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_delta_deletes_unused_shims() {
+        let mut manifest = ShimManifest::default();
+        manifest.record(Path::new("/src/app"), names(&["eslint", "tsc"]));
+        manifest.record(Path::new("/src/lib"), names(&["tsc"]));
+
+        let delta = manifest
+            .delta(Path::new("/src/app"), &names(&[]))
+            .expect("could not compute delta");
+        assert!(delta.create.is_empty());
+        assert_eq!(delta.delete, vec!["eslint".to_string()]);
+    }
+
+    #[test]
+    fn test_is_used() {
+        let mut manifest = ShimManifest::default();
+        manifest.record(Path::new("/src/app"), names(&["eslint", "tsc"]));
+        manifest.record(Path::new("/src/deleted"), names(&["mocha"]));
+        let root_exists = |root: &Path| root != Path::new("/src/deleted");
+
+        assert!(manifest.is_used("tsc", root_exists));
+        assert!(!manifest.is_used("mocha", root_exists));
+        assert!(!manifest.is_used("prettier", root_exists));
+    }
+
+    #[test]
+    fn test_copy_shims() {
+        let root = tempdir().expect("could not create temporary directory");
+        let launchbin = root.path().join("launchbin");
+        fs::write(&launchbin, "launcher").unwrap();
+        let old_dir = root.path().join("bin");
+        fs::create_dir(&old_dir).unwrap();
+        fs::write(old_dir.join("node"), "node shim").unwrap();
+        fs::write(old_dir.join("tsc"), "stale shim").unwrap();
+        let new_dir = root.path().join("shims");
+
+        let mut copied = copy_shims(&old_dir, &new_dir, &launchbin).expect("could not copy");
+        copied.sort();
+
+        assert_eq!(copied, vec!["node".to_string(), "tsc".to_string()]);
+        assert_eq!(
+            fs::read_to_string(new_dir.join("node")).unwrap(),
+            "node shim"
+        );
+        // third-party shims reach the launcher again rather than keeping their contents
+        assert_eq!(fs::read_to_string(new_dir.join("tsc")).unwrap(), "launcher");
+        // the originals are only removed once the new location is recorded
+        assert!(old_dir.join("node").is_file());
+    }
+
+    #[test]
+    fn test_copy_shims_without_shim_dir() {
+        let root = tempdir().expect("could not create temporary directory");
+        let new_dir = root.path().join("shims");
+
+        let copied = copy_shims(
+            &root.path().join("missing"),
+            &new_dir,
+            &root.path().join("launchbin"),
+        )
+        .expect("could not copy");
+
+        assert!(copied.is_empty());
+        assert!(new_dir.is_dir());
+    }
+
+    #[test]
+    fn test_relocated_config() {
+        let mut config: Config = "[layout]\nread_only = true\n"
+            .parse()
+            .expect("could not parse config");
+        config.layout.shim_dir = Some(PathBuf::from("/opt/notion/bin"));
+
+        let saved: Config = config
+            .to_toml()
+            .expect("could not render config")
+            .parse()
+            .expect("could not parse saved config");
+        assert!(saved.layout.read_only);
+        assert_eq!(
+            saved.layout.shim_dir,
+            Some(PathBuf::from("/opt/notion/bin"))
+        );
+    }
+
+    #[test]
+    fn test_record_empty() {
+        let mut manifest = ShimManifest::default();
+        manifest.record(Path::new("/src/app"), names(&["tsc"]));
+        manifest.record(Path::new("/src/app"), names(&[]));
+        assert!(manifest.projects.is_empty());
+    }
+}
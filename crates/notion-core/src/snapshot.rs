@@ -0,0 +1,265 @@
+//! Provides the `Snapshot` type, a lightweight manifest of the contents of the
+//! Notion inventory and the user toolchain, without any of the tool binaries.
+//! A snapshot can later be restored by re-fetching every tool it lists.
+
+use std::collections::BTreeMap;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use crate::alias::{self, Aliases};
+use crate::package;
+use crate::platform::PlatformSpec;
+use crate::session::Session;
+use crate::tool::ToolSpec;
+use crate::toolchain::serial::Platform;
+use crate::version::VersionSpec;
+use notion_fail::{Fallible, ResultExt};
+
+/// A manifest of fetched tool versions and the user's default toolchain.
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    /// The versions of Node in the inventory.
+    pub node: Vec<Version>,
+    /// The versions of Yarn in the inventory.
+    pub yarn: Vec<Version>,
+    /// The versions of npm in the inventory.
+    pub npm: Vec<Version>,
+    /// The versions of Deno in the inventory.
+    pub deno: Vec<Version>,
+    /// The user's default platform, if any.
+    pub default: Option<PlatformSpec>,
+    /// The packages installed in the user toolchain, by name.
+    pub packages: BTreeMap<String, Version>,
+    /// The user's aliases, by the name of the tool and then of the alias.
+    pub aliases: BTreeMap<String, BTreeMap<String, VersionSpec>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerialSnapshot {
+    #[serde(default)]
+    node: Vec<String>,
+    #[serde(default)]
+    yarn: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    npm: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    deno: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default: Option<Platform>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    packages: BTreeMap<String, String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl Snapshot {
+    /// Captures the current state of the inventory and user toolchain.
+    pub fn current(session: &Session) -> Fallible<Self> {
+        let inventory = session.inventory()?;
+        Ok(Snapshot {
+            node: inventory.node.versions.iter().cloned().collect(),
+            yarn: inventory.yarn.versions.iter().cloned().collect(),
+            npm: inventory.npm.versions.iter().cloned().collect(),
+            deno: inventory.deno.versions.iter().cloned().collect(),
+            default: session.user_platform()?.map(|platform| (*platform).clone()),
+            packages: package::installed()?
+                .into_iter()
+                .map(|package| (package.name, package.version))
+                .collect(),
+            aliases: current_aliases()?,
+        })
+    }
+
+    /// Re-fetches every tool listed in this snapshot and restores the user's
+    /// aliases and default toolchain, then reinstalls its packages with that toolchain.
+    pub fn restore(&self, session: &mut Session) -> Fallible<()> {
+        for (tool, aliases) in &self.aliases {
+            for (name, version) in aliases {
+                alias::create(tool, name, version)?;
+            }
+        }
+
+        let tools: Vec<ToolSpec> = self
+            .node
+            .iter()
+            .map(|version| ToolSpec::Node(VersionSpec::exact(version)))
+            .chain(
+                self.yarn
+                    .iter()
+                    .map(|version| ToolSpec::Yarn(VersionSpec::exact(version))),
+            )
+            .chain(
+                self.npm
+                    .iter()
+                    .map(|version| ToolSpec::Npm(VersionSpec::exact(version))),
+            )
+            .chain(
+                self.deno
+                    .iter()
+                    .map(|version| ToolSpec::Deno(VersionSpec::exact(version))),
+            )
+            .collect();
+        session.fetch_all(&tools)?;
+
+        if let Some(ref platform) = self.default {
+            session.install(&ToolSpec::Node(VersionSpec::exact(&platform.node_runtime)))?;
+            // installing Node selects its bundled npm
+            if let Some(npm) = platform.custom_npm()? {
+                session.install(&ToolSpec::Npm(VersionSpec::exact(&npm)))?;
+            }
+            if let Some(ref yarn) = platform.yarn {
+                session.install(&ToolSpec::Yarn(VersionSpec::exact(yarn)))?;
+            }
+            if let Some(ref deno) = platform.deno {
+                session.install(&ToolSpec::Deno(VersionSpec::exact(deno)))?;
+            }
+        }
+
+        for (name, version) in &self.packages {
+            session.install(&ToolSpec::Package(
+                name.clone(),
+                VersionSpec::exact(version),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes this snapshot to a JSON string.
+    pub fn to_json(&self) -> Fallible<String> {
+        let serial = SerialSnapshot {
+            node: self.node.iter().map(|v| v.to_string()).collect(),
+            yarn: self.yarn.iter().map(|v| v.to_string()).collect(),
+            npm: self.npm.iter().map(|v| v.to_string()).collect(),
+            deno: self.deno.iter().map(|v| v.to_string()).collect(),
+            default: self.default.as_ref().map(|platform| platform.to_serial()),
+            packages: self
+                .packages
+                .iter()
+                .map(|(name, version)| (name.clone(), version.to_string()))
+                .collect(),
+            aliases: self
+                .aliases
+                .iter()
+                .map(|(tool, aliases)| {
+                    let aliases = aliases
+                        .iter()
+                        .map(|(name, version)| (name.clone(), version.to_string()))
+                        .collect();
+                    (tool.clone(), aliases)
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&serial).unknown()
+    }
+
+    /// Deserializes a snapshot from a JSON string.
+    pub fn from_json(src: &str) -> Fallible<Self> {
+        let serial: SerialSnapshot = serde_json::de::from_str(src).unknown()?;
+        Ok(Snapshot {
+            node: parse_versions(serial.node)?,
+            yarn: parse_versions(serial.yarn)?,
+            npm: parse_versions(serial.npm)?,
+            deno: parse_versions(serial.deno)?,
+            default: match serial.default {
+                Some(platform) => platform.into_image()?,
+                None => None,
+            },
+            packages: serial
+                .packages
+                .into_iter()
+                .map(|(name, version)| Ok((name, VersionSpec::parse_version(version)?)))
+                .collect::<Fallible<_>>()?,
+            aliases: serial
+                .aliases
+                .into_iter()
+                .map(|(tool, aliases)| {
+                    let aliases = aliases
+                        .into_iter()
+                        .map(|(name, version)| Ok((name, VersionSpec::parse(version)?)))
+                        .collect::<Fallible<_>>()?;
+                    Ok((tool, aliases))
+                })
+                .collect::<Fallible<_>>()?,
+        })
+    }
+}
+
+/// Reads the user's aliases, by the name of the tool and then of the alias.
+fn current_aliases() -> Fallible<BTreeMap<String, BTreeMap<String, VersionSpec>>> {
+    let mut aliases = BTreeMap::new();
+    for alias in Aliases::current()?.list() {
+        aliases
+            .entry(alias.tool.to_string())
+            .or_insert_with(BTreeMap::new)
+            .insert(alias.name.to_string(), VersionSpec::parse(alias.version)?);
+    }
+    Ok(aliases)
+}
+
+fn parse_versions(versions: Vec<String>) -> Fallible<Vec<Version>> {
+    versions.iter().map(VersionSpec::parse_version).collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::Snapshot;
+    use crate::platform::PlatformSpec;
+    use crate::version::VersionSpec;
+    use semver::Version;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_round_trip() {
+        let snapshot = Snapshot {
+            node: vec![
+                Version::parse("8.9.4").unwrap(),
+                Version::parse("10.13.0").unwrap(),
+            ],
+            yarn: vec![Version::parse("1.12.3").unwrap()],
+            npm: vec![Version::parse("6.5.0").unwrap()],
+            deno: vec![Version::parse("1.0.0").unwrap()],
+            default: Some(PlatformSpec {
+                node_runtime: Version::parse("10.13.0").unwrap(),
+                npm: Some(Version::parse("6.5.0").unwrap()),
+                yarn: None,
+                deno: Some(Version::parse("1.0.0").unwrap()),
+            }),
+            packages: vec![("typescript".to_string(), Version::parse("3.1.6").unwrap())]
+                .into_iter()
+                .collect::<BTreeMap<_, _>>(),
+            aliases: vec![(
+                "node".to_string(),
+                vec![("backend".to_string(), VersionSpec::parse("^10.13").unwrap())]
+                    .into_iter()
+                    .collect::<BTreeMap<_, _>>(),
+            )]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>(),
+        };
+
+        let json = snapshot.to_json().expect("could not serialize snapshot");
+        let parsed = Snapshot::from_json(&json).expect("could not parse snapshot");
+        assert_eq!(parsed, snapshot);
+    }
+
+    #[test]
+    fn test_from_json_empty() {
+        let parsed = Snapshot::from_json("{}").expect("could not parse snapshot");
+        assert_eq!(parsed.node.len(), 0);
+        assert_eq!(parsed.yarn.len(), 0);
+        assert_eq!(parsed.npm.len(), 0);
+        assert_eq!(parsed.deno.len(), 0);
+        assert_eq!(parsed.default, None);
+        assert!(parsed.packages.is_empty());
+        assert!(parsed.aliases.is_empty());
+    }
+}
@@ -1,13 +1,25 @@
 //! The view layer of Notion, with utilities for styling command-line output.
 
+use std::cell::{Cell, RefCell};
 use std::env;
 use std::fmt::{self, Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 
-use console::style;
+use console::{style, Term};
 use failure::Fail;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use serde_json;
 use term_size;
 
+use crate::config::Config;
+use crate::env::{is_ci, is_json_output, is_screen_reader, PROGRESS, PROGRESS_FD};
+use crate::output::{self, Verbosity};
+use notion_fail::NotionError;
+
 /// Represents the context from which an error is being reported.
 pub enum ErrorContext {
     /// An error reported from the `notion` executable.
@@ -17,8 +29,61 @@ pub enum ErrorContext {
     Shim,
 }
 
+/// An error in the `json` output mode (see `NOTION_OUTPUT`), written as a single line.
+#[derive(Serialize, Debug, PartialEq)]
+struct ErrorReport<'a> {
+    /// The stable identifier of the kind of error, such as `no-version-found`.
+    code: &'static str,
+    exit_code: i32,
+    /// The first paragraph of the message.
+    message: &'a str,
+    /// `notion` or `shim`.
+    context: &'static str,
+    /// The rest of the message, which usually suggests how to resolve the error.
+    suggestion: Option<&'a str>,
+}
+
+impl<'a> ErrorReport<'a> {
+    fn new(cx: ErrorContext, err: &NotionError, text: &'a str) -> Self {
+        let (message, suggestion) = split_message(text);
+        ErrorReport {
+            code: err.error_code(),
+            exit_code: err.exit_code() as i32,
+            message,
+            context: match cx {
+                ErrorContext::Notion => "notion",
+                ErrorContext::Shim => "shim",
+            },
+            suggestion,
+        }
+    }
+
+    fn display(&self) {
+        eprintln!("{}", serde_json::to_string(self).unwrap_or_default());
+    }
+}
+
+/// Separates the first paragraph of an error message from the rest, if any.
+fn split_message(text: &str) -> (&str, Option<&str>) {
+    match text.find("\n\n") {
+        Some(index) => {
+            let rest = text[index..].trim();
+            (
+                text[..index].trim(),
+                if rest.is_empty() { None } else { Some(rest) },
+            )
+        }
+        None => (text.trim(), None),
+    }
+}
+
 /// Displays an error to stderr.
-pub fn display_error<E: Display>(cx: ErrorContext, err: &E) {
+pub fn display_error(cx: ErrorContext, err: &NotionError) {
+    if is_json_output() {
+        let text = err.to_string();
+        ErrorReport::new(cx, err, &text).display();
+        return;
+    }
     display_error_prefix(cx);
     eprintln!("{}", err);
 }
@@ -41,17 +106,34 @@ pub fn display_error_prefix(cx: ErrorContext) {
     }
 }
 
+/// Displays a warning to stderr with a styled prefix, unless the output is quiet.
+pub fn display_warning<D: Display>(message: D) {
+    if output::enabled(Verbosity::Normal) {
+        eprintln!("{} {}", style("warning:").yellow().bold(), message);
+    }
+}
+
 /// Displays a generic message for internal errors to stderr.
-pub fn display_unknown_error<E: Fail>(cx: ErrorContext, err: &E) {
+pub fn display_unknown_error(cx: ErrorContext, err: &NotionError) {
+    if is_json_output() {
+        ErrorReport::new(
+            cx,
+            err,
+            "an internal error occurred\n\n\
+             Please file an issue at: https://github.com/notion-cli/notion/issues",
+        )
+        .display();
+        return;
+    }
     display_error_prefix(cx);
     eprintln!("an internal error occurred");
     eprintln!();
 
-    if env::var("NOTION_DEV").is_ok() {
+    if env::var("NOTION_DEV").is_ok() || output::enabled(Verbosity::Debug) {
         eprintln!("{} {:?}", style("details:").yellow().bold(), err);
         eprintln!();
 
-        let backtrace = err.backtrace();
+        let backtrace = Fail::backtrace(err);
 
         // For now, we require RUST_BACKTRACE for this to work.
         // See: https://github.com/notion-cli/notion/issues/75
@@ -81,65 +163,677 @@ pub fn display_unknown_error<E: Fail>(cx: ErrorContext, err: &E) {
 #[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
 pub enum Action {
     Fetching,
+    Installing,
+    Unpacking,
+    Verifying,
+    Pinning,
+    Removing,
+    Configuring,
+    Building,
 }
 
 impl Action {
     // this is the maximum width of the displayed Action strings, used for formatting
-    const MAX_WIDTH: usize = 10;
+    const MAX_WIDTH: usize = 11;
 }
 
 impl Display for Action {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         let s = match self {
             &Action::Fetching => "Fetching",
+            &Action::Installing => "Installing",
+            &Action::Unpacking => "Unpacking",
+            &Action::Verifying => "Verifying",
+            &Action::Pinning => "Pinning",
+            &Action::Removing => "Removing",
+            &Action::Configuring => "Configuring",
+            &Action::Building => "Building",
         };
         f.write_str(s)
     }
 }
 
+/// How the progress of long-running operations is displayed.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ProgressMode {
+    /// Animated progress bars and spinners.
+    Fancy,
+    /// Occasional single-line status updates, for logs that don't support animation.
+    Plain,
+    /// One JSON event per line, for GUI wrappers and IDE integrations that render their
+    /// own progress.
+    Json,
+    /// Nothing, when the standard error is redirected to a file or a pipe that isn't read
+    /// by a CI system.
+    Hidden,
+}
+
+impl ProgressMode {
+    /// Parses a progress mode name, as given to `--progress`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "fancy" => Some(ProgressMode::Fancy),
+            "plain" => Some(ProgressMode::Plain),
+            "json" => Some(ProgressMode::Json),
+            _ => None,
+        }
+    }
+
+    /// Returns the progress mode selected by the `NOTION_PROGRESS` environment variable.
+    /// Otherwise, the accessibility mode (enabled by `NOTION_SCREEN_READER` or by
+    /// `accessibility.screen_reader` in the configuration) and CI environments select
+    /// the plain mode, and progress is hidden unless the standard error is a terminal.
+    pub fn current() -> Self {
+        if let Some(mode) = env::var(PROGRESS)
+            .ok()
+            .and_then(|name| ProgressMode::parse(&name))
+        {
+            return mode;
+        }
+
+        let screen_reader = is_screen_reader()
+            || Config::current()
+                .map(|config| config.accessibility.screen_reader)
+                .unwrap_or(false);
+        if screen_reader || is_ci() {
+            ProgressMode::Plain
+        } else if !Term::stderr().is_term() {
+            ProgressMode::Hidden
+        } else {
+            ProgressMode::Fancy
+        }
+    }
+}
+
+/// How progress bars and spinners are drawn in the fancy mode.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProgressTheme {
+    /// The template of progress bars, where `{bar}` stands for the bar itself.
+    pub bar_template: String,
+    /// The template of spinners.
+    pub spinner_template: String,
+    /// The characters drawing the filled part, the head and the empty part of a bar.
+    pub progress_chars: String,
+    /// The frames of a spinner followed by its final character, if not the default ones.
+    pub tick_chars: Option<String>,
+    /// The colors of the bar (e.g. `cyan/blue`), if any.
+    pub bar_color: Option<String>,
+}
+
+impl ProgressTheme {
+    /// Returns the theme with the given name: `default`, `ascii` (for terminals that
+    /// can't render the Unicode spinner) or `minimal` (messages and percentages only).
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(ProgressTheme::default()),
+            "ascii" => Some(ProgressTheme {
+                tick_chars: Some("|/-\\ ".to_string()),
+                ..ProgressTheme::default()
+            }),
+            "minimal" => Some(ProgressTheme {
+                bar_template: "{msg} {percent:>3}%".to_string(),
+                spinner_template: "{msg}".to_string(),
+                bar_color: None,
+                ..ProgressTheme::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the theme selected in the user configuration.
+    fn current() -> Self {
+        Config::current()
+            .map(|config| config.progress.theme())
+            .unwrap_or_default()
+    }
+
+    /// Builds the indicatif template of a progress bar `width` characters wide.
+    fn bar_template(&self, width: usize) -> String {
+        let bar = match self.bar_color {
+            Some(ref color) => format!("{{bar:{}.{}}}", width, color),
+            None => format!("{{bar:{}}}", width),
+        };
+        self.bar_template.replace("{bar}", &bar)
+    }
+}
+
+impl Default for ProgressTheme {
+    fn default() -> Self {
+        ProgressTheme {
+            bar_template: "{msg}  [{bar}] {percent:>3}%".to_string(),
+            spinner_template: "{spinner} {msg}".to_string(),
+            progress_chars: "=> ".to_string(),
+            tick_chars: None,
+            bar_color: Some("cyan/blue".to_string()),
+        }
+    }
+}
+
+/// The progress indicator of a long-running operation.
+pub struct Progress {
+    inner: ProgressInner,
+}
+
+enum ProgressInner {
+    /// Nothing is displayed, at the quiet verbosity.
+    Hidden,
+    /// An animated bar or spinner, with the message it was started with.
+    Fancy(ProgressBar, String),
+    Plain(RefCell<PlainProgress>),
+    Json(RefCell<JsonProgress>),
+}
+
+/// The state of a progress indicator in plain mode.
+struct PlainProgress {
+    message: String,
+    len: Option<u64>,
+    position: u64,
+    /// The last percentage that was reported, in multiples of `PLAIN_STEP`.
+    reported: u64,
+    started: Instant,
+}
+
+/// The interval, in percent, between plain progress updates.
+const PLAIN_STEP: u64 = 20;
+
+impl PlainProgress {
+    fn start(message: String, len: Option<u64>) -> Self {
+        eprintln!("{}\u{2026}", message);
+        PlainProgress {
+            message,
+            len,
+            position: 0,
+            reported: 0,
+            started: Instant::now(),
+        }
+    }
+
+    fn inc(&mut self, delta: u64) {
+        self.position += delta;
+        if let Some(len) = self.len.filter(|&len| len > 0) {
+            let percent = ::std::cmp::min(self.position * 100 / len, 100);
+            let step = percent - percent % PLAIN_STEP;
+            if step > self.reported && step < 100 {
+                self.reported = step;
+                eprintln!("{}\u{2026} {}%", self.message, step);
+            }
+        }
+    }
+
+    fn abandon(&self) {
+        eprintln!("{}\u{2026} failed", self.message);
+    }
+
+    fn retrying(&mut self, retry: u32, attempts: u32) {
+        eprintln!("{}\u{2026} retry {}/{}", self.message, retry, attempts);
+        self.position = 0;
+        self.reported = 0;
+    }
+
+    fn finish(&self) {
+        let elapsed = self.started.elapsed();
+        eprintln!(
+            "{}\u{2026} done in {:.1}s",
+            self.message,
+            elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1000.0
+        );
+    }
+}
+
+/// An event of the `json` progress mode, written as a single line.
+#[derive(Serialize, Debug, PartialEq)]
+struct ProgressEvent<'a> {
+    /// `start`, `progress`, `retry`, `finish` or `abandon`.
+    event: &'static str,
+    /// Distinguishes the events of operations running at the same time.
+    id: usize,
+    /// What the operation is doing, e.g. `Fetching v10.15.0`.
+    phase: &'a str,
+    /// The bytes (or other steps) processed so far, for operations with a known length.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percent: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_ms: Option<u64>,
+}
+
+impl<'a> ProgressEvent<'a> {
+    fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self).unwrap_or_default();
+        line.push('\n');
+        line
+    }
+}
+
+/// Writes a line of the `json` progress mode to the file descriptor designated by
+/// `NOTION_PROGRESS_FD`, or to the standard error.
+fn emit(line: &str) {
+    if cfg!(unix) {
+        if let Ok(fd) = env::var(PROGRESS_FD) {
+            if let Ok(mut file) = OpenOptions::new()
+                .append(true)
+                .open(format!("/dev/fd/{}", fd.trim()))
+            {
+                let _ = file.write_all(line.as_bytes());
+                return;
+            }
+        }
+    }
+    let _ = io::stderr().write_all(line.as_bytes());
+}
+
+/// The source of the `id`s of progress events.
+static NEXT_PROGRESS_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// The state of a progress indicator in `json` mode.
+struct JsonProgress {
+    id: usize,
+    phase: String,
+    len: Option<u64>,
+    position: u64,
+    /// The last percentage that was reported.
+    reported: Option<u64>,
+    started: Instant,
+}
+
+impl JsonProgress {
+    fn start(phase: String, len: Option<u64>) -> Self {
+        let progress = JsonProgress {
+            id: NEXT_PROGRESS_ID.fetch_add(1, Ordering::SeqCst),
+            phase,
+            len,
+            position: 0,
+            reported: None,
+            started: Instant::now(),
+        };
+        emit(&progress.event("start").to_line());
+        progress
+    }
+
+    fn event(&self, event: &'static str) -> ProgressEvent<'_> {
+        ProgressEvent {
+            event,
+            id: self.id,
+            phase: &self.phase,
+            bytes: self.len.map(|_| self.position),
+            total: self.len,
+            percent: self.percent(),
+            elapsed_ms: None,
+        }
+    }
+
+    fn percent(&self) -> Option<u64> {
+        self.len
+            .filter(|&len| len > 0)
+            .map(|len| ::std::cmp::min(self.position * 100 / len, 100))
+    }
+
+    fn inc(&mut self, delta: u64) {
+        self.position += delta;
+        // reported once per percent, so that large downloads don't flood the stream
+        let percent = self.percent();
+        if percent.is_some() && percent != self.reported {
+            self.reported = percent;
+            emit(&self.event("progress").to_line());
+        }
+    }
+
+    fn retrying(&mut self, retry: u32, attempts: u32) {
+        self.position = 0;
+        self.reported = None;
+        let phase = format!("{} (retry {}/{})", self.phase, retry, attempts);
+        emit(
+            &ProgressEvent {
+                phase: &phase,
+                ..self.event("retry")
+            }
+            .to_line(),
+        );
+    }
+
+    fn finish(&self) {
+        let elapsed = self.started.elapsed();
+        emit(
+            &ProgressEvent {
+                elapsed_ms: Some(elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis())),
+                ..self.event("finish")
+            }
+            .to_line(),
+        );
+    }
+}
+
+impl Progress {
+    fn plain(message: String, len: Option<u64>) -> Self {
+        Progress {
+            inner: ProgressInner::Plain(RefCell::new(PlainProgress::start(message, len))),
+        }
+    }
+
+    fn json(phase: String, len: Option<u64>) -> Self {
+        Progress {
+            inner: ProgressInner::Json(RefCell::new(JsonProgress::start(phase, len))),
+        }
+    }
+
+    /// Sets the number of steps of the operation, once it is known.
+    pub fn set_length(&self, len: u64) {
+        match self.inner {
+            ProgressInner::Hidden => {}
+            ProgressInner::Fancy(ref bar, _) => bar.set_length(len),
+            ProgressInner::Plain(ref plain) => plain.borrow_mut().len = Some(len),
+            ProgressInner::Json(ref json) => json.borrow_mut().len = Some(len),
+        }
+    }
+
+    /// Advances the progress by `delta` steps.
+    pub fn inc(&self, delta: u64) {
+        match self.inner {
+            ProgressInner::Hidden => {}
+            ProgressInner::Fancy(ref bar, _) => bar.inc(delta),
+            ProgressInner::Plain(ref plain) => plain.borrow_mut().inc(delta),
+            ProgressInner::Json(ref json) => json.borrow_mut().inc(delta),
+        }
+    }
+
+    /// Marks the operation as complete, clearing a progress bar or spinner from the
+    /// terminal.
+    pub fn finish(&self) {
+        match self.inner {
+            ProgressInner::Hidden => {}
+            ProgressInner::Fancy(ref bar, _) => bar.finish_and_clear(),
+            ProgressInner::Plain(ref plain) => plain.borrow().finish(),
+            ProgressInner::Json(ref json) => json.borrow().finish(),
+        }
+    }
+
+    /// Marks the operation as failed, clearing a progress bar or spinner from the
+    /// terminal.
+    pub fn abandon(&self) {
+        match self.inner {
+            ProgressInner::Hidden => {}
+            ProgressInner::Fancy(ref bar, _) => bar.finish_and_clear(),
+            ProgressInner::Plain(ref plain) => plain.borrow().abandon(),
+            ProgressInner::Json(ref json) => emit(&json.borrow().event("abandon").to_line()),
+        }
+    }
+
+    /// Reports that the operation failed and is being attempted again, as attempt
+    /// `retry` of `attempts` (e.g., `Fetching v14.17.0 (retry 2/3)`), starting over from
+    /// no progress.
+    pub fn retrying(&self, retry: u32, attempts: u32) {
+        match self.inner {
+            ProgressInner::Hidden => {}
+            ProgressInner::Fancy(ref bar, ref message) => {
+                bar.set_position(0);
+                bar.set_message(&format!("{} (retry {}/{})", message, retry, attempts));
+            }
+            ProgressInner::Plain(ref plain) => plain.borrow_mut().retrying(retry, attempts),
+            ProgressInner::Json(ref json) => json.borrow_mut().retrying(retry, attempts),
+        }
+    }
+}
+
 /// Constructs a command-line progress bar with the specified Action enum
 /// (e.g., `Action::Installing`), details string (e.g., `"v1.23.4"`), and logical
 /// length (i.e., the number of logical progress steps in the process being
 /// visualized by the progress bar).
-pub fn progress_bar(action: Action, details: &str, len: u64) -> ProgressBar {
+pub fn progress_bar(action: Action, details: &str, len: u64) -> Progress {
+    step_bar(None, action, details, len)
+}
+
+/// Constructs a progress bar, as with `progress_bar`, for the step of a sequence
+/// described by `step` (e.g., `"[2/4]"`), if any.
+fn step_bar(step: Option<&str>, action: Action, details: &str, len: u64) -> Progress {
+    if !output::enabled(Verbosity::Normal) {
+        return Progress {
+            inner: ProgressInner::Hidden,
+        };
+    }
+
+    let prefix = step.map(|step| format!("{} ", step)).unwrap_or_default();
+    let message = format!("{}{} {}", prefix, action, details);
+    match ProgressMode::current() {
+        ProgressMode::Plain => Progress::plain(message, Some(len)),
+        ProgressMode::Json => Progress::json(message, Some(len)),
+        ProgressMode::Fancy => Progress {
+            inner: ProgressInner::Fancy(
+                fancy_bar(step, action, details, len),
+                fancy_message(step, action, details),
+            ),
+        },
+        ProgressMode::Hidden => Progress {
+            inner: ProgressInner::Hidden,
+        },
+    }
+}
+
+/// Constructs the animated progress bar of the fancy mode.
+fn fancy_bar(step: Option<&str>, action: Action, details: &str, len: u64) -> ProgressBar {
     let display_width = term_size::dimensions().map(|(w, _)| w).unwrap_or(80);
-    let msg_width = Action::MAX_WIDTH + 1 + details.len();
+    let prefix_width = step.map(|step| step.len() + 1).unwrap_or(0);
+    let msg_width = prefix_width + Action::MAX_WIDTH + 1 + details.len();
 
-    //   Installing v1.23.4  [====================>                   ]  50%
-    // |----------| |-----|   |--------------------------------------|  |-|
-    //    action    details                      bar                 percentage
-    let available_width = display_width - 2 - msg_width - 2 - 2 - 1 - 3 - 1;
+    //   [2/4]  Unpacking v1.23.4  [====================>                   ]  50%
+    // |-----| |---------| |-----|  |--------------------------------------|  |-|
+    //   step    action    details                      bar                percentage
+    let available_width = display_width.saturating_sub(2 + msg_width + 2 + 2 + 1 + 3 + 1);
     let bar_width = ::std::cmp::min(available_width, 40);
 
     let bar = ProgressBar::new(len);
+    bar.set_message(&fancy_message(step, action, details));
+    let theme = ProgressTheme::current();
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template(&theme.bar_template(bar_width))
+            .progress_chars(&theme.progress_chars),
+    );
+    bar
+}
 
-    bar.set_message(&format!(
-        "{: >width$} {}",
+/// The message of a fancy progress bar, e.g. `[2/4] Unpacking v1.23.4`.
+fn fancy_message(step: Option<&str>, action: Action, details: &str) -> String {
+    let prefix = step
+        .map(|step| format!("{} ", style(step).dim()))
+        .unwrap_or_default();
+    format!(
+        "{}{: >width$} {}",
+        prefix,
         style(action.to_string()).green().bold(),
         details,
         width = Action::MAX_WIDTH
-    ));
-    bar.set_style(
-        ProgressStyle::default_bar()
-            .template(&format!(
-                "{{msg}}  [{{bar:{}.cyan/blue}}] {{percent:>3}}%",
-                bar_width
-            ))
-            .progress_chars("=> "),
-    );
+    )
+}
 
-    bar
+/// A group of progress bars for operations running at the same time, such as the
+/// concurrent downloads of several archives, drawn together under a shared header.
+pub struct ProgressGroup {
+    inner: GroupInner,
+}
+
+enum GroupInner {
+    Hidden,
+    Fancy(MultiProgress),
+    Plain,
+    Json,
+}
+
+impl ProgressGroup {
+    /// Displays the `header` of a new group of progress bars.
+    pub fn new(header: &str) -> Self {
+        let inner = if !output::enabled(Verbosity::Normal) {
+            GroupInner::Hidden
+        } else {
+            match ProgressMode::current() {
+                ProgressMode::Fancy => GroupInner::Fancy(MultiProgress::new()),
+                ProgressMode::Plain => GroupInner::Plain,
+                ProgressMode::Json => GroupInner::Json,
+                ProgressMode::Hidden => GroupInner::Hidden,
+            }
+        };
+        match inner {
+            GroupInner::Fancy(_) => eprintln!("{}", style(header).bold()),
+            GroupInner::Plain => eprintln!("{}", header),
+            GroupInner::Hidden | GroupInner::Json => {}
+        }
+        ProgressGroup { inner }
+    }
+
+    /// Adds a progress bar to the group, as with `progress_bar`. The bar can be advanced
+    /// from another thread.
+    pub fn add(&self, action: Action, details: &str, len: u64) -> Progress {
+        match self.inner {
+            GroupInner::Hidden => Progress {
+                inner: ProgressInner::Hidden,
+            },
+            GroupInner::Fancy(ref multi) => Progress {
+                inner: ProgressInner::Fancy(
+                    multi.add(fancy_bar(None, action, details, len)),
+                    fancy_message(None, action, details),
+                ),
+            },
+            GroupInner::Plain => Progress::plain(format!("{} {}", action, details), Some(len)),
+            GroupInner::Json => Progress::json(format!("{} {}", action, details), Some(len)),
+        }
+    }
+
+    /// Draws the progress bars until every one of them is finished or abandoned. In the
+    /// modes without animation, the progress is reported as it happens instead.
+    pub fn join(self) {
+        if let GroupInner::Fancy(multi) = self.inner {
+            let _ = multi.join_and_clear();
+        }
+    }
 }
 
 /// Constructs a command-line progress spinner with the specified "message"
 /// string. The spinner is ticked by default every 20ms.
-pub fn progress_spinner(message: &str) -> ProgressBar {
+pub fn progress_spinner(message: &str) -> Progress {
+    if !output::enabled(Verbosity::Normal) {
+        return Progress {
+            inner: ProgressInner::Hidden,
+        };
+    }
+
+    match ProgressMode::current() {
+        ProgressMode::Plain => return Progress::plain(message.to_string(), None),
+        ProgressMode::Json => return Progress::json(message.to_string(), None),
+        ProgressMode::Hidden => {
+            return Progress {
+                inner: ProgressInner::Hidden,
+            }
+        }
+        ProgressMode::Fancy => {}
+    }
+
     // ⠋ Fetching public registry: https://nodejs.org/dist/index.json
     let spinner = ProgressBar::new_spinner();
 
     spinner.set_message(message);
-    spinner.set_style(ProgressStyle::default_spinner().template("{spinner} {msg}"));
+    let theme = ProgressTheme::current();
+    let mut spinner_style = ProgressStyle::default_spinner().template(&theme.spinner_template);
+    if let Some(ref chars) = theme.tick_chars {
+        spinner_style = spinner_style.tick_chars(chars);
+    }
+    spinner.set_style(spinner_style);
     spinner.enable_steady_tick(20); // tick the spinner every 20ms
 
-    spinner
+    Progress {
+        inner: ProgressInner::Fancy(spinner, message.to_string()),
+    }
+}
+
+/// The steps of an operation made of several phases, each shown with its place in the
+/// sequence (e.g., `[2/4] Unpacking v14.17.0`).
+pub struct Steps {
+    total: usize,
+    started: Cell<usize>,
+}
+
+impl Steps {
+    /// Begins a sequence of `total` steps.
+    pub fn new(total: usize) -> Self {
+        Steps {
+            total,
+            started: Cell::new(0),
+        }
+    }
+
+    /// Describes the next step, e.g. `[2/4]`.
+    fn next(&self) -> String {
+        let step = self.started.get() + 1;
+        self.started.set(step);
+        format!("[{}/{}]", step, self.total)
+    }
+
+    /// Starts the next step, with a progress bar as with `progress_bar`.
+    pub fn bar(&self, action: Action, details: &str, len: u64) -> Progress {
+        step_bar(Some(&self.next()), action, details, len)
+    }
+
+    /// Starts the next step, with a spinner as with `progress_spinner`.
+    pub fn spinner(&self, action: Action, details: &str) -> Progress {
+        progress_spinner(&format!("{} {} {}", self.next(), action, details))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{split_message, ProgressEvent, Steps};
+
+    #[test]
+    fn test_progress_event_line() {
+        let event = ProgressEvent {
+            event: "progress",
+            id: 3,
+            phase: "Fetching v10.15.0",
+            bytes: Some(512),
+            total: Some(1024),
+            percent: Some(50),
+            elapsed_ms: None,
+        };
+        assert_eq!(
+            event.to_line(),
+            "{\"event\":\"progress\",\"id\":3,\"phase\":\"Fetching v10.15.0\",\
+             \"bytes\":512,\"total\":1024,\"percent\":50}\n"
+        );
+
+        let event = ProgressEvent {
+            event: "start",
+            id: 4,
+            phase: "Building Node v10.15.0",
+            bytes: None,
+            total: None,
+            percent: None,
+            elapsed_ms: None,
+        };
+        assert_eq!(
+            event.to_line(),
+            "{\"event\":\"start\",\"id\":4,\"phase\":\"Building Node v10.15.0\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_steps() {
+        let steps = Steps::new(3);
+        assert_eq!(steps.next(), "[1/3]");
+        assert_eq!(steps.next(), "[2/3]");
+        assert_eq!(steps.next(), "[3/3]");
+    }
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(
+            split_message("Could not find Node version 99\n\nTry `notion list`.\n"),
+            ("Could not find Node version 99", Some("Try `notion list`."))
+        );
+        assert_eq!(
+            split_message("Invalid proxy URL: http://"),
+            ("Invalid proxy URL: http://", None)
+        );
+    }
 }
@@ -1,11 +1,14 @@
 use std::env::{args_os, ArgsOs};
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
 use std::process::Command;
 
 use failure::Fail;
 
-use super::{arg0, command_for, NoSuchToolError, Tool};
+use super::{arg0, command_for, launcher, NoSuchToolError, Tool};
+use crate::package::BinConfig;
 use crate::path;
+use crate::platform::Image;
 use crate::session::{ActivityKind, Session};
 use notion_fail::{throw, ExitCode, Fallible, NotionFail};
 use notion_fail_derive::*;
@@ -19,33 +22,24 @@ impl Tool for Binary {
 
         let mut args = args_os();
         let exe = arg0(&mut args)?;
+        let name = bin_name(&exe);
 
         // first try to use the project toolchain
         if let Some(project) = session.project()? {
             // check if the executable is a direct dependency
-            if project.has_direct_bin(&exe)? {
-                // use the full path to the file
-                let mut path_to_bin = project.local_bin_dir();
-                path_to_bin.push(&exe);
+            if project.has_direct_bin(&name)? {
+                let bin_dir = project.local_bin_dir();
 
                 // if we're in a pinned project, use the project's platform.
                 if let Some(ref platform) = session.project_platform()? {
                     let image = platform.checkout(session)?;
-                    return Ok(Self::from_components(
-                        &path_to_bin.as_os_str(),
-                        args,
-                        &image.path()?,
-                    ));
+                    return Self::from_bin_dir(&bin_dir, &exe, args, &image);
                 }
 
                 // otherwise use the user platform.
                 if let Some(ref platform) = session.user_platform()? {
                     let image = platform.checkout(session)?;
-                    return Ok(Self::from_components(
-                        &path_to_bin.as_os_str(),
-                        args,
-                        &image.path()?,
-                    ));
+                    return Self::from_bin_dir(&bin_dir, &exe, args, &image);
                 }
 
                 // if there's no user platform selected, fail.
@@ -55,20 +49,30 @@ impl Tool for Binary {
             }
         }
 
+        // next try the binaries of the installed packages, which always run with the
+        // platform they were installed with, whatever the project pins
+        if let Some(bin) = BinConfig::current(&name.to_string_lossy())? {
+            let image = bin.platform.checkout(session)?;
+            return Self::from_bin_dir(&bin.bin_dir()?, &exe, args, &image);
+        }
+
         // next try to use the user toolchain
         if let Some(ref platform) = session.user_platform()? {
             // use the full path to the binary
-            // ISSUE (#160): Look up the platform image bound to the user tool.
             let image = platform.checkout(session)?;
             let node_str = image.node.runtime.to_string();
             let npm_str = image.node.npm.to_string();
-            let mut third_p_bin_dir = path::node_image_3p_bin_dir(&node_str, &npm_str)?;
-            third_p_bin_dir.push(&exe);
-            return Ok(Self::from_components(
-                &third_p_bin_dir.as_os_str(),
-                args,
-                &image.path()?,
-            ));
+
+            // prefer binaries from the per-version global prefix, falling back to the image
+            let global_bin_dir = path::node_global_bin_dir(&node_str)?;
+            let bin_dir = if global_bin_dir.join(&exe).exists()
+                || launcher::find(&global_bin_dir, &name).is_some()
+            {
+                global_bin_dir
+            } else {
+                path::node_image_3p_bin_dir(&node_str, &npm_str)?
+            };
+            return Self::from_bin_dir(&bin_dir, &exe, args, &image);
         };
 
         // at this point, there is no project or user toolchain
@@ -87,6 +91,49 @@ impl Tool for Binary {
     }
 }
 
+impl Binary {
+    /// Constructs the command for the executable `exe` in `bin_dir`, using the platform
+    /// `image`. Binaries exposed through npm's script launchers (as on Windows) are run
+    /// by passing the launcher's script to the image's Node directly, since the launcher
+    /// would otherwise run it with the first `node` it finds.
+    fn from_bin_dir(bin_dir: &Path, exe: &OsStr, args: ArgsOs, image: &Image) -> Fallible<Self> {
+        let path_var = image.path()?;
+
+        if let Some(launcher) = launcher::find(bin_dir, &bin_name(exe)) {
+            if let Some(script) = launcher::script_for(&launcher)? {
+                let node_str = image.node.runtime.to_string();
+                let npm_str = image.node.npm.to_string();
+                let node_exe = if cfg!(windows) { "node.exe" } else { "node" };
+                let node = path::node_image_bin_dir(&node_str, &npm_str)?.join(node_exe);
+
+                let mut command = Command::new(node);
+                command.arg(script);
+                command.args(args);
+                command.env("PATH", &path_var);
+                return Ok(Binary(command));
+            }
+        }
+
+        Ok(Self::from_components(
+            bin_dir.join(exe).as_os_str(),
+            args,
+            &path_var,
+        ))
+    }
+}
+
+/// Determines the name of the binary a shim stands in for, which is the name of the
+/// shim without the `.exe` extension it has on Windows.
+fn bin_name(exe: &OsStr) -> OsString {
+    let path = Path::new(exe);
+    match (path.extension(), path.file_stem()) {
+        (Some(ext), Some(stem)) if ext.to_string_lossy().eq_ignore_ascii_case("exe") => {
+            stem.to_os_string()
+        }
+        _ => exe.to_os_string(),
+    }
+}
+
 #[derive(Debug, Fail, NotionFail)]
 #[fail(display = "No toolchain available to run shim {}", shim_name)]
 #[notion_fail(code = "ExecutionFailure")]
@@ -0,0 +1,31 @@
+use std::env::{args_os, ArgsOs};
+use std::ffi::OsStr;
+use std::process::Command;
+
+use super::{arg0, command_for, Tool};
+use crate::session::{ActivityKind, Session};
+
+use notion_fail::Fallible;
+
+/// Represents a Deno executable.
+pub struct Deno(Command);
+
+impl Tool for Deno {
+    fn new(session: &mut Session) -> Fallible<Self> {
+        session.add_event_start(ActivityKind::Deno);
+
+        let mut args = args_os();
+        let exe = arg0(&mut args)?;
+
+        let (image, _) = session.checkout_tool("deno")?;
+        Ok(Self::from_components(&exe, args, &image.path()?))
+    }
+
+    fn from_components(exe: &OsStr, args: ArgsOs, path_var: &OsStr) -> Self {
+        Deno(command_for(exe, args, path_var))
+    }
+
+    fn command(self) -> Command {
+        self.0
+    }
+}
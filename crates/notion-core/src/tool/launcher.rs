@@ -0,0 +1,167 @@
+//! Provides support for the script launchers npm generates for package binaries.
+//!
+//! On Windows, npm doesn't link a package's executables to its JavaScript entry
+//! points; instead it writes `.cmd` and `.ps1` launchers that run the script with
+//! whichever `node` they find first. To guarantee that these binaries run with the
+//! Node version selected by Notion, shims read the script out of the launcher and
+//! run it with that Node directly.
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use notion_fail::{Fallible, ResultExt};
+
+/// The extensions of the launchers generated by npm, in order of preference.
+const LAUNCHER_EXTENSIONS: [&'static str; 2] = ["cmd", "ps1"];
+
+/// The variables that launchers use to refer to their own directory.
+const BASEDIR_PREFIXES: [&'static str; 4] = ["%~dp0\\", "%dp0%\\", "$basedir/", "$basedir\\"];
+
+/// Finds the launcher for the binary `name` in `dir`, if there is one.
+pub(crate) fn find(dir: &Path, name: &OsStr) -> Option<PathBuf> {
+    LAUNCHER_EXTENSIONS
+        .iter()
+        .map(|ext| {
+            let mut file_name = OsString::from(name);
+            file_name.push(".");
+            file_name.push(ext);
+            dir.join(file_name)
+        })
+        .find(|path| path.is_file())
+}
+
+/// Reads the launcher at `launcher` and determines the location of the script it runs.
+pub(crate) fn script_for(launcher: &Path) -> Fallible<Option<PathBuf>> {
+    let contents = fs::read_to_string(launcher).unknown()?;
+    let base_dir = launcher.parent().unwrap_or_else(|| Path::new(""));
+
+    Ok(parse_script(&contents).map(|components| {
+        components
+            .iter()
+            .fold(base_dir.to_path_buf(), |path, component| path.join(component))
+    }))
+}
+
+/// Extracts the path, relative to the launcher's directory, of the script a launcher
+/// runs. Launchers quote every path they refer to, and the first path relative to the
+/// launcher's directory that isn't the bundled `node` is the script.
+fn parse_script(contents: &str) -> Option<Vec<String>> {
+    contents
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .filter_map(|quoted| {
+            BASEDIR_PREFIXES
+                .iter()
+                .find(|prefix| quoted.starts_with(*prefix))
+                .map(|prefix| &quoted[prefix.len()..])
+        })
+        .find(|relative| !is_node(relative))
+        .map(|relative| {
+            relative
+                .split(|c| c == '\\' || c == '/')
+                .filter(|component| !component.is_empty())
+                .map(|component| component.to_string())
+                .collect()
+        })
+}
+
+fn is_node(relative: &str) -> bool {
+    match relative {
+        "node" | "node.exe" | "node$exe" => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::parse_script;
+
+    #[test]
+    fn test_parse_script_cmd() {
+        let contents = r#"@ECHO off
+GOTO start
+:find_dp0
+SET dp0=%~dp0
+EXIT /b
+:start
+SETLOCAL
+CALL :find_dp0
+
+IF EXIST "%dp0%\node.exe" (
+  SET "_prog=%dp0%\node.exe"
+) ELSE (
+  SET "_prog=node"
+  SET PATHEXT=%PATHEXT:;.JS;=;%
+)
+
+endLocal & goto #_undefined_# 2>NUL || title %COMSPEC% & "%_prog%"  "%dp0%\node_modules\typescript\bin\tsc" %*
+"#;
+        assert_eq!(
+            parse_script(contents),
+            Some(vec![
+                "node_modules".to_string(),
+                "typescript".to_string(),
+                "bin".to_string(),
+                "tsc".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_script_legacy_cmd() {
+        let contents = r#"@IF EXIST "%~dp0\node.exe" (
+  "%~dp0\node.exe"  "%~dp0\..\typescript\bin\tsc" %*
+) ELSE (
+  @SETLOCAL
+  @SET PATHEXT=%PATHEXT:;.JS;=;%
+  node  "%~dp0\..\typescript\bin\tsc" %*
+)"#;
+        assert_eq!(
+            parse_script(contents),
+            Some(vec![
+                "..".to_string(),
+                "typescript".to_string(),
+                "bin".to_string(),
+                "tsc".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_script_ps1() {
+        let contents = r#"#!/usr/bin/env pwsh
+$basedir=Split-Path $MyInvocation.MyCommand.Definition -Parent
+
+$exe=""
+if ($PSVersionTable.PSVersion -lt "6.0" -or $IsWindows) {
+  $exe=".exe"
+}
+$ret=0
+if (Test-Path "$basedir/node$exe") {
+  & "$basedir/node$exe"  "$basedir/node_modules/typescript/bin/tsc" $args
+  $ret=$LASTEXITCODE
+} else {
+  & "node$exe"  "$basedir/node_modules/typescript/bin/tsc" $args
+  $ret=$LASTEXITCODE
+}
+exit $ret
+"#;
+        assert_eq!(
+            parse_script(contents),
+            Some(vec![
+                "node_modules".to_string(),
+                "typescript".to_string(),
+                "bin".to_string(),
+                "tsc".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_script_unrecognized() {
+        assert_eq!(parse_script("@ECHO off\nnode %*\n"), None);
+    }
+}
@@ -10,7 +10,10 @@ use std::process::{Command, ExitStatus};
 
 use failure::Fail;
 
-use crate::env::UNSAFE_GLOBAL;
+use crate::conflict;
+use crate::env::{is_quiet, UNSAFE_GLOBAL};
+use crate::interrupt;
+use crate::output;
 use crate::session::{ActivityKind, Session};
 use crate::style;
 use crate::version::VersionSpec;
@@ -18,6 +21,8 @@ use notion_fail::{ExitCode, FailExt, Fallible, NotionError, NotionFail};
 use notion_fail_derive::*;
 
 mod binary;
+mod deno;
+mod launcher;
 mod node;
 mod npm;
 mod npx;
@@ -25,6 +30,7 @@ mod script;
 mod yarn;
 
 pub use self::binary::Binary;
+pub use self::deno::Deno;
 pub use self::node::Node;
 pub use self::npm::Npm;
 pub use self::npx::Npx;
@@ -43,18 +49,70 @@ pub enum ToolSpec {
     Node(VersionSpec),
     Yarn(VersionSpec),
     Npm(VersionSpec),
+    Deno(VersionSpec),
     Package(String, VersionSpec),
 }
 
+/// The names of the tools that Notion manages itself, as opposed to packages.
+const TOOL_NAMES: [&'static str; 4] = ["node", "yarn", "npm", "deno"];
+
 impl ToolSpec {
     pub fn from_str(tool_name: &str, version: VersionSpec) -> Self {
         match tool_name {
             "node" => ToolSpec::Node(version),
             "yarn" => ToolSpec::Yarn(version),
             "npm" => ToolSpec::Npm(version),
+            "deno" => ToolSpec::Deno(version),
             package => ToolSpec::Package(package.to_string(), version),
         }
     }
+
+    /// Parses a tool name given on the command line. Unlike `from_str`, this warns about
+    /// names that look like a misspelling of one of Notion's tools, since they are
+    /// treated as package names, which many real packages (`pnpm`, `npx`) also are.
+    pub fn from_cli_str(tool_name: &str, version: VersionSpec) -> Fallible<Self> {
+        if let Some(suggestion) = suggest_tool_name(tool_name) {
+            style::display_warning(format!(
+                "`{}` is treated as a package. Did you mean `{}`?",
+                tool_name, suggestion
+            ));
+        }
+        Ok(ToolSpec::from_str(tool_name, version))
+    }
+}
+
+/// Finds the tool name that `name` is most likely a misspelling of, if any.
+fn suggest_tool_name(name: &str) -> Option<&'static str> {
+    if TOOL_NAMES.contains(&name) {
+        return None;
+    }
+
+    let lowercase = name.to_lowercase();
+    TOOL_NAMES
+        .iter()
+        .map(|tool| (*tool, edit_distance(&lowercase, tool)))
+        .filter(|&(_, distance)| distance <= 1)
+        .min_by_key(|&(_, distance)| distance)
+        .map(|(tool, _)| tool)
+}
+
+/// Computes the Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current.push(substitution.min(insertion).min(deletion));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
 }
 
 impl Debug for ToolSpec {
@@ -63,6 +121,7 @@ impl Debug for ToolSpec {
             &ToolSpec::Node(ref version) => format!("node version {}", version),
             &ToolSpec::Yarn(ref version) => format!("yarn version {}", version),
             &ToolSpec::Npm(ref version) => format!("npm version {}", version),
+            &ToolSpec::Deno(ref version) => format!("deno version {}", version),
             &ToolSpec::Package(ref name, ref version) => format!("{} version {}", name, version),
         };
         f.write_str(&s)
@@ -75,6 +134,7 @@ impl Display for ToolSpec {
             &ToolSpec::Node(ref version) => format!("node version {}", version),
             &ToolSpec::Yarn(ref version) => format!("yarn version {}", version),
             &ToolSpec::Npm(ref version) => format!("npm version {}", version),
+            &ToolSpec::Deno(ref version) => format!("deno version {}", version),
             &ToolSpec::Package(ref name, ref version) => format!("{} version {}", name, version),
         };
         f.write_str(&s)
@@ -108,9 +168,13 @@ pub trait Tool: Sized {
         let mut session = Session::new();
 
         session.add_event_start(ActivityKind::Tool);
+        // diagnostics are best-effort, so they never keep the tool from running
+        let _ = conflict::warn_if_debug();
 
         match Self::new(&mut session) {
             Ok(tool) => {
+                // the warning is only advice, so it never keeps the tool from running
+                let _ = session.warn_unsupported_node();
                 tool.exec(session);
             }
             Err(err) => {
@@ -136,7 +200,11 @@ pub trait Tool: Sized {
     /// Delegates the current process to this tool.
     fn exec(self, mut session: Session) -> ! {
         let mut command = self.command();
-        let status = command.status();
+        output::debug(format!("running {:?}", command));
+        let status = {
+            let _child = interrupt::defer_to_child();
+            command.status()
+        };
         Self::finalize(&session, &status);
         match status {
             Ok(status) if status.success() => {
@@ -150,10 +218,17 @@ pub trait Tool: Sized {
                 session.exit_tool(code);
             }
             Err(err) => {
+                // follow the shell conventions for an executable that is missing (127)
+                // or that could not be run (126)
+                let exit_code = if err.kind() == io::ErrorKind::NotFound {
+                    ExitCode::ExecutableNotFound
+                } else {
+                    ExitCode::ExecutionFailure
+                };
                 let notion_err = err.with_context(BinaryExecError::from_io_error);
                 display_error(&notion_err);
                 session.add_event_error(ActivityKind::Tool, &notion_err);
-                session.exit(ExitCode::ExecutionFailure);
+                session.exit(exit_code);
             }
         }
     }
@@ -194,8 +269,51 @@ See `notion help install` for help adding {} to your personal toolchain."#,
     tool, tool, tool
 )]
 #[notion_fail(code = "NoVersionMatch")]
-struct NoSuchToolError {
-    tool: String,
+pub(crate) struct NoSuchToolError {
+    pub(crate) tool: String,
+}
+
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "{}", message)]
+#[notion_fail(code = "NoVersionMatch")]
+struct NoDefaultNodeError {
+    message: String,
+}
+
+impl NoDefaultNodeError {
+    fn new() -> Self {
+        let message = if is_quiet() {
+            "No Node version selected: not in a project and no default version is set.".to_string()
+        } else {
+            r#"
+Node is not available yet: you are not inside a project and no default version is set.
+
+To get started, install a default version of Node:
+
+    notion install node latest
+
+Or pick a specific version to use by default:
+
+    notion install node <version>
+
+See `notion help pin` for help adding Node to a project toolchain."#
+                .to_string()
+        };
+        NoDefaultNodeError { message }
+    }
+}
+
+/// Produces the error a shim reports when no Node version is available. Outside of
+/// a project this is most likely a first run, so the user is guided to a default.
+pub(crate) fn no_node_error(session: &Session) -> Fallible<NotionError> {
+    if session.project()?.is_some() {
+        Ok(NoSuchToolError {
+            tool: "Node".to_string(),
+        }
+        .into())
+    } else {
+        Ok(NoDefaultNodeError::new().into())
+    }
 }
 
 #[derive(Debug, Fail, NotionFail)]
@@ -206,7 +324,109 @@ Consider using `notion install` to add a package to your toolchain (see `notion
 #[notion_fail(code = "InvalidArguments")]
 struct NoGlobalInstallError;
 
+/// The flags of npm and Yarn that take a value, which may be given as the next argument
+/// (as in `npm --prefix dir exec`) rather than as `--flag=value`.
+const VALUE_FLAGS: &[&str] = &[
+    "-C",
+    "-w",
+    "--cache",
+    "--cache-folder",
+    "--cwd",
+    "--global-folder",
+    "--globalconfig",
+    "--link-folder",
+    "--loglevel",
+    "--modules-folder",
+    "--mutex",
+    "--network-timeout",
+    "--prefix",
+    "--registry",
+    "--use-yarnrc",
+    "--userconfig",
+    "--workspace",
+];
+
+/// Returns the subcommand of the current package manager invocation.
+fn subcommand() -> Option<OsString> {
+    find_subcommand(env::args_os().skip(1))
+}
+
+/// Returns the first of `args` that is neither a command-line flag nor the value of one,
+/// i.e. the subcommand of a package manager invocation.
+fn find_subcommand(mut args: impl Iterator<Item = OsString>) -> Option<OsString> {
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some(flag) if VALUE_FLAGS.contains(&flag) => {
+                args.next();
+            }
+            Some(flag) if flag.starts_with("-") => {}
+            _ => return Some(arg),
+        }
+    }
+    None
+}
+
+/// Determines whether the current invocation is a one-off execution flow that
+/// downloads packages on demand, such as `npm exec` or `yarn dlx`.
+fn is_exec_command(names: &[&str]) -> bool {
+    match subcommand() {
+        Some(command) => names.iter().any(|name| command == OsString::from(name)),
+        None => false,
+    }
+}
+
 fn intercept_global_installs() -> bool {
     // We should only intercept global installs if the NOTION_UNSAFE_GLOBAL variable is not set
     env::var_os(UNSAFE_GLOBAL).is_none()
 }
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::{find_subcommand, suggest_tool_name, ToolSpec};
+    use crate::version::VersionSpec;
+    use std::ffi::OsString;
+
+    #[test]
+    fn test_suggest_tool_name() {
+        assert_eq!(suggest_tool_name("yarb"), Some("yarn"));
+        assert_eq!(suggest_tool_name("nod"), Some("node"));
+        assert_eq!(suggest_tool_name("Node"), Some("node"));
+        assert_eq!(suggest_tool_name("node"), None);
+        assert_eq!(suggest_tool_name("ember-cli"), None);
+    }
+
+    #[test]
+    fn test_from_cli_str_accepts_packages_like_tools() {
+        for name in &["pnpm", "npx", "demo"] {
+            match ToolSpec::from_cli_str(name, VersionSpec::Latest) {
+                Ok(ToolSpec::Package(ref package, _)) => assert_eq!(package, *name),
+                _ => panic!("`{}` should be a package", name),
+            }
+        }
+    }
+
+    fn subcommand_of(args: &[&str]) -> Option<OsString> {
+        find_subcommand(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn test_find_subcommand() {
+        assert_eq!(subcommand_of(&["exec", "cowsay"]), Some("exec".into()));
+        assert_eq!(subcommand_of(&["--silent", "exec"]), Some("exec".into()));
+        assert_eq!(
+            subcommand_of(&["--prefix", "foo", "exec"]),
+            Some("exec".into())
+        );
+        assert_eq!(
+            subcommand_of(&["--prefix=foo", "exec"]),
+            Some("exec".into())
+        );
+        assert_eq!(
+            subcommand_of(&["--cwd", "app", "dlx", "cowsay"]),
+            Some("dlx".into())
+        );
+        assert_eq!(subcommand_of(&["--prefix", "foo"]), None);
+        assert_eq!(subcommand_of(&[]), None);
+    }
+}
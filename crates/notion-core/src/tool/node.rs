@@ -2,10 +2,10 @@ use std::env::{args_os, ArgsOs};
 use std::ffi::OsStr;
 use std::process::Command;
 
-use super::{arg0, command_for, NoSuchToolError, Tool};
+use super::{arg0, command_for, Tool};
 use crate::session::{ActivityKind, Session};
 
-use notion_fail::{throw, Fallible};
+use notion_fail::Fallible;
 
 /// Represents a Node executable.
 pub struct Node(Command);
@@ -16,14 +16,8 @@ impl Tool for Node {
 
         let mut args = args_os();
         let exe = arg0(&mut args)?;
-        if let Some(ref platform) = session.current_platform()? {
-            let image = platform.checkout(session)?;
-            Ok(Self::from_components(&exe, args, &image.path()?))
-        } else {
-            throw!(NoSuchToolError {
-                tool: "Node".to_string()
-            });
-        }
+        let (image, _) = session.checkout_tool("node")?;
+        Ok(Self::from_components(&exe, args, &image.path()?))
     }
 
     fn from_components(exe: &OsStr, args: ArgsOs, path_var: &OsStr) -> Self {
@@ -4,9 +4,10 @@ use std::io;
 use std::process::{Command, ExitStatus};
 
 use super::{
-    arg0, command_for, display_error, intercept_global_installs, NoGlobalInstallError,
-    NoSuchToolError, Tool,
+    arg0, command_for, display_error, intercept_global_installs, is_exec_command,
+    NoGlobalInstallError, Tool,
 };
+use crate::env::{GLOBAL_PREFIX, NPM_CACHE};
 use crate::session::{ActivityKind, Session};
 
 use notion_fail::{throw, Fallible};
@@ -25,16 +26,13 @@ impl Tool for Npm {
             throw!(NoGlobalInstallError);
         }
 
-        if let Some(ref platform) = session.current_platform()? {
-            let image = platform.checkout(session)?;
-            Ok(Self::from_components(&exe, args, &image.path()?))
-        } else {
-            // Using 'Node' as the tool name since the npm version is derived from the Node version
-            // This way the error message will prompt the user to add 'Node' to their toolchain, instead of 'npm'
-            throw!(NoSuchToolError {
-                tool: "Node".to_string()
-            });
+        let (image, _) = session.checkout_tool("npm")?;
+        let mut tool = Self::from_components(&exe, args, &image.path()?);
+        tool.0.env(GLOBAL_PREFIX, image.global_prefix()?);
+        if is_exec_command(&["exec", "x"]) {
+            tool.0.env(NPM_CACHE, image.exec_cache()?);
         }
+        Ok(tool)
     }
 
     fn from_components(exe: &OsStr, args: ArgsOs, path_var: &OsStr) -> Self {
@@ -4,7 +4,8 @@ use std::process::Command;
 
 use failure::Fail;
 
-use super::{arg0, command_for, NoSuchToolError, Tool};
+use super::{arg0, command_for, Tool};
+use crate::env::NPM_CACHE;
 use crate::session::{ActivityKind, Session};
 use crate::version::VersionSpec;
 
@@ -33,23 +34,17 @@ impl Tool for Npx {
 
         let mut args = args_os();
         let exe = arg0(&mut args)?;
-        if let Some(ref platform) = session.current_platform()? {
-            let image = platform.checkout(session)?;
-
-            // npx was only included with Node >= 8.2.0. If less than that, we should include a helpful error message
-            let required_node = VersionSpec::parse_requirements(">= 5.2.0")?;
-            if required_node.matches(&image.node.npm) {
-                Ok(Self::from_components(&exe, args, &image.path()?))
-            } else {
-                throw!(NpxNotAvailableError {
-                    version: image.node.npm.to_string()
-                });
-            }
+        let (image, _) = session.checkout_tool("npx")?;
+
+        // npx was only included with Node >= 8.2.0. If less than that, we should include a helpful error message
+        let required_node = VersionSpec::parse_requirements(">= 5.2.0")?;
+        if required_node.matches(image.npm_version()) {
+            let mut tool = Self::from_components(&exe, args, &image.path()?);
+            tool.0.env(NPM_CACHE, image.exec_cache()?);
+            Ok(tool)
         } else {
-            // Using 'Node' as the tool name since the npx version is derived from the Node version
-            // This way the error message will prompt the user to add 'Node' to their toolchain, instead of 'npx'
-            throw!(NoSuchToolError {
-                tool: "Node".to_string()
+            throw!(NpxNotAvailableError {
+                version: image.npm_version().to_string()
             });
         }
     }
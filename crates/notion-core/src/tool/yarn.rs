@@ -4,9 +4,10 @@ use std::io;
 use std::process::{Command, ExitStatus};
 
 use super::{
-    arg0, command_for, display_error, intercept_global_installs, NoGlobalInstallError,
-    NoSuchToolError, Tool,
+    arg0, command_for, display_error, intercept_global_installs, is_exec_command,
+    NoGlobalInstallError, Tool,
 };
+use crate::env::{GLOBAL_PREFIX, YARN_CACHE, YARN_GLOBAL_FOLDER};
 use crate::session::{ActivityKind, Session};
 
 use notion_fail::{throw, Fallible};
@@ -25,14 +26,14 @@ impl Tool for Yarn {
             throw!(NoGlobalInstallError);
         }
 
-        if let Some(ref platform) = session.current_platform()? {
-            let image = platform.checkout(session)?;
-            Ok(Self::from_components(&exe, args, &image.path()?))
-        } else {
-            throw!(NoSuchToolError {
-                tool: "Yarn".to_string()
-            });
+        let (image, _) = session.checkout_tool("yarn")?;
+        let mut tool = Self::from_components(&exe, args, &image.path()?);
+        tool.0.env(GLOBAL_PREFIX, image.global_prefix()?);
+        tool.0.env(YARN_GLOBAL_FOLDER, image.yarn_global_folder()?);
+        if is_exec_command(&["dlx"]) {
+            tool.0.env(YARN_CACHE, image.exec_cache()?);
         }
+        Ok(tool)
     }
 
     fn from_components(exe: &OsStr, args: ArgsOs, path_var: &OsStr) -> Self {
@@ -5,7 +5,7 @@ use lazycell::LazyCell;
 use readext::ReadExt;
 
 use crate::distro::DistroVersion;
-use crate::fs::touch;
+use crate::fs::{ensure_home_writable, is_read_only_home, read_file_opt, touch};
 use crate::path::user_platform_file;
 use crate::platform::PlatformSpec;
 
@@ -44,7 +44,11 @@ pub struct Toolchain {
 impl Toolchain {
     fn current() -> Fallible<Toolchain> {
         let path = user_platform_file()?;
-        let src = touch(&path)?.read_into_string().unknown()?;
+        let src = if is_read_only_home() {
+            read_file_opt(&path).unknown()?.unwrap_or_default()
+        } else {
+            touch(&path)?.read_into_string().unknown()?
+        };
         Ok(Toolchain {
             platform: serial::Platform::from_json(src)?.into_image()?,
         })
@@ -75,6 +79,7 @@ impl Toolchain {
                         node_runtime: node,
                         npm: Some(npm),
                         yarn: None,
+                        deno: None,
                     });
                     dirty = true;
                 }
@@ -87,8 +92,23 @@ impl Toolchain {
                     }
                 }
             }
-            // ISSUE (#175) When we can `notion install npm` then it can be set in the platform file.
-            DistroVersion::Npm(_) => unimplemented!("cannot set npm in platform file"),
+            DistroVersion::Deno(version) => {
+                if let &mut Some(ref mut platform) = &mut self.platform {
+                    if platform.deno != Some(version.clone()) {
+                        platform.deno = Some(version);
+                        dirty = true;
+                    }
+                }
+            }
+            // replaces the npm bundled with the default Node, until Node is installed again
+            DistroVersion::Npm(version) => {
+                if let &mut Some(ref mut platform) = &mut self.platform {
+                    if platform.npm != Some(version.clone()) {
+                        platform.npm = Some(version);
+                        dirty = true;
+                    }
+                }
+            }
             DistroVersion::Package(name, _) => {
                 unimplemented!("cannot set {} in platform file", name)
             }
@@ -103,6 +123,7 @@ impl Toolchain {
     }
 
     pub fn save(&self) -> Fallible<()> {
+        ensure_home_writable("change the user toolchain")?;
         let path = user_platform_file()?;
         let mut file = File::create(&path).unknown()?;
         match &self.platform {
@@ -18,6 +18,8 @@ pub struct Platform {
     pub node: Option<NodeVersion>,
     #[serde(default)]
     pub yarn: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deno: Option<String>,
 }
 
 impl Platform {
@@ -35,11 +37,17 @@ impl Platform {
                 } else {
                     None
                 };
+                let deno = if let Some(deno) = self.deno {
+                    Some(Version::parse(&deno).unknown()?)
+                } else {
+                    None
+                };
 
                 Some(PlatformSpec {
                     node_runtime,
                     npm,
                     yarn,
+                    deno,
                 })
             }
             None => None,
@@ -69,6 +77,7 @@ impl PlatformSpec {
                 npm: self.npm.as_ref().map(|npm| npm.to_string()),
             }),
             yarn: self.yarn.as_ref().map(|yarn| yarn.to_string()),
+            deno: self.deno.as_ref().map(|deno| deno.to_string()),
         }
     }
 }
@@ -101,6 +110,7 @@ pub mod tests {
                 runtime: "4.5.6".to_string(),
                 npm: Some("7.8.9".to_string()),
             }),
+            deno: None,
         };
         assert_eq!(platform, expected_platform);
     }
@@ -112,6 +122,7 @@ pub mod tests {
         let expected_platform = Platform {
             node: None,
             yarn: None,
+            deno: None,
         };
         assert_eq!(platform, expected_platform);
     }
@@ -122,6 +133,7 @@ pub mod tests {
             yarn: Some(semver::Version::parse("1.2.3").expect("could not parse semver version")),
             node_runtime: semver::Version::parse("4.5.6").expect("could not parse semver version"),
             npm: Some(semver::Version::parse("7.8.9").expect("could not parse semver version")),
+            deno: None,
         };
         let json_str = platform
             .to_serial()
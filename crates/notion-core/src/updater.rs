@@ -0,0 +1,480 @@
+//! Provides the self-update of Notion, which replaces the installed executables (Notion
+//! itself, the launchers and the core shims) with those of the newest release of a
+//! channel. Core shims that are links to `launchbin` are kept, since they run the new
+//! launcher once it is in place.
+//!
+//! Each channel publishes a release manifest listing the executables of its newest
+//! release for every platform, with the integrity of each. All of them are downloaded
+//! next to the files they replace and verified before any is replaced. They are then
+//! swapped in one at a time, each installed file being moved aside first, so that a
+//! failure part-way through, or an interrupt, puts back the ones already replaced.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use failure::Fail;
+use semver::Version;
+use serde::Deserialize;
+
+use crate::checksum::Checksum;
+use crate::credentials;
+use crate::fs::ensure_home_writable;
+use crate::interrupt::{self, Cleanup};
+use crate::inventory::RegistryFetchError;
+use crate::path::{self, ARCH, OS};
+use crate::shim::CORE_SHIMS;
+use crate::style::progress_spinner;
+use notion_fail::{throw, ExitCode, Fallible, NotionError, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+#[cfg(feature = "mock-network")]
+use mockito;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "mock-network")] {
+        fn release_manifest_url(channel: Channel) -> String {
+            format!("{}/notion-releases/{}.json", mockito::SERVER_URL, channel)
+        }
+    } else {
+        /// Returns the URL of the release manifest of `channel`.
+        fn release_manifest_url(channel: Channel) -> String {
+            format!("https://www.notionjs.com/releases/{}.json", channel)
+        }
+    }
+}
+
+/// A release channel of Notion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    /// The supported releases.
+    Stable,
+    /// Builds of the latest changes, released ahead of the stable releases.
+    Canary,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Channel::Stable
+    }
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Channel::Stable => "stable",
+            Channel::Canary => "canary",
+        })
+    }
+}
+
+/// Thrown when a release channel is unknown.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Unknown release channel `{}` (expected `stable` or `canary`)",
+    channel
+)]
+#[notion_fail(code = "InvalidArguments")]
+pub(crate) struct UnknownChannelError {
+    channel: String,
+}
+
+impl FromStr for Channel {
+    type Err = NotionError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "stable" => Ok(Channel::Stable),
+            "canary" => Ok(Channel::Canary),
+            _ => throw!(UnknownChannelError {
+                channel: src.to_string(),
+            }),
+        }
+    }
+}
+
+/// Thrown when the release manifest of a channel is invalid, or doesn't provide a release
+/// for this platform.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Invalid release manifest of the {} channel\n\n{}",
+    channel, error
+)]
+#[notion_fail(code = "NetworkError")]
+pub(crate) struct ReleaseManifestError {
+    channel: Channel,
+    error: String,
+}
+
+/// Thrown when the new executables couldn't be swapped in.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Could not replace {}: {}\n\nThe previous executables were restored.",
+    file, error
+)]
+#[notion_fail(code = "FileSystemError")]
+pub(crate) struct SwapError {
+    file: String,
+    error: String,
+}
+
+/// The release manifest of a channel.
+#[derive(Deserialize, Debug)]
+struct ReleaseManifest {
+    /// The version of the newest release of the channel.
+    version: String,
+    /// The executables of the release, by platform (such as `linux-x64`).
+    platforms: HashMap<String, Vec<ReleaseFile>>,
+}
+
+/// An executable of a release.
+#[derive(Deserialize, Debug, Clone)]
+struct ReleaseFile {
+    /// The name of the executable, without any extension: `notion`, `launchbin`,
+    /// `launchscript` or one of the core shims.
+    name: String,
+    url: String,
+    /// The Subresource Integrity of the executable, such as `sha512-<base64 digest>`.
+    integrity: String,
+}
+
+impl ReleaseManifest {
+    /// Produces the version of the release, and its executables for this platform.
+    fn into_release(self, channel: Channel) -> Fallible<(Version, Vec<ReleaseFile>)> {
+        let invalid = |error: String| ReleaseManifestError { channel, error };
+        let version = match Version::parse(&self.version) {
+            Ok(version) => version,
+            Err(error) => throw!(invalid(format!("{} ({})", error, self.version))),
+        };
+        let platform = format!("{}-{}", OS, ARCH);
+        match self.platforms.get(&platform) {
+            Some(files) if !files.is_empty() => Ok((version, files.clone())),
+            _ => throw!(invalid(format!(
+                "Notion v{} has no release for {}",
+                version, platform
+            ))),
+        }
+    }
+}
+
+/// An executable downloaded and verified next to the installed file it replaces.
+struct Staged {
+    staged: PathBuf,
+    target: PathBuf,
+}
+
+/// The installed file that the executable `name` of a release replaces.
+fn target_file(name: &str) -> Fallible<Option<PathBuf>> {
+    Ok(match name {
+        "notion" => Some(path::notion_file()?),
+        "launchbin" => Some(path::launchbin_file()?),
+        "launchscript" => Some(path::launchscript_file()?),
+        name if CORE_SHIMS.contains(&name) => Some(path::shim_file(name)?),
+        _ => None,
+    })
+}
+
+/// Whether the installed file `target` of the executable `name` is a core shim linked to
+/// `launchbin`, which the update of `launchbin` updates as well.
+fn is_shim_link(name: &str, target: &Path) -> bool {
+    CORE_SHIMS.contains(&name)
+        && fs::symlink_metadata(target)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false)
+}
+
+/// The file that an executable being downloaded is written to, next to `target`.
+fn staged_file(target: &Path) -> PathBuf {
+    with_suffix(target, ".new")
+}
+
+/// The file that an installed executable is moved to while it is being replaced.
+fn backup_file(target: &Path) -> PathBuf {
+    with_suffix(target, ".old")
+}
+
+fn with_suffix(file: &Path, suffix: &str) -> PathBuf {
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    file.with_file_name(name)
+}
+
+/// Updates Notion, currently at version `current`, to the newest release of `channel`,
+/// returning the version it was updated to, or `None` if it is already up to date.
+pub fn self_update(channel: Channel, current: &Version) -> Fallible<Option<Version>> {
+    let url = release_manifest_url(channel);
+    let manifest: ReleaseManifest = credentials::get(&url)?
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|mut response| response.json())
+        .with_context(RegistryFetchError::from_error)?;
+    let (version, files) = manifest.into_release(channel)?;
+    if version <= *current {
+        return Ok(None);
+    }
+    ensure_home_writable("update Notion")?;
+
+    let mut targets = Vec::new();
+    for file in files {
+        match target_file(&file.name)? {
+            Some(ref target) if is_shim_link(&file.name, target) => {}
+            Some(target) => targets.push((file, target)),
+            None => throw!(ReleaseManifestError {
+                channel,
+                error: format!("Unknown executable `{}`", file.name),
+            }),
+        }
+    }
+    let _interrupt = interrupt::register(
+        targets
+            .iter()
+            .map(|(_, target)| Cleanup::RemoveFile(staged_file(target)))
+            .collect(),
+    );
+
+    let spinner = progress_spinner(&format!("Fetching Notion v{}", version));
+    let mut staged = Vec::new();
+    for (file, target) in targets {
+        match stage(channel, &file, &target) {
+            Ok(file) => staged.push(file),
+            Err(error) => {
+                let _ = fs::remove_file(staged_file(&target));
+                for file in staged {
+                    let _ = fs::remove_file(&file.staged);
+                }
+                return Err(error);
+            }
+        }
+    }
+    spinner.finish();
+
+    swap(&staged)?;
+    Ok(Some(version))
+}
+
+/// Downloads the executable `file` next to `target`, and verifies it against its integrity.
+fn stage(channel: Channel, file: &ReleaseFile, target: &Path) -> Fallible<Staged> {
+    let checksum = match Checksum::from_integrity(&file.integrity) {
+        Some(checksum) => checksum,
+        None => throw!(ReleaseManifestError {
+            channel,
+            error: format!("Invalid integrity of `{}`: {}", file.name, file.integrity),
+        }),
+    };
+
+    let staged = staged_file(target);
+    let mut response = credentials::get(&file.url)?
+        .send()
+        .and_then(|response| response.error_for_status())
+        .with_context(RegistryFetchError::from_error)?;
+    let mut out = File::create(&staged).unknown()?;
+    io::copy(&mut response, &mut out).unknown()?;
+    drop(out);
+
+    checksum.verify_file(&staged)?;
+    make_executable(&staged)?;
+    Ok(Staged {
+        staged,
+        target: target.to_path_buf(),
+    })
+}
+
+/// Replaces the target of each of `staged` with its staged executable. If any of them
+/// can't be replaced, or the swap is interrupted, the ones already replaced are put back.
+fn swap(staged: &[Staged]) -> Fallible<()> {
+    // left behind by an earlier update, and which must not be restored on an interrupt
+    for file in staged {
+        let _ = fs::remove_file(backup_file(&file.target));
+    }
+    let interrupt = interrupt::register(
+        staged
+            .iter()
+            .map(|file| Cleanup::RestoreFile {
+                backup: backup_file(&file.target),
+                target: file.target.clone(),
+            })
+            .collect(),
+    );
+
+    for (index, file) in staged.iter().enumerate() {
+        if let Err(error) = swap_file(file) {
+            for done in staged[..index].iter().rev() {
+                let _ = restore_file(done);
+            }
+            for pending in &staged[index..] {
+                let _ = fs::remove_file(&pending.staged);
+            }
+            throw!(SwapError {
+                file: file.target.display().to_string(),
+                error: error.to_string(),
+            });
+        }
+    }
+
+    drop(interrupt);
+    // a running executable can't be removed on Windows, so a backup may be left behind
+    for file in staged {
+        let _ = fs::remove_file(backup_file(&file.target));
+    }
+    Ok(())
+}
+
+/// Moves the target of `file` aside, and its staged executable into its place.
+fn swap_file(file: &Staged) -> io::Result<()> {
+    let backup = backup_file(&file.target);
+    let existed = file.target.exists();
+    if existed {
+        fs::rename(&file.target, &backup)?;
+    }
+    if let Err(error) = fs::rename(&file.staged, &file.target) {
+        if existed {
+            let _ = fs::rename(&backup, &file.target);
+        }
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// Puts back the file that the target of `file` replaced, or removes the target if it
+/// replaced none.
+fn restore_file(file: &Staged) -> io::Result<()> {
+    let backup = backup_file(&file.target);
+    if backup.exists() {
+        fs::rename(&backup, &file.target)
+    } else {
+        fs::remove_file(&file.target)
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(file: &Path) -> Fallible<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(file).unknown()?.permissions();
+    permissions.set_mode(0o755);
+    fs::set_permissions(file, permissions).unknown()
+}
+
+#[cfg(windows)]
+fn make_executable(_file: &Path) -> Fallible<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_channel() {
+        assert_eq!("stable".parse::<Channel>().unwrap(), Channel::Stable);
+        assert_eq!("canary".parse::<Channel>().unwrap(), Channel::Canary);
+        assert!("nightly".parse::<Channel>().is_err());
+        assert_eq!(Channel::default(), Channel::Stable);
+        assert_eq!(Channel::Canary.to_string(), "canary");
+    }
+
+    #[test]
+    fn test_into_release() {
+        let src = format!(
+            r#"{{
+    "version": "0.8.0",
+    "platforms": {{
+        "{}-{}": [{{ "name": "notion", "url": "https://example.com/notion", "integrity": "sha512-AA==" }}],
+        "other-x": [{{ "name": "notion", "url": "https://example.com/other", "integrity": "sha512-AA==" }}]
+    }}
+}}"#,
+            OS, ARCH
+        );
+        let manifest: ReleaseManifest = serde_json::from_str(&src).unwrap();
+        let (version, files) = manifest.into_release(Channel::Stable).unwrap();
+        assert_eq!(version, Version::parse("0.8.0").unwrap());
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].url, "https://example.com/notion");
+
+        let manifest: ReleaseManifest =
+            serde_json::from_str(r#"{ "version": "0.8.0", "platforms": {} }"#).unwrap();
+        assert!(manifest.into_release(Channel::Canary).is_err());
+    }
+
+    fn staged_in(dir: &Path, name: &str, old: Option<&str>) -> Staged {
+        let target = dir.join(name);
+        if let Some(old) = old {
+            fs::write(&target, old).unwrap();
+        }
+        let staged = staged_file(&target);
+        fs::write(&staged, "new").unwrap();
+        Staged { staged, target }
+    }
+
+    #[test]
+    fn test_swap() {
+        let dir = tempdir().unwrap();
+        let staged = vec![
+            staged_in(dir.path(), "notion", Some("old")),
+            staged_in(dir.path(), "launchbin", None),
+        ];
+
+        swap(&staged).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("notion")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("launchbin")).unwrap(),
+            "new"
+        );
+        assert!(!dir.path().join("notion.new").exists());
+        assert!(!dir.path().join("notion.old").exists());
+    }
+
+    #[test]
+    fn test_swap_rollback() {
+        let dir = tempdir().unwrap();
+        let mut staged = vec![
+            staged_in(dir.path(), "notion", Some("old")),
+            staged_in(dir.path(), "launchbin", None),
+            staged_in(dir.path(), "launchscript", Some("old")),
+        ];
+        // the staged executable of the last file has gone missing
+        fs::remove_file(&staged[2].staged).unwrap();
+        staged[2].staged = dir.path().join("missing.new");
+
+        assert!(swap(&staged).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("notion")).unwrap(),
+            "old"
+        );
+        assert!(!dir.path().join("launchbin").exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("launchscript")).unwrap(),
+            "old"
+        );
+        assert!(!dir.path().join("notion.old").exists());
+        assert!(!dir.path().join("launchscript.old").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_is_shim_link() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempdir().unwrap();
+        let launchbin = dir.path().join("launchbin");
+        fs::write(&launchbin, "launchbin").unwrap();
+        symlink(&launchbin, dir.path().join("node")).unwrap();
+        symlink(&launchbin, dir.path().join("notion")).unwrap();
+        fs::write(dir.path().join("yarn"), "yarn").unwrap();
+
+        assert!(is_shim_link("node", &dir.path().join("node")));
+        assert!(!is_shim_link("yarn", &dir.path().join("yarn")));
+        assert!(!is_shim_link("npm", &dir.path().join("npm")));
+        // only the core shims are kept as links
+        assert!(!is_shim_link("notion", &dir.path().join("notion")));
+    }
+}
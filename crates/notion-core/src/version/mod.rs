@@ -9,13 +9,16 @@ use semver::{ReqParseError, SemVerError, Version, VersionReq};
 use notion_fail::{ExitCode, Fallible, NotionFail, ResultExt};
 use notion_fail_derive::*;
 
-use self::serial::parse_requirements;
+use self::serial::{parse_npm_range, parse_requirements};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VersionSpec {
     Latest,
     Semver(VersionReq),
     Exact(Version),
+    /// A named version: the moving `lts` tag, an alias (see `notion alias`), or a
+    /// dist-tag of a package.
+    Tag(String),
 }
 
 impl fmt::Display for VersionSpec {
@@ -24,6 +27,7 @@ impl fmt::Display for VersionSpec {
             VersionSpec::Latest => write!(f, "latest"),
             VersionSpec::Semver(ref req) => req.fmt(f),
             VersionSpec::Exact(ref version) => version.fmt(f),
+            VersionSpec::Tag(ref tag) => tag.fmt(f),
         }
     }
 }
@@ -49,6 +53,11 @@ impl VersionSpec {
         parse_requirements(s.as_ref()).with_context(VersionParseError::from_req_parse_error)
     }
 
+    /// Parses an npm version range into its alternatives.
+    pub fn parse_npm_range(s: impl AsRef<str>) -> Fallible<Vec<VersionReq>> {
+        parse_npm_range(s.as_ref()).with_context(VersionParseError::from_req_parse_error)
+    }
+
     pub fn parse_version(s: impl AsRef<str>) -> Fallible<Version> {
         Version::parse(s.as_ref()).with_context(VersionParseError::from_semver_error)
     }
@@ -63,10 +72,25 @@ impl FromStr for VersionSpec {
         }
 
         if let Ok(ref exact) = VersionSpec::parse_version(s) {
-            Ok(VersionSpec::exact(exact))
-        } else {
-            Ok(VersionSpec::Semver(parse_requirements(s)?))
+            return Ok(VersionSpec::exact(exact));
+        }
+        match parse_requirements(s) {
+            Ok(requirement) => Ok(VersionSpec::Semver(requirement)),
+            Err(_) if is_tag(s) => Ok(VersionSpec::Tag(s.to_string())),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Determines whether `s` is a valid name for a version, which starts with a letter and
+/// continues with letters, digits, `-`, `_` or `.`.
+pub(crate) fn is_tag(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
         }
+        _ => false,
     }
 }
 
@@ -90,3 +114,28 @@ impl VersionParseError {
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+
+    use super::VersionSpec;
+    use semver::Version;
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(
+            VersionSpec::parse("lts").unwrap(),
+            VersionSpec::Tag("lts".to_string())
+        );
+        assert_eq!(
+            VersionSpec::parse("backend-v2").unwrap(),
+            VersionSpec::Tag("backend-v2".to_string())
+        );
+        assert_eq!(VersionSpec::parse("latest").unwrap(), VersionSpec::Latest);
+        assert_eq!(
+            VersionSpec::parse("14.17.0").unwrap(),
+            VersionSpec::Exact(Version::parse("14.17.0").unwrap())
+        );
+        assert!(VersionSpec::parse("14.x.y").is_err());
+    }
+}
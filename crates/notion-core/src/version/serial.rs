@@ -1,22 +1,60 @@
 use semver::{ReqParseError, VersionReq};
 
 pub fn parse_requirements(src: &str) -> Result<VersionReq, ReqParseError> {
-    let src = src.trim();
+    VersionReq::parse(&defaulted(src.trim()))
+}
+
+/// Treats a bare version (optionally with a `v` prefix) as an exact requirement.
+fn defaulted(src: &str) -> String {
     if src.len() > 0 && src.chars().next().unwrap().is_digit(10) {
-        let defaulted = format!("={}", src);
-        VersionReq::parse(&defaulted)
+        format!("={}", src)
     } else if src.len() > 0 && src.chars().next().unwrap() == 'v' {
-        let defaulted = src.replacen("v", "=", 1);
-        VersionReq::parse(&defaulted)
+        src.replacen("v", "=", 1)
     } else {
-        VersionReq::parse(src)
+        src.to_string()
     }
 }
 
+/// Parses an npm version range, as found in the `engines` of a `package.json`, into its
+/// alternatives. Unlike the comma-separated requirements of Cargo, npm separates the
+/// comparators of a range with spaces (`>=12 <15`), allows hyphen ranges (`12 - 14`),
+/// and separates alternatives with `||`.
+pub fn parse_npm_range(src: &str) -> Result<Vec<VersionReq>, ReqParseError> {
+    src.split("||")
+        .map(|alternative| {
+            let mut comparators: Vec<String> = Vec::new();
+            let mut tokens = alternative.split_whitespace().peekable();
+            while let Some(token) = tokens.next() {
+                if token == "-" {
+                    // a hyphen range includes both of its ends
+                    if let (Some(lower), Some(upper)) = (comparators.pop(), tokens.next()) {
+                        comparators.push(format!(">={}", lower.trim_start_matches('=')));
+                        comparators.push(format!("<={}", upper.trim_start_matches('v')));
+                    }
+                } else if token.chars().all(|c| "<>=~^".contains(c)) {
+                    // an operator separated from its version by a space
+                    let version = tokens.next().unwrap_or("");
+                    comparators.push(format!("{}{}", token, version.trim_start_matches('v')));
+                } else if token.contains(|c| c == 'x' || c == 'X' || c == '*') {
+                    // a wildcard already matches a range of versions
+                    comparators.push(token.trim_start_matches('v').to_string());
+                } else {
+                    comparators.push(defaulted(token));
+                }
+            }
+            if comparators.is_empty() {
+                VersionReq::parse("*")
+            } else {
+                VersionReq::parse(&comparators.join(", "))
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 pub mod tests {
 
-    use crate::version::serial::parse_requirements;
+    use crate::version::serial::{parse_npm_range, parse_requirements};
     use semver::VersionReq;
 
     #[test]
@@ -42,4 +80,31 @@ pub mod tests {
             VersionReq::parse(">=1.4").unwrap()
         );
     }
+
+    #[test]
+    fn test_parse_npm_range() {
+        assert_eq!(
+            parse_npm_range(">=12 <15").unwrap(),
+            vec![VersionReq::parse(">=12, <15").unwrap()]
+        );
+        assert_eq!(
+            parse_npm_range(">= 10.13.0").unwrap(),
+            vec![VersionReq::parse(">=10.13.0").unwrap()]
+        );
+        assert_eq!(
+            parse_npm_range("10 - 12.4").unwrap(),
+            vec![VersionReq::parse(">=10, <=12.4").unwrap()]
+        );
+        assert_eq!(
+            parse_npm_range("^8.9 || v10").unwrap(),
+            vec![
+                VersionReq::parse("^8.9").unwrap(),
+                VersionReq::parse("=10").unwrap()
+            ]
+        );
+        assert_eq!(
+            parse_npm_range("").unwrap(),
+            vec![VersionReq::parse("*").unwrap()]
+        );
+    }
 }
@@ -18,6 +18,7 @@ pub fn notion_fail(token_stream: TokenStream) -> TokenStream {
     let mut code = Ident::new("UnknownError", Span::call_site());
     let mut code_set = false;
     let mut is_friendly = Ident::new("true", Span::call_site());
+    let mut error_code = default_error_code(&name.to_string());
 
     for meta in input.attrs.iter().filter_map(get_notion_fail_meta_items) {
         for item in meta {
@@ -49,6 +50,15 @@ pub fn notion_fail(token_stream: TokenStream) -> TokenStream {
                     }
                 }
 
+                Meta(NameValue(ref m)) if m.ident == "error_code" => {
+                    if let Lit::Str(s) = &m.lit {
+                        error_code = s.value();
+                    } else {
+                        // Defined, but not a string.
+                        panic!("#[notion_fail()]: 'error_code' must be a string.");
+                    }
+                }
+
                 Meta(NameValue(m)) => {
                     panic!("#[notion_fail()]: not a recognized name: '{}'", m.ident);
                 }
@@ -73,12 +83,38 @@ pub fn notion_fail(token_stream: TokenStream) -> TokenStream {
             fn is_user_friendly(&self) -> bool {
                 #is_friendly
             }
+
+            fn error_code(&self) -> &'static str {
+                #error_code
+            }
         }
     };
 
     tokens.into()
 }
 
+/// Derives the error code of a type from its name, in kebab case and without any `Error`
+/// suffix, e.g. `no-version-found` for `NoVersionFoundError`.
+fn default_error_code(name: &str) -> String {
+    let name = if name.len() > "Error".len() && name.ends_with("Error") {
+        &name[..name.len() - "Error".len()]
+    } else {
+        name
+    };
+    let mut code = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                code.push('-');
+            }
+            code.extend(c.to_lowercase());
+        } else {
+            code.push(c);
+        }
+    }
+    code
+}
+
 fn get_notion_fail_meta_items(attr: &syn::Attribute) -> Option<Vec<NestedMeta>> {
     if attr.path.segments.len() == 1 && attr.path.segments[0].ident == "notion_fail" {
         match attr.interpret_meta() {
@@ -23,6 +23,15 @@
 //! The `NotionFail::exit_code()` method allows each error type to indicate what the
 //! process exit code should be if the error is the reason for exiting Notion.
 //!
+//! ## Error codes
+//!
+//! The `NotionFail::error_code()` method identifies each kind of error with a string
+//! that tools (such as editor integrations) can match on, since unlike the message it
+//! doesn't change between versions. The derived implementation uses the name of the
+//! type in kebab case without its `Error` suffix, so that `NoVersionFoundError` is
+//! `no-version-found`; `#[notion_fail(error_code = "...")]` overrides it, and must be
+//! used to keep the code when a type is renamed. Internal errors are `unknown`.
+//!
 //! # The `NotionError` type and `Fallible` functions
 //!
 //! The main error type provided by this crate is `NotionError`. This acts more
@@ -247,6 +256,29 @@ macro_rules! throw {
 }
 
 /// Exit codes supported by the NotionFail trait.
+///
+/// These codes are a stable part of Notion's interface, so that scripts and CI
+/// pipelines can branch on the kind of failure. Values are never reassigned; new
+/// kinds of failure get new values.
+///
+/// | Code | Meaning                                                        |
+/// |------|----------------------------------------------------------------|
+/// | 0    | success                                                        |
+/// | 1    | unknown or internal error                                      |
+/// | 3    | invalid command-line arguments                                 |
+/// | 4    | a requested version could not be resolved                      |
+/// | 5    | a network request failed                                       |
+/// | 6    | the environment is missing or misconfigured                    |
+/// | 7    | a file could not be read or written                            |
+/// | 8    | package or Notion configuration is missing or incorrect        |
+/// | 9    | the command or feature is not yet implemented                  |
+/// | 10   | Notion lacks permission to modify a file or directory          |
+/// | 126  | a shim could not run its tool (including any Notion failure)   |
+/// | 127  | a shim's tool could not be found                               |
+///
+/// Shims follow the shell conventions for 126 and 127, so that from the perspective
+/// of the caller they behave like the executable they stand in for. Any other code
+/// returned from a shim is the exit code of the tool itself.
 #[derive(Copy, Clone, Debug, Serialize)]
 pub enum ExitCode {
     /// No error occurred.
@@ -276,6 +308,9 @@ pub enum ExitCode {
     /// The command or feature is not yet implemented.
     NotYetImplemented = 9,
 
+    /// A file or directory could not be modified due to insufficient permissions.
+    PermissionError = 10,
+
     /// The requested executable could not be run.
     ExecutionFailure = 126,
 
@@ -296,6 +331,9 @@ pub trait NotionFail: Fail {
 
     /// Returns the process exit code that should be returned if the process exits with this error.
     fn exit_code(&self) -> ExitCode;
+
+    /// Returns the stable identifier of this kind of error, such as `no-version-found`.
+    fn error_code(&self) -> &'static str;
 }
 
 /// The `NotionError` type, which can contain any Notion failure.
@@ -309,6 +347,9 @@ pub struct NotionError {
 
     /// The result of `error.exit_code()`.
     exit_code: ExitCode,
+
+    /// The result of `error.error_code()`.
+    error_code: &'static str,
 }
 
 impl Fail for NotionError {
@@ -361,16 +402,23 @@ impl NotionError {
     pub fn exit_code(&self) -> ExitCode {
         self.exit_code
     }
+
+    /// Returns the stable identifier of the kind of this error.
+    pub fn error_code(&self) -> &'static str {
+        self.error_code
+    }
 }
 
 impl<T: NotionFail> From<T> for NotionError {
     fn from(failure: T) -> Self {
         let user_friendly = failure.is_user_friendly();
         let exit_code = failure.exit_code();
+        let error_code = failure.error_code();
         NotionError {
             error: failure.into(),
             user_friendly,
             exit_code,
+            error_code,
         }
     }
 }
@@ -402,7 +450,7 @@ pub trait ResultExt<T, E> {
 
 /// A wrapper type for unknown errors.
 #[derive(NotionFail)]
-#[notion_fail(code = "UnknownError", friendly = "false")]
+#[notion_fail(code = "UnknownError", friendly = "false", error_code = "unknown")]
 struct UnknownNotionError {
     error: failure::Error,
 }
@@ -474,6 +522,10 @@ impl<D: NotionFail> NotionFail for failure::Context<D> {
     fn exit_code(&self) -> ExitCode {
         self.get_context().exit_code()
     }
+
+    fn error_code(&self) -> &'static str {
+        self.get_context().error_code()
+    }
 }
 
 /// A convenient shorthand for `Result` types that produce `NotionError`s.
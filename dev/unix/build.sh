@@ -44,6 +44,7 @@ encode_base64_sed_command node NODE "$build_dir/node"
 encode_base64_sed_command npm NPM "$build_dir/npm"
 encode_base64_sed_command npx NPX "$build_dir/npx"
 encode_base64_sed_command yarn YARN "$build_dir/yarn"
+encode_base64_sed_command deno DENO "$build_dir/deno"
 encode_base64_sed_command launchbin LAUNCHBIN "$build_dir/launchbin"
 encode_base64_sed_command launchscript LAUNCHSCRIPT "$build_dir/launchscript"
 encode_expand_sed_command bash_launcher BASH_LAUNCHER "$shell_dir/unix/load.sh"
@@ -53,6 +54,7 @@ sed -f notion.base64.txt \
     -f npm.base64.txt \
     -f npx.base64.txt \
     -f yarn.base64.txt \
+    -f deno.base64.txt \
     -f launchbin.base64.txt \
     -f launchscript.base64.txt \
     -f bash_launcher.expand.txt \
@@ -65,6 +67,7 @@ rm notion.base64.txt \
    npm.base64.txt \
    npx.base64.txt \
    yarn.base64.txt \
+   deno.base64.txt \
    launchbin.base64.txt \
    launchscript.base64.txt \
    bash_launcher.expand.txt
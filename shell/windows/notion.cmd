@@ -1 +1,27 @@
-@echo Not yet implemented.
+@echo off
+
+if defined NOTION_HOME (
+    set "NOTION_ROOT=%NOTION_HOME%"
+) else (
+    set "NOTION_ROOT=%LOCALAPPDATA%\Notion"
+)
+
+rem Generate a random name, to avoid clashing with concurrent executions.
+set "NOTION_POSTSCRIPT=%NOTION_ROOT%\tmp\notion_tmp_%RANDOM%%RANDOM%.cmd"
+
+rem Forward the arguments to the Notion executable.
+set "NOTION_SHELL=cmd"
+"%NOTION_ROOT%\notion.exe" %*
+set "NOTION_EXIT_CODE=%ERRORLEVEL%"
+set NOTION_SHELL=
+
+rem Call the post-invocation script if it is present, then delete it.
+rem This allows the invocation to potentially modify the caller's environment (e.g., PATH).
+if exist "%NOTION_POSTSCRIPT%" (
+    call "%NOTION_POSTSCRIPT%"
+    del "%NOTION_POSTSCRIPT%"
+)
+
+set NOTION_POSTSCRIPT=
+set NOTION_ROOT=
+set NOTION_EXIT_CODE= & exit /b %NOTION_EXIT_CODE%
@@ -1 +1,22 @@
-Write-Host "Not yet implemented"
+function notion {
+    $NotionRoot = if ($env:NOTION_HOME) { $env:NOTION_HOME } else { "$env:LOCALAPPDATA\Notion" }
+
+    # Generate 32 bits of randomness, to avoid clashing with concurrent executions.
+    $env:NOTION_POSTSCRIPT = "$NotionRoot\tmp\notion_tmp_$(Get-Random).ps1"
+
+    # Forward the arguments to the Notion executable.
+    $env:NOTION_SHELL = "powershell"
+    & "$NotionRoot\notion.exe" @args
+    $ExitCode = $LASTEXITCODE
+    Remove-Item Env:NOTION_SHELL
+
+    # Call the post-invocation script if it is present, then delete it.
+    # This allows the invocation to potentially modify the caller's environment (e.g., PATH).
+    if (Test-Path $env:NOTION_POSTSCRIPT) {
+        . $env:NOTION_POSTSCRIPT
+        Remove-Item $env:NOTION_POSTSCRIPT
+    }
+
+    Remove-Item Env:NOTION_POSTSCRIPT
+    $global:LASTEXITCODE = $ExitCode
+}
@@ -0,0 +1,132 @@
+use serde::Deserialize;
+
+use notion_core::alias::{self, Aliases};
+use notion_core::session::{ActivityKind, Session};
+use notion_core::style::display_warning;
+use notion_core::version::VersionSpec;
+use notion_fail::{ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    cmd_create: bool,
+    cmd_delete: bool,
+    cmd_list: bool,
+    arg_name: Option<String>,
+    arg_version: Option<String>,
+    flag_tool: Option<String>,
+}
+
+pub(crate) enum Alias {
+    Help,
+    Create {
+        tool: String,
+        name: String,
+        version: VersionSpec,
+    },
+    Delete {
+        tool: String,
+        name: String,
+    },
+    List,
+}
+
+impl Command for Alias {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Manage the names given to versions
+
+Usage:
+    notion alias create <name> <version>
+    notion alias delete [--tool=<tool>] <name>
+    notion alias list
+    notion alias -h | --help
+
+Options:
+    --tool=<tool>  The tool the alias is for (Node by default)
+    -h, --help     Display this message
+
+An alias names a version, a range or `lts` of a tool, and can then be used in
+its place, in commands and in the `toolchain` of package.json alike. The
+<version> is of Node unless it names another tool, e.g.:
+
+    notion alias create backend 14.17.0
+    notion alias create classic yarn@1.22
+    notion install node@backend
+
+The `lts` tag is built in, and names the newest LTS release of Node. Tools
+installed as `lts` or as an alias are resolved again by `notion update`.
+";
+
+    fn help() -> Self {
+        Alias::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            cmd_create,
+            cmd_delete,
+            cmd_list,
+            arg_name,
+            arg_version,
+            flag_tool,
+        }: Args,
+    ) -> Fallible<Self> {
+        let name = arg_name.unwrap_or_default();
+        if cmd_create {
+            let version = arg_version.unwrap_or_default();
+            let (tool, version) = match version.find('@') {
+                Some(at) => (version[..at].to_string(), &version[at + 1..]),
+                None => ("node".to_string(), &version[..]),
+            };
+            Ok(Alias::Create {
+                tool,
+                name,
+                version: VersionSpec::parse(version)?,
+            })
+        } else if cmd_delete {
+            Ok(Alias::Delete {
+                tool: flag_tool.unwrap_or_else(|| "node".to_string()),
+                name,
+            })
+        } else if cmd_list {
+            Ok(Alias::List)
+        } else {
+            Ok(Alias::Help)
+        }
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Alias);
+        match self {
+            Alias::Help => Help::Command(CommandName::Alias).run(session)?,
+            Alias::Create {
+                tool,
+                name,
+                version,
+            } => {
+                alias::create(&tool, &name, &version)?;
+                println!("Created the alias {} of {} {}", name, tool, version);
+            }
+            Alias::Delete { tool, name } => {
+                if alias::delete(&tool, &name)? {
+                    println!("Deleted the alias {} of {}", name, tool);
+                } else {
+                    display_warning(&format!("{} has no alias named `{}`", tool, name));
+                }
+            }
+            Alias::List => {
+                let aliases = Aliases::current()?;
+                for alias in aliases.list() {
+                    println!("{} {} = {}", alias.tool, alias.name, alias.version);
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Alias, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use serde::Deserialize;
+
+use notion_core::disk::Size;
+use notion_core::gc;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    cmd_prune: bool,
+    flag_keep_latest: Option<usize>,
+    flag_force: bool,
+}
+
+pub(crate) enum Cache {
+    Help,
+    Prune { keep_latest: usize, force: bool },
+}
+
+impl Command for Cache {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Prune the inventory of fetched versions
+
+Usage:
+    notion cache prune [--keep-latest=<n>] [--force]
+    notion cache -h | --help
+
+Options:
+    --keep-latest=<n>  Also keep the <n> highest versions of each tool
+    --force            Also remove the default versions and the ones pinned by this project
+    -h, --help         Display this message
+
+Removes the downloaded archives and unpacked images of every fetched version
+of Node, Yarn and Deno except the ones in the user toolchain and the ones
+pinned by the current project. Versions that are removed but needed later are
+fetched again automatically.
+";
+
+    fn help() -> Self {
+        Cache::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            cmd_prune,
+            flag_keep_latest,
+            flag_force,
+        }: Args,
+    ) -> Fallible<Self> {
+        if !cmd_prune {
+            return Ok(Cache::Help);
+        }
+        Ok(Cache::Prune {
+            keep_latest: flag_keep_latest.unwrap_or(0),
+            force: flag_force,
+        })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Cache);
+        match self {
+            Cache::Help => Help::Command(CommandName::Cache).run(session)?,
+            Cache::Prune { keep_latest, force } => {
+                let removed = gc::prune(session, keep_latest, force)?;
+                for version in removed.iter() {
+                    println!("Removed {}", version);
+                }
+                let freed: u64 = removed.iter().map(|version| version.size).sum();
+                println!("Freed {}", Size(freed));
+            }
+        };
+        session.add_event_end(ActivityKind::Cache, ExitCode::Success);
+        Ok(())
+    }
+}
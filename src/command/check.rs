@@ -0,0 +1,80 @@
+use serde::Deserialize;
+
+use failure::Fail;
+
+use notion_core::check;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{throw, ExitCode, Fallible, NotionFail};
+use notion_fail_derive::*;
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_offline: bool,
+}
+
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Toolchain check failed:\n{}", problems)]
+#[notion_fail(code = "ConfigurationError")]
+struct CheckFailedError {
+    problems: String,
+}
+
+pub(crate) enum Check {
+    Help,
+    Project { offline: bool },
+}
+
+impl Command for Check {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Validate the current project's toolchain
+
+Usage:
+    notion check [options]
+
+Options:
+    -h, --help     Display this message
+    --offline      Check the local inventory instead of the upstream registries
+
+Verifies that the project pins a toolchain, that the pinned versions are
+published (or already fetched, with --offline), and that package-lock.json or
+yarn.lock, if present, agrees with the dependencies in package.json. Exits with
+a non-zero status if there are any problems, so it can be used as a CI gate.
+";
+
+    fn help() -> Self {
+        Check::Help
+    }
+
+    fn parse(_: Notion, Args { flag_offline }: Args) -> Fallible<Self> {
+        Ok(Check::Project {
+            offline: flag_offline,
+        })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Check);
+        match self {
+            Check::Help => Help::Command(CommandName::Check).run(session)?,
+            Check::Project { offline } => {
+                let problems = check::check(session, offline)?;
+                if !problems.is_empty() {
+                    throw!(CheckFailedError {
+                        problems: problems
+                            .iter()
+                            .map(|problem| format!("  - {}", problem))
+                            .collect::<Vec<String>>()
+                            .join("\n"),
+                    });
+                }
+                println!("Toolchain OK");
+            }
+        };
+        session.add_event_end(ActivityKind::Check, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use serde::Deserialize;
+
+use failure::Fail;
+
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{throw, ExitCode, Fallible, NotionFail};
+use notion_fail_derive::*;
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    cmd_cache_key: bool,
+}
+
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "No toolchain found\n\nPin a Node version in this project with `notion pin node`.")]
+#[notion_fail(code = "ConfigurationError")]
+struct NoToolchainError;
+
+pub(crate) enum Ci {
+    Help,
+    CacheKey,
+}
+
+impl Command for Ci {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Helpers for continuous integration
+
+Usage:
+    notion ci cache-key
+    notion ci -h | --help
+
+Options:
+    -h, --help     Display this message
+
+`notion ci cache-key` prints a key derived from the project's toolchain (or the
+user toolchain outside of a project) and the current OS and architecture. Use it
+to key caches of NOTION_HOME, so they are invalidated exactly when pins change.
+For example, with CircleCI:
+
+    - run: notion ci cache-key > notion-cache-key
+    - restore_cache:
+        key: notion-{{ checksum \"notion-cache-key\" }}
+";
+
+    fn help() -> Self {
+        Ci::Help
+    }
+
+    fn parse(_: Notion, Args { cmd_cache_key }: Args) -> Fallible<Self> {
+        Ok(if cmd_cache_key { Ci::CacheKey } else { Ci::Help })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Ci);
+        match self {
+            Ci::Help => Help::Command(CommandName::Ci).run(session)?,
+            Ci::CacheKey => match session.current_platform()? {
+                Some(platform) => println!("{}", platform.cache_key()),
+                None => throw!(NoToolchainError),
+            },
+        };
+        session.add_event_end(ActivityKind::Ci, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+use serde::Deserialize;
+
+use notion_core::inventory::query;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{throw, ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::error::CliParseError;
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    arg_shell: Option<String>,
+    arg_tool: Option<String>,
+    flag_versions: bool,
+}
+
+/// The shells that completion scripts are generated for.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum ScriptShell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+pub(crate) enum Completions {
+    Help,
+    Script(ScriptShell),
+    Versions(String),
+}
+
+/// The tools whose versions are completed, as `<tool> <version>` or `<tool>@<version>`.
+const TOOLS: &'static str = "node yarn npm deno";
+
+/// The commands that take a tool and its version.
+const TOOL_COMMANDS: &'static str = "fetch install pin shell uninstall";
+
+const BASH: &'static str = r##"# bash completion for notion
+
+_notion() {
+    local cur prev
+    cur="${COMP_WORDS[COMP_CWORD]}"
+    prev="${COMP_WORDS[COMP_CWORD-1]}"
+
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=($(compgen -W "{commands}" -- "$cur"))
+        return
+    fi
+
+    case " {tool_commands} " in
+        *" ${COMP_WORDS[1]} "*)
+            if [[ "$cur" == *@* ]]; then
+                local tool="${cur%%@*}"
+                local versions="$(notion completions --versions "$tool" 2>/dev/null)"
+                COMPREPLY=($(compgen -P "$tool@" -W "$versions" -- "${cur#*@}"))
+            elif [[ " {tools} " == *" $prev "* ]]; then
+                local versions="$(notion completions --versions "$prev" 2>/dev/null)"
+                COMPREPLY=($(compgen -W "$versions" -- "$cur"))
+            else
+                COMPREPLY=($(compgen -W "{tools}" -- "$cur"))
+            fi
+            return
+            ;;
+    esac
+
+    case "${COMP_WORDS[1]}" in
+        help)
+            COMPREPLY=($(compgen -W "{commands}" -- "$cur"))
+            ;;
+        completions)
+            COMPREPLY=($(compgen -W "bash zsh fish powershell" -- "$cur"))
+            ;;
+    esac
+}
+
+complete -F _notion notion
+"##;
+
+const ZSH: &'static str = r##"#compdef notion
+
+_notion() {
+    local -a commands tools tool_commands
+    commands=({commands})
+    tools=({tools})
+    tool_commands=({tool_commands})
+
+    if (( CURRENT == 2 )); then
+        compadd -a commands
+        return
+    fi
+
+    if (( ${tool_commands[(Ie)${words[2]}]} )); then
+        if compset -P '*@'; then
+            compadd -- ${(f)"$(notion completions --versions "${IPREFIX%@}" 2>/dev/null)"}
+        elif (( ${tools[(Ie)${words[CURRENT-1]}]} )); then
+            compadd -- ${(f)"$(notion completions --versions "${words[CURRENT-1]}" 2>/dev/null)"}
+        else
+            compadd -a tools
+        fi
+        return
+    fi
+
+    case "${words[2]}" in
+        help)
+            compadd -a commands
+            ;;
+        completions)
+            compadd bash zsh fish powershell
+            ;;
+    esac
+}
+
+compdef _notion notion
+"##;
+
+const FISH: &'static str = r##"# fish completion for notion
+
+function __notion_tool_versions
+    set -l token (commandline -ct)
+    if string match -q '*@*' -- $token
+        set -l tool (string split -m 1 @ -- $token)[1]
+        for version in (notion completions --versions $tool 2>/dev/null)
+            echo $tool@$version
+        end
+        return
+    end
+
+    set -l previous (commandline -opc)[-1]
+    if contains -- $previous {tools}
+        notion completions --versions $previous 2>/dev/null
+    else
+        printf '%s\n' {tools}
+    end
+end
+
+complete -c notion -f
+complete -c notion -n __fish_use_subcommand -a '{commands}'
+complete -c notion -n '__fish_seen_subcommand_from {tool_commands}' -a '(__notion_tool_versions)'
+complete -c notion -n '__fish_seen_subcommand_from help' -a '{commands}'
+complete -c notion -n '__fish_seen_subcommand_from completions' -a 'bash zsh fish powershell'
+"##;
+
+const POWERSHELL: &'static str = r##"# PowerShell completion for notion
+
+Register-ArgumentCompleter -Native -CommandName notion -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $commands = '{commands}' -split ' '
+    $tools = '{tools}' -split ' '
+    $toolCommands = '{tool_commands}' -split ' '
+
+    $words = @($commandAst.CommandElements | ForEach-Object { $_.ToString() })
+    # the index of the word being completed
+    $index = if ($wordToComplete) { $words.Count - 1 } else { $words.Count }
+
+    $candidates = if ($index -le 1) {
+        $commands
+    } elseif ($toolCommands -contains $words[1]) {
+        if ($wordToComplete -like '*@*') {
+            $tool = $wordToComplete.Split('@')[0]
+            notion completions --versions $tool 2>$null | ForEach-Object { "$tool@$_" }
+        } elseif ($tools -contains $words[$index - 1]) {
+            notion completions --versions $words[$index - 1] 2>$null
+        } else {
+            $tools
+        }
+    } elseif ($words[1] -eq 'help') {
+        $commands
+    } elseif ($words[1] -eq 'completions') {
+        'bash', 'zsh', 'fish', 'powershell'
+    }
+
+    $candidates | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }
+}
+"##;
+
+impl ScriptShell {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "bash" => Some(ScriptShell::Bash),
+            "zsh" => Some(ScriptShell::Zsh),
+            "fish" => Some(ScriptShell::Fish),
+            "powershell" => Some(ScriptShell::PowerShell),
+            _ => None,
+        }
+    }
+
+    /// Generates the completion script of this shell.
+    fn script(self) -> String {
+        let template = match self {
+            ScriptShell::Bash => BASH,
+            ScriptShell::Zsh => ZSH,
+            ScriptShell::Fish => FISH,
+            ScriptShell::PowerShell => POWERSHELL,
+        };
+        template
+            .replace("{commands}", &command_names().join(" "))
+            .replace("{tool_commands}", TOOL_COMMANDS)
+            .replace("{tools}", TOOLS)
+    }
+}
+
+/// Lists the commands of the `notion` usage message, so that the scripts offer the same
+/// commands as `notion help`.
+fn command_names() -> Vec<&'static str> {
+    Notion::USAGE
+        .lines()
+        .skip_while(|line| !line.ends_with("commands are:"))
+        .skip(1)
+        .take_while(|line| line.starts_with("    "))
+        .filter_map(|line| line.split_whitespace().next())
+        .collect()
+}
+
+impl Command for Completions {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Print a shell completion script
+
+Usage:
+    notion completions <shell>
+    notion completions --versions <tool>
+    notion completions -h | --help
+
+Options:
+    --versions     List the versions of <tool> to complete, newest first
+    -h, --help     Display this message
+
+Supported shells are `bash`, `zsh`, `fish` and `powershell`. The scripts complete
+the commands, and the versions of tools (as in `notion pin node@<TAB>`) from the
+fetched versions and the cached Node index, without reaching the network. E.g.:
+
+    notion completions bash > ~/.local/share/bash-completion/completions/notion
+    notion completions zsh > ~/.zfunc/_notion
+    notion completions fish > ~/.config/fish/completions/notion.fish
+    notion completions powershell >> $PROFILE
+";
+
+    fn help() -> Self {
+        Completions::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            arg_shell,
+            arg_tool,
+            flag_versions,
+        }: Args,
+    ) -> Fallible<Self> {
+        if flag_versions {
+            return Ok(Completions::Versions(arg_tool.unwrap_or_default()));
+        }
+        let name = arg_shell.unwrap_or_default();
+        match ScriptShell::parse(&name) {
+            Some(shell) => Ok(Completions::Script(shell)),
+            None => throw!(CliParseError {
+                usage: None,
+                error: format!(
+                    "invalid shell: `{}` (expected `bash`, `zsh`, `fish` or `powershell`)",
+                    name
+                ),
+            }),
+        }
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Completions);
+        match self {
+            Completions::Help => Help::Command(CommandName::Completions).run(session)?,
+            Completions::Script(shell) => print!("{}", shell.script()),
+            Completions::Versions(tool) => {
+                for version in query::completion_versions(session, &tool)? {
+                    println!("{}", version);
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Completions, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use serde::Deserialize;
 
 use failure::Fail;
 
-use notion_core::session::{ActivityKind, Session};
+use notion_core::session::{self, ActivityKind, Session};
 use notion_fail::{throw, ExitCode, Fallible, NotionFail};
 use notion_fail_derive::*;
 
@@ -15,6 +15,7 @@ use crate::Notion;
 pub(crate) struct Args {
     flag_project: bool,
     flag_user: bool,
+    flag_json: bool,
 }
 
 #[derive(Debug, Fail, NotionFail)]
@@ -27,6 +28,7 @@ pub(crate) enum Current {
     Project,
     User,
     All,
+    Json,
 }
 
 impl Command for Current {
@@ -42,6 +44,8 @@ Options:
     -h, --help     Display this message
     -p, --project  Display the current project's Node version
     -u, --user     Display the user's Node version
+    --json         Print each tool that runs, with its version, where that
+                   version is selected and its executable, as a JSON array
 ";
 
     fn help() -> Self {
@@ -53,9 +57,12 @@ Options:
         Args {
             flag_project,
             flag_user,
+            flag_json,
         }: Args,
     ) -> Fallible<Current> {
-        Ok(if !flag_project && flag_user {
+        Ok(if flag_json {
+            Current::Json
+        } else if !flag_project && flag_user {
             Current::User
         } else if flag_project && !flag_user {
             Current::Project
@@ -105,6 +112,10 @@ Options:
 
                 any
             }
+            Current::Json => {
+                println!("{}", session::to_json(&session.resolve_tools()?)?);
+                true
+            }
         };
         session.add_event_end(ActivityKind::Current, ExitCode::Success);
         if !result {
@@ -0,0 +1,113 @@
+use serde::Deserialize;
+
+use failure::Fail;
+
+use notion_core::diagnostics;
+use notion_core::prompt::confirm;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{throw, ExitCode, Fallible, NotionFail};
+use notion_fail_derive::*;
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_fix: bool,
+}
+
+#[derive(Debug, Fail, NotionFail)]
+#[fail(
+    display = "Found {} problem{} with the Notion installation",
+    count, plural
+)]
+#[notion_fail(code = "ConfigurationError")]
+struct DoctorFailedError {
+    count: usize,
+    plural: &'static str,
+}
+
+pub(crate) enum Doctor {
+    Help,
+    Diagnose { fix: bool },
+}
+
+impl Command for Doctor {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Diagnose problems with the Notion installation
+
+Usage:
+    notion doctor [options]
+
+Options:
+    -h, --help     Display this message
+    --fix          Offer to fix each problem that can be fixed
+
+Checks that the shim directory is on PATH ahead of any other Node, that
+NOTION_HOME is writable, that the shims are intact and all still in use, that
+the archives in the inventory match their recorded checksums, and that
+nodejs.org is reachable. Exits with a non-zero status if any problem remains.
+";
+
+    fn help() -> Self {
+        Doctor::Help
+    }
+
+    fn parse(_: Notion, Args { flag_fix }: Args) -> Fallible<Self> {
+        Ok(Doctor::Diagnose { fix: flag_fix })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Doctor);
+        match self {
+            Doctor::Help => Help::Command(CommandName::Doctor).run(session)?,
+            Doctor::Diagnose { fix } => {
+                let mut remaining = 0;
+                let mut fixable = 0;
+                for report in diagnostics::run() {
+                    if report.findings.is_empty() {
+                        println!("{}: ok", report.check);
+                        continue;
+                    }
+                    println!("{}:", report.check);
+                    for finding in report.findings {
+                        println!("  - {}", finding.problem);
+                        match finding.fix {
+                            Some(ref repair) if fix => {
+                                if confirm(&format!("    {}?", repair), Some(true))? {
+                                    repair.apply()?;
+                                    println!("    Fixed");
+                                } else {
+                                    remaining += 1;
+                                }
+                            }
+                            Some(_) => {
+                                fixable += 1;
+                                remaining += 1;
+                            }
+                            None => remaining += 1,
+                        }
+                    }
+                }
+
+                if fixable > 0 {
+                    println!();
+                    println!(
+                        "Run `notion doctor --fix` to fix {} of these problems.",
+                        fixable
+                    );
+                }
+                if remaining > 0 {
+                    throw!(DoctorFailedError {
+                        count: remaining,
+                        plural: if remaining == 1 { "" } else { "s" },
+                    });
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Doctor, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use notion_core::path;
+use notion_core::session::{ActivityKind, Session};
+use notion_core::setup::{self, ProfileShell};
+use notion_fail::{throw, ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::error::CliParseError;
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_shell: Option<String>,
+    flag_toolchain: bool,
+}
+
+pub(crate) enum Env {
+    Help,
+    Print(ProfileShell),
+    Toolchain(ProfileShell),
+}
+
+impl Command for Env {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Print the shell setup for the shim directory
+
+Usage:
+    notion env [--shell=<shell>] [--toolchain]
+    notion env -h | --help
+
+Options:
+    --shell=<shell>  Print commands for `sh` (bash, zsh), `fish` or `powershell` [default: sh]
+    --toolchain      Also put the bin directories of the current toolchain on PATH
+    -h, --help       Display this message
+
+Prints the commands that set NOTION_HOME and put the shim directory at the front of
+PATH, for a shell profile to evaluate. Since the shim directory can be moved with
+`layout.shim_dir` (or NOTION_SHIM_DIR), profiles set up by the installer run, e.g.:
+
+    eval \"$(\"$NOTION_HOME/notion\" env)\"
+
+With --toolchain, the bin directories of the Node, npm, Yarn and Deno versions
+that run in the current directory come first, fetched if they are missing, so
+that they run without the shims. This suits environments without a shell
+profile, such as a Docker RUN step:
+
+    RUN eval \"$(notion env --toolchain)\" && npm ci
+";
+
+    fn help() -> Self {
+        Env::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            flag_shell,
+            flag_toolchain,
+        }: Args,
+    ) -> Fallible<Self> {
+        let name = flag_shell.unwrap_or_else(|| "sh".to_string());
+        match ProfileShell::parse(&name) {
+            Some(shell) if flag_toolchain => Ok(Env::Toolchain(shell)),
+            Some(shell) => Ok(Env::Print(shell)),
+            None => throw!(CliParseError {
+                usage: None,
+                error: format!(
+                    "invalid shell: `{}` (expected `sh`, `fish` or `powershell`)",
+                    name
+                ),
+            }),
+        }
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Env);
+        match self {
+            Env::Help => Help::Command(CommandName::Env).run(session)?,
+            Env::Print(shell) => print!(
+                "{}",
+                setup::profile_script(shell, &path::notion_home()?, &path::shim_dir()?)
+            ),
+            Env::Toolchain(shell) => {
+                let shim_dir = path::shim_dir()?;
+                let bins = session.platform_bins()?;
+                let dirs: Vec<&Path> = bins
+                    .iter()
+                    .map(|dir| dir.as_path())
+                    .chain(Some(shim_dir.as_path()))
+                    .collect();
+                print!(
+                    "{}",
+                    setup::path_script(shell, &path::notion_home()?, &dirs)
+                );
+            }
+        };
+        session.add_event_end(ActivityKind::Env, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use failure::Fail;
+
+use notion_core::envfile::{self, Format};
+use notion_core::output;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+use crate::command::{Command, CommandName, Help};
+use crate::error::CliParseError;
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_format: Option<String>,
+    flag_output: Option<String>,
+}
+
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "No toolchain found\n\nPin a Node version in this project with `notion pin node`.")]
+#[notion_fail(code = "ConfigurationError")]
+struct NoToolchainError;
+
+pub(crate) enum Envfile {
+    Help,
+    Write {
+        format: Format,
+        output: Option<PathBuf>,
+    },
+}
+
+impl Command for Envfile {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Write an environment file for the toolchain
+
+Usage:
+    notion envfile [--format=<format>] [--output=<file>]
+    notion envfile -h | --help
+
+Options:
+    --format=<format>    Write `dotenv` lines or an `sh` script [default: dotenv]
+    -o, --output=<file>  Write to <file> instead of the standard output
+    -h, --help           Display this message
+
+The file sets PATH to find the project's pinned tools (or the user toolchain's,
+outside of a project) and exports NOTION_NODE_VERSION (and the Yarn and Deno
+versions, if pinned), so that programs which load env files, such as Docker
+Compose or IDE run configurations, use exactly the pinned toolchain.
+";
+
+    fn help() -> Self {
+        Envfile::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            flag_format,
+            flag_output,
+        }: Args,
+    ) -> Fallible<Self> {
+        let name = flag_format.unwrap_or_else(|| "dotenv".to_string());
+        let format = match Format::parse(&name) {
+            Some(format) => format,
+            None => throw!(CliParseError {
+                usage: None,
+                error: format!("invalid format: `{}` (expected `dotenv` or `sh`)", name),
+            }),
+        };
+        Ok(Envfile::Write {
+            format,
+            output: flag_output.map(PathBuf::from),
+        })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Envfile);
+        match self {
+            Envfile::Help => Help::Command(CommandName::Envfile).run(session)?,
+            Envfile::Write { format, output } => {
+                let platform = match session.current_platform()? {
+                    Some(platform) => platform,
+                    None => throw!(NoToolchainError),
+                };
+                let image = platform.checkout(session)?;
+                let contents = envfile::render(format, &envfile::variables(&image)?);
+                match output {
+                    Some(file) => {
+                        fs::write(&file, contents).unknown()?;
+                        output::info(format!("Wrote {}", file.display()));
+                    }
+                    None => print!("{}", contents),
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Envfile, ExitCode::Success);
+        Ok(())
+    }
+}
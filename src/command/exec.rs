@@ -0,0 +1,146 @@
+use std::ffi::OsStr;
+use std::io;
+use std::mem;
+use std::process;
+
+use serde::Deserialize;
+
+use failure::Fail;
+
+use notion_core::envfile;
+use notion_core::interrupt;
+use notion_core::isolate;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{throw, ExitCode, Fallible, NotionFail, ResultExt};
+use notion_fail_derive::*;
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_isolated: bool,
+    arg_command: String,
+    arg_args: Vec<String>,
+}
+
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "No toolchain found\n\nPin a Node version in this project with `notion pin node`.")]
+#[notion_fail(code = "ConfigurationError")]
+struct NoToolchainError;
+
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "Could not find executable `{}`", command)]
+#[notion_fail(code = "ExecutableNotFound")]
+struct ExecutableNotFoundError {
+    command: String,
+}
+
+pub(crate) enum Exec {
+    Help,
+    Run {
+        command: String,
+        args: Vec<String>,
+        isolated: bool,
+    },
+}
+
+impl Command for Exec {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Run a command with the current toolchain
+
+Usage:
+    notion exec [--isolated] [--] <command> [<args>...]
+    notion exec -h | --help
+
+Options:
+    --isolated  Run the command in a minimal environment with only the pinned toolchain
+    -h, --help  Display this message
+
+Runs <command> with PATH finding the project's pinned tools (or the user toolchain's,
+outside of a project) before any others. Arguments starting with `-` must follow a
+`--`, e.g. `notion exec -- node --version`.
+
+With --isolated, the command inherits only a few variables describing the user and
+terminal (HOME, TERM, LANG, ...), and the entries of PATH that lead to other Node
+installations (including nvm, nodenv, fnm and n) are removed, to reproduce issues that
+only happen on some machines without their environment getting in the way.
+";
+
+    fn help() -> Self {
+        Exec::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            flag_isolated,
+            arg_command,
+            arg_args,
+        }: Args,
+    ) -> Fallible<Self> {
+        Ok(Exec::Run {
+            command: arg_command,
+            args: arg_args,
+            isolated: flag_isolated,
+        })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Exec);
+        match self {
+            Exec::Help => Help::Command(CommandName::Exec).run(session)?,
+            Exec::Run {
+                command,
+                args,
+                isolated,
+            } => {
+                let platform = match session.current_platform()? {
+                    Some(platform) => platform,
+                    None => throw!(NoToolchainError),
+                };
+                let image = platform.checkout(session)?;
+
+                let child = if isolated {
+                    isolate::command(&image, OsStr::new(&command), &args)?
+                } else {
+                    let mut child = process::Command::new(&command);
+                    child.args(&args).envs(envfile::variables(&image)?);
+                    child
+                };
+                run_child(session, ActivityKind::Exec, child, command)?;
+            }
+        };
+        session.add_event_end(ActivityKind::Exec, ExitCode::Success);
+        Ok(())
+    }
+}
+
+/// Runs `child`, the process of `command`, exiting Notion with its status if it fails.
+pub(crate) fn run_child(
+    session: &mut Session,
+    activity: ActivityKind,
+    mut child: process::Command,
+    command: String,
+) -> Fallible<()> {
+    let status = {
+        let _child = interrupt::defer_to_child();
+        child.status()
+    };
+    let status = match status {
+        Ok(status) => status,
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+            throw!(ExecutableNotFoundError { command })
+        }
+        Err(error) => return Err(error).unknown(),
+    };
+    if !status.success() {
+        // ISSUE (#36): if None, in unix, find out the signal
+        let code = status.code().unwrap_or(1);
+        session.add_event_tool_end(activity, code);
+        mem::replace(session, Session::new()).exit_tool(code);
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::Write;
+
+use serde::Deserialize;
+
+use notion_core::export::ToolchainLock;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{ExitCode, Fallible, ResultExt};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    arg_file: Option<String>,
+}
+
+pub(crate) enum Export {
+    Help,
+    Write(Option<String>),
+}
+
+impl Command for Export {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Write the exact versions of the toolchain and global packages
+
+Usage:
+    notion export [<file>]
+    notion export -h | --help
+
+Options:
+    -h, --help     Display this message
+
+Records the exact versions of Node, npm, Yarn and Deno that run in the current
+directory (the project's, or else the user defaults) and of the packages
+installed in the user toolchain. The lock is written to stdout unless a file is
+given, and sets up the same toolchain elsewhere, e.g. on a CI machine:
+
+    notion export > toolchain.lock
+    notion setup --from toolchain.lock
+";
+
+    fn help() -> Self {
+        Export::Help
+    }
+
+    fn parse(_: Notion, Args { arg_file }: Args) -> Fallible<Self> {
+        Ok(Export::Write(arg_file))
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Export);
+        match self {
+            Export::Help => Help::Command(CommandName::Export).run(session)?,
+            Export::Write(file) => {
+                let json = ToolchainLock::current(session)?.to_json()?;
+                match file {
+                    Some(file) => {
+                        let mut file = File::create(file).unknown()?;
+                        file.write_all(json.as_bytes()).unknown()?;
+                    }
+                    None => println!("{}", json),
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Export, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use serde::Deserialize;
 
+use notion_core::prefetch;
 use notion_core::session::{ActivityKind, Session};
 use notion_core::tool::ToolSpec;
 use notion_core::version::VersionSpec;
@@ -12,11 +13,13 @@ use crate::Notion;
 pub(crate) struct Args {
     arg_tool: String,
     arg_version: String,
+    flag_lts_updates: bool,
 }
 
 pub(crate) enum Fetch {
     Help,
     Tool(ToolSpec),
+    LtsUpdates,
 }
 
 impl Command for Fetch {
@@ -27,9 +30,11 @@ Fetch a tool to the local machine
 
 Usage:
     notion fetch <tool> <version>
+    notion fetch --lts-updates
     notion fetch -h | --help
 
 Options:
+    --lts-updates  Fetch newer LTS patches of the pinned Node versions
     -h, --help     Display this message
 ";
 
@@ -42,10 +47,14 @@ Options:
         Args {
             arg_tool,
             arg_version,
+            flag_lts_updates,
         }: Args,
     ) -> Fallible<Self> {
+        if flag_lts_updates {
+            return Ok(Fetch::LtsUpdates);
+        }
         let version = VersionSpec::parse(&arg_version)?;
-        Ok(Fetch::Tool(ToolSpec::from_str(&arg_tool, version)))
+        Ok(Fetch::Tool(ToolSpec::from_cli_str(&arg_tool, version)?))
     }
 
     fn run(self, session: &mut Session) -> Fallible<()> {
@@ -55,6 +64,11 @@ Options:
             Fetch::Tool(toolspec) => {
                 session.fetch(&toolspec)?;
             }
+            Fetch::LtsUpdates => {
+                for version in prefetch::fetch_lts_updates(session)? {
+                    println!("Fetched Node v{}", version);
+                }
+            }
         };
         session.add_event_end(ActivityKind::Fetch, ExitCode::Success);
         Ok(())
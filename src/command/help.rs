@@ -4,7 +4,10 @@ use notion_core::session::{ActivityKind, Session};
 use notion_fail::{throw, ExitCode, Fallible};
 
 use crate::command::{
-    Activate, Command, CommandName, Config, Current, Deactivate, Fetch, Install, Pin, Use, Version,
+    Activate, Alias, Cache, Check, Ci, Command, CommandName, Completions, Config, Current,
+    Deactivate, Doctor, Env, Envfile, Exec, Export, Fetch, Install, List, Mirror, Outdated, Pin,
+    Refresh, Relocate, Restore, Run, SelfUpdate, Setup, Shell, Snapshot, Uninstall, Update, Use,
+    Version, Which,
 };
 use crate::{CliParseError, Notion};
 #[cfg(feature = "notion-dev")]
@@ -66,10 +69,34 @@ Options:
                 Help::Command(CommandName::Current) => Current::USAGE,
                 Help::Command(CommandName::Deactivate) => Deactivate::USAGE,
                 Help::Command(CommandName::Activate) => Activate::USAGE,
+                Help::Command(CommandName::Relocate) => Relocate::USAGE,
+                Help::Command(CommandName::Snapshot) => Snapshot::USAGE,
+                Help::Command(CommandName::Restore) => Restore::USAGE,
+                Help::Command(CommandName::Mirror) => Mirror::USAGE,
+                Help::Command(CommandName::Ci) => Ci::USAGE,
+                Help::Command(CommandName::Check) => Check::USAGE,
                 Help::Command(CommandName::Help) => Help::USAGE,
                 Help::Command(CommandName::Version) => Version::USAGE,
                 Help::Command(CommandName::Fetch) => Fetch::USAGE,
                 Help::Command(CommandName::Install) => Install::USAGE,
+                Help::Command(CommandName::Setup) => Setup::USAGE,
+                Help::Command(CommandName::Shell) => Shell::USAGE,
+                Help::Command(CommandName::Envfile) => Envfile::USAGE,
+                Help::Command(CommandName::Env) => Env::USAGE,
+                Help::Command(CommandName::Exec) => Exec::USAGE,
+                Help::Command(CommandName::List) => List::USAGE,
+                Help::Command(CommandName::Uninstall) => Uninstall::USAGE,
+                Help::Command(CommandName::Cache) => Cache::USAGE,
+                Help::Command(CommandName::Doctor) => Doctor::USAGE,
+                Help::Command(CommandName::Completions) => Completions::USAGE,
+                Help::Command(CommandName::Run) => Run::USAGE,
+                Help::Command(CommandName::Alias) => Alias::USAGE,
+                Help::Command(CommandName::Update) => Update::USAGE,
+                Help::Command(CommandName::Refresh) => Refresh::USAGE,
+                Help::Command(CommandName::SelfUpdate) => SelfUpdate::USAGE,
+                Help::Command(CommandName::Which) => Which::USAGE,
+                Help::Command(CommandName::Export) => Export::USAGE,
+                Help::Command(CommandName::Outdated) => Outdated::USAGE,
                 #[cfg(feature = "notion-dev")]
                 Help::Command(CommandName::Shim) => Shim::USAGE,
             }
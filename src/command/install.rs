@@ -1,24 +1,63 @@
+use std::path::PathBuf;
+
+use semver::Version;
 use serde::Deserialize;
 
+use failure::Fail;
+
+use notion_core::gc;
+use notion_core::output;
 use notion_core::session::{ActivityKind, Session};
+use notion_core::style::display_warning;
 use notion_core::tool::ToolSpec;
 use notion_core::version::VersionSpec;
-use notion_fail::{ExitCode, Fallible};
+use notion_fail::{throw, ExitCode, Fallible, NotionFail};
+use notion_fail_derive::*;
 
 use result::ResultOptionExt;
 
-use crate::command::{Command, CommandName, Help};
+use crate::command::{pick_version, split_tool_version, Command, CommandName, Help};
 use crate::Notion;
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct Args {
     arg_tool: String,
     arg_version: Option<String>,
+    flag_from_source: bool,
+    flag_interactive: bool,
+    flag_file: Option<String>,
+    flag_version: Option<String>,
+}
+
+/// Thrown when a tool other than Node is to be built from source.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "{} cannot be built from source\n\nOnly Node can be installed with `--from-source`.", tool)]
+#[notion_fail(code = "InvalidArguments")]
+struct SourceBuildToolError {
+    tool: String,
+}
+
+/// Thrown when a tool other than Node is to be installed from a local build.
+#[derive(Debug, Fail, NotionFail)]
+#[fail(display = "{} cannot be installed from a local build\n\nOnly Node can be installed with `--file`.", tool)]
+#[notion_fail(code = "InvalidArguments")]
+struct LocalBuildToolError {
+    tool: String,
 }
 
 pub(crate) enum Install {
     Help,
-    Tool(ToolSpec),
+    Tool {
+        toolspec: ToolSpec,
+        /// Whether to let the user pick the version, when Notion can prompt.
+        pick: bool,
+    },
+    FromSource(VersionSpec),
+    Local {
+        path: PathBuf,
+        /// The version of the build, if given rather than read from the build.
+        version: Option<Version>,
+    },
 }
 
 impl Command for Install {
@@ -28,14 +67,44 @@ impl Command for Install {
 Install a tool in the user toolchain
 
 Usage:
-    notion install <tool> [<version>]
+    notion install [--from-source] <tool> [<version>]
+    notion install --interactive <tool> [<version>]
+    notion install --file=<path> [--version=<version>] <tool>
     notion install -h | --help
 
 Options:
-    -h, --help     Display this message
+    --from-source        Build Node from its source (for platforms without prebuilt binaries)
+    --interactive        Pick the version from those that match
+    --file=<path>        Install Node from a local archive or directory
+    --version=<version>  The version of Node in <path>, if it can't be read from it
+    -h, --help           Display this message
 
 Supported Tools:
-    Currently Notion supports installing `node` and `yarn` - support for more tools is coming soon!
+    Notion supports installing `node`, `yarn`, `npm` and `deno`, and any package from
+    the npm registry that provides binaries (e.g. `notion install typescript`).
+
+Installing `npm` replaces the npm bundled with the default Node, which is used again
+once Node is installed anew.
+
+Without a version, or with `--interactive`, a tool's version is picked from a list
+of the published versions that match (LTS releases and fetched versions are
+marked), which can be filtered by typing part of a version. Without a terminal,
+the newest matching version is installed instead.
+
+Packages are installed with the default Node of the user toolchain, each in its
+own directory, and their binaries always run with that Node version, whatever
+Node the current project pins.
+
+With `--from-source`, the Node source is downloaded and built with `./configure`
+and `make`, which requires a C++ toolchain and Python. Set MAKEFLAGS (e.g. `-j8`)
+to build in parallel.
+
+With `--file`, Node is installed from a build on the local filesystem instead of
+being fetched: a distribution archive (`.tar.gz` or `.zip`) or the directory one
+unpacks into, which must have the layout of a Node distribution. Its version is
+read from its `include/node/node_version.h` header or its file name, unless given
+with `--version`. The build is copied into the inventory, where it is used like
+any fetched version, by pins and shims alike.
 ";
 
     fn help() -> Self {
@@ -47,14 +116,38 @@ Supported Tools:
         Args {
             arg_tool,
             arg_version,
+            flag_from_source,
+            flag_interactive,
+            flag_file,
+            flag_version,
         }: Args,
     ) -> Fallible<Self> {
+        let (arg_tool, arg_version) = split_tool_version(arg_tool, arg_version);
+        if let Some(file) = flag_file {
+            if arg_tool != "node" {
+                throw!(LocalBuildToolError { tool: arg_tool });
+            }
+            let version = flag_version
+                .or(arg_version)
+                .map(|version| VersionSpec::parse_version(version.trim_start_matches('v')))
+                .invert()?;
+            return Ok(Install::Local {
+                path: PathBuf::from(file),
+                version,
+            });
+        }
+
+        let pick = flag_interactive || arg_version.is_none();
         let version = arg_version
             .map(VersionSpec::parse)
             .invert()?
             .unwrap_or_default();
 
-        Ok(Install::Tool(ToolSpec::from_str(&arg_tool, version)))
+        match ToolSpec::from_cli_str(&arg_tool, version)? {
+            ToolSpec::Node(version) if flag_from_source => Ok(Install::FromSource(version)),
+            _ if flag_from_source => throw!(SourceBuildToolError { tool: arg_tool }),
+            toolspec => Ok(Install::Tool { toolspec, pick }),
+        }
     }
 
     fn run(self, session: &mut Session) -> Fallible<()> {
@@ -63,10 +156,32 @@ Supported Tools:
             Install::Help => {
                 Help::Command(CommandName::Install).run(session)?;
             }
-            Install::Tool(toolspec) => {
+            Install::Tool { toolspec, pick } => {
+                let toolspec = if pick {
+                    pick_version(session, toolspec)?
+                } else {
+                    toolspec
+                };
                 session.install(&toolspec)?;
             }
+            Install::FromSource(version) => {
+                session.install_from_source(&version)?;
+            }
+            Install::Local { path, version } => {
+                session.install_local_node(&path, version.as_ref())?;
+            }
         };
+        // the install itself succeeded, so a failed collection is only reported
+        match gc::collect_if_due(session) {
+            Ok(ref removed) if !removed.is_empty() => {
+                for version in removed {
+                    output::info(format!("Removed unused {} from the inventory", version));
+                }
+                output::info("(configured by `gc` in config.toml; set NOTION_NO_GC=1 to skip)");
+            }
+            Ok(_) => {}
+            Err(error) => display_warning(format!("could not clean up the inventory: {}", error)),
+        }
         session.add_event_end(ActivityKind::Install, ExitCode::Success);
         Ok(())
     }
@@ -0,0 +1,83 @@
+use serde::Deserialize;
+
+use notion_core::inventory::query;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{throw, ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::error::CliParseError;
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_format: Option<String>,
+}
+
+/// The output of `notion list`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Format {
+    Human,
+    Json,
+}
+
+pub(crate) enum List {
+    Help,
+    List(Format),
+}
+
+impl Command for List {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+List the fetched tool versions
+
+Usage:
+    notion list [--format=<format>]
+    notion list -h | --help
+
+Options:
+    --format=<format>  Print `human` lines or a `json` array [default: human]
+    -h, --help         Display this message
+
+Lists every version of Node, Yarn and Deno in the inventory, followed by the
+fetched packages, marking the versions in the user toolchain as `default` and
+the versions pinned by the current project as `pinned`.
+";
+
+    fn help() -> Self {
+        List::Help
+    }
+
+    fn parse(_: Notion, Args { flag_format }: Args) -> Fallible<Self> {
+        let name = flag_format.unwrap_or_else(|| "human".to_string());
+        let format = match name.as_str() {
+            "human" => Format::Human,
+            "json" => Format::Json,
+            _ => throw!(CliParseError {
+                usage: None,
+                error: format!("invalid format: `{}` (expected `human` or `json`)", name),
+            }),
+        };
+        Ok(List::List(format))
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::List);
+        match self {
+            List::Help => Help::Command(CommandName::List).run(session)?,
+            List::List(format) => {
+                let entries = query::entries(session)?;
+                match format {
+                    Format::Human => {
+                        for entry in entries.iter() {
+                            println!("{}", entry.to_line());
+                        }
+                    }
+                    Format::Json => println!("{}", query::to_json(&entries)?),
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::List, ExitCode::Success);
+        Ok(())
+    }
+}
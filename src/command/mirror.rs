@@ -0,0 +1,98 @@
+use std::env;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use notion_core::mirror;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{ExitCode, Fallible, ResultExt};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    cmd_sync: bool,
+    arg_dir: Option<String>,
+}
+
+pub(crate) enum Mirror {
+    Help,
+    Sync(Option<PathBuf>),
+}
+
+impl Command for Mirror {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Maintain an offline mirror of Node and Yarn releases
+
+Usage:
+    notion mirror sync [<dir>]
+    notion mirror -h | --help
+
+Options:
+    -h, --help     Display this message
+
+Downloads the releases listed in the [mirror] section of the Notion
+configuration, along with their indexes and checksums, into a directory laid
+out like nodejs.org. For example:
+
+    [mirror]
+    dir = \"/srv/mirror\"
+    node = [\"10\", \"8\"]
+    yarn = [\"1\"]
+    platforms = [\"linux-x64\", \"darwin-x64\", \"win-x64\"]
+
+Files that are already present are not downloaded again. Once the directory
+is served over HTTP, point the hooks in hooks.toml at it:
+
+    [node.index]
+    prefix = \"https://mirror.example.com/node/\"
+
+    [node.distro]
+    template = \"https://mirror.example.com/node/v{{version}}/node-v{{version}}-{{os}}-{{arch}}.tar.gz\"
+";
+
+    fn help() -> Self {
+        Mirror::Help
+    }
+
+    fn parse(_: Notion, Args { cmd_sync, arg_dir }: Args) -> Fallible<Self> {
+        if !cmd_sync {
+            return Ok(Mirror::Help);
+        }
+
+        let dir = match arg_dir {
+            Some(dir) => {
+                let dir = PathBuf::from(dir);
+                Some(if dir.is_absolute() {
+                    dir
+                } else {
+                    env::current_dir().unknown()?.join(dir)
+                })
+            }
+            None => None,
+        };
+        Ok(Mirror::Sync(dir))
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Mirror);
+        match self {
+            Mirror::Help => Help::Command(CommandName::Mirror).run(session)?,
+            Mirror::Sync(dir) => {
+                let synced = mirror::sync(&session.config()?.mirror, dir)?;
+                for version in &synced.node {
+                    println!("Mirrored node v{}", version);
+                }
+                for version in &synced.yarn {
+                    println!("Mirrored yarn v{}", version);
+                }
+                println!("Downloaded {} new files", synced.downloaded);
+            }
+        };
+        session.add_event_end(ActivityKind::Mirror, ExitCode::Success);
+        Ok(())
+    }
+}
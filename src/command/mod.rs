@@ -1,33 +1,85 @@
 mod activate;
+mod alias;
+mod cache;
+mod check;
+mod ci;
+mod completions;
 mod config;
 mod current;
 mod deactivate;
+mod doctor;
+mod env;
+mod envfile;
+mod exec;
+mod export;
 mod fetch;
 mod help;
 mod install;
+mod list;
+mod mirror;
+mod outdated;
 mod pin;
+mod refresh;
+mod relocate;
+mod restore;
+mod run;
+mod self_update;
+mod setup;
+mod shell;
 mod shim;
+mod snapshot;
+mod uninstall;
+mod update;
 mod use_;
 mod version;
+mod which;
 
 pub(crate) use self::activate::Activate;
+pub(crate) use self::alias::Alias;
+pub(crate) use self::cache::Cache;
+pub(crate) use self::check::Check;
+pub(crate) use self::ci::Ci;
+pub(crate) use self::completions::Completions;
 pub(crate) use self::config::Config;
 pub(crate) use self::current::Current;
 pub(crate) use self::deactivate::Deactivate;
+pub(crate) use self::doctor::Doctor;
+pub(crate) use self::env::Env;
+pub(crate) use self::envfile::Envfile;
+pub(crate) use self::exec::Exec;
+pub(crate) use self::export::Export;
 pub(crate) use self::fetch::Fetch;
 pub(crate) use self::help::Help;
 pub(crate) use self::install::Install;
+pub(crate) use self::list::List;
+pub(crate) use self::mirror::Mirror;
+pub(crate) use self::outdated::Outdated;
 pub(crate) use self::pin::Pin;
+pub(crate) use self::refresh::Refresh;
+pub(crate) use self::relocate::Relocate;
+pub(crate) use self::restore::Restore;
+pub(crate) use self::run::Run;
+pub(crate) use self::self_update::SelfUpdate;
+pub(crate) use self::setup::Setup;
+pub(crate) use self::shell::Shell;
 #[cfg(feature = "notion-dev")]
 pub(crate) use self::shim::Shim;
+pub(crate) use self::snapshot::Snapshot;
+pub(crate) use self::uninstall::Uninstall;
+pub(crate) use self::update::Update;
 pub(crate) use self::use_::Use;
 pub(crate) use self::version::Version;
+pub(crate) use self::which::Which;
 
 use docopt::Docopt;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 
+use notion_core::inventory::query;
+use notion_core::prompt;
 use notion_core::session::Session;
+use notion_core::tool::ToolSpec;
+use notion_core::version::VersionSpec;
 use notion_fail::{throw, FailExt, Fallible};
 
 use crate::{CliParseError, DocoptExt, Notion};
@@ -46,6 +98,30 @@ pub(crate) enum CommandName {
     Current,
     Deactivate,
     Activate,
+    Relocate,
+    Snapshot,
+    Restore,
+    Mirror,
+    Ci,
+    Check,
+    Setup,
+    Shell,
+    Envfile,
+    Env,
+    Exec,
+    List,
+    Uninstall,
+    Cache,
+    Doctor,
+    Completions,
+    Run,
+    Alias,
+    Update,
+    Refresh,
+    SelfUpdate,
+    Which,
+    Export,
+    Outdated,
     #[cfg(feature = "notion-dev")]
     Shim,
     Help,
@@ -66,6 +142,30 @@ impl Display for CommandName {
                 CommandName::Deactivate => "deactivate",
                 CommandName::Activate => "activate",
                 CommandName::Current => "current",
+                CommandName::Relocate => "relocate",
+                CommandName::Snapshot => "snapshot",
+                CommandName::Restore => "restore",
+                CommandName::Mirror => "mirror",
+                CommandName::Ci => "ci",
+                CommandName::Check => "check",
+                CommandName::Setup => "setup",
+                CommandName::Shell => "shell",
+                CommandName::Envfile => "envfile",
+                CommandName::Env => "env",
+                CommandName::Exec => "exec",
+                CommandName::List => "list",
+                CommandName::Uninstall => "uninstall",
+                CommandName::Cache => "cache",
+                CommandName::Doctor => "doctor",
+                CommandName::Completions => "completions",
+                CommandName::Run => "run",
+                CommandName::Alias => "alias",
+                CommandName::Update => "update",
+                CommandName::Refresh => "refresh",
+                CommandName::SelfUpdate => "self-update",
+                CommandName::Which => "which",
+                CommandName::Export => "export",
+                CommandName::Outdated => "outdated",
                 #[cfg(feature = "notion-dev")]
                 CommandName::Shim => "shim",
                 CommandName::Help => "help",
@@ -88,6 +188,30 @@ impl FromStr for CommandName {
             "current" => CommandName::Current,
             "deactivate" => CommandName::Deactivate,
             "activate" => CommandName::Activate,
+            "relocate" => CommandName::Relocate,
+            "snapshot" => CommandName::Snapshot,
+            "restore" => CommandName::Restore,
+            "mirror" => CommandName::Mirror,
+            "ci" => CommandName::Ci,
+            "check" => CommandName::Check,
+            "setup" => CommandName::Setup,
+            "shell" => CommandName::Shell,
+            "envfile" => CommandName::Envfile,
+            "env" => CommandName::Env,
+            "exec" => CommandName::Exec,
+            "list" => CommandName::List,
+            "uninstall" => CommandName::Uninstall,
+            "cache" => CommandName::Cache,
+            "doctor" => CommandName::Doctor,
+            "completions" => CommandName::Completions,
+            "run" => CommandName::Run,
+            "alias" => CommandName::Alias,
+            "update" => CommandName::Update,
+            "refresh" => CommandName::Refresh,
+            "self-update" => CommandName::SelfUpdate,
+            "which" => CommandName::Which,
+            "export" => CommandName::Export,
+            "outdated" => CommandName::Outdated,
             #[cfg(feature = "notion-dev")]
             "shim" => CommandName::Shim,
             "help" => CommandName::Help,
@@ -142,3 +266,47 @@ pub(crate) trait Command: Sized {
         }
     }
 }
+
+/// Splits a `<tool>@<version>` argument (e.g. `yarn@1.x`) into the tool and the version,
+/// unless the version is given as a separate argument.
+pub(crate) fn split_tool_version(
+    tool: String,
+    version: Option<String>,
+) -> (String, Option<String>) {
+    match (tool.find('@'), version) {
+        (Some(index), None) if index > 0 => (
+            tool[..index].to_string(),
+            Some(tool[index + 1..].to_string()),
+        ),
+        (_, version) => (tool, version),
+    }
+}
+
+/// Asks the user to pick one of the published versions of the tool of `toolspec` that
+/// match its version, newest first, returning the tool with the picked version. The
+/// toolspec is returned as it is when Notion can't prompt (e.g. because it isn't run
+/// from a terminal) or when no published version matches, leaving it to resolve as
+/// usual.
+pub(crate) fn pick_version(session: &Session, toolspec: ToolSpec) -> Fallible<ToolSpec> {
+    if !prompt::is_interactive() {
+        return Ok(toolspec);
+    }
+    let name = match toolspec {
+        ToolSpec::Node(_) => "node",
+        ToolSpec::Yarn(_) => "yarn",
+        ToolSpec::Npm(_) => "npm",
+        ToolSpec::Deno(_) => "deno",
+        ToolSpec::Package(..) => return Ok(toolspec),
+    };
+    let available = query::available(session, &toolspec)?;
+    if available.is_empty() {
+        return Ok(toolspec);
+    }
+
+    let labels: Vec<String> = available.iter().map(|version| version.to_label()).collect();
+    let picked = prompt::select(&format!("Which {} version?", name), &labels, Some(0))?;
+    Ok(ToolSpec::from_str(
+        name,
+        VersionSpec::exact(&available[picked].version),
+    ))
+}
@@ -0,0 +1,63 @@
+use serde::Deserialize;
+
+use notion_core::lifecycle;
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args;
+
+pub(crate) enum Outdated {
+    Help,
+    Outdated,
+}
+
+impl Command for Outdated {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+List the pinned Node versions that are no longer supported
+
+Usage:
+    notion outdated
+    notion outdated -h | --help
+
+Options:
+    -h, --help     Display this message
+
+Checks the Node versions of the user toolchain, of the current project, of the
+projects that Notion has run tools in, and of the installed packages against the
+Node release schedule, and lists those that are past their end of life, or past
+their LTS window and only receive critical fixes. Running a tool with such a
+version also warns about it, once a day.
+";
+
+    fn help() -> Self {
+        Outdated::Help
+    }
+
+    fn parse(_: Notion, _: Args) -> Fallible<Self> {
+        Ok(Outdated::Outdated)
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Outdated);
+        match self {
+            Outdated::Help => Help::Command(CommandName::Outdated).run(session)?,
+            Outdated::Outdated => {
+                let outdated = lifecycle::outdated(session)?;
+                if outdated.is_empty() {
+                    println!("Every pinned Node version is supported");
+                }
+                for pin in outdated {
+                    println!("{}", pin.to_line());
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Outdated, ExitCode::Success);
+        Ok(())
+    }
+}
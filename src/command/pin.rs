@@ -1,23 +1,34 @@
 use serde::Deserialize;
 
+use notion_core::prompt;
 use notion_core::session::{ActivityKind, Session};
 use notion_core::style::{display_error, display_unknown_error, ErrorContext};
 use notion_core::tool::ToolSpec;
 use notion_core::version::VersionSpec;
-use notion_fail::{ExitCode, Fallible};
+use notion_fail::{throw, ExitCode, Fallible};
 
-use crate::command::{Command, CommandName, Help};
+use crate::command::{pick_version, split_tool_version, Command, CommandName, Help};
+use crate::error::CliParseError;
 use crate::Notion;
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct Args {
     arg_tool: String,
-    arg_version: String,
+    arg_version: Option<String>,
+    flag_dry_run: bool,
+    flag_engines: bool,
+    flag_interactive: bool,
 }
 
 pub(crate) enum Pin {
     Help,
-    Tool(ToolSpec),
+    Tool {
+        toolspec: ToolSpec,
+        engines: bool,
+        dry_run: bool,
+        /// Whether to let the user pick the version.
+        pick: bool,
+    },
 }
 
 impl Command for Pin {
@@ -27,11 +38,21 @@ impl Command for Pin {
 Select a tool for the current project's toolchain
 
 Usage:
-    notion pin <tool> <version>
+    notion pin [--dry-run] [--engines] [--interactive] <tool> [<version>]
     notion pin -h | --help
 
 Options:
+    --dry-run      Show the change to package.json without writing it
+    --engines      Also write the pinned Node version to `engines.node`
+    --interactive  Pick the version from those that match
     -h, --help     Display this message
+
+The version can also be given as `<tool>@<version>`, e.g. `notion pin yarn@1.x`.
+
+With `--interactive`, the version is picked from a list of the published versions
+that match the given version, or of all of them (LTS releases and fetched versions
+are marked), which can be filtered by typing part of a version. Without a terminal,
+a version must be given, and the newest version that matches it is pinned.
 ";
 
     fn help() -> Self {
@@ -43,18 +64,58 @@ Options:
         Args {
             arg_tool,
             arg_version,
+            flag_dry_run,
+            flag_engines,
+            flag_interactive,
         }: Args,
     ) -> Fallible<Self> {
-        let version = VersionSpec::parse(&arg_version)?;
-        Ok(Pin::Tool(ToolSpec::from_str(&arg_tool, version)))
+        let (arg_tool, arg_version) = split_tool_version(arg_tool, arg_version);
+        let pick = flag_interactive && prompt::is_interactive();
+        let version = match arg_version {
+            Some(version) => VersionSpec::parse(&version)?,
+            None if pick => VersionSpec::Latest,
+            None => throw!(CliParseError {
+                usage: None,
+                error: format!(
+                    "no version given for `{}` (e.g. `notion pin {}@latest`)",
+                    arg_tool, arg_tool
+                ),
+            }),
+        };
+        Ok(Pin::Tool {
+            toolspec: ToolSpec::from_str(&arg_tool, version),
+            engines: flag_engines,
+            dry_run: flag_dry_run,
+            pick,
+        })
     }
 
     fn run(self, session: &mut Session) -> Fallible<()> {
         session.add_event_start(ActivityKind::Pin);
-        match self {
-            Pin::Help => Help::Command(CommandName::Pin).run(session)?,
-            Pin::Tool(toolspec) => session.pin(&toolspec)?,
+        let dry_run = match self {
+            Pin::Help => {
+                Help::Command(CommandName::Pin).run(session)?;
+                false
+            }
+            Pin::Tool {
+                toolspec,
+                engines,
+                dry_run,
+                pick,
+            } => {
+                let toolspec = if pick {
+                    pick_version(session, toolspec)?
+                } else {
+                    toolspec
+                };
+                session.pin(&toolspec, engines, dry_run)?;
+                dry_run
+            }
         };
+        if dry_run {
+            session.add_event_end(ActivityKind::Pin, ExitCode::Success);
+            return Ok(());
+        }
         if let Some(project) = session.project()? {
             let errors = project.autoshim();
 
@@ -0,0 +1,56 @@
+use serde::Deserialize;
+
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args;
+
+pub(crate) enum Refresh {
+    Help,
+    Refresh,
+}
+
+impl Command for Refresh {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Fetch the Node index again
+
+Usage:
+    notion refresh
+    notion refresh -h | --help
+
+Options:
+    -h, --help     Display this message
+
+The Node index is cached, and only fetched again once it expires (as the server's
+caching headers say, or after `ttl_minutes` of the `[index]` configuration). This
+fetches it again right away. While the registry can't be reached, versions are
+otherwise resolved against the expired index.
+";
+
+    fn help() -> Self {
+        Refresh::Help
+    }
+
+    fn parse(_: Notion, _: Args) -> Fallible<Self> {
+        Ok(Refresh::Refresh)
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Refresh);
+        match self {
+            Refresh::Help => Help::Command(CommandName::Refresh).run(session)?,
+            Refresh::Refresh => {
+                let count = session.refresh_index()?;
+                println!("Refreshed the Node index ({} versions)", count);
+            }
+        };
+        session.add_event_end(ActivityKind::Refresh, ExitCode::Success);
+        Ok(())
+    }
+}
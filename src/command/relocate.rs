@@ -0,0 +1,71 @@
+use std::env;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use notion_core::session::{ActivityKind, Session};
+use notion_core::shim;
+use notion_fail::{ExitCode, Fallible, ResultExt};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    arg_dir: String,
+}
+
+pub(crate) enum Relocate {
+    Help,
+    Dir(PathBuf),
+}
+
+impl Command for Relocate {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Move the shim directory to a new location
+
+Usage:
+    notion relocate <dir>
+    notion relocate -h | --help
+
+Options:
+    -h, --help     Display this message
+
+The new location is saved in the Notion configuration file and all existing
+shims are regenerated there. Make sure the new directory is on your PATH.
+Since NOTION_SHIM_DIR overrides the saved location, it must be unset first.
+";
+
+    fn help() -> Self {
+        Relocate::Help
+    }
+
+    fn parse(_: Notion, Args { arg_dir }: Args) -> Fallible<Self> {
+        let dir = PathBuf::from(arg_dir);
+        let dir = if dir.is_absolute() {
+            dir
+        } else {
+            env::current_dir().unknown()?.join(dir)
+        };
+        Ok(Relocate::Dir(dir))
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Relocate);
+        match self {
+            Relocate::Help => Help::Command(CommandName::Relocate).run(session)?,
+            Relocate::Dir(dir) => {
+                let moved = shim::relocate(session.config_mut()?, dir.clone())?;
+                println!("Moved {} shims to {}", moved.len(), dir.display());
+                println!(
+                    "Update your PATH to include {} (run `notion activate` to update the current shell)",
+                    dir.display()
+                );
+            }
+        };
+        session.add_event_end(ActivityKind::Relocate, ExitCode::Success);
+        Ok(())
+    }
+}
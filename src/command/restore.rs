@@ -0,0 +1,56 @@
+use std::fs::read_to_string;
+
+use serde::Deserialize;
+
+use notion_core::session::{ActivityKind, Session};
+use notion_core::snapshot::Snapshot;
+use notion_fail::{ExitCode, Fallible, ResultExt};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    arg_file: String,
+}
+
+pub(crate) enum Restore {
+    Help,
+    File(String),
+}
+
+impl Command for Restore {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Re-fetch the tools and defaults recorded by `notion snapshot`
+
+Usage:
+    notion restore <file>
+    notion restore -h | --help
+
+Options:
+    -h, --help     Display this message
+";
+
+    fn help() -> Self {
+        Restore::Help
+    }
+
+    fn parse(_: Notion, Args { arg_file }: Args) -> Fallible<Self> {
+        Ok(Restore::File(arg_file))
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Restore);
+        match self {
+            Restore::Help => Help::Command(CommandName::Restore).run(session)?,
+            Restore::File(file) => {
+                let src = read_to_string(file).unknown()?;
+                Snapshot::from_json(&src)?.restore(session)?;
+            }
+        };
+        session.add_event_end(ActivityKind::Restore, ExitCode::Success);
+        Ok(())
+    }
+}
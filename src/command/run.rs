@@ -0,0 +1,97 @@
+use std::process;
+
+use serde::Deserialize;
+
+use notion_core::envfile;
+use notion_core::session::{ActivityKind, Session};
+use notion_core::version::VersionSpec;
+use notion_fail::{ExitCode, Fallible};
+
+use result::ResultOptionExt;
+
+use crate::command::exec::run_child;
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_node: String,
+    flag_yarn: Option<String>,
+    arg_command: String,
+    arg_args: Vec<String>,
+}
+
+pub(crate) enum Run {
+    Help,
+    Run {
+        node: VersionSpec,
+        yarn: Option<VersionSpec>,
+        command: String,
+        args: Vec<String>,
+    },
+}
+
+impl Command for Run {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Run a command with a chosen Node and Yarn
+
+Usage:
+    notion run --node=<version> [--yarn=<version>] [--] <command> [<args>...]
+    notion run -h | --help
+
+Options:
+    --node=<version>  Run with the Node version matching <version>
+    --yarn=<version>  Run with the Yarn version matching <version>
+    -h, --help        Display this message
+
+Runs <command> with PATH finding the chosen tools before any others, fetching them
+if necessary, without changing the project's pins or the user toolchain. Exits with
+the status of the command. Arguments starting with `-` must follow a `--`, e.g.:
+
+    notion run --node 12.22.1 --yarn 1.22 -- yarn test --verbose
+";
+
+    fn help() -> Self {
+        Run::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            flag_node,
+            flag_yarn,
+            arg_command,
+            arg_args,
+        }: Args,
+    ) -> Fallible<Self> {
+        Ok(Run::Run {
+            node: VersionSpec::parse(&flag_node)?,
+            yarn: flag_yarn.map(VersionSpec::parse).invert()?,
+            command: arg_command,
+            args: arg_args,
+        })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Run);
+        match self {
+            Run::Help => Help::Command(CommandName::Run).run(session)?,
+            Run::Run {
+                node,
+                yarn,
+                command,
+                args,
+            } => {
+                let image = session.ephemeral_platform(node, yarn)?.checkout(session)?;
+
+                let mut child = process::Command::new(&command);
+                child.args(&args).envs(envfile::variables(&image)?);
+                run_child(session, ActivityKind::Run, child, command)?;
+            }
+        };
+        session.add_event_end(ActivityKind::Run, ExitCode::Success);
+        Ok(())
+    }
+}
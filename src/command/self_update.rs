@@ -0,0 +1,73 @@
+use semver::Version;
+use serde::Deserialize;
+
+use notion_core::session::{ActivityKind, Session};
+use notion_core::updater::{self, Channel};
+use notion_fail::{ExitCode, Fallible, ResultExt};
+
+use crate::command::{Command, CommandName, Help};
+use crate::{Notion, VERSION};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_channel: Option<String>,
+}
+
+pub(crate) enum SelfUpdate {
+    Help,
+    SelfUpdate { channel: Option<Channel> },
+}
+
+impl Command for SelfUpdate {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Update Notion to its newest release
+
+Usage:
+    notion self-update [--channel=<channel>]
+    notion self-update -h | --help
+
+Options:
+    --channel=<channel>  The release channel to update from: `stable` or `canary`
+    -h, --help           Display this message
+
+Replaces Notion, its launchers and the node, npm, npx, yarn and deno shims with
+those of the newest release of the channel. The channel is `channel` of the
+`[update]` configuration unless given, and `stable` by default. Every file is
+downloaded and verified against its checksum before any is replaced, and if one
+can't be replaced, the ones already replaced are put back.
+";
+
+    fn help() -> Self {
+        SelfUpdate::Help
+    }
+
+    fn parse(_: Notion, Args { flag_channel }: Args) -> Fallible<Self> {
+        let channel = match flag_channel {
+            Some(channel) => Some(channel.parse()?),
+            None => None,
+        };
+        Ok(SelfUpdate::SelfUpdate { channel })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::SelfUpdate);
+        match self {
+            SelfUpdate::Help => Help::Command(CommandName::SelfUpdate).run(session)?,
+            SelfUpdate::SelfUpdate { channel } => {
+                let channel = match channel {
+                    Some(channel) => channel,
+                    None => session.config()?.update.channel,
+                };
+                let current = Version::parse(VERSION).unknown()?;
+                match updater::self_update(channel, &current)? {
+                    Some(version) => println!("Updated Notion to v{}", version),
+                    None => println!("Notion v{} is up to date ({})", current, channel),
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::SelfUpdate, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use notion_core::export::ToolchainLock;
+use notion_core::path;
+use notion_core::session::{ActivityKind, Session};
+use notion_core::setup;
+use notion_fail::{ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    flag_undo: bool,
+    flag_from: Option<String>,
+}
+
+pub(crate) enum Setup {
+    Help,
+    Install,
+    Uninstall,
+    From(String),
+}
+
+impl Command for Setup {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Add Notion to the user environment
+
+Usage:
+    notion setup [--undo]
+    notion setup --from=<file>
+    notion setup -h | --help
+
+Options:
+    --undo         Remove Notion from the user environment
+    --from=<file>  Install the toolchain recorded by `notion export` instead
+    -h, --help     Display this message
+
+On Windows, this sets NOTION_HOME and puts the shim directory at the front of
+the user PATH, in the registry. Terminals opened afterwards pick up the change.
+On Unix, the installer configures your shell profile instead.
+
+With --from, this fetches the exact versions of Node, npm, Yarn, Deno and the
+packages recorded in the file, and makes them the user defaults, so that a fresh
+machine runs the same toolchain as the one it was exported from. In shell-less
+environments, such as a Docker RUN step, `notion env --toolchain` prints the PATH to use.
+";
+
+    fn help() -> Self {
+        Setup::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            flag_undo,
+            flag_from,
+        }: Args,
+    ) -> Fallible<Self> {
+        Ok(if let Some(file) = flag_from {
+            Setup::From(file)
+        } else if flag_undo {
+            Setup::Uninstall
+        } else {
+            Setup::Install
+        })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Setup);
+        match self {
+            Setup::Help => Help::Command(CommandName::Setup).run(session)?,
+            Setup::Install => {
+                let shim_dir = path::shim_dir()?;
+                let changes = setup::install(&path::notion_home()?, &shim_dir)?;
+                if changes.is_empty() {
+                    println!("Notion is already set up in the user environment");
+                } else {
+                    if changes.path {
+                        println!("Added {} to the user PATH", shim_dir.display());
+                    }
+                    if changes.notion_home {
+                        println!("Set NOTION_HOME to {}", path::notion_home()?.display());
+                    }
+                    println!("Open a new terminal for the change to take effect");
+                }
+            }
+            Setup::Uninstall => {
+                let shim_dir = path::shim_dir()?;
+                let changes = setup::uninstall(&shim_dir)?;
+                if changes.is_empty() {
+                    println!("Notion is not set up in the user environment");
+                } else {
+                    if changes.path {
+                        println!("Removed {} from the user PATH", shim_dir.display());
+                    }
+                    if changes.notion_home {
+                        println!("Unset NOTION_HOME");
+                    }
+                }
+            }
+            Setup::From(file) => {
+                ToolchainLock::from_file(Path::new(&file))?.setup(session)?;
+            }
+        };
+        session.add_event_end(ActivityKind::Setup, ExitCode::Success);
+        Ok(())
+    }
+}
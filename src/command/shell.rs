@@ -0,0 +1,117 @@
+use serde::Deserialize;
+
+use notion_core::output;
+use notion_core::session::{ActivityKind, Session};
+use notion_core::shell::{CurrentShell, Postscript, Shell as PostscriptShell};
+use notion_core::tool::ToolSpec;
+use notion_core::version::VersionSpec;
+use notion_fail::{throw, ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::error::CliParseError;
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    arg_spec: String,
+    arg_tool: String,
+    flag_reset: bool,
+}
+
+pub(crate) enum Shell {
+    Help,
+    Override(ToolSpec),
+    Reset(String),
+}
+
+/// The tools whose version can be overridden for a shell session.
+const OVERRIDABLE_TOOLS: &'static [&'static str] = &["node", "npm", "yarn", "deno"];
+
+impl Command for Shell {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Override a tool version in the current shell
+
+Usage:
+    notion shell <spec>
+    notion shell --reset <tool>
+    notion shell -h | --help
+
+Options:
+    --reset        Remove the override of <tool> from the current shell
+    -h, --help     Display this message
+
+The <spec> is a tool and a version, such as `node@11` or `yarn@1.12`. The
+override takes precedence over the project and user toolchains in the current
+shell only; nothing is written to package.json or the user toolchain.
+";
+
+    fn help() -> Self {
+        Shell::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            arg_spec,
+            arg_tool,
+            flag_reset,
+        }: Args,
+    ) -> Fallible<Self> {
+        if flag_reset {
+            check_overridable(&arg_tool)?;
+            return Ok(Shell::Reset(arg_tool));
+        }
+
+        let mut parts = arg_spec.splitn(2, '@');
+        let tool = parts.next().unwrap_or("").to_string();
+        let version = match parts.next() {
+            Some(version) => version,
+            None => {
+                throw!(CliParseError {
+                    usage: None,
+                    error: format!("missing version in `{}` (e.g. `node@11`)", arg_spec),
+                });
+            }
+        };
+        check_overridable(&tool)?;
+
+        let version = VersionSpec::parse(version)?;
+        Ok(Shell::Override(ToolSpec::from_cli_str(&tool, version)?))
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Shell);
+        match self {
+            Shell::Help => Help::Command(CommandName::Shell).run(session)?,
+            Shell::Override(toolspec) => {
+                let shell = CurrentShell::detect()?;
+                let postscript = session.shell_override(&toolspec)?;
+                shell.save_postscript(&postscript)?;
+                if let Postscript::ToolVersion { tool, version } = postscript {
+                    output::info(format!("Using {} v{} in the current shell", tool, version));
+                }
+            }
+            Shell::Reset(tool) => {
+                let shell = CurrentShell::detect()?;
+                shell.save_postscript(&Postscript::ClearToolVersion(tool))?;
+            }
+        };
+        session.add_event_end(ActivityKind::Shell, ExitCode::Success);
+        Ok(())
+    }
+}
+
+fn check_overridable(tool: &str) -> Fallible<()> {
+    if !OVERRIDABLE_TOOLS.contains(&tool) {
+        throw!(CliParseError {
+            usage: None,
+            error: format!(
+                "cannot override `{}` in a shell (expected `node`, `npm`, `yarn` or `deno`)",
+                tool
+            ),
+        });
+    }
+    Ok(())
+}
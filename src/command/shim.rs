@@ -6,6 +6,7 @@ use std::fs;
 use std::path::PathBuf;
 
 use console::style;
+use notion_core::output::{self, Verbosity};
 use notion_core::project::Project;
 use notion_core::session::{ActivityKind, Session};
 use notion_core::style::{display_error, display_unknown_error, ErrorContext};
@@ -129,6 +130,8 @@ Options:
             flag_verbose,
         }: Args,
     ) -> Fallible<Self> {
+        // `notion --verbose` makes every shim command verbose as well
+        let flag_verbose = flag_verbose || output::enabled(Verbosity::Verbose);
         Ok(if flag_help {
             Shim::Help
         } else if cmd_auto {
@@ -0,0 +1,66 @@
+use std::fs::File;
+use std::io::Write;
+
+use serde::Deserialize;
+
+use notion_core::session::{ActivityKind, Session};
+use notion_core::snapshot;
+use notion_fail::{ExitCode, Fallible, ResultExt};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    arg_file: Option<String>,
+}
+
+pub(crate) enum Snapshot {
+    Help,
+    Write(Option<String>),
+}
+
+impl Command for Snapshot {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Write a manifest of the installed tools and user defaults
+
+Usage:
+    notion snapshot [<file>]
+    notion snapshot -h | --help
+
+Options:
+    -h, --help     Display this message
+
+The manifest is written to stdout unless a file is given. It records tool
+versions only (no binaries), and can be replayed with `notion restore`.
+";
+
+    fn help() -> Self {
+        Snapshot::Help
+    }
+
+    fn parse(_: Notion, Args { arg_file }: Args) -> Fallible<Self> {
+        Ok(Snapshot::Write(arg_file))
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Snapshot);
+        match self {
+            Snapshot::Help => Help::Command(CommandName::Snapshot).run(session)?,
+            Snapshot::Write(file) => {
+                let json = snapshot::Snapshot::current(session)?.to_json()?;
+                match file {
+                    Some(file) => {
+                        let mut file = File::create(file).unknown()?;
+                        file.write_all(json.as_bytes()).unknown()?;
+                    }
+                    None => println!("{}", json),
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Snapshot, ExitCode::Success);
+        Ok(())
+    }
+}
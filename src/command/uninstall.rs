@@ -0,0 +1,80 @@
+use serde::Deserialize;
+
+use notion_core::gc;
+use notion_core::session::{ActivityKind, Session};
+use notion_core::tool::ToolSpec;
+use notion_core::version::VersionSpec;
+use notion_fail::{ExitCode, Fallible};
+
+use result::ResultOptionExt;
+
+use crate::command::{split_tool_version, Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    arg_tool: String,
+    arg_version: Option<String>,
+    flag_force: bool,
+}
+
+pub(crate) enum Uninstall {
+    Help,
+    Tool { toolspec: ToolSpec, force: bool },
+}
+
+impl Command for Uninstall {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Remove a tool version from the inventory
+
+Usage:
+    notion uninstall [--force] <tool> [<version>]
+    notion uninstall -h | --help
+
+Options:
+    --force     Remove the version even if it is the default or pinned by this project
+    -h, --help  Display this message
+
+Removes the downloaded archive and the unpacked image of an exact version of
+`node`, `yarn`, `npm` or `deno`, e.g. `notion uninstall node@10.13.0`. See
+`notion cache prune` to remove all of the versions that are no longer used.
+";
+
+    fn help() -> Self {
+        Uninstall::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            arg_tool,
+            arg_version,
+            flag_force,
+        }: Args,
+    ) -> Fallible<Self> {
+        let (arg_tool, arg_version) = split_tool_version(arg_tool, arg_version);
+        let version = arg_version
+            .map(VersionSpec::parse)
+            .invert()?
+            .unwrap_or_default();
+        Ok(Uninstall::Tool {
+            toolspec: ToolSpec::from_cli_str(&arg_tool, version)?,
+            force: flag_force,
+        })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Uninstall);
+        match self {
+            Uninstall::Help => Help::Command(CommandName::Uninstall).run(session)?,
+            Uninstall::Tool { toolspec, force } => {
+                let removed = gc::uninstall(session, &toolspec, force)?;
+                println!("Removed {}", removed);
+            }
+        };
+        session.add_event_end(ActivityKind::Uninstall, ExitCode::Success);
+        Ok(())
+    }
+}
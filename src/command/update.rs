@@ -0,0 +1,57 @@
+use serde::Deserialize;
+
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args;
+
+pub(crate) enum Update {
+    Help,
+    Update,
+}
+
+impl Command for Update {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Refresh the Node index and the tools installed as named versions
+
+Usage:
+    notion update
+    notion update -h | --help
+
+Options:
+    -h, --help     Display this message
+
+Fetches the Node index again, and installs again each tool of the user toolchain
+that was installed as `lts` or as an alias (see `notion alias`), so that it is
+the version the name now stands for. Projects pinning a named version resolve it
+against the refreshed index when they are next used.
+";
+
+    fn help() -> Self {
+        Update::Help
+    }
+
+    fn parse(_: Notion, _: Args) -> Fallible<Self> {
+        Ok(Update::Update)
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Update);
+        match self {
+            Update::Help => Help::Command(CommandName::Update).run(session)?,
+            Update::Update => {
+                for toolspec in session.update()? {
+                    println!("Updated {}", toolspec);
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Update, ExitCode::Success);
+        Ok(())
+    }
+}
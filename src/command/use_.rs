@@ -4,47 +4,79 @@
 
 use serde::Deserialize;
 
-use notion_core::session::Session;
-use notion_fail::{throw, Fallible};
+use notion_core::output;
+use notion_core::session::{ActivityKind, Session};
+use notion_core::shell::{CurrentShell, Postscript, Shell};
+use notion_fail::{ExitCode, Fallible};
 
-use crate::command::Command;
-use crate::error::CliParseError;
+use crate::command::{Command, CommandName, Help};
 use crate::Notion;
 
 #[derive(Debug, Deserialize)]
-pub(crate) struct Args;
+pub(crate) struct Args {
+    flag_profile: String,
+}
 
 pub(crate) enum Use {
     Help,
-    Use,
+    Profile(String),
 }
 
 impl Command for Use {
     type Args = Args;
 
     const USAGE: &'static str = "
+Select a toolchain profile of the current project in the current shell
+
+Usage:
+    notion use --profile=<name>
+    notion use -h | --help
+
+Options:
+    --profile=<name>  Use the toolchain profile <name> (or `default`)
+    -h, --help        Display this message
+
+Profiles are named alternatives to the project's toolchain, listed in package.json
+next to it, e.g. to test against an older Node line without switching branches:
+
+    \"toolchain\": { \"node\": \"12.4.0\" },
+    \"toolchainProfiles\": {
+        \"legacy\": { \"node\": \"8.16.0\" }
+    }
+
+The selected profile is exported as NOTION_PROFILE, which can also be set directly
+(e.g. in CI). While a profile is selected, `notion pin` updates that profile.
+
 To install a tool in your user toolchain, use 'notion install'
 To pin a tool in a project toolchain, use 'notion pin'
-
-See 'notion --help' for more information.
 ";
 
     fn help() -> Self {
         Use::Help
     }
 
-    fn parse(_: Notion, _: Args) -> Fallible<Self> {
-        Ok(Use::Use)
+    fn parse(_: Notion, Args { flag_profile }: Args) -> Fallible<Self> {
+        Ok(Use::Profile(flag_profile))
     }
 
-    fn run(self, _: &mut Session) -> Fallible<()> {
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Use);
+        match self {
+            Use::Help => Help::Command(CommandName::Use).run(session)?,
+            Use::Profile(profile) => {
+                let shell = CurrentShell::detect()?;
+                let postscript = session.use_profile(&profile)?;
+                shell.save_postscript(&postscript)?;
+                match postscript {
+                    Postscript::Profile(Some(profile)) => output::info(format!(
+                        "Using the `{}` toolchain profile in the current shell",
+                        profile
+                    )),
+                    _ => output::info("Using the default toolchain in the current shell"),
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Use, ExitCode::Success);
         Ok(())
     }
-
-    fn go(_: Notion, _: &mut Session) -> Fallible<()> {
-        throw!(CliParseError {
-            usage: None,
-            error: format!("no such command: `use`\n{}", Use::USAGE)
-        });
-    }
 }
@@ -0,0 +1,80 @@
+use serde::Deserialize;
+
+use notion_core::session::{ActivityKind, Session};
+use notion_fail::{ExitCode, Fallible};
+
+use crate::command::{Command, CommandName, Help};
+use crate::Notion;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Args {
+    arg_tool: String,
+    flag_json: bool,
+}
+
+pub(crate) enum Which {
+    Help,
+    Which { tool: String, json: bool },
+}
+
+impl Command for Which {
+    type Args = Args;
+
+    const USAGE: &'static str = "
+Show which version of a tool runs, and why
+
+Usage:
+    notion which [--json] <tool>
+    notion which -h | --help
+
+Options:
+    --json         Print the tool as a JSON object
+    -h, --help     Display this message
+
+Prints the executable that the <tool> shim (`node`, `npm`, `npx`, `yarn` or
+`deno`) runs in the current directory, its version, and where that version is
+selected: the project pin, its .nvmrc or .node-version file, its engines
+fallback, the user default, a `notion shell` override, or the npm bundled with
+Node. Versions that aren't fetched yet are fetched, as the shim would.
+";
+
+    fn help() -> Self {
+        Which::Help
+    }
+
+    fn parse(
+        _: Notion,
+        Args {
+            arg_tool,
+            flag_json,
+        }: Args,
+    ) -> Fallible<Self> {
+        Ok(Which::Which {
+            tool: arg_tool,
+            json: flag_json,
+        })
+    }
+
+    fn run(self, session: &mut Session) -> Fallible<()> {
+        session.add_event_start(ActivityKind::Which);
+        match self {
+            Which::Help => Help::Command(CommandName::Which).run(session)?,
+            Which::Which { tool, json } => {
+                let resolved = session.resolve_tool(&tool)?;
+                if json {
+                    println!("{}", resolved.to_json()?);
+                } else {
+                    if let Some(ref path) = resolved.path {
+                        println!("{}", path.display());
+                    }
+                    println!(
+                        "{} v{} ({})",
+                        resolved.tool, resolved.version, resolved.source
+                    );
+                }
+            }
+        };
+        session.add_event_end(ActivityKind::Which, ExitCode::Success);
+        Ok(())
+    }
+}
@@ -0,0 +1,6 @@
+use notion_core::tool::{Deno, Tool};
+
+/// The entry point for the `deno` shim.
+pub fn main() {
+    Deno::launch()
+}
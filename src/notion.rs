@@ -4,18 +4,25 @@ use failure;
 mod command;
 mod error;
 
+use std::env;
 use std::string::ToString;
 
 use docopt::Docopt;
 use serde::Deserialize;
 
+use notion_core::conflict;
+use notion_core::env::{is_json_output, NONINTERACTIVE, OFFLINE, PROGRESS, VERBOSITY};
+use notion_core::output::Verbosity;
+use notion_core::prefetch;
 use notion_core::session::{ActivityKind, Session};
-use notion_core::style::{display_error, display_unknown_error, ErrorContext};
+use notion_core::style::{display_error, display_unknown_error, ErrorContext, ProgressMode};
 use notion_fail::{throw, ExitCode, FailExt, Fallible, NotionError};
 
 use crate::command::{
-    Activate, Command, CommandName, Config, Current, Deactivate, Fetch, Help, Install, Pin, Use,
-    Version,
+    Activate, Alias, Cache, Check, Ci, Command, CommandName, Completions, Config, Current,
+    Deactivate, Doctor, Env, Envfile, Exec, Export, Fetch, Help, Install, List, Mirror, Outdated,
+    Pin, Refresh, Relocate, Restore, Run, SelfUpdate, Setup, Shell, Snapshot, Uninstall, Update,
+    Use, Version, Which,
 };
 use crate::error::{CliParseError, CommandUnimplementedError, DocoptExt, NotionErrorExt};
 #[cfg(feature = "notion-dev")]
@@ -29,12 +36,15 @@ struct Args {
     arg_args: Vec<String>,
     flag_version: bool,
     flag_verbose: bool,
+    flag_quiet: bool,
+    flag_no_input: bool,
+    flag_offline: bool,
+    flag_progress: Option<String>,
 }
 
 pub(crate) struct Notion {
     command: CommandName,
     args: Vec<String>,
-    verbose: bool,
 }
 
 impl Notion {
@@ -42,14 +52,18 @@ impl Notion {
 Notion: the hassle-free JavaScript toolchain manager
 
 Usage:
-    notion [-v | --verbose] [<command> <args> ...]
+    notion [-v | --verbose | -q | --quiet] [--no-input] [--offline] [--progress=<mode>] [<command> <args> ...]
     notion -h | --help
     notion -V | --version
 
 Options:
-    -h, --help     Display this message
-    -V, --version  Print version info and exit
-    -v, --verbose  Use verbose output
+    -h, --help         Display this message
+    -V, --version      Print version info and exit
+    -v, --verbose      Use verbose output
+    -q, --quiet        Display errors only, without warnings or progress
+    --no-input         Never prompt; take the default answer or fail
+    --offline          Resolve versions from the local inventory only
+    --progress=<mode>  Display progress as `fancy` bars, `plain` lines or `json` events
 
 Some common notion commands are:
     fetch          Fetch a tool to the local machine
@@ -59,18 +73,37 @@ Some common notion commands are:
     current        Display the currently activated Node version
     deactivate     Disable Notion in the current shell
     activate       Re-Enable Notion in the current shell
+    relocate       Move the shim directory to a new location
+    snapshot       Write a manifest of the installed tools
+    restore        Re-fetch the tools recorded in a snapshot
+    mirror         Maintain an offline mirror of Node and Yarn releases
+    ci             Helpers for continuous integration
+    check          Validate the current project's toolchain
+    setup          Add Notion to the user environment (Windows)
+    shell          Override a tool version in the current shell
+    use            Select a toolchain profile in the current shell
+    envfile        Write an environment file for the toolchain
+    env            Print the shell setup for the shim directory
+    exec           Run a command with the current toolchain
+    list           List the fetched tool versions
+    uninstall      Remove a tool version from the inventory
+    cache          Prune the inventory of fetched versions
+    doctor         Diagnose problems with the Notion installation
+    completions    Print a shell completion script
+    run            Run a command with a chosen Node and Yarn
+    alias          Manage the names given to versions
+    update         Refresh the Node index and the named versions
+    refresh        Fetch the Node index again
+    self-update    Update Notion to its newest release
+    which          Show which version of a tool runs, and why
+    export         Write the exact versions of the toolchain and global packages
+    outdated       List the pinned Node versions that are no longer supported
     help           Display this message
     version        Print version info and exit
 
 See 'notion help <command>' for more information on a specific command.
 ";
 
-    // This isn't used yet but we can use it for verbose mode in the future.
-    #[allow(dead_code)]
-    pub(crate) fn verbose(&self) -> bool {
-        self.verbose
-    }
-
     pub(crate) fn full_argv(&self) -> Vec<String> {
         let mut argv = vec![String::from("notion"), self.command.to_string()];
         let mut sub_argv = self.args.clone();
@@ -79,7 +112,11 @@ See 'notion help <command>' for more information on a specific command.
     }
 
     fn go(session: &mut Session) -> Fallible<()> {
-        Self::parse()?.run(session)
+        let notion = Self::parse()?;
+        // the warning is only advice, so failing to detect a conflict never fails the
+        // command (it is checked once the flags have selected the verbosity)
+        let _ = conflict::warn_once();
+        notion.run(session)
     }
 
     fn parse() -> Fallible<Notion> {
@@ -108,19 +145,48 @@ See 'notion help <command>' for more information on a specific command.
             }) => Notion {
                 command: CommandName::Help,
                 args: vec![],
-                verbose: false,
             },
 
             Ok(Args {
                 arg_command: Some(cmd),
                 arg_args,
                 flag_verbose,
+                flag_quiet,
+                flag_no_input,
+                flag_offline,
+                flag_progress,
                 ..
-            }) => Notion {
-                command: cmd,
-                args: arg_args,
-                verbose: flag_verbose,
-            },
+            }) => {
+                // prompts check the environment, so the flag is forwarded through it
+                // (which also reaches any child processes)
+                if flag_no_input {
+                    env::set_var(NONINTERACTIVE, "1");
+                }
+                if flag_offline {
+                    env::set_var(OFFLINE, "1");
+                }
+                if flag_verbose {
+                    env::set_var(VERBOSITY, Verbosity::Verbose.name());
+                } else if flag_quiet {
+                    env::set_var(VERBOSITY, Verbosity::Quiet.name());
+                }
+                if let Some(mode) = flag_progress {
+                    if ProgressMode::parse(&mode).is_none() {
+                        throw!(CliParseError {
+                            usage: None,
+                            error: format!(
+                                "invalid progress mode: `{}` (expected `fancy`, `plain` or `json`)",
+                                mode
+                            ),
+                        });
+                    }
+                    env::set_var(PROGRESS, mode);
+                }
+                Notion {
+                    command: cmd,
+                    args: arg_args,
+                }
+            }
 
             Err(err) => {
                 // Docopt models `-h` and `--help` as errors, so this
@@ -129,7 +195,6 @@ See 'notion help <command>' for more information on a specific command.
                     Notion {
                         command: CommandName::Help,
                         args: vec![],
-                        verbose: false,
                     }
                 }
                 // Docopt models `-V` and `--version` as errors, so this
@@ -138,7 +203,6 @@ See 'notion help <command>' for more information on a specific command.
                     Notion {
                         command: CommandName::Version,
                         args: vec![],
-                        verbose: false,
                     }
                 }
                 // The only type that gets deserialized is CommandName. If
@@ -173,6 +237,30 @@ See 'notion help <command>' for more information on a specific command.
             CommandName::Current => Current::go(self, session),
             CommandName::Deactivate => Deactivate::go(self, session),
             CommandName::Activate => Activate::go(self, session),
+            CommandName::Relocate => Relocate::go(self, session),
+            CommandName::Snapshot => Snapshot::go(self, session),
+            CommandName::Restore => Restore::go(self, session),
+            CommandName::Mirror => Mirror::go(self, session),
+            CommandName::Ci => Ci::go(self, session),
+            CommandName::Check => Check::go(self, session),
+            CommandName::Setup => Setup::go(self, session),
+            CommandName::Shell => Shell::go(self, session),
+            CommandName::Envfile => Envfile::go(self, session),
+            CommandName::Env => Env::go(self, session),
+            CommandName::Exec => Exec::go(self, session),
+            CommandName::List => List::go(self, session),
+            CommandName::Uninstall => Uninstall::go(self, session),
+            CommandName::Cache => Cache::go(self, session),
+            CommandName::Doctor => Doctor::go(self, session),
+            CommandName::Completions => Completions::go(self, session),
+            CommandName::Run => Run::go(self, session),
+            CommandName::Alias => Alias::go(self, session),
+            CommandName::Update => Update::go(self, session),
+            CommandName::Refresh => Refresh::go(self, session),
+            CommandName::SelfUpdate => SelfUpdate::go(self, session),
+            CommandName::Which => Which::go(self, session),
+            CommandName::Export => Export::go(self, session),
+            CommandName::Outdated => Outdated::go(self, session),
             #[cfg(feature = "notion-dev")]
             CommandName::Shim => Shim::go(self, session),
             CommandName::Help => Help::go(self, session),
@@ -188,6 +276,10 @@ fn display_error_and_usage(err: &NotionError) {
         display_unknown_error(ErrorContext::Notion, err);
     }
 
+    // the usage would break the single JSON object of the error
+    if is_json_output() {
+        return;
+    }
     if let Some(ref usage) = err.usage() {
         eprintln!();
         eprintln!("{}", usage);
@@ -201,7 +293,11 @@ pub fn main() {
     session.add_event_start(ActivityKind::Notion);
 
     let exit_code = match Notion::go(&mut session) {
-        Ok(_) => ExitCode::Success,
+        Ok(_) => {
+            // the prefetch is a best-effort optimization, so it never fails the command
+            let _ = prefetch::spawn_if_due(&session);
+            ExitCode::Success
+        }
         Err(err) => {
             display_error_and_usage(&err);
             session.add_event_error(ActivityKind::Notion, &err);
@@ -7,10 +7,11 @@ use notion_fail::ExitCode;
 
 cfg_if::cfg_if! {
     // Note: Windows and Unix appear to handle a missing executable differently
-    // On Unix, it results in the Command::status() method returning an Err Result
+    // On Unix, it results in the Command::status() method returning an Err Result,
+    // which the shim reports as a missing executable
     // On Windows, it results in the Command::status() method returning Ok(3221225495)
     if #[cfg(target_os = "macos")] {
-        const MISSING_EXECUTABLE_EXIT_CODE: i32 = ExitCode::ExecutionFailure as i32;
+        const MISSING_EXECUTABLE_EXIT_CODE: i32 = ExitCode::ExecutableNotFound as i32;
         const NODE_VERSION_FIXTURES: [DistroMetadata; 1] = [
             DistroMetadata {
                 version: "10.99.1040",
@@ -19,7 +20,7 @@ cfg_if::cfg_if! {
             },
         ];
     } else if #[cfg(target_os = "linux")] {
-        const MISSING_EXECUTABLE_EXIT_CODE: i32 = ExitCode::ExecutionFailure as i32;
+        const MISSING_EXECUTABLE_EXIT_CODE: i32 = ExitCode::ExecutableNotFound as i32;
         const NODE_VERSION_FIXTURES: [DistroMetadata; 1] = [
             DistroMetadata {
                 version: "10.99.1040",
@@ -0,0 +1,38 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn error_as_json() {
+    let s = sandbox()
+        .package_json(r#"{ "name": "test-package" }"#)
+        .env("NOTION_OUTPUT", "json")
+        .env("NOTION_OFFLINE", "1")
+        .build();
+
+    assert_that!(
+        s.notion("pin node latest"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains(
+                "{\"code\":\"offline-version\",\"exit_code\":4,\
+                 \"message\":\"No Node version matching latest is available offline\",\
+                 \"context\":\"notion\",\"suggestion\":\"Versions in the inventory: [..]\"}"
+            )
+    );
+}
+
+#[test]
+fn usage_error_as_json() {
+    let s = sandbox().env("NOTION_OUTPUT", "json").build();
+
+    assert_that!(
+        s.notion("pin"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("{\"code\":\"cli-parse\",\"exit_code\":3,[..]}")
+            .with_stderr_does_not_contain("[..]Usage:[..]")
+    );
+}
@@ -3,6 +3,26 @@ mod support;
 // test files
 
 mod intercept_global_installs;
+mod json_output;
+mod no_default_node;
+mod notion_alias;
+mod notion_check;
+mod notion_completions;
+mod notion_ci;
 mod notion_current;
 mod notion_deactivate;
+mod notion_doctor;
+mod notion_export;
+mod notion_fetch;
+mod notion_install;
+mod notion_outdated;
 mod notion_pin;
+mod notion_refresh;
+mod notion_relocate;
+mod notion_run;
+mod notion_self_update;
+mod notion_uninstall;
+mod notion_which;
+mod offline;
+mod seed_dir;
+mod verbosity;
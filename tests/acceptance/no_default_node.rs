@@ -0,0 +1,44 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn shim_without_default_guides_user() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.npm("--version"),
+        execs()
+            .with_status(ExitCode::ExecutionFailure as i32)
+            .with_stderr_contains("[..]no default version is set[..]")
+            .with_stderr_contains("[..]notion install node latest")
+    );
+}
+
+#[test]
+fn shim_without_default_is_terse_in_ci() {
+    let s = sandbox().env("CI", "true").build();
+
+    assert_that!(
+        s.npm("--version"),
+        execs()
+            .with_status(ExitCode::ExecutionFailure as i32)
+            .with_stderr_contains("[..]no default version is set.")
+            .with_stderr_does_not_contain("[..]notion install node latest")
+    );
+}
+
+#[test]
+fn package_install_without_default_guides_user() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("install typescript"),
+        execs()
+            .with_status(ExitCode::ConfigurationError as i32)
+            .with_stderr_contains("[..]Installing typescript requires a default Node version")
+            .with_stderr_contains("[..]notion install node[..]")
+    );
+}
@@ -0,0 +1,51 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn create_and_list() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("alias create backend 14.17.0"),
+        execs()
+            .with_status(ExitCode::Success as i32)
+            .with_stdout_contains("Created the alias backend of node 14.17.0")
+    );
+    assert_that!(
+        s.notion("alias create classic yarn@1.22"),
+        execs().with_status(ExitCode::Success as i32)
+    );
+    assert_that!(
+        s.notion("alias list"),
+        execs()
+            .with_status(ExitCode::Success as i32)
+            .with_stdout("node backend = 14.17.0\nyarn classic = ^1.22\n")
+    );
+}
+
+#[test]
+fn reserved_name() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("alias create lts 12"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("error: Cannot create the alias `lts`")
+    );
+}
+
+#[test]
+fn unknown_alias() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("--offline install node@frontend"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains("error: No node version is named `frontend`")
+    );
+}
@@ -0,0 +1,40 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+const BASIC_PACKAGE_JSON: &'static str = r#"{
+  "name": "test-package"
+}"#;
+
+const PINNED_PACKAGE_JSON: &'static str = r#"{
+  "name": "test-package",
+  "toolchain": {
+    "node": "10.13.0"
+  }
+}"#;
+
+#[test]
+fn unpinned_project() {
+    let s = sandbox().package_json(BASIC_PACKAGE_JSON).build();
+
+    assert_that!(
+        s.notion("check --offline"),
+        execs()
+            .with_status(ExitCode::ConfigurationError as i32)
+            .with_stderr_contains("[..]The project has no toolchain pins[..]")
+    );
+}
+
+#[test]
+fn pinned_version_not_fetched() {
+    let s = sandbox().package_json(PINNED_PACKAGE_JSON).build();
+
+    assert_that!(
+        s.notion("check --offline"),
+        execs()
+            .with_status(ExitCode::ConfigurationError as i32)
+            .with_stderr_contains("[..]node version 10.13.0 has not been fetched[..]")
+    );
+}
@@ -0,0 +1,39 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+const BASIC_PACKAGE_JSON: &'static str = r#"{
+  "name": "test-package"
+}"#;
+
+const PINNED_PACKAGE_JSON: &'static str = r#"{
+  "name": "test-package",
+  "toolchain": {
+    "node": "10.13.0",
+    "npm": "6.4.1"
+  }
+}"#;
+
+#[test]
+fn cache_key_for_pinned_project() {
+    let s = sandbox().package_json(PINNED_PACKAGE_JSON).build();
+
+    assert_that!(
+        s.notion("ci cache-key"),
+        execs().with_status(0).with_stdout_contains("notion-[..]-[..]-[..]")
+    );
+}
+
+#[test]
+fn cache_key_without_toolchain() {
+    let s = sandbox().package_json(BASIC_PACKAGE_JSON).build();
+
+    assert_that!(
+        s.notion("ci cache-key"),
+        execs()
+            .with_status(ExitCode::ConfigurationError as i32)
+            .with_stderr_contains("[..]No toolchain found[..]")
+    );
+}
@@ -0,0 +1,62 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+const NODE_VERSION_INFO: &'static str = r#"[
+{"version":"v10.99.1040","npm":"6.2.26","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]},
+{"version":"v9.27.6","npm":"5.6.17","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]},
+{"version":"v8.9.10","npm":"5.6.7","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]}
+]
+"#;
+
+#[test]
+fn bash_script() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("completions bash"),
+        execs()
+            .with_status(ExitCode::Success as i32)
+            .with_stdout_contains("complete -F _notion notion")
+            .with_stdout_contains("[..]compgen -W \"fetch install pin [..] version\"[..]")
+    );
+}
+
+#[test]
+fn unknown_shell() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("completions tcsh"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("error: invalid shell: `tcsh`[..]")
+    );
+}
+
+#[test]
+fn versions_from_cached_index() {
+    // even an expired index is good enough for completions
+    let s = sandbox().node_cache(NODE_VERSION_INFO, true).build();
+
+    assert_that!(
+        s.notion("completions --versions node"),
+        execs()
+            .with_status(ExitCode::Success as i32)
+            .with_stdout("10.99.1040\n9.27.6\n8.9.10\n")
+    );
+}
+
+#[test]
+fn versions_of_unknown_tool() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("completions --versions typescript"),
+        execs()
+            .with_status(ExitCode::Success as i32)
+            .with_stdout("")
+    );
+}
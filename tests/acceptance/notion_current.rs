@@ -51,6 +51,81 @@ fn pinned_project_with_user_node_default() {
     );
 }
 
+#[test]
+fn project_with_nvmrc() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .project_file(".nvmrc", "v10.13.0\n")
+        .build();
+
+    assert_that!(
+        s.notion("current"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("project: v10.13.0 (active)")
+    );
+}
+
+#[test]
+fn project_with_node_version_file() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .project_file(".node-version", "8.9.4")
+        .build();
+
+    assert_that!(
+        s.notion("current"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("project: v8.9.4 (active)")
+    );
+}
+
+const ENGINES_PACKAGE_JSON: &'static str = r#"{
+  "name": "test-package",
+  "engines": {
+    "node": ">=8 <10"
+  }
+}"#;
+
+const NODE_VERSION_INFO: &'static str = r#"[
+{"version":"v10.99.1040","npm":"6.2.26","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]},
+{"version":"v9.27.6","npm":"5.6.17","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]},
+{"version":"v8.9.10","npm":"5.6.7","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]}
+]
+"#;
+
+#[test]
+fn project_with_engines() {
+    let s = sandbox()
+        .package_json(ENGINES_PACKAGE_JSON)
+        .config("[project]\nengines = true\n")
+        .node_available_versions(NODE_VERSION_INFO)
+        .build();
+
+    assert_that!(
+        s.notion("current"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("project: v9.27.6 (active)")
+    );
+}
+
+#[test]
+fn project_with_engines_not_enabled() {
+    let s = sandbox()
+        .package_json(ENGINES_PACKAGE_JSON)
+        .node_available_versions(NODE_VERSION_INFO)
+        .build();
+
+    assert_that!(
+        s.notion("current"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr("error: no versions found")
+    );
+}
+
 #[test]
 fn unpinned_project() {
     let s = sandbox().package_json(BASIC_PACKAGE_JSON).build();
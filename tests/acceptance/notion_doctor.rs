@@ -0,0 +1,19 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn missing_launcher() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("--offline doctor"),
+        execs()
+            .with_status(ExitCode::ConfigurationError as i32)
+            .with_stdout_contains("Inventory: ok")
+            .with_stdout_contains("[..]The launcher [..] is missing[..]")
+            .with_stderr_contains("[..]problem[..] with the Notion installation")
+    );
+}
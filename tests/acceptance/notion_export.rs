@@ -0,0 +1,119 @@
+use crate::support::sandbox::{sandbox, DistroMetadata, NodeFixture};
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+const NODE_VERSION_INFO: &'static str = r#"[
+{"version":"v10.99.1040","npm":"6.2.26","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]}
+]
+"#;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        const NODE_VERSION_FIXTURES: [DistroMetadata; 1] = [
+            DistroMetadata {
+                version: "10.99.1040",
+                compressed_size: 1096,
+                uncompressed_size: None,
+            },
+        ];
+    } else {
+        const NODE_VERSION_FIXTURES: [DistroMetadata; 1] = [
+            DistroMetadata {
+                version: "10.99.1040",
+                compressed_size: 273,
+                uncompressed_size: Some(0x00280000),
+            },
+        ];
+    }
+}
+
+const TOOLCHAIN_LOCK: &'static str = r#"{
+  "platform": {
+    "node": {
+      "runtime": "10.99.1040",
+      "npm": null
+    },
+    "yarn": null
+  },
+  "packages": []
+}"#;
+
+#[test]
+fn export_user_default() {
+    let s = sandbox()
+        .platform(r#"{"node":{"runtime":"9.11.2","npm":"5.6.0"},"yarn":"1.12.3"}"#)
+        .build();
+
+    assert_that!(
+        s.notion("export"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("      \"runtime\": \"9.11.2\",")
+            .with_stdout_contains("    \"yarn\": \"1.12.3\"")
+            .with_stdout_contains("  \"packages\": []")
+    );
+}
+
+#[test]
+fn export_project_pin() {
+    let s = sandbox()
+        .package_json(r#"{"name":"test-package","toolchain":{"node":"10.99.1040"}}"#)
+        .platform(r#"{"node":{"runtime":"9.11.2","npm":"5.6.0"}}"#)
+        .build();
+
+    assert_that!(
+        s.notion("export"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("      \"runtime\": \"10.99.1040\",")
+    );
+}
+
+#[test]
+fn setup_from_lock() {
+    let s = sandbox()
+        .project_file("toolchain.lock", TOOLCHAIN_LOCK)
+        .node_available_versions(NODE_VERSION_INFO)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("setup --from=toolchain.lock"),
+        execs().with_status(0)
+    );
+    assert_that!(
+        s.notion("current --user"),
+        execs().with_status(0).with_stdout_contains("v10.99.1040")
+    );
+}
+
+#[test]
+fn setup_from_invalid_lock() {
+    let s = sandbox()
+        .project_file("toolchain.lock", r#"{"packages": 1}"#)
+        .build();
+
+    assert_that!(
+        s.notion("setup --from=toolchain.lock"),
+        execs()
+            .with_status(ExitCode::ConfigurationError as i32)
+            .with_stderr_contains("[..]Could not read the toolchain lock toolchain.lock")
+    );
+}
+
+#[test]
+fn env_with_toolchain() {
+    let s = sandbox()
+        .platform(r#"{"node":{"runtime":"10.99.1040","npm":"6.2.26"}}"#)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("env --toolchain"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("export PATH='[..]10.99.1040[..]':'[..]':\"$PATH\"")
+    );
+}
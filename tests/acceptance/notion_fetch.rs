@@ -0,0 +1,17 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn fetch_package() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("fetch typescript 3.1"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("error: Cannot fetch the package `typescript`")
+    );
+}
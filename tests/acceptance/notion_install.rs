@@ -0,0 +1,121 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        const NODE_EXECUTABLE: &'static str = "node.exe";
+        const NPM_MANIFEST: &'static str = "node_modules/npm/package.json";
+    } else {
+        const NODE_EXECUTABLE: &'static str = "bin/node";
+        const NPM_MANIFEST: &'static str = "lib/node_modules/npm/package.json";
+    }
+}
+
+const NPM_PACKAGE_JSON: &'static str = r#"{"name":"npm","version":"6.2.26"}"#;
+
+const NODE_VERSION_HEADER: &'static str = "#ifndef SRC_NODE_VERSION_H_
+#define SRC_NODE_VERSION_H_
+
+#define NODE_MAJOR_VERSION 10
+#define NODE_MINOR_VERSION 99
+#define NODE_PATCH_VERSION 2000
+
+#endif
+";
+
+#[test]
+fn install_node_from_directory() {
+    let s = sandbox()
+        .project_file(&format!("node-build/{}", NODE_EXECUTABLE), "")
+        .project_file(&format!("node-build/{}", NPM_MANIFEST), NPM_PACKAGE_JSON)
+        .project_file(
+            "node-build/include/node/node_version.h",
+            NODE_VERSION_HEADER,
+        )
+        .build();
+
+    assert_that!(
+        s.notion("install node --file=node-build"),
+        execs().with_status(0)
+    );
+    assert_that!(
+        s.notion("which node"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("node v10.99.2000 (user default)")
+    );
+    assert_that!(
+        s.notion("which npm"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("npm v6.2.26 (bundled with Node)")
+    );
+}
+
+#[test]
+fn install_node_from_directory_with_version() {
+    let s = sandbox()
+        .project_file(&format!("custom-build/{}", NODE_EXECUTABLE), "")
+        .project_file(&format!("custom-build/{}", NPM_MANIFEST), NPM_PACKAGE_JSON)
+        .build();
+
+    assert_that!(
+        s.notion("install node --file=custom-build"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("[..]Could not determine the Node version of custom-build")
+    );
+    assert_that!(
+        s.notion("install node --file=custom-build --version=10.99.2001"),
+        execs().with_status(0)
+    );
+    assert_that!(
+        s.notion("which node"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("node v10.99.2001 (user default)")
+    );
+}
+
+#[test]
+fn install_node_from_invalid_directory() {
+    let s = sandbox()
+        .project_file(&format!("node-build/{}", NODE_EXECUTABLE), "")
+        .build();
+
+    assert_that!(
+        s.notion("install node --file=node-build --version=10.99.2000"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("[..]node-build is not a Node build: it has no [..]package.json")
+    );
+}
+
+#[test]
+fn install_node_from_missing_file() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("install node --file=node-v10.99.2000-linux-x64.tar.gz"),
+        execs()
+            .with_status(ExitCode::FileSystemError as i32)
+            .with_stderr_contains(
+                "[..]Could not find a Node build at node-v10.99.2000-linux-x64.tar.gz"
+            )
+    );
+}
+
+#[test]
+fn install_yarn_from_file() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("install yarn --file=yarn-v1.12.99.tar.gz"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("[..]yarn cannot be installed from a local build")
+    );
+}
@@ -0,0 +1,72 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+const EOL_PLATFORM: &'static str = r#"{"node":{"runtime":"10.99.1040","npm":"6.2.26"}}"#;
+
+const SUPPORTED_PLATFORM: &'static str = r#"{"node":{"runtime":"99.0.0","npm":"6.2.26"}}"#;
+
+const NODE_99_SCHEDULE: &'static str = r#"{
+  "v99": { "start": "2019-04-23", "end": "2020-06-01" }
+}"#;
+
+const EOL_PACKAGE_JSON: &'static str = r#"{
+  "name": "test-package",
+  "toolchain": {
+    "node": "6.19.62"
+  }
+}"#;
+
+#[test]
+fn user_default_past_end_of_life() {
+    let s = sandbox().platform(EOL_PLATFORM).build();
+
+    assert_that!(
+        s.notion("outdated"),
+        execs().with_status(0).with_stdout_contains(
+            "user default: Node v10.99.1040 reached its end of life on 2021-04-30[..]"
+        )
+    );
+}
+
+#[test]
+fn project_past_end_of_life() {
+    let s = sandbox()
+        .platform(SUPPORTED_PLATFORM)
+        .package_json(EOL_PACKAGE_JSON)
+        .build();
+
+    assert_that!(
+        s.notion("outdated"),
+        execs().with_status(0).with_stdout_contains(
+            "project [..]: Node v6.19.62 reached its end of life on 2019-04-30[..]"
+        )
+    );
+}
+
+#[test]
+fn everything_supported() {
+    let s = sandbox().platform(SUPPORTED_PLATFORM).build();
+
+    assert_that!(
+        s.notion("outdated"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("Every pinned Node version is supported")
+    );
+}
+
+#[test]
+fn schedule_from_the_release_team() {
+    let s = sandbox()
+        .platform(SUPPORTED_PLATFORM)
+        .node_schedule(NODE_99_SCHEDULE)
+        .build();
+
+    assert_that!(
+        s.notion("outdated"),
+        execs().with_status(0).with_stdout_contains(
+            "user default: Node v99.0.0 reached its end of life on 2020-06-01[..]"
+        )
+    );
+}
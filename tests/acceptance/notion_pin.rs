@@ -1,4 +1,4 @@
-use crate::support::sandbox::{sandbox, DistroMetadata, NodeFixture, YarnFixture};
+use crate::support::sandbox::{sandbox, DistroMetadata, NodeFixture, NpmFixture, YarnFixture};
 use hamcrest2::{assert_that, core::Matcher};
 use test_support::matchers::execs;
 
@@ -155,6 +155,17 @@ const YARN_VERSION_INFO: &'static str = r#"[
 {"tag_name":"v1.12.99","assets":[{"name":"yarn-v1.12.99.tar.gz"}]}
 ]"#;
 
+const NPM_VERSION_INFO: &'static str = r#"{
+"dist-tags":{"latest":"8.1.0"},
+"versions":{"7.24.2":{},"8.0.0":{},"8.1.0":{}}
+}"#;
+
+const NPM_VERSION_FIXTURES: [DistroMetadata; 1] = [DistroMetadata {
+    version: "8.1.0",
+    compressed_size: 228,
+    uncompressed_size: Some(0x00280000),
+}];
+
 const YARN_VERSION_FIXTURES: [DistroMetadata; 4] = [
     DistroMetadata {
         version: "1.12.99",
@@ -199,6 +210,23 @@ fn pin_node() {
     )
 }
 
+#[test]
+fn pin_node_with_unreachable_registry() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .node_cache(NODE_VERSION_INFO, true)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("pin node 6"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("Pinned node version 6.19.62 (npm 3.10.1066) in package.json")
+            .with_stderr_contains("[..]Using the cached Node index.")
+    );
+}
+
 #[test]
 fn pin_node_latest() {
     let s = sandbox()
@@ -281,6 +309,27 @@ fn pin_yarn() {
     )
 }
 
+#[test]
+fn pin_yarn_at_version() {
+    let s = sandbox()
+        .package_json(&package_json_with_pinned_node("1.2.3"))
+        .yarn_available_versions(YARN_VERSION_INFO)
+        .distro_mocks::<YarnFixture>(&YARN_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("pin yarn@1.x"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("Pinned yarn version 1.12.99 in package.json")
+    );
+
+    assert_eq!(
+        s.read_package_json(),
+        package_json_with_pinned_node_yarn("1.2.3", "1.12.99"),
+    )
+}
+
 #[test]
 fn pin_yarn_latest() {
     let s = sandbox()
@@ -342,3 +391,195 @@ fn pin_yarn_leaves_npm() {
         package_json_with_pinned_node_npm_yarn("1.2.3", "3.4.5", "1.4.159"),
     )
 }
+
+#[test]
+fn pin_npm() {
+    let s = sandbox()
+        .package_json(&package_json_with_pinned_node("1.2.3"))
+        .npm_available_versions(NPM_VERSION_INFO)
+        .distro_mocks::<NpmFixture>(&NPM_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("pin npm@8"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("Pinned npm version 8.1.0 in package.json")
+    );
+
+    assert_eq!(
+        s.read_package_json(),
+        package_json_with_pinned_node_npm("1.2.3", "8.1.0"),
+    )
+}
+
+#[test]
+fn pin_npm_no_node() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .npm_available_versions(NPM_VERSION_INFO)
+        .distro_mocks::<NpmFixture>(&NPM_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("pin npm latest"),
+        execs()
+            .with_status(ExitCode::ConfigurationError as i32)
+            .with_stderr_contains("error: There is no pinned node version for this project")
+    );
+
+    assert_eq!(s.read_package_json(), BASIC_PACKAGE_JSON,)
+}
+
+#[test]
+fn pin_node_dry_run() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .node_available_versions(NODE_VERSION_INFO)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("pin --dry-run node 6"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("[..]\"node\": \"6.19.62\"[..]")
+            .with_stdout_contains(
+                "Would pin node version 6.19.62 (npm 3.10.1066) in package.json (dry run)"
+            )
+    );
+
+    assert_eq!(s.read_package_json(), BASIC_PACKAGE_JSON)
+}
+
+#[test]
+fn pin_node_dry_run_in_ci() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .node_available_versions(NODE_VERSION_INFO)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .env("CI", "true")
+        .build();
+
+    assert_that!(
+        s.notion("pin --dry-run node@6"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("[..]\"node\": \"6.19.62\"[..]")
+    );
+
+    assert_eq!(s.read_package_json(), BASIC_PACKAGE_JSON)
+}
+
+#[test]
+fn pin_node_backs_up_package_json() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .node_available_versions(NODE_VERSION_INFO)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(s.notion("pin node 6"), execs().with_status(0));
+
+    assert_eq!(
+        s.read_manifest_backups(),
+        vec![BASIC_PACKAGE_JSON.to_string()]
+    );
+}
+
+#[test]
+fn pin_node_read_only_home() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .node_available_versions(NODE_VERSION_INFO)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .env("NOTION_READ_ONLY", "1")
+        .build();
+
+    assert_that!(
+        s.notion("pin node 6"),
+        execs()
+            .with_status(ExitCode::PermissionError as i32)
+            .with_stderr_contains(
+                "error: Cannot fetch Node v6.19.62 because NOTION_HOME is read-only"
+            )
+    );
+
+    assert_eq!(s.read_package_json(), BASIC_PACKAGE_JSON)
+}
+
+#[test]
+fn pin_node_engines() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .node_available_versions(NODE_VERSION_INFO)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(s.notion("pin --engines node 6"), execs().with_status(0));
+
+    let package_json: serde_json::Value =
+        serde_json::from_str(&s.read_package_json()).expect("invalid package.json");
+    assert_eq!(package_json["toolchain"]["node"], "6.19.62");
+    assert_eq!(package_json["engines"]["node"], "6.19.62");
+}
+
+#[test]
+fn pin_node_profile() {
+    let s = sandbox()
+        .package_json(
+            r#"{
+  "name": "test-package",
+  "toolchain": {
+    "node": "10.99.1040"
+  },
+  "toolchainProfiles": {
+    "legacy": {
+      "node": "4.1.0"
+    }
+  }
+}"#,
+        )
+        .node_available_versions(NODE_VERSION_INFO)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .env("NOTION_PROFILE", "legacy")
+        .build();
+
+    assert_that!(s.notion("pin node 6"), execs().with_status(0));
+
+    let package_json: serde_json::Value =
+        serde_json::from_str(&s.read_package_json()).expect("invalid package.json");
+    assert_eq!(package_json["toolchain"]["node"], "10.99.1040");
+    assert_eq!(
+        package_json["toolchainProfiles"]["legacy"]["node"],
+        "6.19.62"
+    );
+}
+
+#[test]
+fn pin_node_interactive_without_terminal() {
+    let s = sandbox()
+        .package_json(BASIC_PACKAGE_JSON)
+        .node_available_versions(NODE_VERSION_INFO)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("pin --interactive node 6"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("Pinned node version 6.19.62 (npm 3.10.1066) in package.json")
+    );
+}
+
+#[test]
+fn pin_node_interactive_without_terminal_or_version() {
+    let s = sandbox().package_json(BASIC_PACKAGE_JSON).build();
+
+    assert_that!(
+        s.notion("pin --interactive node"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("[..]no version given for `node`[..]")
+    );
+}
@@ -0,0 +1,41 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+const NODE_VERSION_INFO: &'static str = r#"[
+{"version":"v10.99.1040","npm":"6.2.26","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]},
+{"version":"v9.27.6","npm":"5.6.17","files":["linux-x64","osx-x64-tar","win-x64-zip","win-x86-zip"]}
+]
+"#;
+
+#[test]
+fn refreshes_unexpired_index() {
+    let s = sandbox()
+        .node_cache(NODE_VERSION_INFO, false)
+        .node_available_versions(NODE_VERSION_INFO)
+        .build();
+
+    assert_that!(
+        s.notion("refresh"),
+        execs()
+            .with_status(ExitCode::Success as i32)
+            .with_stdout_contains("Refreshed the Node index (2 versions)")
+    );
+}
+
+#[test]
+fn unreachable_registry() {
+    let s = sandbox()
+        .node_cache(NODE_VERSION_INFO, true)
+        .mock_not_found()
+        .build();
+
+    assert_that!(
+        s.notion("refresh"),
+        execs()
+            .with_status(ExitCode::NetworkError as i32)
+            .with_stderr_contains("error: Could not fetch public registry")
+    );
+}
@@ -0,0 +1,19 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn relocate_with_shim_dir_env() {
+    let s = sandbox().env("NOTION_SHIM_DIR", "/opt/notion/bin").build();
+
+    assert_that!(
+        s.notion("relocate /srv/notion/bin"),
+        execs()
+            .with_status(ExitCode::ConfigurationError as i32)
+            .with_stderr_contains(
+                "[..]Cannot move the shims while NOTION_SHIM_DIR is set (to /opt/notion/bin)"
+            )
+    );
+}
@@ -0,0 +1,29 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn requires_node() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("run --yarn 1.22 -- yarn test"),
+        execs().with_status(ExitCode::InvalidArguments as i32)
+    );
+}
+
+#[test]
+fn unavailable_node_offline() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("--offline run --node 12.22.1 -- node --version"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains(
+                "error: No Node version matching [..]12.22.1 is available offline"
+            )
+    );
+}
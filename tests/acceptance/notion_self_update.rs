@@ -0,0 +1,79 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_core::path::{ARCH, OS};
+use notion_fail::ExitCode;
+
+fn release_manifest(version: &str) -> String {
+    format!(
+        r#"{{
+    "version": "{}",
+    "platforms": {{
+        "{}-{}": [
+            {{
+                "name": "notion",
+                "url": "https://example.com/notion",
+                "integrity": "sha512-AA=="
+            }}
+        ]
+    }}
+}}"#,
+        version, OS, ARCH
+    )
+}
+
+#[test]
+fn up_to_date() {
+    let s = sandbox()
+        .release_manifest("stable", &release_manifest("0.0.1"))
+        .build();
+
+    assert_that!(
+        s.notion("self-update"),
+        execs()
+            .with_status(ExitCode::Success as i32)
+            .with_stdout_contains("[..] is up to date (stable)")
+    );
+}
+
+#[test]
+fn configured_channel() {
+    let s = sandbox()
+        .config("[update]\nchannel = \"canary\"\n")
+        .release_manifest("canary", &release_manifest("0.0.1"))
+        .build();
+
+    assert_that!(
+        s.notion("self-update"),
+        execs()
+            .with_status(ExitCode::Success as i32)
+            .with_stdout_contains("[..] is up to date (canary)")
+    );
+}
+
+#[test]
+fn unknown_channel() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("self-update --channel nightly"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("error: Unknown release channel `nightly`[..]")
+    );
+}
+
+#[test]
+fn no_release_for_platform() {
+    let s = sandbox()
+        .release_manifest("stable", r#"{"version":"99.0.0","platforms":{}}"#)
+        .build();
+
+    assert_that!(
+        s.notion("self-update"),
+        execs()
+            .with_status(ExitCode::NetworkError as i32)
+            .with_stderr_contains("error: Invalid release manifest of the stable channel")
+    );
+}
@@ -0,0 +1,72 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn uninstall_version_not_fetched() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("uninstall node@10.99.0"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains("error: Node v10.99.0 is not fetched")
+    );
+}
+
+#[test]
+fn uninstall_range() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("uninstall node 10"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains("Uninstall an exact version of node, yarn, npm or deno")
+    );
+}
+
+#[test]
+fn prune_empty_inventory() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("cache prune --keep-latest=1"),
+        execs().with_status(0).with_stdout_contains("Freed 0 B")
+    );
+}
+
+const INTERRUPTED_FETCH: &'static str =
+    r#"{"tool":"Node","version":"10.99.0","step":"downloading"}"#;
+
+#[test]
+fn uninstall_after_interrupted_fetch() {
+    let s = sandbox()
+        .journal_entry("node-10.99.0.json", INTERRUPTED_FETCH)
+        .build();
+
+    assert_that!(
+        s.notion("uninstall node@10.99.0"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains("[..]rolling back the interrupted fetch of Node v10.99.0")
+            .with_stderr_contains("error: Node v10.99.0 is not fetched")
+    );
+}
+
+#[test]
+fn prune_after_interrupted_fetch() {
+    let s = sandbox()
+        .journal_entry("node-10.99.0.json", INTERRUPTED_FETCH)
+        .build();
+
+    assert_that!(
+        s.notion("cache prune --keep-latest=1"),
+        execs()
+            .with_status(0)
+            .with_stderr_contains("[..]rolling back the interrupted fetch of Node v10.99.0")
+            .with_stdout_contains("Freed 0 B")
+    );
+}
@@ -0,0 +1,114 @@
+use crate::support::sandbox::{sandbox, DistroMetadata, NodeFixture};
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        const NODE_VERSION_FIXTURES: [DistroMetadata; 1] = [
+            DistroMetadata {
+                version: "10.99.1040",
+                compressed_size: 1096,
+                uncompressed_size: None,
+            },
+        ];
+    } else {
+        const NODE_VERSION_FIXTURES: [DistroMetadata; 1] = [
+            DistroMetadata {
+                version: "10.99.1040",
+                compressed_size: 273,
+                uncompressed_size: Some(0x00280000),
+            },
+        ];
+    }
+}
+
+const PLATFORM: &'static str = r#"{"node":{"runtime":"10.99.1040","npm":"6.2.26"}}"#;
+
+const PINNED_PACKAGE_JSON: &'static str = r#"{
+  "name": "test-package",
+  "toolchain": {
+    "node": "10.99.1040"
+  }
+}"#;
+
+#[test]
+fn user_default() {
+    let s = sandbox()
+        .platform(PLATFORM)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("which node"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("node v10.99.1040 (user default)")
+    );
+    assert_that!(
+        s.notion("which npm"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("npm v6.2.26 (bundled with Node)")
+    );
+}
+
+#[test]
+fn project_pin_as_json() {
+    let s = sandbox()
+        .package_json(PINNED_PACKAGE_JSON)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("which --json node"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("  \"version\": \"10.99.1040\",")
+            .with_stdout_contains("  \"source\": \"project\",")
+    );
+}
+
+#[test]
+fn current_as_json() {
+    let s = sandbox()
+        .platform(PLATFORM)
+        .distro_mocks::<NodeFixture>(&NODE_VERSION_FIXTURES)
+        .build();
+
+    assert_that!(
+        s.notion("current --json"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("    \"tool\": \"node\",")
+            .with_stdout_contains("    \"tool\": \"npx\",")
+            .with_stdout_does_not_contain("[..]\"yarn\"[..]")
+    );
+}
+
+#[test]
+fn no_node() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("which node"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains("[..]Node is not available yet[..]")
+    );
+}
+
+#[test]
+fn unknown_tool() {
+    let s = sandbox().platform(PLATFORM).build();
+
+    assert_that!(
+        s.notion("which tsc"),
+        execs()
+            .with_status(ExitCode::InvalidArguments as i32)
+            .with_stderr_contains(
+                "[..]Cannot resolve `tsc` (expected `node`, `npm`, `npx`, `yarn` or `deno`)"
+            )
+    );
+}
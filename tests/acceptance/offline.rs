@@ -0,0 +1,33 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn install_offline_without_cached_versions() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("--offline install node 10"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains("is available offline")
+            .with_stderr_contains("Versions in the inventory: none")
+    );
+}
+
+#[test]
+fn pin_offline_with_environment_variable() {
+    let s = sandbox()
+        .package_json(r#"{ "name": "test-package" }"#)
+        .env("NOTION_OFFLINE", "1")
+        .build();
+
+    assert_that!(
+        s.notion("pin node latest"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains("error: No Node version matching latest is available offline")
+    );
+}
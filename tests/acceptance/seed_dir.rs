@@ -0,0 +1,56 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_core::path::node_distro_file_name;
+use notion_fail::ExitCode;
+
+const PLATFORM: &'static str = r#"{"node":{"runtime":"10.99.1040","npm":"6.2.26"}}"#;
+
+#[cfg(unix)]
+const SEEDED_NODE: &'static str = "seed/tools/image/node/10.99.1040/6.2.26/bin/node";
+
+#[cfg(windows)]
+const SEEDED_NODE: &'static str = "seed/tools/image/node/10.99.1040/6.2.26/node.exe";
+
+#[test]
+fn runs_seeded_image() {
+    let builder = sandbox();
+    let seed_dir = builder.root().join("seed");
+    let s = builder
+        .platform(PLATFORM)
+        .env("NOTION_SEED_DIR", &seed_dir.to_string_lossy())
+        .env("NOTION_OFFLINE", "1")
+        .project_file(
+            &format!(
+                "seed/tools/inventory/node/{}",
+                node_distro_file_name("10.99.1040")
+            ),
+            "",
+        )
+        .project_file("seed/tools/inventory/node/node-v10.99.1040-npm", "6.2.26")
+        .project_file(SEEDED_NODE, "")
+        .build();
+
+    // nothing is mocked, so the version can only come from the seed directory
+    assert_that!(
+        s.notion("which node"),
+        execs()
+            .with_status(0)
+            .with_stdout_contains("[..]seed[..]node[..]")
+            .with_stdout_contains("node v10.99.1040 (user default)")
+    );
+}
+
+#[test]
+fn fetches_without_seed() {
+    let s = sandbox()
+        .platform(PLATFORM)
+        .env("NOTION_OFFLINE", "1")
+        .build();
+
+    assert_that!(
+        s.notion("which node"),
+        execs().with_status(ExitCode::NoVersionMatch as i32)
+    );
+}
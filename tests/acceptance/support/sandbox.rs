@@ -138,6 +138,10 @@ pub struct YarnFixture {
     pub metadata: DistroMetadata,
 }
 
+pub struct NpmFixture {
+    pub metadata: DistroMetadata,
+}
+
 impl From<DistroMetadata> for NodeFixture {
     fn from(metadata: DistroMetadata) -> Self {
         Self { metadata }
@@ -150,6 +154,12 @@ impl From<DistroMetadata> for YarnFixture {
     }
 }
 
+impl From<DistroMetadata> for NpmFixture {
+    fn from(metadata: DistroMetadata) -> Self {
+        Self { metadata }
+    }
+}
+
 impl DistroFixture for NodeFixture {
     fn server_path(&self) -> String {
         let version = &self.metadata.version;
@@ -189,6 +199,21 @@ impl DistroFixture for YarnFixture {
     }
 }
 
+impl DistroFixture for NpmFixture {
+    fn server_path(&self) -> String {
+        let version = &self.metadata.version;
+        format!("/npm-registry/npm/-/npm-{}.tgz", version)
+    }
+
+    fn fixture_path(&self) -> String {
+        format!("tests/fixtures/npm-{}.tgz", self.metadata.version)
+    }
+
+    fn metadata(&self) -> &DistroMetadata {
+        &self.metadata
+    }
+}
+
 impl SandboxBuilder {
     /// Root of the project, ex: `/path/to/cargo/target/integration_test/t0/foo`
     pub fn root(&self) -> PathBuf {
@@ -228,6 +253,13 @@ impl SandboxBuilder {
         self
     }
 
+    /// Add a file to the root of the project, such as `.nvmrc` (chainable)
+    pub fn project_file(mut self, name: &str, contents: &str) -> Self {
+        let file = self.root().join(name);
+        self.files.push(FileBuilder::new(file, contents));
+        self
+    }
+
     /// Set the platform.json for the sandbox (chainable)
     pub fn platform(mut self, contents: &str) -> Self {
         self.files
@@ -235,6 +267,20 @@ impl SandboxBuilder {
         self
     }
 
+    /// Set the config.toml for the sandbox (chainable)
+    pub fn config(mut self, contents: &str) -> Self {
+        self.files
+            .push(FileBuilder::new(user_config_file(), contents));
+        self
+    }
+
+    /// Leave the journal entry of an interrupted fetch in the inventory (chainable)
+    pub fn journal_entry(mut self, name: &str, contents: &str) -> Self {
+        let file = install_journal_dir().join(name);
+        self.files.push(FileBuilder::new(file, contents));
+        self
+    }
+
     /// Set the shell for the sandbox (chainable)
     pub fn notion_shell(self, shell_name: &str) -> Self {
         self.env("NOTION_SHELL", shell_name)
@@ -264,6 +310,18 @@ impl SandboxBuilder {
         self
     }
 
+    /// Setup mock to return the release schedule of Node (chainable)
+    pub fn node_schedule(mut self, body: &str) -> Self {
+        let mock = mock("GET", "/node-schedule.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .create();
+        self.root.mocks.push(mock);
+
+        self
+    }
+
     /// Setup mock to return the available yarn versions (chainable)
     pub fn yarn_available_versions(mut self, body: &str) -> Self {
         let mock = mock("GET", "/yarn-releases/index.json")
@@ -275,6 +333,35 @@ impl SandboxBuilder {
         self
     }
 
+    /// Setup mock to return the registry metadata of npm, and the metadata of each of its
+    /// versions, which publishes no integrity (chainable)
+    pub fn npm_available_versions(mut self, body: &str) -> Self {
+        let mock = mock("GET", "/npm-registry/npm")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .create();
+        self.root.mocks.push(mock);
+        let mock = mock("GET", Matcher::Regex(r"^/npm-registry/npm/\d".to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"dist":{}}"#)
+            .create();
+        self.root.mocks.push(mock);
+        self
+    }
+
+    /// Setup mock to return the release manifest of a Notion release channel (chainable)
+    pub fn release_manifest(mut self, channel: &str, body: &str) -> Self {
+        let mock = mock("GET", &format!("/notion-releases/{}.json", channel)[..])
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .create();
+        self.root.mocks.push(mock);
+        self
+    }
+
     /// Setup mock to return the latest version of yarn (chainable)
     pub fn yarn_latest(mut self, version: &str) -> Self {
         let mock = mock("GET", "/yarn-latest")
@@ -399,6 +486,9 @@ fn inventory_dir() -> PathBuf {
 fn user_dir() -> PathBuf {
     notion_tools_dir().join("user")
 }
+fn install_journal_dir() -> PathBuf {
+    inventory_dir().join("journal")
+}
 fn node_inventory_dir() -> PathBuf {
     inventory_dir().join("node")
 }
@@ -414,6 +504,9 @@ fn cache_dir() -> PathBuf {
 fn node_cache_dir() -> PathBuf {
     cache_dir().join("node")
 }
+fn manifest_backup_dir() -> PathBuf {
+    notion_home().join("backups").join("manifests")
+}
 #[allow(dead_code)]
 fn node_index_file() -> PathBuf {
     node_cache_dir().join("index.json")
@@ -429,6 +522,9 @@ fn package_json_file(mut root: PathBuf) -> PathBuf {
 fn user_platform_file() -> PathBuf {
     user_dir().join("platform.json")
 }
+fn user_config_file() -> PathBuf {
+    notion_home().join("config.toml")
+}
 
 pub struct Sandbox {
     root: PathBuf,
@@ -457,6 +553,16 @@ impl Sandbox {
             .env("PATH", &self.path)
             .env("NOTION_POSTSCRIPT", notion_postscript())
             .env_remove("NOTION_SHELL")
+            .env_remove("NOTION_QUIET")
+            .env_remove("CI")
+            .env_remove("NOTION_NONINTERACTIVE")
+            .env_remove("NOTION_PROGRESS")
+            .env_remove("NOTION_VERBOSITY")
+            // the mock server is reached directly, whatever proxy the machine uses
+            .env_remove("HTTP_PROXY")
+            .env_remove("http_proxy")
+            .env_remove("HTTPS_PROXY")
+            .env_remove("https_proxy")
             .env_remove("MSYSTEM"); // assume cmd.exe everywhere on windows
 
         // overrides for env vars
@@ -506,6 +612,23 @@ impl Sandbox {
         let postscript_file = notion_postscript();
         read_file_to_string(postscript_file)
     }
+
+    /// The contents of every `package.json` backup in the sandbox.
+    pub fn read_manifest_backups(&self) -> Vec<String> {
+        let mut backups = Vec::new();
+        if let Ok(dirs) = fs::read_dir(manifest_backup_dir()) {
+            for dir in dirs {
+                let dir = ok_or_panic! { dir }.path();
+                for entry in ok_or_panic! { fs::read_dir(dir) } {
+                    let path = ok_or_panic! { entry }.path();
+                    if path.extension().map_or(false, |ext| ext == "json") {
+                        backups.push(read_file_to_string(path));
+                    }
+                }
+            }
+        }
+        backups
+    }
 }
 
 // Generates a sandboxed environment
@@ -0,0 +1,43 @@
+use crate::support::sandbox::sandbox;
+use hamcrest2::{assert_that, core::Matcher};
+use test_support::matchers::execs;
+
+use notion_fail::ExitCode;
+
+#[test]
+fn quiet_still_displays_errors() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("--quiet --offline install node 10"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains("error: No Node version matching")
+    );
+}
+
+#[test]
+fn quiet_with_environment_variable() {
+    let s = sandbox()
+        .package_json(r#"{ "name": "test-package" }"#)
+        .env("NOTION_VERBOSITY", "quiet")
+        .env("NOTION_OFFLINE", "1")
+        .build();
+
+    assert_that!(
+        s.notion("pin node latest"),
+        execs()
+            .with_status(ExitCode::NoVersionMatch as i32)
+            .with_stderr_contains("error: No Node version matching latest is available offline")
+    );
+}
+
+#[test]
+fn verbose_and_quiet_are_exclusive() {
+    let s = sandbox().build();
+
+    assert_that!(
+        s.notion("--verbose --quiet current"),
+        execs().with_status(ExitCode::InvalidArguments as i32)
+    );
+}